@@ -79,6 +79,32 @@ hyperfine --prepare "cargo run --release --package=benchmark-import-prepare --qu
           --command-name "pg_dump => psql sql-copy" "$PG_DUMP_COMMAND_TO_COPY" \
           --command-name "pg_dump => psql sql-insert" "$PG_DUMP_COMMAND_TO_SQL_INSERTS"
 
+echo "Creating database with many small rows"
+psql --command 'create database many_rows;'
+psql --dbname many_rows --quiet --command "create table narrow as select i as id, md5(i::text) as name, now() as created from generate_series(1, 2000000) i; create table wide as select i as id, repeat(md5(i::text), 20) as payload from generate_series(1, 200000) i;"
+
+ELEFANT_SYNC_COPY_SAME_SERVER="\"$ELEFANT_SYNC_PATH\" --max-parallelism 1 copy --source-db-name many_rows --target-db-name many_rows_import --same-server-optimization"
+ELEFANT_SYNC_COPY_SAME_SERVER_UNOPTIMIZED="\"$ELEFANT_SYNC_PATH\" --max-parallelism 1 copy --source-db-name many_rows --target-db-name many_rows_import"
+
+hyperfine --prepare "psql --quiet --command 'drop database if exists many_rows_import;' --command 'create database many_rows_import;'" --warmup 1 \
+          --export-markdown "benchmarks/results/same-server-optimization.md" \
+          --command-name "elefant-sync copy same server optimized" "$ELEFANT_SYNC_COPY_SAME_SERVER" \
+          --command-name "elefant-sync copy same server unoptimized" "$ELEFANT_SYNC_COPY_SAME_SERVER_UNOPTIMIZED"
+
+echo "Creating database with a wide table"
+psql --command 'create database wide_table;'
+psql --dbname wide_table --quiet --command "do \$\$ begin execute 'create table telemetry as select ' || (select string_agg(format('s.i + %s as c%s', n, n), ', ') from generate_series(1, 500) n) || ' from generate_series(1, 20000) s(i)'; end \$\$;"
+
+ELEFANT_SYNC_WIDE_TABLE_INSERTS="\"$ELEFANT_SYNC_PATH\" export --source-db-name wide_table sql-file --path benchmarks/results/elefant_sync_wide_table-insert.sql --format InsertStatements --insert-mode-column-limit 1000"
+ELEFANT_SYNC_WIDE_TABLE_FALLBACK="\"$ELEFANT_SYNC_PATH\" export --source-db-name wide_table sql-file --path benchmarks/results/elefant_sync_wide_table-fallback.sql --format InsertStatements"
+ELEFANT_SYNC_WIDE_TABLE_COPY="\"$ELEFANT_SYNC_PATH\" export --source-db-name wide_table sql-file --path benchmarks/results/elefant_sync_wide_table-copy.sql --format CopyStatements"
+
+hyperfine --warmup 1 \
+          --export-markdown "benchmarks/results/export-wide-table.md" \
+          --command-name "elefant-sync sql-insert 500 columns" "$ELEFANT_SYNC_WIDE_TABLE_INSERTS" \
+          --command-name "elefant-sync sql-insert 500 columns with copy fallback" "$ELEFANT_SYNC_WIDE_TABLE_FALLBACK" \
+          --command-name "elefant-sync sql-copy 500 columns" "$ELEFANT_SYNC_WIDE_TABLE_COPY"
+
 echo "Finished benchmark"
 
 docker stop "$DOCKER_PID"
@@ -0,0 +1,546 @@
+use crate::exit_code::{SyncError, SyncResult};
+use elefant_tools::{
+    copy_data, ConnectionPool, CopyDataOptions, CopyDataReport, ElefantToolsError,
+    PostgresClientWrapper, PostgresInstanceStorage, Result, ResultExt,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{error, info, instrument};
+
+/// The copy jobs run by `elefant-sync batch`, read from a TOML file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BatchConfig {
+    /// How many jobs to run at the same time. Jobs are started in the order they are listed.
+    #[serde(default = "one_job")]
+    pub max_concurrent_jobs: NonZeroUsize,
+
+    /// What to do with the remaining jobs when a job fails.
+    #[serde(default)]
+    pub on_error: OnError,
+
+    #[serde(rename = "job")]
+    pub jobs: Vec<BatchJob>,
+}
+
+fn one_job() -> NonZeroUsize {
+    NonZeroUsize::new(1).unwrap()
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum OnError {
+    /// Don't start any more jobs. Jobs that are already running are finished.
+    #[default]
+    FailFast,
+    /// Run the remaining jobs anyway.
+    Continue,
+}
+
+/// A single copy from a source database to a target database.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BatchJob {
+    /// The name of the job in the log and the report.
+    pub name: String,
+    pub source: BatchDatabase,
+    pub target: BatchDatabase,
+    /// The options of the copy, using the same names as the library. `max_parallel`
+    /// defaults to `--max-parallelism`.
+    #[serde(default)]
+    pub options: CopyDataOptions,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BatchDatabase {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    /// The name of the database.
+    pub name: String,
+}
+
+fn default_port() -> u16 {
+    5432
+}
+
+impl BatchDatabase {
+    fn get_connection_string(&self) -> String {
+        format!(
+            "host={} port={} user={} password={} dbname={}",
+            self.host, self.port, self.user, self.password, self.name
+        )
+    }
+}
+
+impl BatchConfig {
+    pub(crate) fn parse(config: &str) -> Result<Self> {
+        let config: BatchConfig = toml::from_str(config)
+            .map_err(|e| ElefantToolsError::InvalidBatchConfig(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that jobs that can run at the same time don't copy to the same database, as they
+    /// would be creating the same objects at once.
+    fn validate(&self) -> Result<()> {
+        if self.jobs.is_empty() {
+            return Err(ElefantToolsError::InvalidBatchConfig(
+                "no jobs are defined".to_string(),
+            ));
+        }
+
+        if self.max_concurrent_jobs.get() == 1 {
+            return Ok(());
+        }
+
+        for (i, first) in self.jobs.iter().enumerate() {
+            let target = |job: &BatchJob| {
+                (
+                    job.target.host.clone(),
+                    job.target.port,
+                    job.target.name.clone(),
+                )
+            };
+
+            if let Some(second) = self.jobs[i + 1..]
+                .iter()
+                .find(|j| target(j) == target(first))
+            {
+                return Err(ElefantToolsError::BatchJobsShareTarget {
+                    first_job: first.name.clone(),
+                    second_job: second.name.clone(),
+                    database: format!(
+                        "{} on {}:{}",
+                        first.target.name, first.target.host, first.target.port
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// How a job in the batch ended.
+#[derive(Debug)]
+pub(crate) enum BatchJobOutcome {
+    Copied {
+        report: CopyDataReport,
+        duration: Duration,
+    },
+    Failed {
+        error: SyncError,
+        duration: Duration,
+    },
+    /// Not started, because an earlier job failed with [OnError::FailFast].
+    Skipped,
+}
+
+#[derive(Debug)]
+pub(crate) struct BatchJobResult {
+    pub name: String,
+    pub outcome: BatchJobOutcome,
+}
+
+/// Runs the jobs of the batch, and returns the result of each job in the order they are listed.
+#[instrument(skip_all)]
+pub(crate) async fn run_batch(
+    config: BatchConfig,
+    max_parallel: NonZeroUsize,
+) -> Vec<BatchJobResult> {
+    let failed = AtomicBool::new(false);
+    let on_error = config.on_error;
+
+    let mut results = futures::stream::iter(config.jobs.into_iter().enumerate())
+        .map(|(index, job)| {
+            let failed = &failed;
+            async move {
+                if on_error == OnError::FailFast && failed.load(Ordering::SeqCst) {
+                    info!("Skipping job {}, as an earlier job failed", job.name);
+                    return (
+                        index,
+                        BatchJobResult {
+                            name: job.name,
+                            outcome: BatchJobOutcome::Skipped,
+                        },
+                    );
+                }
+
+                info!("Starting job {}", job.name);
+                let started = Instant::now();
+                let outcome = match run_job(&job, max_parallel).await {
+                    Ok(report) => BatchJobOutcome::Copied {
+                        report,
+                        duration: started.elapsed(),
+                    },
+                    Err(error) => {
+                        error!("Job {} failed: {}", job.name, error);
+                        failed.store(true, Ordering::SeqCst);
+                        BatchJobOutcome::Failed {
+                            error,
+                            duration: started.elapsed(),
+                        }
+                    }
+                };
+
+                (
+                    index,
+                    BatchJobResult {
+                        name: job.name,
+                        outcome,
+                    },
+                )
+            }
+        })
+        .buffer_unordered(config.max_concurrent_jobs.get())
+        .collect::<Vec<_>>()
+        .await;
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+async fn run_job(job: &BatchJob, max_parallel: NonZeroUsize) -> SyncResult<CopyDataReport> {
+    let mut options = job.options.clone();
+    let max_parallel = *options.max_parallel.get_or_insert(max_parallel);
+
+    let source_connection = PostgresClientWrapper::new(&job.source.get_connection_string())
+        .await
+        .map_err(SyncError::connecting_to_source)?;
+    let source = PostgresInstanceStorage::with_connection_pool(
+        &source_connection,
+        ConnectionPool::new(max_parallel),
+    )
+    .await?;
+
+    let target_connection = PostgresClientWrapper::new(&job.target.get_connection_string())
+        .await
+        .map_err(SyncError::connecting_to_target)?;
+    let mut target = PostgresInstanceStorage::with_connection_pool(
+        &target_connection,
+        ConnectionPool::new(max_parallel),
+    )
+    .await?;
+
+    Ok(copy_data(&source, &mut target, options).await?)
+}
+
+/// Reads the config file and runs the batch, printing a report of all the jobs. Fails with the
+/// error of the first failed job, so the exit code reflects what went wrong.
+pub(crate) async fn do_batch(config_path: &str, max_parallel: NonZeroUsize) -> SyncResult {
+    let config = std::fs::read_to_string(config_path).with_context_path("read", config_path)?;
+    let config = BatchConfig::parse(&config)?;
+
+    let results = run_batch(config, max_parallel).await;
+    print!("{}", format_batch_report(&results));
+
+    match results.into_iter().find_map(|r| match r.outcome {
+        BatchJobOutcome::Failed { error, .. } => Some(error),
+        _ => None,
+    }) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Formats the outcome of each job as a table, in the order the jobs are listed.
+pub(crate) fn format_batch_report(results: &[BatchJobResult]) -> String {
+    let name_width = results
+        .iter()
+        .map(|r| r.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+
+    let mut report = format!(
+        "{:<name_width$}  {:<7}  {:>10}  {:>6}  Details\n",
+        "Job", "Result", "Duration", "Tables"
+    );
+
+    for result in results {
+        let line = match &result.outcome {
+            BatchJobOutcome::Copied { report, duration } => format!(
+                "{:<name_width$}  {:<7}  {:>9.3}s  {:>6}  {}",
+                result.name,
+                "ok",
+                duration.as_secs_f64(),
+                report.table_timings.len(),
+                match report.warnings.len() {
+                    0 => String::new(),
+                    1 => "1 warning".to_string(),
+                    n => format!("{} warnings", n),
+                }
+            ),
+            BatchJobOutcome::Failed { error, duration } => format!(
+                "{:<name_width$}  {:<7}  {:>9.3}s  {:>6}  {}",
+                result.name,
+                "failed",
+                duration.as_secs_f64(),
+                "",
+                error
+            ),
+            BatchJobOutcome::Skipped => format!(
+                "{:<name_width$}  {:<7}  {:>10}  {:>6}  {}",
+                result.name, "skipped", "", "", "an earlier job failed"
+            ),
+        };
+
+        report.push_str(line.trim_end());
+        report.push('\n');
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elefant_test_macros::pg_test;
+    use elefant_tools::test_helpers;
+    use elefant_tools::test_helpers::TestHelper;
+    use elefant_tools::TableCopyTiming;
+
+    //language=toml
+    const CONFIG: &str = r#"
+max_concurrent_jobs = 2
+on_error = "continue"
+
+[[job]]
+name = "orders"
+source = { host = "localhost", user = "postgres", password = "passw0rd", name = "orders" }
+target = { host = "localhost", port = 5433, user = "postgres", password = "passw0rd", name = "orders" }
+
+[[job]]
+name = "analytics"
+source = { host = "localhost", user = "postgres", password = "passw0rd", name = "analytics" }
+target = { host = "localhost", port = 5433, user = "postgres", password = "passw0rd", name = "analytics" }
+
+[job.options]
+schema_only = true
+exclude_schemas = ["partman"]
+"#;
+
+    #[test]
+    fn parses_config() {
+        let config = BatchConfig::parse(CONFIG).unwrap();
+
+        assert_eq!(config.max_concurrent_jobs.get(), 2);
+        assert_eq!(config.on_error, OnError::Continue);
+        assert_eq!(config.jobs.len(), 2);
+        assert_eq!(config.jobs[0].source.port, 5432);
+        assert_eq!(config.jobs[0].target.port, 5433);
+        assert!(!config.jobs[0].options.schema_only);
+        assert!(config.jobs[1].options.schema_only);
+        assert_eq!(
+            config.jobs[1].options.exclude_schemas,
+            vec!["partman".to_string()]
+        );
+    }
+
+    #[test]
+    fn concurrent_jobs_cannot_share_a_target() {
+        let config = CONFIG.replace("name = \"analytics\" }", "name = \"orders\" }");
+
+        let error = BatchConfig::parse(&config).unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                ElefantToolsError::BatchJobsShareTarget { first_job, second_job, .. } if first_job == "orders" && second_job == "analytics"
+            ),
+            "{:?}",
+            error
+        );
+        assert_eq!(
+            SyncError::from(error).exit_code(),
+            crate::exit_code::VALIDATION_ERROR
+        );
+
+        let sequential = config.replace("max_concurrent_jobs = 2", "max_concurrent_jobs = 1");
+        BatchConfig::parse(&sequential).unwrap();
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let config = CONFIG.replace("on_error", "on_failure");
+
+        let error = BatchConfig::parse(&config).unwrap_err();
+        assert!(
+            matches!(&error, ElefantToolsError::InvalidBatchConfig(_)),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn formats_batch_report() {
+        let results = vec![
+            BatchJobResult {
+                name: "orders".to_string(),
+                outcome: BatchJobOutcome::Copied {
+                    report: CopyDataReport {
+                        table_timings: vec![TableCopyTiming::default(), TableCopyTiming::default()],
+                        warnings: vec!["skipped extension".to_string()],
+                        ..Default::default()
+                    },
+                    duration: Duration::from_millis(1500),
+                },
+            },
+            BatchJobResult {
+                name: "analytics".to_string(),
+                outcome: BatchJobOutcome::Failed {
+                    error: ElefantToolsError::ConfirmationDeclined.into(),
+                    duration: Duration::from_millis(250),
+                },
+            },
+            BatchJobResult {
+                name: "reporting".to_string(),
+                outcome: BatchJobOutcome::Skipped,
+            },
+        ];
+
+        assert_eq!(
+            format_batch_report(&results),
+            "Job        Result     Duration  Tables  Details
+orders     ok           1.500s       2  1 warning
+analytics  failed       0.250s          validation error: The operation was not confirmed
+reporting  skipped                      an earlier job failed
+"
+        );
+    }
+
+    fn database(helper: &TestHelper) -> String {
+        format!(
+            "{{ host = \"localhost\", port = {}, user = \"postgres\", password = \"passw0rd\", name = \"{}\" }}",
+            helper.port, helper.test_db_name
+        )
+    }
+
+    #[pg_test(
+        arg(postgres = 16),
+        arg(postgres = 16),
+        arg(postgres = 16),
+        arg(postgres = 16)
+    )]
+    async fn runs_batch(
+        first_source: &TestHelper,
+        first_destination: &TestHelper,
+        second_source: &TestHelper,
+        second_destination: &TestHelper,
+    ) {
+        first_source
+            .execute_not_query("create table orders(id int); insert into orders values (1);")
+            .await;
+        second_source
+            .execute_not_query("create table events(id int); insert into events values (2);")
+            .await;
+
+        let config = format!(
+            r#"
+max_concurrent_jobs = 2
+
+[[job]]
+name = "orders"
+source = {}
+target = {}
+
+[[job]]
+name = "events"
+source = {}
+target = {}
+
+[job.options]
+schema_only = true
+"#,
+            database(first_source),
+            database(first_destination),
+            database(second_source),
+            database(second_destination)
+        );
+
+        let results = run_batch(
+            BatchConfig::parse(&config).unwrap(),
+            NonZeroUsize::new(1).unwrap(),
+        )
+        .await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "orders");
+        assert!(
+            matches!(results[0].outcome, BatchJobOutcome::Copied { .. }),
+            "{:?}",
+            results[0]
+        );
+        assert_eq!(results[1].name, "events");
+        assert!(
+            matches!(results[1].outcome, BatchJobOutcome::Copied { .. }),
+            "{:?}",
+            results[1]
+        );
+
+        let orders = first_destination
+            .get_single_results::<i32>("select id from orders;")
+            .await;
+        assert_eq!(orders, vec![1]);
+
+        let events = second_destination
+            .get_single_results::<i32>("select id from events;")
+            .await;
+        assert!(events.is_empty());
+    }
+
+    #[pg_test(arg(postgres = 16), arg(postgres = 16))]
+    async fn fail_fast_skips_remaining_jobs(source: &TestHelper, destination: &TestHelper) {
+        source
+            .execute_not_query("create table orders(id int); insert into orders values (1);")
+            .await;
+
+        let config = format!(
+            r#"
+[[job]]
+name = "missing"
+source = {{ host = "localhost", port = {}, user = "postgres", password = "passw0rd", name = "does_not_exist" }}
+target = {}
+
+[[job]]
+name = "orders"
+source = {}
+target = {}
+"#,
+            source.port,
+            database(destination),
+            database(source),
+            database(destination)
+        );
+
+        let results = run_batch(
+            BatchConfig::parse(&config).unwrap(),
+            NonZeroUsize::new(1).unwrap(),
+        )
+        .await;
+
+        assert!(
+            matches!(&results[0].outcome, BatchJobOutcome::Failed { error, .. } if error.exit_code() == crate::exit_code::SOURCE_CONNECTION_ERROR),
+            "{:?}",
+            results[0]
+        );
+        assert!(
+            matches!(results[1].outcome, BatchJobOutcome::Skipped),
+            "{:?}",
+            results[1]
+        );
+
+        let tables = destination
+            .get_single_results::<String>(
+                "select tablename::text from pg_tables where schemaname = 'public';",
+            )
+            .await;
+        assert!(tables.is_empty());
+    }
+}
@@ -1,10 +1,12 @@
 use clap::{Args, Parser, Subcommand};
-use elefant_tools::SqlDataMode;
+use elefant_tools::{DatabaseFilter, ObjectFilter, SqlDataMode, SqlFileOptions};
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
 use std::thread;
 
 #[derive(Parser, Debug, Clone)]
-#[command(author, version, about, long_about)]
+#[command(author, version = get_version(), about, long_about)]
 #[command(propagate_version = true)]
 /// A replacement for db_dump and db_restore that supports advanced processing such as moving between schemas.
 ///
@@ -15,6 +17,10 @@ pub struct Cli {
 
     /// How many threads to use when exporting or importing. Defaults to the number of estimated cores
     /// on the machine. If the available parallelism cannot be determined, it defaults to 1.
+    ///
+    /// With 1, no database connections are opened besides the ones to the source and the target.
+    /// Otherwise at most this many additional connections are opened to each of them, which are
+    /// reused between the tables.
     #[arg(long, default_value_t = get_default_max_parallelism(), env)]
     pub max_parallelism: NonZeroUsize,
 }
@@ -23,6 +29,25 @@ fn get_default_max_parallelism() -> NonZeroUsize {
     thread::available_parallelism().unwrap_or(NonZeroUsize::new(1).unwrap())
 }
 
+/// The version of the tool, together with the database versions it supports.
+pub(crate) fn get_version() -> &'static str {
+    static VERSION: OnceLock<String> = OnceLock::new();
+
+    VERSION.get_or_init(|| {
+        format!(
+            "{} (supports {})",
+            env!("CARGO_PKG_VERSION"),
+            elefant_tools::get_supported_versions_description()
+        )
+    })
+}
+/// Writes the shell completions for the cli to the provided writer.
+pub(crate) fn write_completions(shell: clap_complete::Shell, writer: &mut impl std::io::Write) {
+    use clap::CommandFactory;
+    let mut command = Cli::command();
+    clap_complete::generate(shell, &mut command, "elefant-sync", writer);
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     /// Export a database schema to a file or directory to be imported later on
@@ -43,6 +68,24 @@ pub enum Commands {
     },
     /// Copy a database schema from one database to another
     Copy(CopyArgs),
+    /// Run several copies listed in a TOML file, one after another or a few at a time, and
+    /// print a report of all of them
+    Batch {
+        /// The path to the TOML file with the copy jobs
+        #[arg(long, env = "BATCH_CONFIG")]
+        config: String,
+    },
+    /// Introspect every database on a server and write a JSON snapshot of each of them to a
+    /// directory, as `<database>.json`
+    Inventory(InventoryArgs),
+    /// Compare the structure of two databases, and print the SQL that makes the target match the
+    /// source. The SQL is not applied
+    Diff(DiffArgs),
+    /// Generate shell completions for elefant-sync and write them to stdout
+    Completions {
+        /// The shell to generate completions for
+        shell: clap_complete::Shell,
+    },
 }
 
 #[derive(Args, Debug, Clone)]
@@ -71,12 +114,169 @@ pub struct ExportDbArgs {
     #[arg(long, env)]
     pub source_schema: Option<String>,
 
+    /// A schema to leave out of the export, for example `partman` when using pg_partman.
+    /// Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub exclude_schema: Vec<String>,
+
+    /// Only export the tables matching this pattern, as `schema.table`, where `*` matches any number
+    /// of characters. Partitions are exported along with their partitioned table. Can be specified
+    /// multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub include_table: Vec<String>,
+
+    /// Leave out the tables matching this pattern, as `schema.table`, where `*` matches any number of
+    /// characters. Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub exclude_table: Vec<String>,
+
+    /// Only export the views matching this pattern, as `schema.view`. Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub include_view: Vec<String>,
+
+    /// Leave out the views matching this pattern, as `schema.view`. Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub exclude_view: Vec<String>,
+
+    /// Only export the materialized views matching this pattern, as `schema.view`. Can be specified
+    /// multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub include_materialized_view: Vec<String>,
+
+    /// Leave out the materialized views matching this pattern, as `schema.view`. Can be specified
+    /// multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub exclude_materialized_view: Vec<String>,
+
+    /// Only export the functions matching this pattern, as `schema.function` for every overload, or
+    /// as `schema.function(arguments)` with the arguments as listed by `\df`. Can be specified
+    /// multiple times.
+    #[arg(long, env)]
+    pub include_function: Vec<String>,
+
+    /// Leave out the functions matching this pattern, in the same format as `--include-function`.
+    /// Can be specified multiple times.
+    #[arg(long, env)]
+    pub exclude_function: Vec<String>,
+
+    /// Only export the triggers matching this pattern, as `schema.table.trigger`. Can be specified
+    /// multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub include_trigger: Vec<String>,
+
+    /// Leave out the triggers matching this pattern, as `schema.table.trigger`. Can be specified
+    /// multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub exclude_trigger: Vec<String>,
+
+    /// Only export the sequences matching this pattern, as `schema.sequence`. Can be specified
+    /// multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub include_sequence: Vec<String>,
+
+    /// Leave out the sequences matching this pattern, as `schema.sequence`. Can be specified
+    /// multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub exclude_sequence: Vec<String>,
+
     /// Only the schema will be exported, but not the data
     #[arg(long, env)]
     pub schema_only: bool,
+
+    /// A column to leave out of the created tables and the data, as `schema.table.column`.
+    /// Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',', value_parser = parse_excluded_column)]
+    pub exclude_column: Vec<(String, String)>,
+
+    /// Skip the indexes, constraints, views, triggers, policies and generated columns that use an excluded column,
+    /// and the foreign keys, views and inheriting tables that use an excluded table, instead of failing.
+    #[arg(long, env)]
+    pub drop_dependent_objects: bool,
+
+    /// Export the rows of tables with a foreign key to the table itself with parents before their children,
+    /// so they can be imported while the foreign key is enforced.
+    #[arg(long, env)]
+    pub order_self_referencing: bool,
+
+    /// Copy TimescaleDB hypertables as plain tables and continuous aggregates as plain materialized views,
+    /// leaving out the jobs and policies, for targets without TimescaleDB.
+    #[arg(long, env)]
+    pub strip_timescale: bool,
+}
+
+/// Splits `old=new` into the old and the new tablespace.
+fn parse_tablespace_mapping(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+            Ok((old.to_string(), new.to_string()))
+        }
+        _ => Err(format!(
+            "'{}' is not a tablespace mapping, expected `old=new`",
+            value
+        )),
+    }
+}
+
+/// Parses a size such as `50GB`, `1.5 TB` or `4096` into bytes. The units are powers of 1024,
+/// like the sizes reported by Postgres.
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split_at = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split_at);
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" | "bytes" => 1,
+        "kb" => 1 << 10,
+        "mb" => 1 << 20,
+        "gb" => 1 << 30,
+        "tb" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "'{}' is not a size, expected a number with an optional unit of B, kB, MB, GB or TB",
+                value
+            ))
+        }
+    };
+
+    match number.parse::<f64>() {
+        Ok(number) if number >= 0.0 => Ok((number * multiplier as f64) as u64),
+        _ => Err(format!("'{}' is not a size, expected a number such as `50GB`", value)),
+    }
+}
+
+/// Splits `schema.table.column` into `schema.table` and `column`.
+fn parse_excluded_column(value: &str) -> Result<(String, String), String> {
+    match value.rsplit_once('.') {
+        Some((table, column)) if !table.is_empty() && !column.is_empty() => {
+            Ok((table.to_string(), column.to_string()))
+        }
+        _ => Err(format!(
+            "'{}' is not a column, expected `schema.table.column`",
+            value
+        )),
+    }
 }
 
 impl ExportDbArgs {
+    pub(crate) fn get_object_filter(&self) -> ObjectFilter {
+        ObjectFilter {
+            include_tables: self.include_table.clone(),
+            exclude_tables: self.exclude_table.clone(),
+            include_views: self.include_view.clone(),
+            exclude_views: self.exclude_view.clone(),
+            include_materialized_views: self.include_materialized_view.clone(),
+            exclude_materialized_views: self.exclude_materialized_view.clone(),
+            include_functions: self.include_function.clone(),
+            exclude_functions: self.exclude_function.clone(),
+            include_triggers: self.include_trigger.clone(),
+            exclude_triggers: self.exclude_trigger.clone(),
+            include_sequences: self.include_sequence.clone(),
+            exclude_sequences: self.exclude_sequence.clone(),
+        }
+    }
+
     pub(crate) fn get_connection_string(&self) -> String {
         format!(
             "host={} port={} user={} password={} dbname={}",
@@ -97,7 +297,24 @@ impl ExportDbArgs {
             source_db_password: "passw0rd".to_string(),
             source_db_name: helper.test_db_name.clone(),
             source_schema: None,
+            exclude_schema: Vec::new(),
+            include_table: Vec::new(),
+            exclude_table: Vec::new(),
+            include_view: Vec::new(),
+            exclude_view: Vec::new(),
+            include_materialized_view: Vec::new(),
+            exclude_materialized_view: Vec::new(),
+            include_function: Vec::new(),
+            exclude_function: Vec::new(),
+            include_trigger: Vec::new(),
+            exclude_trigger: Vec::new(),
+            include_sequence: Vec::new(),
+            exclude_sequence: Vec::new(),
             schema_only: false,
+            exclude_column: Vec::new(),
+            drop_dependent_objects: false,
+            order_self_referencing: false,
+            strip_timescale: false,
         }
     }
 }
@@ -110,30 +327,126 @@ pub enum Storage {
     SqlFile {
         /// The path to the .sql file to import/export
         #[arg(long)]
-        path: String,
+        path: PathBuf,
 
         /// How many rows to generate per insert statement. Only considered on export
         #[arg(long, default_value_t = 1000, env)]
         max_rows_per_insert: usize,
 
+        /// How big an insert statement can be in bytes before a new statement is started, regardless
+        /// of the number of rows. Only considered on export
+        #[arg(long, default_value_t = SqlFileOptions::default().max_bytes_per_insert, env)]
+        max_bytes_per_insert: usize,
+
         /// How many DDL commands to generate per chunk. Only considered on export
         #[arg(long, default_value_t = 10, env)]
         max_commands_per_chunk: usize,
 
+        /// Wrap each chunk of DDL commands in a transaction, so each chunk is applied atomically
+        /// when the file is imported with psql. Only considered on export
+        #[arg(long, default_value_t = false, env)]
+        transaction_per_chunk: bool,
+
         /// The format to use when exporting. Only considered on export
         #[arg(long, default_value_t = SqlDataMode::CopyStatements, env)]
         format: SqlDataMode,
+
+        /// How many bytes to write at most before waiting for the file to be flushed.
+        /// Only considered on export
+        #[arg(long, default_value_t = SqlFileOptions::default().max_buffer_bytes, env)]
+        max_buffer_bytes: usize,
+
+        /// The character separating the columns in copy statements. Defaults to a tab.
+        /// Only considered on export with the CopyStatements format
+        #[arg(long, env)]
+        copy_delimiter: Option<char>,
+
+        /// The string representing null values in copy statements. Defaults to `\N`.
+        /// Only considered on export with the CopyStatements format
+        #[arg(long, env)]
+        copy_null_marker: Option<String>,
+
+        /// Tables with more columns than this are exported as copy statements, even with the
+        /// InsertStatements format. Only considered on export
+        #[arg(long, default_value_t = SqlFileOptions::default().insert_mode_column_limit, env)]
+        insert_mode_column_limit: usize,
     },
-    //
-    // /// Export to a directory of SQL files. This directory can be run directly against postgres without needing the
-    // /// elefant-sync tool to import it, however no additional processing can be done during import.
-    // /// Filenames are specified so files can be imported in alphabetical order.
-    // /// This is only recommended for very small databases. For larger databases, use one of the Elefant options.
-    // SqlDirectory {
-    //     #[arg(long)]
-    //     path: String,
-    // },
-    //
+
+    /// Export the data of each table to a separate csv file in a directory, named `<schema>.<table>.csv`.
+    /// Only the data is exported, the schema is not included. This is useful for handing data to
+    /// other tools, but can not be imported again using elefant-sync.
+    CsvDir {
+        /// The directory to write the csv files to. Will be created if it doesn't exist
+        #[arg(long)]
+        path: PathBuf,
+
+        /// The character to separate values with. Files use the .tsv extension if this is a tab
+        #[arg(long, default_value_t = ',', env)]
+        delimiter: char,
+
+        /// Don't write a header row with the column names
+        #[arg(long, env)]
+        no_header: bool,
+    },
+
+    /// Import a single csv file with a header row into an existing table. The header is checked
+    /// against the columns of the table before any data is loaded. Can only be used for import.
+    CsvFile {
+        /// The path to the .csv file to import
+        #[arg(long)]
+        path: PathBuf,
+
+        /// The table to load the data into, for example `public.customers`. If no schema is
+        /// specified, `public` is used
+        #[arg(long)]
+        table: String,
+
+        /// The character separating the values in the file
+        #[arg(long, default_value_t = ',', env)]
+        delimiter: char,
+
+        /// The string that represents null values. Defaults to an unquoted empty string
+        #[arg(long, env)]
+        null_string: Option<String>,
+
+        /// Truncate the table before loading the data
+        #[arg(long, env)]
+        truncate: bool,
+    },
+
+    /// Export to a directory of SQL files, with a file for each table, view, function and other
+    /// object, and a file with the data of each table. This makes the export easy to review and to
+    /// keep in version control. The order to apply the files in is stored in `apply_order.txt`,
+    /// which is used when the directory is imported again.
+    SqlDirectory {
+        /// The directory to write the files to. Will be created if it doesn't exist. The files of
+        /// an earlier export to the directory are replaced
+        #[arg(long)]
+        path: PathBuf,
+
+        /// How many rows to generate per insert statement. Only considered on export
+        #[arg(long, default_value_t = 1000, env)]
+        max_rows_per_insert: usize,
+
+        /// The format to use for the data when exporting. Only considered on export
+        #[arg(long, default_value_t = SqlDataMode::CopyStatements, env)]
+        format: SqlDataMode,
+
+        /// The string separating the statements in the files. A fixed one is used by default, so
+        /// only the files of the objects that changed differ between two exports. Only considered
+        /// on export
+        #[arg(long, default_value = "elefant-sync", env)]
+        chunk_separator: String,
+    },
+
+    /// Export to an archive in the custom format of pg_dump, which can be restored with pg_restore,
+    /// including in parallel with `--jobs`. The archive is not compressed. Only supported on export
+    PgCustomFile {
+        /// The path of the archive to write to
+        #[arg(long)]
+        path: PathBuf,
+    },
+
     // /// Export to a single 'Elefant' file. This file can be imported later on using the import command
     // /// and supports advanced processing such as moving between schemas or only importing certain schemas or tables
     // ElefantFile {
@@ -177,6 +490,16 @@ pub struct ImportDbArgs {
     /// the same schema as it was exported from.
     #[arg(long, env)]
     pub target_schema: Option<String>,
+
+    /// Ask for confirmation before writing to a target database that already contains
+    /// tables, views, functions or other objects
+    #[arg(long, env = "ELEFANT_SYNC_CONFIRM")]
+    pub confirm: bool,
+
+    /// Answer yes to the confirmation asked for by `--confirm`. The summary of the target
+    /// database is still printed
+    #[arg(long, env = "ELEFANT_SYNC_YES")]
+    pub yes: bool,
 }
 
 impl ImportDbArgs {
@@ -197,6 +520,15 @@ impl ImportDbArgs {
         connection_string
     }
 
+    /// A connection string for the `postgres` database on the target server, used
+    /// when the target database itself might not exist yet.
+    pub(crate) fn get_maintenance_connection_string(&self) -> String {
+        format!(
+            "host={} port={} user={} password={} dbname=postgres",
+            self.target_db_host, self.target_db_port, self.target_db_user, self.target_db_password
+        )
+    }
+
     #[cfg(test)]
     pub(crate) fn from_test_helper(helper: &elefant_tools::test_helpers::TestHelper) -> Self {
         Self {
@@ -206,10 +538,81 @@ impl ImportDbArgs {
             target_db_password: "passw0rd".to_string(),
             target_db_name: helper.test_db_name.clone(),
             target_schema: None,
+            confirm: false,
+            yes: false,
         }
     }
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct InventoryArgs {
+    /// The host of the server to introspect
+    #[arg(long, env)]
+    pub source_db_host: String,
+
+    /// The port of the server to introspect
+    #[arg(long, default_value_t = 5432, env)]
+    pub source_db_port: u16,
+
+    /// The username to use when connecting to the databases
+    #[arg(long, env)]
+    pub source_db_user: String,
+
+    /// The password to use when connecting to the databases
+    #[arg(long, env)]
+    pub source_db_password: String,
+
+    /// The database to connect to when listing the databases on the server
+    #[arg(long, default_value = "postgres", env)]
+    pub maintenance_db_name: String,
+
+    /// Only introspect the databases matching this pattern, where `*` matches any number of
+    /// characters. Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub include_database: Vec<String>,
+
+    /// Leave out the databases matching this pattern, where `*` matches any number of characters.
+    /// Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub exclude_database: Vec<String>,
+
+    /// The directory to write the snapshots to. It is created if it doesn't exist
+    #[arg(long, env)]
+    pub output: PathBuf,
+}
+
+impl InventoryArgs {
+    pub(crate) fn get_connection_string(&self) -> String {
+        format!(
+            "host={} port={} user={} password={} dbname={}",
+            self.source_db_host,
+            self.source_db_port,
+            self.source_db_user,
+            self.source_db_password,
+            self.maintenance_db_name
+        )
+    }
+
+    pub(crate) fn get_database_filter(&self) -> DatabaseFilter {
+        DatabaseFilter {
+            include: self.include_database.clone(),
+            exclude: self.exclude_database.clone(),
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DiffArgs {
+    /// The connection string of the database with the wanted structure, such as
+    /// `host=localhost user=postgres password=passw0rd dbname=app`
+    #[arg(long, env)]
+    pub source_connection_string: String,
+
+    /// The connection string of the database to generate the migration for
+    #[arg(long, env)]
+    pub target_connection_string: String,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct CopyArgs {
     #[command(flatten)]
@@ -223,6 +626,162 @@ pub struct CopyArgs {
     /// not sql-files.
     #[arg(long, default_value_t = false, env)]
     pub differential: bool,
+
+    /// Fail instead of only warning when the encoding or locale of the source and target
+    /// databases differ.
+    #[arg(long, default_value_t = false, env)]
+    pub strict_encoding: bool,
+
+    /// Check that the text in the source can be converted to the encoding of the target before
+    /// anything is copied, when the encodings differ. The columns with text that cannot be
+    /// converted are listed. Reads every text column in the source once.
+    #[arg(long, default_value_t = false, env)]
+    pub preflight_encoding_check: bool,
+
+    /// Allow a differential copy to drop columns from the target that no longer exist
+    /// in the source. Views using those columns are dropped and recreated.
+    #[arg(long, default_value_t = false, env)]
+    pub allow_destructive: bool,
+
+    /// Allow a differential copy to drop the tables, views, functions, indexes and columns of the
+    /// target that no longer exist in the source. Without it, the objects that would have been
+    /// dropped are logged
+    #[arg(long, default_value_t = false, env, requires = "differential")]
+    pub allow_drops: bool,
+
+    /// A file with columns that have been renamed in the source, as `schema.table.old_column=new_column`
+    /// on each line. A differential copy renames them in the target instead of dropping the old column.
+    #[arg(long, env)]
+    pub column_rename_hints: Option<String>,
+
+    /// Don't create indexes and unique constraints, so the data is loaded faster.
+    /// Run the copy again with `--indexes-only` to create them afterwards.
+    /// Foreign keys referencing a skipped index are skipped as well.
+    #[arg(long, default_value_t = false, env, conflicts_with = "indexes_only")]
+    pub skip_indexes: bool,
+
+    /// Also skip primary keys when using `--skip-indexes`.
+    #[arg(long, default_value_t = false, env, requires = "skip_indexes")]
+    pub skip_primary_keys: bool,
+
+    /// Only create the indexes and constraints that are missing in the target, such as
+    /// the ones skipped by an earlier copy with `--skip-indexes`. No data is copied.
+    #[arg(long, default_value_t = false, env)]
+    pub indexes_only: bool,
+
+    /// Build indexes with `create index concurrently`, so writes to the tables are not blocked
+    /// while the indexes are created.
+    #[arg(long, default_value_t = false, env)]
+    pub concurrently: bool,
+
+    /// Create the target database if it doesn't exist, using the same encoding and locale
+    /// as the source database.
+    #[arg(long, default_value_t = false, env)]
+    pub create_target_db: bool,
+
+    /// Print how long the data of each table took to copy, and how much of the time was spent
+    /// waiting for the source and the target, with the slowest tables first.
+    #[arg(long, default_value_t = false, env)]
+    pub timing_report: bool,
+
+    /// After the copy, compare this many random rows of each table with the source, column
+    /// by column. Tables without a primary key only have their number of rows compared.
+    /// Exits with the data copy error code if any differences are found.
+    #[arg(long, env)]
+    pub verify_sample_rows: Option<usize>,
+
+    /// After the copy, compare the number of rows and a checksum of all the rows of each table
+    /// with the source. This reads every table completely on both sides, but finds any
+    /// difference. Exits with the data copy error code if any differences are found.
+    #[arg(long, env, conflicts_with = "verify_sample_rows")]
+    pub verify: bool,
+
+    /// Print what the copy would do, such as the data format, whether the tables are copied in
+    /// parallel and the order of the tables, and exit without copying anything.
+    #[arg(long, default_value_t = false, env)]
+    pub explain: bool,
+
+    /// Print the statements the copy would apply to the target, and an estimate of the rows of
+    /// each table, without changing the target or copying any data. The target is still read, so
+    /// this shows what `--differential` would change.
+    #[arg(long, default_value_t = false, env)]
+    pub dry_run: bool,
+
+    /// Record the progress of the copy in this file, so an interrupted copy can be run again with
+    /// the same arguments to continue where it stopped. Tables that were copied completely are
+    /// skipped, and partially copied tables are copied again. The file is removed once the copy
+    /// has finished.
+    #[arg(long, env)]
+    pub resume_file: Option<PathBuf>,
+
+    /// Copy the source schema into a new staging schema, and swap it with the target schema in
+    /// a single transaction once the copy is done. The old schema is dropped afterwards.
+    /// Requires `--source-schema`.
+    #[arg(
+        long,
+        default_value_t = false,
+        env,
+        requires = "source_schema",
+        conflicts_with_all = ["differential", "indexes_only"]
+    )]
+    pub staging_swap: bool,
+
+    /// Keep the replaced schema after a `--staging-swap`, instead of dropping it.
+    #[arg(long, default_value_t = false, env, requires = "staging_swap")]
+    pub keep_old_schema: bool,
+
+    /// Copy the data in larger chunks when the source and the target are databases on the same
+    /// server, as the data then doesn't have to go over a network.
+    #[arg(long, default_value_t = false, env)]
+    pub same_server_optimization: bool,
+
+    /// Leave the materialized views unpopulated, instead of refreshing them after the data is
+    /// copied. Makes schema only copies faster.
+    #[arg(long, default_value_t = false, env)]
+    pub no_refresh_materialized_views: bool,
+
+    /// Don't copy the data of tables larger than this in the source, such as `50GB` or `512MB`.
+    /// The tables are still created, and the sequences are still set. The size counts the table
+    /// and its toast data, but not its indexes.
+    #[arg(long, env, value_parser = parse_size)]
+    pub skip_data_larger_than: Option<u64>,
+
+    /// How large the target database can grow, such as `100GB`. Postgres cannot tell how much
+    /// disk space is free, so if the current size of the target plus the size of the copied
+    /// tables is larger than this, a warning is shown before the copy starts.
+    #[arg(long, env, value_parser = parse_size)]
+    pub destination_capacity: Option<u64>,
+
+    /// Skip what requires the target user to be a superuser, such as untrusted extensions and
+    /// leakproof functions, with a warning instead of failing. Useful for managed Postgres services.
+    #[arg(long, default_value_t = false, env)]
+    pub privilege_downgrade: bool,
+
+    /// Also recreate the indexes of a differential copy whose storage parameters differ from the
+    /// source. Indexes that are built differently are always recreated.
+    #[arg(long, default_value_t = false, env, requires = "differential")]
+    pub strict_index_comparison: bool,
+
+    /// Store the tables and indexes in a tablespace of the source in another tablespace in the
+    /// target, as `old=new`. Use `pg_default` as the old name for the default tablespace of the
+    /// source database. Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',', value_parser = parse_tablespace_mapping)]
+    pub map_tablespace: Vec<(String, String)>,
+
+    /// Create the tables and indexes in the default tablespace of the target, regardless of
+    /// where they are stored in the source.
+    #[arg(long, default_value_t = false, env, conflicts_with = "map_tablespace")]
+    pub no_tablespaces: bool,
+
+    /// Don't copy the privileges granted on schemas, tables, views and functions. Otherwise the
+    /// roles they are granted to have to exist in the target.
+    #[arg(long, default_value_t = false, env)]
+    pub skip_privileges: bool,
+
+    /// A role to grant `usage` and `select` on every copied sequence to, so it can insert into
+    /// serial columns. The role has to exist in the target. Can be specified multiple times.
+    #[arg(long, env, value_delimiter = ',')]
+    pub grant_sequences_to: Vec<String>,
 }
 
 #[test]
@@ -230,3 +789,160 @@ fn verify_cli() {
     use clap::CommandFactory;
     Cli::command().debug_assert()
 }
+
+#[test]
+fn version_contains_supported_versions() {
+    let version = get_version();
+
+    assert!(version.starts_with(env!("CARGO_PKG_VERSION")));
+    assert!(version.contains(&format!(
+        "Postgres {}-{}",
+        elefant_tools::MIN_SUPPORTED_POSTGRES_VERSION,
+        elefant_tools::MAX_TESTED_POSTGRES_VERSION
+    )));
+    assert!(version.contains(&format!(
+        "TimescaleDB {}+",
+        elefant_tools::MIN_SUPPORTED_TIMESCALEDB_VERSION
+    )));
+}
+
+#[test]
+fn fish_completions() {
+    let mut output = Vec::new();
+    write_completions(clap_complete::Shell::Fish, &mut output);
+    let output = String::from_utf8(output).unwrap();
+
+    // Only the completions themselves are compared, as the indentation and the helper functions
+    // differ between clap_complete versions.
+    fn completions(script: &str) -> Vec<&str> {
+        script
+            .lines()
+            .map(str::trim)
+            .filter(|l| l.starts_with("complete -c"))
+            .collect()
+    }
+
+    assert_eq!(
+        completions(&output),
+        completions(include_str!("snapshots/completions.fish"))
+    );
+}
+
+#[test]
+fn invalid_arguments_exit_with_validation_error() {
+    let error = Cli::try_parse_from([
+        "elefant-sync",
+        "export",
+        "--source-db-user",
+        "postgres",
+        "--source-db-password",
+        "passw0rd",
+        "--source-db-name",
+        "db",
+        "--exclude-column",
+        "no_table",
+        "csv-dir",
+        "--path",
+        "out",
+    ])
+    .unwrap_err();
+
+    assert_eq!(error.exit_code(), crate::exit_code::VALIDATION_ERROR as i32);
+}
+
+#[test]
+fn list_arguments_take_comma_separated_values() {
+    let cli = Cli::try_parse_from([
+        "elefant-sync",
+        "export",
+        "--source-db-host",
+        "localhost",
+        "--source-db-user",
+        "postgres",
+        "--source-db-password",
+        "passw0rd",
+        "--source-db-name",
+        "db",
+        "--exclude-column",
+        "public.people.ssn,public.people.salary",
+        "--exclude-schema",
+        "partman",
+        "--exclude-schema",
+        "audit",
+        "csv-dir",
+        "--path",
+        "out",
+    ])
+    .unwrap();
+
+    let Commands::Export { db_args, .. } = cli.command else {
+        panic!("Expected the export command");
+    };
+    assert_eq!(
+        db_args.exclude_column,
+        vec![
+            ("public.people".to_string(), "ssn".to_string()),
+            ("public.people".to_string(), "salary".to_string()),
+        ]
+    );
+    assert_eq!(db_args.exclude_schema, vec!["partman", "audit"]);
+}
+
+#[test]
+fn function_patterns_are_not_split_on_commas() {
+    let cli = Cli::try_parse_from([
+        "elefant-sync",
+        "export",
+        "--source-db-host",
+        "localhost",
+        "--source-db-user",
+        "postgres",
+        "--source-db-password",
+        "passw0rd",
+        "--source-db-name",
+        "db",
+        "--exclude-function",
+        "public.add(a integer, b integer)",
+        "--include-function",
+        "public.concat(text, text)",
+        "csv-dir",
+        "--path",
+        "out",
+    ])
+    .unwrap();
+
+    let Commands::Export { db_args, .. } = cli.command else {
+        panic!("Expected the export command");
+    };
+    assert_eq!(
+        db_args.exclude_function,
+        vec!["public.add(a integer, b integer)"]
+    );
+    assert_eq!(db_args.include_function, vec!["public.concat(text, text)"]);
+}
+
+#[test]
+fn confirmation_environment_variables_are_prefixed() {
+    use clap::CommandFactory;
+    let command = Cli::command();
+    let copy = command.find_subcommand("copy").unwrap();
+
+    let env = |id: &str| {
+        copy.get_arguments()
+            .find(|a| a.get_id() == id)
+            .and_then(|a| a.get_env())
+            .map(|e| e.to_string_lossy().to_string())
+    };
+    assert_eq!(env("confirm").as_deref(), Some("ELEFANT_SYNC_CONFIRM"));
+    assert_eq!(env("yes").as_deref(), Some("ELEFANT_SYNC_YES"));
+}
+
+#[test]
+fn parses_sizes() {
+    assert_eq!(parse_size("4096"), Ok(4096));
+    assert_eq!(parse_size("512MB"), Ok(512 * 1024 * 1024));
+    assert_eq!(parse_size("50GB"), Ok(50 * 1024 * 1024 * 1024));
+    assert_eq!(parse_size("1.5 kB"), Ok(1536));
+    assert!(parse_size("50 parsecs").is_err());
+    assert!(parse_size("GB").is_err());
+}
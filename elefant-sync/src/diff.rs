@@ -0,0 +1,74 @@
+use crate::cli::DiffArgs;
+use crate::exit_code::{SyncError, SyncResult};
+use elefant_tools::{diff, PostgresClientWrapper, PostgresInstanceStorage, SchemaReader};
+use tracing::instrument;
+
+/// Introspects both databases and prints the SQL that migrates the target to the source.
+#[instrument(skip_all)]
+pub(crate) async fn do_diff(args: DiffArgs) -> SyncResult {
+    let sql = get_migration_sql(&args).await?;
+
+    if sql.is_empty() {
+        eprintln!("The databases have the same structure");
+    } else {
+        println!("{}", sql);
+    }
+
+    Ok(())
+}
+
+async fn get_migration_sql(args: &DiffArgs) -> SyncResult<String> {
+    let source_connection = PostgresClientWrapper::new(&args.source_connection_string)
+        .await
+        .map_err(SyncError::connecting_to_source)?;
+    let target_connection = PostgresClientWrapper::new(&args.target_connection_string)
+        .await
+        .map_err(SyncError::connecting_to_target)?;
+
+    let source_definition = SchemaReader::new(&source_connection)
+        .introspect_database()
+        .await?;
+    let target_definition = SchemaReader::new(&target_connection)
+        .introspect_database()
+        .await?;
+
+    // The SQL runs against the target, so it is quoted for the keywords of the target.
+    let target = PostgresInstanceStorage::new(&target_connection).await?;
+
+    Ok(diff(&source_definition, &target_definition)
+        .to_migration_sql(&target.get_identifier_quoter()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elefant_test_macros::pg_test;
+    use elefant_tools::test_helpers;
+    use elefant_tools::test_helpers::TestHelper;
+
+    fn get_connection_string(helper: &TestHelper) -> String {
+        format!(
+            "host=localhost port={} user=postgres password=passw0rd dbname={}",
+            helper.port, helper.test_db_name
+        )
+    }
+
+    #[pg_test(arg(postgres = 16), arg(postgres = 16))]
+    async fn prints_the_migration_of_the_target(source: &TestHelper, target: &TestHelper) {
+        source
+            .execute_not_query("create table users(id int primary key, email text);")
+            .await;
+        target
+            .execute_not_query("create table users(id int primary key);")
+            .await;
+
+        let sql = get_migration_sql(&DiffArgs {
+            source_connection_string: get_connection_string(source),
+            target_connection_string: get_connection_string(target),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(sql, "alter table public.users add column email text;");
+    }
+}
@@ -0,0 +1,99 @@
+use elefant_tools::{ElefantToolsError, ErrorClass};
+use std::fmt::{Display, Formatter};
+use std::process::ExitCode;
+
+/// Invalid arguments, or options that are not valid for the databases. Retrying will not help.
+/// This is also the code clap exits with when the arguments cannot be parsed.
+pub(crate) const VALIDATION_ERROR: u8 = 2;
+/// Connecting to the source database failed.
+pub(crate) const SOURCE_CONNECTION_ERROR: u8 = 3;
+/// Connecting to the target database failed.
+pub(crate) const TARGET_CONNECTION_ERROR: u8 = 4;
+/// Postgres rejected a statement, typically while creating the structure in the target.
+pub(crate) const DDL_ERROR: u8 = 5;
+/// Copying the data failed, including losing a connection after it was established.
+pub(crate) const DATA_COPY_ERROR: u8 = 6;
+/// The data was copied, but differs from the source when verified. Copying it again will most
+/// likely give the same result.
+pub(crate) const VERIFICATION_ERROR: u8 = 7;
+/// Anything else, which is most likely a bug.
+pub(crate) const INTERNAL_ERROR: u8 = 1;
+
+/// Which database a connection was being made to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum ConnectionTarget {
+    Source,
+    Target,
+}
+
+/// An error from elefant-sync, which knows the exit code it should result in.
+#[derive(Debug)]
+pub(crate) struct SyncError {
+    pub(crate) error: ElefantToolsError,
+    /// Set when the error happened while connecting to a database.
+    connecting_to: Option<ConnectionTarget>,
+}
+
+impl SyncError {
+    /// Marks the error as happening while connecting to the source database.
+    pub(crate) fn connecting_to_source(error: ElefantToolsError) -> Self {
+        Self {
+            error,
+            connecting_to: Some(ConnectionTarget::Source),
+        }
+    }
+
+    /// Marks the error as happening while connecting to the target database.
+    pub(crate) fn connecting_to_target(error: ElefantToolsError) -> Self {
+        Self {
+            error,
+            connecting_to: Some(ConnectionTarget::Target),
+        }
+    }
+
+    pub(crate) fn class(&self) -> ErrorClass {
+        self.error.class()
+    }
+
+    pub(crate) fn exit_code(&self) -> u8 {
+        match (self.class(), self.connecting_to) {
+            (ErrorClass::Validation, _) => VALIDATION_ERROR,
+            (ErrorClass::Connection, Some(ConnectionTarget::Source)) => SOURCE_CONNECTION_ERROR,
+            (ErrorClass::Connection, Some(ConnectionTarget::Target)) => TARGET_CONNECTION_ERROR,
+            (ErrorClass::Connection, None) | (ErrorClass::DataCopy, _) => DATA_COPY_ERROR,
+            (ErrorClass::Ddl, _) => DDL_ERROR,
+            (ErrorClass::Verification, _) => VERIFICATION_ERROR,
+            (ErrorClass::Internal, _) => INTERNAL_ERROR,
+        }
+    }
+}
+
+impl From<ElefantToolsError> for SyncError {
+    fn from(error: ElefantToolsError) -> Self {
+        Self {
+            error,
+            connecting_to: None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SyncError {
+    fn from(error: std::io::Error) -> Self {
+        ElefantToolsError::from(error).into()
+    }
+}
+
+impl From<&SyncError> for ExitCode {
+    fn from(error: &SyncError) -> Self {
+        ExitCode::from(error.exit_code())
+    }
+}
+
+impl Display for SyncError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} error: {}", self.class(), self.error)
+    }
+}
+
+/// A result type that uses the SyncError as the error type
+pub(crate) type SyncResult<T = ()> = Result<T, SyncError>;
@@ -0,0 +1,174 @@
+use crate::cli::InventoryArgs;
+use crate::exit_code::{SyncError, SyncResult};
+use elefant_tools::{introspect_all_databases, DatabaseIntrospection, ResultExt};
+use std::num::NonZeroUsize;
+use tracing::instrument;
+
+/// Introspects the databases on the server and writes a snapshot of each of them to the output
+/// directory, printing a report of all the databases. The databases that could be introspected
+/// are written even if others failed, but the command still fails with the error of the first
+/// failed database, so the exit code reflects what went wrong.
+#[instrument(skip_all)]
+pub(crate) async fn do_inventory(args: InventoryArgs, max_parallel: NonZeroUsize) -> SyncResult {
+    std::fs::create_dir_all(&args.output).with_context_path("create", &args.output)?;
+
+    let results = introspect_all_databases(
+        &args.get_connection_string(),
+        &args.get_database_filter(),
+        max_parallel,
+    )
+    .await
+    .map_err(SyncError::connecting_to_source)?;
+
+    for result in &results {
+        if let Ok(database) = &result.result {
+            let path = args
+                .output
+                .join(get_snapshot_file_name(&result.database_name));
+            std::fs::write(&path, database.to_snapshot_json()?)
+                .with_context_path("write", &path)?;
+        }
+    }
+
+    print!("{}", format_inventory_report(&results));
+
+    match results.into_iter().find_map(|r| r.result.err()) {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// The name of the snapshot file of a database. Database names can contain slashes, which are
+/// replaced so the file ends up in the output directory.
+fn get_snapshot_file_name(database_name: &str) -> String {
+    format!("{}.json", database_name.replace(['/', '\\'], "_"))
+}
+
+/// Formats the outcome of each database as a table, sorted by the name of the database.
+fn format_inventory_report(results: &[DatabaseIntrospection]) -> String {
+    let name_width = results
+        .iter()
+        .map(|r| r.database_name.len())
+        .max()
+        .unwrap_or(0)
+        .max(8);
+
+    let mut report = format!(
+        "{:<name_width$}  {:<6}  {:>10}  {:>6}  Details\n",
+        "Database", "Result", "Duration", "Tables"
+    );
+
+    for result in results {
+        let line = match &result.result {
+            Ok(database) => format!(
+                "{:<name_width$}  {:<6}  {:>9.3}s  {:>6}",
+                result.database_name,
+                "ok",
+                result.duration.as_secs_f64(),
+                database
+                    .schemas
+                    .iter()
+                    .map(|s| s.tables.len())
+                    .sum::<usize>()
+            ),
+            Err(error) => format!(
+                "{:<name_width$}  {:<6}  {:>9.3}s  {:>6}  {}",
+                result.database_name,
+                "failed",
+                result.duration.as_secs_f64(),
+                "",
+                error
+            ),
+        };
+
+        report.push_str(line.trim_end());
+        report.push('\n');
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elefant_test_macros::pg_test;
+    use elefant_tools::test_helpers;
+    use elefant_tools::test_helpers::TestHelper;
+    use elefant_tools::{
+        read_snapshot_file, ElefantToolsError, PostgresDatabase, PostgresSchema, PostgresTable,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn formats_inventory_report() {
+        let results = vec![
+            DatabaseIntrospection {
+                database_name: "customers".to_string(),
+                result: Ok(PostgresDatabase {
+                    schemas: vec![PostgresSchema {
+                        name: "public".to_string(),
+                        tables: vec![PostgresTable::new("a"), PostgresTable::new("b")],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }),
+                duration: Duration::from_millis(1500),
+            },
+            DatabaseIntrospection {
+                database_name: "orders".to_string(),
+                result: Err(ElefantToolsError::ConfirmationDeclined),
+                duration: Duration::from_millis(20),
+            },
+        ];
+
+        assert_eq!(
+            format_inventory_report(&results),
+            format!(
+                "Database   Result    Duration  Tables  Details\n\
+                 customers  ok          1.500s       2\n\
+                 orders     failed      0.020s          {}\n",
+                ElefantToolsError::ConfirmationDeclined
+            )
+        );
+    }
+
+    #[test]
+    fn snapshot_file_names_stay_in_the_directory() {
+        assert_eq!(get_snapshot_file_name("orders"), "orders.json");
+        assert_eq!(
+            get_snapshot_file_name("../etc/passwd"),
+            ".._etc_passwd.json"
+        );
+    }
+
+    #[pg_test(arg(postgres = 16))]
+    async fn writes_a_snapshot_per_database(helper: &TestHelper) {
+        helper
+            .execute_not_query("create table users(id int primary key);")
+            .await;
+
+        let output = std::env::temp_dir().join(format!("inventory_{}", helper.test_db_name));
+
+        do_inventory(
+            InventoryArgs {
+                source_db_host: "localhost".to_string(),
+                source_db_port: helper.port,
+                source_db_user: "postgres".to_string(),
+                source_db_password: "passw0rd".to_string(),
+                maintenance_db_name: "postgres".to_string(),
+                include_database: vec![helper.test_db_name.clone()],
+                exclude_database: Vec::new(),
+                output: output.clone(),
+            },
+            NonZeroUsize::new(2).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let snapshot =
+            read_snapshot_file(output.join(format!("{}.json", helper.test_db_name))).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+
+        assert!(snapshot.table("public", "users").is_some());
+    }
+}
@@ -1,27 +1,44 @@
 use crate::cli::{Commands, CopyArgs, ExportDbArgs, ImportDbArgs, Storage};
+use crate::exit_code::{SyncError, SyncResult};
 use clap::Parser;
 use elefant_tools::PostgresClientWrapper;
 use elefant_tools::{
-    apply_sql_file, copy_data, CopyDataOptions, PostgresInstanceStorage, Result, SqlFileOptions,
+    apply_sql_directory, apply_sql_file_parallel, copy_data, plan_copy, ColumnRename,
+    ConnectionPool, CopyDataOptions, CopyTextOptions, CsvDirectoryDestination, CsvDirectoryOptions,
+    CsvFileOptions, CsvFileSource, DatabaseEncoding, ElefantToolsError, PgCustomFormatDestination,
+    PostgresInstanceStorage, Result, ResultExt, SchemaReader, SqlDirectoryDestination,
+    SqlFileOptions, StagingSwap, TableCopyTiming, TableVerification, TableVerificationResult,
+    VerificationLevel,
 };
+use std::collections::HashMap;
+use std::io::Write;
 use std::num::NonZeroUsize;
-use tracing::instrument;
+use std::process::ExitCode;
+use tracing::{info, instrument};
 
+mod batch;
 mod cli;
+mod diff;
+mod exit_code;
+mod inventory;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     tracing_subscriber::fmt::init();
 
     let cli = cli::Cli::parse();
 
-    run(cli).await?;
-
-    Ok(())
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(&e)
+        }
+    }
 }
 
 #[instrument(skip_all)]
-async fn run(cli: cli::Cli) -> Result<()> {
+async fn run(cli: cli::Cli) -> SyncResult {
     match cli.command {
         Commands::Export {
             db_args,
@@ -35,6 +52,18 @@ async fn run(cli: cli::Cli) -> Result<()> {
         Commands::Copy(copy_args) => {
             do_copy(copy_args, cli.max_parallelism).await?;
         }
+        Commands::Batch { config } => {
+            batch::do_batch(&config, cli.max_parallelism).await?;
+        }
+        Commands::Inventory(inventory_args) => {
+            inventory::do_inventory(inventory_args, cli.max_parallelism).await?;
+        }
+        Commands::Diff(diff_args) => {
+            diff::do_diff(diff_args).await?;
+        }
+        Commands::Completions { shell } => {
+            cli::write_completions(shell, &mut std::io::stdout());
+        }
     }
 
     Ok(())
@@ -45,11 +74,17 @@ async fn do_export(
     db_args: ExportDbArgs,
     destination: Storage,
     max_parallelism: NonZeroUsize,
-) -> Result<()> {
+) -> SyncResult {
     let connection_string = db_args.get_connection_string();
 
-    let source_connection = PostgresClientWrapper::new(&connection_string).await?;
-    let source = PostgresInstanceStorage::new(&source_connection).await?;
+    let source_connection = PostgresClientWrapper::new(&connection_string)
+        .await
+        .map_err(SyncError::connecting_to_source)?;
+    let source = PostgresInstanceStorage::with_connection_pool(
+        &source_connection,
+        ConnectionPool::new(max_parallelism),
+    )
+    .await?;
 
     let copy_data_options = CopyDataOptions {
         max_parallel: Some(max_parallelism),
@@ -57,31 +92,137 @@ async fn do_export(
         schema_only: db_args.schema_only,
         data_format: None,
         rename_schema_to: None,
+        exclude_schemas: db_args.exclude_schema.clone(),
+        object_filter: db_args.get_object_filter(),
         differential: false,
+        strict_encoding: false,
+        preflight_encoding_check: false,
+        allow_destructive: false,
+        allow_drops: false,
+        column_renames: Vec::new(),
+        hypertable_conversions: Vec::new(),
+        strip_timescale: db_args.strip_timescale,
+        skip_indexes: false,
+        skip_primary_keys: false,
+        indexes_only: false,
+        create_indexes_concurrently: false,
+        table_select_overrides: HashMap::new(),
+        max_table_data_size_bytes: None,
+        destination_capacity_bytes: None,
+        exclude_columns: db_args.exclude_column.clone(),
+        drop_dependent_objects: db_args.drop_dependent_objects,
+        verification: VerificationLevel::None,
+        order_self_referencing: db_args.order_self_referencing,
+        staging_swap: None,
+        same_server_optimization: false,
+        privilege_downgrade: false,
+        strict_index_comparison: false,
+        tablespace_mapping: HashMap::new(),
+        no_tablespaces: false,
+        skip_privileges: false,
+        refresh_materialized_views: true,
+        grant_sequences_to: Vec::new(),
+        dry_run: false,
+        resume_state_path: None,
+        statement_interceptor: None,
+        introspection_extensions: Vec::new(),
+        ddl_extensions: Vec::new(),
     };
 
     match destination {
         Storage::SqlFile {
             path,
             max_rows_per_insert,
+            max_bytes_per_insert,
             format,
             max_commands_per_chunk,
+            transaction_per_chunk,
+            max_buffer_bytes,
+            copy_delimiter,
+            copy_null_marker,
+            insert_mode_column_limit,
         } => {
+            let default_copy_options = CopyTextOptions::default();
             let mut sql_file_destination = elefant_tools::SqlFile::new_file(
                 &path,
                 source.get_identifier_quoter(),
                 SqlFileOptions {
                     max_rows_per_insert,
+                    max_bytes_per_insert,
                     data_mode: format,
                     max_commands_per_chunk,
+                    transaction_per_chunk,
                     chunk_separator: SqlFileOptions::default().chunk_separator,
+                    max_buffer_bytes,
+                    copy_options: CopyTextOptions {
+                        delimiter: copy_delimiter.unwrap_or(default_copy_options.delimiter),
+                        null_marker: copy_null_marker.unwrap_or(default_copy_options.null_marker),
+                    },
+                    insert_mode_column_limit,
+                    create_parent_directories: true,
                 },
             )
             .await?;
 
             copy_data(&source, &mut sql_file_destination, copy_data_options).await?;
+            info!(
+                "Wrote {} bytes to {}. The largest insert statement is {} bytes",
+                sql_file_destination.get_bytes_written(),
+                path.display(),
+                sql_file_destination.get_largest_insert_bytes()
+            );
+        }
+        Storage::SqlDirectory {
+            path,
+            max_rows_per_insert,
+            format,
+            chunk_separator,
+        } => {
+            let mut sql_directory_destination = SqlDirectoryDestination::new(
+                &path,
+                source.get_identifier_quoter(),
+                SqlFileOptions {
+                    max_rows_per_insert,
+                    data_mode: format,
+                    chunk_separator,
+                    ..SqlFileOptions::default()
+                },
+            )
+            .await?;
+
+            copy_data(&source, &mut sql_directory_destination, copy_data_options).await?;
+            info!("Wrote the export to {}", path.display());
+        }
+        Storage::PgCustomFile { path } => {
+            let mut custom_format_destination = PgCustomFormatDestination::new(
+                &path,
+                source.get_identifier_quoter(),
+                &db_args.source_db_name,
+            )
+            .await?;
+
+            copy_data(&source, &mut custom_format_destination, copy_data_options).await?;
+            info!("Wrote the archive to {}", path.display());
+        }
+        Storage::CsvDir {
+            path,
+            delimiter,
+            no_header,
+        } => {
+            let mut csv_destination = CsvDirectoryDestination::new(
+                &path,
+                CsvDirectoryOptions {
+                    delimiter,
+                    header: !no_header,
+                },
+            )
+            .await?;
+
+            copy_data(&source, &mut csv_destination, copy_data_options).await?;
+        }
+        Storage::CsvFile { .. } => {
+            return Err(ElefantToolsError::ImportOnlyStorage("csv-file".to_string()).into());
         }
-        // Storage::SqlDirectory { path } => Box::new(crate::SqlDirectoryDestination::new(path)),
         // Storage::ElefantFile { path } => Box::new(crate::ElefantFileDestination::new(path)),
         // Storage::ElefantDirectory { path } => Box::new(crate::ElefantDirectoryDestination::new(path)),
     }
@@ -90,15 +231,60 @@ async fn do_export(
 }
 
 #[instrument(skip_all)]
-async fn do_import(db_args: ImportDbArgs, source: Storage, _usize: NonZeroUsize) -> Result<()> {
+async fn do_import(
+    db_args: ImportDbArgs,
+    source: Storage,
+    max_parallelism: NonZeroUsize,
+) -> SyncResult {
     let connection_string = db_args.get_connection_string();
 
-    let target_connection = PostgresClientWrapper::new(&connection_string).await?;
+    let target_connection = PostgresClientWrapper::new(&connection_string)
+        .await
+        .map_err(SyncError::connecting_to_target)?;
     match source {
         Storage::SqlFile { path, .. } => {
-            let file = tokio::fs::File::open(path).await?;
+            confirm_target(&db_args, &target_connection).await?;
+
+            let file = tokio::fs::File::open(&path)
+                .await
+                .with_context_path("open", &path)?;
             let mut reader = tokio::io::BufReader::new(file);
-            apply_sql_file(&mut reader, &target_connection).await?;
+            apply_sql_file_parallel(&mut reader, &target_connection, max_parallelism).await?;
+        }
+        Storage::SqlDirectory { path, .. } => {
+            confirm_target(&db_args, &target_connection).await?;
+
+            apply_sql_directory(&path, &target_connection).await?;
+        }
+        Storage::CsvDir { .. } => {
+            return Err(ElefantToolsError::ExportOnlyStorage("csv-dir".to_string()).into());
+        }
+        Storage::PgCustomFile { .. } => {
+            return Err(ElefantToolsError::ExportOnlyStorage("pg-custom-file".to_string()).into());
+        }
+        Storage::CsvFile {
+            path,
+            table,
+            delimiter,
+            null_string,
+            truncate,
+        } => {
+            let (schema, table) = table.split_once('.').unwrap_or(("public", &table));
+
+            let source = CsvFileSource::open(
+                &path,
+                CsvFileOptions {
+                    delimiter,
+                    null_string,
+                    truncate_first: truncate,
+                },
+            )
+            .await?;
+
+            let rows = source
+                .import_into_table(&target_connection, schema, table)
+                .await?;
+            info!("Imported {} rows into {}.{}", rows, schema, table);
         }
     }
 
@@ -106,38 +292,293 @@ async fn do_import(db_args: ImportDbArgs, source: Storage, _usize: NonZeroUsize)
 }
 
 #[instrument(skip_all)]
-async fn do_copy(copy_args: CopyArgs, max_parallel: NonZeroUsize) -> Result<()> {
-    let source_connection =
-        PostgresClientWrapper::new(&copy_args.source.get_connection_string()).await?;
-    let source = PostgresInstanceStorage::new(&source_connection).await?;
-
-    let target_connection =
-        PostgresClientWrapper::new(&copy_args.target.get_connection_string()).await?;
-    let mut target = PostgresInstanceStorage::new(&target_connection).await?;
-
-    copy_data(
-        &source,
-        &mut target,
-        CopyDataOptions {
-            data_format: None,
-            max_parallel: Some(max_parallel),
-            rename_schema_to: copy_args.target.target_schema,
-            target_schema: copy_args.source.source_schema.clone(),
-            schema_only: copy_args.source.schema_only,
-            differential: copy_args.differential,
+async fn do_copy(copy_args: CopyArgs, max_parallel: NonZeroUsize) -> SyncResult {
+    let source_connection = PostgresClientWrapper::new(&copy_args.source.get_connection_string())
+        .await
+        .map_err(SyncError::connecting_to_source)?;
+    let source = PostgresInstanceStorage::with_connection_pool(
+        &source_connection,
+        ConnectionPool::new(max_parallel),
+    )
+    .await?;
+
+    if copy_args.create_target_db && !copy_args.explain && !copy_args.dry_run {
+        let maintenance_connection =
+            PostgresClientWrapper::new(&copy_args.target.get_maintenance_connection_string())
+                .await
+                .map_err(SyncError::connecting_to_target)?;
+        let created = DatabaseEncoding::read(&source_connection)
+            .await?
+            .create_database_if_missing(&maintenance_connection, &copy_args.target.target_db_name)
+            .await?;
+        if created {
+            info!("Created target database {}", copy_args.target.target_db_name);
+        }
+    }
+
+    let summary = SchemaReader::new(&source_connection)
+        .quick_summary(copy_args.source.source_schema.as_deref())
+        .await?;
+    info!("About to copy {}", summary);
+
+    let target_connection = PostgresClientWrapper::new(&copy_args.target.get_connection_string())
+        .await
+        .map_err(SyncError::connecting_to_target)?;
+
+    let column_renames = match &copy_args.column_rename_hints {
+        Some(path) => ColumnRename::parse_hints(
+            &std::fs::read_to_string(path).with_context_path("read", path)?,
+        )?,
+        None => Vec::new(),
+    };
+
+    let options = CopyDataOptions {
+        data_format: None,
+        max_parallel: Some(max_parallel),
+        rename_schema_to: copy_args.target.target_schema.clone(),
+        target_schema: copy_args.source.source_schema.clone(),
+        exclude_schemas: copy_args.source.exclude_schema.clone(),
+        object_filter: copy_args.source.get_object_filter(),
+        schema_only: copy_args.source.schema_only,
+        differential: copy_args.differential,
+        strict_encoding: copy_args.strict_encoding,
+        preflight_encoding_check: copy_args.preflight_encoding_check,
+        allow_destructive: copy_args.allow_destructive,
+        allow_drops: copy_args.allow_drops,
+        column_renames,
+        hypertable_conversions: Vec::new(),
+        strip_timescale: copy_args.source.strip_timescale,
+        skip_indexes: copy_args.skip_indexes,
+        skip_primary_keys: copy_args.skip_primary_keys,
+        indexes_only: copy_args.indexes_only,
+        create_indexes_concurrently: copy_args.concurrently,
+        table_select_overrides: HashMap::new(),
+        max_table_data_size_bytes: copy_args.skip_data_larger_than,
+        destination_capacity_bytes: copy_args.destination_capacity,
+        exclude_columns: copy_args.source.exclude_column.clone(),
+        drop_dependent_objects: copy_args.source.drop_dependent_objects,
+        verification: match copy_args.verify_sample_rows {
+            Some(rows_per_table) => VerificationLevel::Sample { rows_per_table },
+            None if copy_args.verify => VerificationLevel::Checksum,
+            None => VerificationLevel::None,
         },
+        order_self_referencing: copy_args.source.order_self_referencing,
+        staging_swap: copy_args
+            .staging_swap
+            .then(|| StagingSwap::new(copy_args.keep_old_schema)),
+        same_server_optimization: copy_args.same_server_optimization,
+        privilege_downgrade: copy_args.privilege_downgrade,
+        strict_index_comparison: copy_args.strict_index_comparison,
+        tablespace_mapping: copy_args.map_tablespace.iter().cloned().collect(),
+        no_tablespaces: copy_args.no_tablespaces,
+        skip_privileges: copy_args.skip_privileges,
+        refresh_materialized_views: !copy_args.no_refresh_materialized_views,
+        grant_sequences_to: copy_args.grant_sequences_to.clone(),
+        dry_run: copy_args.dry_run,
+        resume_state_path: copy_args.resume_file.clone(),
+        statement_interceptor: None,
+        introspection_extensions: Vec::new(),
+        ddl_extensions: Vec::new(),
+    };
+
+    if copy_args.explain {
+        let target = PostgresInstanceStorage::new(&target_connection).await?;
+        print!("{}", plan_copy(&source, &target, options).await?);
+        return Ok(());
+    }
+
+    if !copy_args.dry_run {
+        confirm_target(&copy_args.target, &target_connection).await?;
+    }
+    let mut target = PostgresInstanceStorage::with_connection_pool(
+        &target_connection,
+        ConnectionPool::new(max_parallel),
     )
     .await?;
 
+    let report = copy_data(&source, &mut target, options).await?;
+
+    if let Some(dry_run) = &report.dry_run {
+        print!("{}", dry_run);
+        return Ok(());
+    }
+
+    if copy_args.timing_report {
+        print!("{}", format_timing_report(&report.table_timings));
+    }
+
+    if copy_args.verify_sample_rows.is_some() || copy_args.verify {
+        print!("{}", format_verification_report(&report.verification));
+
+        let mismatched_tables = report
+            .verification
+            .iter()
+            .filter(|v| !v.result.is_match())
+            .map(|v| format!("{}.{}", v.schema_name, v.table_name))
+            .collect::<Vec<_>>();
+        if !mismatched_tables.is_empty() {
+            return Err(ElefantToolsError::VerificationFailed(mismatched_tables).into());
+        }
+    }
+
     Ok(())
 }
 
+/// Formats the result of verifying the copied data, with examples of the differing rows.
+fn format_verification_report(verification: &[TableVerification]) -> String {
+    let mut report = String::new();
+
+    for table in verification {
+        let name = format!("{}.{}", table.schema_name, table.table_name);
+        match &table.result {
+            TableVerificationResult::Sampled {
+                rows_compared,
+                mismatched_rows,
+                examples,
+            } => {
+                report.push_str(&format!(
+                    "{}: {} of {} sampled rows differ\n",
+                    name, mismatched_rows, rows_compared
+                ));
+
+                for example in examples {
+                    let key = example
+                        .primary_key
+                        .iter()
+                        .map(|(column, value)| format!("{}={}", column, value))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    if example.missing_in_destination {
+                        report.push_str(&format!("    ({}): missing in the target\n", key));
+                    }
+
+                    for column in &example.differing_columns {
+                        let format_value = |value: &Option<String>| match value {
+                            Some(v) => format!("'{}'", v),
+                            None => "null".to_string(),
+                        };
+                        report.push_str(&format!(
+                            "    ({}): {} is {} in the source, but {} in the target\n",
+                            key,
+                            column.column_name,
+                            format_value(&column.source_value),
+                            format_value(&column.destination_value)
+                        ));
+                    }
+                }
+            }
+            TableVerificationResult::RowCountOnly {
+                source_rows,
+                destination_rows,
+            } => {
+                report.push_str(&format!(
+                    "{}: {} rows in the source, {} rows in the target (no primary key, only the number of rows is compared)\n",
+                    name, source_rows, destination_rows
+                ));
+            }
+            TableVerificationResult::Checksum {
+                source_rows,
+                destination_rows,
+                source_checksum,
+                destination_checksum,
+            } => {
+                report.push_str(&format!(
+                    "{}: {} rows in the source, {} rows in the target, the checksums {}\n",
+                    name,
+                    source_rows,
+                    destination_rows,
+                    if source_checksum == destination_checksum {
+                        "match"
+                    } else {
+                        "differ"
+                    }
+                ));
+            }
+        }
+    }
+
+    report
+}
+
+/// Formats the timings of the copied tables as a table, with the slowest tables first.
+fn format_timing_report(timings: &[TableCopyTiming]) -> String {
+    let mut timings = timings.iter().collect::<Vec<_>>();
+    timings.sort_by_key(|t| std::cmp::Reverse(t.total));
+
+    let names = timings
+        .iter()
+        .map(|t| format!("{}.{}", t.schema_name, t.table_name))
+        .collect::<Vec<_>>();
+    let name_width = names.iter().map(|n| n.len()).max().unwrap_or(0).max(5);
+
+    let mut report = format!(
+        "{:<name_width$}  {:>10}  {:>11}  {:>11}  {:>12}  {:>14}  {:>8}\n",
+        "Table", "Total", "Source wait", "Target wait", "Rows", "Bytes", "Chunks"
+    );
+
+    for (name, timing) in names.iter().zip(timings) {
+        report.push_str(&format!(
+            "{:<name_width$}  {:>9.3}s  {:>10.3}s  {:>10.3}s  {:>12}  {:>14}  {:>8}\n",
+            name,
+            timing.total.as_secs_f64(),
+            timing.source_wait.as_secs_f64(),
+            timing.destination_wait.as_secs_f64(),
+            timing.rows,
+            timing.bytes,
+            timing.chunks
+        ));
+    }
+
+    report
+}
+
+/// Asks for confirmation on stdin before writing to a target database that isn't empty,
+/// if `--confirm` was specified.
+async fn confirm_target(
+    db_args: &ImportDbArgs,
+    target_connection: &PostgresClientWrapper,
+) -> Result<()> {
+    if !db_args.confirm {
+        return Ok(());
+    }
+
+    let summary = SchemaReader::new(target_connection)
+        .quick_summary(db_args.target_schema.as_deref())
+        .await?;
+    if summary.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "The target database {} already contains {}",
+        db_args.target_db_name, summary
+    );
+
+    if db_args.yes {
+        return Ok(());
+    }
+
+    print!("Continue? [y/N] ");
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        Ok(())
+    } else {
+        Err(ElefantToolsError::ConfirmationDeclined)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
     use elefant_test_macros::pg_test;
     use elefant_tools::test_helpers::TestHelper;
-    use elefant_tools::{test_helpers, SqlDataMode};
+    use elefant_tools::{test_helpers, ColumnMismatch, RowMismatch, SqlDataMode};
 
     #[pg_test(arg(postgres = 16), arg(postgres = 16))]
     async fn test_export_import(source: &TestHelper, destination: &TestHelper) {
@@ -158,10 +599,16 @@ mod tests {
             max_parallelism: NonZeroUsize::new(1).unwrap(),
             command: Commands::Export {
                 destination: Storage::SqlFile {
-                    path: sql_file_path.clone(),
+                    path: sql_file_path.clone().into(),
                     max_rows_per_insert: 1000,
+                    max_bytes_per_insert: 1024 * 1024,
                     format: SqlDataMode::InsertStatements,
                     max_commands_per_chunk: 5,
+                    transaction_per_chunk: false,
+                    max_buffer_bytes: 1024,
+                    copy_delimiter: None,
+                    copy_null_marker: None,
+                    insert_mode_column_limit: SqlFileOptions::default().insert_mode_column_limit,
                 },
                 db_args: ExportDbArgs::from_test_helper(source),
             },
@@ -173,10 +620,16 @@ mod tests {
             max_parallelism: NonZeroUsize::new(1).unwrap(),
             command: Commands::Import {
                 source: Storage::SqlFile {
-                    path: sql_file_path,
+                    path: sql_file_path.into(),
                     max_rows_per_insert: 1000,
+                    max_bytes_per_insert: 1024 * 1024,
                     format: SqlDataMode::InsertStatements,
                     max_commands_per_chunk: 5,
+                    transaction_per_chunk: false,
+                    max_buffer_bytes: 1024,
+                    copy_delimiter: None,
+                    copy_null_marker: None,
+                    insert_mode_column_limit: SqlFileOptions::default().insert_mode_column_limit,
                 },
                 db_args: ImportDbArgs::from_test_helper(destination),
             },
@@ -209,10 +662,16 @@ mod tests {
             max_parallelism: NonZeroUsize::new(1).unwrap(),
             command: Commands::Export {
                 destination: Storage::SqlFile {
-                    path: sql_file_path.clone(),
+                    path: sql_file_path.clone().into(),
                     max_rows_per_insert: 1000,
+                    max_bytes_per_insert: 1024 * 1024,
                     format: SqlDataMode::CopyStatements,
                     max_commands_per_chunk: 5,
+                    transaction_per_chunk: false,
+                    max_buffer_bytes: 1024,
+                    copy_delimiter: None,
+                    copy_null_marker: None,
+                    insert_mode_column_limit: SqlFileOptions::default().insert_mode_column_limit,
                 },
                 db_args: ExportDbArgs::from_test_helper(source),
             },
@@ -224,10 +683,16 @@ mod tests {
             max_parallelism: NonZeroUsize::new(1).unwrap(),
             command: Commands::Import {
                 source: Storage::SqlFile {
-                    path: sql_file_path,
+                    path: sql_file_path.into(),
                     max_rows_per_insert: 1000,
+                    max_bytes_per_insert: 1024 * 1024,
                     format: SqlDataMode::CopyStatements,
                     max_commands_per_chunk: 5,
+                    transaction_per_chunk: false,
+                    max_buffer_bytes: 1024,
+                    copy_delimiter: None,
+                    copy_null_marker: None,
+                    insert_mode_column_limit: SqlFileOptions::default().insert_mode_column_limit,
                 },
                 db_args: ImportDbArgs::from_test_helper(destination),
             },
@@ -258,6 +723,34 @@ mod tests {
                 source: ExportDbArgs::from_test_helper(source),
                 target: ImportDbArgs::from_test_helper(destination),
                 differential: false,
+                strict_encoding: false,
+                preflight_encoding_check: false,
+                allow_destructive: false,
+                allow_drops: false,
+                column_rename_hints: None,
+                skip_indexes: false,
+                skip_primary_keys: false,
+                indexes_only: false,
+                concurrently: false,
+                create_target_db: false,
+                timing_report: false,
+                verify_sample_rows: None,
+                verify: false,
+                explain: false,
+                dry_run: false,
+                resume_file: None,
+                staging_swap: false,
+                keep_old_schema: false,
+                same_server_optimization: false,
+                no_refresh_materialized_views: false,
+                skip_data_larger_than: None,
+                destination_capacity: None,
+                privilege_downgrade: false,
+                strict_index_comparison: false,
+                map_tablespace: Vec::new(),
+                no_tablespaces: false,
+                skip_privileges: false,
+                grant_sequences_to: Vec::new(),
             }),
         };
 
@@ -269,6 +762,93 @@ mod tests {
         assert_eq!(rows, vec![1]);
     }
 
+    #[test]
+    fn timing_report_lists_slowest_tables_first() {
+        let timing = |table_name: &str, total_ms: u64, bytes: u64| TableCopyTiming {
+            schema_name: "public".to_string(),
+            table_name: table_name.to_string(),
+            total: Duration::from_millis(total_ms),
+            source_wait: Duration::from_millis(total_ms / 4),
+            destination_wait: Duration::from_millis(total_ms - total_ms / 4),
+            bytes,
+            chunks: bytes / 100,
+            rows: bytes / 10,
+        };
+
+        let report = format_timing_report(&[
+            timing("people", 1200, 4500),
+            timing("orders_with_long_name", 34000, 1_000_000),
+        ]);
+
+        assert_eq!(
+            report,
+            "\
+Table                              Total  Source wait  Target wait          Rows           Bytes    Chunks
+public.orders_with_long_name     34.000s       8.500s      25.500s        100000         1000000     10000
+public.people                     1.200s       0.300s       0.900s           450            4500        45
+"
+        );
+    }
+
+    #[test]
+    fn verification_report_lists_differing_rows() {
+        let report = format_verification_report(&[
+            TableVerification {
+                schema_name: "public".to_string(),
+                table_name: "people".to_string(),
+                result: TableVerificationResult::Sampled {
+                    rows_compared: 100,
+                    mismatched_rows: 2,
+                    examples: vec![
+                        RowMismatch {
+                            primary_key: vec![("id".to_string(), "5".to_string())],
+                            missing_in_destination: false,
+                            differing_columns: vec![ColumnMismatch {
+                                column_name: "name".to_string(),
+                                source_value: Some("Søren".to_string()),
+                                destination_value: Some("S?ren".to_string()),
+                            }],
+                        },
+                        RowMismatch {
+                            primary_key: vec![("id".to_string(), "7".to_string())],
+                            missing_in_destination: true,
+                            differing_columns: vec![],
+                        },
+                    ],
+                },
+            },
+            TableVerification {
+                schema_name: "public".to_string(),
+                table_name: "log".to_string(),
+                result: TableVerificationResult::RowCountOnly {
+                    source_rows: 10,
+                    destination_rows: 10,
+                },
+            },
+            TableVerification {
+                schema_name: "public".to_string(),
+                table_name: "orders".to_string(),
+                result: TableVerificationResult::Checksum {
+                    source_rows: 20,
+                    destination_rows: 20,
+                    source_checksum: "123".to_string(),
+                    destination_checksum: "456".to_string(),
+                },
+            },
+        ]);
+
+        assert_eq!(
+            report,
+            "\
+public.people: 2 of 100 sampled rows differ
+    (id=5): name is 'Søren' in the source, but 'S?ren' in the target
+    (id=7): missing in the target
+public.log: 10 rows in the source, 10 rows in the target (no primary key, only the number of rows is compared)
+public.orders: 20 rows in the source, 20 rows in the target, the checksums differ
+"
+        );
+    }
+
     #[pg_test(arg(postgres = 16), arg(postgres = 16))]
     async fn test_copy_between_schemas(source: &TestHelper, destination: &TestHelper) {
         source
@@ -294,6 +874,34 @@ mod tests {
                     ..ImportDbArgs::from_test_helper(destination)
                 },
                 differential: false,
+                strict_encoding: false,
+                preflight_encoding_check: false,
+                allow_destructive: false,
+                allow_drops: false,
+                column_rename_hints: None,
+                skip_indexes: false,
+                skip_primary_keys: false,
+                indexes_only: false,
+                concurrently: false,
+                create_target_db: false,
+                timing_report: false,
+                verify_sample_rows: None,
+                verify: false,
+                explain: false,
+                dry_run: false,
+                resume_file: None,
+                staging_swap: false,
+                keep_old_schema: false,
+                same_server_optimization: false,
+                no_refresh_materialized_views: false,
+                skip_data_larger_than: None,
+                destination_capacity: None,
+                privilege_downgrade: false,
+                strict_index_comparison: false,
+                map_tablespace: Vec::new(),
+                no_tablespaces: false,
+                skip_privileges: false,
+                grant_sequences_to: Vec::new(),
             }),
         };
 
@@ -304,4 +912,274 @@ mod tests {
             .await;
         assert_eq!(rows, vec![1]);
     }
+
+    #[pg_test(arg(postgres = 16))]
+    async fn test_copy_creates_target_database(source: &TestHelper) {
+        source
+            .execute_not_query(
+                r#"
+        create table test_table(id int);
+        insert into test_table(id) values (1);
+        "#,
+            )
+            .await;
+
+        let target_db_name = format!("{}_created", source.test_db_name);
+        let target = ImportDbArgs {
+            target_db_name: target_db_name.clone(),
+            ..ImportDbArgs::from_test_helper(source)
+        };
+
+        let parameters = cli::Cli {
+            max_parallelism: NonZeroUsize::new(1).unwrap(),
+            command: Commands::Copy(CopyArgs {
+                source: ExportDbArgs::from_test_helper(source),
+                target: target.clone(),
+                differential: false,
+                strict_encoding: true,
+                preflight_encoding_check: false,
+                allow_destructive: false,
+                allow_drops: false,
+                column_rename_hints: None,
+                skip_indexes: false,
+                skip_primary_keys: false,
+                indexes_only: false,
+                concurrently: false,
+                create_target_db: true,
+                timing_report: false,
+                verify_sample_rows: None,
+                verify: false,
+                explain: false,
+                dry_run: false,
+                resume_file: None,
+                staging_swap: false,
+                keep_old_schema: false,
+                same_server_optimization: false,
+                no_refresh_materialized_views: false,
+                skip_data_larger_than: None,
+                destination_capacity: None,
+                privilege_downgrade: false,
+                strict_index_comparison: false,
+                map_tablespace: Vec::new(),
+                no_tablespaces: false,
+                skip_privileges: false,
+                grant_sequences_to: Vec::new(),
+            }),
+        };
+
+        run(parameters).await.unwrap();
+
+        let target_connection = PostgresClientWrapper::new(&target.get_connection_string())
+            .await
+            .unwrap();
+        let rows = target_connection
+            .get_single_results::<i32>("select id from test_table;")
+            .await
+            .unwrap();
+        assert_eq!(rows, vec![1]);
+        assert_eq!(
+            DatabaseEncoding::read(&target_connection).await.unwrap(),
+            DatabaseEncoding::read(source.get_conn()).await.unwrap()
+        );
+        drop(target_connection);
+
+        let maintenance_connection =
+            PostgresClientWrapper::new(&target.get_maintenance_connection_string())
+                .await
+                .unwrap();
+        maintenance_connection
+            .execute_non_query(&format!("drop database {} with (force);", target_db_name))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unreachable_source_exits_with_source_connection_error() {
+        let source = ExportDbArgs {
+            source_db_host: "localhost".to_string(),
+            source_db_port: 1,
+            source_db_user: "postgres".to_string(),
+            source_db_password: "passw0rd".to_string(),
+            source_db_name: "unreachable".to_string(),
+            source_schema: None,
+            exclude_schema: Vec::new(),
+            include_table: Vec::new(),
+            exclude_table: Vec::new(),
+            include_view: Vec::new(),
+            exclude_view: Vec::new(),
+            include_materialized_view: Vec::new(),
+            exclude_materialized_view: Vec::new(),
+            include_function: Vec::new(),
+            exclude_function: Vec::new(),
+            include_trigger: Vec::new(),
+            exclude_trigger: Vec::new(),
+            include_sequence: Vec::new(),
+            exclude_sequence: Vec::new(),
+            schema_only: false,
+            exclude_column: Vec::new(),
+            drop_dependent_objects: false,
+            order_self_referencing: false,
+            strip_timescale: false,
+        };
+
+        let result = run(cli::Cli {
+            max_parallelism: NonZeroUsize::new(1).unwrap(),
+            command: Commands::Export {
+                db_args: source,
+                destination: Storage::CsvDir {
+                    path: "unused".into(),
+                    delimiter: ',',
+                    no_header: false,
+                },
+            },
+        })
+        .await;
+
+        let error = result.unwrap_err();
+        assert_eq!(error.class(), elefant_tools::ErrorClass::Connection);
+        assert_eq!(error.exit_code(), exit_code::SOURCE_CONNECTION_ERROR);
+    }
+
+    #[pg_test(arg(postgres = 16))]
+    async fn unreachable_target_exits_with_target_connection_error(source: &TestHelper) {
+        let mut target = ImportDbArgs::from_test_helper(source);
+        target.target_db_port = 1;
+
+        let result = run(cli::Cli {
+            max_parallelism: NonZeroUsize::new(1).unwrap(),
+            command: Commands::Copy(CopyArgs {
+                source: ExportDbArgs::from_test_helper(source),
+                target,
+                differential: false,
+                strict_encoding: false,
+                preflight_encoding_check: false,
+                allow_destructive: false,
+                allow_drops: false,
+                column_rename_hints: None,
+                skip_indexes: false,
+                skip_primary_keys: false,
+                indexes_only: false,
+                concurrently: false,
+                create_target_db: false,
+                timing_report: false,
+                verify_sample_rows: None,
+                verify: false,
+                explain: false,
+                dry_run: false,
+                resume_file: None,
+                staging_swap: false,
+                keep_old_schema: false,
+                same_server_optimization: false,
+                no_refresh_materialized_views: false,
+                skip_data_larger_than: None,
+                destination_capacity: None,
+                privilege_downgrade: false,
+                strict_index_comparison: false,
+                map_tablespace: Vec::new(),
+                no_tablespaces: false,
+                skip_privileges: false,
+                grant_sequences_to: Vec::new(),
+            }),
+        })
+        .await;
+
+        assert_eq!(
+            result.unwrap_err().exit_code(),
+            exit_code::TARGET_CONNECTION_ERROR
+        );
+    }
+
+    fn copy_with_confirmation(source: &TestHelper, destination: &TestHelper, yes: bool) -> cli::Cli {
+        cli::Cli {
+            max_parallelism: NonZeroUsize::new(1).unwrap(),
+            command: Commands::Copy(CopyArgs {
+                source: ExportDbArgs::from_test_helper(source),
+                target: ImportDbArgs {
+                    confirm: true,
+                    yes,
+                    ..ImportDbArgs::from_test_helper(destination)
+                },
+                differential: true,
+                strict_encoding: false,
+                preflight_encoding_check: false,
+                allow_destructive: false,
+                allow_drops: false,
+                column_rename_hints: None,
+                skip_indexes: false,
+                skip_primary_keys: false,
+                indexes_only: false,
+                concurrently: false,
+                create_target_db: false,
+                timing_report: false,
+                verify_sample_rows: None,
+                verify: false,
+                explain: false,
+                dry_run: false,
+                resume_file: None,
+                staging_swap: false,
+                keep_old_schema: false,
+                same_server_optimization: false,
+                no_refresh_materialized_views: false,
+                skip_data_larger_than: None,
+                destination_capacity: None,
+                privilege_downgrade: false,
+                strict_index_comparison: false,
+                map_tablespace: Vec::new(),
+                no_tablespaces: false,
+                skip_privileges: false,
+                grant_sequences_to: Vec::new(),
+            }),
+        }
+    }
+
+    #[pg_test(arg(postgres = 16), arg(postgres = 16))]
+    async fn test_copy_asks_for_confirmation_when_target_is_not_empty(
+        source: &TestHelper,
+        destination: &TestHelper,
+    ) {
+        source
+            .execute_not_query(
+                r#"
+        create table test_table(id int);
+        insert into test_table(id) values (1);
+        create table other_table(id int);
+        insert into other_table(id) values (2);
+        "#,
+            )
+            .await;
+        destination
+            .execute_not_query("create table test_table(id int);")
+            .await;
+
+        // Nothing can be read from stdin during tests, so the confirmation is declined.
+        let result = run(copy_with_confirmation(source, destination, false)).await;
+        assert!(
+            matches!(
+                &result,
+                Err(SyncError {
+                    error: ElefantToolsError::ConfirmationDeclined,
+                    ..
+                })
+            ),
+            "Expected the copy to be declined, got {:?}",
+            result
+        );
+        assert_eq!(result.unwrap_err().exit_code(), exit_code::VALIDATION_ERROR);
+
+        let tables = destination
+            .get_single_results::<String>(
+                "select table_name::text from information_schema.tables where table_schema = 'public';",
+            )
+            .await;
+        assert_eq!(tables, vec!["test_table".to_string()]);
+
+        run(copy_with_confirmation(source, destination, true))
+            .await
+            .unwrap();
+
+        let rows = destination
+            .get_single_results::<i32>("select id from other_table;")
+            .await;
+        assert_eq!(rows, vec![2]);
+    }
 }
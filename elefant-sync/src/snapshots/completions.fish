@@ -0,0 +1,263 @@
+# Print an optspec for argparse to handle cmd's options that are independent of any subcommand.
+function __fish_elefant_sync_global_optspecs
+	string join \n max-parallelism= h/help V/version
+end
+
+function __fish_elefant_sync_needs_command
+	# Figure out if the current invocation already has a command.
+	set -l cmd (commandline -opc)
+	set -e cmd[1]
+	argparse -s (__fish_elefant_sync_global_optspecs) -- $cmd 2>/dev/null
+	or return
+	if set -q argv[1]
+		# Also print the command, so this can be used to figure out what it is.
+		echo $argv[1]
+		return 1
+	end
+	return 0
+end
+
+function __fish_elefant_sync_using_subcommand
+	set -l cmd (__fish_elefant_sync_needs_command)
+	test -z "$cmd"
+	and return 1
+	contains -- $cmd[1] $argv
+end
+
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -l max-parallelism -d 'How many threads to use when exporting or importing. Defaults to the number of estimated cores on the machine. If the available parallelism cannot be determined, it defaults to 1' -r
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -s h -l help -d 'Print help (see more with \'--help\')'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -f -a "export" -d 'Export a database schema to a file or directory to be imported later on'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -f -a "import" -d 'Import a database schema from a file or directory that was made using the export command'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -f -a "copy" -d 'Copy a database schema from one database to another'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -f -a "batch" -d 'Run several copies listed in a TOML file, one after another or a few at a time, and print a report of all of them'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -f -a "inventory" -d 'Introspect every database on a server and write a JSON snapshot of each of them to a directory, as `<database>.json`'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -f -a "diff" -d 'Compare the structure of two databases, and print the SQL that makes the target match the source. The SQL is not applied'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -f -a "completions" -d 'Generate shell completions for elefant-sync and write them to stdout'
+complete -c elefant-sync -n "__fish_elefant_sync_needs_command" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l source-db-host -d 'The host of the source database to export from' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l source-db-port -d 'The port of the source database to export from' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l source-db-user -d 'The username to use when connecting to the source database' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l source-db-password -d 'The password to use when connecting to the source database' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l source-db-name -d 'The name of the source database to export from' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l source-schema -d 'The schema to export. If not specified, all schemas will be exported' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l exclude-schema -d 'A schema to leave out of the export, for example `partman` when using pg_partman. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l include-table -d 'Only export the tables matching this pattern, as `schema.table`, where `*` matches any number of characters. Partitions are exported along with their partitioned table. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l exclude-table -d 'Leave out the tables matching this pattern, as `schema.table`, where `*` matches any number of characters. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l include-view -d 'Only export the views matching this pattern, as `schema.view`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l exclude-view -d 'Leave out the views matching this pattern, as `schema.view`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l include-materialized-view -d 'Only export the materialized views matching this pattern, as `schema.view`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l exclude-materialized-view -d 'Leave out the materialized views matching this pattern, as `schema.view`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l include-function -d 'Only export the functions matching this pattern, as `schema.function` for every overload, or as `schema.function(arguments)` with the arguments as listed by `\\df`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l exclude-function -d 'Leave out the functions matching this pattern, in the same format as `--include-function`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l include-trigger -d 'Only export the triggers matching this pattern, as `schema.table.trigger`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l exclude-trigger -d 'Leave out the triggers matching this pattern, as `schema.table.trigger`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l include-sequence -d 'Only export the sequences matching this pattern, as `schema.sequence`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l exclude-sequence -d 'Leave out the sequences matching this pattern, as `schema.sequence`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l exclude-column -d 'A column to leave out of the created tables and the data, as `schema.table.column`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l schema-only -d 'Only the schema will be exported, but not the data'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l drop-dependent-objects -d 'Skip the indexes, constraints, views, triggers, policies and generated columns that use an excluded column, and the foreign keys, views and inheriting tables that use an excluded table, instead of failing'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l order-self-referencing -d 'Export the rows of tables with a foreign key to the table itself with parents before their children, so they can be imported while the foreign key is enforced'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l strip-timescale -d 'Copy TimescaleDB hypertables as plain tables and continuous aggregates as plain materialized views, leaving out the jobs and policies, for targets without TimescaleDB'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "sql-file" -d 'Export to a single SQL file. This files can be run directly against postgres without needing the elefant-sync tool to import it, however no additional processing can be done during import. This is only recommended for very small databases. For larger databases, use one of the Elefant options'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "csv-dir" -d 'Export the data of each table to a separate csv file in a directory, named `<schema>.<table>.csv`. Only the data is exported, the schema is not included. This is useful for handing data to other tools, but can not be imported again using elefant-sync'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "csv-file" -d 'Import a single csv file with a header row into an existing table. The header is checked against the columns of the table before any data is loaded. Can only be used for import'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "sql-directory" -d 'Export to a directory of SQL files, with a file for each table, view, function and other object, and a file with the data of each table. This makes the export easy to review and to keep in version control. The order to apply the files in is stored in `apply_order.txt`, which is used when the directory is imported again'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "pg-custom-file" -d 'Export to an archive in the custom format of pg_dump, which can be restored with pg_restore, including in parallel with `--jobs`. The archive is not compressed. Only supported on export'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l path -d 'The path to the .sql file to import/export' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l max-rows-per-insert -d 'How many rows to generate per insert statement. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l max-bytes-per-insert -d 'How big an insert statement can be in bytes before a new statement is started, regardless of the number of rows. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l max-commands-per-chunk -d 'How many DDL commands to generate per chunk. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l format -d 'The format to use when exporting. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l max-buffer-bytes -d 'How many bytes to write at most before waiting for the file to be flushed. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l copy-delimiter -d 'The character separating the columns in copy statements. Defaults to a tab. Only considered on export with the CopyStatements format' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l copy-null-marker -d 'The string representing null values in copy statements. Defaults to `\\N`. Only considered on export with the CopyStatements format' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l insert-mode-column-limit -d 'Tables with more columns than this are exported as copy statements, even with the InsertStatements format. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -l transaction-per-chunk -d 'Wrap each chunk of DDL commands in a transaction, so each chunk is applied atomically when the file is imported with psql. Only considered on export'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-file" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-dir" -l path -d 'The directory to write the csv files to. Will be created if it doesn\'t exist' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-dir" -l delimiter -d 'The character to separate values with. Files use the .tsv extension if this is a tab' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-dir" -l no-header -d 'Don\'t write a header row with the column names'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-dir" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-dir" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-file" -l path -d 'The path to the .csv file to import' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-file" -l table -d 'The table to load the data into, for example `public.customers`. If no schema is specified, `public` is used' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-file" -l delimiter -d 'The character separating the values in the file' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-file" -l null-string -d 'The string that represents null values. Defaults to an unquoted empty string' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-file" -l truncate -d 'Truncate the table before loading the data'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-file" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from csv-file" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-directory" -l path -d 'The directory to write the files to. Will be created if it doesn\'t exist. The files of an earlier export to the directory are replaced' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-directory" -l max-rows-per-insert -d 'How many rows to generate per insert statement. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-directory" -l format -d 'The format to use for the data when exporting. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-directory" -l chunk-separator -d 'The string separating the statements in the files. A fixed one is used by default, so only the files of the objects that changed differ between two exports. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-directory" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from sql-directory" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from pg-custom-file" -l path -d 'The path of the archive to write to' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from pg-custom-file" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from pg-custom-file" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from help" -f -a "sql-file" -d 'Export to a single SQL file. This files can be run directly against postgres without needing the elefant-sync tool to import it, however no additional processing can be done during import. This is only recommended for very small databases. For larger databases, use one of the Elefant options'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from help" -f -a "csv-dir" -d 'Export the data of each table to a separate csv file in a directory, named `<schema>.<table>.csv`. Only the data is exported, the schema is not included. This is useful for handing data to other tools, but can not be imported again using elefant-sync'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from help" -f -a "csv-file" -d 'Import a single csv file with a header row into an existing table. The header is checked against the columns of the table before any data is loaded. Can only be used for import'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from help" -f -a "sql-directory" -d 'Export to a directory of SQL files, with a file for each table, view, function and other object, and a file with the data of each table. This makes the export easy to review and to keep in version control. The order to apply the files in is stored in `apply_order.txt`, which is used when the directory is imported again'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from help" -f -a "pg-custom-file" -d 'Export to an archive in the custom format of pg_dump, which can be restored with pg_restore, including in parallel with `--jobs`. The archive is not compressed. Only supported on export'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand export; and __fish_seen_subcommand_from help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l target-db-host -d 'The host of the target database to import to' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l target-db-port -d 'The port of the target database to import to' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l target-db-user -d 'The username to use when connecting to the target database' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l target-db-password -d 'The password to use when connecting to the target database' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l target-db-name -d 'The name of the target database to import to' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l target-schema -d 'The schema to import to. If not specified, the schema will be imported to the same schema as it was exported from' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l confirm -d 'Ask for confirmation before writing to a target database that already contains tables, views, functions or other objects'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -l yes -d 'Answer yes to the confirmation asked for by `--confirm`. The summary of the target database is still printed'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "sql-file" -d 'Export to a single SQL file. This files can be run directly against postgres without needing the elefant-sync tool to import it, however no additional processing can be done during import. This is only recommended for very small databases. For larger databases, use one of the Elefant options'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "csv-dir" -d 'Export the data of each table to a separate csv file in a directory, named `<schema>.<table>.csv`. Only the data is exported, the schema is not included. This is useful for handing data to other tools, but can not be imported again using elefant-sync'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "csv-file" -d 'Import a single csv file with a header row into an existing table. The header is checked against the columns of the table before any data is loaded. Can only be used for import'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "sql-directory" -d 'Export to a directory of SQL files, with a file for each table, view, function and other object, and a file with the data of each table. This makes the export easy to review and to keep in version control. The order to apply the files in is stored in `apply_order.txt`, which is used when the directory is imported again'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "pg-custom-file" -d 'Export to an archive in the custom format of pg_dump, which can be restored with pg_restore, including in parallel with `--jobs`. The archive is not compressed. Only supported on export'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and not __fish_seen_subcommand_from sql-file csv-dir csv-file sql-directory pg-custom-file help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l path -d 'The path to the .sql file to import/export' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l max-rows-per-insert -d 'How many rows to generate per insert statement. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l max-bytes-per-insert -d 'How big an insert statement can be in bytes before a new statement is started, regardless of the number of rows. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l max-commands-per-chunk -d 'How many DDL commands to generate per chunk. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l format -d 'The format to use when exporting. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l max-buffer-bytes -d 'How many bytes to write at most before waiting for the file to be flushed. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l copy-delimiter -d 'The character separating the columns in copy statements. Defaults to a tab. Only considered on export with the CopyStatements format' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l copy-null-marker -d 'The string representing null values in copy statements. Defaults to `\\N`. Only considered on export with the CopyStatements format' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l insert-mode-column-limit -d 'Tables with more columns than this are exported as copy statements, even with the InsertStatements format. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -l transaction-per-chunk -d 'Wrap each chunk of DDL commands in a transaction, so each chunk is applied atomically when the file is imported with psql. Only considered on export'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-file" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-dir" -l path -d 'The directory to write the csv files to. Will be created if it doesn\'t exist' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-dir" -l delimiter -d 'The character to separate values with. Files use the .tsv extension if this is a tab' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-dir" -l no-header -d 'Don\'t write a header row with the column names'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-dir" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-dir" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-file" -l path -d 'The path to the .csv file to import' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-file" -l table -d 'The table to load the data into, for example `public.customers`. If no schema is specified, `public` is used' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-file" -l delimiter -d 'The character separating the values in the file' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-file" -l null-string -d 'The string that represents null values. Defaults to an unquoted empty string' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-file" -l truncate -d 'Truncate the table before loading the data'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-file" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from csv-file" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-directory" -l path -d 'The directory to write the files to. Will be created if it doesn\'t exist. The files of an earlier export to the directory are replaced' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-directory" -l max-rows-per-insert -d 'How many rows to generate per insert statement. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-directory" -l format -d 'The format to use for the data when exporting. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-directory" -l chunk-separator -d 'The string separating the statements in the files. A fixed one is used by default, so only the files of the objects that changed differ between two exports. Only considered on export' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-directory" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from sql-directory" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from pg-custom-file" -l path -d 'The path of the archive to write to' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from pg-custom-file" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from pg-custom-file" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from help" -f -a "sql-file" -d 'Export to a single SQL file. This files can be run directly against postgres without needing the elefant-sync tool to import it, however no additional processing can be done during import. This is only recommended for very small databases. For larger databases, use one of the Elefant options'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from help" -f -a "csv-dir" -d 'Export the data of each table to a separate csv file in a directory, named `<schema>.<table>.csv`. Only the data is exported, the schema is not included. This is useful for handing data to other tools, but can not be imported again using elefant-sync'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from help" -f -a "csv-file" -d 'Import a single csv file with a header row into an existing table. The header is checked against the columns of the table before any data is loaded. Can only be used for import'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from help" -f -a "sql-directory" -d 'Export to a directory of SQL files, with a file for each table, view, function and other object, and a file with the data of each table. This makes the export easy to review and to keep in version control. The order to apply the files in is stored in `apply_order.txt`, which is used when the directory is imported again'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from help" -f -a "pg-custom-file" -d 'Export to an archive in the custom format of pg_dump, which can be restored with pg_restore, including in parallel with `--jobs`. The archive is not compressed. Only supported on export'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand import; and __fish_seen_subcommand_from help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l source-db-host -d 'The host of the source database to export from' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l source-db-port -d 'The port of the source database to export from' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l source-db-user -d 'The username to use when connecting to the source database' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l source-db-password -d 'The password to use when connecting to the source database' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l source-db-name -d 'The name of the source database to export from' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l source-schema -d 'The schema to export. If not specified, all schemas will be exported' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l exclude-schema -d 'A schema to leave out of the export, for example `partman` when using pg_partman. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l include-table -d 'Only export the tables matching this pattern, as `schema.table`, where `*` matches any number of characters. Partitions are exported along with their partitioned table. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l exclude-table -d 'Leave out the tables matching this pattern, as `schema.table`, where `*` matches any number of characters. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l include-view -d 'Only export the views matching this pattern, as `schema.view`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l exclude-view -d 'Leave out the views matching this pattern, as `schema.view`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l include-materialized-view -d 'Only export the materialized views matching this pattern, as `schema.view`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l exclude-materialized-view -d 'Leave out the materialized views matching this pattern, as `schema.view`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l include-function -d 'Only export the functions matching this pattern, as `schema.function` for every overload, or as `schema.function(arguments)` with the arguments as listed by `\\df`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l exclude-function -d 'Leave out the functions matching this pattern, in the same format as `--include-function`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l include-trigger -d 'Only export the triggers matching this pattern, as `schema.table.trigger`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l exclude-trigger -d 'Leave out the triggers matching this pattern, as `schema.table.trigger`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l include-sequence -d 'Only export the sequences matching this pattern, as `schema.sequence`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l exclude-sequence -d 'Leave out the sequences matching this pattern, as `schema.sequence`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l exclude-column -d 'A column to leave out of the created tables and the data, as `schema.table.column`. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l target-db-host -d 'The host of the target database to import to' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l target-db-port -d 'The port of the target database to import to' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l target-db-user -d 'The username to use when connecting to the target database' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l target-db-password -d 'The password to use when connecting to the target database' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l target-db-name -d 'The name of the target database to import to' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l target-schema -d 'The schema to import to. If not specified, the schema will be imported to the same schema as it was exported from' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l column-rename-hints -d 'A file with columns that have been renamed in the source, as `schema.table.old_column=new_column` on each line. A differential copy renames them in the target instead of dropping the old column' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l verify-sample-rows -d 'After the copy, compare this many random rows of each table with the source, column by column. Tables without a primary key only have their number of rows compared. Exits with the data copy error code if any differences are found' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l resume-file -d 'Record the progress of the copy in this file, so an interrupted copy can be run again with the same arguments to continue where it stopped. Tables that were copied completely are skipped, and partially copied tables are copied again. The file is removed once the copy has finished' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l skip-data-larger-than -d 'Don\'t copy the data of tables larger than this in the source, such as `50GB` or `512MB`. The tables are still created, and the sequences are still set. The size counts the table and its toast data, but not its indexes' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l destination-capacity -d 'How large the target database can grow, such as `100GB`. Postgres cannot tell how much disk space is free, so if the current size of the target plus the size of the copied tables is larger than this, a warning is shown before the copy starts' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l map-tablespace -d 'Store the tables and indexes in a tablespace of the source in another tablespace in the target, as `old=new`. Use `pg_default` as the old name for the default tablespace of the source database. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l grant-sequences-to -d 'A role to grant `usage` and `select` on every copied sequence to, so it can insert into serial columns. The role has to exist in the target. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l schema-only -d 'Only the schema will be exported, but not the data'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l drop-dependent-objects -d 'Skip the indexes, constraints, views, triggers, policies and generated columns that use an excluded column, and the foreign keys, views and inheriting tables that use an excluded table, instead of failing'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l order-self-referencing -d 'Export the rows of tables with a foreign key to the table itself with parents before their children, so they can be imported while the foreign key is enforced'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l strip-timescale -d 'Copy TimescaleDB hypertables as plain tables and continuous aggregates as plain materialized views, leaving out the jobs and policies, for targets without TimescaleDB'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l confirm -d 'Ask for confirmation before writing to a target database that already contains tables, views, functions or other objects'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l yes -d 'Answer yes to the confirmation asked for by `--confirm`. The summary of the target database is still printed'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l differential -d 'Only the structures missing in the destination will be copied. Data copy is only checked against "empty table" vs "non-empty table". This only works with data sources that supports structural inspections, aka not sql-files'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l strict-encoding -d 'Fail instead of only warning when the encoding or locale of the source and target databases differ'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l preflight-encoding-check -d 'Check that the text in the source can be converted to the encoding of the target before anything is copied, when the encodings differ. The columns with text that cannot be converted are listed. Reads every text column in the source once'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l allow-destructive -d 'Allow a differential copy to drop columns from the target that no longer exist in the source. Views using those columns are dropped and recreated'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l allow-drops -d 'Allow a differential copy to drop the tables, views, functions, indexes and columns of the target that no longer exist in the source. Without it, the objects that would have been dropped are logged'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l skip-indexes -d 'Don\'t create indexes and unique constraints, so the data is loaded faster. Run the copy again with `--indexes-only` to create them afterwards. Foreign keys referencing a skipped index are skipped as well'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l skip-primary-keys -d 'Also skip primary keys when using `--skip-indexes`'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l indexes-only -d 'Only create the indexes and constraints that are missing in the target, such as the ones skipped by an earlier copy with `--skip-indexes`. No data is copied'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l concurrently -d 'Build indexes with `create index concurrently`, so writes to the tables are not blocked while the indexes are created'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l create-target-db -d 'Create the target database if it doesn\'t exist, using the same encoding and locale as the source database'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l timing-report -d 'Print how long the data of each table took to copy, and how much of the time was spent waiting for the source and the target, with the slowest tables first'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l verify -d 'After the copy, compare the number of rows and a checksum of all the rows of each table with the source. This reads every table completely on both sides, but finds any difference. Exits with the data copy error code if any differences are found'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l explain -d 'Print what the copy would do, such as the data format, whether the tables are copied in parallel and the order of the tables, and exit without copying anything'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l dry-run -d 'Print the statements the copy would apply to the target, and an estimate of the rows of each table, without changing the target or copying any data. The target is still read, so this shows what `--differential` would change'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l staging-swap -d 'Copy the source schema into a new staging schema, and swap it with the target schema in a single transaction once the copy is done. The old schema is dropped afterwards. Requires `--source-schema`'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l keep-old-schema -d 'Keep the replaced schema after a `--staging-swap`, instead of dropping it'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l same-server-optimization -d 'Copy the data in larger chunks when the source and the target are databases on the same server, as the data then doesn\'t have to go over a network'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l no-refresh-materialized-views -d 'Leave the materialized views unpopulated, instead of refreshing them after the data is copied. Makes schema only copies faster'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l privilege-downgrade -d 'Skip what requires the target user to be a superuser, such as untrusted extensions and leakproof functions, with a warning instead of failing. Useful for managed Postgres services'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l strict-index-comparison -d 'Also recreate the indexes of a differential copy whose storage parameters differ from the source. Indexes that are built differently are always recreated'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l no-tablespaces -d 'Create the tables and indexes in the default tablespace of the target, regardless of where they are stored in the source'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -l skip-privileges -d 'Don\'t copy the privileges granted on schemas, tables, views and functions. Otherwise the roles they are granted to have to exist in the target'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand copy" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand batch" -l config -d 'The path to the TOML file with the copy jobs' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand batch" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand batch" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -l source-db-host -d 'The host of the server to introspect' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -l source-db-port -d 'The port of the server to introspect' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -l source-db-user -d 'The username to use when connecting to the databases' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -l source-db-password -d 'The password to use when connecting to the databases' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -l maintenance-db-name -d 'The database to connect to when listing the databases on the server' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -l include-database -d 'Only introspect the databases matching this pattern, where `*` matches any number of characters. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -l exclude-database -d 'Leave out the databases matching this pattern, where `*` matches any number of characters. Can be specified multiple times' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -l output -d 'The directory to write the snapshots to. It is created if it doesn\'t exist' -r -F
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand inventory" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand diff" -l source-connection-string -d 'The connection string of the database with the wanted structure, such as `host=localhost user=postgres password=passw0rd dbname=app`' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand diff" -l target-connection-string -d 'The connection string of the database to generate the migration for' -r
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand diff" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand diff" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand completions" -s h -l help -d 'Print help'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand completions" -s V -l version -d 'Print version'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and not __fish_seen_subcommand_from export import copy batch inventory diff completions help" -f -a "export" -d 'Export a database schema to a file or directory to be imported later on'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and not __fish_seen_subcommand_from export import copy batch inventory diff completions help" -f -a "import" -d 'Import a database schema from a file or directory that was made using the export command'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and not __fish_seen_subcommand_from export import copy batch inventory diff completions help" -f -a "copy" -d 'Copy a database schema from one database to another'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and not __fish_seen_subcommand_from export import copy batch inventory diff completions help" -f -a "batch" -d 'Run several copies listed in a TOML file, one after another or a few at a time, and print a report of all of them'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and not __fish_seen_subcommand_from export import copy batch inventory diff completions help" -f -a "inventory" -d 'Introspect every database on a server and write a JSON snapshot of each of them to a directory, as `<database>.json`'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and not __fish_seen_subcommand_from export import copy batch inventory diff completions help" -f -a "diff" -d 'Compare the structure of two databases, and print the SQL that makes the target match the source. The SQL is not applied'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and not __fish_seen_subcommand_from export import copy batch inventory diff completions help" -f -a "completions" -d 'Generate shell completions for elefant-sync and write them to stdout'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and not __fish_seen_subcommand_from export import copy batch inventory diff completions help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from export" -f -a "sql-file" -d 'Export to a single SQL file. This files can be run directly against postgres without needing the elefant-sync tool to import it, however no additional processing can be done during import. This is only recommended for very small databases. For larger databases, use one of the Elefant options'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from export" -f -a "csv-dir" -d 'Export the data of each table to a separate csv file in a directory, named `<schema>.<table>.csv`. Only the data is exported, the schema is not included. This is useful for handing data to other tools, but can not be imported again using elefant-sync'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from export" -f -a "csv-file" -d 'Import a single csv file with a header row into an existing table. The header is checked against the columns of the table before any data is loaded. Can only be used for import'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from export" -f -a "sql-directory" -d 'Export to a directory of SQL files, with a file for each table, view, function and other object, and a file with the data of each table. This makes the export easy to review and to keep in version control. The order to apply the files in is stored in `apply_order.txt`, which is used when the directory is imported again'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from export" -f -a "pg-custom-file" -d 'Export to an archive in the custom format of pg_dump, which can be restored with pg_restore, including in parallel with `--jobs`. The archive is not compressed. Only supported on export'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from import" -f -a "sql-file" -d 'Export to a single SQL file. This files can be run directly against postgres without needing the elefant-sync tool to import it, however no additional processing can be done during import. This is only recommended for very small databases. For larger databases, use one of the Elefant options'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from import" -f -a "csv-dir" -d 'Export the data of each table to a separate csv file in a directory, named `<schema>.<table>.csv`. Only the data is exported, the schema is not included. This is useful for handing data to other tools, but can not be imported again using elefant-sync'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from import" -f -a "csv-file" -d 'Import a single csv file with a header row into an existing table. The header is checked against the columns of the table before any data is loaded. Can only be used for import'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from import" -f -a "sql-directory" -d 'Export to a directory of SQL files, with a file for each table, view, function and other object, and a file with the data of each table. This makes the export easy to review and to keep in version control. The order to apply the files in is stored in `apply_order.txt`, which is used when the directory is imported again'
+complete -c elefant-sync -n "__fish_elefant_sync_using_subcommand help; and __fish_seen_subcommand_from import" -f -a "pg-custom-file" -d 'Export to an archive in the custom format of pg_dump, which can be restored with pg_restore, including in parallel with `--jobs`. The archive is not compressed. Only supported on export'
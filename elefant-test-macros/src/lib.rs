@@ -8,6 +8,8 @@ use syn::{parse_macro_input, ItemFn};
 enum TestArgsArg {
     Postgres(i32),
     TimescaleDb(i32),
+    /// A primary with a physical streaming replica, injected as a `ReplicatedTestHelper`.
+    PostgresReplicated(i32),
 }
 
 impl TestArgsArg {
@@ -15,9 +17,14 @@ impl TestArgsArg {
         match self {
             TestArgsArg::Postgres(v) => format!("postgres_{}", v),
             TestArgsArg::TimescaleDb(v) => format!("timescale_{}", v),
+            TestArgsArg::PostgresReplicated(v) => format!("postgres_replicated_{}", v),
         }
     }
 
+    fn is_timescale(&self) -> bool {
+        matches!(self, TestArgsArg::TimescaleDb(_))
+    }
+
     fn get_port(&self) -> Result<u16, darling::Error> {
         match self {
             TestArgsArg::Postgres(12) => Ok(5412),
@@ -25,13 +32,23 @@ impl TestArgsArg {
             TestArgsArg::Postgres(14) => Ok(5414),
             TestArgsArg::Postgres(15) => Ok(5415),
             TestArgsArg::Postgres(16) => Ok(5416),
+            TestArgsArg::Postgres(17) => Ok(5417),
             TestArgsArg::TimescaleDb(15) => Ok(5515),
             TestArgsArg::TimescaleDb(16) => Ok(5516),
+            TestArgsArg::PostgresReplicated(16) => Ok(5616),
             _ => Err(darling::Error::custom(
                 "Unknown postgres implementation / version",
             )),
         }
     }
+
+    /// The port of the replica, which is the port after the primary.
+    fn get_replica_port(&self) -> Result<Option<u16>, darling::Error> {
+        match self {
+            TestArgsArg::PostgresReplicated(_) => Ok(Some(self.get_port()? + 1)),
+            _ => Ok(None),
+        }
+    }
 }
 
 #[derive(Debug, FromMeta)]
@@ -94,9 +111,9 @@ pub fn pg_test(args: TokenStream, item: TokenStream) -> TokenStream {
     let mut arg_idents = Vec::with_capacity(args.args.len());
 
     for (arg, input) in args.args.iter().zip(input.sig.inputs.iter()) {
-        let port = match arg.get_port() {
-            Ok(p) => p,
-            Err(e) => {
+        let (port, replica_port) = match (arg.get_port(), arg.get_replica_port()) {
+            (Ok(p), Ok(r)) => (p, r),
+            (Err(e), _) | (_, Err(e)) => {
                 return TokenStream::from(e.write_errors());
             }
         };
@@ -126,8 +143,13 @@ pub fn pg_test(args: TokenStream, item: TokenStream) -> TokenStream {
 
         let arg_name = arg_ident.to_string();
 
-        test_helpers_create.push(quote! {
-            let mut #arg_ident = test_helpers::get_test_helper_on_port(#arg_name, #port).await;
+        test_helpers_create.push(match replica_port {
+            Some(replica_port) => quote! {
+                let mut #arg_ident = test_helpers::get_replicated_test_helper_on_ports(#arg_name, #port, #replica_port).await;
+            },
+            None => quote! {
+                let mut #arg_ident = test_helpers::get_test_helper_on_port(#arg_name, #port).await;
+            },
         });
         test_helpers_stop.push(quote! {
             #arg_ident.stop().await;
@@ -151,10 +173,22 @@ pub fn pg_test(args: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
+    // Tests against TimescaleDB only exist when the crate is compiled with timescale support.
+    let (feature_gate, dead_code_allowance) = if args.args.iter().any(|a| a.is_timescale()) {
+        (
+            quote! { #[cfg(feature = "timescale")] },
+            quote! { #[cfg_attr(not(feature = "timescale"), allow(dead_code))] },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+
     let test_function = quote! {
 
+        #dead_code_allowance
         #input
 
+        #feature_gate
         #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
         async fn #actual_test_function_name() {
             #(#test_helpers_create)*
@@ -0,0 +1,30 @@
+//! Copies a single table from one database to another.
+//!
+//! Usage: `cargo run --example copy_table -- "<source connection string>" "<destination connection string>" public customers`
+use elefant_tools::prelude::*;
+
+#[tokio::main]
+async fn main() -> elefant_tools::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() != 5 {
+        eprintln!("Usage: cargo run --example copy_table -- \"<source connection string>\" \"<destination connection string>\" <schema> <table>");
+        std::process::exit(1);
+    }
+
+    let source = PostgresClientWrapper::new(&args[1]).await?;
+    let destination = PostgresClientWrapper::new(&args[2]).await?;
+
+    let report = copy_table(
+        &source,
+        &destination,
+        &args[3],
+        &args[4],
+        Default::default(),
+    )
+    .await?;
+
+    println!("{:?}", report);
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! Fails a build when the database does not have the columns the code expects. The contents of
+//! `main` can be used as a `build.rs`, with `elefant-tools` added to the `[build-dependencies]`.
+//!
+//! The database is introspected when `DATABASE_URL` is set, and the snapshot is written next to
+//! the build script, so it can be committed. Builds without a database, such as in CI, validate
+//! against the committed snapshot instead.
+//!
+//! Usage: `DATABASE_URL="<connection string>" cargo run --example schema_validation_build_script -- schema.json`
+use elefant_tools::{introspect_to_file, read_snapshot_file};
+
+/// The columns the code depends on, as (schema, table, column, data type).
+const EXPECTED_COLUMNS: &[(&str, &str, &str, &str)] = &[
+    ("public", "customers", "id", "int4"),
+    ("public", "customers", "email", "text"),
+];
+
+fn main() {
+    let snapshot_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "schema.json".to_string());
+
+    println!("cargo:rerun-if-changed={}", snapshot_path);
+    println!("cargo:rerun-if-env-changed=DATABASE_URL");
+
+    let database = match std::env::var("DATABASE_URL") {
+        Ok(connection_string) => introspect_to_file(&connection_string, &snapshot_path),
+        Err(_) => read_snapshot_file(&snapshot_path),
+    }
+    .unwrap_or_else(|e| panic!("Failed to get schema snapshot '{}': {}", snapshot_path, e));
+
+    for (schema_name, table_name, column_name, data_type) in EXPECTED_COLUMNS {
+        let column = database
+            .table(schema_name, table_name)
+            .and_then(|table| table.column(column_name))
+            .unwrap_or_else(|| {
+                panic!(
+                    "Column {}.{}.{} does not exist in the database",
+                    schema_name, table_name, column_name
+                )
+            });
+
+        assert_eq!(
+            &column.data_type, data_type,
+            "Column {}.{}.{} has the wrong type",
+            schema_name, table_name, column_name
+        );
+    }
+}
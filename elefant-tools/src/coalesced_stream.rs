@@ -0,0 +1,98 @@
+use crate::Result;
+use bytes::{Bytes, BytesMut};
+use futures::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// How large the chunks are made when the source and the destination are on the same server,
+/// see [crate::CopyDataOptions::same_server_optimization].
+pub(crate) const SAME_SERVER_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Wraps the data stream of a source, joining the chunks that are already available into
+/// chunks of up to about `target_size` bytes.
+///
+/// Postgres sends a chunk for every row, so the destination otherwise receives and writes many
+/// small chunks. Chunks are never held back waiting for more data, so this doesn't add latency.
+pub(crate) struct CoalescedStream<S> {
+    inner: Pin<Box<S>>,
+    target_size: usize,
+    buffer: BytesMut,
+    finished: bool,
+}
+
+impl<S> CoalescedStream<S> {
+    pub(crate) fn new(inner: S, target_size: usize) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            target_size,
+            buffer: BytesMut::new(),
+            finished: false,
+        }
+    }
+
+    fn take_buffer(&mut self) -> Poll<Option<Result<Bytes>>> {
+        Poll::Ready(Some(Ok(self.buffer.split().freeze())))
+    }
+}
+
+impl<S: Stream<Item = Result<Bytes>>> Stream for CoalescedStream<S> {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.finished && self.buffer.len() < self.target_size {
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => self.buffer.extend_from_slice(&bytes),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => self.finished = true,
+                Poll::Pending if self.buffer.is_empty() => return Poll::Pending,
+                Poll::Pending => break,
+            }
+        }
+
+        if self.buffer.is_empty() {
+            Poll::Ready(None)
+        } else {
+            self.take_buffer()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn joins_available_chunks_up_to_the_target_size() {
+        let chunks = ["foo\n", "bar\n", "baz\n", "qux\n", "quux\n"]
+            .into_iter()
+            .map(|c| Ok(Bytes::from(c)));
+        let stream = CoalescedStream::new(futures::stream::iter(chunks), 8);
+
+        let chunks = stream.map(|c| c.unwrap()).collect::<Vec<_>>().await;
+
+        assert_eq!(
+            chunks,
+            vec![
+                Bytes::from("foo\nbar\n"),
+                Bytes::from("baz\nqux\n"),
+                Bytes::from("quux\n"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_wait_for_more_data_before_returning_a_chunk() {
+        let (sender, receiver) = futures::channel::mpsc::unbounded::<Result<Bytes>>();
+        let mut stream = CoalescedStream::new(receiver, 1024);
+
+        sender.unbounded_send(Ok(Bytes::from("foo\n"))).unwrap();
+        sender.unbounded_send(Ok(Bytes::from("bar\n"))).unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), Bytes::from("foo\nbar\n"));
+
+        sender.unbounded_send(Ok(Bytes::from("baz\n"))).unwrap();
+        drop(sender);
+        assert_eq!(stream.next().await.unwrap().unwrap(), Bytes::from("baz\n"));
+        assert!(stream.next().await.is_none());
+    }
+}
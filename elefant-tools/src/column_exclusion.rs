@@ -0,0 +1,551 @@
+use crate::*;
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+use tracing::warn;
+
+/// An object that cannot be created without one of the excluded columns or tables.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum DependentObject {
+    Table {
+        schema_name: String,
+        table_name: String,
+    },
+    GeneratedColumn {
+        schema_name: String,
+        table_name: String,
+        column_name: String,
+    },
+    Index {
+        schema_name: String,
+        table_name: String,
+        index_name: String,
+    },
+    Constraint {
+        schema_name: String,
+        table_name: String,
+        constraint_name: String,
+    },
+    Trigger {
+        schema_name: String,
+        table_name: String,
+        trigger_name: String,
+    },
+    View {
+        schema_name: String,
+        view_name: String,
+    },
+    Policy {
+        schema_name: String,
+        table_name: String,
+        policy_name: String,
+    },
+}
+
+impl Display for DependentObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependentObject::Table {
+                schema_name,
+                table_name,
+            } => write!(f, "table {}.{}", schema_name, table_name),
+            DependentObject::GeneratedColumn {
+                schema_name,
+                table_name,
+                column_name,
+            } => write!(
+                f,
+                "generated column {}.{}.{}",
+                schema_name, table_name, column_name
+            ),
+            DependentObject::Index {
+                schema_name,
+                table_name,
+                index_name,
+            } => write!(f, "index {} on {}.{}", index_name, schema_name, table_name),
+            DependentObject::Constraint {
+                schema_name,
+                table_name,
+                constraint_name,
+            } => write!(
+                f,
+                "constraint {} on {}.{}",
+                constraint_name, schema_name, table_name
+            ),
+            DependentObject::Trigger {
+                schema_name,
+                table_name,
+                trigger_name,
+            } => write!(
+                f,
+                "trigger {} on {}.{}",
+                trigger_name, schema_name, table_name
+            ),
+            DependentObject::View {
+                schema_name,
+                view_name,
+            } => write!(f, "view {}.{}", schema_name, view_name),
+            DependentObject::Policy {
+                schema_name,
+                table_name,
+                policy_name,
+            } => write!(
+                f,
+                "policy {} on {}.{}",
+                policy_name, schema_name, table_name
+            ),
+        }
+    }
+}
+
+/// A column to exclude, as `(schema, table, column)`.
+type ExcludedColumn = (String, String, String);
+
+/// Removes the excluded columns from the tables in the definition, so they are neither created nor copied.
+///
+/// Objects using the columns are reported as an error, unless `drop_dependent_objects` is set, in which
+/// case they are removed as well. Columns used in expressions are found by looking for their name in the
+/// expression, so an object using a function with the same name as a column is reported too.
+pub(crate) fn exclude_columns(
+    definition: &mut PostgresDatabase,
+    exclude_columns: &[(String, String)],
+    drop_dependent_objects: bool,
+) -> Result<()> {
+    if exclude_columns.is_empty() {
+        return Ok(());
+    }
+
+    let mut excluded = Vec::new();
+
+    for (table, column_name) in exclude_columns {
+        let (schema_name, table_name) = table.split_once('.').unwrap_or(("public", table));
+
+        let table = definition
+            .try_get_schema(schema_name)
+            .and_then(|s| s.try_get_table(table_name))
+            .ok_or_else(|| ElefantToolsError::TableNotFound {
+                schema_name: schema_name.to_string(),
+                table_name: table_name.to_string(),
+            })?;
+
+        if !table.columns.iter().any(|c| c.name == *column_name) {
+            return Err(ElefantToolsError::ColumnNotFound {
+                schema_name: schema_name.to_string(),
+                table_name: table_name.to_string(),
+                column_name: column_name.to_string(),
+            });
+        }
+
+        excluded.push((
+            schema_name.to_string(),
+            table_name.to_string(),
+            column_name.to_string(),
+        ));
+    }
+
+    add_partition_columns(definition, &mut excluded);
+
+    let dependent_objects = find_dependent_objects(definition, &mut excluded);
+
+    if !dependent_objects.is_empty() && !drop_dependent_objects {
+        return Err(ElefantToolsError::ExcludedColumnsHaveDependentObjects(
+            dependent_objects.iter().map(|o| o.to_string()).collect(),
+        ));
+    }
+
+    for object in &dependent_objects {
+        warn!("Skipping {}, as it uses an excluded column", object);
+    }
+
+    for schema in &mut definition.schemas {
+        let schema_name = schema.name.clone();
+        let is_dependent = |object: DependentObject| dependent_objects.contains(&object);
+
+        for table in &mut schema.tables {
+            let table_name = table.name.clone();
+
+            table.columns.retain(|c| {
+                !excluded.contains(&(schema_name.clone(), table_name.clone(), c.name.clone()))
+            });
+
+            table.indices.retain(|index| {
+                !is_dependent(DependentObject::Index {
+                    schema_name: schema_name.clone(),
+                    table_name: table_name.clone(),
+                    index_name: index.name.clone(),
+                })
+            });
+
+            table.constraints.retain(|constraint| {
+                !is_dependent(DependentObject::Constraint {
+                    schema_name: schema_name.clone(),
+                    table_name: table_name.clone(),
+                    constraint_name: constraint.name().to_string(),
+                })
+            });
+        }
+
+        schema.triggers.retain(|trigger| {
+            !is_dependent(DependentObject::Trigger {
+                schema_name: schema_name.clone(),
+                table_name: trigger.table_name.clone(),
+                trigger_name: trigger.name.clone(),
+            })
+        });
+
+        schema.policies.retain(|policy| {
+            !is_dependent(DependentObject::Policy {
+                schema_name: schema_name.clone(),
+                table_name: policy.table_name.clone(),
+                policy_name: policy.name.clone(),
+            })
+        });
+
+        schema.views.retain(|view| {
+            !is_dependent(DependentObject::View {
+                schema_name: schema_name.clone(),
+                view_name: view.name.clone(),
+            })
+        });
+    }
+
+    Ok(())
+}
+
+/// The partitions of a partitioned table have the same columns as the table itself, so columns
+/// excluded from the partitioned table are excluded from the partitions as well.
+fn add_partition_columns(definition: &PostgresDatabase, excluded: &mut Vec<ExcludedColumn>) {
+    let mut index = 0;
+    while index < excluded.len() {
+        let (schema_name, table_name, column_name) = excluded[index].clone();
+
+        let partitions = definition
+            .try_get_schema(&schema_name)
+            .into_iter()
+            .flat_map(|s| &s.tables)
+            .filter(|t| match &t.table_type {
+                TableTypeDetails::PartitionedChildTable { parent_table, .. } => {
+                    *parent_table == table_name
+                }
+                _ => false,
+            });
+
+        for partition in partitions {
+            let column = (
+                schema_name.clone(),
+                partition.name.clone(),
+                column_name.clone(),
+            );
+            if !excluded.contains(&column) {
+                excluded.push(column);
+            }
+        }
+
+        index += 1;
+    }
+}
+
+/// Finds the objects using the excluded columns. Generated columns using an excluded column are
+/// added to the excluded columns, as they cannot be computed without it.
+fn find_dependent_objects(
+    definition: &PostgresDatabase,
+    excluded: &mut Vec<ExcludedColumn>,
+) -> Vec<DependentObject> {
+    let mut dependent_objects = Vec::new();
+
+    loop {
+        let generated_columns = definition
+            .schemas
+            .iter()
+            .flat_map(|s| s.tables.iter().map(move |t| (s, t)))
+            .flat_map(|(s, t)| t.columns.iter().map(move |c| (s, t, c)))
+            .filter(|(s, t, c)| {
+                !excluded.contains(&(s.name.clone(), t.name.clone(), c.name.clone()))
+            })
+            .filter(|(s, t, c)| {
+                c.generated.as_ref().is_some_and(|expression| {
+                    excluded_columns_of(excluded, &s.name, &t.name)
+                        .any(|column| expression_references_column(expression, column))
+                })
+            })
+            .map(|(s, t, c)| (s.name.clone(), t.name.clone(), c.name.clone()))
+            .collect_vec();
+
+        if generated_columns.is_empty() {
+            break;
+        }
+
+        for (schema_name, table_name, column_name) in generated_columns {
+            dependent_objects.push(DependentObject::GeneratedColumn {
+                schema_name: schema_name.clone(),
+                table_name: table_name.clone(),
+                column_name: column_name.clone(),
+            });
+            excluded.push((schema_name, table_name, column_name));
+        }
+    }
+
+    for schema in &definition.schemas {
+        for table in &schema.tables {
+            let columns = excluded_columns_of(excluded, &schema.name, &table.name).collect_vec();
+            let uses_excluded_column = |expression: &str| {
+                columns
+                    .iter()
+                    .any(|c| expression_references_column(expression, c))
+            };
+
+            let mut dependent_indices = Vec::new();
+            for index in &table.indices {
+                let is_dependent = index
+                    .key_columns
+                    .iter()
+                    .any(|c| uses_excluded_column(&c.name))
+                    || index
+                        .included_columns
+                        .iter()
+                        .any(|c| uses_excluded_column(&c.name))
+                    || index.predicate.as_deref().is_some_and(uses_excluded_column);
+
+                if is_dependent {
+                    dependent_indices.push(index.name.as_str());
+                    dependent_objects.push(DependentObject::Index {
+                        schema_name: schema.name.clone(),
+                        table_name: table.name.clone(),
+                        index_name: index.name.clone(),
+                    });
+                }
+            }
+
+            for constraint in &table.constraints {
+                let is_dependent = match constraint {
+                    PostgresConstraint::Check(check) => uses_excluded_column(&check.check_clause),
+                    PostgresConstraint::ForeignKey(fk) => {
+                        fk.columns
+                            .iter()
+                            .any(|c| columns.contains(&c.name.as_str()))
+                            || references_excluded_column(excluded, &schema.name, fk)
+                    }
+                    PostgresConstraint::Unique(unique) => {
+                        dependent_indices.contains(&unique.unique_index_name.as_str())
+                            || uses_excluded_column(&unique.definition)
+                    }
+                };
+
+                if is_dependent {
+                    dependent_objects.push(DependentObject::Constraint {
+                        schema_name: schema.name.clone(),
+                        table_name: table.name.clone(),
+                        constraint_name: constraint.name().to_string(),
+                    });
+                }
+            }
+
+            for trigger in schema
+                .triggers
+                .iter()
+                .filter(|t| t.table_name == table.name)
+            {
+                if trigger
+                    .condition
+                    .as_deref()
+                    .is_some_and(uses_excluded_column)
+                {
+                    dependent_objects.push(DependentObject::Trigger {
+                        schema_name: schema.name.clone(),
+                        table_name: table.name.clone(),
+                        trigger_name: trigger.name.clone(),
+                    });
+                }
+            }
+
+            for policy in schema
+                .policies
+                .iter()
+                .filter(|p| p.table_name == table.name)
+            {
+                if policy
+                    .using_expression
+                    .as_deref()
+                    .is_some_and(uses_excluded_column)
+                    || policy
+                        .check_expression
+                        .as_deref()
+                        .is_some_and(uses_excluded_column)
+                {
+                    dependent_objects.push(DependentObject::Policy {
+                        schema_name: schema.name.clone(),
+                        table_name: table.name.clone(),
+                        policy_name: policy.name.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let views = definition
+        .schemas
+        .iter()
+        .flat_map(|s| s.views.iter().map(move |v| (s, v)))
+        .collect_vec();
+
+    let mut dependent_views = views
+        .iter()
+        .filter(|(_, v)| {
+            v.column_dependencies.iter().any(|(table_id, column_name)| {
+                definition.schemas.iter().any(|s| {
+                    s.tables.iter().any(|t| {
+                        t.object_id == *table_id
+                            && excluded.contains(&(
+                                s.name.clone(),
+                                t.name.clone(),
+                                column_name.clone(),
+                            ))
+                    })
+                })
+            })
+        })
+        .map(|(_, v)| v.object_id)
+        .collect_vec();
+
+    loop {
+        let indirect = views
+            .iter()
+            .filter(|(_, v)| !dependent_views.contains(&v.object_id))
+            .filter(|(_, v)| v.depends_on.iter().any(|d| dependent_views.contains(d)))
+            .map(|(_, v)| v.object_id)
+            .collect_vec();
+
+        if indirect.is_empty() {
+            break;
+        }
+
+        dependent_views.extend(indirect);
+    }
+
+    for (schema, view) in &views {
+        if dependent_views.contains(&view.object_id) {
+            dependent_objects.push(DependentObject::View {
+                schema_name: schema.name.clone(),
+                view_name: view.name.clone(),
+            });
+        }
+    }
+
+    dependent_objects
+}
+
+fn excluded_columns_of<'e>(
+    excluded: &'e [ExcludedColumn],
+    schema_name: &'e str,
+    table_name: &'e str,
+) -> impl Iterator<Item = &'e str> {
+    excluded
+        .iter()
+        .filter(move |(s, t, _)| s == schema_name && t == table_name)
+        .map(|(_, _, c)| c.as_str())
+}
+
+/// Checks if the foreign key references an excluded column in another table.
+fn references_excluded_column(
+    excluded: &[ExcludedColumn],
+    schema_name: &str,
+    fk: &PostgresForeignKey,
+) -> bool {
+    let referenced_schema = fk.referenced_schema.as_deref().unwrap_or(schema_name);
+
+    excluded_columns_of(excluded, referenced_schema, &fk.referenced_table)
+        .any(|column| fk.referenced_columns.iter().any(|c| c.name == column))
+}
+
+/// Checks if the sql expression uses the column. Names followed by `(` are functions, and
+/// names following `::` are types, so they are not considered to be columns.
+fn expression_references_column(expression: &str, column: &str) -> bool {
+    let chars = expression.chars().collect_vec();
+    let mut index = 0;
+    let mut after_cast = false;
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        if c == '\'' {
+            index += 1;
+            while index < chars.len() {
+                if chars[index] == '\'' {
+                    if chars.get(index + 1) == Some(&'\'') {
+                        index += 1;
+                    } else {
+                        break;
+                    }
+                }
+                index += 1;
+            }
+            index += 1;
+            after_cast = false;
+        } else if c == '"' {
+            let mut name = String::new();
+            index += 1;
+            while index < chars.len() {
+                if chars[index] == '"' {
+                    if chars.get(index + 1) == Some(&'"') {
+                        index += 1;
+                    } else {
+                        break;
+                    }
+                }
+                name.push(chars[index]);
+                index += 1;
+            }
+            index += 1;
+
+            if name == column && !after_cast && chars.get(index) != Some(&'(') {
+                return true;
+            }
+            after_cast = false;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = index;
+            while index < chars.len()
+                && (chars[index].is_alphanumeric() || chars[index] == '_' || chars[index] == '$')
+            {
+                index += 1;
+            }
+            let name = chars[start..index]
+                .iter()
+                .collect::<String>()
+                .to_lowercase();
+
+            let is_function = chars[index..].iter().find(|c| !c.is_whitespace()) == Some(&'(');
+            if name == column && !after_cast && !is_function {
+                return true;
+            }
+            after_cast = false;
+        } else if c == ':' && chars.get(index + 1) == Some(&':') {
+            index += 2;
+            after_cast = true;
+        } else {
+            if !c.is_whitespace() {
+                after_cast = false;
+            }
+            index += 1;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_columns_in_expressions() {
+        assert!(expression_references_column("(age > 0)", "age"));
+        assert!(expression_references_column("people.age", "age"));
+        assert!(expression_references_column("\"Age\" + 1", "Age"));
+        assert!(expression_references_column("lower(name)", "name"));
+        assert!(!expression_references_column("\"Age\" + 1", "age"));
+        assert!(!expression_references_column("page > 0", "age"));
+        assert!(!expression_references_column("'age' = name", "age"));
+        assert!(!expression_references_column("'it''s age' = name", "age"));
+        assert!(!expression_references_column("lower(name)", "lower"));
+        assert!(!expression_references_column("(id)::text", "text"));
+    }
+}
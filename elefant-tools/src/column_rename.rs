@@ -0,0 +1,192 @@
+use crate::storage::CopyDestination;
+use crate::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// A column that was renamed in the source, see [CopyDataOptions::column_renames].
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ColumnRename {
+    /// The table, as `schema.table`, using the names in the destination.
+    pub table: String,
+    /// The name of the column in the destination.
+    pub old_name: String,
+    /// The name of the column in the source.
+    pub new_name: String,
+}
+
+impl ColumnRename {
+    /// Reads renames written as `schema.table.old_column=new_column`, one per line.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse_hints(hints: &str) -> Result<Vec<ColumnRename>> {
+        hints
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line_number, line)| {
+                let invalid = || ElefantToolsError::InvalidColumnRenameHint {
+                    line_number,
+                    hint: line.to_string(),
+                };
+
+                let (old_column, new_name) = line.split_once('=').ok_or_else(invalid)?;
+                let (table, old_name) = old_column.trim().rsplit_once('.').ok_or_else(invalid)?;
+                let new_name = new_name.trim();
+
+                if table.is_empty() || old_name.is_empty() || new_name.is_empty() {
+                    return Err(invalid());
+                }
+
+                Ok(ColumnRename {
+                    table: table.to_string(),
+                    old_name: old_name.to_string(),
+                    new_name: new_name.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Renames the columns in the destination that have been renamed in the source, instead of
+/// dropping the old column and losing its data. The data type is changed as well if it differs.
+///
+/// Renames where the old column is missing in the destination, or the new column is missing in
+/// the source or already exists in the destination, are skipped with a warning.
+pub(crate) async fn rename_hinted_columns<D: CopyDestination>(
+    destination: &mut D,
+    definition: &PostgresDatabase,
+    target_definition: &mut PostgresDatabase,
+    renames: &[ColumnRename],
+) -> Result<()> {
+    let identifier_quoter = destination.get_identifier_quoter();
+
+    for rename in renames {
+        let (schema_name, table_name) = rename
+            .table
+            .split_once('.')
+            .unwrap_or(("public", &rename.table));
+
+        let Some((schema, table)) = definition
+            .try_get_schema(schema_name)
+            .and_then(|s| s.try_get_table(table_name).map(|t| (s, t)))
+        else {
+            warn!(
+                "Not renaming column {} of table {}, as the table does not exist in the source",
+                rename.old_name, rename.table
+            );
+            continue;
+        };
+
+        let Some(target_table) = target_definition
+            .schemas
+            .iter_mut()
+            .find(|s| s.name == schema.name)
+            .and_then(|s| s.tables.iter_mut().find(|t| t.name == table.name))
+        else {
+            warn!(
+                "Not renaming column {} of table {}, as the table does not exist in the destination",
+                rename.old_name, rename.table
+            );
+            continue;
+        };
+
+        let Some(new_column) = table.columns.iter().find(|c| c.name == rename.new_name) else {
+            warn!(
+                "Not renaming column {} of table {}, as the new column {} does not exist in the source",
+                rename.old_name, rename.table, rename.new_name
+            );
+            continue;
+        };
+
+        if target_table.columns.iter().any(|c| c.name == rename.new_name) {
+            warn!(
+                "Not renaming column {} of table {}, as the new column {} already exists in the destination",
+                rename.old_name, rename.table, rename.new_name
+            );
+            continue;
+        }
+
+        let Some(old_column) = target_table
+            .columns
+            .iter_mut()
+            .find(|c| c.name == rename.old_name)
+        else {
+            warn!(
+                "Not renaming column {} of table {}, as it does not exist in the destination",
+                rename.old_name, rename.table
+            );
+            continue;
+        };
+
+        info!(
+            "Renaming column {} of table {} to {}",
+            rename.old_name, rename.table, rename.new_name
+        );
+        destination
+            .apply_transactional_statement(&new_column.get_rename_statement(
+                &rename.old_name,
+                table,
+                schema,
+                &identifier_quoter,
+            ))
+            .await?;
+
+        if old_column.get_type_expression(&identifier_quoter)
+            != new_column.get_type_expression(&identifier_quoter)
+        {
+            destination
+                .apply_transactional_statement(&new_column.get_alter_type_statement(
+                    table,
+                    schema,
+                    &identifier_quoter,
+                ))
+                .await?;
+        }
+
+        old_column.name = new_column.name.clone();
+        old_column.data_type = new_column.data_type.clone();
+        old_column.data_type_length = new_column.data_type_length;
+        old_column.array_dimensions = new_column.array_dimensions;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rename_hints() {
+        let renames = ColumnRename::parse_hints(
+            "# Renamed in the billing release\n\npublic.people.nickname = display_name\nsales.orders.ref=reference\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            renames,
+            vec![
+                ColumnRename {
+                    table: "public.people".to_string(),
+                    old_name: "nickname".to_string(),
+                    new_name: "display_name".to_string(),
+                },
+                ColumnRename {
+                    table: "sales.orders".to_string(),
+                    old_name: "ref".to_string(),
+                    new_name: "reference".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_hints_without_a_new_name() {
+        let result = ColumnRename::parse_hints("public.people.nickname\n");
+
+        assert!(matches!(
+            result,
+            Err(ElefantToolsError::InvalidColumnRenameHint { line_number: 1, .. })
+        ));
+    }
+}
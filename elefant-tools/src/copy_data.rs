@@ -1,18 +1,61 @@
-use crate::object_id::DependencySortable;
+use crate::column_exclusion::exclude_columns;
+use crate::column_rename::rename_hinted_columns;
+use crate::coalesced_stream::{CoalescedStream, SAME_SERVER_CHUNK_SIZE};
+use crate::copy_extension::{
+    apply_extension_statements, introspect_custom_objects, DdlExtensionPhase,
+};
+use crate::copy_timing::{StreamTimer, TimedStream};
+#[cfg(feature = "timescale")]
+use crate::hypertable_conversion::convert_to_hypertables;
+#[cfg(feature = "timescale")]
+use crate::timescale_compatibility::{check_timescale_compatibility, strip_timescale};
+use crate::object_id::{DependencySortable, HaveDependencies};
 use crate::parallel_runner::ParallelRunner;
-use crate::quoting::IdentifierQuoter;
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
+use crate::self_reference_ordering::SelfReferenceOrdering;
+use crate::staging_swap::{find_swap_blockers, get_swapped_schema_name, swap_staging_schema};
+use crate::statement_interceptor::InterceptingDestination;
+use crate::object_filter::filter_objects;
+use crate::table_filter::is_table_selected;
+use crate::resume_state::ResumeState;
+use crate::privilege_check::{
+    check_policy_roles, check_roles, check_sequence_grant_roles, remove_privileges,
+};
+use crate::tablespace_mapping::{check_tablespaces, map_tablespaces};
 use crate::storage::DataFormat;
 use crate::storage::{CopyDestination, CopySource};
+use crate::copy_plan::{
+    get_capacity_warning, get_copied_tables, get_extension_availability, get_tables_with_data, negotiate_parallelism,
+    CopiedTable,
+};
+use crate::schema_reader::human_readable_bytes;
+use crate::dry_run::{estimate_table_rows, DryRunDestination};
+use crate::verification::{checksum_table, get_relation_name, verify_table};
 use crate::*;
+use futures::future::Either;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
-use tracing::{debug, info, instrument};
-
-#[derive(Debug, Default)]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tokio_postgres::error::SqlState;
+use tracing::{debug, info, instrument, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CopyDataOptions {
     /// Force this data format to be used
     pub data_format: Option<DataFormat>,
-    /// How many tables to copy in parallel at most
+    /// How many tables to copy in parallel at most.
+    ///
+    /// With `1` (the default) everything is copied sequentially over the connections the source
+    /// and the destination were created with, and no additional connections are opened. Otherwise
+    /// a [PostgresInstanceStorage] borrows the additional connections from its [ConnectionPool],
+    /// which can be made smaller than this to limit the connections further.
     pub max_parallel: Option<NonZeroUsize>,
 
     /// The schema to inspect
@@ -22,7 +65,16 @@ pub struct CopyDataOptions {
     /// when applied to the destination.
     pub rename_schema_to: Option<String>,
 
-    /// Only the schema will be copied, but not any data
+    /// Schemas to leave out of the copy, for example the `partman` schema of pg_partman.
+    /// Extensions installed in these schemas are left out as well. Combines with `target_schema`.
+    pub exclude_schemas: Vec<String>,
+
+    /// Selects the tables, views, materialized views, functions, triggers and sequences to copy.
+    /// Everything is copied by default.
+    pub object_filter: ObjectFilter,
+
+    /// Only the schema will be copied, but not any data. The current values of the sequences
+    /// count as data, so the sequences start over in the destination.
     pub schema_only: bool,
 
     /// Only the structures missing in the destination will be copied.
@@ -30,217 +82,1731 @@ pub struct CopyDataOptions {
     /// This only works with data sources that supports structural inspections, aka
     /// not sql-files.
     pub differential: bool,
+
+    /// Fail instead of only warning when the encoding or locale of the source and
+    /// destination databases differ.
+    pub strict_encoding: bool,
+
+    /// Check that the data in the text columns of the source can be converted to the encoding of
+    /// the destination before anything is copied, when the encodings differ. Fails with
+    /// [ElefantToolsError::InvalidlyEncodedColumns] listing the columns that cannot, such as columns
+    /// with invalid UTF-8 in a `SQL_ASCII` database. Each text column is read once, so this can
+    /// take a while for large databases.
+    pub preflight_encoding_check: bool,
+
+    /// Allow a differential copy to drop columns that no longer exist in the source from
+    /// existing tables in the destination. Views using those columns are dropped and
+    /// recreated from the source.
+    pub allow_destructive: bool,
+
+    /// Allow a differential copy to drop the tables, views, functions and indices of the
+    /// destination that no longer exist in the source, and the removed columns like
+    /// `allow_destructive`. Without it, the objects that would have been dropped are logged.
+    /// Only schemas that exist in the source are looked at, and with an `object_filter` only the
+    /// tables selected by it.
+    pub allow_drops: bool,
+
+    /// Columns that have been renamed in the source since the destination was copied. A
+    /// differential copy renames them in the destination, keeping their data, instead of
+    /// treating them as a dropped and a new column.
+    pub column_renames: Vec<ColumnRename>,
+
+    /// Plain tables to create as hypertables in the destination, before their data is copied.
+    /// Only applied when the destination has the timescaledb extension installed, otherwise
+    /// the tables are copied as plain tables with a warning.
+    #[cfg(feature = "timescale")]
+    pub hypertable_conversions: Vec<HypertableConversion>,
+
+    /// Copy the hypertables as plain tables and the continuous aggregates as plain materialized
+    /// views, leaving out the timescaledb extension, the jobs and the compression and retention
+    /// policies, for destinations without Timescale. `time_bucket` in continuous aggregates is
+    /// replaced with `date_bin`, which cannot do buckets of months or years.
+    ///
+    /// Without it, a copy of a source using Timescale fails before anything is copied if the
+    /// destination does not have Timescale, or has an older version of it.
+    #[cfg(feature = "timescale")]
+    pub strip_timescale: bool,
+
+    /// Don't create indexes and unique constraints, so the data can be loaded faster.
+    /// They can be created afterwards by running another copy with `indexes_only`.
+    /// Foreign keys referencing the columns of a skipped index are skipped as well,
+    /// as they cannot be created without it.
+    pub skip_indexes: bool,
+
+    /// Also skip primary keys when `skip_indexes` is set.
+    pub skip_primary_keys: bool,
+
+    /// Only create the indexes, primary keys, unique constraints and foreign keys that are
+    /// missing in the destination, for example after a copy with `skip_indexes`.
+    /// Nothing else is copied, and tables missing in the destination are ignored.
+    /// This only works with destinations that supports structural inspections.
+    pub indexes_only: bool,
+
+    /// Build the indexes using `create index concurrently`, so they don't block writes to the tables.
+    /// Primary keys and unique constraints still take a lock when they are added.
+    pub create_indexes_concurrently: bool,
+
+    /// Copy the data of a table using a custom select query, instead of all the rows of the table.
+    /// The keys are the tables in the source, as `schema.table`.
+    ///
+    /// The query has to return the same columns as the table in the destination, in the same order,
+    /// which is checked before anything is copied.
+    pub table_select_overrides: HashMap<String, String>,
+
+    /// Don't copy the data of tables larger than this many bytes in the source, counting the
+    /// table and its toast data but not its indexes. The tables are still created, and listed in
+    /// [CopyDataReport::skipped_tables]. The sequences are still set to their values in the source.
+    pub max_table_data_size_bytes: Option<u64>,
+
+    /// How many bytes the destination database can grow to. Postgres cannot tell how much free
+    /// disk space it has, so this is a hint: if the current size of the destination plus the size
+    /// of the copied tables in the source is larger, a warning is logged and listed in
+    /// [CopyDataReport::warnings] before anything is copied. The copy still runs.
+    pub destination_capacity_bytes: Option<u64>,
+
+    /// Columns that should not exist in the destination, as `(schema.table, column)`.
+    /// They are left out of both the created tables and the copied data.
+    pub exclude_columns: Vec<(String, String)>,
+
+    /// Skip the indexes, constraints, views, triggers, policies and generated columns that use an
+    /// excluded column, and the foreign keys, views and inheriting tables that use an excluded table,
+    /// instead of failing before anything is copied.
+    pub drop_dependent_objects: bool,
+
+    /// How the copied data is compared with the source after the copy. Tables copied using
+    /// `table_select_overrides` are not verified, as their data is expected to differ.
+    pub verification: VerificationLevel,
+
+    /// Copy the rows of tables with a foreign key to the table itself with parents before their
+    /// children, so the data can be inserted while the foreign key is enforced. Fails if the rows
+    /// reference each other in a cycle, or if a table has more than one foreign key to itself.
+    /// Does not apply to tables in `table_select_overrides`.
+    pub order_self_referencing: bool,
+
+    /// Copy `target_schema` into a new staging schema, and swap it with the schema in the
+    /// destination in a single transaction once the copy is done, so the existing schema can be
+    /// queried until then. Cannot be combined with `differential` or `indexes_only`. Fails if
+    /// objects in other schemas of the destination depend on the schema.
+    pub staging_swap: Option<StagingSwap>,
+
+    /// Copy the data in larger chunks when the source and the destination are databases on the
+    /// same server, as the data then doesn't have to go over a network. Off by default, as it has
+    /// only been measured on generated data so far, see
+    /// `benchmarks/results/same-server-optimization.md`.
+    pub same_server_optimization: bool,
+
+    /// Skip the attributes that require the destination user to be a superuser, such as marking
+    /// functions as leakproof, with a warning instead of failing when the user isn't allowed to
+    /// set them. This is usually the case for managed Postgres services. The skipped
+    /// statements are listed in [CopyDataReport::warnings].
+    pub privilege_downgrade: bool,
+
+    /// Also recreate indexes in a differential copy when only their storage parameters differ
+    /// from the source. Indexes whose columns, sort order, included columns or predicate differ
+    /// are always recreated.
+    pub strict_index_comparison: bool,
+
+    /// Tablespaces to store the tables and indexes in, instead of the tablespaces they are stored
+    /// in in the source. The keys are the tablespaces in the source, where `pg_default` is the
+    /// default tablespace of the source database. Tablespaces that are not mapped are kept.
+    /// The tablespaces have to exist in the destination, which is checked before anything is copied.
+    pub tablespace_mapping: HashMap<String, String>,
+
+    /// Create the tables and indexes in the default tablespace of the destination, regardless
+    /// of where they are stored in the source. Overrides `tablespace_mapping`.
+    pub no_tablespaces: bool,
+
+    /// Don't copy the privileges granted on or revoked from schemas, tables, views and functions.
+    /// Otherwise the roles they are granted to have to exist in the destination, which is checked
+    /// before anything is copied. Useful when copying between clusters with different roles.
+    pub skip_privileges: bool,
+
+    /// Refresh the materialized views after the data is copied, in the order they depend on each
+    /// other, so they contain the copied data. On by default. Without it, the materialized views
+    /// are left unpopulated, which makes schema only copies faster. This includes the continuous
+    /// aggregates of Timescale.
+    pub refresh_materialized_views: bool,
+
+    /// Roles to grant `usage` and `select` on every copied sequence to, so they can insert into
+    /// the serial columns using the sequences. The roles have to exist in the destination, which
+    /// is checked before anything is copied.
+    pub grant_sequences_to: Vec<String>,
+
+    /// Don't change the destination, but record the statements that would have been applied in
+    /// [CopyDataReport::dry_run], together with an estimate of the rows of each table instead of
+    /// copying the data. The destination is still read, so a differential dry run shows what
+    /// would be changed.
+    pub dry_run: bool,
+
+    /// Records the progress of the copy in this file as each step finishes, so a copy that was
+    /// interrupted, for example by a lost connection, can be run again with the same options to
+    /// continue where it stopped. Tables that were copied completely are skipped, and tables that
+    /// were only partially copied are truncated and copied again. The file is removed once the
+    /// copy has finished. Resuming requires a destination that can be introspected, and the file
+    /// is not used for dry runs.
+    pub resume_state_path: Option<PathBuf>,
+
+    /// Decides what happens to each DDL statement before it is applied to the destination, such
+    /// as skipping or rewriting it. The data of the tables is not intercepted.
+    #[serde(skip)]
+    pub statement_interceptor: Option<Arc<dyn StatementInterceptor>>,
+
+    /// Read objects elefant-tools does not know about from the source, after the rest of it has
+    /// been introspected. They are stored in [PostgresDatabase::custom_objects], and created in
+    /// the destination by the matching [CopyDataOptions::ddl_extensions]. Only runs if the
+    /// source is a Postgres database.
+    #[serde(skip)]
+    pub introspection_extensions: Vec<Arc<dyn IntrospectionExtension>>,
+
+    /// Create the objects read by the [CopyDataOptions::introspection_extensions] in the
+    /// destination, see [DdlExtension::phase] for when.
+    #[serde(skip)]
+    pub ddl_extensions: Vec<Arc<dyn DdlExtension>>,
 }
 
-const NON_ZERO_USIZE1: NonZeroUsize = unsafe {
-    // SAFETY: 1 is not zero
-    NonZeroUsize::new_unchecked(1)
-};
+impl Default for CopyDataOptions {
+    fn default() -> Self {
+        Self {
+            data_format: None,
+            max_parallel: None,
+            target_schema: None,
+            rename_schema_to: None,
+            exclude_schemas: Vec::new(),
+            object_filter: ObjectFilter::default(),
+            schema_only: false,
+            differential: false,
+            strict_encoding: false,
+            preflight_encoding_check: false,
+            allow_destructive: false,
+            allow_drops: false,
+            column_renames: Vec::new(),
+            #[cfg(feature = "timescale")]
+            hypertable_conversions: Vec::new(),
+            #[cfg(feature = "timescale")]
+            strip_timescale: false,
+            skip_indexes: false,
+            skip_primary_keys: false,
+            indexes_only: false,
+            create_indexes_concurrently: false,
+            table_select_overrides: HashMap::new(),
+            max_table_data_size_bytes: None,
+            destination_capacity_bytes: None,
+            exclude_columns: Vec::new(),
+            drop_dependent_objects: false,
+            verification: VerificationLevel::default(),
+            order_self_referencing: false,
+            staging_swap: None,
+            same_server_optimization: false,
+            privilege_downgrade: false,
+            strict_index_comparison: false,
+            tablespace_mapping: HashMap::new(),
+            no_tablespaces: false,
+            skip_privileges: false,
+            refresh_materialized_views: true,
+            grant_sequences_to: Vec::new(),
+            dry_run: false,
+            resume_state_path: None,
+            statement_interceptor: None,
+            introspection_extensions: Vec::new(),
+            ddl_extensions: Vec::new(),
+        }
+    }
+}
+
+const NON_ZERO_USIZE1: NonZeroUsize = NonZeroUsize::new(1).unwrap();
 
 impl CopyDataOptions {
-    fn get_max_parallel_or_1(&self) -> NonZeroUsize {
+    pub(crate) fn get_max_parallel_or_1(&self) -> NonZeroUsize {
         self.max_parallel.unwrap_or(NON_ZERO_USIZE1)
     }
+
+    /// Finds the select query to copy the data of the table with, if one was specified in
+    /// [CopyDataOptions::table_select_overrides].
+    pub(crate) fn get_table_select_override(
+        &self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+    ) -> Option<&str> {
+        self.table_select_overrides
+            .iter()
+            .find(|(name, _)| {
+                let (schema_name, table_name) = name.split_once('.').unwrap_or(("public", name));
+                schema_name == schema.name && table_name == table.name
+            })
+            .map(|(_, select)| select.as_str())
+    }
+}
+
+/// What happened while copying the data.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct CopyDataReport {
+    /// How long the data of each table took to copy, in the order the tables finished.
+    /// Tables that were skipped, for example because of `differential`, are not included.
+    pub table_timings: Vec<TableCopyTiming>,
+    /// The result of verifying the copied data of each table, see [CopyDataOptions::verification].
+    pub verification: Vec<TableVerification>,
+    /// Things that were left out of the copy instead of failing it, see
+    /// [CopyDataOptions::privilege_downgrade], and whether the destination might run out of
+    /// space, see [CopyDataOptions::destination_capacity_bytes].
+    pub warnings: Vec<String>,
+    /// The statements skipped by [CopyDataOptions::statement_interceptor].
+    pub skipped_statements: Vec<String>,
+    /// What the copy would have done, if it was a dry run, see [CopyDataOptions::dry_run].
+    pub dry_run: Option<DryRunReport>,
+    /// The tables which data was not copied, see [CopyDataOptions::max_table_data_size_bytes].
+    pub skipped_tables: Vec<SkippedTable>,
 }
 
 /// Copies data and structures from the provided source to the destination.
 ///
 /// This is probably the main function you want to deal with when using Elefant Tools as a library.
+/// It is the same as calling [plan_copy] followed by [execute_copy].
 #[instrument(skip_all)]
 pub async fn copy_data<'d, S: CopySourceFactory, D: CopyDestinationFactory<'d>>(
     source: &S,
     destination: &'d mut D,
     options: CopyDataOptions,
-) -> Result<()> {
+) -> Result<CopyDataReport> {
+    // The futures are boxed, as they are too large to be kept on the stack of the caller.
+    let plan = Box::pin(plan_copy(source, destination, options)).await?;
+    debug!("Copy plan:\n{}", plan);
+
+    Box::pin(execute_copy(plan, source, destination)).await
+}
+
+/// Decides what [copy_data] will do, without writing anything to the destination.
+///
+/// This negotiates the parallelism and the data format with the source and the destination,
+/// introspects the source and validates the options against it.
+#[instrument(skip_all)]
+pub async fn plan_copy<'d, S: CopySourceFactory, D: CopyDestinationFactory<'d>>(
+    source: &S,
+    destination: &D,
+    options: CopyDataOptions,
+) -> Result<CopyPlan> {
+    if options.staging_swap.is_some() {
+        get_swapped_schema_name(&options)?;
+    }
+
+    let destination_encoding = check_database_encoding(source, destination, &options).await?;
+
     let data_format = get_data_type(source, destination, &options).await?;
 
-    let expected_parallelism = if options.get_max_parallel_or_1() == NON_ZERO_USIZE1 {
-        SupportedParallelism::Sequential
+    let parallelism = negotiate_parallelism(
+        options.get_max_parallel_or_1(),
+        source.supported_parallelism(),
+        destination.supported_parallelism(),
+    );
+
+    let introspection_source: SequentialOrParallel<S::SequentialSource, S::ParallelSource> =
+        SequentialOrParallel::Sequential(source.create_sequential_source().await?);
+
+    let mut definition = introspection_source.get_introspection().await?;
+    introspect_custom_objects(source, &mut definition, &options).await?;
+    exclude_columns(
+        &mut definition,
+        &options.exclude_columns,
+        options.drop_dependent_objects,
+    )?;
+    filter_objects(
+        &mut definition,
+        &options.object_filter,
+        options.drop_dependent_objects,
+    )?;
+
+    warn_about_unsupported_extensions(&definition, &options);
+
+    let source_definition = if let Some(target_schema) = &options.target_schema {
+        definition.filtered_to_schema(target_schema)
+    } else {
+        definition
+    };
+
+    let source_definition = if options.exclude_schemas.is_empty() {
+        source_definition
+    } else {
+        source_definition.without_schemas(&options.exclude_schemas)
+    };
+
+    if options.preflight_encoding_check && !options.schema_only {
+        if let Some(destination_encoding) = &destination_encoding {
+            check_data_encoding(source, &source_definition, destination_encoding).await?;
+        }
+    }
+
+    let rename_to = match &options.staging_swap {
+        Some(staging_swap) => Some(&staging_swap.staging_schema),
+        None => options.rename_schema_to.as_ref(),
+    };
+    let mut target_definition = if let (Some(target_schema), Some(rename_to)) =
+        (&options.target_schema, rename_to)
+    {
+        source_definition.with_renamed_schema(target_schema, rename_to)
+    } else {
+        source_definition.clone()
+    };
+
+    #[cfg(feature = "timescale")]
+    if options.strip_timescale {
+        strip_timescale(&mut target_definition)?;
+    }
+
+    let extensions = check_extensions(destination, &target_definition).await?;
+    #[cfg(feature = "timescale")]
+    check_timescale_compatibility(source, destination, &target_definition, &extensions).await?;
+    let skipped_extensions = if options.privilege_downgrade {
+        remove_uncreatable_extensions(&mut target_definition, &extensions)
     } else {
-        source
-            .supported_parallelism()
-            .negotiate_parallelism(destination.supported_parallelism())
+        Vec::new()
     };
 
-    let (source, mut destination) = match expected_parallelism {
+    if let Some(access_methods) = destination.get_table_access_methods().await? {
+        check_table_access_methods(&target_definition, &access_methods)?;
+    }
+
+    map_tablespaces(
+        &mut target_definition,
+        &options.tablespace_mapping,
+        options.no_tablespaces,
+    );
+    if let Some(tablespaces) = destination.get_tablespaces().await? {
+        check_tablespaces(&target_definition, &options.tablespace_mapping, &tablespaces)?;
+    }
+
+    if options.skip_privileges {
+        remove_privileges(&mut target_definition);
+    }
+    if let Some(roles) = destination.get_roles().await? {
+        if !options.skip_privileges {
+            check_roles(&target_definition, &roles)?;
+        }
+        check_policy_roles(&target_definition, &roles)?;
+        check_sequence_grant_roles(&options.grant_sequences_to, &roles)?;
+    }
+
+    #[cfg(feature = "timescale")]
+    if !options.hypertable_conversions.is_empty() {
+        let timescale_is_installed = destination
+            .get_server_capabilities()
+            .await?
+            .is_some_and(|c| c.timescale.is_some());
+        if !timescale_is_installed {
+            warn!(
+                "The destination does not have timescaledb installed, so no tables are converted to hypertables"
+            );
+        }
+
+        convert_to_hypertables(
+            &mut target_definition,
+            &options.hypertable_conversions,
+            timescale_is_installed,
+        )?;
+    }
+
+    validate_table_select_overrides(
+        &introspection_source,
+        &source_definition,
+        &target_definition,
+        &options,
+    )
+    .await?;
+
+    // Fail before anything is copied, if the rows of a table cannot be ordered.
+    if options.order_self_referencing {
+        for schema in &source_definition.schemas {
+            for table in &schema.tables {
+                if options.get_table_select_override(schema, table).is_none() {
+                    SelfReferenceOrdering::find(schema, table)?;
+                }
+            }
+        }
+    }
+
+    let same_server = options.same_server_optimization
+        && is_same_server(source, destination).await?;
+
+    let skipped_tables = match options.max_table_data_size_bytes {
+        Some(max_size) if !options.schema_only && !options.indexes_only => {
+            // Keywords don't have to be quoted when the name is cast to `regclass`.
+            let identifier_quoter = IdentifierQuoter::empty();
+            let tables = get_copied_tables(&source_definition, &target_definition);
+            match &introspection_source {
+                SequentialOrParallel::Sequential(s) => {
+                    find_oversized_tables(s, &tables, max_size, &identifier_quoter).await?
+                }
+                SequentialOrParallel::Parallel(s) => {
+                    find_oversized_tables(s, &tables, max_size, &identifier_quoter).await?
+                }
+            }
+        }
+        _ => Vec::new(),
+    };
+
+    let estimated_data_size_bytes = if !options.schema_only && !options.indexes_only {
+        let identifier_quoter = IdentifierQuoter::empty();
+        let tables = get_tables_with_data(&source_definition, &target_definition, &skipped_tables);
+        match &introspection_source {
+            SequentialOrParallel::Sequential(s) => {
+                estimate_data_size(s, &tables, &identifier_quoter).await?
+            }
+            SequentialOrParallel::Parallel(s) => {
+                estimate_data_size(s, &tables, &identifier_quoter).await?
+            }
+        }
+    } else {
+        None
+    };
+
+    let capacity_warning = match (estimated_data_size_bytes, options.destination_capacity_bytes) {
+        (Some(estimated_size), Some(capacity)) => match destination.get_database_size().await? {
+            Some(destination_size) => {
+                get_capacity_warning(estimated_size, destination_size, capacity)
+            }
+            None => {
+                warn!("The destination cannot tell its size, so it is not checked whether the copied tables fit");
+                None
+            }
+        },
+        _ => None,
+    };
+
+    if let Some(warning) = &capacity_warning {
+        warn!("{}", warning);
+    }
+
+    Ok(CopyPlan::new(
+        parallelism,
+        data_format,
+        same_server,
+        skipped_extensions,
+        extensions,
+        skipped_tables,
+        estimated_data_size_bytes,
+        capacity_warning,
+        source_definition,
+        target_definition,
+        options,
+    ))
+}
+
+/// The combined size of the tables in the source, including their indexes and toast data.
+/// `None` if the source cannot tell the size of its tables.
+async fn estimate_data_size<S: CopySource>(
+    source: &S,
+    tables: &[CopiedTable<'_>],
+    identifier_quoter: &IdentifierQuoter,
+) -> Result<Option<u64>> {
+    let relations = tables
+        .iter()
+        .map(|t| {
+            quote_value_string(&get_relation_name(
+                t.source_schema,
+                t.source_table,
+                identifier_quoter,
+            ))
+        })
+        .join(", ");
+
+    let Some(rows) = source
+        .get_text_rows(&format!(
+            "select coalesce(sum(pg_total_relation_size(r::regclass)), 0)::text from unnest(array[{}]::text[]) r;",
+            relations
+        ))
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    Ok(rows
+        .into_iter()
+        .next()
+        .and_then(|row| row.into_iter().next().flatten())
+        .and_then(|size| size.parse::<u64>().ok()))
+}
+
+/// Finds the tables which data is larger than `max_size` bytes in the source. Nothing is skipped
+/// if the source cannot tell the size of its tables.
+async fn find_oversized_tables<S: CopySource>(
+    source: &S,
+    tables: &[CopiedTable<'_>],
+    max_size: u64,
+    identifier_quoter: &IdentifierQuoter,
+) -> Result<Vec<SkippedTable>> {
+    let mut skipped = Vec::new();
+
+    for table in tables {
+        let relation =
+            get_relation_name(table.source_schema, table.source_table, identifier_quoter);
+
+        let Some(rows) = source
+            .get_text_rows(&format!(
+                "select pg_table_size({}::regclass)::text;",
+                quote_value_string(&relation)
+            ))
+            .await?
+        else {
+            warn!("The source cannot tell the size of its tables, so the data of all tables is copied");
+            return Ok(Vec::new());
+        };
+
+        let size = rows
+            .into_iter()
+            .next()
+            .and_then(|row| row.into_iter().next().flatten())
+            .and_then(|size| size.parse::<u64>().ok())
+            .unwrap_or_default();
+
+        if size > max_size {
+            info!(
+                "Skipping the data of table {}.{} as it is {}, which is larger than {}",
+                table.source_schema.name,
+                table.source_table.name,
+                human_readable_bytes(size as i64),
+                human_readable_bytes(max_size as i64)
+            );
+            skipped.push(SkippedTable {
+                schema_name: table.target_schema.name.clone(),
+                table_name: table.target_table.name.clone(),
+                size_bytes: size,
+            });
+        }
+    }
+
+    Ok(skipped)
+}
+
+/// Checks whether each extension of the definition can be created in the destination. Returns
+/// an empty list if the destination cannot tell.
+async fn check_extensions<'d, D: CopyDestinationFactory<'d>>(
+    destination: &D,
+    target_definition: &PostgresDatabase,
+) -> Result<Vec<PlannedExtension>> {
+    let Some(installed) = destination.get_installed_extensions().await? else {
+        return Ok(Vec::new());
+    };
+    let available = destination.get_available_extensions().await?;
+    let superuser_only = destination
+        .get_superuser_only_extensions()
+        .await?
+        .unwrap_or_default();
+
+    Ok(target_definition
+        .enabled_extensions
+        .iter()
+        .map(|ext| PlannedExtension {
+            name: ext.name.clone(),
+            availability: get_extension_availability(
+                &ext.name,
+                &installed,
+                available.as_deref(),
+                &superuser_only,
+            ),
+        })
+        .collect())
+}
+
+/// Removes the extensions that cannot be created in the destination from the definition, as the
+/// destination user is not allowed to, or they are not available there. Returns the names of the
+/// removed extensions.
+fn remove_uncreatable_extensions(
+    target_definition: &mut PostgresDatabase,
+    extensions: &[PlannedExtension],
+) -> Vec<String> {
+    let mut skipped = Vec::new();
+    target_definition.enabled_extensions.retain(|ext| {
+        let availability = extensions
+            .iter()
+            .find(|e| e.name == ext.name)
+            .map(|e| e.availability);
+
+        match availability {
+            Some(
+                availability @ (ExtensionAvailability::SuperuserOnly
+                | ExtensionAvailability::Unavailable),
+            ) => {
+                warn!(
+                    "{}",
+                    get_skipped_extension_warning(&ext.name, availability)
+                );
+                skipped.push(ext.name.clone());
+                false
+            }
+            _ => true,
+        }
+    });
+
+    skipped
+}
+
+/// Explains why an extension was left out of the copy.
+fn get_skipped_extension_warning(name: &str, availability: ExtensionAvailability) -> String {
+    match availability {
+        ExtensionAvailability::Unavailable => format!(
+            "Skipped extension {} as it is not available on the destination server",
+            name
+        ),
+        _ => format!(
+            "Skipped extension {} as the destination user is not allowed to create it",
+            name
+        ),
+    }
+}
+
+/// Finds the extensions of the definition that are not installed in the destination after they
+/// were created with [PostgresExtension::get_guarded_create_statement], and returns a warning for
+/// each of them.
+async fn find_skipped_guarded_extensions<S: CopyDestination, P: CopyDestination + Clone + Sync>(
+    destination: &SequentialOrParallel<S, P>,
+    target_definition: &PostgresDatabase,
+) -> Result<Vec<String>> {
+    if target_definition.enabled_extensions.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let Some(installed) = destination
+        .get_text_rows("select extname::text from pg_extension;")
+        .await?
+    else {
+        return Ok(Vec::new());
+    };
+    let installed = installed
+        .into_iter()
+        .filter_map(|row| row.into_iter().next().flatten())
+        .collect_vec();
+
+    Ok(target_definition
+        .enabled_extensions
+        .iter()
+        .filter(|ext| !installed.contains(&ext.name))
+        .map(|ext| {
+            let warning =
+                get_skipped_extension_warning(&ext.name, ExtensionAvailability::SuperuserOnly);
+            warn!("{}", warning);
+            warning
+        })
+        .collect())
+}
+
+/// Checks if the source and the destination are databases on the same server.
+async fn is_same_server<'d, S: CopySourceFactory, D: CopyDestinationFactory<'d>>(
+    source: &S,
+    destination: &D,
+) -> Result<bool> {
+    let source_identity = source.get_server_identity().await?;
+    let destination_identity = destination.get_server_identity().await?;
+
+    Ok(matches!((source_identity, destination_identity), (Some(s), Some(d)) if s == d))
+}
+
+/// Runs a copy planned by [plan_copy]. The source and the destination should be the same as
+/// the plan was made with.
+#[instrument(skip_all)]
+pub async fn execute_copy<'d, S: CopySourceFactory, D: CopyDestinationFactory<'d>>(
+    plan: CopyPlan,
+    source: &S,
+    destination: &'d mut D,
+) -> Result<CopyDataReport> {
+    let CopyPlan {
+        parallelism,
+        data_format,
+        same_server,
+        skipped_extensions,
+        extensions,
+        phases,
+        skipped_tables,
+        capacity_warning,
+        options,
+        source_definition,
+        mut target_definition,
+        ..
+    } = plan;
+
+    let resume_state = match &options.resume_state_path {
+        Some(path) if !options.dry_run => Some(ResumeState::load(path, &source_definition)?),
+        _ => None,
+    };
+    let resuming = resume_state.as_ref().is_some_and(|s| s.is_resuming());
+
+    let skipped_statements = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let dry_run_statements = options
+        .dry_run
+        .then(|| Arc::new(std::sync::Mutex::new(Vec::new())));
+    let intercept = |destination| {
+        InterceptingDestination::new(
+            DryRunDestination::with_statements(destination, dry_run_statements.clone()),
+            options.statement_interceptor.clone(),
+            skipped_statements.clone(),
+        )
+    };
+
+    // Read before the destination is created, as only the factory knows the server.
+    let destination_capabilities = destination.get_server_capabilities().await?;
+
+    let (source, mut destination) = match parallelism {
         SupportedParallelism::Sequential => (
             SequentialOrParallel::Sequential(source.create_sequential_source().await?),
-            SequentialOrParallel::Sequential(destination.create_sequential_destination().await?),
+            SequentialOrParallel::Sequential(intercept(
+                destination.create_sequential_destination().await?,
+            )),
         ),
         SupportedParallelism::Parallel => (
             source.create_source().await?,
-            destination.create_destination().await?,
+            match destination.create_destination().await? {
+                SequentialOrParallel::Sequential(d) => SequentialOrParallel::Sequential(intercept(d)),
+                SequentialOrParallel::Parallel(d) => SequentialOrParallel::Parallel(InterceptingDestination::new(
+                    DryRunDestination::with_statements(d, dry_run_statements.clone()),
+                    options.statement_interceptor.clone(),
+                    skipped_statements.clone(),
+                )),
+            },
         ),
     };
 
-    let definition = source.get_introspection().await?;
-    let destination_definition = if options.differential {
+    let mut destination_definition = if options.indexes_only {
+        destination
+            .try_get_introspeciton()
+            .await?
+            .ok_or(ElefantToolsError::IndexesOnlyRequiresIntrospection)?
+    } else if options.differential {
         destination
             .try_get_introspeciton()
             .await?
             .unwrap_or_default()
+    } else if resuming {
+        // Some of the structure might already have been applied, so it is compared with the
+        // destination instead of being created again.
+        destination
+            .try_get_introspeciton()
+            .await?
+            .ok_or(ElefantToolsError::ResumeRequiresIntrospection)?
     } else {
         default()
     };
 
-    let source_definition = if let Some(target_schema) = &options.target_schema {
-        definition.filtered_to_schema(target_schema)
+    if let Some(target_schema) = &options.target_schema {
+        destination_definition.filtered_to_schema(target_schema);
+    }
+
+    if options.staging_swap.is_some() {
+        let schema_name = get_swapped_schema_name(&options)?;
+        match destination.try_get_introspeciton().await? {
+            Some(existing) => {
+                let blockers = find_swap_blockers(&existing, schema_name);
+                if !blockers.is_empty() {
+                    return Err(ElefantToolsError::StagingSwapBlocked {
+                        schema_name: schema_name.to_string(),
+                        blockers,
+                    });
+                }
+            }
+            None => warn!(
+                "The destination cannot be introspected, so objects depending on schema {} are not checked before it is swapped",
+                schema_name
+            ),
+        }
+    }
+
+    if options.indexes_only {
+        remove_tables_missing_in_destination(&mut target_definition, &destination_definition);
     } else {
-        definition
-    };
+        if options.skip_indexes {
+            let identifier_quoter = match &destination {
+                SequentialOrParallel::Sequential(d) => d.get_identifier_quoter(),
+                SequentialOrParallel::Parallel(d) => d.get_identifier_quoter(),
+            };
+            remove_skipped_indexes(
+                &mut target_definition,
+                options.skip_primary_keys,
+                &identifier_quoter,
+            );
+        }
+    }
 
-    let target_definition = if let (Some(target_schema), Some(rename_to)) =
-        (&options.target_schema, &options.rename_schema_to)
-    {
-        source_definition.with_renamed_schema(target_schema, rename_to)
+    let pre_data_structure_applied = resume_state
+        .as_ref()
+        .is_some_and(|s| s.is_pre_data_structure_applied());
+    if options.indexes_only {
+        // Only the indexes are created, after the data.
+    } else if pre_data_structure_applied {
+        info!("Skipping the structure before the data, as it was applied before the copy was interrupted");
     } else {
-        source_definition.clone()
+        destination.begin_transaction().await?;
+
+        match &mut destination {
+            SequentialOrParallel::Sequential(ref mut d) => {
+                if options.differential {
+                    rename_hinted_columns(
+                        d,
+                        &target_definition,
+                        &mut destination_definition,
+                        &options.column_renames,
+                    )
+                    .await?;
+                    drop_removed_objects(
+                        d,
+                        &target_definition,
+                        &mut destination_definition,
+                        &options,
+                    )
+                    .await?;
+                }
+                drop_removed_columns(
+                    d,
+                    &target_definition,
+                    &mut destination_definition,
+                    options.allow_destructive || options.allow_drops,
+                )
+                .await?;
+                apply_pre_copy_structure(
+                    d,
+                    &target_definition,
+                    &destination_definition,
+                    options.privilege_downgrade,
+                    destination_capabilities,
+                )
+                .await?;
+                apply_extension_statements(
+                    d,
+                    &target_definition,
+                    &options,
+                    DdlExtensionPhase::BeforeData,
+                )
+                .await?;
+            }
+            SequentialOrParallel::Parallel(ref mut d) => {
+                if options.differential {
+                    rename_hinted_columns(
+                        d,
+                        &target_definition,
+                        &mut destination_definition,
+                        &options.column_renames,
+                    )
+                    .await?;
+                    drop_removed_objects(
+                        d,
+                        &target_definition,
+                        &mut destination_definition,
+                        &options,
+                    )
+                    .await?;
+                }
+                drop_removed_columns(
+                    d,
+                    &target_definition,
+                    &mut destination_definition,
+                    options.allow_destructive || options.allow_drops,
+                )
+                .await?;
+                apply_pre_copy_structure(
+                    d,
+                    &target_definition,
+                    &destination_definition,
+                    options.privilege_downgrade,
+                    destination_capabilities,
+                )
+                .await?;
+                apply_extension_statements(
+                    d,
+                    &target_definition,
+                    &options,
+                    DdlExtensionPhase::BeforeData,
+                )
+                .await?;
+            }
+        }
+
+        destination.commit_transaction().await?;
+
+        if let Some(resume_state) = &resume_state {
+            resume_state.mark_pre_data_structure_applied()?;
+        }
+    }
+
+    // The extensions are created in a `do` block that ignores missing privileges, so it is only
+    // known afterward whether they were created.
+    let guarded_extension_warnings =
+        if options.privilege_downgrade && !options.indexes_only && !options.dry_run {
+            find_skipped_guarded_extensions(&destination, &target_definition).await?
+        } else {
+            Vec::new()
+        };
+
+    let table_timings = Mutex::new(Vec::new());
+    let mut dry_run_tables = Vec::new();
+
+    if phases.contains(&CopyPhase::Data) && options.dry_run {
+        let tables = get_tables_with_data(&source_definition, &target_definition, &skipped_tables);
+        let identifier_quoter = match &destination {
+            SequentialOrParallel::Sequential(d) => d.get_identifier_quoter(),
+            SequentialOrParallel::Parallel(d) => d.get_identifier_quoter(),
+        };
+        dry_run_tables = match &source {
+            SequentialOrParallel::Sequential(s) => {
+                estimate_table_rows(s, &tables, &identifier_quoter).await?
+            }
+            SequentialOrParallel::Parallel(s) => {
+                estimate_table_rows(s, &tables, &identifier_quoter).await?
+            }
+        };
+    } else if phases.contains(&CopyPhase::Data) {
+        let mut parallel_runner = ParallelRunner::new(options.get_max_parallel_or_1());
+
+        for CopiedTable {
+            source_schema,
+            source_table,
+            target_schema,
+            target_table,
+        } in get_tables_with_data(&source_definition, &target_definition, &skipped_tables)
+        {
+            match source {
+                SequentialOrParallel::Sequential(ref source) => match &mut destination {
+                    SequentialOrParallel::Sequential(ref mut destination) => {
+                        do_resumable_copy(
+                            source,
+                            destination,
+                            target_schema,
+                            target_table,
+                            source_schema,
+                            source_table,
+                            &data_format,
+                            same_server,
+                            &options,
+                            &table_timings,
+                            resume_state.as_ref(),
+                        )
+                        .await?
+                    }
+                    SequentialOrParallel::Parallel(ref mut destination) => {
+                        do_resumable_copy(
+                            source,
+                            destination,
+                            target_schema,
+                            target_table,
+                            source_schema,
+                            source_table,
+                            &data_format,
+                            same_server,
+                            &options,
+                            &table_timings,
+                            resume_state.as_ref(),
+                        )
+                        .await?
+                    }
+                },
+                SequentialOrParallel::Parallel(ref source) => match &mut destination {
+                    SequentialOrParallel::Sequential(ref mut destination) => {
+                        do_resumable_copy(
+                            source,
+                            destination,
+                            target_schema,
+                            target_table,
+                            source_schema,
+                            source_table,
+                            &data_format,
+                            same_server,
+                            &options,
+                            &table_timings,
+                            resume_state.as_ref(),
+                        )
+                        .await?
+                    }
+                    SequentialOrParallel::Parallel(ref mut destination) => {
+                        let source = source.clone();
+                        let destination = destination.clone();
+                        let df = data_format.clone();
+                        let opt = &options;
+                        let timings = &table_timings;
+                        let resume_state = resume_state.as_ref();
+                        parallel_runner
+                            .enqueue(async move {
+                                let source = source;
+                                let mut destination = destination;
+                                do_resumable_copy(
+                                    &source,
+                                    &mut destination,
+                                    target_schema,
+                                    target_table,
+                                    source_schema,
+                                    source_table,
+                                    &df,
+                                    same_server,
+                                    opt,
+                                    timings,
+                                    resume_state,
+                                )
+                                .await
+                            })
+                            .await?;
+                    }
+                },
+            }
+        }
+
+        parallel_runner.run_remaining().await?;
+    }
+
+    let rows_copied = table_timings.lock().await.iter().any(|t| t.rows > 0)
+        || resume_state.as_ref().is_some_and(|s| s.has_completed_tables());
+
+    match &mut destination {
+        SequentialOrParallel::Sequential(ref mut destination) => {
+            apply_post_copy_structure_sequential(
+                destination,
+                &target_definition,
+                &options,
+                &destination_definition,
+                rows_copied,
+            )
+            .await?;
+        }
+        SequentialOrParallel::Parallel(ref mut destination) => {
+            apply_post_copy_structure_parallel(
+                destination,
+                &target_definition,
+                &options,
+                &destination_definition,
+                rows_copied,
+            )
+            .await?;
+        }
+    }
+
+    if !options.indexes_only {
+        match &mut destination {
+            SequentialOrParallel::Sequential(ref mut d) => {
+                apply_extension_statements(
+                    d,
+                    &target_definition,
+                    &options,
+                    DdlExtensionPhase::AfterData,
+                )
+                .await?
+            }
+            SequentialOrParallel::Parallel(ref mut d) => {
+                apply_extension_statements(
+                    d,
+                    &target_definition,
+                    &options,
+                    DdlExtensionPhase::AfterData,
+                )
+                .await?
+            }
+        }
+    }
+
+    let mut warnings = skipped_extensions
+        .iter()
+        .map(|name| {
+            let availability = extensions
+                .iter()
+                .find(|e| &e.name == name)
+                .map_or(ExtensionAvailability::SuperuserOnly, |e| e.availability);
+            get_skipped_extension_warning(name, availability)
+        })
+        .collect_vec();
+    warnings.extend(guarded_extension_warnings);
+    warnings.extend(capacity_warning);
+    if !options.indexes_only {
+        let skipped_statements = match &mut destination {
+            SequentialOrParallel::Sequential(ref mut destination) => {
+                apply_superuser_statements(
+                    destination,
+                    &target_definition,
+                    &options,
+                    &destination_definition,
+                )
+                .await?
+            }
+            SequentialOrParallel::Parallel(ref mut destination) => {
+                apply_superuser_statements(
+                    destination,
+                    &target_definition,
+                    &options,
+                    &destination_definition,
+                )
+                .await?
+            }
+        };
+        warnings.extend(skipped_statements);
+    }
+
+    let verification = match options.verification {
+        verification @ (VerificationLevel::Sample { .. } | VerificationLevel::Checksum)
+            if phases.contains(&CopyPhase::Verification) && !options.dry_run =>
+        {
+            let tables = get_tables_to_verify(
+                &source_definition,
+                &target_definition,
+                &skipped_tables,
+                &options,
+            );
+            match (&source, &destination) {
+                (SequentialOrParallel::Sequential(s), SequentialOrParallel::Sequential(d)) => {
+                    verify_copy(s, d, &tables, verification).await?
+                }
+                (SequentialOrParallel::Sequential(s), SequentialOrParallel::Parallel(d)) => {
+                    verify_copy(s, d, &tables, verification).await?
+                }
+                (SequentialOrParallel::Parallel(s), SequentialOrParallel::Sequential(d)) => {
+                    verify_copy(s, d, &tables, verification).await?
+                }
+                (SequentialOrParallel::Parallel(s), SequentialOrParallel::Parallel(d)) => {
+                    verify_copy(s, d, &tables, verification).await?
+                }
+            }
+        }
+        _ => vec![],
     };
 
-    if let Some(target_schema) = &options.target_schema {
-        destination_definition.filtered_to_schema(target_schema);
+    if let Some(staging_swap) = &options.staging_swap {
+        let schema_name = get_swapped_schema_name(&options)?;
+        match &mut destination {
+            SequentialOrParallel::Sequential(ref mut d) => {
+                swap_staging_schema(d, staging_swap, schema_name).await?
+            }
+            SequentialOrParallel::Parallel(ref mut d) => {
+                swap_staging_schema(d, staging_swap, schema_name).await?
+            }
+        }
+    }
+
+    destination.finish().await?;
+
+    if let Some(resume_state) = resume_state {
+        resume_state.remove()?;
+    }
+
+    let skipped_statements = std::mem::take(&mut *skipped_statements.lock().unwrap()); // SAFE: Only panics if another thread panicked while pushing
+    let dry_run = dry_run_statements.map(|statements| DryRunReport {
+        statements: std::mem::take(&mut *statements.lock().unwrap()), // SAFE: Only panics if another thread panicked while pushing
+        tables: dry_run_tables,
+    });
+
+    Ok(CopyDataReport {
+        table_timings: table_timings.into_inner(),
+        verification,
+        warnings,
+        skipped_statements,
+        dry_run,
+        skipped_tables,
+    })
+}
+
+/// Finds the tables with copied data, which are the tables with data that isn't copied
+/// using a select override, and isn't skipped for being too large.
+fn get_tables_to_verify<'a>(
+    source_definition: &'a PostgresDatabase,
+    target_definition: &'a PostgresDatabase,
+    skipped_tables: &[SkippedTable],
+    options: &CopyDataOptions,
+) -> Vec<CopiedTable<'a>> {
+    get_tables_with_data(source_definition, target_definition, skipped_tables)
+        .into_iter()
+        .filter(|t| {
+            options
+                .get_table_select_override(t.source_schema, t.source_table)
+                .is_none()
+        })
+        .collect()
+}
+
+/// Compares the data of the tables in the destination with the source, see [VerificationLevel].
+/// All the tables are compared, even if some of them differ.
+#[instrument(skip_all)]
+async fn verify_copy<S: CopySource, D: CopyDestination>(
+    source: &S,
+    destination: &D,
+    tables: &[CopiedTable<'_>],
+    verification: VerificationLevel,
+) -> Result<Vec<TableVerification>> {
+    let identifier_quoter = destination.get_identifier_quoter();
+    let mut results = Vec::with_capacity(tables.len());
+
+    for table in tables {
+        let result = match verification {
+            VerificationLevel::Sample { rows_per_table } => {
+                verify_table(source, destination, table, rows_per_table, &identifier_quoter)
+                    .await?
+            }
+            VerificationLevel::Checksum => {
+                checksum_table(source, destination, table, &identifier_quoter).await?
+            }
+            VerificationLevel::None => return Ok(vec![]),
+        };
+        let Some(result) = result else {
+            warn!("The source or the destination does not support verifying the copied data");
+            return Ok(vec![]);
+        };
+
+        if let TableVerificationResult::RowCountOnly { .. } = result.result {
+            info!(
+                "Table {}.{} has no primary key, so only the number of rows is verified",
+                result.schema_name, result.table_name
+            );
+        }
+
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+/// Removes the indexes and unique constraints that should not be created by a copy with
+/// `skip_indexes`. Foreign keys require a unique index on the referenced columns, so foreign keys
+/// referencing the columns of a removed index are removed as well, leaving them for the
+/// `indexes_only` copy.
+fn remove_skipped_indexes(
+    definition: &mut PostgresDatabase,
+    skip_primary_keys: bool,
+    identifier_quoter: &IdentifierQuoter,
+) {
+    let mut removed_keys = Vec::new();
+
+    for schema in &mut definition.schemas {
+        for table in &mut schema.tables {
+            table.indices.retain(|index| {
+                if index.index_constraint_type == PostgresIndexType::PrimaryKey && !skip_primary_keys {
+                    return true;
+                }
+
+                if index.index_constraint_type != PostgresIndexType::Index {
+                    removed_keys.push((
+                        schema.name.clone(),
+                        table.name.clone(),
+                        index.key_columns.iter().map(|c| c.name.clone()).sorted().collect_vec(),
+                    ));
+                }
+
+                debug!("Skipping index {} on table {}", index.name, table.name);
+                false
+            });
+
+            table
+                .constraints
+                .retain(|c| !matches!(c, PostgresConstraint::Unique(_)));
+        }
+    }
+
+    for schema in &mut definition.schemas {
+        for table in &mut schema.tables {
+            table.constraints.retain(|constraint| {
+                let PostgresConstraint::ForeignKey(fk) = constraint else {
+                    return true;
+                };
+
+                let referenced_schema = fk.referenced_schema.as_ref().unwrap_or(&schema.name);
+                let referenced_columns = fk
+                    .referenced_columns
+                    .iter()
+                    .map(|c| c.name.quote(identifier_quoter, ColumnName))
+                    .sorted()
+                    .collect_vec();
+
+                let references_removed_key =
+                    removed_keys.iter().any(|(schema_name, table_name, columns)| {
+                        schema_name == referenced_schema
+                            && *table_name == fk.referenced_table
+                            && *columns == referenced_columns
+                    });
+
+                if references_removed_key {
+                    info!(
+                        "Skipping foreign key {} on table {}, as the index it references is skipped",
+                        fk.name, table.name
+                    );
+                }
+
+                !references_removed_key
+            });
+        }
+    }
+}
+
+/// Removes the tables that don't exist in the destination, together with the foreign keys
+/// referencing them, as a copy with `indexes_only` doesn't create any tables.
+fn remove_tables_missing_in_destination(
+    definition: &mut PostgresDatabase,
+    destination_definition: &PostgresDatabase,
+) {
+    let table_exists = |schema_name: &str, table_name: &str| {
+        destination_definition
+            .try_get_schema(schema_name)
+            .and_then(|s| s.try_get_table(table_name))
+            .is_some()
+    };
+
+    for schema in &mut definition.schemas {
+        schema.tables.retain(|table| {
+            let exists = table_exists(&schema.name, &table.name);
+            if !exists {
+                warn!(
+                    "Table {}.{} does not exist in the destination, so its indexes are not created",
+                    schema.name, table.name
+                );
+            }
+            exists
+        });
+
+        for table in &mut schema.tables {
+            table.constraints.retain(|constraint| match constraint {
+                PostgresConstraint::ForeignKey(fk) => table_exists(
+                    fk.referenced_schema.as_ref().unwrap_or(&schema.name),
+                    &fk.referenced_table,
+                ),
+                _ => true,
+            });
+        }
+    }
+}
+
+/// Drops the tables, views, functions and indices in the destination that no longer exist in
+/// the source, see [CopyDataOptions::allow_drops]. Without `allow_drops` they are only logged.
+///
+/// The objects are dropped in reverse dependency order, so views are dropped before the tables
+/// they use. Foreign keys referencing the dropped tables are dropped first, as they are not part
+/// of the dependencies of the tables. The dropped objects are removed from `target_definition`.
+#[instrument(skip_all)]
+async fn drop_removed_objects<D: CopyDestination>(
+    destination: &mut D,
+    definition: &PostgresDatabase,
+    target_definition: &mut PostgresDatabase,
+    options: &CopyDataOptions,
+) -> Result<()> {
+    let identifier_quoter = destination.get_identifier_quoter();
+    let filter = &options.object_filter;
+    let filters_tables = !filter.include_tables.is_empty() || !filter.exclude_tables.is_empty();
+
+    let mut removed_objects = Vec::new();
+    let mut removed_indices = Vec::new();
+    for target_schema in &target_definition.schemas {
+        let Some(schema) = definition.try_get_schema(&target_schema.name) else {
+            continue;
+        };
+
+        for target_table in &target_schema.tables {
+            let Some(table) = schema.try_get_table(&target_table.name) else {
+                if !filters_tables
+                    || is_table_selected(
+                        target_schema,
+                        target_table,
+                        &filter.include_tables,
+                        &filter.exclude_tables,
+                    )
+                {
+                    removed_objects.push(PostgresThingWithDependencies::Table(
+                        target_table,
+                        target_schema,
+                    ));
+                }
+                continue;
+            };
+
+            if options.skip_indexes {
+                continue;
+            }
+
+            for index in &target_table.indices {
+                // Indexes attached to the index of a partitioned table are dropped together with it.
+                if table.indices.iter().any(|i| i.name == index.name)
+                    || index.parent_index.is_some()
+                    || (options.skip_primary_keys
+                        && index.index_constraint_type == PostgresIndexType::PrimaryKey)
+                {
+                    continue;
+                }
+
+                removed_indices.push((target_schema, target_table, index));
+            }
+        }
+
+        // Views and functions might only be missing because of the filter.
+        if !filter.is_empty() {
+            continue;
+        }
+
+        for view in &target_schema.views {
+            if !schema.views.iter().any(|v| v.name == view.name) {
+                removed_objects.push(PostgresThingWithDependencies::View(view, target_schema));
+            }
+        }
+
+        for function in &target_schema.functions {
+            if !schema.functions.iter().any(|f| {
+                f.function_name == function.function_name && f.arguments == function.arguments
+            }) {
+                removed_objects.push(PostgresThingWithDependencies::Function(
+                    function,
+                    target_schema,
+                ));
+            }
+        }
+
+        for aggregate_function in &target_schema.aggregate_functions {
+            if !schema.aggregate_functions.iter().any(|f| {
+                f.function_name == aggregate_function.function_name
+                    && f.arguments == aggregate_function.arguments
+            }) {
+                removed_objects.push(PostgresThingWithDependencies::AggregateFunction(
+                    aggregate_function,
+                    target_schema,
+                ));
+            }
+        }
+    }
+
+    if !options.allow_drops {
+        for object in &removed_objects {
+            warn!(
+                "The {} {} no longer exists in the source, but is kept in the destination",
+                object.get_kind(),
+                (&object).object_name()
+            );
+        }
+        for (schema, table, index) in &removed_indices {
+            warn!(
+                "Index {} of table {}.{} no longer exists in the source, but is kept in the destination",
+                index.name, schema.name, table.name
+            );
+        }
+        return Ok(());
+    }
+
+    let removed_tables = removed_objects
+        .iter()
+        .filter_map(|o| match o {
+            PostgresThingWithDependencies::Table(table, schema) => {
+                Some((schema.name.as_str(), table.name.as_str()))
+            }
+            _ => None,
+        })
+        .collect_vec();
+
+    let mut statements = Vec::new();
+    for schema in &target_definition.schemas {
+        for table in &schema.tables {
+            for constraint in &table.constraints {
+                let PostgresConstraint::ForeignKey(fk) = constraint else {
+                    continue;
+                };
+                let referenced_schema = fk.referenced_schema.as_deref().unwrap_or(&schema.name);
+                if removed_tables.contains(&(referenced_schema, fk.referenced_table.as_str()))
+                    && !removed_tables.contains(&(schema.name.as_str(), table.name.as_str()))
+                {
+                    info!(
+                        "Dropping foreign key {} of table {}.{}, as it references a dropped table",
+                        fk.name, schema.name, table.name
+                    );
+                    statements.push(format!(
+                        "alter table {}.{} drop constraint {};",
+                        schema.name.quote(&identifier_quoter, ColumnName),
+                        table.name.quote(&identifier_quoter, ColumnName),
+                        fk.name.quote(&identifier_quoter, ColumnName)
+                    ));
+                }
+            }
+        }
+    }
+
+    for (schema, table, index) in &removed_indices {
+        info!(
+            "Dropping index {} of table {}.{}",
+            index.name, schema.name, table.name
+        );
+        statements.push(index.get_drop_statement(schema, table, &identifier_quoter));
+    }
+
+    let mut removed_objects = removed_objects.iter().sort_by_dependencies()?;
+    removed_objects.reverse();
+    for object in &removed_objects {
+        info!("Dropping {} {}", object.get_kind(), object.object_name());
+        statements.push(object.get_drop_sql(&identifier_quoter));
+    }
+
+    let dropped_ids = removed_objects
+        .iter()
+        .map(|o| o.object_id())
+        .collect_vec();
+    let dropped_indices = removed_indices
+        .iter()
+        .map(|(schema, table, index)| {
+            (schema.name.clone(), table.name.clone(), index.name.clone())
+        })
+        .collect_vec();
+
+    for statement in statements {
+        destination.apply_transactional_statement(&statement).await?;
     }
 
-    destination.begin_transaction().await?;
-
-    match &mut destination {
-        SequentialOrParallel::Sequential(ref mut d) => {
-            apply_pre_copy_structure(d, &target_definition, &destination_definition).await?;
-        }
-        SequentialOrParallel::Parallel(ref mut d) => {
-            apply_pre_copy_structure(d, &target_definition, &destination_definition).await?;
+    for schema in &mut target_definition.schemas {
+        schema.tables.retain(|t| !dropped_ids.contains(&t.object_id));
+        schema.views.retain(|v| !dropped_ids.contains(&v.object_id));
+        schema.functions.retain(|f| !dropped_ids.contains(&f.object_id));
+        schema
+            .aggregate_functions
+            .retain(|f| !dropped_ids.contains(&f.object_id));
+
+        for table in &mut schema.tables {
+            table.indices.retain(|i| {
+                !dropped_indices.contains(&(schema.name.clone(), table.name.clone(), i.name.clone()))
+            });
         }
     }
 
-    destination.commit_transaction().await?;
+    Ok(())
+}
 
-    if !options.schema_only {
-        let mut parallel_runner = ParallelRunner::new(options.get_max_parallel_or_1());
+/// Drops the columns of existing tables in the destination that no longer exist in the source.
+///
+/// Views using those columns, and the views depending on those views, have to be dropped first.
+/// They are removed from `target_definition` as well, so they are recreated from the source
+/// together with the rest of the missing structures. Without `allow_destructive` nothing is
+/// dropped, and an error is returned if any views depend on the removed columns.
+#[instrument(skip_all)]
+async fn drop_removed_columns<D: CopyDestination>(
+    destination: &mut D,
+    definition: &PostgresDatabase,
+    target_definition: &mut PostgresDatabase,
+    allow_destructive: bool,
+) -> Result<()> {
+    let identifier_quoter = destination.get_identifier_quoter();
 
-        for target_schema in &target_definition.schemas {
-            let source_schema = source_definition
-                .schemas
-                .iter()
-                .find(|s| s.object_id == target_schema.object_id);
-            let source_schema = match source_schema {
-                Some(s) => s,
-                None => {
-                    continue;
-                }
+    let mut removed_columns = Vec::new();
+    for schema in &definition.schemas {
+        let Some(target_schema) = target_definition.try_get_schema(&schema.name) else {
+            continue;
+        };
+
+        for table in &schema.tables {
+            let Some(target_table) = target_schema.try_get_table(&table.name) else {
+                continue;
             };
 
-            for target_table in &target_schema.tables {
-                if let TableTypeDetails::PartitionedParentTable { .. } = &target_table.table_type {
-                    continue;
+            for column in &target_table.columns {
+                if !table.columns.iter().any(|c| c.name == column.name) {
+                    removed_columns.push((
+                        target_schema.name.clone(),
+                        target_table.name.clone(),
+                        target_table.object_id,
+                        column.name.clone(),
+                    ));
                 }
+            }
+        }
+    }
+
+    let mut views_to_drop = Vec::new();
+    let mut columns_to_drop = Vec::new();
 
-                let source_table = source_schema
-                    .tables
+    for (schema_name, table_name, table_object_id, column_name) in removed_columns {
+        let mut dependent_views = target_definition
+            .schemas
+            .iter()
+            .flat_map(|s| s.views.iter())
+            .filter(|v| {
+                v.column_dependencies
                     .iter()
-                    .find(|t| t.object_id == target_table.object_id);
-                let source_table = match source_table {
-                    Some(s) => s,
-                    None => {
-                        continue;
-                    }
-                };
+                    .any(|(id, name)| *id == table_object_id && *name == column_name)
+            })
+            .map(|v| v.object_id)
+            .collect::<Vec<_>>();
 
-                match source {
-                    SequentialOrParallel::Sequential(ref source) => match &mut destination {
-                        SequentialOrParallel::Sequential(ref mut destination) => {
-                            do_copy(
-                                source,
-                                destination,
-                                target_schema,
-                                target_table,
-                                source_schema,
-                                source_table,
-                                &data_format,
-                                &options,
-                            )
-                            .await?
-                        }
-                        SequentialOrParallel::Parallel(ref mut destination) => {
-                            do_copy(
-                                source,
-                                destination,
-                                target_schema,
-                                target_table,
-                                source_schema,
-                                source_table,
-                                &data_format,
-                                &options,
-                            )
-                            .await?
-                        }
-                    },
-                    SequentialOrParallel::Parallel(ref source) => match &mut destination {
-                        SequentialOrParallel::Sequential(ref mut destination) => {
-                            do_copy(
-                                source,
-                                destination,
-                                target_schema,
-                                target_table,
-                                source_schema,
-                                source_table,
-                                &data_format,
-                                &options,
-                            )
-                            .await?
-                        }
-                        SequentialOrParallel::Parallel(ref mut destination) => {
-                            let source = source.clone();
-                            let destination = destination.clone();
-                            let df = data_format.clone();
-                            let opt = &options;
-                            parallel_runner
-                                .enqueue(async move {
-                                    let source = source;
-                                    let mut destination = destination;
-                                    do_copy(
-                                        &source,
-                                        &mut destination,
-                                        target_schema,
-                                        target_table,
-                                        source_schema,
-                                        source_table,
-                                        &df,
-                                        opt,
-                                    )
-                                    .await
-                                })
-                                .await?;
-                        }
-                    },
-                }
+        loop {
+            let indirect = target_definition
+                .schemas
+                .iter()
+                .flat_map(|s| s.views.iter())
+                .filter(|v| !dependent_views.contains(&v.object_id))
+                .filter(|v| v.depends_on.iter().any(|d| dependent_views.contains(d)))
+                .map(|v| v.object_id)
+                .collect::<Vec<_>>();
+
+            if indirect.is_empty() {
+                break;
+            }
+
+            dependent_views.extend(indirect);
+        }
+
+        if !allow_destructive {
+            if dependent_views.is_empty() {
+                warn!(
+                    "Column {} of table {}.{} no longer exists in the source, but is kept in the destination",
+                    column_name, schema_name, table_name
+                );
+                continue;
             }
+
+            return Err(ElefantToolsError::ColumnDropBlockedByViews {
+                views: target_definition
+                    .schemas
+                    .iter()
+                    .flat_map(|s| s.views.iter().map(move |v| (s, v)))
+                    .filter(|(_, v)| dependent_views.contains(&v.object_id))
+                    .map(|(s, v)| format!("{}.{}", s.name, v.name))
+                    .collect(),
+                schema_name,
+                table_name,
+                column_name,
+            });
         }
 
-        parallel_runner.run_remaining().await?;
+        views_to_drop.extend(dependent_views);
+        columns_to_drop.push((schema_name, table_name, column_name));
     }
 
-    match &mut destination {
-        SequentialOrParallel::Sequential(ref mut destination) => {
-            apply_post_copy_structure_sequential(
-                destination,
-                &target_definition,
-                &destination_definition,
-            )
-            .await?;
+    for schema in &mut target_definition.schemas {
+        for view in &schema.views {
+            if !views_to_drop.contains(&view.object_id) {
+                continue;
+            }
+
+            info!(
+                "Dropping view {}.{} to recreate it from the source",
+                schema.name, view.name
+            );
+            destination
+                .apply_transactional_statement(&format!(
+                    "drop {}view if exists {}.{} cascade;",
+                    if view.is_materialized {
+                        "materialized "
+                    } else {
+                        ""
+                    },
+                    schema.name.quote(&identifier_quoter, ColumnName),
+                    view.name.quote(&identifier_quoter, ColumnName)
+                ))
+                .await?;
         }
-        SequentialOrParallel::Parallel(ref mut destination) => {
-            apply_post_copy_structure_parallel(
-                destination,
-                &target_definition,
-                &options,
-                &destination_definition,
-            )
+
+        schema
+            .views
+            .retain(|v| !views_to_drop.contains(&v.object_id));
+    }
+
+    for (schema_name, table_name, column_name) in columns_to_drop {
+        info!(
+            "Dropping column {} from table {}.{}",
+            column_name, schema_name, table_name
+        );
+        destination
+            .apply_transactional_statement(&format!(
+                "alter table {}.{} drop column {};",
+                schema_name.quote(&identifier_quoter, ColumnName),
+                table_name.quote(&identifier_quoter, ColumnName),
+                column_name.quote(&identifier_quoter, ColumnName)
+            ))
             .await?;
+
+        if let Some(table) = target_definition
+            .schemas
+            .iter_mut()
+            .find(|s| s.name == schema_name)
+            .and_then(|s| s.tables.iter_mut().find(|t| t.name == table_name))
+        {
+            table.columns.retain(|c| c.name != column_name);
         }
     }
 
-    destination.finish().await?;
-
     Ok(())
 }
 
@@ -250,25 +1816,39 @@ pub async fn copy_data<'d, S: CopySourceFactory, D: CopyDestinationFactory<'d>>(
 /// * Creating functions
 /// * Creating views
 /// * Creating custom types
+///
+/// Everything but schemas and extensions are created in dependency order. With
+/// `privilege_downgrade`, extensions the destination user is not allowed to create are skipped,
+/// see [PostgresExtension::get_guarded_create_statement].
 #[instrument(skip_all)]
 async fn apply_pre_copy_structure<D: CopyDestination>(
     destination: &mut D,
     definition: &PostgresDatabase,
     target_definition: &PostgresDatabase,
+    privilege_downgrade: bool,
+    destination_capabilities: Option<ServerCapabilities>,
 ) -> Result<()> {
     let identifier_quoter = destination.get_identifier_quoter();
 
     for schema in &definition.schemas {
 
         let target_schema = target_definition.try_get_schema(&schema.name);
-        if target_schema.is_none() {
+        if let Some(target_schema) = target_schema {
+            if let Some(sql) = schema.get_comment_change_statement(target_schema, &identifier_quoter) {
+                destination.apply_transactional_statement(&sql).await?;
+            }
+        } else {
             destination
                 .apply_transactional_statement(&schema.get_create_statement(&identifier_quoter))
                 .await?;
-        }
 
-        if let Some(comment_statement) = schema.get_set_comment_statement(&identifier_quoter) {
-            destination.apply_transactional_statement(&comment_statement).await?;
+            if let Some(comment_statement) = schema.get_set_comment_statement(&identifier_quoter) {
+                destination.apply_transactional_statement(&comment_statement).await?;
+            }
+
+            if let Some(privilege_statements) = schema.get_privilege_statements(&identifier_quoter) {
+                destination.apply_transactional_statement(&privilege_statements).await?;
+            }
         }
     }
 
@@ -282,43 +1862,81 @@ async fn apply_pre_copy_structure<D: CopyDestination>(
             continue;
         }
 
-        destination
-            .apply_transactional_statement(&ext.get_create_statement(&identifier_quoter))
-            .await?;
+        let statement = if privilege_downgrade {
+            ext.get_guarded_create_statement(&identifier_quoter)
+        } else {
+            ext.get_create_statement(&identifier_quoter)
+        };
+        destination.apply_transactional_statement(&statement).await?;
     }
 
+    let mut tables_and_functions: Vec<PostgresThingWithDependencies> = Vec::new();
+    let mut replaced_functions = Vec::new();
+
     for schema in &definition.schemas {
         let target_schema = target_definition.try_get_schema(&schema.name);
 
         for enumeration in &schema.enums {
-            if target_schema.is_some_and(|s| s.enums.iter().any(|e| e.name == enumeration.name)) {
+            if let Some(existing_enum) =
+                target_schema.and_then(|s| s.enums.iter().find(|e| e.name == enumeration.name))
+            {
                 debug!("Enum {} already exists in destination", enumeration.name);
+                if let Some(sql) = enumeration.get_comment_change_statement(
+                    existing_enum,
+                    schema,
+                    &identifier_quoter,
+                ) {
+                    destination.apply_transactional_statement(&sql).await?;
+                }
                 continue;
             }
 
-            destination
-                .apply_transactional_statement(
-                    &enumeration.get_create_statement(&identifier_quoter),
-                )
-                .await?;
+            tables_and_functions.push(PostgresThingWithDependencies::Enum(enumeration, schema));
         }
-    }
-
-    let mut tables_and_functions: Vec<PostgresThingWithDependencies> = Vec::new();
-
-    for schema in &definition.schemas {
-        let target_schema = target_definition.try_get_schema(&schema.name);
 
         for function in &schema.functions {
-            if target_schema.is_some_and(|s| {
-                s.functions
+            let target_functions = target_schema
+                .map(|s| {
+                    s.functions
+                        .iter()
+                        .filter(|f| f.function_name == function.function_name)
+                        .collect_vec()
+                })
+                .unwrap_or_default();
+
+            if !target_functions.is_empty() {
+                if let Some(existing_function) = target_functions
                     .iter()
-                    .any(|f| f.function_name == function.function_name)
-            }) {
-                debug!(
-                    "Function {} already exists in destination",
-                    function.function_name
-                );
+                    .find(|f| f.arguments == function.arguments && f.kind == function.kind)
+                {
+                    if let Some(sql) = function.get_comment_change_statement(
+                        existing_function,
+                        schema,
+                        &identifier_quoter,
+                    ) {
+                        destination.apply_transactional_statement(&sql).await?;
+                    }
+                }
+
+                // Only the body is replaced, as `create or replace` cannot change the arguments
+                // or the result of a function. Reformatting the body is not a change.
+                if target_functions.iter().any(|f| {
+                    f.arguments == function.arguments
+                        && f.result == function.result
+                        && f.kind == function.kind
+                        && f.sql_body != function.sql_body
+                }) {
+                    debug!(
+                        "Function {} has a changed body, replacing it",
+                        function.function_name
+                    );
+                    replaced_functions.push((function, schema));
+                } else {
+                    debug!(
+                        "Function {} already exists in destination",
+                        function.function_name
+                    );
+                }
                 continue;
             }
 
@@ -345,11 +1963,46 @@ async fn apply_pre_copy_structure<D: CopyDestination>(
         }
 
         for table in &schema.tables {
-            if target_schema
-                .and_then(|s| s.try_get_table(&table.name))
-                .is_some()
-            {
+            if let Some(existing_table) = target_schema.and_then(|s| s.try_get_table(&table.name)) {
                 debug!("Table {} already exists in destination", table.name);
+
+                for sql in
+                    table.get_comment_change_statements(existing_table, schema, &identifier_quoter)
+                {
+                    destination.apply_transactional_statement(&sql).await?;
+                }
+
+                if table.access_method != existing_table.access_method {
+                    if destination_capabilities.is_some_and(|c| !c.supports_set_access_method) {
+                        warn!(
+                            "Table {} uses a different access method in the destination, which cannot be changed before Postgres 15, so it is kept",
+                            table.name
+                        );
+                    } else {
+                        destination
+                            .apply_transactional_statement(
+                                &table.get_set_access_method_statement(schema, &identifier_quoter),
+                            )
+                            .await?;
+                    }
+                }
+
+                #[cfg(feature = "timescale")]
+                if table.is_timescale_table() && !existing_table.is_timescale_table() {
+                    let migrate_data = destination.has_data_in_table(schema, table).await?;
+
+                    if let Some(sql) = table.get_create_hypertable_statement(
+                        schema,
+                        &identifier_quoter,
+                        &HypertableCreateOptions {
+                            migrate_data,
+                            if_not_exists: true,
+                        },
+                    ) {
+                        destination.apply_transactional_statement(&sql).await?;
+                    }
+                }
+
                 continue;
             }
 
@@ -357,34 +2010,222 @@ async fn apply_pre_copy_structure<D: CopyDestination>(
         }
 
         for view in &schema.views {
-            if target_schema.is_some_and(|s| s.views.iter().any(|v| v.name == view.name)) {
+            if let Some(existing_view) =
+                target_schema.and_then(|s| s.views.iter().find(|v| v.name == view.name))
+            {
                 debug!("View {} already exists in destination", view.name);
+                if let Some(sql) =
+                    view.get_comment_change_statement(existing_view, schema, &identifier_quoter)
+                {
+                    destination.apply_transactional_statement(&sql).await?;
+                }
                 continue;
             }
 
+            #[cfg(feature = "timescale")]
+            if let ViewOptions::TimescaleContinuousAggregate {
+                finalized: false, ..
+            } = &view.view_options
+            {
+                warn!("Continuous aggregate {} uses the old non-finalized format. It will be recreated in the finalized format, as newer versions of Timescale no longer supports creating the old format.", view.name);
+            }
+
             tables_and_functions.push(PostgresThingWithDependencies::View(view, schema));
         }
 
         for domain in &schema.domains {
-            if target_schema.is_some_and(|s| s.domains.iter().any(|d| d.name == domain.name)) {
+            if let Some(existing_domain) =
+                target_schema.and_then(|s| s.domains.iter().find(|d| d.name == domain.name))
+            {
                 debug!("Domain {} already exists in destination", domain.name);
+                if let Some(sql) =
+                    domain.get_comment_change_statement(existing_domain, schema, &identifier_quoter)
+                {
+                    destination.apply_transactional_statement(&sql).await?;
+                }
                 continue;
             }
 
             tables_and_functions.push(PostgresThingWithDependencies::Domain(domain, schema));
         }
+
+        for composite_type in &schema.composite_types {
+            if let Some(existing_type) = target_schema.and_then(|s| {
+                s.composite_types
+                    .iter()
+                    .find(|t| t.name == composite_type.name)
+            }) {
+                debug!("Composite type {} already exists in destination", composite_type.name);
+                if let Some(sql) = composite_type.get_comment_change_statement(
+                    existing_type,
+                    schema,
+                    &identifier_quoter,
+                ) {
+                    destination.apply_transactional_statement(&sql).await?;
+                }
+                continue;
+            }
+
+            tables_and_functions.push(PostgresThingWithDependencies::CompositeType(
+                composite_type,
+                schema,
+            ));
+        }
     }
 
-    let sorted = tables_and_functions.iter().sort_by_dependencies();
+    let sorted = tables_and_functions.iter().sort_by_dependencies()?;
 
     for thing in sorted {
         let sql = thing.get_create_sql(&identifier_quoter);
         destination.apply_transactional_statement(&sql).await?;
+
+        if let Some(sql) = thing.get_privilege_statements(&identifier_quoter) {
+            destination.apply_transactional_statement(&sql).await?;
+        }
+    }
+
+    for (function, schema) in replaced_functions {
+        let sql = function.get_replace_statement(schema, &identifier_quoter);
+        destination.apply_transactional_statement(&sql).await?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the tables in [CopyDataOptions::table_select_overrides] exist, and that the
+/// select queries return the columns of the tables in the destination.
+async fn validate_table_select_overrides<S: CopySource, P: CopySource + Clone + Sync>(
+    source: &SequentialOrParallel<S, P>,
+    source_definition: &PostgresDatabase,
+    target_definition: &PostgresDatabase,
+    options: &CopyDataOptions,
+) -> Result<()> {
+    for (name, select) in options.table_select_overrides.iter().sorted() {
+        let (schema_name, table_name) = name.split_once('.').unwrap_or(("public", name));
+
+        let (source_schema, source_table) = source_definition
+            .try_get_schema(schema_name)
+            .and_then(|s| s.try_get_table(table_name).map(|t| (s, t)))
+            .ok_or_else(|| ElefantToolsError::TableNotFound {
+                schema_name: schema_name.to_string(),
+                table_name: table_name.to_string(),
+            })?;
+
+        let target_table = target_definition
+            .schemas
+            .iter()
+            .filter(|s| s.object_id == source_schema.object_id)
+            .flat_map(|s| &s.tables)
+            .find(|t| t.object_id == source_table.object_id);
+        let target_table = match target_table {
+            Some(t) => t,
+            None => continue,
+        };
+
+        let expected_columns = target_table
+            .get_writable_columns()
+            .map(|c| c.name.clone())
+            .collect_vec();
+        let actual_columns = source.get_select_columns(select).await?;
+
+        if expected_columns != actual_columns {
+            return Err(ElefantToolsError::TableSelectOverrideMismatch {
+                schema_name: schema_name.to_string(),
+                table_name: table_name.to_string(),
+                missing_columns: expected_columns
+                    .iter()
+                    .filter(|c| !actual_columns.contains(c))
+                    .cloned()
+                    .collect(),
+                extra_columns: actual_columns
+                    .iter()
+                    .filter(|c| !expected_columns.contains(c))
+                    .cloned()
+                    .collect(),
+                expected_columns,
+            });
+        }
     }
 
     Ok(())
 }
 
+/// Copies the data of a table, unless it was copied completely before the copy was interrupted,
+/// see [CopyDataOptions::resume_state_path]. A table that was only partially copied is truncated
+/// and copied again.
+#[allow(clippy::too_many_arguments)]
+async fn do_resumable_copy<S: CopySource, D: CopyDestination>(
+    source: &S,
+    destination: &mut D,
+    target_schema: &PostgresSchema,
+    target_table: &PostgresTable,
+    source_schema: &PostgresSchema,
+    source_table: &PostgresTable,
+    data_format: &DataFormat,
+    same_server: bool,
+    options: &CopyDataOptions,
+    table_timings: &Mutex<Vec<TableCopyTiming>>,
+    resume_state: Option<&ResumeState>,
+) -> Result<()> {
+    let Some(resume_state) = resume_state else {
+        return do_copy(
+            source,
+            destination,
+            target_schema,
+            target_table,
+            source_schema,
+            source_table,
+            data_format,
+            same_server,
+            options,
+            table_timings,
+        )
+        .await;
+    };
+
+    if resume_state.is_table_completed(target_schema, target_table) {
+        info!(
+            "Skipping table {}.{} as it was copied before the copy was interrupted",
+            target_schema.name, target_table.name
+        );
+        return Ok(());
+    }
+
+    if resume_state.is_resuming()
+        && destination
+            .has_data_in_table(target_schema, target_table)
+            .await?
+    {
+        info!(
+            "Truncating table {}.{} as it was only partially copied before the copy was interrupted",
+            target_schema.name, target_table.name
+        );
+        let identifier_quoter = destination.get_identifier_quoter();
+        destination
+            .apply_transactional_statement(&format!(
+                "truncate table {};",
+                get_relation_name(target_schema, target_table, &identifier_quoter)
+            ))
+            .await?;
+    }
+
+    do_copy(
+        source,
+        destination,
+        target_schema,
+        target_table,
+        source_schema,
+        source_table,
+        data_format,
+        same_server,
+        options,
+        table_timings,
+    )
+    .await?;
+
+    resume_state.mark_table_completed(target_schema, target_table)
+}
+
 /// Actually copies data between two tables.
 #[instrument(skip_all)]
 #[allow(clippy::too_many_arguments)]
@@ -396,41 +2237,242 @@ async fn do_copy<S: CopySource, D: CopyDestination>(
     source_schema: &PostgresSchema,
     source_table: &PostgresTable,
     data_format: &DataFormat,
+    same_server: bool,
     options: &CopyDataOptions,
+    table_timings: &Mutex<Vec<TableCopyTiming>>,
 ) -> Result<()> {
     let has_data = options.differential
         && destination
             .has_data_in_table(target_schema, target_table)
             .await?;
 
-    if !has_data {
-        info!(
-            "Skipping table {} as it already has data in the destination",
-            target_table.name
+    if !has_data {
+        info!(
+            "Skipping table {} as it already has data in the destination",
+            target_table.name
+        );
+        if options
+            .get_table_select_override(source_schema, source_table)
+            .is_none()
+            && target_table.get_writable_columns().next().is_none()
+        {
+            return copy_rows_without_columns(
+                source,
+                destination,
+                target_schema,
+                target_table,
+                source_schema,
+                source_table,
+                table_timings,
+            )
+            .await;
+        }
+
+        let started = Instant::now();
+        let self_reference_ordering = if options.order_self_referencing {
+            SelfReferenceOrdering::find(source_schema, source_table)?
+        } else {
+            None
+        };
+        let data = match (
+            options.get_table_select_override(source_schema, source_table),
+            self_reference_ordering,
+        ) {
+            (Some(select), _) => source.get_data_from_select(select, data_format).await?,
+            (None, Some(ordering)) => {
+                let identifier_quoter = destination.get_identifier_quoter();
+                ordering.check_for_cycles(source, &identifier_quoter).await?;
+                source
+                    .get_data_from_select(
+                        &ordering.get_ordered_select(&identifier_quoter),
+                        data_format,
+                    )
+                    .await?
+            }
+            (None, None) => {
+                source
+                    .get_data(source_schema, source_table, data_format)
+                    .await?
+            }
+        };
+        let start_wait = started.elapsed();
+
+        let timer = Arc::new(StreamTimer::default());
+        let stream = if same_server {
+            Either::Left(CoalescedStream::new(data.data, SAME_SERVER_CHUNK_SIZE))
+        } else {
+            Either::Right(data.data)
+        };
+        let data = TableData {
+            data: TimedStream::new(stream, timer.clone(), &data.data_format),
+            data_format: data.data_format,
+            cleanup: data.cleanup,
+        };
+
+        if let Err(e) = destination
+            .apply_data(target_schema, target_table, data)
+            .await
+        {
+            let e = add_encoding_error_context(e, target_schema, target_table, &timer);
+            return Err(add_disk_full_context(
+                e,
+                target_schema,
+                target_table,
+                &table_timings.lock().await,
+            ));
+        }
+
+        let timing = timer.into_timing(
+            &target_schema.name,
+            &target_table.name,
+            started.elapsed(),
+            start_wait,
+        );
+        table_timings.lock().await.push(timing);
+    }
+
+    Ok(())
+}
+
+/// Adds the table that was being copied, and how much data had been copied before it, to errors
+/// from the destination running out of disk space. Other errors are returned as they are.
+fn add_disk_full_context(
+    error: ElefantToolsError,
+    schema: &PostgresSchema,
+    table: &PostgresTable,
+    copied_tables: &[TableCopyTiming],
+) -> ElefantToolsError {
+    if error.postgres_code() != Some(&SqlState::DISK_FULL) {
+        return error;
+    }
+
+    ElefantToolsError::DestinationDiskFull {
+        schema_name: schema.name.clone(),
+        table_name: table.name.clone(),
+        copied_tables: copied_tables.len(),
+        copied_bytes: copied_tables.iter().map(|t| t.bytes).sum(),
+        source: Box::new(error),
+    }
+}
+
+/// Adds the table and how far into its data the copy got to encoding errors, so the rows with
+/// invalid data can be found. Other errors are returned as they are.
+fn add_encoding_error_context(
+    error: ElefantToolsError,
+    schema: &PostgresSchema,
+    table: &PostgresTable,
+    timer: &StreamTimer,
+) -> ElefantToolsError {
+    if !error.is_encoding_error() {
+        return error;
+    }
+
+    let column_name = error
+        .postgres_db_error()
+        .and_then(|e| e.where_())
+        .and_then(get_copy_error_column)
+        .map(|c| c.to_string());
+
+    ElefantToolsError::InvalidlyEncodedData {
+        schema_name: schema.name.clone(),
+        table_name: table.name.clone(),
+        column_name,
+        rows_copied: timer.rows(),
+        byte_offset: timer.bytes(),
+        source: Box::new(error),
+    }
+}
+
+/// Finds the column in the context Postgres gives to errors in `copy`, which looks like
+/// `COPY items, line 2, column name: "..."`. The column is not included for errors that
+/// happen before the line is split into columns.
+fn get_copy_error_column(context: &str) -> Option<&str> {
+    let (_, column) = context.lines().next()?.split_once(", column ")?;
+
+    Some(column.split_once(':').map_or(column, |(c, _)| c))
+}
+
+/// Copies a table without any columns that can be written to, because it has no columns at all,
+/// or only generated columns. `copy` cannot be used with an empty column list, so the rows are
+/// counted in the source and inserted with their default values in the destination instead.
+///
+/// The table is skipped with a warning if the source cannot count the rows.
+async fn copy_rows_without_columns<S: CopySource, D: CopyDestination>(
+    source: &S,
+    destination: &mut D,
+    target_schema: &PostgresSchema,
+    target_table: &PostgresTable,
+    source_schema: &PostgresSchema,
+    source_table: &PostgresTable,
+    table_timings: &Mutex<Vec<TableCopyTiming>>,
+) -> Result<()> {
+    let started = Instant::now();
+    let identifier_quoter = destination.get_identifier_quoter();
+
+    let count_sql = format!(
+        "select count(*)::text from {}.{}",
+        source_schema.name.quote(&identifier_quoter, ColumnName),
+        source_table.name.quote(&identifier_quoter, ColumnName)
+    );
+    let Some(rows) = source.get_text_rows(&count_sql).await? else {
+        warn!(
+            "Skipping table {} as it has no columns that can be copied, and the source cannot count its rows",
+            source_table.name
+        );
+        return Ok(());
+    };
+    let source_wait = started.elapsed();
+
+    let rows: u64 = rows
+        .first()
+        .and_then(|r| r.first())
+        .and_then(|c| c.as_deref())
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0);
+
+    if rows > 0 {
+        debug!(
+            "Inserting {} rows with default values into table {}, as it has no columns that can be copied",
+            rows, target_table.name
         );
-        let data = source
-            .get_data(source_schema, source_table, data_format)
-            .await?;
-
         destination
-            .apply_data(target_schema, target_table, data)
+            .apply_transactional_statement(&format!(
+                "insert into {}.{} select from generate_series(1, {});",
+                target_schema.name.quote(&identifier_quoter, ColumnName),
+                target_table.name.quote(&identifier_quoter, ColumnName),
+                rows
+            ))
             .await?;
     }
 
+    let total = started.elapsed();
+    table_timings.lock().await.push(TableCopyTiming {
+        schema_name: target_schema.name.clone(),
+        table_name: target_table.name.clone(),
+        total,
+        source_wait,
+        destination_wait: total.saturating_sub(source_wait),
+        rows,
+        ..default()
+    });
+
     Ok(())
 }
 
 /// Get instructions to apply after the data has been copied. This includes:
-/// * Creating indexes
+/// * Creating indexes, also on materialized views
 /// * Creating constraints
 /// * Creating triggers
+/// * Enabling row level security and creating the policies
 /// * Refreshing materialized views
 #[instrument(skip_all)]
 fn get_post_apply_statement_groups(
     definition: &PostgresDatabase,
     identifier_quoter: &IdentifierQuoter,
+    options: &CopyDataOptions,
     target_definition: &PostgresDatabase,
-) -> Vec<Vec<String>> {
+    rows_copied: bool,
+) -> Result<Vec<Vec<String>>> {
     let mut statements = Vec::new();
 
     for schema in &definition.schemas {
@@ -438,45 +2480,219 @@ fn get_post_apply_statement_groups(
 
         let mut group_1 = Vec::new();
         let mut group_2 = Vec::new();
+        let mut concurrent_groups: Vec<Vec<String>> = Vec::new();
+
+        // Indexes on partitioned tables created with `on only`, which don't cover all the
+        // partitions yet, so the indexes of the partitions have to be attached manually.
+        let partial_parent_indices: HashSet<&str> = schema
+            .tables
+            .iter()
+            .filter(|t| matches!(t.table_type, TableTypeDetails::PartitionedParentTable { .. }))
+            .flat_map(|t| &t.indices)
+            .filter(|i| i.not_valid)
+            .map(|i| i.name.as_str())
+            .collect();
+
         for table in &schema.tables {
             let existing_table = existing_schema.and_then(|s| s.try_get_table(&table.name));
+            let mut concurrent_index_count = 0;
 
             for index in &table.indices {
-                if index.index_constraint_type == PostgresIndexType::PrimaryKey {
+                // The indexes of partitions are created by postgres when the index of the
+                // partitioned table is created, so only one index exists per partition tree.
+                let attach_statement = match &index.parent_index {
+                    Some(parent_index) if !partial_parent_indices.contains(parent_index.as_str()) => {
+                        continue;
+                    }
+                    Some(_) => index.get_attach_statement(schema, identifier_quoter),
+                    None => None,
+                };
+
+                // Unique constraints of partitioned tables cannot use an existing index, so they
+                // create their index from their definition instead.
+                if matches!(table.table_type, TableTypeDetails::PartitionedParentTable { .. })
+                    && table.constraints.iter().any(|c| {
+                        matches!(c, PostgresConstraint::Unique(uk) if uk.unique_index_name == index.name)
+                    })
+                {
                     continue;
                 }
 
-                if existing_table.is_some_and(|t| t.indices.iter().any(|i| i.name == index.name)) {
-                    debug!(
-                        "Index {} on table {} already exists in destination",
-                        index.name, table.name
-                    );
+                // Primary keys are created together with the table, but might be missing from
+                // tables that already exists, if they were skipped by `skip_indexes`.
+                if index.index_constraint_type == PostgresIndexType::PrimaryKey
+                    && existing_table.is_none()
+                {
                     continue;
                 }
 
-                if !table.is_timescale_table() {
-                    let sql = index.get_create_index_command(schema, table, identifier_quoter);
-                    group_1.push(sql);
-                } 
+                // An index that differs from the source is dropped first, together with the
+                // constraint using it. The dropped constraint is added again right after the
+                // index, as the unique constraints are only created when missing.
+                let mut drop_statement = None;
+                if let (Some(existing_schema), Some(existing_table)) = (existing_schema, existing_table) {
+                    if let Some(existing_index) =
+                        existing_table.indices.iter().find(|i| i.name == index.name)
+                    {
+                        if existing_index.is_equivalent_to(index, options.strict_index_comparison) {
+                            debug!(
+                                "Index {} on table {} already exists in destination",
+                                index.name, table.name
+                            );
+                            if let Some(sql) = index.get_comment_change_statement(
+                                existing_index,
+                                schema,
+                                identifier_quoter,
+                            ) {
+                                group_2.push(sql);
+                            }
+                            // The index might exist without having been attached yet.
+                            if existing_index.parent_index.is_none() {
+                                if let Some(sql) = attach_statement {
+                                    group_2.push(sql);
+                                }
+                            }
+                            continue;
+                        }
+
+                        info!(
+                            "Index {} on table {} differs from the destination, recreating it",
+                            index.name, table.name
+                        );
+                        drop_statement = Some(existing_index.get_drop_statement(
+                            existing_schema,
+                            existing_table,
+                            identifier_quoter,
+                        ));
+                    }
+                }
+
+                let recreated_constraint = drop_statement.as_ref().and_then(|_| {
+                    let existing_table = existing_table?;
+                    table.constraints.iter().find_map(|c| match c {
+                        PostgresConstraint::Unique(uk)
+                            if uk.unique_index_name == index.name
+                                && existing_table
+                                    .constraints
+                                    .iter()
+                                    .any(|c| c.name() == uk.name) =>
+                        {
+                            Some(uk.get_create_statement(table, schema, identifier_quoter))
+                        }
+                        _ => None,
+                    })
+                });
+
+                // Hypertables get their indices created together with the table, unless the table
+                // already existed in the destination and was only converted to a hypertable.
+                if !table.is_timescale_table() || existing_table.is_some() {
+                    if let Some(sql) = attach_statement {
+                        group_2.push(sql);
+                    }
+
+                    // Indexes on partitioned tables cannot be created concurrently.
+                    if options.create_indexes_concurrently
+                        && !matches!(table.table_type, TableTypeDetails::PartitionedParentTable { .. })
+                    {
+                        if let Some(sql) = drop_statement {
+                            group_1.push(sql);
+                        }
+
+                        // Only one index can be built concurrently on a table at a time, so the
+                        // indices of each table are spread out over separate groups.
+                        if concurrent_groups.len() == concurrent_index_count {
+                            concurrent_groups.push(Vec::new());
+                        }
+                        concurrent_groups[concurrent_index_count].push(
+                            index.get_create_index_concurrently_command(
+                                schema,
+                                table,
+                                identifier_quoter,
+                            ),
+                        );
+                        concurrent_index_count += 1;
+
+                        if index.index_constraint_type != PostgresIndexType::PrimaryKey {
+                            if let Some(sql) = index.get_set_comment_statement(identifier_quoter) {
+                                group_2.push(sql);
+                            }
+                        }
+                        if let Some(sql) = recreated_constraint {
+                            group_2.push(sql);
+                        }
+                    } else {
+                        let mut sql = drop_statement.unwrap_or_default();
+                        if !sql.is_empty() {
+                            sql.push('\n');
+                        }
+                        sql.push_str(&index.get_create_index_command(schema, table, identifier_quoter));
+                        if let Some(constraint) = recreated_constraint {
+                            sql.push('\n');
+                            sql.push_str(&constraint);
+                        }
+                        group_1.push(sql);
+                    }
+                }
             }
         }
 
+        if options.indexes_only {
+            statements.push(group_1);
+            statements.extend(concurrent_groups);
+            statements.push(group_2);
+            continue;
+        }
+
         for sequence in &schema.sequences {
             let existing_sequence = existing_schema
                 .and_then(|s| s.sequences.iter().find(|seq| seq.name == sequence.name));
 
-            if existing_sequence.is_none() || sequence.is_internally_created {
-                group_1.push(sequence.get_create_statement(schema, identifier_quoter));
+            if sequence.is_internally_created {
+                // The sequences of identity columns are created together with the column, see
+                // IdentityOptions.
+                if existing_sequence.is_none() {
+                    if let Some(sql) =
+                        sequence.get_set_comment_statement(schema, identifier_quoter)
+                    {
+                        group_1.push(sql);
+                    }
+                }
+            } else if let Some(existing_sequence) = existing_sequence {
+                if let Some(sql) =
+                    sequence.get_alter_statement(existing_sequence, schema, identifier_quoter)
+                {
+                    group_1.push(sql);
+                } else {
+                    debug!("Sequence {} already exists in destination", sequence.name);
+                }
             } else {
-                debug!("Sequence {} already exists in destination", sequence.name);
+                group_1.push(sequence.get_create_statement(schema, identifier_quoter));
             }
-            if existing_sequence.is_none()
-                || existing_sequence.is_some_and(|s| s.last_value != sequence.last_value)
+            if let Some(existing_sequence) = existing_sequence {
+                if let Some(sql) =
+                    sequence.get_comment_change_statement(existing_sequence, schema, identifier_quoter)
+                {
+                    group_1.push(sql);
+                }
+            }
+            // The values are set after the data is copied, so sequences of identity columns
+            // continue after the copied rows.
+            if !options.schema_only
+                && (existing_sequence.is_none()
+                    || existing_sequence.is_some_and(|s| s.last_value != sequence.last_value))
             {
                 if let Some(sql) = sequence.get_set_value_statement(schema, identifier_quoter) {
                     group_2.push(sql);
                 }
             }
+
+            if !options.grant_sequences_to.is_empty() {
+                group_2.push(sequence.get_grant_usage_statement(
+                    schema,
+                    identifier_quoter,
+                    &options.grant_sequences_to,
+                ));
+            }
         }
 
         for table in &schema.tables {
@@ -486,6 +2702,21 @@ fn get_post_apply_statement_groups(
                 let target_column =
                     existing_table.and_then(|t| t.columns.iter().find(|c| c.name == column.name));
 
+                if let (Some(options), Some(target_options)) = (
+                    &column.identity_options,
+                    target_column.and_then(|c| c.identity_options.as_ref()),
+                ) {
+                    if !options.has_same_sequence_options(target_options) {
+                        if let Some(sql) = column.get_alter_identity_options_statement(
+                            table,
+                            schema,
+                            identifier_quoter,
+                        ) {
+                            group_1.push(sql);
+                        }
+                    }
+                }
+
                 if target_column.is_some_and(|c| c.default_value == column.default_value) {
                     debug!(
                         "Default value for column {} on table {} already matches destination",
@@ -494,6 +2725,14 @@ fn get_post_apply_statement_groups(
                     continue;
                 }
 
+                if is_default_set_by_parent(schema, existing_schema, table, column) {
+                    debug!(
+                        "Default value for column {} on partition {} is set by its parent",
+                        column.name, table.name
+                    );
+                    continue;
+                }
+
                 if let Some(sql) =
                     column.get_alter_table_set_default_statement(table, schema, identifier_quoter)
                 {
@@ -503,9 +2742,13 @@ fn get_post_apply_statement_groups(
         }
 
         statements.push(group_1);
+        statements.extend(concurrent_groups);
         statements.push(group_2);
     }
 
+    // The constraints of partitioned tables have to be created after the constraints of their
+    // partitions, so the existing indices of the partitions are attached instead of duplicated.
+    let mut partitioned_unique_constraints = Vec::new();
     for schema in &definition.schemas {
         let existing_schema = target_definition.try_get_schema(&schema.name);
 
@@ -524,7 +2767,10 @@ fn get_post_apply_statement_groups(
                         );
                         continue;
                     }
-                    if !table.is_timescale_table() {
+                    if let TableTypeDetails::PartitionedParentTable { .. } = &table.table_type {
+                        let sql = uk.get_create_statement(table, schema, identifier_quoter);
+                        partitioned_unique_constraints.push(sql);
+                    } else if !table.is_timescale_table() {
                         let sql = uk.get_create_statement(table, schema, identifier_quoter);
                         group_3.push(sql);
                     }
@@ -533,6 +2779,61 @@ fn get_post_apply_statement_groups(
         }
         statements.push(group_3);
     }
+    statements.push(partitioned_unique_constraints);
+
+    // Check constraints are normally created together with their table, but constraints that are
+    // not valid must not be validated against the copied rows, and tables that already exist
+    // might be missing constraints. Inherited tables and their parents share their constraints,
+    // so they are added one at a time.
+    if !options.indexes_only {
+        for schema in &definition.schemas {
+            let existing_schema = target_definition.try_get_schema(&schema.name);
+            for table in &schema.tables {
+                let existing_table = existing_schema.and_then(|s| s.try_get_table(&table.name));
+                for constraint in &table.constraints {
+                    let PostgresConstraint::Check(check) = constraint else {
+                        continue;
+                    };
+
+                    let Some(existing_table) = existing_table else {
+                        if check.not_valid {
+                            let sql = check.get_create_statement(table, schema, identifier_quoter);
+                            statements.push(vec![sql]);
+                        }
+                        continue;
+                    };
+
+                    let existing_check = existing_table.constraints.iter().find_map(|c| match c {
+                        PostgresConstraint::Check(c) if c.name == check.name => Some(c),
+                        _ => None,
+                    });
+
+                    match existing_check {
+                        Some(existing)
+                            if existing.check_clause == check.check_clause
+                                && existing.no_inherit == check.no_inherit =>
+                        {
+                            debug!(
+                                "Check constraint {} on table {} already exists in destination",
+                                check.name, table.name
+                            );
+                        }
+                        Some(existing) => {
+                            let mut sql =
+                                existing.get_drop_statement(table, schema, identifier_quoter);
+                            sql.push('\n');
+                            sql.push_str(&check.get_create_statement(table, schema, identifier_quoter));
+                            statements.push(vec![sql]);
+                        }
+                        None => {
+                            let sql = check.get_create_statement(table, schema, identifier_quoter);
+                            statements.push(vec![sql]);
+                        }
+                    }
+                }
+            }
+        }
+    }
 
     for schema in &definition.schemas {
         let existing_schema = target_definition.try_get_schema(&schema.name);
@@ -558,16 +2859,60 @@ fn get_post_apply_statement_groups(
         }
     }
 
+    // Materialized views are created without data, so their indices are cheap to create before
+    // the views are refreshed.
+    let mut view_indices = Vec::new();
+    for schema in &definition.schemas {
+        let existing_schema = target_definition.try_get_schema(&schema.name);
+
+        for view in &schema.views {
+            let existing_view =
+                existing_schema.and_then(|s| s.views.iter().find(|v| v.name == view.name));
+
+            for index in &view.indices {
+                if existing_view.is_some_and(|v| v.indices.iter().any(|i| i.name == index.name)) {
+                    debug!(
+                        "Index {} on materialized view {} already exists in destination",
+                        index.name, view.name
+                    );
+                    continue;
+                }
+
+                view_indices.push(index.get_create_view_index_command(
+                    schema,
+                    view,
+                    identifier_quoter,
+                ));
+            }
+        }
+    }
+    statements.push(view_indices);
+
+    if options.indexes_only {
+        return Ok(statements);
+    }
+
     let mut group_4 = Vec::new();
     for schema in &definition.schemas {
         let existing_schema = target_definition.try_get_schema(&schema.name);
 
         for trigger in &schema.triggers {
-            if existing_schema.is_some_and(|s| s.triggers.iter().any(|t| t.name == trigger.name)) {
+            if let Some(existing_trigger) =
+                existing_schema.and_then(|s| s.triggers.iter().find(|t| t.name == trigger.name))
+            {
                 debug!(
                     "Trigger {} on table {} already exists in destination",
                     trigger.name, trigger.table_name
                 );
+                if existing_trigger.table_name == trigger.table_name {
+                    if let Some(sql) = trigger.get_comment_change_statement(
+                        existing_trigger,
+                        schema,
+                        identifier_quoter,
+                    ) {
+                        group_4.push(sql);
+                    }
+                }
                 continue;
             }
 
@@ -577,15 +2922,150 @@ fn get_post_apply_statement_groups(
     }
     statements.push(group_4);
 
+    // The functions and tables used by the policies all exist by now.
+    let mut row_level_security = Vec::new();
     for schema in &definition.schemas {
-        for view in schema.views.iter().sort_by_dependencies() {
+        let existing_schema = target_definition.try_get_schema(&schema.name);
+
+        for table in &schema.tables {
+            let existing_table = existing_schema.and_then(|s| s.try_get_table(&table.name));
+            if existing_table.is_some_and(|t| {
+                t.rls_enabled == table.rls_enabled && t.rls_forced == table.rls_forced
+            }) {
+                continue;
+            }
+
+            row_level_security
+                .extend(table.get_row_level_security_statements(schema, identifier_quoter));
+        }
+
+        for policy in &schema.policies {
+            if existing_schema.is_some_and(|s| {
+                s.policies
+                    .iter()
+                    .any(|p| p.name == policy.name && p.table_name == policy.table_name)
+            }) {
+                debug!(
+                    "Policy {} on table {} already exists in destination",
+                    policy.name, policy.table_name
+                );
+                continue;
+            }
+
+            row_level_security.push(policy.get_create_statement(schema, identifier_quoter));
+        }
+    }
+    statements.push(row_level_security);
+
+    if options.refresh_materialized_views {
+        statements.extend(get_refresh_statement_groups(
+            definition,
+            identifier_quoter,
+            target_definition,
+            rows_copied,
+        )?);
+    }
+
+    #[cfg(feature = "timescale")]
+    statements.push(get_timescale_statements(
+        definition,
+        target_definition,
+        identifier_quoter,
+    ));
+
+    Ok(statements)
+}
+
+/// Checks if the default of a partition column is set by setting the default on the parent,
+/// which happens when the partition inherits the default and the default of the parent differs in
+/// the destination.
+fn is_default_set_by_parent(
+    schema: &PostgresSchema,
+    existing_schema: Option<&PostgresSchema>,
+    table: &PostgresTable,
+    column: &PostgresColumn,
+) -> bool {
+    let TableTypeDetails::PartitionedChildTable { parent_table, .. } = &table.table_type else {
+        return false;
+    };
+
+    if !column.inherits_default {
+        return false;
+    }
+
+    let Some(parent_column) = schema
+        .try_get_table(parent_table)
+        .and_then(|t| t.column(&column.name))
+    else {
+        return false;
+    };
+
+    existing_schema
+        .and_then(|s| s.try_get_table(parent_table))
+        .and_then(|t| t.column(&column.name))
+        .is_none_or(|c| c.default_value != parent_column.default_value)
+}
+
+/// The statements refreshing the materialized views that are stale in the destination, each in
+/// a group of its own, as a view has to be refreshed after the views it reads from.
+fn get_refresh_statement_groups(
+    definition: &PostgresDatabase,
+    identifier_quoter: &IdentifierQuoter,
+    target_definition: &PostgresDatabase,
+    rows_copied: bool,
+) -> Result<Vec<Vec<String>>> {
+    let mut statements = Vec::new();
+
+    // Materialized views can use materialized views in other schemas, so they
+    // have to be sorted across all schemas.
+    let views = definition
+        .schemas
+        .iter()
+        .flat_map(|s| {
+            s.views
+                .iter()
+                .map(move |v| PostgresThingWithDependencies::View(v, s))
+        })
+        .collect_vec();
+    let mut refreshed_views = Vec::new();
+    for view in views.iter().sort_by_dependencies()? {
+        if let PostgresThingWithDependencies::View(view, schema) = view {
+            if view.not_populated {
+                continue;
+            }
+
+            let existing_view = target_definition
+                .try_get_schema(&schema.name)
+                .and_then(|s| s.views.iter().find(|v| v.name == view.name));
+
+            // A view that is already populated in the destination is only stale if rows
+            // were copied, or if a view it reads from has to be refreshed.
+            let is_stale = existing_view.is_none_or(|v| v.not_populated)
+                || rows_copied
+                || view.depends_on.iter().any(|d| refreshed_views.contains(d));
+            if !is_stale {
+                continue;
+            }
+
             if let Some(sql) = view.get_refresh_sql(schema, identifier_quoter) {
                 statements.push(vec![sql]);
+                refreshed_views.push(view.object_id);
             }
         }
     }
 
-    let mut group_5 = Vec::new();
+    Ok(statements)
+}
+
+/// The timescale jobs, and the compression and retention policies of the hypertables, that
+/// are missing in the destination.
+#[cfg(feature = "timescale")]
+fn get_timescale_statements(
+    definition: &PostgresDatabase,
+    target_definition: &PostgresDatabase,
+    identifier_quoter: &IdentifierQuoter,
+) -> Vec<String> {
+    let mut statements = Vec::new();
     for job in &definition.timescale_support.user_defined_jobs {
         if target_definition
             .timescale_support
@@ -604,7 +3084,7 @@ fn get_post_apply_statement_groups(
             continue;
         }
 
-        group_5.push(job.get_create_sql(identifier_quoter));
+        statements.push(job.get_create_sql(identifier_quoter));
     }
 
     for schema in &definition.schemas {
@@ -642,27 +3122,99 @@ fn get_post_apply_statement_groups(
             if let Some(timescale_post) =
                 table.get_timescale_post_settings(schema, identifier_quoter)
             {
-                group_5.push(timescale_post);
+                statements.push(timescale_post);
             }
         }
     }
 
-    statements.push(group_5);
+    statements
+}
+
+/// The statements that can only be run by a superuser, for the functions in the destination
+/// that do not already have the attributes.
+fn get_superuser_statements(
+    definition: &PostgresDatabase,
+    identifier_quoter: &IdentifierQuoter,
+    target_definition: &PostgresDatabase,
+) -> Vec<String> {
+    let mut statements = Vec::new();
+
+    for schema in &definition.schemas {
+        let existing_schema = target_definition.try_get_schema(&schema.name);
+
+        for function in &schema.functions {
+            if existing_schema.is_some_and(|s| {
+                s.functions
+                    .iter()
+                    .any(|f| f.function_name == function.function_name && f.leak_proof)
+            }) {
+                continue;
+            }
+
+            if let Some(sql) = function.get_set_leak_proof_statement(schema, identifier_quoter) {
+                statements.push(sql);
+            }
+        }
+    }
 
     statements
 }
 
+/// Applies the statements from [get_superuser_statements] one at a time. If
+/// [CopyDataOptions::privilege_downgrade] is set, the statements the destination user is not
+/// allowed to run are skipped, and returned as warnings.
+#[instrument(skip_all)]
+async fn apply_superuser_statements<D: CopyDestination>(
+    destination: &mut D,
+    definition: &PostgresDatabase,
+    options: &CopyDataOptions,
+    target_definition: &PostgresDatabase,
+) -> Result<Vec<String>> {
+    let identifier_quoter = destination.get_identifier_quoter();
+    let mut warnings = Vec::new();
+
+    for statement in get_superuser_statements(definition, &identifier_quoter, target_definition) {
+        match destination
+            .apply_non_transactional_statement(&statement)
+            .await
+        {
+            Ok(()) => {}
+            Err(e)
+                if options.privilege_downgrade
+                    && e.postgres_code() == Some(&SqlState::INSUFFICIENT_PRIVILEGE) =>
+            {
+                let warning = format!(
+                    "Skipped `{}` as the destination user is not allowed to run it",
+                    statement
+                );
+                warn!("{}", warning);
+                warnings.push(warning);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(warnings)
+}
+
 /// Applies the structures generated in [get_post_apply_statement_groups] to the destination sequentially.
 #[instrument(skip_all)]
 async fn apply_post_copy_structure_sequential<D: CopyDestination>(
     destination: &mut D,
     definition: &PostgresDatabase,
+    options: &CopyDataOptions,
     target_definition: &PostgresDatabase,
+    rows_copied: bool,
 ) -> Result<()> {
     let identifier_quoter = destination.get_identifier_quoter();
 
-    let statement_groups =
-        get_post_apply_statement_groups(definition, &identifier_quoter, target_definition);
+    let statement_groups = get_post_apply_statement_groups(
+        definition,
+        &identifier_quoter,
+        options,
+        target_definition,
+        rows_copied,
+    )?;
 
     for group in statement_groups {
         for statement in group {
@@ -682,11 +3234,17 @@ async fn apply_post_copy_structure_parallel<D: CopyDestination + Sync + Clone>(
     definition: &PostgresDatabase,
     options: &CopyDataOptions,
     target_definition: &PostgresDatabase,
+    rows_copied: bool,
 ) -> Result<()> {
     let identifier_quoter = destination.get_identifier_quoter();
 
-    let statement_groups =
-        get_post_apply_statement_groups(definition, &identifier_quoter, target_definition);
+    let statement_groups = get_post_apply_statement_groups(
+        definition,
+        &identifier_quoter,
+        options,
+        target_definition,
+        rows_copied,
+    )?;
 
     for group in statement_groups {
         if group.is_empty() {
@@ -718,10 +3276,126 @@ async fn apply_post_copy_structure_parallel<D: CopyDestination + Sync + Clone>(
     Ok(())
 }
 
+/// Checks that the source and destination databases use the same encoding and locale.
+/// Copying between databases where these differ can silently mangle non-ASCII data
+/// or change the behaviour of unique constraints that depends on collation.
+///
+/// Returns the character encoding of the destination if it differs from the source.
+#[instrument(skip_all)]
+async fn check_database_encoding(
+    source: &impl CopySourceFactory,
+    destination: &impl CopyDestinationFactory<'_>,
+    options: &CopyDataOptions,
+) -> Result<Option<String>> {
+    let (Some(source_encoding), Some(destination_encoding)) = (
+        source.get_database_encoding().await?,
+        destination.get_database_encoding().await?,
+    ) else {
+        return Ok(None);
+    };
+
+    let mismatches = source_encoding.get_mismatches(&destination_encoding);
+    if mismatches.is_empty() {
+        return Ok(None);
+    }
+
+    if options.strict_encoding {
+        return Err(ElefantToolsError::EncodingMismatch(mismatches));
+    }
+
+    for mismatch in &mismatches {
+        warn!(
+            "Database encoding mismatch: {}. Non-ASCII data might be mangled, and constraints depending on collation might behave differently.",
+            mismatch
+        );
+    }
+
+    Ok(Some(destination_encoding.encoding).filter(|e| *e != source_encoding.encoding))
+}
+
+/// Checks that the data of the text columns in the source can be converted to the encoding of the
+/// destination, see [CopyDataOptions::preflight_encoding_check].
+#[instrument(skip_all)]
+async fn check_data_encoding(
+    source: &impl CopySourceFactory,
+    source_definition: &PostgresDatabase,
+    destination_encoding: &str,
+) -> Result<()> {
+    let Some(invalid_columns) = source
+        .find_invalidly_encoded_columns(source_definition, destination_encoding)
+        .await?
+    else {
+        warn!("The source cannot check the encoding of its data, so it is copied without checking it");
+        return Ok(());
+    };
+
+    if invalid_columns.is_empty() {
+        return Ok(());
+    }
+
+    Err(ElefantToolsError::InvalidlyEncodedColumns(invalid_columns))
+}
+
+/// Checks that the access methods of the tables exist in the destination, so the copy fails
+/// before anything is created instead of halfway through the tables.
+fn check_table_access_methods(
+    definition: &PostgresDatabase,
+    access_methods: &[String],
+) -> Result<()> {
+    for schema in &definition.schemas {
+        for table in &schema.tables {
+            if let Some(access_method) = &table.access_method {
+                if !access_methods.contains(access_method) {
+                    return Err(ElefantToolsError::TableAccessMethodNotFound {
+                        schema_name: schema.name.clone(),
+                        table_name: table.name.clone(),
+                        access_method: access_method.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Warns about extensions whose objects are copied, but not in a way the extension can keep using.
+fn warn_about_unsupported_extensions(definition: &PostgresDatabase, options: &CopyDataOptions) {
+    for warning in get_unsupported_extension_warnings(definition, options) {
+        warn!("{}", warning);
+    }
+}
+
+fn get_unsupported_extension_warnings(
+    definition: &PostgresDatabase,
+    options: &CopyDataOptions,
+) -> Vec<String> {
+    definition
+        .enabled_extensions
+        .iter()
+        .filter(|e| e.name == "pg_partman")
+        .map(|e| {
+            let mut warning = format!(
+                "pg_partman is installed in the schema {}. Its configuration tables reference the partitioned tables by oid, so pg_partman will not accept them in the destination, and partition maintenance will have to be set up again there. The existing partitions are copied as normal partitions.",
+                e.schema_name
+            );
+
+            if !options.exclude_schemas.contains(&e.schema_name) {
+                warning.push_str(&format!(
+                    " Exclude the schema {} to leave the configuration out of the copy.",
+                    e.schema_name
+                ));
+            }
+
+            warning
+        })
+        .collect()
+}
+
 /// Get the data format to use when copying data from the source to the destination, that both
 /// source and destination supports.
 #[instrument(skip_all)]
-async fn get_data_type(
+pub(crate) async fn get_data_type(
     source: &impl CopySourceFactory,
     destination: &impl CopyDestinationFactory<'_>,
     options: &CopyDataOptions,
@@ -729,9 +3403,11 @@ async fn get_data_type(
     let source_formats = source.supported_data_format().await?;
     let destination_formats = destination.supported_data_format().await?;
 
-    let overlap = source_formats
+    // Take the formats from the destination, as the destination decides the exact
+    // options for formats such as csv.
+    let overlap = destination_formats
         .iter()
-        .filter(|f| destination_formats.contains(f))
+        .filter(|f| source_formats.contains(f))
         .collect_vec();
 
     if overlap.is_empty()
@@ -0,0 +1,203 @@
+//! Lets code outside elefant-tools carry objects along with a copy that elefant-tools does not
+//! know about, such as the catalogs of in-house extensions. See
+//! [CopyDataOptions::introspection_extensions] and [CopyDataOptions::ddl_extensions].
+
+mod pg_cron;
+
+use crate::*;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use tracing::warn;
+
+pub use pg_cron::PgCronJobs;
+
+/// An object read by an [IntrospectionExtension], which is stored in
+/// [PostgresDatabase::custom_objects] so it is part of snapshots as well.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CustomObject {
+    /// The name of the extension that read the object, see [IntrospectionExtension::name].
+    pub extension: String,
+    /// The object, in whatever shape the extension wants.
+    pub data: serde_json::Value,
+}
+
+/// Reads custom objects from the source, after elefant-tools has introspected the rest of it.
+pub trait IntrospectionExtension: Debug + Send + Sync {
+    /// Identifies the objects of this extension, so the matching [DdlExtension] can find them.
+    fn name(&self) -> &str;
+
+    /// Reads the objects from the source database.
+    fn introspect<'a>(
+        &'a self,
+        connection: &'a PostgresClientWrapper,
+    ) -> BoxFuture<'a, Result<Vec<CustomObject>>>;
+}
+
+/// When the statements of a [DdlExtension] are applied, relative to the rest of the copy.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum DdlExtensionPhase {
+    /// After the tables, types and functions are created, but before the data is copied. The
+    /// statements are applied in the same transaction as the rest of the structure.
+    BeforeData,
+    /// After the data is copied, and the indexes, constraints and triggers are created.
+    #[default]
+    AfterData,
+}
+
+/// Creates the custom objects read by an [IntrospectionExtension] in the destination.
+///
+/// The statements are applied on every copy, also differential ones, so they should not fail if
+/// the objects already exist. They go through the [StatementInterceptor] like any other statement.
+pub trait DdlExtension: Debug + Send + Sync {
+    /// The name of the [IntrospectionExtension] whose objects this creates.
+    fn name(&self) -> &str;
+
+    /// When the statements are applied.
+    fn phase(&self) -> DdlExtensionPhase {
+        DdlExtensionPhase::AfterData
+    }
+
+    /// Generates the statements that create the objects. `custom_objects` are the objects of
+    /// this extension in `database`, which is the structure that is copied to the destination.
+    fn generate(
+        &self,
+        database: &PostgresDatabase,
+        custom_objects: &[&CustomObject],
+    ) -> Vec<DdlStatement>;
+}
+
+/// Reads the custom objects of the introspection extensions into the definition.
+pub(crate) async fn introspect_custom_objects<S: BaseCopyTarget>(
+    source: &S,
+    definition: &mut PostgresDatabase,
+    options: &CopyDataOptions,
+) -> Result<()> {
+    if options.introspection_extensions.is_empty() {
+        return Ok(());
+    }
+
+    let Some(connection) = source.get_postgres_connection() else {
+        warn!("The source is not a Postgres database, so the introspection extensions are not run");
+        return Ok(());
+    };
+
+    for extension in &options.introspection_extensions {
+        definition
+            .custom_objects
+            .extend(extension.introspect(connection).await?);
+    }
+
+    Ok(())
+}
+
+/// Applies the statements of the DDL extensions that belong in `phase`.
+pub(crate) async fn apply_extension_statements<D: CopyDestination>(
+    destination: &mut D,
+    definition: &PostgresDatabase,
+    options: &CopyDataOptions,
+    phase: DdlExtensionPhase,
+) -> Result<()> {
+    for statement in get_extension_statements(definition, options, phase) {
+        if statement.transactional {
+            destination
+                .apply_transactional_statement(&statement.sql)
+                .await?;
+        } else {
+            destination
+                .apply_non_transactional_statement(&statement.sql)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn get_extension_statements(
+    definition: &PostgresDatabase,
+    options: &CopyDataOptions,
+    phase: DdlExtensionPhase,
+) -> Vec<DdlStatement> {
+    options
+        .ddl_extensions
+        .iter()
+        .filter(|e| e.phase() == phase)
+        .flat_map(|extension| {
+            let custom_objects = definition
+                .custom_objects
+                .iter()
+                .filter(|o| o.extension == extension.name())
+                .collect::<Vec<_>>();
+            extension.generate(definition, &custom_objects)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct Grants {
+        phase: DdlExtensionPhase,
+    }
+
+    impl DdlExtension for Grants {
+        fn name(&self) -> &str {
+            "grants"
+        }
+
+        fn phase(&self) -> DdlExtensionPhase {
+            self.phase
+        }
+
+        fn generate(
+            &self,
+            _database: &PostgresDatabase,
+            custom_objects: &[&CustomObject],
+        ) -> Vec<DdlStatement> {
+            custom_objects
+                .iter()
+                .map(|o| DdlStatement::transactional(format!("grant {};", o.data.as_str().unwrap())))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn only_passes_the_objects_of_the_extension_in_its_phase() {
+        let definition = PostgresDatabase {
+            custom_objects: vec![
+                CustomObject {
+                    extension: "grants".to_string(),
+                    data: serde_json::json!("select on foo to bar"),
+                },
+                CustomObject {
+                    extension: "other".to_string(),
+                    data: serde_json::json!("something else"),
+                },
+            ],
+            ..default()
+        };
+        let options = CopyDataOptions {
+            ddl_extensions: vec![
+                Arc::new(Grants {
+                    phase: DdlExtensionPhase::BeforeData,
+                }),
+                Arc::new(Grants {
+                    phase: DdlExtensionPhase::AfterData,
+                }),
+            ],
+            ..default()
+        };
+
+        assert_eq!(
+            get_extension_statements(&definition, &options, DdlExtensionPhase::BeforeData),
+            vec![DdlStatement::transactional("grant select on foo to bar;")]
+        );
+        assert_eq!(
+            get_extension_statements(&definition, &options, DdlExtensionPhase::AfterData).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use crate::copy_extension::{CustomObject, DdlExtension, IntrospectionExtension};
+use crate::postgres_client_wrapper::FromRow;
+use crate::quoting::quote_value_string;
+use crate::*;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Row;
+use tracing::warn;
+
+/// Copies the jobs scheduled with [pg_cron](https://github.com/citusdata/pg_cron), which are
+/// stored in the `cron.job` table of the extension rather than as objects of their own.
+///
+/// Both the source and the destination have to be the database pg_cron is installed in, see
+/// `cron.database_name`. Jobs that run in the source database are scheduled to run in the
+/// destination database instead, while jobs that run in other databases keep running there.
+/// Only jobs with a name are copied, as scheduling a job with a name replaces the existing job of
+/// that name, so copying again does not schedule the jobs twice.
+///
+/// Add it to both [CopyDataOptions::introspection_extensions] and
+/// [CopyDataOptions::ddl_extensions].
+#[derive(Debug, Clone, Default)]
+pub struct PgCronJobs;
+
+const NAME: &str = "pg_cron_jobs";
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct PgCronJob {
+    name: String,
+    schedule: String,
+    command: String,
+    /// `None` if the job runs in the copied database.
+    database: Option<String>,
+    username: String,
+    active: bool,
+}
+
+struct PgCronJobRow {
+    name: Option<String>,
+    schedule: String,
+    command: String,
+    database: Option<String>,
+    username: String,
+    active: bool,
+}
+
+impl FromRow for PgCronJobRow {
+    fn from_row(row: Row) -> Result<Self> {
+        Ok(PgCronJobRow {
+            name: row.try_get(0)?,
+            schedule: row.try_get(1)?,
+            command: row.try_get(2)?,
+            database: row.try_get(3)?,
+            username: row.try_get(4)?,
+            active: row.try_get(5)?,
+        })
+    }
+}
+
+//language=postgresql
+const JOBS_QUERY: &str = r#"
+select jobname::text,
+       schedule::text,
+       command::text,
+       nullif(database, current_database())::text,
+       username::text,
+       active
+from cron.job
+order by jobname, jobid;
+"#;
+
+impl IntrospectionExtension for PgCronJobs {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn introspect<'a>(
+        &'a self,
+        connection: &'a PostgresClientWrapper,
+    ) -> BoxFuture<'a, Result<Vec<CustomObject>>> {
+        Box::pin(async move {
+            let is_installed = connection
+                .get_single_result::<bool>("select to_regclass('cron.job') is not null;")
+                .await?;
+            if !is_installed {
+                return Ok(Vec::new());
+            }
+
+            let mut jobs = Vec::new();
+            for row in connection.get_results::<PgCronJobRow>(JOBS_QUERY).await? {
+                let Some(name) = row.name else {
+                    warn!(
+                        "Skipping the pg_cron job '{}' scheduled at '{}', as only jobs with a name are copied",
+                        row.command, row.schedule
+                    );
+                    continue;
+                };
+
+                let job = PgCronJob {
+                    name,
+                    schedule: row.schedule,
+                    command: row.command,
+                    database: row.database,
+                    username: row.username,
+                    active: row.active,
+                };
+                jobs.push(CustomObject {
+                    extension: NAME.to_string(),
+                    data: serde_json::to_value(job)?,
+                });
+            }
+
+            Ok(jobs)
+        })
+    }
+}
+
+impl DdlExtension for PgCronJobs {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn generate(
+        &self,
+        _database: &PostgresDatabase,
+        custom_objects: &[&CustomObject],
+    ) -> Vec<DdlStatement> {
+        custom_objects
+            .iter()
+            .filter_map(|o| match serde_json::from_value::<PgCronJob>(o.data.clone()) {
+                Ok(job) => Some(job),
+                Err(e) => {
+                    warn!("Skipping a pg_cron job that could not be read: {}", e);
+                    None
+                }
+            })
+            .map(|job| {
+                DdlStatement::transactional(format!(
+                    "select cron.schedule_in_database({}, {}, {}, {}, {}, {});",
+                    quote_value_string(&job.name),
+                    quote_value_string(&job.schedule),
+                    quote_value_string(&job.command),
+                    job.database
+                        .as_deref()
+                        .map_or("current_database()".to_string(), quote_value_string),
+                    quote_value_string(&job.username),
+                    job.active
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use elefant_test_macros::pg_test;
+    use std::sync::Arc;
+
+    fn job(name: &str, database: Option<&str>) -> CustomObject {
+        CustomObject {
+            extension: NAME.to_string(),
+            data: serde_json::to_value(PgCronJob {
+                name: name.to_string(),
+                schedule: "0 3 * * *".to_string(),
+                command: "call cleanup('old')".to_string(),
+                database: database.map(|d| d.to_string()),
+                username: "app".to_string(),
+                active: true,
+            })
+            .unwrap(),
+        }
+    }
+
+    #[test]
+    fn schedules_the_jobs_in_the_destination() {
+        let nightly = job("nightly", None);
+        let reports = job("reports", Some("reporting"));
+
+        let statements = PgCronJobs.generate(&PostgresDatabase::default(), &[&nightly, &reports]);
+
+        assert_eq!(
+            statements,
+            vec![
+                DdlStatement::transactional("select cron.schedule_in_database('nightly', '0 3 * * *', 'call cleanup(''old'')', current_database(), 'app', true);"),
+                DdlStatement::transactional("select cron.schedule_in_database('reports', '0 3 * * *', 'call cleanup(''old'')', 'reporting', 'app', true);"),
+            ]
+        );
+    }
+
+    // A stand-in for the catalog of pg_cron, which has to be preloaded to be installed.
+    //language=postgresql
+    const CRON_CATALOG: &str = r#"
+    create schema cron;
+    create table cron.job(
+        jobid bigserial primary key,
+        schedule text not null,
+        command text not null,
+        nodename text not null default 'localhost',
+        nodeport int not null default 5432,
+        database text not null default current_database(),
+        username text not null default current_user,
+        active boolean not null default true,
+        jobname text
+    );
+    "#;
+
+    #[pg_test(arg(postgres = 16))]
+    async fn reads_the_named_jobs(helper: &TestHelper) {
+        helper.execute_not_query(CRON_CATALOG).await;
+        helper
+            .execute_not_query(
+                r#"
+            insert into cron.job(schedule, command, jobname, database, username, active)
+            values ('0 3 * * *', 'vacuum', 'nightly', current_database(), 'app', true),
+                   ('*/5 * * * *', 'select 1', 'ping', 'postgres', 'app', false),
+                   ('* * * * *', 'select 2', null, current_database(), 'app', true);
+            "#,
+            )
+            .await;
+
+        let objects = PgCronJobs.introspect(helper.get_conn()).await.unwrap();
+
+        assert_eq!(
+            objects
+                .iter()
+                .map(|o| serde_json::from_value::<PgCronJob>(o.data.clone()).unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                PgCronJob {
+                    name: "nightly".to_string(),
+                    schedule: "0 3 * * *".to_string(),
+                    command: "vacuum".to_string(),
+                    database: None,
+                    username: "app".to_string(),
+                    active: true,
+                },
+                PgCronJob {
+                    name: "ping".to_string(),
+                    schedule: "*/5 * * * *".to_string(),
+                    command: "select 1".to_string(),
+                    database: Some("postgres".to_string()),
+                    username: "app".to_string(),
+                    active: false,
+                },
+            ]
+        );
+    }
+
+    #[pg_test(arg(postgres = 16), arg(postgres = 16))]
+    async fn copies_the_jobs_with_the_database(source: &TestHelper, destination: &TestHelper) {
+        source.execute_not_query(CRON_CATALOG).await;
+        source
+            .execute_not_query(
+                r#"
+            create table events(id int primary key);
+
+            insert into cron.job(schedule, command, jobname, username)
+            values ('0 3 * * *', 'delete from events', 'cleanup', 'app');
+            "#,
+            )
+            .await;
+        destination.execute_not_query(CRON_CATALOG).await;
+        destination
+            .execute_not_query(
+                r#"
+            create function cron.schedule_in_database(job_name text, schedule text, command text, database text, username text, active boolean) returns bigint as $$
+                insert into cron.job(jobname, schedule, command, database, username, active)
+                values (job_name, schedule, command, database, username, active)
+                returning jobid;
+            $$ language sql;
+            "#,
+            )
+            .await;
+
+        let source_storage = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+        let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+            .await
+            .unwrap();
+
+        copy_data(
+            &source_storage,
+            &mut destination_storage,
+            CopyDataOptions {
+                exclude_schemas: vec!["cron".to_string()],
+                introspection_extensions: vec![Arc::new(PgCronJobs)],
+                ddl_extensions: vec![Arc::new(PgCronJobs)],
+                ..default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let jobs = destination
+            .get_results::<(String, String, String, String)>(
+                "select jobname, command, username, database from cron.job;",
+            )
+            .await;
+        assert_eq!(
+            jobs,
+            vec![(
+                "cleanup".to_string(),
+                "delete from events".to_string(),
+                "app".to_string(),
+                destination
+                    .get_single_result::<String>("select current_database();")
+                    .await
+            )]
+        );
+    }
+
+    #[pg_test(arg(postgres = 16))]
+    async fn reads_nothing_without_pg_cron(helper: &TestHelper) {
+        let objects = PgCronJobs.introspect(helper.get_conn()).await.unwrap();
+
+        assert_eq!(objects, vec![]);
+    }
+}
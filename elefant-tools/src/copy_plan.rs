@@ -0,0 +1,668 @@
+use crate::models::{PostgresDatabase, PostgresSchema, PostgresTable, TableTypeDetails};
+use crate::schema_reader::human_readable_bytes;
+use crate::storage::{DataFormat, SupportedParallelism};
+use crate::{CopyDataOptions, VerificationLevel};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
+
+/// What [crate::copy_data] will do, decided before anything is written to the destination.
+///
+/// Create it with [crate::plan_copy] and run it with [crate::execute_copy].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopyPlan {
+    /// Whether the tables are copied in parallel, as negotiated between the source and the destination.
+    pub parallelism: SupportedParallelism,
+    /// The data format both the source and the destination supports.
+    pub data_format: DataFormat,
+    /// Whether the source and the destination are on the same server, and the data is copied in
+    /// larger chunks because of it.
+    pub same_server: bool,
+    /// The extensions that are left out, as the destination user is not allowed to create them,
+    /// or they are not available in the destination. See [CopyDataOptions::privilege_downgrade].
+    pub skipped_extensions: Vec<String>,
+    /// Whether each extension of the source can be created in the destination. Empty if the
+    /// destination cannot tell.
+    pub extensions: Vec<PlannedExtension>,
+    /// The phases of the copy that will run, in order.
+    pub phases: Vec<CopyPhase>,
+    /// The tables with data to copy, in the order they are started in.
+    pub tables: Vec<PlannedTable>,
+    /// The tables which data is not copied, as it is larger than
+    /// [CopyDataOptions::max_table_data_size_bytes]. The tables are still created.
+    pub skipped_tables: Vec<SkippedTable>,
+    /// The size of the copied tables in the source, including their indexes and toast data.
+    /// `None` if no data is copied, or the source cannot tell.
+    pub estimated_data_size_bytes: Option<u64>,
+    /// Set if the destination might run out of space, see
+    /// [CopyDataOptions::destination_capacity_bytes].
+    pub capacity_warning: Option<String>,
+    pub(crate) options: CopyDataOptions,
+    /// The structure of the source, limited to what is copied.
+    pub(crate) source_definition: PostgresDatabase,
+    /// The structure the destination should end up with.
+    pub(crate) target_definition: PostgresDatabase,
+}
+
+/// A part of a copy. Which phases run depends on the [CopyDataOptions].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CopyPhase {
+    /// Creates the schemas, tables, functions, views and so on that the data is copied into.
+    PreDataStructure,
+    /// Copies the data of the tables.
+    Data,
+    /// Creates the indexes, constraints and triggers, after the data has been copied.
+    PostDataStructure,
+    /// Compares the copied data with the source, see [CopyDataOptions::verification].
+    Verification,
+    /// Swaps the staging schema with the existing schema, see [CopyDataOptions::staging_swap].
+    StagingSwap,
+}
+
+impl Display for CopyPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CopyPhase::PreDataStructure => write!(f, "structure before data"),
+            CopyPhase::Data => write!(f, "data"),
+            CopyPhase::PostDataStructure => write!(f, "structure after data"),
+            CopyPhase::Verification => write!(f, "verification"),
+            CopyPhase::StagingSwap => write!(f, "staging swap"),
+        }
+    }
+}
+
+/// An extension of the source, and whether it can be created in the destination.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlannedExtension {
+    pub name: String,
+    pub availability: ExtensionAvailability,
+}
+
+/// Whether an extension can be created in the destination, see [CopyPlan::extensions].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ExtensionAvailability {
+    /// The extension is installed in the destination already, so it is left alone.
+    Installed,
+    /// The extension is not installed, but the destination user can create it.
+    Installable,
+    /// The extension is available on the destination server, but only a superuser can create it.
+    SuperuserOnly,
+    /// The extension is not available on the destination server at all.
+    Unavailable,
+}
+
+impl Display for ExtensionAvailability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtensionAvailability::Installed => write!(f, "installed"),
+            ExtensionAvailability::Installable => write!(f, "installable"),
+            ExtensionAvailability::SuperuserOnly => write!(f, "superuser only"),
+            ExtensionAvailability::Unavailable => write!(f, "not available"),
+        }
+    }
+}
+
+/// A table which data is copied.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PlannedTable {
+    pub schema_name: String,
+    pub table_name: String,
+    /// The query the data is copied with, see [CopyDataOptions::table_select_overrides].
+    pub select_override: Option<String>,
+}
+
+/// A table which data is not copied, as it is larger than
+/// [CopyDataOptions::max_table_data_size_bytes].
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SkippedTable {
+    pub schema_name: String,
+    pub table_name: String,
+    /// The size of the data of the table in the source, including toast data but not indexes.
+    pub size_bytes: u64,
+}
+
+/// A table in the destination, together with the table in the source its data comes from.
+pub(crate) struct CopiedTable<'a> {
+    pub source_schema: &'a PostgresSchema,
+    pub source_table: &'a PostgresTable,
+    pub target_schema: &'a PostgresSchema,
+    pub target_table: &'a PostgresTable,
+}
+
+impl CopyPlan {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        parallelism: SupportedParallelism,
+        data_format: DataFormat,
+        same_server: bool,
+        skipped_extensions: Vec<String>,
+        extensions: Vec<PlannedExtension>,
+        skipped_tables: Vec<SkippedTable>,
+        estimated_data_size_bytes: Option<u64>,
+        capacity_warning: Option<String>,
+        source_definition: PostgresDatabase,
+        target_definition: PostgresDatabase,
+        options: CopyDataOptions,
+    ) -> Self {
+        let copies_data = !options.schema_only && !options.indexes_only;
+
+        let mut phases = Vec::new();
+        if !options.indexes_only {
+            phases.push(CopyPhase::PreDataStructure);
+        }
+        if copies_data {
+            phases.push(CopyPhase::Data);
+        }
+        phases.push(CopyPhase::PostDataStructure);
+        if copies_data && options.verification != VerificationLevel::None {
+            phases.push(CopyPhase::Verification);
+        }
+        if options.staging_swap.is_some() {
+            phases.push(CopyPhase::StagingSwap);
+        }
+
+        let tables = if copies_data {
+            get_tables_with_data(&source_definition, &target_definition, &skipped_tables)
+                .iter()
+                .map(|t| PlannedTable {
+                    schema_name: t.target_schema.name.clone(),
+                    table_name: t.target_table.name.clone(),
+                    select_override: options
+                        .get_table_select_override(t.source_schema, t.source_table)
+                        .map(|s| s.to_string()),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        CopyPlan {
+            parallelism,
+            data_format,
+            same_server,
+            skipped_extensions,
+            extensions,
+            phases,
+            tables,
+            skipped_tables,
+            estimated_data_size_bytes,
+            capacity_warning,
+            options,
+            source_definition,
+            target_definition,
+        }
+    }
+
+    /// Whether the phase will run.
+    pub fn has_phase(&self, phase: CopyPhase) -> bool {
+        self.phases.contains(&phase)
+    }
+}
+
+impl Display for CopyPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.parallelism {
+            SupportedParallelism::Sequential => writeln!(f, "Parallelism: sequential")?,
+            SupportedParallelism::Parallel => writeln!(
+                f,
+                "Parallelism: parallel, at most {} tables at a time",
+                self.options.get_max_parallel_or_1()
+            )?,
+        }
+
+        match &self.data_format {
+            DataFormat::Text { .. } => writeln!(f, "Data format: text")?,
+            DataFormat::PostgresBinary {
+                postgres_version: Some(version),
+            } => writeln!(f, "Data format: postgres binary ({})", version)?,
+            DataFormat::PostgresBinary {
+                postgres_version: None,
+            } => writeln!(f, "Data format: postgres binary")?,
+            DataFormat::Csv { .. } => writeln!(f, "Data format: csv")?,
+        }
+
+        if self.same_server {
+            writeln!(f, "Same server: data is copied in larger chunks")?;
+        }
+
+        if let Some(size) = self.estimated_data_size_bytes {
+            writeln!(
+                f,
+                "Estimated size of the copied tables: {}",
+                human_readable_bytes(size as i64)
+            )?;
+        }
+
+        if let Some(warning) = &self.capacity_warning {
+            writeln!(f, "Warning: {}", warning)?;
+        }
+
+        if !self.skipped_extensions.is_empty() {
+            writeln!(
+                f,
+                "Skipped extensions, as they cannot be created in the destination: {}",
+                self.skipped_extensions.join(", ")
+            )?;
+        }
+
+        if !self.extensions.is_empty() {
+            writeln!(
+                f,
+                "Extensions: {}",
+                self.extensions
+                    .iter()
+                    .map(|e| format!("{} ({})", e.name, e.availability))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+
+        writeln!(
+            f,
+            "Phases: {}",
+            self.phases
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+
+        if self.has_phase(CopyPhase::Data) {
+            writeln!(f, "Tables ({}), in copy order:", self.tables.len())?;
+            for table in &self.tables {
+                write!(f, "    {}.{}", table.schema_name, table.table_name)?;
+                if table.select_override.is_some() {
+                    write!(f, " (using a select override)")?;
+                }
+                writeln!(f)?;
+            }
+
+            if !self.skipped_tables.is_empty() {
+                writeln!(
+                    f,
+                    "Tables without data, as it is larger than {} ({}):",
+                    human_readable_bytes(
+                        self.options.max_table_data_size_bytes.unwrap_or_default() as i64
+                    ),
+                    self.skipped_tables.len()
+                )?;
+                for table in &self.skipped_tables {
+                    writeln!(
+                        f,
+                        "    {}.{} ({})",
+                        table.schema_name,
+                        table.table_name,
+                        human_readable_bytes(table.size_bytes as i64)
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Decides how the tables are copied. Parallelism is only used if both the source and the
+/// destination supports it, and more than one table is allowed to be copied at a time.
+pub(crate) fn negotiate_parallelism(
+    max_parallel: NonZeroUsize,
+    source: SupportedParallelism,
+    destination: SupportedParallelism,
+) -> SupportedParallelism {
+    if max_parallel.get() == 1 {
+        SupportedParallelism::Sequential
+    } else {
+        source.negotiate_parallelism(destination)
+    }
+}
+
+/// Decides whether an extension can be created in the destination. `available` is `None` if the
+/// destination cannot list the available extensions, in which case the extension is assumed to be.
+pub(crate) fn get_extension_availability(
+    name: &str,
+    installed: &[String],
+    available: Option<&[String]>,
+    superuser_only: &[String],
+) -> ExtensionAvailability {
+    let contains = |names: &[String]| names.iter().any(|n| n == name);
+
+    if contains(installed) {
+        ExtensionAvailability::Installed
+    } else if available.is_some_and(|available| !contains(available)) {
+        ExtensionAvailability::Unavailable
+    } else if contains(superuser_only) {
+        ExtensionAvailability::SuperuserOnly
+    } else {
+        ExtensionAvailability::Installable
+    }
+}
+
+/// Pairs up the tables in the target with the tables in the source their data is copied from,
+/// in the order the data is copied. Partitioned tables are left out, as their data is in the partitions.
+pub(crate) fn get_copied_tables<'a>(
+    source_definition: &'a PostgresDatabase,
+    target_definition: &'a PostgresDatabase,
+) -> Vec<CopiedTable<'a>> {
+    let mut tables = Vec::new();
+
+    for target_schema in &target_definition.schemas {
+        let Some(source_schema) = source_definition
+            .schemas
+            .iter()
+            .find(|s| s.object_id == target_schema.object_id)
+        else {
+            continue;
+        };
+
+        for target_table in &target_schema.tables {
+            if let TableTypeDetails::PartitionedParentTable { .. } = &target_table.table_type {
+                continue;
+            }
+
+            let Some(source_table) = source_schema
+                .tables
+                .iter()
+                .find(|t| t.object_id == target_table.object_id)
+            else {
+                continue;
+            };
+
+            tables.push(CopiedTable {
+                source_schema,
+                source_table,
+                target_schema,
+                target_table,
+            });
+        }
+    }
+
+    tables
+}
+
+/// Warns if the copied tables are not expected to fit in the destination, as the current size of
+/// the destination database plus the size of the copied tables in the source is larger than the
+/// capacity of the destination.
+pub(crate) fn get_capacity_warning(
+    estimated_data_size_bytes: u64,
+    destination_size_bytes: u64,
+    capacity_bytes: u64,
+) -> Option<String> {
+    let expected_size = destination_size_bytes.saturating_add(estimated_data_size_bytes);
+    if expected_size <= capacity_bytes {
+        return None;
+    }
+
+    Some(format!(
+        "The destination database uses {} and the copied tables use {} in the source, which is more than the {} available to the destination",
+        human_readable_bytes(destination_size_bytes as i64),
+        human_readable_bytes(estimated_data_size_bytes as i64),
+        human_readable_bytes(capacity_bytes as i64)
+    ))
+}
+
+/// The tables which data is copied, leaving out the tables that are too large to copy.
+pub(crate) fn get_tables_with_data<'a>(
+    source_definition: &'a PostgresDatabase,
+    target_definition: &'a PostgresDatabase,
+    skipped_tables: &[SkippedTable],
+) -> Vec<CopiedTable<'a>> {
+    get_copied_tables(source_definition, target_definition)
+        .into_iter()
+        .filter(|t| {
+            !skipped_tables.iter().any(|s| {
+                s.schema_name == t.target_schema.name && s.table_name == t.target_table.name
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default, ObjectId, PostgresColumn};
+    use std::collections::HashMap;
+
+    fn definition() -> PostgresDatabase {
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                object_id: ObjectId::new(1),
+                tables: vec![
+                    PostgresTable {
+                        name: "people".to_string(),
+                        object_id: ObjectId::new(2),
+                        columns: vec![PostgresColumn {
+                            name: "id".to_string(),
+                            data_type: "int4".to_string(),
+                            ..default()
+                        }],
+                        ..default()
+                    },
+                    PostgresTable {
+                        name: "events".to_string(),
+                        object_id: ObjectId::new(3),
+                        table_type: TableTypeDetails::PartitionedParentTable {
+                            partition_strategy: crate::TablePartitionStrategy::Range,
+                            default_partition_name: None,
+                            partition_columns: crate::PartitionedTableColumns::Columns(vec![
+                                "id".to_string(),
+                            ]),
+                        },
+                        ..default()
+                    },
+                    PostgresTable {
+                        name: "events_2024".to_string(),
+                        object_id: ObjectId::new(4),
+                        table_type: TableTypeDetails::PartitionedChildTable {
+                            parent_table: "events".to_string(),
+                            partition_expression: "FOR VALUES FROM (1) TO (10)".to_string(),
+                        },
+                        ..default()
+                    },
+                ],
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    fn plan(options: CopyDataOptions) -> CopyPlan {
+        CopyPlan::new(
+            SupportedParallelism::Parallel,
+            DataFormat::PostgresBinary {
+                postgres_version: None,
+            },
+            false,
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            None,
+            None,
+            definition(),
+            definition(),
+            options,
+        )
+    }
+
+    #[test]
+    fn decides_extension_availability() {
+        let installed = vec!["plpgsql".to_string()];
+        let available = vec![
+            "plpgsql".to_string(),
+            "pg_trgm".to_string(),
+            "dblink".to_string(),
+        ];
+        let superuser_only = vec!["dblink".to_string()];
+        let availability = |name: &str| {
+            get_extension_availability(name, &installed, Some(&available), &superuser_only)
+        };
+
+        assert_eq!(availability("plpgsql"), ExtensionAvailability::Installed);
+        assert_eq!(availability("pg_trgm"), ExtensionAvailability::Installable);
+        assert_eq!(availability("dblink"), ExtensionAvailability::SuperuserOnly);
+        assert_eq!(availability("postgis"), ExtensionAvailability::Unavailable);
+        assert_eq!(
+            get_extension_availability("postgis", &installed, None, &superuser_only),
+            ExtensionAvailability::Installable
+        );
+    }
+
+    #[test]
+    fn lists_the_extensions_with_their_availability() {
+        let mut plan = plan(default());
+        plan.extensions = vec![
+            PlannedExtension {
+                name: "pg_trgm".to_string(),
+                availability: ExtensionAvailability::Installed,
+            },
+            PlannedExtension {
+                name: "postgis".to_string(),
+                availability: ExtensionAvailability::Unavailable,
+            },
+        ];
+
+        assert!(plan
+            .to_string()
+            .contains("Extensions: pg_trgm (installed), postgis (not available)\n"));
+    }
+
+    #[test]
+    fn plans_tables_in_copy_order_without_partitioned_parents() {
+        let plan = plan(CopyDataOptions {
+            max_parallel: NonZeroUsize::new(4),
+            table_select_overrides: HashMap::from([(
+                "public.people".to_string(),
+                "select id from people where id > 10".to_string(),
+            )]),
+            verification: VerificationLevel::Sample { rows_per_table: 10 },
+            ..default()
+        });
+
+        assert_eq!(
+            plan.phases,
+            vec![
+                CopyPhase::PreDataStructure,
+                CopyPhase::Data,
+                CopyPhase::PostDataStructure,
+                CopyPhase::Verification
+            ]
+        );
+        assert_eq!(
+            plan.to_string(),
+            "\
+Parallelism: parallel, at most 4 tables at a time
+Data format: postgres binary
+Phases: structure before data, data, structure after data, verification
+Tables (2), in copy order:
+    public.people (using a select override)
+    public.events_2024
+"
+        );
+    }
+
+    #[test]
+    fn warns_when_the_destination_might_run_out_of_space() {
+        const GB: u64 = 1024 * 1024 * 1024;
+
+        assert_eq!(get_capacity_warning(40 * GB, 5 * GB, 50 * GB), None);
+        assert_eq!(get_capacity_warning(45 * GB, 5 * GB, 50 * GB), None);
+        assert_eq!(
+            get_capacity_warning(46 * GB, 5 * GB, 50 * GB),
+            Some("The destination database uses 5.0 GB and the copied tables use 46 GB in the source, which is more than the 50 GB available to the destination".to_string())
+        );
+        assert!(get_capacity_warning(u64::MAX, 1, 50 * GB).is_some());
+    }
+
+    #[test]
+    fn leaves_out_the_tables_that_are_too_large() {
+        let plan = CopyPlan::new(
+            SupportedParallelism::Sequential,
+            DataFormat::PostgresBinary {
+                postgres_version: None,
+            },
+            false,
+            Vec::new(),
+            Vec::new(),
+            vec![SkippedTable {
+                schema_name: "public".to_string(),
+                table_name: "events_2024".to_string(),
+                size_bytes: 3 * 1024 * 1024,
+            }],
+            Some(5 * 1024 * 1024),
+            get_capacity_warning(5 * 1024 * 1024, 512 * 1024, 4 * 1024 * 1024),
+            definition(),
+            definition(),
+            CopyDataOptions {
+                max_table_data_size_bytes: Some(1024 * 1024),
+                ..default()
+            },
+        );
+
+        assert_eq!(
+            plan.to_string(),
+            "\
+Parallelism: sequential
+Data format: postgres binary
+Estimated size of the copied tables: 5.0 MB
+Warning: The destination database uses 512 kB and the copied tables use 5.0 MB in the source, which is more than the 4.0 MB available to the destination
+Phases: structure before data, data, structure after data
+Tables (1), in copy order:
+    public.people
+Tables without data, as it is larger than 1.0 MB (1):
+    public.events_2024 (3.0 MB)
+"
+        );
+    }
+
+    #[test]
+    fn schema_only_and_indexes_only_skip_the_data() {
+        let schema_only = plan(CopyDataOptions {
+            schema_only: true,
+            verification: VerificationLevel::Sample { rows_per_table: 10 },
+            ..default()
+        });
+        assert_eq!(
+            schema_only.phases,
+            vec![CopyPhase::PreDataStructure, CopyPhase::PostDataStructure]
+        );
+        assert!(schema_only.tables.is_empty());
+
+        let indexes_only = plan(CopyDataOptions {
+            indexes_only: true,
+            ..default()
+        });
+        assert_eq!(indexes_only.phases, vec![CopyPhase::PostDataStructure]);
+        assert!(indexes_only.tables.is_empty());
+    }
+
+    #[test]
+    fn parallelism_requires_both_sides_and_more_than_one_table_at_a_time() {
+        let two = NonZeroUsize::new(2).unwrap();
+        let one = NonZeroUsize::new(1).unwrap();
+
+        assert_eq!(
+            negotiate_parallelism(
+                two,
+                SupportedParallelism::Parallel,
+                SupportedParallelism::Parallel
+            ),
+            SupportedParallelism::Parallel
+        );
+        assert_eq!(
+            negotiate_parallelism(
+                one,
+                SupportedParallelism::Parallel,
+                SupportedParallelism::Parallel
+            ),
+            SupportedParallelism::Sequential
+        );
+        assert_eq!(
+            negotiate_parallelism(
+                two,
+                SupportedParallelism::Parallel,
+                SupportedParallelism::Sequential
+            ),
+            SupportedParallelism::Sequential
+        );
+    }
+}
@@ -0,0 +1,398 @@
+use crate::copy_data::get_data_type;
+use crate::copy_timing::{StreamTimer, TimedStream};
+use crate::quoting::{AttemptedKeywordUsage, Quotable};
+use crate::storage::{CopyDestination, CopySource};
+use crate::*;
+use std::sync::Arc;
+use tracing::{debug, instrument};
+
+#[derive(Debug)]
+pub struct CopyTableOptions {
+    /// Force this data format to be used
+    pub data_format: Option<DataFormat>,
+
+    /// Create the table in the destination if it doesn't exist already.
+    pub create_if_missing: bool,
+
+    /// Create the indices and unique constraints of the table after the data has been copied.
+    /// Only applies if the table was created by the copy.
+    pub create_indices_after: bool,
+
+    /// Truncate the destination table before copying the data into it.
+    pub truncate_first: bool,
+}
+
+impl Default for CopyTableOptions {
+    fn default() -> Self {
+        Self {
+            data_format: None,
+            create_if_missing: true,
+            create_indices_after: true,
+            truncate_first: false,
+        }
+    }
+}
+
+/// What happened while copying a table.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct CopyTableReport {
+    /// The table did not exist in the destination and was created.
+    pub created_table: bool,
+    /// The destination table was truncated before the data was copied.
+    pub truncated: bool,
+    /// How many indices were created after the data was copied.
+    pub created_indices: usize,
+    /// How many rows were copied.
+    pub rows: u64,
+}
+
+/// Copies a single table, including the data, from the source to the destination.
+///
+/// Foreign keys, triggers and anything else that references other objects are not copied.
+/// Use [copy_data] if you need those.
+#[instrument(skip_all, fields(schema = schema, table = table))]
+pub async fn copy_table(
+    source: &PostgresClientWrapper,
+    destination: &PostgresClientWrapper,
+    schema: &str,
+    table: &str,
+    options: CopyTableOptions,
+) -> Result<CopyTableReport> {
+    let source_connection = source;
+    let destination_connection = destination;
+    let source = PostgresInstanceStorage::new(source_connection).await?;
+    let mut destination = PostgresInstanceStorage::new(destination_connection).await?;
+
+    let data_format = get_data_type(
+        &source,
+        &destination,
+        &CopyDataOptions {
+            data_format: options.data_format.clone(),
+            ..default()
+        },
+    )
+    .await?;
+
+    let source = source.create_sequential_source().await?;
+    let mut destination = destination.create_sequential_destination().await?;
+    let identifier_quoter = destination.get_identifier_quoter();
+
+    // Only the schema of the table is introspected, as the rest of the database is not copied.
+    let source_definition = SchemaReader::for_schema(source_connection, schema)
+        .introspect_database()
+        .await?;
+    let (source_schema, source_table) = source_definition
+        .try_get_schema(schema)
+        .and_then(|s| s.try_get_table(table).map(|t| (s, t)))
+        .ok_or_else(|| ElefantToolsError::TableNotFound {
+            schema_name: schema.to_string(),
+            table_name: table.to_string(),
+        })?;
+
+    let destination_definition = SchemaReader::for_schema(destination_connection, schema)
+        .introspect_database()
+        .await?;
+    let exists_in_destination = destination_definition
+        .try_get_schema(schema)
+        .and_then(|s| s.try_get_table(table))
+        .is_some();
+
+    let mut report = CopyTableReport::default();
+
+    if !exists_in_destination {
+        if !options.create_if_missing {
+            return Err(ElefantToolsError::TableNotFound {
+                schema_name: schema.to_string(),
+                table_name: table.to_string(),
+            });
+        }
+
+        destination.begin_transaction().await?;
+        destination
+            .apply_transactional_statement(&source_schema.get_create_statement(&identifier_quoter))
+            .await?;
+        destination
+            .apply_transactional_statement(
+                &source_table.get_create_statement(source_schema, &identifier_quoter),
+            )
+            .await?;
+        destination.commit_transaction().await?;
+        report.created_table = true;
+    } else if options.truncate_first {
+        destination
+            .apply_transactional_statement(&format!(
+                "truncate table {}.{};",
+                source_schema
+                    .name
+                    .quote(&identifier_quoter, AttemptedKeywordUsage::ColumnName),
+                source_table
+                    .name
+                    .quote(&identifier_quoter, AttemptedKeywordUsage::ColumnName)
+            ))
+            .await?;
+        report.truncated = true;
+    }
+
+    let data = source
+        .get_data(source_schema, source_table, &data_format)
+        .await?;
+    let timer = Arc::new(StreamTimer::default());
+    let data = TableData {
+        data: TimedStream::new(data.data, timer.clone(), &data.data_format),
+        data_format: data.data_format,
+        cleanup: data.cleanup,
+    };
+    destination
+        .apply_data(source_schema, source_table, data)
+        .await?;
+    report.rows = timer.rows();
+
+    if report.created_table {
+        for sequence in &source_schema.sequences {
+            let is_used_by_table = sequence.author_table.as_ref() == Some(&source_table.name)
+                || source_table.depends_on.contains(&sequence.object_id);
+
+            if !is_used_by_table {
+                continue;
+            }
+
+            // The sequences of identity columns are created together with the table.
+            if !sequence.is_internally_created {
+                destination
+                    .apply_transactional_statement(
+                        &sequence.get_create_statement(source_schema, &identifier_quoter),
+                    )
+                    .await?;
+            }
+            if let Some(sql) = sequence.get_set_value_statement(source_schema, &identifier_quoter)
+            {
+                destination.apply_transactional_statement(&sql).await?;
+            }
+        }
+
+        // Partitions get the defaults of their parent when they are created.
+        for column in source_table.columns.iter().filter(|c| !c.inherits_default) {
+            if let Some(sql) = column.get_alter_table_set_default_statement(
+                source_table,
+                source_schema,
+                &identifier_quoter,
+            ) {
+                destination.apply_transactional_statement(&sql).await?;
+            }
+        }
+
+        // Hypertables have their indices created together with the table.
+        if options.create_indices_after && !source_table.is_timescale_table() {
+            for index in &source_table.indices {
+                if index.index_constraint_type == PostgresIndexType::PrimaryKey {
+                    continue;
+                }
+
+                debug!("Creating index {}", index.name);
+                destination
+                    .apply_non_transactional_statement(&index.get_create_index_command(
+                        source_schema,
+                        source_table,
+                        &identifier_quoter,
+                    ))
+                    .await?;
+                report.created_indices += 1;
+            }
+
+            for constraint in &source_table.constraints {
+                if let PostgresConstraint::Unique(uk) = constraint {
+                    debug!("Creating unique constraint {}", uk.name);
+                    destination
+                        .apply_non_transactional_statement(&uk.get_create_statement(
+                            source_table,
+                            source_schema,
+                            &identifier_quoter,
+                        ))
+                        .await?;
+                }
+            }
+        }
+    }
+
+    destination.finish().await?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema_reader::tests::introspect_schema;
+    use crate::test_helpers;
+    use crate::test_helpers::TestHelper;
+    use elefant_test_macros::pg_test;
+    use itertools::Itertools;
+
+    const CUSTOMERS: &str = r#"
+        create table customers(
+            id serial primary key,
+            name text not null unique,
+            email text not null
+        );
+
+        create index customers_email_idx on customers(email);
+
+        insert into customers(name, email) values ('foo', 'foo@example.com'), ('bar', 'bar@example.com');
+
+        create table orders(id int primary key);
+    "#;
+
+    #[pg_test(arg(postgres = 15), arg(postgres = 15))]
+    async fn copies_single_table(source: &TestHelper, destination: &TestHelper) {
+        source.execute_not_query(CUSTOMERS).await;
+
+        let report = copy_table(
+            source.get_conn(),
+            destination.get_conn(),
+            "public",
+            "customers",
+            default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            report,
+            CopyTableReport {
+                created_table: true,
+                truncated: false,
+                created_indices: 2,
+                rows: 2,
+            }
+        );
+
+        let source_definition = introspect_schema(source).await;
+        let source_table = source_definition.schemas[0].try_get_table("customers").unwrap();
+        let destination_definition = introspect_schema(destination).await;
+        let destination_table = destination_definition.schemas[0]
+            .try_get_table("customers")
+            .unwrap();
+        assert_eq!(source_table.columns, destination_table.columns);
+        assert_eq!(
+            source_table.indices.iter().map(|i| &i.name).collect_vec(),
+            destination_table.indices.iter().map(|i| &i.name).collect_vec()
+        );
+
+        let tables = destination
+            .get_single_results::<String>(
+                "select table_name::text from information_schema.tables where table_schema = 'public';",
+            )
+            .await;
+        assert_eq!(tables, vec!["customers".to_string()]);
+
+        destination
+            .execute_not_query("insert into customers(name, email) values ('baz', 'baz@example.com');")
+            .await;
+
+        let items = destination
+            .get_results::<(i32, String)>("select id, name from customers order by id;")
+            .await;
+        assert_eq!(
+            items,
+            vec![
+                (1, "foo".to_string()),
+                (2, "bar".to_string()),
+                (3, "baz".to_string())
+            ]
+        );
+    }
+
+    #[pg_test(arg(postgres = 15), arg(postgres = 15))]
+    async fn only_creates_the_sequences_used_by_the_table(
+        source: &TestHelper,
+        destination: &TestHelper,
+    ) {
+        source
+            .execute_not_query(
+                r#"
+            create sequence id_seq;
+            create table orders(id serial primary key);
+            insert into orders default values;
+
+            create schema other;
+            create table other.orders(id int primary key default nextval('public.id_seq'));
+            "#,
+            )
+            .await;
+
+        copy_table(
+            source.get_conn(),
+            destination.get_conn(),
+            "public",
+            "orders",
+            default(),
+        )
+        .await
+        .unwrap();
+
+        let sequences = destination
+            .get_single_results::<String>(
+                "select sequence_schema || '.' || sequence_name from information_schema.sequences order by 1;",
+            )
+            .await;
+        assert_eq!(sequences, vec!["public.orders_id_seq".to_string()]);
+    }
+
+    #[pg_test(arg(postgres = 15), arg(postgres = 15))]
+    async fn truncates_existing_table(source: &TestHelper, destination: &TestHelper) {
+        source.execute_not_query(CUSTOMERS).await;
+        destination
+            .execute_not_query(
+                r#"
+        create table customers(id int, name text, email text);
+        insert into customers(id, name, email) values (42, 'old', 'old@example.com');
+        "#,
+            )
+            .await;
+
+        let report = copy_table(
+            source.get_conn(),
+            destination.get_conn(),
+            "public",
+            "customers",
+            CopyTableOptions {
+                truncate_first: true,
+                ..default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            report,
+            CopyTableReport {
+                created_table: false,
+                truncated: true,
+                created_indices: 0,
+                rows: 2,
+            }
+        );
+
+        let items = destination
+            .get_results::<(i32, String)>("select id, name from customers order by id;")
+            .await;
+        assert_eq!(items, vec![(1, "foo".to_string()), (2, "bar".to_string())]);
+    }
+
+    #[pg_test(arg(postgres = 15), arg(postgres = 15))]
+    async fn missing_table_is_an_error(source: &TestHelper, destination: &TestHelper) {
+        let result = copy_table(
+            source.get_conn(),
+            destination.get_conn(),
+            "public",
+            "does_not_exist",
+            default(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(ElefantToolsError::TableNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::row_counter::RowCounter;
+use crate::storage::DataFormat;
+use crate::Result;
+use bytes::Bytes;
+use futures::Stream;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Where the time went while copying the data of a single table.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TableCopyTiming {
+    pub schema_name: String,
+    pub table_name: String,
+    /// The total time spent copying the data of the table.
+    pub total: Duration,
+    /// Time spent waiting for the source, both for starting to read the data and for
+    /// each chunk of data.
+    pub source_wait: Duration,
+    /// The rest of the time, which is spent waiting for the destination to accept the data.
+    pub destination_wait: Duration,
+    /// How many bytes were read from the source.
+    pub bytes: u64,
+    /// How many chunks the data was read in.
+    pub chunks: u64,
+    /// How many rows were read from the source.
+    pub rows: u64,
+}
+
+/// Counters updated by [TimedStream]. Atomics are used so the counters can be read after the
+/// stream has been handed to the destination.
+#[derive(Debug, Default)]
+pub(crate) struct StreamTimer {
+    source_wait_nanos: AtomicU64,
+    bytes: AtomicU64,
+    chunks: AtomicU64,
+    rows: AtomicU64,
+}
+
+impl StreamTimer {
+    /// How many rows have been read from the stream so far.
+    pub(crate) fn rows(&self) -> u64 {
+        self.rows.load(Ordering::Relaxed)
+    }
+
+    /// How many bytes have been read from the stream so far.
+    pub(crate) fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn into_timing(
+        self: Arc<Self>,
+        schema_name: &str,
+        table_name: &str,
+        total: Duration,
+        start_wait: Duration,
+    ) -> TableCopyTiming {
+        let source_wait =
+            start_wait + Duration::from_nanos(self.source_wait_nanos.load(Ordering::Relaxed));
+
+        TableCopyTiming {
+            schema_name: schema_name.to_string(),
+            table_name: table_name.to_string(),
+            total,
+            source_wait,
+            destination_wait: total.saturating_sub(source_wait),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            chunks: self.chunks.load(Ordering::Relaxed),
+            rows: self.rows.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Wraps the data stream of a source, measuring how long is spent waiting for each chunk,
+/// and counting the bytes and rows in the chunks.
+///
+/// The waiting starts when the stream is first polled for a chunk, and ends when the chunk is ready.
+pub(crate) struct TimedStream<S> {
+    inner: Pin<Box<S>>,
+    timer: Arc<StreamTimer>,
+    row_counter: RowCounter,
+    waiting_since: Option<Instant>,
+}
+
+impl<S> TimedStream<S> {
+    pub(crate) fn new(inner: S, timer: Arc<StreamTimer>, data_format: &DataFormat) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            timer,
+            row_counter: RowCounter::new(data_format),
+            waiting_since: None,
+        }
+    }
+}
+
+impl<S: Stream<Item = Result<Bytes>>> Stream for TimedStream<S> {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let waiting_since = *self.waiting_since.get_or_insert_with(Instant::now);
+
+        let result = self.inner.as_mut().poll_next(cx);
+
+        if let Poll::Ready(item) = &result {
+            self.waiting_since = None;
+            self.timer
+                .source_wait_nanos
+                .fetch_add(waiting_since.elapsed().as_nanos() as u64, Ordering::Relaxed);
+
+            if let Some(Ok(bytes)) = item {
+                self.timer
+                    .bytes
+                    .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                self.timer.chunks.fetch_add(1, Ordering::Relaxed);
+
+                let rows = self.row_counter.count(bytes);
+                self.timer.rows.fetch_add(rows, Ordering::Relaxed);
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn counts_bytes_chunks_and_rows() {
+        let timer = Arc::new(StreamTimer::default());
+        let chunks = vec![Ok(Bytes::from("foo\n")), Ok(Bytes::from("bar\nbaz\n"))];
+        let stream = TimedStream::new(
+            futures::stream::iter(chunks),
+            timer.clone(),
+            &DataFormat::Text {
+                options: crate::default(),
+            },
+        );
+
+        let read = stream.collect::<Vec<_>>().await;
+        assert_eq!(read.len(), 2);
+
+        let timing = timer.into_timing(
+            "public",
+            "items",
+            Duration::from_secs(2),
+            Duration::from_secs(1),
+        );
+        assert_eq!(timing.bytes, 12);
+        assert_eq!(timing.chunks, 2);
+        assert_eq!(timing.rows, 3);
+        assert!(timing.source_wait >= Duration::from_secs(1));
+        assert_eq!(timing.destination_wait, timing.total - timing.source_wait);
+    }
+}
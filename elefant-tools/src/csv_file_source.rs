@@ -0,0 +1,375 @@
+use crate::quoting::{quote_value_string, AttemptedKeywordUsage, Quotable};
+use crate::schema_reader::SchemaReader;
+use crate::*;
+use bytes::Bytes;
+use futures::{pin_mut, SinkExt};
+use itertools::Itertools;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader};
+use tracing::{debug, instrument};
+
+/// Options for importing a csv file into a table.
+#[derive(Debug, Clone)]
+pub struct CsvFileOptions {
+    /// The character separating the values in a row.
+    pub delimiter: char,
+
+    /// The string that represents a null value. Defaults to an unquoted empty string,
+    /// which is also what Postgres uses.
+    pub null_string: Option<String>,
+
+    /// Truncate the table before loading the data into it.
+    pub truncate_first: bool,
+}
+
+impl Default for CsvFileOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            null_string: None,
+            truncate_first: false,
+        }
+    }
+}
+
+/// A csv file with a header row, that can be loaded into an existing table.
+///
+/// This does not implement [CopySource], as a csv file has no schema to offer, instead the
+/// header is validated against the table that the data is loaded into.
+pub struct CsvFileSource<R: AsyncBufRead + Unpin + Send> {
+    reader: R,
+    options: CsvFileOptions,
+}
+
+impl CsvFileSource<BufReader<File>> {
+    /// Opens the csv file at the specified path.
+    pub async fn open(path: impl AsRef<Path>, options: CsvFileOptions) -> Result<Self> {
+        let file = File::open(&path).await.with_context_path("open", &path)?;
+        Ok(Self::new(BufReader::new(file), options))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin + Send> CsvFileSource<R> {
+    pub fn new(reader: R, options: CsvFileOptions) -> Self {
+        Self { reader, options }
+    }
+
+    /// Loads the csv data into the specified table, returning how many rows were loaded.
+    ///
+    /// The header of the file is checked against the columns of the table before any data
+    /// is sent, and all columns of the table has to be present in the file.
+    #[instrument(skip_all, fields(schema = schema, table = table))]
+    pub async fn import_into_table(
+        mut self,
+        destination: &PostgresClientWrapper,
+        schema: &str,
+        table: &str,
+    ) -> Result<u64> {
+        let header = self.read_header().await?;
+        let header_columns = parse_csv_row(&header, self.options.delimiter);
+        debug!("Found columns {:?} in csv file", header_columns);
+
+        let definition = SchemaReader::for_schema(destination, schema)
+            .introspect_database()
+            .await?;
+        let (target_schema, target_table) = definition
+            .try_get_schema(schema)
+            .and_then(|s| s.try_get_table(table).map(|t| (s, t)))
+            .ok_or_else(|| ElefantToolsError::TableNotFound {
+                schema_name: schema.to_string(),
+                table_name: table.to_string(),
+            })?;
+
+        let table_columns = target_table
+            .get_writable_columns()
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+
+        let missing_columns = table_columns
+            .iter()
+            .filter(|c| !header_columns.contains(c))
+            .cloned()
+            .collect::<Vec<_>>();
+        let extra_columns = header_columns
+            .iter()
+            .filter(|c| !table_columns.contains(c))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !missing_columns.is_empty() || !extra_columns.is_empty() {
+            return Err(ElefantToolsError::CsvHeaderMismatch {
+                schema_name: schema.to_string(),
+                table_name: table.to_string(),
+                missing_columns,
+                extra_columns,
+            });
+        }
+
+        let destination_storage = PostgresInstanceStorage::new(destination).await?;
+        let identifier_quoter = destination_storage.get_identifier_quoter();
+        let table_name = format!(
+            "{}.{}",
+            target_schema
+                .name
+                .quote(&identifier_quoter, AttemptedKeywordUsage::ColumnName),
+            target_table
+                .name
+                .quote(&identifier_quoter, AttemptedKeywordUsage::ColumnName)
+        );
+
+        let mut copy_statement = format!(
+            "copy {} ({}) from stdin with (format csv, header {}, delimiter {}",
+            table_name,
+            identifier_quoter
+                .quote_iter(&header_columns, AttemptedKeywordUsage::ColumnName)
+                .join(", "),
+            // 'match' is only supported from Postgres 15. The header has already been validated
+            // above, so older versions just skip it.
+            if destination_storage.capabilities().supports_copy_header_match {
+                "match"
+            } else {
+                "true"
+            },
+            quote_value_string(&self.options.delimiter.to_string()),
+        );
+        if let Some(null_string) = &self.options.null_string {
+            copy_statement.push_str(", null ");
+            copy_statement.push_str(&quote_value_string(null_string));
+        }
+        copy_statement.push_str(");");
+
+        destination.execute_non_query("begin;").await?;
+
+        match self
+            .copy_into_table(destination, &table_name, &copy_statement, header)
+            .await
+        {
+            Ok(rows) => {
+                destination.execute_non_query("commit;").await?;
+                Ok(rows)
+            }
+            Err(e) => {
+                destination.execute_non_query("rollback;").await?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn copy_into_table(
+        &mut self,
+        destination: &PostgresClientWrapper,
+        table_name: &str,
+        copy_statement: &str,
+        header: String,
+    ) -> Result<u64> {
+        if self.options.truncate_first {
+            destination
+                .execute_non_query(&format!("truncate table {};", table_name))
+                .await?;
+        }
+
+        let sink = destination.copy_in::<Bytes>(copy_statement).await?;
+        pin_mut!(sink);
+
+        sink.feed(Bytes::from(header)).await.with_query(copy_statement)?;
+
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = self.reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+
+            sink.feed(Bytes::copy_from_slice(&buffer[..read]))
+                .await
+                .with_query(copy_statement)?;
+        }
+
+        let rows = sink.finish().await.with_query(copy_statement)?;
+
+        Ok(rows)
+    }
+
+    /// Reads the header row of the file. The header might span multiple lines if a column name
+    /// is quoted and contains a newline.
+    async fn read_header(&mut self) -> Result<String> {
+        let mut header = String::new();
+
+        loop {
+            let read = self.reader.read_line(&mut header).await?;
+            if read == 0 || header.matches('"').count().is_multiple_of(2) {
+                break;
+            }
+        }
+
+        if header.is_empty() {
+            return Err(ElefantToolsError::CsvFileMissingHeader);
+        }
+
+        Ok(header)
+    }
+}
+
+/// Splits a single csv row into its values, following the same quoting rules as Postgres.
+fn parse_csv_row(row: &str, delimiter: char) -> Vec<String> {
+    let row = row.trim_end_matches(['\r', '\n']);
+
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = row.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => {
+                values.push(std::mem::take(&mut current));
+            }
+            c => current.push(c),
+        }
+    }
+
+    values.push(current);
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers;
+    use crate::test_helpers::TestHelper;
+    use elefant_test_macros::pg_test;
+
+    const CUSTOMERS: &str = r#"
+        create table customers(
+            id int primary key,
+            name text not null,
+            note text
+        );
+    "#;
+
+    async fn import(helper: &TestHelper, content: &str, options: CsvFileOptions) -> Result<u64> {
+        CsvFileSource::new(content.as_bytes(), options)
+            .import_into_table(helper.get_conn(), "public", "customers")
+            .await
+    }
+
+    #[test]
+    fn parses_quoted_header() {
+        assert_eq!(
+            parse_csv_row("id,\"full, name\",\"say \"\"hi\"\"\"\r\n", ','),
+            vec!["id", "full, name", "say \"hi\""]
+        );
+        assert_eq!(parse_csv_row("a\tb\n", '\t'), vec!["a", "b"]);
+    }
+
+    #[pg_test(arg(postgres = 15))]
+    async fn imports_quoted_fields_and_newlines(helper: &TestHelper) {
+        helper.execute_not_query(CUSTOMERS).await;
+
+        let rows = import(
+            helper,
+            "note,id,name\n\"said \"\"hello\"\"\",1,\"Smith, John\"\n\"first line\nsecond line\",2,Jane\n,3,Bob\n",
+            default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(rows, 3);
+
+        let items = helper
+            .get_results::<(i32, String, Option<String>)>(
+                "select id, name, note from customers order by id;",
+            )
+            .await;
+        assert_eq!(
+            items,
+            vec![
+                (
+                    1,
+                    "Smith, John".to_string(),
+                    Some(r#"said "hello""#.to_string())
+                ),
+                (
+                    2,
+                    "Jane".to_string(),
+                    Some("first line\nsecond line".to_string())
+                ),
+                (3, "Bob".to_string(), None),
+            ]
+        );
+    }
+
+    #[pg_test(arg(postgres = 15))]
+    async fn imports_with_custom_delimiter_null_string_and_truncate(helper: &TestHelper) {
+        helper.execute_not_query(CUSTOMERS).await;
+        helper
+            .execute_not_query("insert into customers(id, name) values (42, 'old');")
+            .await;
+
+        import(
+            helper,
+            "id;name;note\n1;foo;NULL\n2;bar;\n",
+            CsvFileOptions {
+                delimiter: ';',
+                null_string: Some("NULL".to_string()),
+                truncate_first: true,
+            },
+        )
+        .await
+        .unwrap();
+
+        let items = helper
+            .get_results::<(i32, String, Option<String>)>(
+                "select id, name, note from customers order by id;",
+            )
+            .await;
+        assert_eq!(
+            items,
+            vec![
+                (1, "foo".to_string(), None),
+                (2, "bar".to_string(), Some("".to_string())),
+            ]
+        );
+    }
+
+    #[pg_test(arg(postgres = 15))]
+    async fn mismatched_header_is_reported_before_loading(helper: &TestHelper) {
+        helper.execute_not_query(CUSTOMERS).await;
+        helper
+            .execute_not_query("insert into customers(id, name) values (42, 'old');")
+            .await;
+
+        let result = import(
+            helper,
+            "id,full_name,note\n1,foo,\n",
+            CsvFileOptions {
+                truncate_first: true,
+                ..default()
+            },
+        )
+        .await;
+
+        match result {
+            Err(ElefantToolsError::CsvHeaderMismatch {
+                missing_columns,
+                extra_columns,
+                ..
+            }) => {
+                assert_eq!(missing_columns, vec!["name".to_string()]);
+                assert_eq!(extra_columns, vec!["full_name".to_string()]);
+            }
+            r => panic!("Expected a header mismatch, got {:?}", r),
+        }
+
+        let ids = helper
+            .get_single_results::<i32>("select id from customers;")
+            .await;
+        assert_eq!(ids, vec![42]);
+    }
+}
@@ -0,0 +1,585 @@
+use crate::postgres_client_wrapper::FromRow;
+use crate::quoting::{quote_value_string, AttemptedKeywordUsage, Quotable};
+use crate::{
+    IdentifierQuoter, PostgresClientWrapper, PostgresColumn, PostgresDatabase,
+    PostgresInstanceStorage, PostgresSchema, PostgresTable, Result, TableTypeDetails,
+};
+use std::fmt::{Display, Formatter};
+use tokio_postgres::Row;
+use tracing::{debug, instrument};
+
+/// The encoding and locale of a database.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DatabaseEncoding {
+    /// The character encoding, for example `UTF8`.
+    pub encoding: String,
+    /// The `LC_COLLATE` of the database, which decides the sort order of strings.
+    pub collate: String,
+    /// The `LC_CTYPE` of the database, which decides the character classification.
+    pub ctype: String,
+}
+
+impl FromRow for DatabaseEncoding {
+    fn from_row(row: Row) -> Result<Self> {
+        Ok(DatabaseEncoding {
+            encoding: row.try_get(0)?,
+            collate: row.try_get(1)?,
+            ctype: row.try_get(2)?,
+        })
+    }
+}
+
+impl DatabaseEncoding {
+    /// Reads the encoding and locale of the database the connection is connected to.
+    pub async fn read(connection: &PostgresClientWrapper) -> Result<Self> {
+        connection
+            .get_result(
+                //language=postgresql
+                r#"
+            select pg_encoding_to_char(encoding)::text, datcollate::text, datctype::text
+            from pg_database
+            where datname = current_database();
+            "#,
+            )
+            .await
+    }
+
+    /// Finds the settings that differs between the source and the destination.
+    pub fn get_mismatches(&self, destination: &DatabaseEncoding) -> Vec<EncodingMismatch> {
+        let mut mismatches = Vec::new();
+
+        let mut compare = |setting: &'static str, source: &String, destination: &String| {
+            if source != destination {
+                mismatches.push(EncodingMismatch {
+                    setting,
+                    source: source.clone(),
+                    destination: destination.clone(),
+                });
+            }
+        };
+
+        compare("encoding", &self.encoding, &destination.encoding);
+        compare("collate", &self.collate, &destination.collate);
+        compare("ctype", &self.ctype, &destination.ctype);
+
+        mismatches
+    }
+
+    /// Gets a statement that creates a new database with this encoding and locale.
+    pub fn get_create_database_statement(
+        &self,
+        database_name: &str,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        format!(
+            "create database {} template template0 encoding {} lc_collate {} lc_ctype {};",
+            database_name.quote(identifier_quoter, AttemptedKeywordUsage::Other),
+            quote_value_string(&self.encoding),
+            quote_value_string(&self.collate),
+            quote_value_string(&self.ctype)
+        )
+    }
+
+    /// Creates the database with this encoding and locale, unless a database with that name
+    /// exists already. Returns whether the database was created.
+    ///
+    /// The connection should be to another database on the same server, for example `postgres`.
+    pub async fn create_database_if_missing(
+        &self,
+        connection: &PostgresClientWrapper,
+        database_name: &str,
+    ) -> Result<bool> {
+        let exists = connection
+            .get_single_result::<bool>(&format!(
+                "select exists(select 1 from pg_database where datname = {});",
+                quote_value_string(database_name)
+            ))
+            .await?;
+
+        if exists {
+            return Ok(false);
+        }
+
+        let identifier_quoter = PostgresInstanceStorage::new(connection)
+            .await?
+            .get_identifier_quoter();
+        connection
+            .execute_non_query(
+                &self.get_create_database_statement(database_name, &identifier_quoter),
+            )
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Gets a query that fails if a value of the column cannot be converted to
+    /// `destination_encoding`, without copying the values anywhere.
+    ///
+    /// `SQL_ASCII` databases store text as the bytes the client sent without validating them, but
+    /// the data is copied out as UTF-8, so the values are checked to be valid UTF-8 instead.
+    pub(crate) fn get_encoding_check_query(
+        &self,
+        destination_encoding: &str,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+        column: &PostgresColumn,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let source_encoding = if self.encoding == "SQL_ASCII" {
+            "UTF8"
+        } else {
+            &self.encoding
+        };
+
+        format!(
+            "select count(convert(textsend({}::text), {}, {})) from {}.{};",
+            column.name.quote(identifier_quoter, AttemptedKeywordUsage::ColumnName),
+            quote_value_string(source_encoding),
+            quote_value_string(destination_encoding),
+            schema.name.quote(identifier_quoter, AttemptedKeywordUsage::ColumnName),
+            table.name.quote(identifier_quoter, AttemptedKeywordUsage::ColumnName),
+        )
+    }
+}
+
+/// Checks the text columns of all tables in the definition for data that cannot be converted to
+/// `destination_encoding`. Each column is checked with its own query, so the connection should not
+/// be in a transaction, as the first failing check would abort it.
+#[instrument(skip_all)]
+pub(crate) async fn find_invalidly_encoded_columns(
+    connection: &PostgresClientWrapper,
+    definition: &PostgresDatabase,
+    destination_encoding: &str,
+    identifier_quoter: &IdentifierQuoter,
+) -> Result<Vec<InvalidlyEncodedColumn>> {
+    let source_encoding = DatabaseEncoding::read(connection).await?;
+
+    let mut invalid_columns = Vec::new();
+    for schema in &definition.schemas {
+        for table in &schema.tables {
+            // The partitions are checked by themselves.
+            if let TableTypeDetails::PartitionedParentTable { .. } = table.table_type {
+                continue;
+            }
+
+            for column in table.get_writable_columns().filter(|c| can_contain_text(c)) {
+                let sql = source_encoding.get_encoding_check_query(
+                    destination_encoding,
+                    schema,
+                    table,
+                    column,
+                    identifier_quoter,
+                );
+                debug!("Checking encoding of {}.{}.{}", schema.name, table.name, column.name);
+
+                match connection.execute_non_query(&sql).await {
+                    Ok(()) => {}
+                    Err(e) if e.is_encoding_error() => {
+                        invalid_columns.push(InvalidlyEncodedColumn {
+                            schema_name: schema.name.clone(),
+                            table_name: table.name.clone(),
+                            column_name: column.name.clone(),
+                            message: e
+                                .postgres_db_error()
+                                .map(|e| e.message().to_string())
+                                .unwrap_or_else(|| e.to_string()),
+                        });
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    Ok(invalid_columns)
+}
+
+/// If the column is of a type stored as text, which has to be converted between encodings.
+fn can_contain_text(column: &PostgresColumn) -> bool {
+    matches!(
+        column.data_type.as_str(),
+        "text" | "varchar" | "bpchar" | "citext" | "json" | "jsonb" | "xml"
+    )
+}
+
+/// A single setting that differs between the source and the destination database.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EncodingMismatch {
+    pub setting: &'static str,
+    pub source: String,
+    pub destination: String,
+}
+
+impl Display for EncodingMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is '{}' in the source, but '{}' in the destination",
+            self.setting, self.source, self.destination
+        )
+    }
+}
+
+/// A text column with data that cannot be converted to the encoding of the destination, see
+/// [crate::CopyDataOptions::preflight_encoding_check].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InvalidlyEncodedColumn {
+    pub schema_name: String,
+    pub table_name: String,
+    pub column_name: String,
+    /// The error Postgres gave when converting the data, which includes the first invalid bytes.
+    pub message: String,
+}
+
+impl Display for InvalidlyEncodedColumn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}.{} ({})",
+            self.schema_name, self.table_name, self.column_name, self.message
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers;
+    use crate::test_helpers::TestHelper;
+    use crate::*;
+    use elefant_test_macros::pg_test;
+    use std::sync::{Arc, Mutex};
+
+    /// Wraps a destination to report a different encoding than the actual database has.
+    struct WithEncoding<T> {
+        inner: T,
+        encoding: DatabaseEncoding,
+    }
+
+    impl<T: BaseCopyTarget + Sync> BaseCopyTarget for WithEncoding<T> {
+        async fn supported_data_format(&self) -> Result<Vec<DataFormat>> {
+            self.inner.supported_data_format().await
+        }
+
+        async fn get_database_encoding(&self) -> Result<Option<DatabaseEncoding>> {
+            Ok(Some(self.encoding.clone()))
+        }
+    }
+
+    impl<'a, T: CopyDestinationFactory<'a> + Send + Sync> CopyDestinationFactory<'a>
+        for WithEncoding<T>
+    {
+        type SequentialDestination = T::SequentialDestination;
+        type ParallelDestination = T::ParallelDestination;
+
+        async fn create_destination(
+            &'a mut self,
+        ) -> Result<SequentialOrParallel<Self::SequentialDestination, Self::ParallelDestination>>
+        {
+            self.inner.create_destination().await
+        }
+
+        async fn create_sequential_destination(
+            &'a mut self,
+        ) -> Result<Self::SequentialDestination> {
+            self.inner.create_sequential_destination().await
+        }
+
+        fn supported_parallelism(&self) -> SupportedParallelism {
+            self.inner.supported_parallelism()
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn copy_with_destination_encoding(
+        source: &TestHelper,
+        destination: &TestHelper,
+        strict_encoding: bool,
+    ) -> Result<CopyDataReport> {
+        let mut destination_encoding = DatabaseEncoding::read(destination.get_conn())
+            .await
+            .unwrap();
+        destination_encoding.encoding = "LATIN1".to_string();
+
+        let source = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+        let mut destination = WithEncoding {
+            inner: PostgresInstanceStorage::new(destination.get_conn())
+                .await
+                .unwrap(),
+            encoding: destination_encoding,
+        };
+
+        copy_data(
+            &source,
+            &mut destination,
+            CopyDataOptions {
+                strict_encoding,
+                ..default()
+            },
+        )
+        .await
+    }
+
+    #[pg_test(arg(postgres = 15), arg(postgres = 15))]
+    async fn warns_about_encoding_mismatch(source: &TestHelper, destination: &TestHelper) {
+        source
+            .execute_not_query(
+                "create table people(name text); insert into people(name) values ('Æble');",
+            )
+            .await;
+
+        let logs = CapturedLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .with_ansi(false)
+            .finish();
+        let guard = tracing::subscriber::set_default(subscriber);
+
+        copy_with_destination_encoding(source, destination, false)
+            .await
+            .unwrap();
+
+        drop(guard);
+
+        let source_encoding = DatabaseEncoding::read(source.get_conn()).await.unwrap();
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            logs.contains(&format!(
+                "encoding is '{}' in the source, but 'LATIN1' in the destination",
+                source_encoding.encoding
+            )),
+            "Expected encoding warning in logs: {}",
+            logs
+        );
+
+        let names = destination
+            .get_single_results::<String>("select name from people;")
+            .await;
+        assert_eq!(names, vec!["Æble".to_string()]);
+    }
+
+    #[pg_test(arg(postgres = 15), arg(postgres = 15))]
+    async fn strict_encoding_fails_on_mismatch(source: &TestHelper, destination: &TestHelper) {
+        source
+            .execute_not_query("create table people(name text);")
+            .await;
+
+        let result = copy_with_destination_encoding(source, destination, true).await;
+
+        match result {
+            Err(ElefantToolsError::EncodingMismatch(mismatches)) => {
+                assert_eq!(mismatches.len(), 1);
+                assert_eq!(mismatches[0].setting, "encoding");
+                assert_eq!(mismatches[0].destination, "LATIN1");
+            }
+            r => panic!("Expected an encoding mismatch, got {:?}", r),
+        }
+
+        let tables = destination
+            .get_single_results::<String>(
+                "select table_name::text from information_schema.tables where table_schema = 'public';",
+            )
+            .await;
+        assert_eq!(tables, Vec::<String>::new());
+    }
+
+    fn encoding(encoding: &str, collate: &str, ctype: &str) -> DatabaseEncoding {
+        DatabaseEncoding {
+            encoding: encoding.to_string(),
+            collate: collate.to_string(),
+            ctype: ctype.to_string(),
+        }
+    }
+
+    #[test]
+    fn identical_encodings_have_no_mismatches() {
+        let source = encoding("UTF8", "en_US.UTF-8", "en_US.UTF-8");
+
+        assert_eq!(source.get_mismatches(&source.clone()), vec![]);
+    }
+
+    #[test]
+    fn reports_each_differing_setting() {
+        let source = encoding("UTF8", "en_US.UTF-8", "en_US.UTF-8");
+        let destination = encoding("SQL_ASCII", "C", "en_US.UTF-8");
+
+        let mismatches = source.get_mismatches(&destination);
+
+        assert_eq!(
+            mismatches,
+            vec![
+                EncodingMismatch {
+                    setting: "encoding",
+                    source: "UTF8".to_string(),
+                    destination: "SQL_ASCII".to_string(),
+                },
+                EncodingMismatch {
+                    setting: "collate",
+                    source: "en_US.UTF-8".to_string(),
+                    destination: "C".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "encoding is 'UTF8' in the source, but 'SQL_ASCII' in the destination"
+        );
+    }
+
+    #[test]
+    fn create_database_statement() {
+        let source = encoding("UTF8", "en_US.UTF-8", "C");
+
+        assert_eq!(
+            source.get_create_database_statement("my_db", &IdentifierQuoter::empty()),
+            "create database my_db template template0 encoding 'UTF8' lc_collate 'en_US.UTF-8' lc_ctype 'C';"
+        );
+    }
+
+    /// Creates a `SQL_ASCII` database next to the helper, with `café` written in latin1 in
+    /// `items`, which is not valid UTF-8.
+    async fn create_sql_ascii_source(helper: &TestHelper) -> TestHelper {
+        let source = helper
+            .create_another_database_with_encoding("sql_ascii_source", "SQL_ASCII")
+            .await;
+
+        source
+            .execute_not_query(
+                r#"
+            create table items(id int primary key, name text, description varchar(100));
+            insert into items(id, name, description) values (1, 'coffee', 'hot'), (2, E'caf\351', 'cold');
+
+            create table clean_items(name text);
+            insert into clean_items(name) values ('tea');
+            "#,
+            )
+            .await;
+
+        source
+    }
+
+    async fn get_public_tables(helper: &TestHelper) -> Vec<String> {
+        helper
+            .get_single_results::<String>(
+                "select table_name::text from information_schema.tables where table_schema = 'public';",
+            )
+            .await
+    }
+
+    #[pg_test(arg(postgres = 15))]
+    async fn preflight_encoding_check_finds_invalid_columns(destination: &TestHelper) {
+        let source = create_sql_ascii_source(destination).await;
+
+        let source_storage = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+        let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+            .await
+            .unwrap();
+
+        let result = copy_data(
+            &source_storage,
+            &mut destination_storage,
+            CopyDataOptions {
+                preflight_encoding_check: true,
+                ..default()
+            },
+        )
+        .await;
+
+        match result {
+            Err(ElefantToolsError::InvalidlyEncodedColumns(columns)) => {
+                assert_eq!(columns.len(), 1, "{:?}", columns);
+                assert_eq!(columns[0].schema_name, "public");
+                assert_eq!(columns[0].table_name, "items");
+                assert_eq!(columns[0].column_name, "name");
+                assert!(
+                    columns[0].message.contains("0xe9"),
+                    "Expected the invalid byte in the message: {}",
+                    columns[0].message
+                );
+            }
+            r => panic!("Expected invalidly encoded columns, got {:?}", r),
+        }
+
+        assert_eq!(get_public_tables(destination).await, Vec::<String>::new());
+
+        source.stop().await;
+    }
+
+    #[pg_test(arg(postgres = 15))]
+    async fn encoding_errors_during_copy_include_the_table(destination: &TestHelper) {
+        let source = create_sql_ascii_source(destination).await;
+
+        let source_storage = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+        let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+            .await
+            .unwrap();
+
+        let result = copy_data(&source_storage, &mut destination_storage, default()).await;
+
+        match result {
+            Err(ElefantToolsError::InvalidlyEncodedData {
+                schema_name,
+                table_name,
+                source: error,
+                ..
+            }) => {
+                assert_eq!(schema_name, "public");
+                assert_eq!(table_name, "items");
+                assert!(error.is_encoding_error(), "{:?}", error);
+            }
+            r => panic!("Expected invalidly encoded data, got {:?}", r),
+        }
+
+        source.stop().await;
+    }
+
+    #[test]
+    fn encoding_check_query() {
+        let column = PostgresColumn {
+            name: "name".to_string(),
+            data_type: "text".to_string(),
+            ..default()
+        };
+        let table = PostgresTable {
+            name: "items".to_string(),
+            columns: vec![column.clone()],
+            ..default()
+        };
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..default()
+        };
+        let quoter = IdentifierQuoter::empty();
+
+        assert_eq!(
+            encoding("SQL_ASCII", "C", "C").get_encoding_check_query("UTF8", &schema, &table, &column, &quoter),
+            "select count(convert(textsend(name::text), 'UTF8', 'UTF8')) from public.items;"
+        );
+        assert_eq!(
+            encoding("UTF8", "C", "C").get_encoding_check_query("LATIN1", &schema, &table, &column, &quoter),
+            "select count(convert(textsend(name::text), 'UTF8', 'LATIN1')) from public.items;"
+        );
+    }
+}
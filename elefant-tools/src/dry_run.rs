@@ -0,0 +1,404 @@
+use crate::copy_plan::CopiedTable;
+use crate::quoting::{quote_value_string, IdentifierQuoter};
+use crate::storage::{AsyncCleanup, CopyDestination, CopySource, TableData};
+use crate::verification::get_relation_name;
+use crate::*;
+use bytes::Bytes;
+use futures::Stream;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// What a copy with [CopyDataOptions::dry_run] would have done to the destination.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct DryRunReport {
+    /// The statements that would have been applied, in the order they were planned. Statements
+    /// applied in parallel are recorded in the order they were started.
+    pub statements: Vec<DdlStatement>,
+    /// The tables which data would have been copied, in copy order.
+    pub tables: Vec<DryRunTable>,
+}
+
+/// A table which data would have been copied by a dry run.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DryRunTable {
+    pub schema_name: String,
+    pub table_name: String,
+    /// The number of rows in the source table, as estimated by the statistics of the source.
+    /// `None` if the source has no statistics for the table, or cannot estimate it at all.
+    pub estimated_rows: Option<i64>,
+}
+
+/// How many objects of each kind the statements of a dry run would have created and dropped.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct DryRunSummary {
+    pub created_tables: usize,
+    pub created_indexes: usize,
+    pub created_functions: usize,
+    pub dropped_tables: usize,
+    pub dropped_indexes: usize,
+    pub dropped_functions: usize,
+}
+
+impl DryRunReport {
+    /// Counts the created and dropped objects, based on the statements that would have been
+    /// applied. Procedures and aggregates are counted as functions.
+    pub fn summary(&self) -> DryRunSummary {
+        let mut summary = DryRunSummary::default();
+
+        for statement in &self.statements {
+            let sql = statement.sql.trim_start();
+            let created = strip_prefix_word(sql, "create")
+                .map(|rest| strip_prefix_word(rest, "or replace").unwrap_or(rest));
+            let dropped = strip_prefix_word(sql, "drop");
+
+            if let Some(rest) = created {
+                let rest = strip_prefix_word(rest, "unique").unwrap_or(rest);
+                let rest = strip_prefix_word(rest, "unlogged").unwrap_or(rest);
+                match get_object_kind(rest) {
+                    Some(ObjectKind::Table) => summary.created_tables += 1,
+                    Some(ObjectKind::Index) => summary.created_indexes += 1,
+                    Some(ObjectKind::Function) => summary.created_functions += 1,
+                    None => {}
+                }
+            } else if let Some(rest) = dropped {
+                match get_object_kind(rest) {
+                    Some(ObjectKind::Table) => summary.dropped_tables += 1,
+                    Some(ObjectKind::Index) => summary.dropped_indexes += 1,
+                    Some(ObjectKind::Function) => summary.dropped_functions += 1,
+                    None => {}
+                }
+            }
+        }
+
+        summary
+    }
+}
+
+enum ObjectKind {
+    Table,
+    Index,
+    Function,
+}
+
+fn get_object_kind(sql: &str) -> Option<ObjectKind> {
+    if strip_prefix_word(sql, "table").is_some() {
+        Some(ObjectKind::Table)
+    } else if strip_prefix_word(sql, "index").is_some() {
+        Some(ObjectKind::Index)
+    } else if ["function", "procedure", "aggregate"]
+        .iter()
+        .any(|keyword| strip_prefix_word(sql, keyword).is_some())
+    {
+        Some(ObjectKind::Function)
+    } else {
+        None
+    }
+}
+
+/// Strips the words from the start of the statement, if they are followed by whitespace.
+fn strip_prefix_word<'s>(sql: &'s str, words: &str) -> Option<&'s str> {
+    let rest = sql.strip_prefix(words)?;
+
+    if rest.starts_with(char::is_whitespace) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+impl Display for DryRunReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Statements ({}):", self.statements.len())?;
+        for statement in &self.statements {
+            if !statement.transactional {
+                writeln!(f, "-- Outside a transaction")?;
+            }
+            writeln!(f, "{}", statement.sql.trim_end())?;
+        }
+
+        writeln!(f, "Tables ({}), in copy order:", self.tables.len())?;
+        for table in &self.tables {
+            match table.estimated_rows {
+                Some(rows) => writeln!(
+                    f,
+                    "    {}.{}: about {} rows",
+                    table.schema_name, table.table_name, rows
+                )?,
+                None => writeln!(
+                    f,
+                    "    {}.{}: unknown number of rows",
+                    table.schema_name, table.table_name
+                )?,
+            }
+        }
+
+        writeln!(f, "{}", self.summary())
+    }
+}
+
+impl Display for DryRunSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Would create {} tables, {} indexes and {} functions, and drop {} tables, {} indexes and {} functions",
+            self.created_tables,
+            self.created_indexes,
+            self.created_functions,
+            self.dropped_tables,
+            self.dropped_indexes,
+            self.dropped_functions
+        )
+    }
+}
+
+/// Records the statements instead of applying them to the wrapped destination, see
+/// [CopyDataOptions::dry_run]. Reading from the destination, such as introspecting it, is still
+/// passed through, so the statements are the ones a real copy would apply.
+///
+/// Clones of the destination record into the same list.
+#[derive(Clone)]
+pub struct DryRunDestination<D> {
+    destination: D,
+    statements: Option<Arc<Mutex<Vec<DdlStatement>>>>,
+}
+
+impl<D: CopyDestination> DryRunDestination<D> {
+    pub fn new(destination: D) -> Self {
+        DryRunDestination {
+            destination,
+            statements: Some(Arc::new(Mutex::new(Vec::new()))),
+        }
+    }
+
+    /// Records the statements into `statements`, or passes everything through to the destination
+    /// if `None`, when the copy is not a dry run.
+    pub(crate) fn with_statements(
+        destination: D,
+        statements: Option<Arc<Mutex<Vec<DdlStatement>>>>,
+    ) -> Self {
+        DryRunDestination {
+            destination,
+            statements,
+        }
+    }
+
+    /// The statements recorded so far, by this destination and its clones.
+    pub fn statements(&self) -> Vec<DdlStatement> {
+        match &self.statements {
+            // SAFE: Only panics if another thread panicked while pushing
+            Some(statements) => statements.lock().unwrap().clone(),
+            None => Vec::new(),
+        }
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.statements.is_some()
+    }
+
+    fn record(&self, statement: DdlStatement) -> bool {
+        match &self.statements {
+            Some(statements) => {
+                info!("Not applying statement in dry run: {}", statement.sql);
+                statements.lock().unwrap().push(statement); // SAFE: Only panics if another thread panicked while pushing
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<D: CopyDestination> CopyDestination for DryRunDestination<D> {
+    async fn apply_data<S: Stream<Item = Result<Bytes>> + Send, C: AsyncCleanup>(
+        &mut self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+        data: TableData<S, C>,
+    ) -> Result<()> {
+        if self.is_dry_run() {
+            data.apply_then_cleanup(|_| async { Ok(()) }).await
+        } else {
+            self.destination.apply_data(schema, table, data).await
+        }
+    }
+
+    async fn apply_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        if self.record(DdlStatement::transactional(statement)) {
+            Ok(())
+        } else {
+            self.destination
+                .apply_transactional_statement(statement)
+                .await
+        }
+    }
+
+    async fn apply_non_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        if self.record(DdlStatement::non_transactional(statement)) {
+            Ok(())
+        } else {
+            self.destination
+                .apply_non_transactional_statement(statement)
+                .await
+        }
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        if self.is_dry_run() {
+            Ok(())
+        } else {
+            self.destination.begin_transaction().await
+        }
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        if self.is_dry_run() {
+            Ok(())
+        } else {
+            self.destination.commit_transaction().await
+        }
+    }
+
+    fn get_identifier_quoter(&self) -> Arc<IdentifierQuoter> {
+        self.destination.get_identifier_quoter()
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        if self.is_dry_run() {
+            Ok(())
+        } else {
+            self.destination.finish().await
+        }
+    }
+
+    // The futures of the wrapped destination are returned directly, as the destination might not
+    // be Sync, in which case a future borrowing this wrapper would not be Send.
+    fn try_introspect(&self) -> impl Future<Output = Result<Option<PostgresDatabase>>> + Send {
+        self.destination.try_introspect()
+    }
+
+    fn has_data_in_table(
+        &self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+    ) -> impl Future<Output = Result<bool>> + Send {
+        self.destination.has_data_in_table(schema, table)
+    }
+
+    fn get_text_rows(
+        &self,
+        sql: &str,
+    ) -> impl Future<Output = Result<Option<Vec<Vec<Option<String>>>>>> + Send {
+        self.destination.get_text_rows(sql)
+    }
+}
+
+/// Estimates the number of rows of each table from the statistics of the source, instead of
+/// copying the data.
+pub(crate) async fn estimate_table_rows<S: CopySource>(
+    source: &S,
+    tables: &[CopiedTable<'_>],
+    identifier_quoter: &IdentifierQuoter,
+) -> Result<Vec<DryRunTable>> {
+    let mut estimates = Vec::with_capacity(tables.len());
+
+    for table in tables {
+        let relation =
+            get_relation_name(table.source_schema, table.source_table, identifier_quoter);
+
+        // Tables that have never been analyzed have -1 tuples since Postgres 14.
+        let estimated_rows = source
+            .get_text_rows(&format!(
+                "select reltuples::bigint::text from pg_class where oid = {}::regclass and reltuples >= 0;",
+                quote_value_string(&relation)
+            ))
+            .await?
+            .and_then(|rows| rows.into_iter().next())
+            .and_then(|row| row.into_iter().next().flatten())
+            .and_then(|r| r.parse::<i64>().ok());
+
+        estimates.push(DryRunTable {
+            schema_name: table.target_schema.name.clone(),
+            table_name: table.target_table.name.clone(),
+            estimated_rows,
+        });
+    }
+
+    Ok(estimates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_created_and_dropped_objects() {
+        let report = DryRunReport {
+            statements: vec![
+                DdlStatement::transactional("create table public.items (\n    id int4\n);"),
+                DdlStatement::transactional("create unique index items_idx on public.items using btree (id);"),
+                DdlStatement::non_transactional("create index concurrently items_name_idx on public.items using btree (name);"),
+                DdlStatement::transactional("create or replace function public.f () returns int4 as $$select 1$$ language sql;"),
+                DdlStatement::transactional("create procedure public.p () as $$select 1$$ language sql;"),
+                DdlStatement::transactional("drop table public.old_items;"),
+                DdlStatement::transactional("drop index public.old_idx;"),
+                DdlStatement::transactional("drop aggregate public.my_sum(int4);"),
+                DdlStatement::transactional("create tablespace_thing;"),
+                DdlStatement::transactional("alter table public.items add column name text;"),
+            ],
+            tables: vec![],
+        };
+
+        assert_eq!(
+            report.summary(),
+            DryRunSummary {
+                created_tables: 1,
+                created_indexes: 2,
+                created_functions: 2,
+                dropped_tables: 1,
+                dropped_indexes: 1,
+                dropped_functions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn formats_the_report() {
+        let report = DryRunReport {
+            statements: vec![
+                DdlStatement::transactional("create table public.items (\n    id int4\n);\n"),
+                DdlStatement::non_transactional(
+                    "create index concurrently items_idx on public.items using btree (id);",
+                ),
+            ],
+            tables: vec![
+                DryRunTable {
+                    schema_name: "public".to_string(),
+                    table_name: "items".to_string(),
+                    estimated_rows: Some(1200),
+                },
+                DryRunTable {
+                    schema_name: "public".to_string(),
+                    table_name: "new_items".to_string(),
+                    estimated_rows: None,
+                },
+            ],
+        };
+
+        assert_eq!(
+            report.to_string(),
+            "\
+Statements (2):
+create table public.items (
+    id int4
+);
+-- Outside a transaction
+create index concurrently items_idx on public.items using btree (id);
+Tables (2), in copy order:
+    public.items: about 1200 rows
+    public.new_items: unknown number of rows
+Would create 1 tables, 1 indexes and 0 functions, and drop 0 tables, 0 indexes and 0 functions
+"
+        );
+    }
+}
@@ -1,5 +1,11 @@
+use crate::database_encoding::{EncodingMismatch, InvalidlyEncodedColumn};
+use crate::schema_reader::human_readable_bytes;
 use crate::storage::DataFormat;
+use crate::TimescaleVersion;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
 use thiserror::Error;
+use tokio_postgres::error::{DbError, SqlState};
 
 /// All the errors that can occur in the elefant-tools library
 #[non_exhaustive]
@@ -8,7 +14,7 @@ pub enum ElefantToolsError {
     #[error("Error from postgres: `{0}`")]
     PostgresError(#[from] tokio_postgres::Error),
 
-    #[error("Error from postgres: `{query}` when executing query: `{source}`")]
+    #[error("Error from postgres: `{source}` when executing query: `{query}`")]
     PostgresErrorWithQuery {
         query: String,
         #[source]
@@ -16,9 +22,13 @@ pub enum ElefantToolsError {
     },
 
     #[error(
-        "Invalid number of results returned from query. Expected `{expected}`, got `{actual}`"
+        "Invalid number of results returned from query `{query}`. Expected `{expected}`, got `{actual}`"
     )]
-    InvalidNumberOfResults { actual: usize, expected: usize },
+    InvalidNumberOfResults {
+        actual: usize,
+        expected: usize,
+        query: String,
+    },
 
     #[error("Unknown constraint type '{0}'")]
     UnknownConstraintType(String),
@@ -41,6 +51,12 @@ pub enum ElefantToolsError {
     #[error("Unknown trigger level '{0}'")]
     UnknownTriggerLevel(String),
 
+    #[error("Unknown policy command '{0}'")]
+    UnknownPolicyCommand(String),
+
+    #[error("Unknown privilege object type '{0}'")]
+    UnknownPrivilegeObjectType(String),
+
     #[error("Unknown trigger timing '{0}'")]
     UnknownTriggerTiming(String),
 
@@ -76,9 +92,22 @@ pub enum ElefantToolsError {
     #[error("The table '{0}' is a partitioned table and has both partition columns and a partition expression")]
     PartitionedTableWithBothPartitionColumnsAndExpression(String),
 
-    #[error("Unsupported postgres version: {0}. Minimum supported version is 12")]
+    #[error("Unsupported postgres version: {0}. Minimum supported version is {}", crate::MIN_SUPPORTED_POSTGRES_VERSION)]
     UnsupportedPostgresVersion(i32),
 
+    #[error("Table '{schema_name}.{table_name}' was not found")]
+    TableNotFound {
+        schema_name: String,
+        table_name: String,
+    },
+
+    #[error("Column '{column_name}' of table '{schema_name}.{table_name}' was not found")]
+    ColumnNotFound {
+        schema_name: String,
+        table_name: String,
+        column_name: String,
+    },
+
     #[error("Invalid response from postgres when checking version")]
     InvalidPostgresVersionResponse,
 
@@ -91,6 +120,14 @@ pub enum ElefantToolsError {
     #[error("io error: `{0}`")]
     IoError(#[from] std::io::Error),
 
+    #[error("io error when trying to {operation} '{path}': `{source}`")]
+    IoErrorWithPath {
+        path: String,
+        operation: &'static str,
+        #[source]
+        source: std::io::Error,
+    },
+
     #[error("Data formats are not compatible between source and target. Supported by target: {supported_by_target:?}, supported by source: {supported_by_source:?}, required format: {required_format:?}")]
     DataFormatsNotCompatible {
         supported_by_target: Vec<DataFormat>,
@@ -98,6 +135,30 @@ pub enum ElefantToolsError {
         required_format: Option<DataFormat>,
     },
 
+    #[error("json error: `{0}`")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Snapshot has version {version}, but only version {supported_version} is supported. Recreate the snapshot with this version of elefant-tools")]
+    UnsupportedSnapshotVersion { version: u32, supported_version: u32 },
+
+    #[error("A row of table '{schema_name}.{table_name}' contains the chunk separator line of the sql file, which would split the data when the file is applied. Export again to use another chunk separator")]
+    ChunkSeparatorInData {
+        schema_name: String,
+        table_name: String,
+    },
+
+    #[error("A row of table '{schema_name}.{table_name}' contains an unescaped carriage return. The data of copy statements has to use line feeds only, which is how Postgres writes it")]
+    CarriageReturnInCopyData {
+        schema_name: String,
+        table_name: String,
+    },
+
+    #[error("The statement `{0}` cannot run inside a transaction block, but is part of a chunk with other statements. Move it to a chunk of its own, starting with `-- elefant:non-transactional`")]
+    NonTransactionalStatementInChunk(String),
+
+    #[error("The sql directory '{path}' cannot be applied: {reason}")]
+    InvalidSqlDirectory { path: String, reason: String },
+
     #[error("join error: `{0}`")]
     JoinError(#[from] tokio::task::JoinError),
 
@@ -106,7 +167,474 @@ pub enum ElefantToolsError {
 
     #[error("Aggregate function '{0}' is missing transition function")]
     AggregateFunctionMissingTransitionFunction(String),
+
+    #[error("'{0}' can only be used as an export destination, not as an import source")]
+    ExportOnlyStorage(String),
+
+    #[error("'{0}' can only be used as an import source, not as an export destination")]
+    ImportOnlyStorage(String),
+
+    #[error("The encoding or locale of the source and destination databases differ: {}", .0.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(", "))]
+    EncodingMismatch(Vec<EncodingMismatch>),
+
+    #[error("The data of these columns cannot be converted to the encoding of the destination: {}", .0.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", "))]
+    InvalidlyEncodedColumns(Vec<InvalidlyEncodedColumn>),
+
+    #[error("The data of table '{schema_name}.{table_name}'{} cannot be converted to the encoding of the destination. This happened after {rows_copied} rows ({byte_offset} bytes) of the table were copied: `{source}`", .column_name.as_ref().map(|c| format!(" in column '{c}'")).unwrap_or_default())]
+    InvalidlyEncodedData {
+        schema_name: String,
+        table_name: String,
+        column_name: Option<String>,
+        rows_copied: u64,
+        byte_offset: u64,
+        #[source]
+        source: Box<ElefantToolsError>,
+    },
+
+    #[error("The destination ran out of disk space while copying the data of table '{schema_name}.{table_name}'. {copied_tables} other tables with {} of data had been copied by then. Free up space in the destination, or leave out the largest tables: `{source}`", human_readable_bytes(*copied_bytes as i64))]
+    DestinationDiskFull {
+        schema_name: String,
+        table_name: String,
+        copied_tables: usize,
+        copied_bytes: u64,
+        #[source]
+        source: Box<ElefantToolsError>,
+    },
+
+    #[error("The operation was not confirmed")]
+    ConfirmationDeclined,
+
+    #[error("Invalid copy options: {0}")]
+    InvalidCopyTextOptions(String),
+
+    #[error("Invalid batch config: {0}")]
+    InvalidBatchConfig(String),
+
+    #[error("The batch jobs '{first_job}' and '{second_job}' both copy to the database {database}, so they cannot run concurrently. Set max_concurrent_jobs to 1 to run them in order")]
+    BatchJobsShareTarget {
+        first_job: String,
+        second_job: String,
+        database: String,
+    },
+
+    #[error("The csv file does not have a header row")]
+    CsvFileMissingHeader,
+
+    #[error("The csv header does not match the columns of table '{schema_name}.{table_name}'. Missing columns: {missing_columns:?}, unknown columns: {extra_columns:?}")]
+    CsvHeaderMismatch {
+        schema_name: String,
+        table_name: String,
+        missing_columns: Vec<String>,
+        extra_columns: Vec<String>,
+    },
+
+    #[error("{error}. Cleaning up after the error failed as well: {cleanup_error}")]
+    CleanupFailed {
+        #[source]
+        error: Box<ElefantToolsError>,
+        cleanup_error: Box<ElefantToolsError>,
+    },
+
+    #[error("Circular dependencies detected between: {}", .0.join(", "))]
+    CircularDependencies(Vec<String>),
+
+    #[error("Copying only the missing indexes requires a destination that supports structural inspection")]
+    IndexesOnlyRequiresIntrospection,
+
+    #[error("Resuming a copy requires a destination that supports structural inspection")]
+    ResumeRequiresIntrospection,
+
+    #[error("The resume file '{path}' was written while copying a source with another structure. Delete it to start the copy over")]
+    ResumeStateMismatch { path: String },
+
+    #[error("The select override for table '{schema_name}.{table_name}' has to return the columns {expected_columns:?} in that order. Missing columns: {missing_columns:?}, unknown columns: {extra_columns:?}")]
+    TableSelectOverrideMismatch {
+        schema_name: String,
+        table_name: String,
+        expected_columns: Vec<String>,
+        missing_columns: Vec<String>,
+        extra_columns: Vec<String>,
+    },
+
+    #[error("The excluded columns are used by: {}. Drop the dependent objects to skip them as well.", .0.join(", "))]
+    ExcludedColumnsHaveDependentObjects(Vec<String>),
+
+    #[error("The excluded tables are used by: {}. Drop the dependent objects to skip them as well.", .0.join(", "))]
+    ExcludedTablesHaveDependentObjects(Vec<String>),
+
+    #[error("No {kind} matching '{pattern}' was found")]
+    ObjectNotFound { kind: &'static str, pattern: String },
+
+    #[error("Excluded objects are required by objects that are copied: {}. Exclude the dependent objects as well.", .0.join(", "))]
+    ExcludedObjectsAreRequired(Vec<String>),
+
+    #[error("Column '{column_name}' of table '{schema_name}.{table_name}' no longer exists in the source, but is used by the views {views:?} in the destination. Allow destructive changes to drop and recreate the views.")]
+    ColumnDropBlockedByViews {
+        schema_name: String,
+        table_name: String,
+        column_name: String,
+        views: Vec<String>,
+    },
+
+    #[error("Table '{schema_name}.{table_name}' uses the table access method '{access_method}', which does not exist in the destination. Install the extension providing it in the destination first.")]
+    TableAccessMethodNotFound {
+        schema_name: String,
+        table_name: String,
+        access_method: String,
+    },
+
+    #[error("Tablespace '{0}' does not exist in the destination. Map it to a tablespace that does, or leave out the tablespaces.")]
+    TablespaceNotFound(String),
+
+    #[error("The roles {} are granted privileges, but do not exist in the destination. Create them first, or skip the privileges.", .0.join(", "))]
+    RolesNotFound(Vec<String>),
+
+    #[error("The row level security policies {} apply to roles that do not exist in the destination. Create the roles first.", .0.join(", "))]
+    PolicyRolesNotFound(Vec<String>),
+
+    #[error("The roles {} to grant the sequences to do not exist in the destination. Create them first.", .0.join(", "))]
+    SequenceGrantRolesNotFound(Vec<String>),
+
+    #[error("The copied data differs from the source in the tables: {}", .0.join(", "))]
+    VerificationFailed(Vec<String>),
+
+    #[error("{rows} rows in table '{schema_name}.{table_name}' reference each other in a cycle through the foreign key '{foreign_key_name}', so they cannot be ordered with parents first.")]
+    SelfReferenceCycle {
+        schema_name: String,
+        table_name: String,
+        foreign_key_name: String,
+        rows: u64,
+    },
+
+    #[error("Table '{schema_name}.{table_name}' has several foreign keys to itself ({foreign_key_names}), so its rows cannot be ordered with parents first.")]
+    MultipleSelfReferences {
+        schema_name: String,
+        table_name: String,
+        foreign_key_names: String,
+    },
+
+    #[error("Invalid column rename hint on line {line_number}: '{hint}'. Expected `schema.table.old_column=new_column`.")]
+    InvalidColumnRenameHint { line_number: usize, hint: String },
+
+    #[error("Table '{schema_name}.{table_name}' cannot be converted to a hypertable: {reason}")]
+    InvalidHypertableConversion {
+        schema_name: String,
+        table_name: String,
+        reason: String,
+    },
+
+    #[error("The database has the timescaledb extension installed, but elefant-tools was compiled without the `timescale` feature")]
+    TimescaleSupportDisabled,
+
+    #[error("The source uses TimescaleDB{} for {features}, but the timescaledb extension is not available in the destination. Install it in the destination, or strip the Timescale objects (`--strip-timescale`) to copy the hypertables as plain tables", .source_version.map(|v| format!(" {v}")).unwrap_or_default())]
+    TimescaleMissingInDestination {
+        source_version: Option<TimescaleVersion>,
+        features: String,
+    },
+
+    #[error("The source uses TimescaleDB {source_version} for {features}, but the destination has the older version {destination_version}. Update timescaledb in the destination to at least {source_version}, or strip the Timescale objects (`--strip-timescale`) to copy the hypertables as plain tables")]
+    TimescaleVersionMismatch {
+        source_version: TimescaleVersion,
+        destination_version: TimescaleVersion,
+        features: String,
+    },
+
+    #[error("The continuous aggregate '{schema_name}.{view_name}' uses time_bucket in a way plain Postgres has no equivalent for, so it cannot be copied as a plain materialized view. Leave it out of the copy instead")]
+    ContinuousAggregateNotStrippable {
+        schema_name: String,
+        view_name: String,
+    },
+
+    #[error("A staging swap cannot be used, as {0}")]
+    InvalidStagingSwap(String),
+
+    #[error("Schema '{schema_name}' cannot be swapped, as other objects depend on it: {}", .blockers.join(", "))]
+    StagingSwapBlocked {
+        schema_name: String,
+        blockers: Vec<String>,
+    },
+}
+
+/// A rough classification of what went wrong, for example to decide if retrying might help.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ErrorClass {
+    /// The options, the input or the databases are not valid for the operation.
+    /// Retrying will not help.
+    Validation,
+    /// Connecting to a database failed, or the connection was lost.
+    Connection,
+    /// Postgres rejected a statement, typically while creating the structure in the destination.
+    Ddl,
+    /// Reading or writing the data failed.
+    DataCopy,
+    /// The data was copied, but differs from the source when verified afterwards.
+    Verification,
+    /// Postgres returned something elefant-tools does not understand.
+    Internal,
+}
+
+impl Display for ErrorClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorClass::Validation => write!(f, "validation"),
+            ErrorClass::Connection => write!(f, "connection"),
+            ErrorClass::Ddl => write!(f, "ddl"),
+            ErrorClass::DataCopy => write!(f, "data copy"),
+            ErrorClass::Verification => write!(f, "verification"),
+            ErrorClass::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+impl ElefantToolsError {
+    /// The error code Postgres returned, if the error came from Postgres.
+    pub fn postgres_code(&self) -> Option<&SqlState> {
+        match self {
+            ElefantToolsError::PostgresError(e)
+            | ElefantToolsError::PostgresErrorWithQuery { source: e, .. } => e.code(),
+            _ => None,
+        }
+    }
+
+    /// The error Postgres returned, if the error came from Postgres.
+    pub fn postgres_db_error(&self) -> Option<&DbError> {
+        match self {
+            ElefantToolsError::PostgresError(e)
+            | ElefantToolsError::PostgresErrorWithQuery { source: e, .. } => e.as_db_error(),
+            _ => None,
+        }
+    }
+
+    /// If Postgres failed to convert text between two encodings, because the text is not valid
+    /// in the encoding it is supposed to be in, or contains characters the other encoding lacks.
+    pub fn is_encoding_error(&self) -> bool {
+        self.postgres_code().is_some_and(|code| {
+            code == &SqlState::CHARACTER_NOT_IN_REPERTOIRE
+                || code == &SqlState::UNTRANSLATABLE_CHARACTER
+        })
+    }
+
+    /// Classifies the error. See [ErrorClass].
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            ElefantToolsError::PostgresError(e) => classify_postgres_error(e),
+            ElefantToolsError::PostgresErrorWithQuery { source, .. } => {
+                classify_postgres_error(source)
+            }
+            ElefantToolsError::IoError(_)
+            | ElefantToolsError::IoErrorWithPath { .. }
+            | ElefantToolsError::InvalidlyEncodedData { .. }
+            | ElefantToolsError::ChunkSeparatorInData { .. }
+            | ElefantToolsError::CarriageReturnInCopyData { .. } => ErrorClass::DataCopy,
+            ElefantToolsError::VerificationFailed(_) => ErrorClass::Verification,
+            ElefantToolsError::CleanupFailed { error, .. } => error.class(),
+            ElefantToolsError::DestinationDiskFull { source, .. } => source.class(),
+            ElefantToolsError::UnsupportedPostgresVersion(_)
+            | ElefantToolsError::TableNotFound { .. }
+            | ElefantToolsError::ColumnNotFound { .. }
+            | ElefantToolsError::DataFormatsNotCompatible { .. }
+            | ElefantToolsError::ExportOnlyStorage(_)
+            | ElefantToolsError::ImportOnlyStorage(_)
+            | ElefantToolsError::EncodingMismatch(_)
+            | ElefantToolsError::InvalidlyEncodedColumns(_)
+            | ElefantToolsError::ConfirmationDeclined
+            | ElefantToolsError::InvalidCopyTextOptions(_)
+            | ElefantToolsError::InvalidBatchConfig(_)
+            | ElefantToolsError::BatchJobsShareTarget { .. }
+            | ElefantToolsError::CsvFileMissingHeader
+            | ElefantToolsError::CsvHeaderMismatch { .. }
+            | ElefantToolsError::CircularDependencies(_)
+            | ElefantToolsError::IndexesOnlyRequiresIntrospection
+            | ElefantToolsError::ResumeRequiresIntrospection
+            | ElefantToolsError::ResumeStateMismatch { .. }
+            | ElefantToolsError::TableSelectOverrideMismatch { .. }
+            | ElefantToolsError::ExcludedColumnsHaveDependentObjects(_)
+            | ElefantToolsError::ExcludedTablesHaveDependentObjects(_)
+            | ElefantToolsError::ObjectNotFound { .. }
+            | ElefantToolsError::ExcludedObjectsAreRequired(_)
+            | ElefantToolsError::ColumnDropBlockedByViews { .. }
+            | ElefantToolsError::TableAccessMethodNotFound { .. }
+            | ElefantToolsError::TablespaceNotFound(_)
+            | ElefantToolsError::RolesNotFound(_)
+            | ElefantToolsError::PolicyRolesNotFound(_)
+            | ElefantToolsError::SequenceGrantRolesNotFound(_)
+            | ElefantToolsError::SelfReferenceCycle { .. }
+            | ElefantToolsError::MultipleSelfReferences { .. }
+            | ElefantToolsError::InvalidColumnRenameHint { .. }
+            | ElefantToolsError::InvalidHypertableConversion { .. }
+            | ElefantToolsError::TimescaleSupportDisabled
+            | ElefantToolsError::TimescaleMissingInDestination { .. }
+            | ElefantToolsError::TimescaleVersionMismatch { .. }
+            | ElefantToolsError::ContinuousAggregateNotStrippable { .. }
+            | ElefantToolsError::InvalidStagingSwap(_)
+            | ElefantToolsError::StagingSwapBlocked { .. }
+            | ElefantToolsError::JsonError(_)
+            | ElefantToolsError::UnsupportedSnapshotVersion { .. }
+            | ElefantToolsError::InvalidSqlDirectory { .. }
+            | ElefantToolsError::NonTransactionalStatementInChunk(_) => ErrorClass::Validation,
+            ElefantToolsError::InvalidNumberOfResults { .. }
+            | ElefantToolsError::UnknownConstraintType(_)
+            | ElefantToolsError::UnknownForeignKeyAction(_)
+            | ElefantToolsError::UnknownFunctionKind(_)
+            | ElefantToolsError::UnknownVolatility(_)
+            | ElefantToolsError::UnknownParallel(_)
+            | ElefantToolsError::UnknownAggregateFinalFunctionModify(_)
+            | ElefantToolsError::UnknownTriggerLevel(_)
+            | ElefantToolsError::UnknownPolicyCommand(_)
+            | ElefantToolsError::UnknownPrivilegeObjectType(_)
+            | ElefantToolsError::UnknownTriggerTiming(_)
+            | ElefantToolsError::UnknownTriggerEvent(_)
+            | ElefantToolsError::UnknownColumnIdentity(_)
+            | ElefantToolsError::InvalidTableType(_)
+            | ElefantToolsError::InvalidKeywordType(_)
+            | ElefantToolsError::InvalidTablePartitioningStrategy(_)
+            | ElefantToolsError::PartitionedTableWithoutParent(_)
+            | ElefantToolsError::PartitionedTableHasMultipleParent { .. }
+            | ElefantToolsError::PartitionedTableWithoutExpression(_)
+            | ElefantToolsError::PartitionedTableWithoutPartitionColumns(_)
+            | ElefantToolsError::PartitionedTableWithBothPartitionColumnsAndExpression(_)
+            | ElefantToolsError::InvalidPostgresVersionResponse
+            | ElefantToolsError::HypertableDimensionWithoutInterval { .. }
+            | ElefantToolsError::JoinError(_)
+            | ElefantToolsError::AggregateFunctionMissingTransitionType(_)
+            | ElefantToolsError::AggregateFunctionMissingTransitionFunction(_) => {
+                ErrorClass::Internal
+            }
+        }
+    }
+}
+
+/// Classifies errors from postgres by their SQLSTATE. Errors without one happened in the
+/// client, which are connection errors when they come from the underlying socket.
+fn classify_postgres_error(error: &tokio_postgres::Error) -> ErrorClass {
+    let Some(code) = error.code() else {
+        let is_io_error = std::error::Error::source(error).is_some_and(|s| s.is::<std::io::Error>());
+
+        return if error.is_closed() || is_io_error {
+            ErrorClass::Connection
+        } else {
+            ErrorClass::Internal
+        };
+    };
+
+    // See https://www.postgresql.org/docs/current/errcodes-appendix.html
+    match &code.code()[..2] {
+        // Connection exceptions, invalid authorization, invalid catalog name (the database does not exist),
+        // insufficient resources and operator intervention, like the server shutting down.
+        "08" | "28" | "3D" | "53" | "57" => ErrorClass::Connection,
+        // Data exceptions and integrity constraint violations.
+        "22" | "23" => ErrorClass::DataCopy,
+        _ => ErrorClass::Ddl,
+    }
+}
+
+/// Helpers for attaching context to errors where they cross into elefant-tools, for example the
+/// file or query that was being worked on.
+///
+/// Only public for elefant-sync, it is not part of the stable API.
+#[doc(hidden)]
+pub trait ResultExt<T> {
+    /// Attaches the path of the file or directory to io errors.
+    /// `operation` describes what was being done, for example `create` or `write to`.
+    fn with_context_path(self, operation: &'static str, path: impl AsRef<Path>) -> Result<T>;
+
+    /// Attaches the query that was being executed to errors from postgres.
+    fn with_query(self, query: &str) -> Result<T>;
+}
+
+impl<T, E: Into<ElefantToolsError>> ResultExt<T> for std::result::Result<T, E> {
+    fn with_context_path(self, operation: &'static str, path: impl AsRef<Path>) -> Result<T> {
+        self.map_err(|e| match e.into() {
+            ElefantToolsError::IoError(source) => ElefantToolsError::IoErrorWithPath {
+                path: path.as_ref().display().to_string(),
+                operation,
+                source,
+            },
+            other => other,
+        })
+    }
+
+    fn with_query(self, query: &str) -> Result<T> {
+        self.map_err(|e| match e.into() {
+            ElefantToolsError::PostgresError(source) => ElefantToolsError::PostgresErrorWithQuery {
+                query: query.to_string(),
+                source,
+            },
+            other => other,
+        })
+    }
 }
 
 /// A result type that uses the ElefantToolsError as the error type
 pub type Result<T = ()> = std::result::Result<T, ElefantToolsError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn io_errors_include_the_path() {
+        let result: std::result::Result<(), std::io::Error> =
+            Err(std::io::Error::new(std::io::ErrorKind::NotFound, "not found"));
+
+        let error = result
+            .with_context_path("open", "/tmp/missing.sql")
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "io error when trying to open '/tmp/missing.sql': `not found`"
+        );
+        assert_eq!(error.source().unwrap().to_string(), "not found");
+    }
+
+    #[test]
+    fn other_errors_are_left_alone() {
+        let result: Result<()> = Err(ElefantToolsError::ConfirmationDeclined);
+
+        let error = result
+            .with_context_path("open", "/tmp/missing.sql")
+            .with_query("select 1;")
+            .unwrap_err();
+
+        assert!(matches!(error, ElefantToolsError::ConfirmationDeclined));
+    }
+
+    #[test]
+    fn cleanup_errors_chain_to_the_original_error() {
+        let error = ElefantToolsError::CleanupFailed {
+            error: Box::new(ElefantToolsError::ConfirmationDeclined),
+            cleanup_error: Box::new(ElefantToolsError::CsvFileMissingHeader),
+        };
+
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "The operation was not confirmed"
+        );
+    }
+
+    #[test]
+    fn disk_full_errors_explain_how_far_the_copy_got() {
+        let error = ElefantToolsError::DestinationDiskFull {
+            schema_name: "public".to_string(),
+            table_name: "events".to_string(),
+            copied_tables: 12,
+            copied_bytes: 3 * 1024 * 1024 * 1024,
+            source: Box::new(ElefantToolsError::ConfirmationDeclined),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "The destination ran out of disk space while copying the data of table 'public.events'. 12 other tables with 3.0 GB of data had been copied by then. Free up space in the destination, or leave out the largest tables: `The operation was not confirmed`"
+        );
+        assert_eq!(error.class(), ErrorClass::Validation);
+    }
+
+    #[test]
+    fn verification_failures_are_not_data_copy_errors() {
+        let error = ElefantToolsError::VerificationFailed(vec!["public.items".to_string()]);
+
+        assert_eq!(error.class(), ErrorClass::Verification);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::pg_interval::Interval;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+/// A plain table to turn into a hypertable in the destination, see
+/// [CopyDataOptions::hypertable_conversions].
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HypertableConversion {
+    /// The schema of the table, using the name in the destination.
+    pub schema: String,
+    pub table: String,
+    /// The column to partition the chunks by. Has to be a timestamp, date or integer column.
+    pub time_column: String,
+    /// How much of the time column each chunk covers. An interval with plural units, such as
+    /// `7 days` or `12 hours`, for timestamp and date columns, and a number for integer columns.
+    pub chunk_interval: String,
+    /// An additional column to hash partition the chunks by, and the number of partitions.
+    pub partitioning_column: Option<(String, i16)>,
+}
+
+impl HypertableConversion {
+    fn invalid(&self, reason: impl Into<String>) -> ElefantToolsError {
+        ElefantToolsError::InvalidHypertableConversion {
+            schema_name: self.schema.clone(),
+            table_name: self.table.clone(),
+            reason: reason.into(),
+        }
+    }
+
+    fn get_column<'a>(&self, table: &'a PostgresTable, name: &str) -> Result<&'a PostgresColumn> {
+        table
+            .columns
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| ElefantToolsError::ColumnNotFound {
+                schema_name: self.schema.clone(),
+                table_name: self.table.clone(),
+                column_name: name.to_string(),
+            })
+    }
+
+    /// The hypertable the table should be created as.
+    fn get_table_type(&self, table: &PostgresTable) -> Result<TableTypeDetails> {
+        if table.table_type != TableTypeDetails::Table {
+            return Err(self.invalid("only plain tables can be converted to hypertables"));
+        }
+
+        let time_column = self.get_column(table, &self.time_column)?;
+        if time_column.array_dimensions > 0 {
+            return Err(self.invalid(format!(
+                "the time column {} is an array",
+                self.time_column
+            )));
+        }
+
+        let time_dimension = match time_column.data_type.as_str() {
+            "timestamp" | "timestamptz" | "date" => HypertableDimension::Time {
+                column_name: self.time_column.clone(),
+                time_interval: Interval::from_postgres(&self.chunk_interval).map_err(|_| {
+                    self.invalid(format!(
+                        "{} is not a valid chunk interval",
+                        self.chunk_interval
+                    ))
+                })?,
+            },
+            "int2" | "int4" | "int8" => HypertableDimension::SpaceInterval {
+                column_name: self.time_column.clone(),
+                integer_interval: self.chunk_interval.trim().parse().map_err(|_| {
+                    self.invalid(format!(
+                        "{} is not a valid chunk interval for the integer column {}",
+                        self.chunk_interval, self.time_column
+                    ))
+                })?,
+                integer_now_func_schema: None,
+                integer_now_func: None,
+            },
+            data_type => {
+                return Err(self.invalid(format!(
+                    "the time column {} has the type {}, but has to be a timestamp, date or integer",
+                    self.time_column, data_type
+                )))
+            }
+        };
+
+        let mut dimensions = vec![time_dimension];
+        if let Some((column_name, num_partitions)) = &self.partitioning_column {
+            self.get_column(table, column_name)?;
+            dimensions.push(HypertableDimension::SpacePartitions {
+                column_name: column_name.clone(),
+                num_partitions: *num_partitions,
+            });
+        }
+
+        Ok(TableTypeDetails::TimescaleHypertable {
+            dimensions,
+            compression: None,
+            retention: None,
+            chunk_target_size: None,
+        })
+    }
+}
+
+/// Validates the conversions against the tables in `definition`. If `apply` is set, the tables
+/// are changed to hypertables, so they are created as such before their data is copied.
+pub(crate) fn convert_to_hypertables(
+    definition: &mut PostgresDatabase,
+    conversions: &[HypertableConversion],
+    apply: bool,
+) -> Result<()> {
+    for conversion in conversions {
+        let table = definition
+            .schemas
+            .iter_mut()
+            .find(|s| s.name == conversion.schema)
+            .and_then(|s| s.tables.iter_mut().find(|t| t.name == conversion.table))
+            .ok_or_else(|| ElefantToolsError::TableNotFound {
+                schema_name: conversion.schema.clone(),
+                table_name: conversion.table.clone(),
+            })?;
+
+        let table_type = conversion.get_table_type(table)?;
+        if apply {
+            table.table_type = table_type;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> PostgresDatabase {
+        let column = |name: &str, ordinal_position: i32, data_type: &str| PostgresColumn {
+            name: name.to_string(),
+            ordinal_position,
+            data_type: data_type.to_string(),
+            ..default()
+        };
+
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![PostgresTable {
+                    name: "metrics".to_string(),
+                    columns: vec![
+                        column("time", 1, "timestamptz"),
+                        column("device_id", 2, "int4"),
+                        column("value", 3, "float8"),
+                    ],
+                    ..default()
+                }],
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    fn conversion(time_column: &str, chunk_interval: &str) -> HypertableConversion {
+        HypertableConversion {
+            schema: "public".to_string(),
+            table: "metrics".to_string(),
+            time_column: time_column.to_string(),
+            chunk_interval: chunk_interval.to_string(),
+            partitioning_column: None,
+        }
+    }
+
+    #[test]
+    fn converts_tables_with_time_and_partitioning_columns() {
+        let mut definition = database();
+        let conversion = HypertableConversion {
+            partitioning_column: Some(("device_id".to_string(), 4)),
+            ..conversion("time", "7 days")
+        };
+
+        convert_to_hypertables(&mut definition, &[conversion], true).unwrap();
+
+        assert_eq!(
+            definition.schemas[0].tables[0].table_type,
+            TableTypeDetails::TimescaleHypertable {
+                dimensions: vec![
+                    HypertableDimension::Time {
+                        column_name: "time".to_string(),
+                        time_interval: Interval::new(0, 7, 0),
+                    },
+                    HypertableDimension::SpacePartitions {
+                        column_name: "device_id".to_string(),
+                        num_partitions: 4,
+                    },
+                ],
+                compression: None,
+                retention: None,
+                chunk_target_size: None,
+            }
+        );
+    }
+
+    #[test]
+    fn converts_tables_with_integer_time_columns() {
+        let mut definition = database();
+
+        convert_to_hypertables(&mut definition, &[conversion("device_id", "1000")], true).unwrap();
+
+        let TableTypeDetails::TimescaleHypertable { dimensions, .. } =
+            &definition.schemas[0].tables[0].table_type
+        else {
+            panic!("Table was not converted to a hypertable");
+        };
+        assert_eq!(
+            dimensions,
+            &vec![HypertableDimension::SpaceInterval {
+                column_name: "device_id".to_string(),
+                integer_interval: 1000,
+                integer_now_func_schema: None,
+                integer_now_func: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn only_validates_when_not_applying() {
+        let mut definition = database();
+
+        convert_to_hypertables(&mut definition, &[conversion("time", "12 hours")], false).unwrap();
+
+        assert_eq!(definition, database());
+    }
+
+    #[test]
+    fn rejects_invalid_time_columns() {
+        let mut definition = database();
+
+        let result = convert_to_hypertables(&mut definition, &[conversion("value", "7 days")], true);
+        assert!(matches!(
+            result,
+            Err(ElefantToolsError::InvalidHypertableConversion { .. })
+        ));
+
+        let result =
+            convert_to_hypertables(&mut definition, &[conversion("missing", "7 days")], true);
+        assert!(matches!(
+            result,
+            Err(ElefantToolsError::ColumnNotFound { .. })
+        ));
+
+        let result =
+            convert_to_hypertables(&mut definition, &[conversion("device_id", "7 days")], true);
+        assert!(matches!(
+            result,
+            Err(ElefantToolsError::InvalidHypertableConversion { .. })
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::object_filter::matches_wildcard;
+use crate::parallel_runner::ParallelRunner;
+use crate::{ElefantToolsError, PostgresClientWrapper, PostgresDatabase, Result, SchemaReader};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{info, instrument, warn};
+
+/// Which databases [introspect_all_databases] introspects. The patterns match the name of the
+/// database, where `*` matches any number of characters.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseFilter {
+    /// Only introspect the databases matching one of these patterns. All databases are
+    /// introspected if this is empty.
+    pub include: Vec<String>,
+    /// Leave out the databases matching one of these patterns, even if they are included.
+    pub exclude: Vec<String>,
+}
+
+impl DatabaseFilter {
+    /// Checks if the database should be introspected.
+    pub fn matches(&self, database_name: &str) -> bool {
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| matches_wildcard(pattern, database_name)))
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| matches_wildcard(pattern, database_name))
+    }
+}
+
+/// The outcome of introspecting a single database with [introspect_all_databases].
+#[derive(Debug)]
+pub struct DatabaseIntrospection {
+    pub database_name: String,
+    /// The introspected database, or why it could not be introspected.
+    pub result: Result<PostgresDatabase>,
+    /// How long connecting to and introspecting the database took.
+    pub duration: Duration,
+}
+
+/// Introspects every database on the server that matches the filter, using up to `max_parallel`
+/// connections at a time.
+///
+/// The databases are listed from `pg_database` using `admin_connection_string`, leaving out the
+/// templates and the databases that don't allow connections. Each database is then introspected
+/// on a connection of its own, made from `admin_connection_string` with `dbname` replaced, so the
+/// connection string has to be in the `key=value` format.
+///
+/// A database failing to be introspected doesn't stop the others, its error is returned in
+/// [DatabaseIntrospection::result] instead. Only failing to list the databases fails the call.
+/// The results are sorted by the name of the database.
+#[instrument(skip_all)]
+pub async fn introspect_all_databases(
+    admin_connection_string: &str,
+    filter: &DatabaseFilter,
+    max_parallel: NonZeroUsize,
+) -> Result<Vec<DatabaseIntrospection>> {
+    let database_names = {
+        let admin_connection = PostgresClientWrapper::new(admin_connection_string).await?;
+
+        //language=postgresql
+        admin_connection
+            .get_single_results::<String>(
+                "select datname::text from pg_database where not datistemplate and datallowconn order by datname;",
+            )
+            .await?
+    };
+
+    let results = Mutex::new(Vec::new());
+    let mut parallel_runner = ParallelRunner::new(max_parallel);
+
+    for database_name in database_names {
+        if !filter.matches(&database_name) {
+            continue;
+        }
+
+        let connection_string = with_database_name(admin_connection_string, &database_name);
+        let results = &results;
+        parallel_runner
+            .enqueue(async move {
+                let started = Instant::now();
+                let result = introspect_database(&connection_string).await;
+                let duration = started.elapsed();
+
+                match &result {
+                    Ok(_) => info!("Introspected {} in {:?}", database_name, duration),
+                    Err(e) => warn!("Failed to introspect {}: {}", database_name, e),
+                }
+
+                results.lock().await.push(DatabaseIntrospection {
+                    database_name,
+                    result,
+                    duration,
+                });
+
+                Ok::<(), ElefantToolsError>(())
+            })
+            .await?;
+    }
+
+    parallel_runner.run_remaining().await?;
+
+    let mut results = results.into_inner();
+    results.sort_by(|a, b| a.database_name.cmp(&b.database_name));
+
+    Ok(results)
+}
+
+async fn introspect_database(connection_string: &str) -> Result<PostgresDatabase> {
+    let connection = PostgresClientWrapper::new(connection_string).await?;
+    SchemaReader::new(&connection).introspect_database().await
+}
+
+/// Points a `key=value` connection string to another database. The last value of a key is the
+/// one used, so `dbname` is appended rather than replaced.
+fn with_database_name(connection_string: &str, database_name: &str) -> String {
+    let escaped = database_name.replace('\\', "\\\\").replace('\'', "\\'");
+
+    format!("{} dbname='{}'", connection_string.trim_end(), escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers;
+    use crate::test_helpers::TestHelper;
+    use elefant_test_macros::pg_test;
+
+    #[test]
+    fn filters_databases() {
+        let filter = DatabaseFilter {
+            include: vec!["customer_*".to_string()],
+            exclude: vec!["customer_test".to_string()],
+        };
+
+        assert!(filter.matches("customer_acme"));
+        assert!(!filter.matches("customer_test"));
+        assert!(!filter.matches("postgres"));
+        assert!(DatabaseFilter::default().matches("postgres"));
+    }
+
+    #[test]
+    fn replaces_the_database_name() {
+        let connection_string = with_database_name(
+            "host=localhost user=postgres dbname=postgres",
+            r"it's a \ name",
+        );
+
+        let config = connection_string.parse::<tokio_postgres::Config>().unwrap();
+        assert_eq!(config.get_dbname(), Some(r"it's a \ name"));
+        assert_eq!(config.get_user(), Some("postgres"));
+    }
+
+    #[pg_test(arg(postgres = 15))]
+    async fn introspects_matching_databases(helper: &TestHelper) {
+        helper
+            .execute_not_query("create table users(id int primary key);")
+            .await;
+
+        let admin_connection_string = format!(
+            "host=localhost port={} user=postgres password=passw0rd dbname=postgres",
+            helper.port
+        );
+        let filter = DatabaseFilter {
+            include: vec![helper.test_db_name.clone()],
+            exclude: Vec::new(),
+        };
+
+        let results = introspect_all_databases(
+            &admin_connection_string,
+            &filter,
+            NonZeroUsize::new(4).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].database_name, helper.test_db_name);
+        let database = results[0].result.as_ref().unwrap();
+        assert!(database.table("public", "users").is_some());
+    }
+}
@@ -1,27 +1,121 @@
+//! The public API is re-exported here explicitly, so adding or removing an item is a visible
+//! change. `src/snapshots/public_api.txt` lists the exported names, see the `public_api` tests.
+//! Most programs only need [prelude].
+
 #[cfg(any(test, feature = "test_utilities"))]
 pub mod test_helpers;
 
 mod chunk_reader;
+mod coalesced_stream;
+mod column_exclusion;
+mod column_rename;
 mod copy_data;
+mod copy_extension;
+mod copy_plan;
+mod copy_table;
+mod copy_timing;
+mod csv_file_source;
+mod database_encoding;
+mod dry_run;
 mod error;
 mod helpers;
+#[cfg(feature = "timescale")]
+mod hypertable_conversion;
+mod inventory;
 mod models;
+mod object_filter;
 mod object_id;
 mod parallel_runner;
 mod pg_interval;
 mod postgres_client_wrapper;
+mod privilege_check;
 mod quoting;
+mod resume_state;
+mod row_counter;
+mod schema_diff;
 mod schema_reader;
+mod self_reference_ordering;
+mod server_capabilities;
+mod snapshot;
+mod staging_swap;
+mod statement_interceptor;
 mod storage;
+mod supported_versions;
+mod table_filter;
+mod tablespace_mapping;
+#[cfg(feature = "timescale")]
+mod timescale_compatibility;
+mod verification;
 mod whitespace_ignorant_string;
 
-pub use copy_data::*;
-pub use error::*;
-pub use models::*;
-pub use object_id::ObjectId;
+pub mod prelude;
+
+pub use column_rename::ColumnRename;
+pub use copy_data::{copy_data, execute_copy, plan_copy, CopyDataOptions, CopyDataReport};
+pub use copy_extension::{
+    CustomObject, DdlExtension, DdlExtensionPhase, IntrospectionExtension, PgCronJobs,
+};
+pub use copy_plan::{
+    CopyPhase, CopyPlan, ExtensionAvailability, PlannedExtension, PlannedTable, SkippedTable,
+};
+pub use copy_table::{copy_table, CopyTableOptions, CopyTableReport};
+pub use copy_timing::TableCopyTiming;
+pub use csv_file_source::{CsvFileOptions, CsvFileSource};
+pub use database_encoding::{DatabaseEncoding, EncodingMismatch, InvalidlyEncodedColumn};
+pub use dry_run::{DryRunDestination, DryRunReport, DryRunSummary, DryRunTable};
+pub use error::{ElefantToolsError, ErrorClass, Result, ResultExt};
+#[cfg(feature = "timescale")]
+pub use hypertable_conversion::HypertableConversion;
+pub use inventory::{introspect_all_databases, DatabaseFilter, DatabaseIntrospection};
+pub use models::{
+    ColumnIdentity, FinalModify, FunctionKind, HypertableCompression,
+    HypertableCompressionOrderedColumn, HypertableCreateOptions, HypertableDimension,
+    HypertableRetention, IdentityOptions, Parallel, PartitionedTableColumns,
+    PostgresAggregateFunction, PostgresCheckConstraint, PostgresColumn, PostgresCompositeType,
+    PostgresCompositeTypeAttribute, PostgresConstraint, PostgresDatabase, PostgresDomain,
+    PostgresDomainConstraint, PostgresEnum, PostgresExtension, PostgresForeignKey,
+    PostgresForeignKeyColumn, PostgresForeignKeyReferencedColumn, PostgresFunction, PostgresIndex,
+    PostgresIndexColumnDirection, PostgresIndexIncludedColumn, PostgresIndexKeyColumn,
+    PostgresIndexNullsOrder, PostgresIndexType, PostgresPolicyCommand, PostgresPrivilege,
+    PostgresRowLevelSecurityPolicy, PostgresSchema,
+    PostgresSequence, PostgresTable, PostgresTrigger, PostgresTriggerEvent, PostgresTriggerLevel,
+    PostgresTriggerTiming, PostgresUniqueConstraint, PostgresView, PostgresViewColumn,
+    ReferenceAction, TablePartitionStrategy, TableTypeDetails,
+    TimescaleContinuousAggregateRefreshOptions, TimescaleDbUserDefinedJob, TimescaleSupport,
+    ViewOptions, Volatility,
+};
+pub(crate) use models::PostgresThingWithDependencies;
+pub use object_filter::ObjectFilter;
+pub use object_id::{ObjectId, ObjectIdGenerator};
 pub use postgres_client_wrapper::PostgresClientWrapper;
 pub use quoting::IdentifierQuoter;
-pub use storage::*;
+pub use schema_diff::{diff, SchemaChange, SchemaDiff};
+pub use schema_reader::{SchemaReader, SchemaSummary};
+pub use server_capabilities::{ServerCapabilities, TimescaleVersion};
+pub use snapshot::{introspect_to_file, read_snapshot_file, SNAPSHOT_VERSION};
+pub use staging_swap::StagingSwap;
+pub use statement_interceptor::{
+    AppendStorageParameter, DdlStatement, InterceptResult, StatementInterceptor,
+};
+pub use storage::{
+    apply_sql_directory, apply_sql_file, apply_sql_file_parallel, apply_sql_string, AsyncCleanup,
+    BaseCopyTarget, ConnectionPool, CopyDestination, CopyDestinationFactory, CopySource,
+    CopySourceFactory, CopyTextOptions, CsvDirectoryDestination, CsvDirectoryOptions, DataFormat,
+    ParallelCopyDestinationNotAvailable, PgCustomFormatDestination, PooledConnection, PostgresInstanceStorage,
+    SequentialOrParallel, SqlDataMode, SqlDirectoryDestination, SqlFile, SqlFileOptions, SupportedParallelism, TableBytesWritten,
+    TableData,
+};
+pub use supported_versions::{
+    get_supported_versions_description, MAX_TESTED_POSTGRES_VERSION,
+    MIN_SUPPORTED_POSTGRES_VERSION, MIN_SUPPORTED_TIMESCALEDB_VERSION,
+    TESTED_TIMESCALEDB_POSTGRES_VERSIONS,
+};
+pub use verification::{
+    ColumnMismatch, RowMismatch, TableVerification, TableVerificationResult, VerificationLevel,
+};
+
+#[cfg(test)]
+mod public_api;
 
 pub(crate) fn default<T: Default>() -> T {
     T::default()
@@ -1,5 +1,8 @@
 use crate::object_id::ObjectId;
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
 use crate::whitespace_ignorant_string::WhitespaceIgnorantString;
+use crate::{PostgresSchema, PostgresTable};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -9,6 +12,13 @@ pub struct PostgresCheckConstraint {
     pub check_clause: WhitespaceIgnorantString,
     pub comment: Option<String>,
     pub object_id: ObjectId,
+    /// The constraint only applies to the table itself, and not to the tables inheriting from it.
+    pub no_inherit: bool,
+    /// The existing rows of the table have not been validated against the constraint.
+    ///
+    /// Such constraints are added after the data has been copied, as they would otherwise be
+    /// validated when the table is created.
+    pub not_valid: bool,
 }
 
 impl PartialOrd for PostgresCheckConstraint {
@@ -22,3 +32,65 @@ impl Ord for PostgresCheckConstraint {
         self.name.cmp(&other.name)
     }
 }
+
+impl PostgresCheckConstraint {
+    /// The constraint as written in `create table`, without the `constraint` keyword.
+    pub(crate) fn get_table_definition(&self, quoter: &IdentifierQuoter) -> String {
+        let mut sql = format!(
+            "{} check {}",
+            self.name.quote(quoter, ColumnName),
+            self.check_clause
+        );
+
+        if self.no_inherit {
+            sql.push_str(" no inherit");
+        }
+
+        sql
+    }
+
+    pub fn get_create_statement(
+        &self,
+        table: &PostgresTable,
+        schema: &PostgresSchema,
+        quoter: &IdentifierQuoter,
+    ) -> String {
+        let mut sql = format!(
+            "alter table {}.{} add constraint {}",
+            schema.name.quote(quoter, ColumnName),
+            table.name.quote(quoter, ColumnName),
+            self.get_table_definition(quoter)
+        );
+
+        if self.not_valid {
+            sql.push_str(" not valid");
+        }
+        sql.push(';');
+
+        if let Some(comment) = &self.comment {
+            sql.push_str(&format!(
+                "\ncomment on constraint {} on {}.{} is {};",
+                self.name.quote(quoter, ColumnName),
+                schema.name.quote(quoter, ColumnName),
+                table.name.quote(quoter, ColumnName),
+                quote_value_string(comment)
+            ));
+        }
+
+        sql
+    }
+
+    pub fn get_drop_statement(
+        &self,
+        table: &PostgresTable,
+        schema: &PostgresSchema,
+        quoter: &IdentifierQuoter,
+    ) -> String {
+        format!(
+            "alter table {}.{} drop constraint {};",
+            schema.name.quote(quoter, ColumnName),
+            table.name.quote(quoter, ColumnName),
+            self.name.quote(quoter, ColumnName)
+        )
+    }
+}
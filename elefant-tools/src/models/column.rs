@@ -16,6 +16,13 @@ pub struct PostgresColumn {
     pub array_dimensions: i32,
     pub data_type_length: Option<i32>,
     pub identity: Option<ColumnIdentity>,
+    /// The options of the sequence generating the values of an identity column.
+    pub identity_options: Option<IdentityOptions>,
+    /// If the column is inherited from the parent of a partition, with the same default value as
+    /// the parent. Setting the default on the parent sets it on the partitions as well, so the
+    /// default is only set on the parent.
+    #[serde(default)]
+    pub inherits_default: bool,
 }
 
 impl PostgresColumn {
@@ -35,10 +42,124 @@ impl PostgresColumn {
             )
         })
     }
+
+    /// The data type of the column, including the length and array dimensions, as used when
+    /// creating the column.
+    pub fn get_type_expression(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        let mut sql = self
+            .data_type
+            .quote(identifier_quoter, AttemptedKeywordUsage::ColumnName);
+
+        if let Some(length) = self.data_type_length {
+            sql.push_str(&format!("({})", length));
+        }
+
+        for _ in 0..self.array_dimensions {
+            sql.push_str("[]");
+        }
+
+        sql
+    }
+
+    /// The column as written in `create table` and `alter table ... add column`, without the
+    /// default value, which is set separately.
+    pub(crate) fn get_column_definition(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        let mut sql = self.name.quote(identifier_quoter, AttemptedKeywordUsage::ColumnName);
+        sql.push(' ');
+        sql.push_str(&self.get_type_expression(identifier_quoter));
+
+        if !self.is_nullable {
+            sql.push_str(" not null");
+        }
+
+        if let Some(generated) = &self.generated {
+            sql.push_str(" generated always as (");
+            sql.push_str(generated);
+            sql.push_str(") stored");
+        }
+
+        if let Some(identity) = &self.identity {
+            sql.push_str(" generated ");
+            match identity {
+                ColumnIdentity::GeneratedAlways => sql.push_str("always"),
+                ColumnIdentity::GeneratedByDefault => sql.push_str("by default")
+            }
+            sql.push_str(" as identity");
+
+            if let Some(options) = &self.identity_options {
+                sql.push_str(" ( ");
+                sql.push_str(&options.get_sequence_options(identifier_quoter));
+                sql.push_str(" )");
+            }
+        }
+
+        sql
+    }
+
+    /// Renames the column `old_name` of the table to the name of this column.
+    pub fn get_rename_statement(
+        &self,
+        old_name: &str,
+        table: &PostgresTable,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        format!(
+            "alter table {}.{} rename column {} to {};",
+            schema.name.quote(identifier_quoter, Other),
+            table.name.quote(identifier_quoter, Other),
+            old_name.quote(identifier_quoter, Other),
+            self.name.quote(identifier_quoter, Other)
+        )
+    }
+
+    /// Changes the data type of the column to the data type of this column.
+    pub fn get_alter_type_statement(
+        &self,
+        table: &PostgresTable,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let type_expression = self.get_type_expression(identifier_quoter);
+        let name = self.name.quote(identifier_quoter, Other);
+
+        format!(
+            "alter table {}.{} alter column {} type {} using {}::{};",
+            schema.name.quote(identifier_quoter, Other),
+            table.name.quote(identifier_quoter, Other),
+            name,
+            type_expression,
+            name,
+            type_expression
+        )
+    }
 }
 
 impl PostgresColumn {
-    pub fn get_simplified_data_type(&self) -> SimplifiedDataType {
+    /// Changes the options of the identity sequence of the column to the options of this column.
+    pub fn get_alter_identity_options_statement(
+        &self,
+        table: &PostgresTable,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let options = self.identity_options.as_ref()?;
+
+        Some(format!(
+            "alter table {}.{} alter column {} set start with {} set increment by {} set minvalue {} set maxvalue {} set cache {} set {};",
+            schema.name.quote(identifier_quoter, Other),
+            table.name.quote(identifier_quoter, Other),
+            self.name.quote(identifier_quoter, Other),
+            options.start,
+            options.increment,
+            options.min,
+            options.max,
+            options.cache,
+            if options.cycle { "cycle" } else { "no cycle" }
+        ))
+    }
+
+    pub(crate) fn get_simplified_data_type(&self) -> SimplifiedDataType {
         if self.array_dimensions > 0 {
             return SimplifiedDataType::Text;
         }
@@ -63,12 +184,14 @@ impl Default for PostgresColumn {
             array_dimensions: 0,
             data_type_length: None,
             identity: None,
+            identity_options: None,
+            inherits_default: false,
         }
     }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
-pub enum SimplifiedDataType {
+pub(crate) enum SimplifiedDataType {
     Number,
     Text,
     Bool,
@@ -80,6 +203,46 @@ pub enum ColumnIdentity {
     GeneratedByDefault
 }
 
+/// The options of the sequence behind an identity column. The sequence is owned by the column,
+/// so it is created and dropped together with it.
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct IdentityOptions {
+    /// The name of the sequence, which is in the same schema as the table.
+    pub sequence_name: String,
+    pub start: i64,
+    pub increment: i64,
+    pub min: i64,
+    pub max: i64,
+    pub cache: i64,
+    pub cycle: bool,
+}
+
+impl IdentityOptions {
+    /// If the sequences are set up the same way, ignoring their names.
+    pub fn has_same_sequence_options(&self, other: &IdentityOptions) -> bool {
+        self.start == other.start
+            && self.increment == other.increment
+            && self.min == other.min
+            && self.max == other.max
+            && self.cache == other.cache
+            && self.cycle == other.cycle
+    }
+
+    /// The options as used in `generated ... as identity (...)`.
+    pub fn get_sequence_options(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        format!(
+            "sequence name {} start with {} increment by {} minvalue {} maxvalue {} cache {}{}",
+            self.sequence_name.quote(identifier_quoter, Other),
+            self.start,
+            self.increment,
+            self.min,
+            self.max,
+            self.cache,
+            if self.cycle { " cycle" } else { "" }
+        )
+    }
+}
+
 impl FromPgChar for ColumnIdentity {
     fn from_pg_char(c: char) -> Result<Self, ElefantToolsError> {
         match c {
@@ -0,0 +1,63 @@
+use crate::quoting::quote_value_string;
+
+/// Gets the statement changing the comment of an object that already exists in the
+/// destination, or `None` if the comment is unchanged. A removed comment is set to `null`.
+pub(crate) fn get_comment_change_statement(
+    object: &str,
+    comment: &Option<String>,
+    existing_comment: &Option<String>,
+) -> Option<String> {
+    if comment == existing_comment {
+        return None;
+    }
+
+    Some(format!(
+        "comment on {} is {};",
+        object,
+        comment
+            .as_ref()
+            .map(|c| quote_value_string(c))
+            .unwrap_or_else(|| "null".to_string())
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_comments_have_no_statement() {
+        assert_eq!(
+            get_comment_change_statement("table public.people", &None, &None),
+            None
+        );
+        assert_eq!(
+            get_comment_change_statement(
+                "table public.people",
+                &Some("The people".to_string()),
+                &Some("The people".to_string())
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn changed_and_removed_comments() {
+        assert_eq!(
+            get_comment_change_statement(
+                "table public.people",
+                &Some("It's people".to_string()),
+                &Some("The people".to_string())
+            ),
+            Some("comment on table public.people is 'It''s people';".to_string())
+        );
+        assert_eq!(
+            get_comment_change_statement(
+                "table public.animals",
+                &None,
+                &Some("The animals".to_string())
+            ),
+            Some("comment on table public.animals is null;".to_string())
+        );
+    }
+}
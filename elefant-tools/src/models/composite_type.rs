@@ -0,0 +1,144 @@
+use crate::models::comment::get_comment_change_statement;
+use crate::object_id::ObjectId;
+use crate::quoting::AttemptedKeywordUsage::{ColumnName, TypeOrFunctionName};
+use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
+use crate::PostgresSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
+pub struct PostgresCompositeType {
+    pub name: String,
+    pub attributes: Vec<PostgresCompositeTypeAttribute>,
+    pub comment: Option<String>,
+    pub object_id: ObjectId,
+    /// The enums, domains and other types used by the attributes.
+    pub depends_on: Vec<ObjectId>,
+}
+
+#[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
+pub struct PostgresCompositeTypeAttribute {
+    pub name: String,
+    pub ordinal_position: i32,
+    pub data_type: String,
+    pub data_type_length: Option<i32>,
+    pub array_dimensions: i32,
+}
+
+impl PostgresCompositeTypeAttribute {
+    fn get_type_expression(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        let mut sql = self.data_type.quote(identifier_quoter, ColumnName);
+
+        if let Some(length) = self.data_type_length {
+            sql.push_str(&format!("({})", length));
+        }
+
+        for _ in 0..self.array_dimensions {
+            sql.push_str("[]");
+        }
+
+        sql
+    }
+}
+
+impl PostgresCompositeType {
+    pub fn get_create_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let escaped_name = format!(
+            "{}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, TypeOrFunctionName)
+        );
+
+        let mut sql = format!("create type {} as (", escaped_name);
+
+        for (i, attribute) in self.attributes.iter().enumerate() {
+            if i > 0 {
+                sql.push(',');
+            }
+            sql.push_str("\n    ");
+            sql.push_str(&attribute.name.quote(identifier_quoter, ColumnName));
+            sql.push(' ');
+            sql.push_str(&attribute.get_type_expression(identifier_quoter));
+        }
+
+        if !self.attributes.is_empty() {
+            sql.push('\n');
+        }
+        sql.push_str(");");
+
+        if let Some(comment) = &self.comment {
+            sql.push_str("\ncomment on type ");
+            sql.push_str(&escaped_name);
+            sql.push_str(" is ");
+            sql.push_str(&quote_value_string(comment));
+            sql.push(';');
+        }
+
+        sql
+    }
+
+    /// Gets the statement changing the comment of the `existing` composite type to the comment of
+    /// this one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresCompositeType,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "type {}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, TypeOrFunctionName)
+        );
+
+        get_comment_change_statement(&object, &self.comment, &existing.comment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_composite_types() {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..Default::default()
+        };
+        let composite_type = PostgresCompositeType {
+            name: "address".to_string(),
+            attributes: vec![
+                PostgresCompositeTypeAttribute {
+                    name: "street".to_string(),
+                    ordinal_position: 1,
+                    data_type: "text".to_string(),
+                    ..Default::default()
+                },
+                PostgresCompositeTypeAttribute {
+                    name: "zip".to_string(),
+                    ordinal_position: 2,
+                    data_type: "varchar".to_string(),
+                    data_type_length: Some(5),
+                    ..Default::default()
+                },
+                PostgresCompositeTypeAttribute {
+                    name: "tags".to_string(),
+                    ordinal_position: 3,
+                    data_type: "text".to_string(),
+                    array_dimensions: 1,
+                    ..Default::default()
+                },
+            ],
+            comment: Some("Where someone lives".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            composite_type.get_create_statement(&schema, &IdentifierQuoter::empty()),
+            "create type public.address as (\n    street text,\n    zip varchar(5),\n    tags text[]\n);\ncomment on type public.address is 'Where someone lives';"
+        );
+    }
+}
@@ -37,4 +37,12 @@ impl PostgresConstraint {
             PostgresConstraint::Unique(constraint) => &constraint.name,
         }
     }
+
+    pub(crate) fn comment(&self) -> &Option<String> {
+        match self {
+            PostgresConstraint::Check(constraint) => &constraint.comment,
+            PostgresConstraint::ForeignKey(constraint) => &constraint.comment,
+            PostgresConstraint::Unique(constraint) => &constraint.comment,
+        }
+    }
 }
@@ -1,7 +1,10 @@
 use crate::models::extension::PostgresExtension;
 use crate::models::schema::PostgresSchema;
 use crate::object_id::ObjectId;
-use crate::{default, TimescaleDbUserDefinedJob};
+use crate::quoting::{quote_value_string, AttemptedKeywordUsage, IdentifierQuoter};
+use crate::{
+    default, CustomObject, PostgresConstraint, PostgresTable, PostgresView, TimescaleDbUserDefinedJob,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
@@ -10,6 +13,10 @@ pub struct PostgresDatabase {
     pub enabled_extensions: Vec<PostgresExtension>,
     pub timescale_support: TimescaleSupport,
     pub object_id: ObjectId,
+    /// Objects read by the [crate::IntrospectionExtension]s, see
+    /// [crate::CopyDataOptions::introspection_extensions].
+    #[serde(default)]
+    pub custom_objects: Vec<CustomObject>,
 }
 
 #[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
@@ -56,6 +63,35 @@ impl PostgresDatabase {
         }
     }
 
+    /// Leaves out the provided schemas, including the extensions and jobs that live in them.
+    pub fn without_schemas(&self, excluded_schemas: &[String]) -> Self {
+        PostgresDatabase {
+            timescale_support: TimescaleSupport {
+                user_defined_jobs: self
+                    .timescale_support
+                    .user_defined_jobs
+                    .iter()
+                    .filter(|j| !excluded_schemas.contains(&j.function_schema))
+                    .cloned()
+                    .collect(),
+                ..self.timescale_support.clone()
+            },
+            schemas: self
+                .schemas
+                .iter()
+                .filter(|s| !excluded_schemas.contains(&s.name))
+                .cloned()
+                .collect(),
+            enabled_extensions: self
+                .enabled_extensions
+                .iter()
+                .filter(|e| !excluded_schemas.contains(&e.schema_name))
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+
     pub fn with_renamed_schema(&self, old_schema_name: &str, new_schema_name: &str) -> Self {
         PostgresDatabase {
             timescale_support: TimescaleSupport {
@@ -83,6 +119,24 @@ impl PostgresDatabase {
                     if s.name == old_schema_name {
                         PostgresSchema {
                             name: new_schema_name.to_string(),
+                            tables: s
+                                .tables
+                                .iter()
+                                .map(|t| with_renamed_schema_references(t, s, new_schema_name))
+                                .collect(),
+                            views: s
+                                .views
+                                .iter()
+                                .map(|v| PostgresView {
+                                    definition: rename_schema_qualifiers(
+                                        &v.definition,
+                                        old_schema_name,
+                                        new_schema_name,
+                                    )
+                                    .into(),
+                                    ..v.clone()
+                                })
+                                .collect(),
                             ..s.clone()
                         }
                     } else {
@@ -97,4 +151,220 @@ impl PostgresDatabase {
     pub(crate) fn try_get_schema(&self, schema_name: &str) -> Option<&PostgresSchema> {
         self.schemas.iter().find(|s| s.name == schema_name)
     }
+
+    /// Finds a table by its schema and name, for example to check an introspected database has
+    /// the expected structure.
+    ///
+    /// ```
+    /// use elefant_tools::{PostgresColumn, PostgresDatabase, PostgresSchema, PostgresTable};
+    ///
+    /// let database = PostgresDatabase {
+    ///     schemas: vec![PostgresSchema {
+    ///         name: "public".to_string(),
+    ///         tables: vec![PostgresTable {
+    ///             columns: vec![PostgresColumn {
+    ///                 name: "email".to_string(),
+    ///                 data_type: "text".to_string(),
+    ///                 ..Default::default()
+    ///             }],
+    ///             ..PostgresTable::new("users")
+    ///         }],
+    ///         ..Default::default()
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let email = database.table("public", "users").and_then(|t| t.column("email"));
+    /// assert_eq!(email.map(|c| c.data_type.as_str()), Some("text"));
+    /// assert!(database.table("public", "orders").is_none());
+    /// ```
+    pub fn table(&self, schema_name: &str, table_name: &str) -> Option<&PostgresTable> {
+        self.try_get_schema(schema_name)?.try_get_table(table_name)
+    }
+}
+
+/// Points the references a table has to its own schema at the renamed schema instead.
+///
+/// Sequence defaults are made fully qualified, as an unqualified sequence name would otherwise
+/// resolve through the `search_path` of the destination, which might find an identically named
+/// sequence in another schema.
+fn with_renamed_schema_references(
+    table: &PostgresTable,
+    old_schema: &PostgresSchema,
+    new_schema_name: &str,
+) -> PostgresTable {
+    let mut table = table.clone();
+
+    for column in &mut table.columns {
+        if let Some(default_value) = &column.default_value {
+            column.default_value = Some(rename_sequence_reference(
+                default_value,
+                old_schema,
+                new_schema_name,
+            ));
+        }
+    }
+
+    for constraint in &mut table.constraints {
+        if let PostgresConstraint::ForeignKey(fk) = constraint {
+            if fk.referenced_schema.as_deref() == Some(old_schema.name.as_str()) {
+                fk.referenced_schema = Some(new_schema_name.to_string());
+            }
+        }
+    }
+
+    table
+}
+
+/// Rewrites a `nextval('<sequence>'::regclass)` default value to reference the sequence in the
+/// renamed schema. Other default values, and sequences in other schemas, are returned unchanged.
+fn rename_sequence_reference(
+    default_value: &str,
+    old_schema: &PostgresSchema,
+    new_schema_name: &str,
+) -> String {
+    let Some(literal) = default_value
+        .strip_prefix("nextval('")
+        .and_then(|rest| rest.strip_suffix("'::regclass)"))
+    else {
+        return default_value.to_string();
+    };
+
+    let sequence_name = match parse_qualified_name(&literal.replace("''", "'")).as_deref() {
+        Some([name]) if old_schema.sequences.iter().any(|s| &s.name == name) => name.clone(),
+        Some([schema, name]) if schema == &old_schema.name => name.clone(),
+        _ => return default_value.to_string(),
+    };
+
+    let quoter = IdentifierQuoter::empty();
+    let qualified_name = format!(
+        "{}.{}",
+        quoter.quote(new_schema_name, AttemptedKeywordUsage::Other),
+        quoter.quote(sequence_name, AttemptedKeywordUsage::Other)
+    );
+
+    format!("nextval({}::regclass)", quote_value_string(&qualified_name))
+}
+
+/// Rewrites the names qualified with the old schema in a query, such as `app.items` in the
+/// definition of a view, to use the renamed schema. Postgres qualifies the names of a view
+/// definition with their schema, unless the schema is on the `search_path`.
+///
+/// Text in string literals is left alone.
+fn rename_schema_qualifiers(sql: &str, old_schema_name: &str, new_schema_name: &str) -> String {
+    let quoter = IdentifierQuoter::empty();
+    let old_qualifier = format!(
+        "{}.",
+        quoter.quote(old_schema_name, AttemptedKeywordUsage::Other)
+    );
+    let new_qualifier = format!(
+        "{}.",
+        quoter.quote(new_schema_name, AttemptedKeywordUsage::Other)
+    );
+    let is_identifier_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '$' | '"' | '.');
+
+    let mut result = String::with_capacity(sql.len());
+    let mut in_string = false;
+    let mut previous = None;
+    let mut rest = sql;
+
+    while let Some(c) = rest.chars().next() {
+        if !in_string
+            && rest.starts_with(&old_qualifier)
+            && !previous.is_some_and(is_identifier_char)
+        {
+            result.push_str(&new_qualifier);
+            rest = &rest[old_qualifier.len()..];
+            previous = Some('.');
+            continue;
+        }
+
+        if c == '\'' {
+            in_string = !in_string;
+        }
+        result.push(c);
+        previous = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    result
+}
+
+/// Splits a possibly qualified and quoted name, like `"My Schema".my_sequence`, into its parts,
+/// following the same rules as the `regclass` input function.
+fn parse_qualified_name(name: &str) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut chars = name.chars().peekable();
+
+    loop {
+        let mut part = String::new();
+
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next()? {
+                    '"' if chars.next_if_eq(&'"').is_some() => part.push('"'),
+                    '"' => break,
+                    c => part.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != '.' && *c != '"') {
+                part.push(c.to_ascii_lowercase());
+            }
+        }
+
+        if part.is_empty() {
+            return None;
+        }
+        parts.push(part);
+
+        match chars.next() {
+            None => return Some(parts),
+            Some('.') => continue,
+            Some(_) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_qualified_names() {
+        assert_eq!(
+            parse_qualified_name("my_seq"),
+            Some(vec!["my_seq".to_string()])
+        );
+        assert_eq!(
+            parse_qualified_name("a.My_Seq"),
+            Some(vec!["a".to_string(), "my_seq".to_string()])
+        );
+        assert_eq!(
+            parse_qualified_name(r#""My ""Schema"""."my.seq""#),
+            Some(vec![r#"My "Schema""#.to_string(), "my.seq".to_string()])
+        );
+        assert_eq!(parse_qualified_name("a..b"), None);
+        assert_eq!(parse_qualified_name(r#""unterminated"#), None);
+    }
+
+    #[test]
+    fn renames_schema_qualifiers_outside_string_literals() {
+        assert_eq!(
+            rename_schema_qualifiers(
+                " SELECT items.name, 'app.items' AS source\n   FROM app.items\n     JOIN my_app.items USING (id);",
+                "app",
+                "_elefant_staging_1"
+            ),
+            " SELECT items.name, 'app.items' AS source\n   FROM _elefant_staging_1.items\n     JOIN my_app.items USING (id);"
+        );
+        assert_eq!(
+            rename_schema_qualifiers(
+                r#"SELECT "My App".items.id FROM "My App".items;"#,
+                "My App",
+                "staging"
+            ),
+            "SELECT staging.items.id FROM staging.items;"
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use crate::models::comment::get_comment_change_statement;
 use crate::quoting::{quote_value_string, AttemptedKeywordUsage, Quotable};
 use crate::{IdentifierQuoter, ObjectId, PostgresSchema};
 use serde::{Deserialize, Serialize};
@@ -71,4 +72,23 @@ impl PostgresDomain {
 
         sql
     }
+
+    /// Gets the statement changing the comment of the `existing` domain to the comment of this one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresDomain,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "domain {}.{}",
+            schema
+                .name
+                .quote(identifier_quoter, AttemptedKeywordUsage::TypeOrFunctionName),
+            self.name
+                .quote(identifier_quoter, AttemptedKeywordUsage::TypeOrFunctionName)
+        );
+
+        get_comment_change_statement(&object, &self.description, &existing.description)
+    }
 }
@@ -1,6 +1,8 @@
-use crate::object_id::ObjectId;
-use crate::quoting::AttemptedKeywordUsage::TypeOrFunctionName;
+use crate::models::comment::get_comment_change_statement;
+use crate::object_id::{HaveDependencies, ObjectId};
+use crate::quoting::AttemptedKeywordUsage::{ColumnName, TypeOrFunctionName};
 use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
+use crate::PostgresSchema;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
@@ -12,18 +14,42 @@ pub struct PostgresEnum {
     pub object_id: ObjectId,
 }
 
+/// Enums never depend on anything else.
+static NO_DEPENDENCIES: Vec<ObjectId> = Vec::new();
+
+impl HaveDependencies for &PostgresEnum {
+    fn depends_on(&self) -> &Vec<ObjectId> {
+        &NO_DEPENDENCIES
+    }
+
+    fn object_id(&self) -> ObjectId {
+        self.object_id
+    }
+
+    fn object_name(&self) -> String {
+        self.name.clone()
+    }
+}
+
 impl PostgresEnum {
-    pub fn get_create_statement(&self, identifier_quoter: &IdentifierQuoter) -> String {
-        let mut sql = format!(
-            "create type {} as enum (",
+    pub fn get_create_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let escaped_name = format!(
+            "{}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
             self.name.quote(identifier_quoter, TypeOrFunctionName)
         );
+
+        let mut sql = format!("create type {} as enum (", escaped_name);
         sql.push_str(&self.values.iter().map(|v| quote_value_string(v)).join(", "));
         sql.push_str(");");
 
         if let Some(comment) = &self.comment {
             sql.push_str("\ncomment on type ");
-            sql.push_str(&self.name.quote(identifier_quoter, TypeOrFunctionName));
+            sql.push_str(&escaped_name);
             sql.push_str(" is ");
             sql.push_str(&quote_value_string(comment));
             sql.push(';');
@@ -31,4 +57,21 @@ impl PostgresEnum {
 
         sql
     }
+
+    /// Gets the statement changing the comment of the `existing` enum to the comment of this
+    /// one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresEnum,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "type {}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, TypeOrFunctionName)
+        );
+
+        get_comment_change_statement(&object, &self.comment, &existing.comment)
+    }
 }
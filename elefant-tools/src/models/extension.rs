@@ -1,6 +1,6 @@
 use crate::object_id::ObjectId;
 use crate::quoting::AttemptedKeywordUsage::ColumnName;
-use crate::quoting::{IdentifierQuoter, Quotable};
+use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
@@ -19,4 +19,45 @@ impl PostgresExtension {
             self.name.quote(identifier_quoter, ColumnName)
         )
     }
+
+    /// Creates the extension in a `do` block that raises a notice instead of failing when the
+    /// user is not allowed to create it, see [crate::CopyDataOptions::privilege_downgrade].
+    pub fn get_guarded_create_statement(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        format!(
+            r#"do $$
+begin
+    {}
+exception when insufficient_privilege then
+    raise notice 'Skipped extension % as the user is not allowed to create it', {};
+end
+$$;"#,
+            self.get_create_statement(identifier_quoter),
+            quote_value_string(&self.name)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default;
+
+    #[test]
+    fn guards_the_create_statement() {
+        let extension = PostgresExtension {
+            name: "pg_trgm".to_string(),
+            ..default()
+        };
+
+        assert_eq!(
+            extension.get_guarded_create_statement(&IdentifierQuoter::empty()),
+            r#"do $$
+begin
+    create extension if not exists pg_trgm;
+exception when insufficient_privilege then
+    raise notice 'Skipped extension % as the user is not allowed to create it', 'pg_trgm';
+end
+$$;"#
+        );
+    }
 }
@@ -1,9 +1,12 @@
+use crate::models::comment::get_comment_change_statement;
 use crate::object_id::ObjectId;
 use crate::postgres_client_wrapper::FromPgChar;
 use crate::quoting::AttemptedKeywordUsage::TypeOrFunctionName;
 use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
 use crate::whitespace_ignorant_string::WhitespaceIgnorantString;
-use crate::{ElefantToolsError, PostgresSchema};
+use crate::models::privilege::get_privilege_statements;
+use crate::{ElefantToolsError, PostgresPrivilege, PostgresSchema};
+use itertools::Itertools;
 use ordered_float::NotNan;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
@@ -67,7 +70,7 @@ impl FromPgChar for Parallel {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Eq, Default, Clone, Serialize, Deserialize)]
 pub struct PostgresFunction {
     pub function_name: String,
     pub language: String,
@@ -88,9 +91,87 @@ pub struct PostgresFunction {
     pub comment: Option<String>,
     pub object_id: ObjectId,
     pub depends_on: Vec<ObjectId>,
+    pub privileges: Vec<PostgresPrivilege>,
+}
+
+impl PartialEq for PostgresFunction {
+    fn eq(&self, other: &Self) -> bool {
+        let PostgresFunction {
+            function_name,
+            language,
+            estimated_cost: _,
+            estimated_rows: _,
+            support_function,
+            kind,
+            security_definer,
+            leak_proof,
+            strict,
+            returns_set,
+            volatility,
+            parallel,
+            sql_body,
+            configuration,
+            arguments,
+            result,
+            comment,
+            object_id,
+            depends_on,
+            privileges,
+        } = self;
+
+        // The cost and rows are only compared when they have been set explicitly, as the
+        // defaults Postgres fills in depend on the language and if the function returns a set.
+        function_name == &other.function_name
+            && language == &other.language
+            && self.get_explicit_cost() == other.get_explicit_cost()
+            && self.get_explicit_rows() == other.get_explicit_rows()
+            && support_function == &other.support_function
+            && kind == &other.kind
+            && security_definer == &other.security_definer
+            && leak_proof == &other.leak_proof
+            && strict == &other.strict
+            && returns_set == &other.returns_set
+            && volatility == &other.volatility
+            && parallel == &other.parallel
+            && sql_body == &other.sql_body
+            && configuration == &other.configuration
+            && arguments == &other.arguments
+            && result == &other.result
+            && comment == &other.comment
+            && object_id == &other.object_id
+            && depends_on == &other.depends_on
+            && privileges == &other.privileges
+    }
 }
 
 impl PostgresFunction {
+    /// If the estimated cost is the one Postgres uses when `cost` is not specified, which is 1
+    /// for functions written in C, and 100 for everything else.
+    pub fn is_default_cost(&self) -> bool {
+        let default_cost = match self.language.as_str() {
+            "c" | "internal" => 1.,
+            _ => 100.,
+        };
+
+        self.estimated_cost.into_inner() == default_cost
+    }
+
+    /// If the estimated number of rows is the one Postgres uses when `rows` is not specified,
+    /// which is 1000 for functions returning a set, and 0 for everything else.
+    pub fn is_default_rows(&self) -> bool {
+        let default_rows = if self.returns_set { 1000. } else { 0. };
+
+        self.estimated_rows.into_inner() == default_rows
+    }
+
+    fn get_explicit_cost(&self) -> Option<NotNan<f32>> {
+        (!self.is_default_cost()).then_some(self.estimated_cost)
+    }
+
+    fn get_explicit_rows(&self) -> Option<NotNan<f32>> {
+        (!self.is_default_rows()).then_some(self.estimated_rows)
+    }
+
     pub fn get_create_statement(
         &self,
         schema: &PostgresSchema,
@@ -141,10 +222,6 @@ impl PostgresFunction {
                 Parallel::Unsafe => sql.push_str(" parallel unsafe "),
             }
 
-            if self.leak_proof {
-                sql.push_str(" leakproof ");
-            }
-
             if self.strict {
                 sql.push_str(" strict ");
             }
@@ -162,10 +239,12 @@ impl PostgresFunction {
         }
 
         if self.kind != FunctionKind::Procedure {
-            sql.push_str("cost ");
-            sql.push_str(&self.estimated_cost.to_string());
+            if !self.is_default_cost() {
+                sql.push_str(" cost ");
+                sql.push_str(&self.estimated_cost.to_string());
+            }
 
-            if self.estimated_rows.into_inner() > 0. {
+            if !self.is_default_rows() {
                 sql.push_str(" rows ");
                 sql.push_str(&self.estimated_rows.to_string());
             }
@@ -192,6 +271,108 @@ impl PostgresFunction {
 
         sql
     }
+
+    /// Gets the statement replacing the function in place, which keeps the privileges and the
+    /// objects depending on it. Only the arguments and the result must not have changed.
+    pub fn get_replace_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let sql = self.get_create_statement(schema, identifier_quoter);
+
+        sql.replacen("create ", "create or replace ", 1)
+    }
+
+    pub fn get_drop_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let function_keyword = if self.kind == FunctionKind::Procedure {
+            "procedure"
+        } else {
+            "function"
+        };
+
+        format!(
+            "drop {} {}.{}({});",
+            function_keyword,
+            schema.name.quote(identifier_quoter, TypeOrFunctionName),
+            self.function_name
+                .quote(identifier_quoter, TypeOrFunctionName),
+            get_identity_arguments(&self.arguments)
+        )
+    }
+
+    /// Gets the statement changing the comment of the `existing` function to the comment of this
+    /// one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresFunction,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let function_keyword = if self.kind == FunctionKind::Procedure {
+            "procedure"
+        } else {
+            "function"
+        };
+
+        let object = format!(
+            "{} {}.{}({})",
+            function_keyword,
+            schema.name.quote(identifier_quoter, TypeOrFunctionName),
+            self.function_name
+                .quote(identifier_quoter, TypeOrFunctionName),
+            get_identity_arguments(&self.arguments)
+        );
+
+        get_comment_change_statement(&object, &self.comment, &existing.comment)
+    }
+
+    /// Gets the statements granting and revoking the privileges of the function.
+    pub fn get_privilege_statements(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let function_keyword = if self.kind == FunctionKind::Procedure {
+            "procedure"
+        } else {
+            "function"
+        };
+
+        let object = format!(
+            "{} {}.{}",
+            function_keyword,
+            schema.name.quote(identifier_quoter, TypeOrFunctionName),
+            self.function_name
+                .quote(identifier_quoter, TypeOrFunctionName)
+        );
+
+        get_privilege_statements(&self.privileges, &object, identifier_quoter)
+    }
+
+    /// Marks the function as leakproof, which requires the user to be a superuser. This is
+    /// not part of [PostgresFunction::get_create_statement], so the function can still be created
+    /// by a user that isn't.
+    pub fn get_set_leak_proof_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        if !self.leak_proof || self.kind == FunctionKind::Procedure {
+            return None;
+        }
+
+        Some(format!(
+            "alter function {}.{} leakproof;",
+            schema.name.quote(identifier_quoter, TypeOrFunctionName),
+            self.function_name
+                .quote(identifier_quoter, TypeOrFunctionName)
+        ))
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Default, Serialize, Deserialize)]
@@ -255,6 +436,20 @@ pub struct PostgresAggregateFunction {
 }
 
 impl PostgresAggregateFunction {
+    pub fn get_drop_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        format!(
+            "drop aggregate {}.{}({});",
+            schema.name.quote(identifier_quoter, TypeOrFunctionName),
+            self.function_name
+                .quote(identifier_quoter, TypeOrFunctionName),
+            get_identity_arguments(&self.arguments)
+        )
+    }
+
     pub fn get_create_statement(
         &self,
         schema: &PostgresSchema,
@@ -348,7 +543,13 @@ impl PostgresAggregateFunction {
 
         if let Some(sort_operator) = &self.sort_operator {
             sql.push_str(",\n\tsortop=");
-            sql.push_str(sort_operator);
+            // Operators outside the search path are schema qualified, which is only allowed
+            // using the operator() syntax.
+            if sort_operator.contains('.') {
+                sql.push_str(&format!("operator({})", sort_operator));
+            } else {
+                sql.push_str(sort_operator);
+            }
         }
 
         match self.parallel {
@@ -362,3 +563,54 @@ impl PostgresAggregateFunction {
         sql
     }
 }
+
+/// Removes the default values from the arguments of a function, as written by
+/// `pg_get_function_arguments`, as they cannot be part of the arguments that identify the
+/// function, such as in `drop function`.
+fn get_identity_arguments(arguments: &str) -> String {
+    let mut identity_arguments = Vec::new();
+    let mut depth = 0;
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (index, c) in arguments.char_indices() {
+        match c {
+            '\'' => in_quotes = !in_quotes,
+            '(' if !in_quotes => depth += 1,
+            ')' if !in_quotes => depth -= 1,
+            ',' if !in_quotes && depth == 0 => {
+                identity_arguments.push(&arguments[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    identity_arguments.push(&arguments[start..]);
+
+    identity_arguments
+        .into_iter()
+        .map(|argument| {
+            let argument = argument.trim();
+            match argument.find(" DEFAULT ") {
+                Some(index) => &argument[..index],
+                None => argument,
+            }
+        })
+        .filter(|argument| !argument.is_empty())
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_arguments_leave_out_defaults() {
+        assert_eq!(get_identity_arguments(""), "");
+        assert_eq!(get_identity_arguments("a integer, b text"), "a integer, b text");
+        assert_eq!(
+            get_identity_arguments("a integer DEFAULT 1, b text DEFAULT 'x, y'::text, c numeric DEFAULT round((1.5)::numeric, 0)"),
+            "a integer, b text, c numeric"
+        );
+    }
+}
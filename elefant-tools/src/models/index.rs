@@ -1,10 +1,12 @@
+use crate::models::comment::get_comment_change_statement;
 use crate::helpers::StringExt;
 use crate::object_id::ObjectId;
 use crate::quoting::AttemptedKeywordUsage::ColumnName;
 use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
-use crate::{PostgresSchema, PostgresTable};
+use crate::{PostgresConstraint, PostgresSchema, PostgresTable, PostgresView, TableTypeDetails};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::collections::HashSet;
 
 #[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
 pub struct PostgresIndex {
@@ -15,8 +17,17 @@ pub struct PostgresIndex {
     pub included_columns: Vec<PostgresIndexIncludedColumn>,
     pub index_constraint_type: PostgresIndexType,
     pub storage_parameters: Vec<String>,
+    /// The tablespace the index is stored in, if it is not the default tablespace of the database.
+    pub tablespace: Option<String>,
     pub comment: Option<String>,
+    /// The index is not valid yet. For an index on a partitioned table this means it was created
+    /// with `on only`, and not every partition has an index attached to it yet.
+    pub not_valid: bool,
+    /// The index on the partitioned parent table this index is attached to, if any.
+    pub parent_index: Option<String>,
     pub object_id: ObjectId,
+    /// Functions used in the index expressions or predicate.
+    pub depends_on: Vec<ObjectId>,
 }
 
 #[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
@@ -48,18 +59,181 @@ impl PostgresIndex {
         schema: &PostgresSchema,
         table: &PostgresTable,
         identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let only = self.not_valid
+            && matches!(table.table_type, TableTypeDetails::PartitionedParentTable { .. });
+        let mut command =
+            self.build_create_index_command(schema, &table.name, identifier_quoter, false, only);
+
+        if self.index_constraint_type != PostgresIndexType::PrimaryKey {
+            if let Some(comment) = self.get_set_comment_statement(identifier_quoter) {
+                command.push('\n');
+                command.push_str(&comment);
+            }
+        }
+
+        command
+    }
+
+    /// Like [PostgresIndex::get_create_index_command], but the index is built without blocking
+    /// writes to the table. Primary keys cannot be built concurrently, so they are created normally.
+    ///
+    /// The comment is not included, as `create index concurrently` cannot run together with other
+    /// statements. Use [PostgresIndex::get_set_comment_statement] afterwards instead.
+    pub fn get_create_index_concurrently_command(
+        &self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        self.build_create_index_command(schema, &table.name, identifier_quoter, true, false)
+    }
+
+    /// Like [PostgresIndex::get_create_index_command], but for an index on a materialized view.
+    pub fn get_create_view_index_command(
+        &self,
+        schema: &PostgresSchema,
+        view: &PostgresView,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let mut command =
+            self.build_create_index_command(schema, &view.name, identifier_quoter, false, false);
+
+        if let Some(comment) = self.get_set_comment_statement(identifier_quoter) {
+            command.push('\n');
+            command.push_str(&comment);
+        }
+
+        command
+    }
+
+    /// If the other index is built the same way as this one, so recreating it would not change
+    /// anything. The comment is ignored, and so are the storage parameters unless `strict` is set.
+    ///
+    /// Sort orders are compared with the defaults filled in, as `asc nulls last` and no explicit
+    /// order are the same, and the included columns are compared regardless of their order.
+    pub fn is_equivalent_to(&self, other: &PostgresIndex, strict: bool) -> bool {
+        self.index_type == other.index_type
+            && self.index_constraint_type == other.index_constraint_type
+            && self.predicate == other.predicate
+            && self.key_columns.len() == other.key_columns.len()
+            && self
+                .key_columns
+                .iter()
+                .zip(&other.key_columns)
+                .all(|(a, b)| a.is_equivalent_to(b))
+            && self.get_included_column_names() == other.get_included_column_names()
+            && (!strict || self.storage_parameters == other.storage_parameters)
+    }
+
+    fn get_included_column_names(&self) -> HashSet<&str> {
+        self.included_columns.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    /// Removes the index from the table, including the constraint using it, if any.
+    pub fn get_drop_statement(
+        &self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let constraint_name = if self.index_constraint_type == PostgresIndexType::PrimaryKey {
+            Some(&self.name)
+        } else {
+            table.constraints.iter().find_map(|c| match c {
+                PostgresConstraint::Unique(uk) if uk.unique_index_name == self.name => {
+                    Some(&uk.name)
+                }
+                _ => None,
+            })
+        };
+
+        match constraint_name {
+            Some(constraint_name) => format!(
+                "alter table {}.{} drop constraint {};",
+                schema.name.quote(identifier_quoter, ColumnName),
+                table.name.quote(identifier_quoter, ColumnName),
+                constraint_name.quote(identifier_quoter, ColumnName)
+            ),
+            None => format!(
+                "drop index {}.{};",
+                schema.name.quote(identifier_quoter, ColumnName),
+                self.name.quote(identifier_quoter, ColumnName)
+            ),
+        }
+    }
+
+    /// Attaches this index to the index of the partitioned parent table, if it is attached to one.
+    /// Only needed when the parent index was created with `on only`, as postgres attaches or
+    /// creates the indexes of the partitions itself otherwise.
+    pub fn get_attach_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        self.parent_index.as_ref().map(|parent_index| {
+            format!(
+                "alter index {}.{} attach partition {}.{};",
+                schema.name.quote(identifier_quoter, ColumnName),
+                parent_index.quote(identifier_quoter, ColumnName),
+                schema.name.quote(identifier_quoter, ColumnName),
+                self.name.quote(identifier_quoter, ColumnName)
+            )
+        })
+    }
+
+    /// Gets the statement changing the comment of the `existing` index to the comment of this one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresIndex,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "index {}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName)
+        );
+
+        get_comment_change_statement(&object, &self.comment, &existing.comment)
+    }
+
+    pub fn get_set_comment_statement(&self, identifier_quoter: &IdentifierQuoter) -> Option<String> {
+        self.comment.as_ref().map(|comment| {
+            format!(
+                "comment on index {} is {};",
+                self.name.quote(identifier_quoter, ColumnName),
+                quote_value_string(comment)
+            )
+        })
+    }
+
+    fn build_create_index_command(
+        &self,
+        schema: &PostgresSchema,
+        relation_name: &str,
+        identifier_quoter: &IdentifierQuoter,
+        concurrently: bool,
+        only: bool,
     ) -> String {
         if PostgresIndexType::PrimaryKey == self.index_constraint_type {
             return format!(
-                "alter table {}.{} add constraint {} primary key ({});",
+                "alter table {}.{} add constraint {} primary key ({}){};",
                 schema.name.quote(identifier_quoter, ColumnName),
-                table.name.quote(identifier_quoter, ColumnName),
+                relation_name.quote(identifier_quoter, ColumnName),
                 self.name.quote(identifier_quoter, ColumnName),
                 self.key_columns
                     .iter()
                     .map(|c| c.name.quote(identifier_quoter, ColumnName))
                     .collect::<Vec<String>>()
-                    .join(", ")
+                    .join(", "),
+                self.tablespace
+                    .as_ref()
+                    .map(|t| format!(
+                        " using index tablespace {}",
+                        t.quote(identifier_quoter, ColumnName)
+                    ))
+                    .unwrap_or_default()
             );
         }
 
@@ -69,11 +243,13 @@ impl PostgresIndex {
         };
 
         let mut command = format!(
-            "create {}index {} on {}.{} using {} (",
+            "create {}index {}{} on {}{}.{} using {} (",
             index_type,
+            if concurrently { "concurrently " } else { "" },
             self.name.quote(identifier_quoter, ColumnName),
+            if only { "only " } else { "" },
             schema.name.quote(identifier_quoter, ColumnName),
-            table.name.quote(identifier_quoter, ColumnName),
+            relation_name.quote(identifier_quoter, ColumnName),
             self.index_type
         );
 
@@ -84,6 +260,16 @@ impl PostgresIndex {
 
             command.push_str(&column.name);
 
+            if let Some(collation) = &column.collation {
+                command.push_str(" collate ");
+                command.push_str(collation);
+            }
+
+            if let Some(operator_class) = &column.operator_class {
+                command.push(' ');
+                command.push_str(operator_class);
+            }
+
             match column.direction {
                 Some(PostgresIndexColumnDirection::Ascending) => {
                     command.push_str(" asc");
@@ -133,6 +319,11 @@ impl PostgresIndex {
             command.push(')');
         }
 
+        if let Some(tablespace) = &self.tablespace {
+            command.push_str(" tablespace ");
+            command.push_str(&tablespace.quote(identifier_quoter, ColumnName));
+        }
+
         if let Some(ref predicate) = self.predicate {
             command.push_str(" where ");
             command.push_str(predicate);
@@ -140,14 +331,6 @@ impl PostgresIndex {
 
         command.push(';');
 
-        if let Some(comment) = &self.comment {
-            command.push_str("\ncomment on index ");
-            command.push_str(&self.name.quote(identifier_quoter, ColumnName));
-            command.push_str(" is ");
-            command.push_str(&quote_value_string(comment));
-            command.push(';');
-        }
-
         command
     }
 }
@@ -158,6 +341,38 @@ pub struct PostgresIndexKeyColumn {
     pub ordinal_position: i32,
     pub direction: Option<PostgresIndexColumnDirection>,
     pub nulls_order: Option<PostgresIndexNullsOrder>,
+    /// The operator class of the column, if it isn't the default one for the data type,
+    /// for example `varchar_pattern_ops`.
+    pub operator_class: Option<String>,
+    /// The collation of the column, if it differs from the collation of the indexed column
+    /// or data type.
+    pub collation: Option<String>,
+}
+
+impl PostgresIndexKeyColumn {
+    /// Compares the columns with the default sort order filled in. Postgres sorts nulls last
+    /// when ascending and first when descending, unless told otherwise.
+    fn is_equivalent_to(&self, other: &PostgresIndexKeyColumn) -> bool {
+        self.name == other.name
+            && self.operator_class == other.operator_class
+            && self.collation == other.collation
+            && self.get_direction() == other.get_direction()
+            && self.get_nulls_order() == other.get_nulls_order()
+    }
+
+    fn get_direction(&self) -> PostgresIndexColumnDirection {
+        self.direction
+            .clone()
+            .unwrap_or(PostgresIndexColumnDirection::Ascending)
+    }
+
+    fn get_nulls_order(&self) -> PostgresIndexNullsOrder {
+        match (&self.nulls_order, self.get_direction()) {
+            (Some(nulls_order), _) => nulls_order.clone(),
+            (None, PostgresIndexColumnDirection::Ascending) => PostgresIndexNullsOrder::Last,
+            (None, PostgresIndexColumnDirection::Descending) => PostgresIndexNullsOrder::First,
+        }
+    }
 }
 
 impl Ord for PostgresIndexKeyColumn {
@@ -201,3 +416,199 @@ impl PartialOrd for PostgresIndexIncludedColumn {
         Some(self.cmp(other))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_column(
+        name: &str,
+        direction: Option<PostgresIndexColumnDirection>,
+        nulls_order: Option<PostgresIndexNullsOrder>,
+    ) -> PostgresIndexKeyColumn {
+        PostgresIndexKeyColumn {
+            name: name.to_string(),
+            ordinal_position: 1,
+            direction,
+            nulls_order,
+            operator_class: None,
+            collation: None,
+        }
+    }
+
+    fn included_column(name: &str, ordinal_position: i32) -> PostgresIndexIncludedColumn {
+        PostgresIndexIncludedColumn {
+            name: name.to_string(),
+            ordinal_position,
+        }
+    }
+
+    fn unique_index(key_columns: Vec<PostgresIndexKeyColumn>) -> PostgresIndex {
+        PostgresIndex {
+            name: "my_index".to_string(),
+            key_columns,
+            index_type: "btree".to_string(),
+            index_constraint_type: PostgresIndexType::Unique {
+                nulls_distinct: true,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_sort_order_is_equivalent_to_no_sort_order() {
+        let implicit = unique_index(vec![key_column("a", None, None)]);
+        let explicit = unique_index(vec![key_column(
+            "a",
+            Some(PostgresIndexColumnDirection::Ascending),
+            Some(PostgresIndexNullsOrder::Last),
+        )]);
+
+        assert!(implicit.is_equivalent_to(&explicit, true));
+        assert!(explicit.is_equivalent_to(&implicit, true));
+    }
+
+    #[test]
+    fn descending_defaults_to_nulls_first() {
+        let implicit = unique_index(vec![key_column(
+            "a",
+            Some(PostgresIndexColumnDirection::Descending),
+            None,
+        )]);
+        let nulls_first = unique_index(vec![key_column(
+            "a",
+            Some(PostgresIndexColumnDirection::Descending),
+            Some(PostgresIndexNullsOrder::First),
+        )]);
+        let nulls_last = unique_index(vec![key_column(
+            "a",
+            Some(PostgresIndexColumnDirection::Descending),
+            Some(PostgresIndexNullsOrder::Last),
+        )]);
+
+        assert!(implicit.is_equivalent_to(&nulls_first, false));
+        assert!(!implicit.is_equivalent_to(&nulls_last, false));
+    }
+
+    #[test]
+    fn different_sort_orders_are_not_equivalent() {
+        let ascending = unique_index(vec![key_column("a", None, None)]);
+        let descending = unique_index(vec![key_column(
+            "a",
+            Some(PostgresIndexColumnDirection::Descending),
+            Some(PostgresIndexNullsOrder::First),
+        )]);
+        let nulls_first = unique_index(vec![key_column(
+            "a",
+            None,
+            Some(PostgresIndexNullsOrder::First),
+        )]);
+
+        assert!(!ascending.is_equivalent_to(&descending, false));
+        assert!(!ascending.is_equivalent_to(&nulls_first, false));
+    }
+
+    #[test]
+    fn included_columns_are_compared_as_sets() {
+        let mut a = unique_index(vec![key_column("a", None, None)]);
+        a.included_columns = vec![included_column("b", 2), included_column("c", 3)];
+        let mut b = unique_index(vec![key_column("a", None, None)]);
+        b.included_columns = vec![included_column("c", 2), included_column("b", 3)];
+        let mut c = unique_index(vec![key_column("a", None, None)]);
+        c.included_columns = vec![included_column("b", 2)];
+
+        assert!(a.is_equivalent_to(&b, false));
+        assert!(!a.is_equivalent_to(&c, false));
+        assert!(!a.is_equivalent_to(&unique_index(vec![key_column("a", None, None)]), false));
+    }
+
+    #[test]
+    fn storage_parameters_are_only_compared_when_strict() {
+        let a = unique_index(vec![key_column("a", None, None)]);
+        let mut b = unique_index(vec![key_column("a", None, None)]);
+        b.storage_parameters = vec!["fillfactor=70".to_string()];
+
+        assert!(a.is_equivalent_to(&b, false));
+        assert!(!a.is_equivalent_to(&b, true));
+    }
+
+    #[test]
+    fn key_column_order_matters() {
+        let a = unique_index(vec![key_column("a", None, None), key_column("b", None, None)]);
+        let b = unique_index(vec![key_column("b", None, None), key_column("a", None, None)]);
+
+        assert!(!a.is_equivalent_to(&b, false));
+    }
+
+    #[test]
+    fn index_kind_matters() {
+        let unique = unique_index(vec![key_column("a", None, None)]);
+        let mut plain = unique_index(vec![key_column("a", None, None)]);
+        plain.index_constraint_type = PostgresIndexType::Index;
+        let mut hash = unique_index(vec![key_column("a", None, None)]);
+        hash.index_type = "hash".to_string();
+
+        assert!(!unique.is_equivalent_to(&plain, false));
+        assert!(!unique.is_equivalent_to(&hash, false));
+    }
+
+    #[test]
+    fn create_commands_include_tablespace() {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..Default::default()
+        };
+        let table = PostgresTable::new("items");
+        let quoter = IdentifierQuoter::empty();
+
+        let mut index = unique_index(vec![key_column("a", None, None)]);
+        index.tablespace = Some("fast_disk".to_string());
+        index.predicate = Some("(a > 0)".to_string());
+        assert_eq!(
+            index.get_create_index_command(&schema, &table, &quoter),
+            "create unique index my_index on public.items using btree (a) tablespace fast_disk where (a > 0);"
+        );
+
+        index.index_constraint_type = PostgresIndexType::PrimaryKey;
+        assert_eq!(
+            index.get_create_index_command(&schema, &table, &quoter),
+            "alter table public.items add constraint my_index primary key (a) using index tablespace fast_disk;"
+        );
+    }
+
+    #[test]
+    fn invalid_indexes_on_partitioned_tables_are_created_only_on_the_parent() {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..Default::default()
+        };
+        let mut table = PostgresTable::new("items");
+        let quoter = IdentifierQuoter::empty();
+
+        let mut index = unique_index(vec![key_column("a", None, None)]);
+        index.not_valid = true;
+        assert_eq!(
+            index.get_create_index_command(&schema, &table, &quoter),
+            "create unique index my_index on public.items using btree (a);"
+        );
+
+        table.table_type = TableTypeDetails::PartitionedParentTable {
+            partition_columns: crate::PartitionedTableColumns::Columns(vec!["a".to_string()]),
+            default_partition_name: None,
+            partition_strategy: crate::TablePartitionStrategy::Range,
+        };
+        assert_eq!(
+            index.get_create_index_command(&schema, &table, &quoter),
+            "create unique index my_index on only public.items using btree (a);"
+        );
+
+        let mut child_index = unique_index(vec![key_column("a", None, None)]);
+        child_index.name = "items_1_a_idx".to_string();
+        assert_eq!(child_index.get_attach_statement(&schema, &quoter), None);
+        child_index.parent_index = Some("my_index".to_string());
+        assert_eq!(
+            child_index.get_attach_statement(&schema, &quoter).unwrap(),
+            "alter index public.my_index attach partition public.items_1_a_idx;"
+        );
+    }
+}
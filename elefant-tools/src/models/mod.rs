@@ -1,5 +1,7 @@
 mod check_constraint;
 mod column;
+mod comment;
+mod composite_type;
 mod constraint;
 mod database;
 mod domain;
@@ -10,7 +12,9 @@ mod function;
 mod hypertable_compression;
 mod hypertable_retention;
 mod index;
+mod policy;
 mod postgres_thing_with_dependencies;
+mod privilege;
 mod schema;
 mod sequence;
 mod table;
@@ -21,6 +25,7 @@ mod view;
 
 pub use check_constraint::*;
 pub use column::*;
+pub use composite_type::*;
 pub use constraint::*;
 pub use database::*;
 pub use domain::*;
@@ -31,7 +36,10 @@ pub use function::*;
 pub use hypertable_compression::*;
 pub use hypertable_retention::*;
 pub use index::*;
+pub use policy::*;
 pub(crate) use postgres_thing_with_dependencies::*;
+pub use privilege::PostgresPrivilege;
+pub(crate) use privilege::PUBLIC_GRANTEE;
 pub use schema::*;
 pub use sequence::*;
 pub use table::*;
@@ -0,0 +1,137 @@
+use crate::models::PUBLIC_GRANTEE;
+use crate::object_id::ObjectId;
+use crate::postgres_client_wrapper::FromPgChar;
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::{IdentifierQuoter, Quotable};
+use crate::{ElefantToolsError, PostgresSchema};
+use serde::{Deserialize, Serialize};
+
+/// A row level security policy created with `create policy`. The policies only apply once row
+/// level security is enabled on the table, see [crate::PostgresTable::rls_enabled].
+#[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
+pub struct PostgresRowLevelSecurityPolicy {
+    pub name: String,
+    pub table_name: String,
+    pub command: PostgresPolicyCommand,
+    /// Permissive policies are combined with `or`, restrictive policies with `and`.
+    pub permissive: bool,
+    /// The roles the policy applies to, where `public` means every role.
+    pub roles: Vec<String>,
+    pub using_expression: Option<String>,
+    pub check_expression: Option<String>,
+    pub object_id: ObjectId,
+    /// The functions and tables used by the expressions.
+    pub depends_on: Vec<ObjectId>,
+}
+
+#[derive(Debug, Eq, PartialEq, Default, Copy, Clone, Serialize, Deserialize)]
+pub enum PostgresPolicyCommand {
+    #[default]
+    All,
+    Select,
+    Insert,
+    Update,
+    Delete,
+}
+
+impl FromPgChar for PostgresPolicyCommand {
+    fn from_pg_char(c: char) -> Result<Self, ElefantToolsError> {
+        match c {
+            '*' => Ok(PostgresPolicyCommand::All),
+            'r' => Ok(PostgresPolicyCommand::Select),
+            'a' => Ok(PostgresPolicyCommand::Insert),
+            'w' => Ok(PostgresPolicyCommand::Update),
+            'd' => Ok(PostgresPolicyCommand::Delete),
+            _ => Err(ElefantToolsError::UnknownPolicyCommand(c.to_string())),
+        }
+    }
+}
+
+impl PostgresPolicyCommand {
+    fn get_command_name(&self) -> &str {
+        match self {
+            PostgresPolicyCommand::All => "all",
+            PostgresPolicyCommand::Select => "select",
+            PostgresPolicyCommand::Insert => "insert",
+            PostgresPolicyCommand::Update => "update",
+            PostgresPolicyCommand::Delete => "delete",
+        }
+    }
+}
+
+impl PostgresRowLevelSecurityPolicy {
+    pub fn get_create_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        let mut sql = "create policy ".to_string();
+
+        sql.push_str(&self.name.quote(identifier_quoter, ColumnName));
+        sql.push_str(" on ");
+        sql.push_str(&schema.name.quote(identifier_quoter, ColumnName));
+        sql.push('.');
+        sql.push_str(&self.table_name.quote(identifier_quoter, ColumnName));
+
+        if !self.permissive {
+            sql.push_str(" as restrictive");
+        }
+
+        sql.push_str(" for ");
+        sql.push_str(self.command.get_command_name());
+
+        sql.push_str(" to ");
+        let roles = self.roles.iter().map(|role| {
+            if role == PUBLIC_GRANTEE {
+                PUBLIC_GRANTEE.to_string()
+            } else {
+                role.quote(identifier_quoter, ColumnName)
+            }
+        });
+        sql.push_str(&roles.collect::<Vec<_>>().join(", "));
+
+        if let Some(using) = &self.using_expression {
+            sql.push_str(" using (");
+            sql.push_str(using);
+            sql.push(')');
+        }
+
+        if let Some(check) = &self.check_expression {
+            sql.push_str(" with check (");
+            sql.push_str(check);
+            sql.push(')');
+        }
+
+        sql.push(';');
+
+        sql
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_policies() {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..Default::default()
+        };
+        let policy = PostgresRowLevelSecurityPolicy {
+            name: "own_documents".to_string(),
+            table_name: "documents".to_string(),
+            command: PostgresPolicyCommand::Update,
+            permissive: false,
+            roles: vec!["app_user".to_string(), "auditor".to_string()],
+            using_expression: Some("(owner = CURRENT_USER)".to_string()),
+            check_expression: Some("(length(title) > 0)".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.get_create_statement(&schema, &IdentifierQuoter::empty()),
+            "create policy own_documents on public.documents as restrictive for update to app_user, auditor using ((owner = CURRENT_USER)) with check ((length(title) > 0));"
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use crate::object_id::HaveDependencies;
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::Quotable;
 use crate::{
-    ObjectId, PostgresAggregateFunction, PostgresDomain, PostgresFunction, PostgresSchema,
-    PostgresTable, PostgresView,
+    FunctionKind, ObjectId, PostgresAggregateFunction, PostgresCompositeType, PostgresDomain,
+    PostgresEnum, PostgresFunction, PostgresSchema, PostgresTable, PostgresView,
 };
 
 pub(crate) enum PostgresThingWithDependencies<'a> {
@@ -10,6 +12,8 @@ pub(crate) enum PostgresThingWithDependencies<'a> {
     Function(&'a PostgresFunction, &'a PostgresSchema),
     AggregateFunction(&'a PostgresAggregateFunction, &'a PostgresSchema),
     Domain(&'a PostgresDomain, &'a PostgresSchema),
+    Enum(&'a PostgresEnum, &'a PostgresSchema),
+    CompositeType(&'a PostgresCompositeType, &'a PostgresSchema),
 }
 
 impl HaveDependencies for &PostgresThingWithDependencies<'_> {
@@ -22,6 +26,10 @@ impl HaveDependencies for &PostgresThingWithDependencies<'_> {
                 &aggregate_function.depends_on
             }
             PostgresThingWithDependencies::Domain(domain, _) => &domain.depends_on,
+            PostgresThingWithDependencies::Enum(enumeration, _) => enumeration.depends_on(),
+            PostgresThingWithDependencies::CompositeType(composite_type, _) => {
+                &composite_type.depends_on
+            }
         }
     }
 
@@ -34,8 +42,33 @@ impl HaveDependencies for &PostgresThingWithDependencies<'_> {
                 aggregate_function.object_id
             }
             PostgresThingWithDependencies::Domain(domain, _) => domain.object_id,
+            PostgresThingWithDependencies::Enum(enumeration, _) => enumeration.object_id,
+            PostgresThingWithDependencies::CompositeType(composite_type, _) => {
+                composite_type.object_id
+            }
         }
     }
+    fn object_name(&self) -> String {
+        let (schema, name) = match self {
+            PostgresThingWithDependencies::Table(table, schema) => (schema, &table.name),
+            PostgresThingWithDependencies::View(view, schema) => (schema, &view.name),
+            PostgresThingWithDependencies::Function(function, schema) => {
+                (schema, &function.function_name)
+            }
+            PostgresThingWithDependencies::AggregateFunction(aggregate_function, schema) => {
+                (schema, &aggregate_function.function_name)
+            }
+            PostgresThingWithDependencies::Domain(domain, schema) => (schema, &domain.name),
+            PostgresThingWithDependencies::Enum(enumeration, schema) => {
+                (schema, &enumeration.name)
+            }
+            PostgresThingWithDependencies::CompositeType(composite_type, schema) => {
+                (schema, &composite_type.name)
+            }
+        };
+
+        format!("{}.{}", schema.name, name)
+    }
 }
 
 impl PostgresThingWithDependencies<'_> {
@@ -56,6 +89,81 @@ impl PostgresThingWithDependencies<'_> {
             PostgresThingWithDependencies::Domain(domain, schema) => {
                 domain.get_create_sql(schema, identifier_quoter)
             }
+            PostgresThingWithDependencies::Enum(enumeration, schema) => {
+                enumeration.get_create_statement(schema, identifier_quoter)
+            }
+            PostgresThingWithDependencies::CompositeType(composite_type, schema) => {
+                composite_type.get_create_statement(schema, identifier_quoter)
+            }
+        }
+    }
+
+    /// The kind of the thing, as used in DDL statements.
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            PostgresThingWithDependencies::Table(..) => "table",
+            PostgresThingWithDependencies::View(view, _) if view.is_materialized => {
+                "materialized view"
+            }
+            PostgresThingWithDependencies::View(..) => "view",
+            PostgresThingWithDependencies::Function(function, _)
+                if function.kind == FunctionKind::Procedure =>
+            {
+                "procedure"
+            }
+            PostgresThingWithDependencies::Function(..) => "function",
+            PostgresThingWithDependencies::AggregateFunction(..) => "aggregate",
+            PostgresThingWithDependencies::Domain(..) => "domain",
+            PostgresThingWithDependencies::Enum(..)
+            | PostgresThingWithDependencies::CompositeType(..) => "type",
+        }
+    }
+
+    pub fn get_drop_sql(&self, identifier_quoter: &crate::IdentifierQuoter) -> String {
+        let (schema, name) = match self {
+            PostgresThingWithDependencies::Function(function, schema) => {
+                return function.get_drop_statement(schema, identifier_quoter);
+            }
+            PostgresThingWithDependencies::AggregateFunction(aggregate_function, schema) => {
+                return aggregate_function.get_drop_statement(schema, identifier_quoter);
+            }
+            PostgresThingWithDependencies::Table(table, schema) => (schema, &table.name),
+            PostgresThingWithDependencies::View(view, schema) => (schema, &view.name),
+            PostgresThingWithDependencies::Domain(domain, schema) => (schema, &domain.name),
+            PostgresThingWithDependencies::Enum(enumeration, schema) => (schema, &enumeration.name),
+            PostgresThingWithDependencies::CompositeType(composite_type, schema) => {
+                (schema, &composite_type.name)
+            }
+        };
+
+        format!(
+            "drop {} {}.{};",
+            self.get_kind(),
+            schema.name.quote(identifier_quoter, ColumnName),
+            name.quote(identifier_quoter, ColumnName)
+        )
+    }
+
+    /// Gets the statements granting and revoking the privileges of the thing, for the things
+    /// that have privileges.
+    pub fn get_privilege_statements(
+        &self,
+        identifier_quoter: &crate::IdentifierQuoter,
+    ) -> Option<String> {
+        match self {
+            PostgresThingWithDependencies::Table(table, schema) => {
+                table.get_privilege_statements(schema, identifier_quoter)
+            }
+            PostgresThingWithDependencies::View(view, schema) => {
+                view.get_privilege_statements(schema, identifier_quoter)
+            }
+            PostgresThingWithDependencies::Function(function, schema) => {
+                function.get_privilege_statements(schema, identifier_quoter)
+            }
+            PostgresThingWithDependencies::AggregateFunction(..)
+            | PostgresThingWithDependencies::Domain(..)
+            | PostgresThingWithDependencies::Enum(..)
+            | PostgresThingWithDependencies::CompositeType(..) => None,
         }
     }
 }
@@ -0,0 +1,104 @@
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::{IdentifierQuoter, Quotable};
+use serde::{Deserialize, Serialize};
+
+/// The name Postgres uses for the privileges granted to every role.
+pub(crate) const PUBLIC_GRANTEE: &str = "public";
+
+/// A privilege on an object that differs from the privileges the object has when it is created,
+/// either because it was granted to a role, or because it was revoked, such as the `EXECUTE`
+/// privilege `public` has on new functions. The privileges of the owner of the object are not
+/// included, as the objects are owned by whoever creates them in the destination.
+#[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
+pub struct PostgresPrivilege {
+    /// The role the privilege is granted to, or `public` for every role.
+    pub grantee: String,
+    /// The privilege, such as `SELECT` or `USAGE`.
+    pub privilege: String,
+    pub with_grant_option: bool,
+    /// If the privilege is revoked instead of granted.
+    pub revoked: bool,
+}
+
+impl PostgresPrivilege {
+    /// Gets the grant or revoke statement for the privilege, where `object` is the kind and the
+    /// quoted name of the object, such as `table public.customers`.
+    pub fn get_statement(&self, object: &str, identifier_quoter: &IdentifierQuoter) -> String {
+        let grantee = if self.grantee == PUBLIC_GRANTEE {
+            PUBLIC_GRANTEE.to_string()
+        } else {
+            self.grantee.quote(identifier_quoter, ColumnName)
+        };
+
+        if self.revoked {
+            format!("revoke {} on {} from {};", self.privilege.to_lowercase(), object, grantee)
+        } else if self.with_grant_option {
+            format!(
+                "grant {} on {} to {} with grant option;",
+                self.privilege.to_lowercase(),
+                object,
+                grantee
+            )
+        } else {
+            format!("grant {} on {} to {};", self.privilege.to_lowercase(), object, grantee)
+        }
+    }
+}
+
+/// Gets the statements for all the privileges of an object, or `None` if it has the privileges
+/// it has when it is created.
+pub(crate) fn get_privilege_statements(
+    privileges: &[PostgresPrivilege],
+    object: &str,
+    identifier_quoter: &IdentifierQuoter,
+) -> Option<String> {
+    if privileges.is_empty() {
+        return None;
+    }
+
+    Some(
+        privileges
+            .iter()
+            .map(|p| p.get_statement(object, identifier_quoter))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn privilege_statements() {
+        let quoter = IdentifierQuoter::empty();
+        let privileges = vec![
+            PostgresPrivilege {
+                grantee: PUBLIC_GRANTEE.to_string(),
+                privilege: "EXECUTE".to_string(),
+                revoked: true,
+                ..Default::default()
+            },
+            PostgresPrivilege {
+                grantee: "Reporting".to_string(),
+                privilege: "SELECT".to_string(),
+                ..Default::default()
+            },
+            PostgresPrivilege {
+                grantee: "app".to_string(),
+                privilege: "UPDATE".to_string(),
+                with_grant_option: true,
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            get_privilege_statements(&privileges, "table public.customers", &quoter).unwrap(),
+            "revoke execute on table public.customers from public;\ngrant select on table public.customers to \"Reporting\";\ngrant update on table public.customers to app with grant option;"
+        );
+        assert_eq!(
+            get_privilege_statements(&[], "table public.customers", &quoter),
+            None
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::models::comment::get_comment_change_statement;
 use crate::models::enumeration::PostgresEnum;
 use crate::models::sequence::PostgresSequence;
 use crate::models::table::PostgresTable;
@@ -5,7 +6,11 @@ use crate::models::view::PostgresView;
 use crate::object_id::ObjectId;
 use crate::quoting::AttemptedKeywordUsage::ColumnName;
 use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
-use crate::{PostgresAggregateFunction, PostgresDomain, PostgresFunction, PostgresTrigger};
+use crate::models::privilege::get_privilege_statements;
+use crate::{
+    PostgresAggregateFunction, PostgresCompositeType, PostgresDomain, PostgresFunction, PostgresPrivilege,
+    PostgresRowLevelSecurityPolicy, PostgresTrigger,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
@@ -16,11 +21,14 @@ pub struct PostgresSchema {
     pub functions: Vec<PostgresFunction>,
     pub aggregate_functions: Vec<PostgresAggregateFunction>,
     pub triggers: Vec<PostgresTrigger>,
+    pub policies: Vec<PostgresRowLevelSecurityPolicy>,
     pub enums: Vec<PostgresEnum>,
     pub name: String,
     pub comment: Option<String>,
     pub domains: Vec<PostgresDomain>,
+    pub composite_types: Vec<PostgresCompositeType>,
     pub object_id: ObjectId,
+    pub privileges: Vec<PostgresPrivilege>,
 }
 
 impl PostgresSchema {
@@ -31,6 +39,13 @@ impl PostgresSchema {
         )
     }
 
+    /// Gets the statements granting and revoking the privileges of the schema.
+    pub fn get_privilege_statements(&self, identifier_quoter: &IdentifierQuoter) -> Option<String> {
+        let object = format!("schema {}", self.name.quote(identifier_quoter, ColumnName));
+
+        get_privilege_statements(&self.privileges, &object, identifier_quoter)
+    }
+
     pub fn get_set_comment_statement(&self, identifier_quoter: &IdentifierQuoter) -> Option<String> {
         if let Some(comment) = &self.comment {
             let mut sql = String::new();
@@ -45,6 +60,17 @@ impl PostgresSchema {
         }
     }
 
+    /// Gets the statement changing the comment of the `existing` schema to the comment of this one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!("schema {}", self.name.quote(identifier_quoter, ColumnName));
+
+        get_comment_change_statement(&object, &self.comment, &existing.comment)
+    }
+
     pub(crate) fn try_get_table(&self, table_name: &str) -> Option<&PostgresTable> {
         self.tables.iter().find(|t| t.name == table_name)
     }
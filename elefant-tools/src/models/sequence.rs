@@ -1,3 +1,5 @@
+use crate::models::comment::get_comment_change_statement;
+use crate::models::PUBLIC_GRANTEE;
 use crate::object_id::ObjectId;
 use crate::quoting::AttemptedKeywordUsage::ColumnName;
 use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
@@ -45,34 +47,44 @@ impl Default for PostgresSequence {
 }
 
 impl PostgresSequence {
+    /// Creates the sequence. Options that have the default value for the data type and direction
+    /// of the sequence are left out, so the statement stays valid if the defaults change.
     pub fn get_create_statement(
         &self,
         schema: &PostgresSchema,
         identifier_quoter: &IdentifierQuoter,
     ) -> String {
-
-        let mut sql = String::new();
-        if self.is_internally_created {
-            sql.push_str("alter sequence ")
-        } else {
-            sql.push_str("create sequence ");
-        }
+        let mut sql = "create sequence ".to_string();
 
         sql.push_str(&schema.name.quote(identifier_quoter, ColumnName));
         sql.push('.');
         sql.push_str(&self.name.quote(identifier_quoter, ColumnName));
-        sql.push_str(" as ");
-        sql.push_str(&self.data_type);
-        sql.push_str(" increment by ");
-        sql.push_str(&self.increment.to_string());
-        sql.push_str(" minvalue ");
-        sql.push_str(&self.min_value.to_string());
-        sql.push_str(" maxvalue ");
-        sql.push_str(&self.max_value.to_string());
-        sql.push_str(" start ");
-        sql.push_str(&self.start_value.to_string());
-        sql.push_str(" cache ");
-        sql.push_str(&self.cache_size.to_string());
+
+        // bigint is the default data type of sequences.
+        if get_data_type_bounds(&self.data_type) != (i64::MIN, i64::MAX) {
+            sql.push_str(" as ");
+            sql.push_str(&self.data_type);
+        }
+        if self.increment != 1 {
+            sql.push_str(" increment by ");
+            sql.push_str(&self.increment.to_string());
+        }
+        if self.min_value != self.get_default_min_value() {
+            sql.push_str(" minvalue ");
+            sql.push_str(&self.min_value.to_string());
+        }
+        if self.max_value != self.get_default_max_value() {
+            sql.push_str(" maxvalue ");
+            sql.push_str(&self.max_value.to_string());
+        }
+        if self.start_value != self.get_default_start_value() {
+            sql.push_str(" start ");
+            sql.push_str(&self.start_value.to_string());
+        }
+        if self.cache_size != 1 {
+            sql.push_str(" cache ");
+            sql.push_str(&self.cache_size.to_string());
+        }
 
         if self.cycle {
             sql.push_str(" cycle");
@@ -80,19 +92,152 @@ impl PostgresSequence {
 
         sql.push(';');
 
-        if let Some(comment) = &self.comment {
-            sql.push_str("\ncomment on sequence ");
-            sql.push_str(&schema.name.quote(identifier_quoter, ColumnName));
-            sql.push('.');
-            sql.push_str(&self.name.quote(identifier_quoter, ColumnName));
-            sql.push_str(" is ");
-            sql.push_str(&quote_value_string(comment));
-            sql.push(';');
+        if let Some(comment) = self.get_set_comment_statement(schema, identifier_quoter) {
+            sql.push('\n');
+            sql.push_str(&comment);
         }
 
         sql
     }
 
+    /// Alters the `existing` sequence to be set up like this one, or `None` if it already is.
+    ///
+    /// A limit that is the default of both sequences is not changed, as Postgres moves it along
+    /// when the data type changes, so `smallint` and `integer` sequences with default limits only
+    /// differ by their data type.
+    pub fn get_alter_statement(
+        &self,
+        existing: &PostgresSequence,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let same_data_type =
+            get_data_type_bounds(&self.data_type) == get_data_type_bounds(&existing.data_type);
+
+        let limit_differs = |value: i64, default: i64, existing_value: i64, existing_default: i64| {
+            if value == default && existing_value == existing_default {
+                false
+            } else {
+                value != existing_value || !same_data_type
+            }
+        };
+
+        let mut options = String::new();
+
+        if !same_data_type {
+            options.push_str(" as ");
+            options.push_str(&self.data_type);
+        }
+        if self.increment != existing.increment {
+            options.push_str(" increment by ");
+            options.push_str(&self.increment.to_string());
+        }
+        if limit_differs(
+            self.min_value,
+            self.get_default_min_value(),
+            existing.min_value,
+            existing.get_default_min_value(),
+        ) {
+            options.push_str(" minvalue ");
+            options.push_str(&self.min_value.to_string());
+        }
+        if limit_differs(
+            self.max_value,
+            self.get_default_max_value(),
+            existing.max_value,
+            existing.get_default_max_value(),
+        ) {
+            options.push_str(" maxvalue ");
+            options.push_str(&self.max_value.to_string());
+        }
+        if limit_differs(
+            self.start_value,
+            self.get_default_start_value(),
+            existing.start_value,
+            existing.get_default_start_value(),
+        ) {
+            options.push_str(" start with ");
+            options.push_str(&self.start_value.to_string());
+        }
+        if self.cache_size != existing.cache_size {
+            options.push_str(" cache ");
+            options.push_str(&self.cache_size.to_string());
+        }
+        if self.cycle != existing.cycle {
+            options.push_str(if self.cycle { " cycle" } else { " no cycle" });
+        }
+
+        if options.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "alter sequence {}.{}{};",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName),
+            options
+        ))
+    }
+
+    /// The minimum value Postgres uses when none is specified.
+    fn get_default_min_value(&self) -> i64 {
+        if self.increment > 0 {
+            1
+        } else {
+            get_data_type_bounds(&self.data_type).0
+        }
+    }
+
+    /// The maximum value Postgres uses when none is specified.
+    fn get_default_max_value(&self) -> i64 {
+        if self.increment > 0 {
+            get_data_type_bounds(&self.data_type).1
+        } else {
+            -1
+        }
+    }
+
+    /// The start value Postgres uses when none is specified, which depends on the limits.
+    fn get_default_start_value(&self) -> i64 {
+        if self.increment > 0 {
+            self.min_value
+        } else {
+            self.max_value
+        }
+    }
+
+    /// Gets the statement changing the comment of the `existing` sequence to the comment of this
+    /// one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresSequence,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "sequence {}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName)
+        );
+
+        get_comment_change_statement(&object, &self.comment, &existing.comment)
+    }
+
+    pub fn get_set_comment_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        self.comment.as_ref().map(|comment| {
+            format!(
+                "comment on sequence {}.{} is {};",
+                schema.name.quote(identifier_quoter, ColumnName),
+                self.name.quote(identifier_quoter, ColumnName),
+                quote_value_string(comment)
+            )
+        })
+    }
+
     pub fn get_set_value_statement(
         &self,
         schema: &PostgresSchema,
@@ -107,6 +252,40 @@ impl PostgresSequence {
             )
         })
     }
+
+    /// Grants the privileges needed to use the sequence in the default value of a serial column,
+    /// see [crate::CopyDataOptions::grant_sequences_to].
+    pub fn get_grant_usage_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+        roles: &[String],
+    ) -> String {
+        let roles = roles.iter().map(|role| {
+            if role == PUBLIC_GRANTEE {
+                PUBLIC_GRANTEE.to_string()
+            } else {
+                role.quote(identifier_quoter, ColumnName)
+            }
+        });
+
+        format!(
+            "grant usage, select on sequence {}.{} to {};",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName),
+            roles.collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+/// The smallest and largest values of the data type of a sequence. Sequences can only be
+/// `smallint`, `integer` or `bigint`, where `bigint` is the default.
+fn get_data_type_bounds(data_type: &str) -> (i64, i64) {
+    match data_type {
+        "int2" | "smallint" => (i16::MIN as i64, i16::MAX as i64),
+        "int4" | "integer" => (i32::MIN as i64, i32::MAX as i64),
+        _ => (i64::MIN, i64::MAX),
+    }
 }
 
 impl Ord for PostgresSequence {
@@ -120,3 +299,148 @@ impl PartialOrd for PostgresSequence {
         Some(self.cmp(other))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(data_type: &str) -> PostgresSequence {
+        let (_, max_value) = get_data_type_bounds(data_type);
+        PostgresSequence {
+            name: "my_sequence".to_string(),
+            data_type: data_type.to_string(),
+            max_value,
+            ..Default::default()
+        }
+    }
+
+    fn create_statement(sequence: &PostgresSequence) -> String {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..Default::default()
+        };
+        sequence.get_create_statement(&schema, &IdentifierQuoter::empty())
+    }
+
+    fn alter_statement(sequence: &PostgresSequence, existing: &PostgresSequence) -> Option<String> {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..Default::default()
+        };
+        sequence.get_alter_statement(existing, &schema, &IdentifierQuoter::empty())
+    }
+
+    #[test]
+    fn grants_usage_to_roles() {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            sequence("int8").get_grant_usage_statement(
+                &schema,
+                &IdentifierQuoter::empty(),
+                &["app".to_string(), "public".to_string()]
+            ),
+            "grant usage, select on sequence public.my_sequence to app, public;"
+        );
+    }
+
+    #[test]
+    fn default_options_are_left_out() {
+        assert_eq!(
+            create_statement(&sequence("int8")),
+            "create sequence public.my_sequence;"
+        );
+        assert_eq!(
+            create_statement(&sequence("int2")),
+            "create sequence public.my_sequence as int2;"
+        );
+    }
+
+    #[test]
+    fn custom_options_are_included() {
+        let mut seq = sequence("int2");
+        seq.min_value = 10;
+        seq.max_value = 1000;
+        seq.start_value = 20;
+        seq.increment = 5;
+        seq.cache_size = 10;
+        seq.cycle = true;
+
+        assert_eq!(
+            create_statement(&seq),
+            "create sequence public.my_sequence as int2 increment by 5 minvalue 10 maxvalue 1000 start 20 cache 10 cycle;"
+        );
+    }
+
+    #[test]
+    fn descending_sequences_use_the_descending_defaults() {
+        let mut seq = sequence("int4");
+        seq.increment = -1;
+        seq.min_value = i32::MIN as i64;
+        seq.max_value = -1;
+        seq.start_value = -1;
+
+        assert_eq!(
+            create_statement(&seq),
+            "create sequence public.my_sequence as int4 increment by -1;"
+        );
+    }
+
+    #[test]
+    fn start_value_defaults_to_the_custom_min_value() {
+        let mut seq = sequence("int8");
+        seq.min_value = 100;
+        seq.start_value = 100;
+
+        assert_eq!(
+            create_statement(&seq),
+            "create sequence public.my_sequence minvalue 100;"
+        );
+    }
+
+    #[test]
+    fn same_sequences_are_not_altered() {
+        let mut seq = sequence("int4");
+        seq.cycle = true;
+
+        assert_eq!(alter_statement(&seq, &seq.clone()), None);
+    }
+
+    #[test]
+    fn changing_the_data_type_keeps_default_limits() {
+        assert_eq!(
+            alter_statement(&sequence("int4"), &sequence("int2")).as_deref(),
+            Some("alter sequence public.my_sequence as int4;")
+        );
+    }
+
+    #[test]
+    fn changing_the_data_type_keeps_custom_limits() {
+        let mut seq = sequence("int4");
+        seq.max_value = i16::MAX as i64;
+
+        assert_eq!(
+            alter_statement(&seq, &sequence("int2")).as_deref(),
+            Some("alter sequence public.my_sequence as int4 maxvalue 32767;")
+        );
+    }
+
+    #[test]
+    fn cycle_is_turned_on_and_off() {
+        let mut cycling = sequence("int8");
+        cycling.cycle = true;
+        let not_cycling = sequence("int8");
+
+        assert_eq!(
+            alter_statement(&cycling, &not_cycling).as_deref(),
+            Some("alter sequence public.my_sequence cycle;")
+        );
+        assert_eq!(
+            alter_statement(&not_cycling, &cycling).as_deref(),
+            Some("alter sequence public.my_sequence no cycle;")
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::helpers::StringExt;
 use crate::models::column::PostgresColumn;
+use crate::models::comment::get_comment_change_statement;
 use crate::models::constraint::PostgresConstraint;
 use crate::models::hypertable_retention::HypertableRetention;
 use crate::models::index::PostgresIndex;
@@ -12,7 +13,8 @@ use crate::quoting::{
     quote_value_string, AttemptedKeywordUsage, IdentifierQuoter, Quotable, QuotableIter,
 };
 use crate::storage::DataFormat;
-use crate::{ColumnIdentity, default, ElefantToolsError, HypertableCompression, PostgresIndexType};
+use crate::models::privilege::get_privilege_statements;
+use crate::{default, ElefantToolsError, HypertableCompression, PostgresIndexType, PostgresPrivilege};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
@@ -24,9 +26,19 @@ pub struct PostgresTable {
     pub indices: Vec<PostgresIndex>,
     pub comment: Option<String>,
     pub storage_parameters: Vec<String>,
+    /// The table access method, if it is not the default `heap`.
+    pub access_method: Option<String>,
+    /// The tablespace the table is stored in, if it is not the default tablespace of the database.
+    pub tablespace: Option<String>,
     pub table_type: TableTypeDetails,
     pub object_id: ObjectId,
     pub depends_on: Vec<ObjectId>,
+    pub privileges: Vec<PostgresPrivilege>,
+    /// If row level security is enabled, so only the rows allowed by the policies of the table
+    /// are visible.
+    pub rls_enabled: bool,
+    /// If row level security also applies to the owner of the table.
+    pub rls_forced: bool,
 }
 
 impl PostgresTable {
@@ -37,6 +49,132 @@ impl PostgresTable {
         }
     }
 
+    /// Finds a column by its name.
+    pub fn column(&self, column_name: &str) -> Option<&PostgresColumn> {
+        self.columns.iter().find(|c| c.name == column_name)
+    }
+
+    /// Gets the statements granting and revoking the privileges of the table.
+    pub fn get_privilege_statements(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "table {}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName)
+        );
+
+        get_privilege_statements(&self.privileges, &object, identifier_quoter)
+    }
+
+    /// Gets the statements enabling and forcing row level security. They are applied after the
+    /// data has been copied, as forced policies would otherwise restrict the copy as well.
+    pub fn get_row_level_security_statements(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Vec<String> {
+        let escaped_name = format!(
+            "{}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName)
+        );
+
+        let mut statements = Vec::new();
+
+        if self.rls_enabled {
+            statements.push(format!(
+                "alter table {} enable row level security;",
+                escaped_name
+            ));
+        }
+
+        if self.rls_forced {
+            statements.push(format!(
+                "alter table {} force row level security;",
+                escaped_name
+            ));
+        }
+
+        statements
+    }
+
+    /// Gets the statements changing the comments of the `existing` table, and of its columns and
+    /// constraints, to the comments of this table. Columns and constraints that only exist on one
+    /// of the tables are skipped, as they get their comments when they are created.
+    pub fn get_comment_change_statements(
+        &self,
+        existing: &PostgresTable,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Vec<String> {
+        let escaped_relation_name = format!(
+            "{}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName)
+        );
+
+        let table_statement = get_comment_change_statement(
+            &format!("table {}", escaped_relation_name),
+            &self.comment,
+            &existing.comment,
+        );
+
+        let column_statements = self.columns.iter().filter_map(|column| {
+            let existing_column = existing.columns.iter().find(|c| c.name == column.name)?;
+            get_comment_change_statement(
+                &format!(
+                    "column {}.{}",
+                    escaped_relation_name,
+                    column.name.quote(identifier_quoter, ColumnName)
+                ),
+                &column.comment,
+                &existing_column.comment,
+            )
+        });
+
+        let constraint_statements = self.constraints.iter().filter_map(|constraint| {
+            let existing_constraint = existing
+                .constraints
+                .iter()
+                .find(|c| c.name() == constraint.name())?;
+            get_comment_change_statement(
+                &format!(
+                    "constraint {} on {}",
+                    constraint.name().quote(identifier_quoter, ColumnName),
+                    escaped_relation_name
+                ),
+                constraint.comment(),
+                existing_constraint.comment(),
+            )
+        });
+
+        table_statement
+            .into_iter()
+            .chain(column_statements)
+            .chain(constraint_statements)
+            .collect()
+    }
+
+    /// Changes the access method of an existing table to the access method of this table.
+    pub fn get_set_access_method_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> String {
+        format!(
+            "alter table {}.{} set access method {};",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName),
+            self.access_method
+                .as_deref()
+                .unwrap_or("heap")
+                .quote(identifier_quoter, ColumnName)
+        )
+    }
+
     pub fn get_create_statement(
         &self,
         schema: &PostgresSchema,
@@ -64,49 +202,12 @@ impl PostgresTable {
 
             let mut text_row_count = 0;
 
-            for (column_index, column) in self.columns.iter().enumerate() {
-                let column_position = (column_index + 1) as i32;
-
+            for column in &self.columns {
                 if text_row_count > 0 {
                     sql.push(',');
                 }
                 sql.push_str("\n    ");
-                sql.push_str(&column.name.quote(identifier_quoter, ColumnName));
-                sql.push(' ');
-                sql.push_str(&column.data_type.quote(identifier_quoter, ColumnName));
-
-                if let Some(length) = column.data_type_length {
-                    sql.push_str(&format!("({})", length));
-                }
-
-                for _ in 0..column.array_dimensions {
-                    sql.push_str("[]");
-                }
-
-                if !column.is_nullable {
-                    sql.push_str(" not null");
-                }
-
-                if let Some(generated) = &column.generated {
-                    sql.push_str(" generated always as (");
-                    sql.push_str(generated);
-                    sql.push_str(") stored");
-                }
-
-                if let Some(identity) = &column.identity {
-                    sql.push_str(" generated ");
-                    match identity {
-                        ColumnIdentity::GeneratedAlways => sql.push_str("always"),
-                        ColumnIdentity::GeneratedByDefault => sql.push_str("by default")
-                    }
-                    sql.push_str(" as identity");
-
-                    if let Some(seq) = &schema.sequences.iter().find(|s| s.author_table.as_ref().is_some_and(|t| *t == self.name) && s.author_table_column_position == Some(column_position)) {
-                        sql.push_str(" ( sequence name ");
-                        sql.push_str(&seq.name.quote(identifier_quoter, TypeOrFunctionName));
-                        sql.push_str(" )");
-                    }
-                }
+                sql.push_str(&column.get_column_definition(identifier_quoter));
 
                 text_row_count += 1;
             }
@@ -124,19 +225,25 @@ impl PostgresTable {
                     // We don't need to escape the column names here as they are already escaped in the index definition.
                     sql.push_join(", ", index.key_columns.iter().map(|c| &c.name));
                     sql.push(')');
+
+                    if let Some(tablespace) = &index.tablespace {
+                        sql.push_str(" using index tablespace ");
+                        sql.push_str(&tablespace.quote(identifier_quoter, ColumnName));
+                    }
                     text_row_count += 1;
                 }
             }
 
             for constraint in &self.constraints {
                 if let PostgresConstraint::Check(check) = constraint {
+                    if check.not_valid {
+                        continue;
+                    }
                     if text_row_count > 0 {
                         sql.push(',');
                     }
                     sql.push_str("\n    constraint ");
-                    sql.push_str(&check.name.quote(identifier_quoter, ColumnName));
-                    sql.push_str(" check ");
-                    sql.push_str(&check.check_clause);
+                    sql.push_str(&check.get_table_definition(identifier_quoter));
                     text_row_count += 1;
                 }
             }
@@ -184,12 +291,22 @@ impl PostgresTable {
             }
         }
 
+        if let Some(access_method) = &self.access_method {
+            sql.push_str("\nusing ");
+            sql.push_str(&access_method.quote(identifier_quoter, ColumnName));
+        }
+
         if !self.storage_parameters.is_empty() {
             sql.push_str("\nwith (");
             sql.push_join(", ", self.storage_parameters.iter());
             sql.push(')');
         }
 
+        if let Some(tablespace) = &self.tablespace {
+            sql.push_str("\ntablespace ");
+            sql.push_str(&tablespace.quote(identifier_quoter, ColumnName));
+        }
+
         sql.push(';');
 
         if let Some(c) = &self.comment {
@@ -213,6 +330,9 @@ impl PostgresTable {
 
         for constraint in &self.constraints {
             if let PostgresConstraint::Check(constraint) = constraint {
+                if constraint.not_valid {
+                    continue;
+                }
                 if let Some(c) = &constraint.comment {
                     sql.push_str(&format!(
                         "\ncomment on constraint {} on {} is {};",
@@ -224,12 +344,7 @@ impl PostgresTable {
             }
         }
 
-        if let TableTypeDetails::TimescaleHypertable {
-            dimensions,
-            compression: _,
-            retention: _,
-        } = &self.table_type
-        {
+        if let TableTypeDetails::TimescaleHypertable { .. } = &self.table_type {
             for index in &self.indices {
                 if index.index_constraint_type == PostgresIndexType::PrimaryKey {
                     continue;
@@ -246,56 +361,104 @@ impl PostgresTable {
                 }
             }
 
+            if let Some(create_hypertable_sql) = self.get_create_hypertable_statement(
+                schema,
+                identifier_quoter,
+                &HypertableCreateOptions::default(),
+            ) {
+                sql.push_str(&create_hypertable_sql);
+            }
+        }
+
+        sql
+    }
 
+    /// Gets the statements that turns this table into a hypertable, if it is a hypertable.
+    /// The table itself must already exist.
+    pub fn get_create_hypertable_statement(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+        options: &HypertableCreateOptions,
+    ) -> Option<String> {
+        let TableTypeDetails::TimescaleHypertable {
+            dimensions,
+            chunk_target_size,
+            ..
+        } = &self.table_type
+        else {
+            return None;
+        };
 
-            // We don't need timescale to create the indices as we do it later on again based on what was exported.
-            for (idx, dim) in dimensions.iter().enumerate() {
-                match dim {
-                    HypertableDimension::Time {
-                        column_name,
-                        time_interval,
-                    } => {
-                        if idx == 0 {
-                            sql.push_str(&format!("\nselect public.create_hypertable('{}', by_range('{}', INTERVAL '{}'), create_default_indexes => false);", escaped_relation_name, column_name.quote(identifier_quoter, ColumnName), time_interval.to_postgres()));
-                        } else {
-                            sql.push_str(&format!("\nselect public.add_dimension('{}', by_range('{}', INTERVAL '{}'));", escaped_relation_name, column_name.quote(identifier_quoter, ColumnName), time_interval.to_postgres()));
-                        }
-                    }
-                    HypertableDimension::SpaceInterval {
-                        column_name,
-                        integer_interval,
-                    } => {
-                        if idx == 0 {
-                            sql.push_str(&format!("\nselect public.create_hypertable('{}', by_range('{}', {}), create_default_indexes => false);", escaped_relation_name, column_name.quote(identifier_quoter, ColumnName), integer_interval));
-                        } else {
-                            sql.push_str(&format!(
-                                "\nselect public.add_dimension('{}', by_range('{}', {}));",
-                                escaped_relation_name,
-                                column_name.quote(identifier_quoter, ColumnName),
-                                integer_interval
-                            ));
-                        }
-                    }
-                    HypertableDimension::SpacePartitions {
-                        column_name,
-                        num_partitions,
-                    } => {
-                        if idx == 0 {
-                            sql.push_str(&format!("\nselect public.create_hypertable('{}', by_hash('{}', {}), create_default_indexes => false);", escaped_relation_name, column_name.quote(identifier_quoter, ColumnName), num_partitions));
-                        } else {
-                            sql.push_str(&format!(
-                                "\nselect public.add_dimension('{}', by_hash('{}', {}));",
-                                escaped_relation_name,
-                                column_name.quote(identifier_quoter, ColumnName),
-                                num_partitions
-                            ));
-                        }
-                    }
-                }
+        let escaped_relation_name = format!(
+            "{}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName)
+        );
+
+        let mut create_hypertable_options = ", create_default_indexes => false".to_string();
+        let mut add_dimension_options = String::new();
+        if options.if_not_exists {
+            create_hypertable_options.push_str(", if_not_exists => true");
+            add_dimension_options.push_str(", if_not_exists => true");
+        }
+        if options.migrate_data {
+            create_hypertable_options.push_str(", migrate_data => true");
+        }
+
+        let mut sql = String::new();
+
+        // We don't need timescale to create the indices as we do it later on again based on what was exported.
+        for (idx, dim) in dimensions.iter().enumerate() {
+            let dimension_builder = match dim {
+                HypertableDimension::Time {
+                    column_name,
+                    time_interval,
+                } => format!(
+                    "by_range('{}', INTERVAL '{}')",
+                    column_name.quote(identifier_quoter, ColumnName),
+                    time_interval.to_postgres()
+                ),
+                HypertableDimension::SpaceInterval {
+                    column_name,
+                    integer_interval,
+                    ..
+                } => format!(
+                    "by_range('{}', {})",
+                    column_name.quote(identifier_quoter, ColumnName),
+                    integer_interval
+                ),
+                HypertableDimension::SpacePartitions {
+                    column_name,
+                    num_partitions,
+                } => format!(
+                    "by_hash('{}', {})",
+                    column_name.quote(identifier_quoter, ColumnName),
+                    num_partitions
+                ),
+            };
+
+            if idx == 0 {
+                sql.push_str(&format!(
+                    "\nselect public.create_hypertable('{}', {}{});",
+                    escaped_relation_name, dimension_builder, create_hypertable_options
+                ));
+            } else {
+                sql.push_str(&format!(
+                    "\nselect public.add_dimension('{}', {}{});",
+                    escaped_relation_name, dimension_builder, add_dimension_options
+                ));
             }
         }
 
-        sql
+        if let Some(chunk_target_size) = chunk_target_size {
+            sql.push_str(&format!(
+                "\nselect public.set_adaptive_chunking('{}', '{}');",
+                escaped_relation_name, chunk_target_size
+            ));
+        }
+
+        Some(sql)
     }
 
     pub fn get_copy_in_command(
@@ -316,16 +479,9 @@ impl PostgresTable {
 
         s.push_str(&cols);
 
-        s.push_str(") from stdin with (format ");
-        match data_format {
-            DataFormat::Text => {
-                s.push_str("text");
-            }
-            DataFormat::PostgresBinary { .. } => {
-                s.push_str("binary");
-            }
-        }
-        s.push_str(", header false);");
+        s.push_str(") from stdin with (");
+        s.push_str(&data_format.get_copy_options());
+        s.push_str(");");
 
         s
     }
@@ -362,16 +518,9 @@ impl PostgresTable {
             s.push_str(") ");
         }
 
-        s.push_str(" to stdout with (format ");
-        match data_format {
-            DataFormat::Text => {
-                s.push_str("text");
-            }
-            DataFormat::PostgresBinary { .. } => {
-                s.push_str("binary");
-            }
-        }
-        s.push_str(", header false, encoding 'utf-8');");
+        s.push_str(" to stdout with (");
+        s.push_str(&data_format.get_copy_options());
+        s.push_str(", encoding 'utf-8');");
 
         s
     }
@@ -396,6 +545,7 @@ impl PostgresTable {
         identifier_quoter: &IdentifierQuoter,
     ) -> Option<String> {
         if let TableTypeDetails::TimescaleHypertable {
+            dimensions,
             compression,
             retention,
             ..
@@ -407,6 +557,25 @@ impl PostgresTable {
                 self.name.quote(identifier_quoter, ColumnName)
             );
             let mut sql = String::new();
+
+            // The integer now function has to be set before any policies are added,
+            // as timescale refuses to add policies to integer based hypertables without it.
+            for dim in dimensions {
+                if let HypertableDimension::SpaceInterval {
+                    integer_now_func_schema: Some(func_schema),
+                    integer_now_func: Some(func_name),
+                    ..
+                } = dim
+                {
+                    sql.push_str(&format!(
+                        "select public.set_integer_now_func('{}', '{}.{}');\n",
+                        escaped_relation_name,
+                        func_schema.quote(identifier_quoter, ColumnName),
+                        func_name.quote(identifier_quoter, TypeOrFunctionName)
+                    ));
+                }
+            }
+
             if let Some(compression) = compression {
                 sql.push_str("alter table ");
                 compression.add_compression_settings(
@@ -461,9 +630,20 @@ pub enum TableTypeDetails {
         dimensions: Vec<HypertableDimension>,
         compression: Option<HypertableCompression>,
         retention: Option<HypertableRetention>,
+        /// The target size of chunks in bytes when adaptive chunking is enabled.
+        chunk_target_size: Option<i64>,
     },
 }
 
+/// Options for how a hypertable should be created.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct HypertableCreateOptions {
+    /// Moves any data already in the table into chunks. Required if the table is not empty.
+    pub migrate_data: bool,
+    /// Don't fail if the table is already a hypertable.
+    pub if_not_exists: bool,
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum PartitionedTableColumns {
@@ -501,6 +681,11 @@ pub enum HypertableDimension {
     SpaceInterval {
         column_name: String,
         integer_interval: i64,
+        /// The schema of the function timescale uses to find the "current time" for integer based dimensions.
+        integer_now_func_schema: Option<String>,
+        /// The function timescale uses to find the "current time" for integer based dimensions.
+        /// Required for compression and retention policies to work on integer based hypertables.
+        integer_now_func: Option<String>,
     },
     SpacePartitions {
         column_name: String,
@@ -1,3 +1,4 @@
+use crate::models::comment::get_comment_change_statement;
 use crate::helpers::StringExt;
 use crate::object_id::ObjectId;
 use crate::postgres_client_wrapper::FromPgChar;
@@ -85,6 +86,24 @@ impl PostgresTrigger {
 
         sql
     }
+
+    /// Gets the statement changing the comment of the `existing` trigger to the comment of this
+    /// one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresTrigger,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "trigger {} on {}.{}",
+            self.name.quote(identifier_quoter, ColumnName),
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.table_name.quote(identifier_quoter, ColumnName)
+        );
+
+        get_comment_change_statement(&object, &self.comment, &existing.comment)
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
@@ -1,7 +1,7 @@
 use crate::object_id::ObjectId;
 use crate::quoting::AttemptedKeywordUsage::ColumnName;
 use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
-use crate::{PostgresSchema, PostgresTable};
+use crate::{PostgresSchema, PostgresTable, TableTypeDetails};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -9,6 +9,11 @@ use std::cmp::Ordering;
 pub struct PostgresUniqueConstraint {
     pub name: String,
     pub unique_index_name: String,
+    /// The definition of the constraint, such as `UNIQUE (sku) INCLUDE (name)`.
+    ///
+    /// Indices on partitioned tables are not introspected, so the constraints of partitioned
+    /// tables are created from this instead of from their index.
+    pub definition: String,
     pub comment: Option<String>,
     pub object_id: ObjectId,
 }
@@ -32,13 +37,23 @@ impl PostgresUniqueConstraint {
         schema: &PostgresSchema,
         quoter: &IdentifierQuoter,
     ) -> String {
-        let mut sql = format!(
-            "alter table {}.{} add constraint {} unique using index {};",
-            schema.name.quote(quoter, ColumnName),
-            table.name.quote(quoter, ColumnName),
-            self.name.quote(quoter, ColumnName),
-            self.unique_index_name.quote(quoter, ColumnName)
-        );
+        let mut sql = if let TableTypeDetails::PartitionedParentTable { .. } = table.table_type {
+            format!(
+                "alter table {}.{} add constraint {} {};",
+                schema.name.quote(quoter, ColumnName),
+                table.name.quote(quoter, ColumnName),
+                self.name.quote(quoter, ColumnName),
+                self.definition
+            )
+        } else {
+            format!(
+                "alter table {}.{} add constraint {} unique using index {};",
+                schema.name.quote(quoter, ColumnName),
+                table.name.quote(quoter, ColumnName),
+                self.name.quote(quoter, ColumnName),
+                self.unique_index_name.quote(quoter, ColumnName)
+            )
+        };
 
         if let Some(comment) = &self.comment {
             sql.push_str("\ncomment on constraint ");
@@ -1,10 +1,12 @@
+use crate::models::comment::get_comment_change_statement;
 use crate::models::hypertable_retention::HypertableRetention;
-use crate::object_id::{HaveDependencies, ObjectId};
+use crate::object_id::ObjectId;
 use crate::pg_interval::Interval;
 use crate::quoting::AttemptedKeywordUsage::ColumnName;
 use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
 use crate::whitespace_ignorant_string::WhitespaceIgnorantString;
-use crate::{HypertableCompression, PostgresSchema};
+use crate::models::privilege::get_privilege_statements;
+use crate::{HypertableCompression, PostgresIndex, PostgresPrivilege, PostgresSchema};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, Default, Clone, Serialize, Deserialize)]
@@ -14,22 +16,36 @@ pub struct PostgresView {
     pub columns: Vec<PostgresViewColumn>,
     pub comment: Option<String>,
     pub is_materialized: bool,
+    /// If the materialized view was created `with no data`, and has not been refreshed since.
+    pub not_populated: bool,
     pub view_options: ViewOptions,
     pub object_id: ObjectId,
     pub depends_on: Vec<ObjectId>,
+    /// The columns of other tables and views this view references, as the object id of
+    /// the table or view and the name of the column.
+    pub column_dependencies: Vec<(ObjectId, String)>,
+    /// The indices of a materialized view. Views that are not materialized cannot have indices.
+    pub indices: Vec<PostgresIndex>,
+    pub privileges: Vec<PostgresPrivilege>,
 }
 
-impl HaveDependencies for &PostgresView {
-    fn depends_on(&self) -> &Vec<ObjectId> {
-        &self.depends_on
-    }
+impl PostgresView {
+    /// Gets the statements granting and revoking the privileges of the view. Views use the same
+    /// privileges as tables.
+    pub fn get_privilege_statements(
+        &self,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "table {}.{}",
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName)
+        );
 
-    fn object_id(&self) -> ObjectId {
-        self.object_id
+        get_privilege_statements(&self.privileges, &object, identifier_quoter)
     }
-}
 
-impl PostgresView {
     pub fn get_create_view_sql(
         &self,
         schema: &PostgresSchema,
@@ -93,8 +109,15 @@ impl PostgresView {
             refresh,
             compression,
             retention,
+            materialized_only,
+            ..
         } = &self.view_options
         {
+            sql.push_str(&format!(
+                "\nalter materialized view {} set (timescaledb.materialized_only = {});",
+                escaped_relation_name, materialized_only
+            ));
+
             if let Some(refresh) = refresh {
                 sql.push_str("\nselect add_continuous_aggregate_policy('");
                 sql.push_str(&escaped_relation_name);
@@ -125,6 +148,23 @@ impl PostgresView {
         sql
     }
 
+    /// Gets the statement changing the comment of the `existing` view to the comment of this one.
+    pub fn get_comment_change_statement(
+        &self,
+        existing: &PostgresView,
+        schema: &PostgresSchema,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        let object = format!(
+            "{}view {}.{}",
+            if self.is_materialized { "materialized " } else { "" },
+            schema.name.quote(identifier_quoter, ColumnName),
+            self.name.quote(identifier_quoter, ColumnName)
+        );
+
+        get_comment_change_statement(&object, &self.comment, &existing.comment)
+    }
+
     pub fn get_refresh_sql(
         &self,
         schema: &PostgresSchema,
@@ -166,6 +206,12 @@ pub enum ViewOptions {
         refresh: Option<TimescaleContinuousAggregateRefreshOptions>,
         compression: Option<HypertableCompression>,
         retention: Option<HypertableRetention>,
+        /// If false, real-time aggregation is enabled and queries will include
+        /// data that has not been materialized yet.
+        materialized_only: bool,
+        /// If false, the continuous aggregate uses the old partial format, which
+        /// newer versions of Timescale no longer supports creating.
+        finalized: bool,
     },
 }
 
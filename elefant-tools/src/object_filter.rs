@@ -0,0 +1,786 @@
+use crate::table_filter::filter_tables;
+use crate::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Selects the objects to copy, with include and exclude patterns per kind of object. In the
+/// patterns `*` matches any number of characters, and objects without a schema are in `public`.
+/// An object is copied if it matches no exclude pattern and either the include patterns of its
+/// kind are empty or one of them matches it. Patterns without wildcards have to match an object,
+/// to catch typos.
+///
+/// Excluding an object that a copied object requires, such as the function of a trigger, fails
+/// the copy before anything is created, listing every such object at once.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ObjectFilter {
+    /// Only copy the tables matching these patterns, as `schema.table`, such as
+    /// `public.events_*`. Partitions are copied along with their partitioned table.
+    pub include_tables: Vec<String>,
+
+    /// Leave out the tables matching these patterns, in the same format as `include_tables`.
+    /// Their indexes, constraints and triggers are left out too. Foreign keys to the tables,
+    /// views using them and tables inheriting from them fail the copy, unless
+    /// `drop_dependent_objects` is set.
+    pub exclude_tables: Vec<String>,
+
+    /// Only copy the views matching these patterns, as `schema.view`. Materialized views are
+    /// selected by `include_materialized_views`.
+    pub include_views: Vec<String>,
+
+    /// Leave out the views matching these patterns, in the same format as `include_views`.
+    pub exclude_views: Vec<String>,
+
+    /// Only copy the materialized views matching these patterns, as `schema.view`.
+    pub include_materialized_views: Vec<String>,
+
+    /// Leave out the materialized views matching these patterns, in the same format as
+    /// `include_materialized_views`.
+    pub exclude_materialized_views: Vec<String>,
+
+    /// Only copy the functions matching these patterns, as `schema.function` for every overload,
+    /// or as `schema.function(arguments)` for a single one, with the arguments as listed by `\df`,
+    /// such as `public.add(a integer, b integer)`. Aggregate functions are selected the same way.
+    pub include_functions: Vec<String>,
+
+    /// Leave out the functions matching these patterns, in the same format as
+    /// `include_functions`.
+    pub exclude_functions: Vec<String>,
+
+    /// Only copy the triggers matching these patterns, as `schema.table.trigger`.
+    pub include_triggers: Vec<String>,
+
+    /// Leave out the triggers matching these patterns, in the same format as `include_triggers`.
+    pub exclude_triggers: Vec<String>,
+
+    /// Only copy the sequences matching these patterns, as `schema.sequence`. The sequences of
+    /// identity columns are part of their table and always copied with it.
+    pub include_sequences: Vec<String>,
+
+    /// Leave out the sequences matching these patterns, in the same format as
+    /// `include_sequences`.
+    pub exclude_sequences: Vec<String>,
+}
+
+impl ObjectFilter {
+    /// If the filter selects every object.
+    pub fn is_empty(&self) -> bool {
+        self.include_tables.is_empty()
+            && self.exclude_tables.is_empty()
+            && !self.filters_other_objects()
+    }
+
+    fn filters_other_objects(&self) -> bool {
+        [
+            &self.include_views,
+            &self.exclude_views,
+            &self.include_materialized_views,
+            &self.exclude_materialized_views,
+            &self.include_functions,
+            &self.exclude_functions,
+            &self.include_triggers,
+            &self.exclude_triggers,
+            &self.include_sequences,
+            &self.exclude_sequences,
+        ]
+        .iter()
+        .any(|patterns| !patterns.is_empty())
+    }
+}
+
+/// A `schema.name` pattern, where `*` matches any number of characters.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct NamePattern<'p> {
+    pub schema: &'p str,
+    pub name: &'p str,
+}
+
+impl<'p> NamePattern<'p> {
+    /// Names without a schema are in `public`, like in [CopyDataOptions::exclude_columns].
+    pub fn parse(pattern: &'p str) -> Self {
+        let (schema, name) = pattern.split_once('.').unwrap_or(("public", pattern));
+        NamePattern { schema, name }
+    }
+
+    pub fn has_wildcards(&self) -> bool {
+        self.schema.contains('*') || self.name.contains('*')
+    }
+
+    pub fn matches(&self, schema_name: &str, name: &str) -> bool {
+        matches_wildcard(self.schema, schema_name) && matches_wildcard(self.name, name)
+    }
+}
+
+/// Checks if the value matches the pattern, where `*` matches any number of characters.
+pub(crate) fn matches_wildcard(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.collect_vec();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+/// A `schema.function` or `schema.function(arguments)` pattern.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct FunctionPattern<'p> {
+    function: NamePattern<'p>,
+    arguments: Option<&'p str>,
+}
+
+impl<'p> FunctionPattern<'p> {
+    fn parse(pattern: &'p str) -> Self {
+        match pattern.split_once('(') {
+            Some((function, arguments)) => FunctionPattern {
+                function: NamePattern::parse(function.trim()),
+                arguments: Some(arguments.trim_end().trim_end_matches(')')),
+            },
+            None => FunctionPattern {
+                function: NamePattern::parse(pattern),
+                arguments: None,
+            },
+        }
+    }
+
+    fn has_wildcards(&self) -> bool {
+        self.function.has_wildcards()
+    }
+
+    fn matches(&self, schema_name: &str, function_name: &str, arguments: &str) -> bool {
+        self.function.matches(schema_name, function_name)
+            && self
+                .arguments
+                .is_none_or(|a| normalize_arguments(a) == normalize_arguments(arguments))
+    }
+}
+
+/// Splits the arguments of a function, ignoring the whitespace between the words.
+fn normalize_arguments(arguments: &str) -> Vec<String> {
+    arguments
+        .split(',')
+        .map(|a| a.split_whitespace().join(" "))
+        .filter(|a| !a.is_empty())
+        .collect()
+}
+
+/// A `schema.table.trigger` pattern. A trigger without a table matches it on any table in `public`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct TriggerPattern<'p> {
+    table: NamePattern<'p>,
+    trigger: &'p str,
+}
+
+impl<'p> TriggerPattern<'p> {
+    fn parse(pattern: &'p str) -> Self {
+        match pattern.rsplit_once('.') {
+            Some((table, trigger)) => TriggerPattern {
+                table: NamePattern::parse(table),
+                trigger,
+            },
+            None => TriggerPattern {
+                table: NamePattern::parse("*"),
+                trigger: pattern,
+            },
+        }
+    }
+
+    fn has_wildcards(&self) -> bool {
+        self.table.has_wildcards() || self.trigger.contains('*')
+    }
+
+    fn matches(&self, schema_name: &str, table_name: &str, trigger_name: &str) -> bool {
+        self.table.matches(schema_name, table_name) && matches_wildcard(self.trigger, trigger_name)
+    }
+}
+
+/// The parsed include and exclude patterns of one kind of object.
+struct Patterns<P> {
+    kind: &'static str,
+    include: Vec<P>,
+    exclude: Vec<P>,
+}
+
+impl<'p, P> Patterns<P> {
+    fn parse(
+        kind: &'static str,
+        include: &'p [String],
+        exclude: &'p [String],
+        parse: impl Fn(&'p str) -> P,
+    ) -> Self {
+        Patterns {
+            kind,
+            include: include.iter().map(|p| parse(p)).collect(),
+            exclude: exclude.iter().map(|p| parse(p)).collect(),
+        }
+    }
+
+    fn is_selected(&self, matches: impl Fn(&P) -> bool) -> bool {
+        !self.exclude.iter().any(&matches)
+            && (self.include.is_empty() || self.include.iter().any(&matches))
+    }
+
+    /// Fails if a pattern without wildcards matches none of the objects.
+    fn check_found(
+        &self,
+        has_wildcards: impl Fn(&P) -> bool,
+        matches_any: impl Fn(&P) -> bool,
+        format: impl Fn(&P) -> String,
+    ) -> Result<()> {
+        match self
+            .include
+            .iter()
+            .chain(&self.exclude)
+            .find(|p| !has_wildcards(p) && !matches_any(p))
+        {
+            Some(pattern) => Err(ElefantToolsError::ObjectNotFound {
+                kind: self.kind,
+                pattern: format(pattern),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Removes the objects that are not selected by the filter from the definition, so they are not
+/// created. Tables are filtered as described for [ObjectFilter::exclude_tables].
+///
+/// The other objects are filtered afterwards. Every remaining object that requires a removed
+/// object is reported in a single error: views, tables, functions, types and policies using removed
+/// views, functions or sequences, and triggers executing removed functions.
+pub(crate) fn filter_objects(
+    definition: &mut PostgresDatabase,
+    filter: &ObjectFilter,
+    drop_dependent_objects: bool,
+) -> Result<()> {
+    filter_tables(
+        definition,
+        &filter.include_tables,
+        &filter.exclude_tables,
+        drop_dependent_objects,
+    )?;
+
+    if !filter.filters_other_objects() {
+        return Ok(());
+    }
+
+    let views = Patterns::parse(
+        "view",
+        &filter.include_views,
+        &filter.exclude_views,
+        NamePattern::parse,
+    );
+    let materialized_views = Patterns::parse(
+        "materialized view",
+        &filter.include_materialized_views,
+        &filter.exclude_materialized_views,
+        NamePattern::parse,
+    );
+    let functions = Patterns::parse(
+        "function",
+        &filter.include_functions,
+        &filter.exclude_functions,
+        FunctionPattern::parse,
+    );
+    let triggers = Patterns::parse(
+        "trigger",
+        &filter.include_triggers,
+        &filter.exclude_triggers,
+        TriggerPattern::parse,
+    );
+    let sequences = Patterns::parse(
+        "sequence",
+        &filter.include_sequences,
+        &filter.exclude_sequences,
+        NamePattern::parse,
+    );
+
+    let schemas = &definition.schemas;
+
+    for view_patterns in [&views, &materialized_views] {
+        let is_materialized = view_patterns.kind == materialized_views.kind;
+        view_patterns.check_found(
+            |p| p.has_wildcards(),
+            |p| {
+                schemas.iter().any(|s| {
+                    s.views.iter().any(|v| {
+                        v.is_materialized == is_materialized && p.matches(&s.name, &v.name)
+                    })
+                })
+            },
+            |p| format!("{}.{}", p.schema, p.name),
+        )?;
+    }
+
+    functions.check_found(
+        |p| p.has_wildcards(),
+        |p| {
+            schemas.iter().any(|s| {
+                s.functions
+                    .iter()
+                    .any(|f| p.matches(&s.name, &f.function_name, &f.arguments))
+                    || s.aggregate_functions
+                        .iter()
+                        .any(|f| p.matches(&s.name, &f.function_name, &f.arguments))
+            })
+        },
+        |p| match p.arguments {
+            Some(arguments) => format!("{}.{}({})", p.function.schema, p.function.name, arguments),
+            None => format!("{}.{}", p.function.schema, p.function.name),
+        },
+    )?;
+
+    triggers.check_found(
+        |p| p.has_wildcards(),
+        |p| {
+            schemas.iter().any(|s| {
+                s.triggers
+                    .iter()
+                    .any(|t| p.matches(&s.name, &t.table_name, &t.name))
+            })
+        },
+        |p| format!("{}.{}.{}", p.table.schema, p.table.name, p.trigger),
+    )?;
+
+    sequences.check_found(
+        |p| p.has_wildcards(),
+        |p| {
+            schemas.iter().any(|s| {
+                s.sequences
+                    .iter()
+                    .any(|q| !q.is_internally_created && p.matches(&s.name, &q.name))
+            })
+        },
+        |p| format!("{}.{}", p.schema, p.name),
+    )?;
+
+    let is_view_selected = |schema_name: &str, view: &PostgresView| {
+        let patterns = if view.is_materialized {
+            &materialized_views
+        } else {
+            &views
+        };
+        patterns.is_selected(|p| p.matches(schema_name, &view.name))
+    };
+    let is_function_selected = |schema_name: &str, function_name: &str, arguments: &str| {
+        functions.is_selected(|p| p.matches(schema_name, function_name, arguments))
+    };
+    let is_trigger_selected = |schema_name: &str, trigger: &PostgresTrigger| {
+        triggers.is_selected(|p| p.matches(schema_name, &trigger.table_name, &trigger.name))
+    };
+    let is_sequence_selected = |schema_name: &str, sequence: &PostgresSequence| {
+        sequence.is_internally_created
+            || sequences.is_selected(|p| p.matches(schema_name, &sequence.name))
+    };
+
+    let mut removed = Vec::new();
+    for schema in schemas {
+        for view in schema
+            .views
+            .iter()
+            .filter(|v| !is_view_selected(&schema.name, v))
+        {
+            removed.push((view.object_id, describe_view(schema, view)));
+        }
+        for function in schema
+            .functions
+            .iter()
+            .filter(|f| !is_function_selected(&schema.name, &f.function_name, &f.arguments))
+        {
+            removed.push((
+                function.object_id,
+                describe_function(
+                    "function",
+                    schema,
+                    &function.function_name,
+                    &function.arguments,
+                ),
+            ));
+        }
+        for function in schema
+            .aggregate_functions
+            .iter()
+            .filter(|f| !is_function_selected(&schema.name, &f.function_name, &f.arguments))
+        {
+            removed.push((
+                function.object_id,
+                describe_function(
+                    "aggregate function",
+                    schema,
+                    &function.function_name,
+                    &function.arguments,
+                ),
+            ));
+        }
+        for sequence in schema
+            .sequences
+            .iter()
+            .filter(|s| !is_sequence_selected(&schema.name, s))
+        {
+            removed.push((
+                sequence.object_id,
+                format!("sequence {}.{}", schema.name, sequence.name),
+            ));
+        }
+    }
+
+    let get_removed = |depends_on: &[ObjectId]| {
+        removed
+            .iter()
+            .filter(|(id, _)| depends_on.contains(id))
+            .map(|(_, description)| description.as_str())
+            .collect_vec()
+    };
+
+    let mut required = Vec::new();
+    let mut add_required = |object: String, removed_objects: Vec<&str>| {
+        for removed_object in removed_objects {
+            required.push(format!("{} requires {}", object, removed_object));
+        }
+    };
+
+    for schema in schemas {
+        for table in &schema.tables {
+            add_required(
+                format!("table {}.{}", schema.name, table.name),
+                get_removed(&table.depends_on),
+            );
+        }
+        for view in schema
+            .views
+            .iter()
+            .filter(|v| is_view_selected(&schema.name, v))
+        {
+            add_required(describe_view(schema, view), get_removed(&view.depends_on));
+        }
+        for function in schema
+            .functions
+            .iter()
+            .filter(|f| is_function_selected(&schema.name, &f.function_name, &f.arguments))
+        {
+            add_required(
+                describe_function(
+                    "function",
+                    schema,
+                    &function.function_name,
+                    &function.arguments,
+                ),
+                get_removed(&function.depends_on),
+            );
+        }
+        for function in schema
+            .aggregate_functions
+            .iter()
+            .filter(|f| is_function_selected(&schema.name, &f.function_name, &f.arguments))
+        {
+            add_required(
+                describe_function(
+                    "aggregate function",
+                    schema,
+                    &function.function_name,
+                    &function.arguments,
+                ),
+                get_removed(&function.depends_on),
+            );
+        }
+        for domain in &schema.domains {
+            add_required(
+                format!("domain {}.{}", schema.name, domain.name),
+                get_removed(&domain.depends_on),
+            );
+        }
+        for composite_type in &schema.composite_types {
+            add_required(
+                format!("type {}.{}", schema.name, composite_type.name),
+                get_removed(&composite_type.depends_on),
+            );
+        }
+        for policy in &schema.policies {
+            add_required(
+                format!(
+                    "policy {} on {}.{}",
+                    policy.name, schema.name, policy.table_name
+                ),
+                get_removed(&policy.depends_on),
+            );
+        }
+
+        // Trigger functions never have arguments, and the trigger calls the function of its
+        // own schema.
+        for trigger in schema
+            .triggers
+            .iter()
+            .filter(|t| is_trigger_selected(&schema.name, t))
+        {
+            let function_is_removed = schema.functions.iter().any(|f| {
+                f.function_name == trigger.function_name
+                    && f.arguments.is_empty()
+                    && !is_function_selected(&schema.name, &f.function_name, &f.arguments)
+            });
+
+            if function_is_removed {
+                add_required(
+                    format!(
+                        "trigger {} on {}.{}",
+                        trigger.name, schema.name, trigger.table_name
+                    ),
+                    vec![&describe_function(
+                        "function",
+                        schema,
+                        &trigger.function_name,
+                        "",
+                    )],
+                );
+            }
+        }
+    }
+
+    if !required.is_empty() {
+        return Err(ElefantToolsError::ExcludedObjectsAreRequired(required));
+    }
+
+    for schema in &mut definition.schemas {
+        let schema_name = schema.name.clone();
+        schema.views.retain(|v| is_view_selected(&schema_name, v));
+        schema
+            .functions
+            .retain(|f| is_function_selected(&schema_name, &f.function_name, &f.arguments));
+        schema
+            .aggregate_functions
+            .retain(|f| is_function_selected(&schema_name, &f.function_name, &f.arguments));
+        schema
+            .triggers
+            .retain(|t| is_trigger_selected(&schema_name, t));
+        schema
+            .sequences
+            .retain(|s| is_sequence_selected(&schema_name, s));
+    }
+
+    Ok(())
+}
+
+fn describe_view(schema: &PostgresSchema, view: &PostgresView) -> String {
+    let kind = if view.is_materialized {
+        "materialized view"
+    } else {
+        "view"
+    };
+    format!("{} {}.{}", kind, schema.name, view.name)
+}
+
+fn describe_function(
+    kind: &str,
+    schema: &PostgresSchema,
+    function_name: &str,
+    arguments: &str,
+) -> String {
+    format!("{} {}.{}({})", kind, schema.name, function_name, arguments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches_wildcard("events", "events"));
+        assert!(!matches_wildcard("events", "events_2024"));
+        assert!(matches_wildcard("events_*", "events_2024"));
+        assert!(matches_wildcard("*", "anything"));
+        assert!(matches_wildcard("*_log", "audit_log"));
+        assert!(!matches_wildcard("*_log", "audit_logs"));
+        assert!(matches_wildcard("a*b*c", "a_b_c"));
+        assert!(matches_wildcard("a*b*c", "abc"));
+        assert!(!matches_wildcard("a*b*c", "acb"));
+        assert!(!matches_wildcard("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn matches_functions_by_name_and_arguments() {
+        let every_overload = FunctionPattern::parse("public.add");
+        assert!(every_overload.matches("public", "add", "a integer, b integer"));
+        assert!(every_overload.matches("public", "add", ""));
+
+        let single_overload = FunctionPattern::parse("add(a  integer,b integer)");
+        assert!(single_overload.matches("public", "add", "a integer, b integer"));
+        assert!(!single_overload.matches("public", "add", "a bigint, b bigint"));
+
+        let without_arguments = FunctionPattern::parse("public.audit()");
+        assert!(without_arguments.matches("public", "audit", ""));
+        assert!(!without_arguments.matches("public", "audit", "a integer"));
+    }
+
+    fn database() -> PostgresDatabase {
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![PostgresTable {
+                    object_id: 1.into(),
+                    depends_on: vec![3.into()],
+                    ..PostgresTable::new("customers")
+                }],
+                sequences: vec![PostgresSequence {
+                    name: "customer_ids".to_string(),
+                    object_id: 3.into(),
+                    ..default()
+                }],
+                functions: vec![
+                    PostgresFunction {
+                        function_name: "audit".to_string(),
+                        object_id: 4.into(),
+                        ..default()
+                    },
+                    PostgresFunction {
+                        function_name: "greeting".to_string(),
+                        arguments: "name text".to_string(),
+                        object_id: 5.into(),
+                        ..default()
+                    },
+                ],
+                views: vec![
+                    PostgresView {
+                        name: "customer_names".to_string(),
+                        object_id: 6.into(),
+                        depends_on: vec![1.into()],
+                        ..default()
+                    },
+                    PostgresView {
+                        name: "short_customer_names".to_string(),
+                        object_id: 7.into(),
+                        depends_on: vec![6.into()],
+                        ..default()
+                    },
+                    PostgresView {
+                        name: "customer_counts".to_string(),
+                        object_id: 8.into(),
+                        is_materialized: true,
+                        depends_on: vec![1.into()],
+                        ..default()
+                    },
+                ],
+                triggers: vec![PostgresTrigger {
+                    name: "customers_audit".to_string(),
+                    table_name: "customers".to_string(),
+                    function_name: "audit".to_string(),
+                    object_id: 9.into(),
+                    ..default()
+                }],
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn reports_every_excluded_object_required_by_a_copied_object() {
+        let mut definition = database();
+
+        let result = filter_objects(
+            &mut definition,
+            &ObjectFilter {
+                exclude_functions: vec!["public.audit".to_string()],
+                exclude_views: vec!["customer_names".to_string()],
+                exclude_sequences: vec!["customer_ids".to_string()],
+                ..default()
+            },
+            false,
+        );
+
+        assert!(
+            matches!(&result, Err(ElefantToolsError::ExcludedObjectsAreRequired(objects)) if objects == &vec![
+                "table public.customers requires sequence public.customer_ids".to_string(),
+                "view public.short_customer_names requires view public.customer_names".to_string(),
+                "trigger customers_audit on public.customers requires function public.audit()".to_string(),
+            ]),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn excludes_objects_not_required_by_copied_objects() {
+        let mut definition = database();
+
+        filter_objects(
+            &mut definition,
+            &ObjectFilter {
+                exclude_functions: vec!["public.audit()".to_string()],
+                exclude_triggers: vec!["public.customers.customers_audit".to_string()],
+                include_views: vec!["*".to_string()],
+                exclude_views: vec!["short_*".to_string()],
+                exclude_materialized_views: vec!["customer_counts".to_string()],
+                ..default()
+            },
+            false,
+        )
+        .unwrap();
+
+        let schema = &definition.schemas[0];
+        assert_eq!(schema.tables.len(), 1);
+        assert_eq!(schema.sequences.len(), 1);
+        assert_eq!(
+            schema
+                .functions
+                .iter()
+                .map(|f| f.function_name.as_str())
+                .collect_vec(),
+            vec!["greeting"]
+        );
+        assert_eq!(
+            schema.views.iter().map(|v| v.name.as_str()).collect_vec(),
+            vec!["customer_names"]
+        );
+        assert!(schema.triggers.is_empty());
+    }
+
+    #[test]
+    fn fails_on_patterns_without_wildcards_matching_nothing() {
+        let mut definition = database();
+
+        let result = filter_objects(
+            &mut definition,
+            &ObjectFilter {
+                exclude_functions: vec!["public.greeting(name varchar)".to_string()],
+                ..default()
+            },
+            false,
+        );
+
+        assert!(
+            matches!(&result, Err(ElefantToolsError::ObjectNotFound { kind: "function", pattern }) if pattern == "public.greeting(name varchar)"),
+            "{:?}",
+            result
+        );
+
+        let result = filter_objects(
+            &mut definition,
+            &ObjectFilter {
+                exclude_views: vec!["customer_counts".to_string()],
+                ..default()
+            },
+            false,
+        );
+
+        assert!(
+            matches!(
+                &result,
+                Err(ElefantToolsError::ObjectNotFound { kind: "view", .. })
+            ),
+            "{:?}",
+            result
+        );
+    }
+}
@@ -1,4 +1,7 @@
+use crate::{ElefantToolsError, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Used for tracking dependencies between objects and to handle renames.
 ///
@@ -39,24 +42,41 @@ impl Eq for ObjectId {}
 /// the same run, while ensuring the generation is deterministic.
 ///
 /// This allows to exact id checking in Tests when relevant.
+///
+/// ObjectIds are only unique within the generator that created them. By default each
+/// introspection uses its own generator, so ObjectIds from different [PostgresDatabase]
+/// values can collide. To get ObjectIds that are unique across multiple introspections,
+/// share a single generator between them, see [SchemaReader::with_object_id_generator].
+/// Generating ids is thread-safe, so the introspections can run concurrently.
+///
+/// [PostgresDatabase]: crate::PostgresDatabase
+/// [SchemaReader::with_object_id_generator]: crate::SchemaReader::with_object_id_generator
+#[derive(Debug)]
 pub struct ObjectIdGenerator {
-    next_id: usize,
+    next_id: AtomicUsize,
 }
 
 impl ObjectIdGenerator {
     /// Creates a new ObjectIdGenerator
     pub fn new() -> Self {
-        Self { next_id: 1 }
+        Self {
+            next_id: AtomicUsize::new(1),
+        }
     }
 
     /// Generates the next ObjectId
-    pub fn next(&mut self) -> ObjectId {
-        let id = self.next_id;
-        self.next_id += 1;
+    pub fn next(&self) -> ObjectId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
         ObjectId::new(id)
     }
 }
 
+impl Default for ObjectIdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Trait for objects that have dependencies
 ///
 /// This is used for topological sorting of objects to ensure
@@ -66,15 +86,23 @@ pub trait HaveDependencies {
     fn depends_on(&self) -> &Vec<ObjectId>;
     /// Returns the [ObjectId] of this object
     fn object_id(&self) -> ObjectId;
+    /// Returns a human readable name of this object, used when reporting circular dependencies.
+    fn object_name(&self) -> String;
 }
 
 /// Trait for iterators that can be sorted by dependencies
 pub trait DependencySortable: Iterator {
-    /// Sorts the items in the iterator by dependencies.
+    /// Sorts the items in the iterator, so every item comes after the items it depends on.
     ///
-    /// # Panics
-    /// This will panic if circular dependencies are detected.
-    fn sort_by_dependencies(self) -> Vec<Self::Item>;
+    /// Items without any dependencies come first. Otherwise items that don't depend on each other
+    /// keep the order they had in the iterator, which makes the result deterministic. For
+    /// introspected databases this means they are ordered by schema and name.
+    /// Dependencies on objects that are not in the iterator are ignored.
+    ///
+    /// # Errors
+    /// Returns [ElefantToolsError::CircularDependencies] with the members of the cycle
+    /// if circular dependencies are detected.
+    fn sort_by_dependencies(self) -> Result<Vec<Self::Item>>;
 }
 
 impl<I> DependencySortable for I
@@ -82,53 +110,102 @@ where
     I: Iterator + Sized,
     I::Item: HaveDependencies,
 {
-    fn sort_by_dependencies(self) -> Vec<Self::Item> {
-        let mut sorted: Vec<Self::Item> = self.collect();
-
-        if sorted.is_empty() {
-            return sorted;
-        }
-
-        // Move everything with 0 dependencies to the front
-        let mut i = 0;
-        let mut j = sorted.len() - 1;
-        while i < j {
-            if sorted[i].depends_on().is_empty() {
-                i += 1;
-            } else if !sorted[j].depends_on().is_empty() {
-                j -= 1;
-            } else {
-                sorted.swap(i, j);
-                i += 1;
-                j -= 1;
+    fn sort_by_dependencies(self) -> Result<Vec<Self::Item>> {
+        let mut items: Vec<Option<Self::Item>> = self.map(Some).collect();
+
+        // The positions are looked up by id, instead of searching all the items for each of
+        // them. Items without an id are equal to any id, see [ObjectId].
+        let mut positions: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut positions_without_id = Vec::new();
+        for (j, item) in items.iter().flatten().enumerate() {
+            match item.object_id().value {
+                Some(value) => positions.entry(value).or_default().push(j),
+                None => positions_without_id.push(j),
             }
         }
 
-        // Sort the rest by dependencies
-        let mut swaps = 0;
-        let max_swaps = sorted.len() * sorted.len();
-        loop {
-            let mut swapped = false;
-            for i in 0..sorted.len() {
-                for j in i + 1..sorted.len() {
-                    if sorted[i].depends_on().contains(&sorted[j].object_id()) {
-                        sorted.swap(i, j);
-                        swapped = true;
-                        swaps += 1;
+        let dependencies = items
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, item)| {
+                let mut item_dependencies = BTreeSet::new();
+                for dependency in item.depends_on() {
+                    match dependency.value {
+                        Some(value) => {
+                            item_dependencies.extend(positions.get(&value).into_iter().flatten())
+                        }
+                        None => item_dependencies.extend(0..items.len()),
                     }
+                    item_dependencies.extend(&positions_without_id);
                 }
+                item_dependencies.remove(&i);
+                item_dependencies.into_iter().collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut dependents = vec![Vec::new(); items.len()];
+        for (i, item_dependencies) in dependencies.iter().enumerate() {
+            for &j in item_dependencies {
+                dependents[j].push(i);
             }
-            if !swapped {
-                break;
-            }
+        }
 
-            if swaps > max_swaps {
-                panic!("Circular dependencies detected");
+        // Ordered by whether the item has any dependencies, and then by the original position.
+        let mut missing_dependencies = dependencies.iter().map(|d| d.len()).collect::<Vec<_>>();
+        let mut ready = missing_dependencies
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count == 0)
+            .map(|(i, _)| (false, i))
+            .collect::<BTreeSet<_>>();
+
+        let mut sorted = Vec::with_capacity(items.len());
+        while let Some((_, i)) = ready.pop_first() {
+            sorted.push(items[i].take().unwrap());
+
+            for &dependent in &dependents[i] {
+                missing_dependencies[dependent] -= 1;
+                if missing_dependencies[dependent] == 0 {
+                    ready.insert((true, dependent));
+                }
             }
         }
 
-        sorted
+        if sorted.len() < items.len() {
+            return Err(ElefantToolsError::CircularDependencies(find_cycle(
+                &items,
+                &dependencies,
+            )));
+        }
+
+        Ok(sorted)
+    }
+}
+
+/// Finds the names of the members of a cycle among the items that could not be sorted.
+///
+/// Every remaining item depends on at least one other remaining item, so following the
+/// dependencies from any of them will eventually lead back to an item that was already visited.
+fn find_cycle<T: HaveDependencies>(items: &[Option<T>], dependencies: &[Vec<usize>]) -> Vec<String> {
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = items.iter().position(|i| i.is_some()).unwrap();
+
+    while !path.contains(&current) {
+        path.push(current);
+        current = *dependencies[current]
+            .iter()
+            .find(|&&d| items[d].is_some())
+            .unwrap();
     }
+
+    // The path follows the dependencies, so it is reversed to list each member before the
+    // members depending on it.
+    let cycle_start = path.iter().position(|&i| i == current).unwrap();
+    std::iter::once(&path[cycle_start])
+        .chain(path[cycle_start + 1..].iter().rev())
+        .map(|&i| items[i].as_ref().unwrap().object_name())
+        .collect()
 }
 
 #[cfg(test)]
@@ -136,7 +213,6 @@ mod tests {
     use super::*;
     use itertools::Itertools;
     use std::fmt::{Debug, Formatter};
-    use std::panic::catch_unwind;
 
     #[derive(Eq, Clone)]
     struct TestItem {
@@ -151,6 +227,10 @@ mod tests {
         fn object_id(&self) -> ObjectId {
             self.object_id
         }
+
+        fn object_name(&self) -> String {
+            format!("item_{}", self.object_id.value.unwrap())
+        }
     }
 
     impl PartialEq for TestItem {
@@ -183,7 +263,7 @@ mod tests {
         ];
 
         for items in items.into_iter().permutations(3) {
-            let sorted = items.into_iter().sort_by_dependencies();
+            let sorted = items.into_iter().sort_by_dependencies().unwrap();
 
             assert!(matches!(sorted[0].object_id.value.unwrap(), 1));
             assert!(matches!(sorted[1].object_id.value.unwrap(), 2 | 3));
@@ -209,7 +289,7 @@ mod tests {
         ];
 
         for items in items.into_iter().permutations(3) {
-            let sorted = items.into_iter().sort_by_dependencies();
+            let sorted = items.into_iter().sort_by_dependencies().unwrap();
 
             assert!(matches!(sorted[0].object_id.value.unwrap(), 2 | 3));
             assert!(matches!(sorted[1].object_id.value.unwrap(), 2 | 3));
@@ -235,7 +315,7 @@ mod tests {
         ];
 
         for items in items.into_iter().permutations(3) {
-            let sorted = items.into_iter().sort_by_dependencies();
+            let sorted = items.into_iter().sort_by_dependencies().unwrap();
 
             assert!(matches!(sorted[0].object_id.value.unwrap(), 2));
             assert!(matches!(sorted[1].object_id.value.unwrap(), 3));
@@ -261,7 +341,7 @@ mod tests {
         ];
 
         for items in items.into_iter().permutations(3) {
-            let sorted = items.into_iter().sort_by_dependencies();
+            let sorted = items.into_iter().sort_by_dependencies().unwrap();
 
             assert!(matches!(sorted[0].object_id.value.unwrap(), 2));
             assert!(matches!(sorted[1].object_id.value.unwrap(), 3));
@@ -271,32 +351,109 @@ mod tests {
 
     #[test]
     fn circular_dependencies() {
-        let result = catch_unwind(|| {
-            let items = vec![
-                TestItem {
-                    object_id: 1.into(),
-                    depends_on: vec![3.into()],
-                },
-                TestItem {
-                    object_id: 2.into(),
-                    depends_on: vec![1.into()],
-                },
-                TestItem {
-                    object_id: 3.into(),
-                    depends_on: vec![2.into()],
-                },
-            ];
-
-            items.into_iter().sort_by_dependencies();
-        });
-
-        assert!(result.is_err());
+        let items = vec![
+            TestItem {
+                object_id: 1.into(),
+                depends_on: vec![3.into()],
+            },
+            TestItem {
+                object_id: 2.into(),
+                depends_on: vec![1.into()],
+            },
+            TestItem {
+                object_id: 3.into(),
+                depends_on: vec![2.into()],
+            },
+            TestItem {
+                object_id: 4.into(),
+                depends_on: vec![],
+            },
+            TestItem {
+                object_id: 5.into(),
+                depends_on: vec![3.into()],
+            },
+        ];
+
+        let result = items.into_iter().sort_by_dependencies();
+
+        match result {
+            Err(ElefantToolsError::CircularDependencies(members)) => {
+                assert_eq!(members, vec!["item_1", "item_2", "item_3"]);
+            }
+            r => panic!("Expected circular dependencies, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn keeps_order_of_independent_items() {
+        let items = vec![
+            TestItem {
+                object_id: 1.into(),
+                depends_on: vec![5.into()],
+            },
+            TestItem {
+                object_id: 2.into(),
+                depends_on: vec![],
+            },
+            TestItem {
+                object_id: 3.into(),
+                // Not part of the items, so it is ignored.
+                depends_on: vec![42.into()],
+            },
+            TestItem {
+                object_id: 4.into(),
+                depends_on: vec![],
+            },
+            TestItem {
+                object_id: 5.into(),
+                depends_on: vec![4.into()],
+            },
+        ];
+
+        let sorted = items.into_iter().sort_by_dependencies().unwrap();
+
+        assert_eq!(
+            sorted
+                .iter()
+                .map(|i| i.object_id.value.unwrap())
+                .collect_vec(),
+            vec![2, 3, 4, 5, 1]
+        );
+    }
+
+    #[test]
+    fn items_without_an_id_are_dependencies_of_every_item_with_dependencies() {
+        let items = vec![
+            TestItem {
+                object_id: 1.into(),
+                depends_on: vec![2.into()],
+            },
+            TestItem {
+                object_id: ObjectId::default(),
+                depends_on: vec![],
+            },
+            TestItem {
+                object_id: 2.into(),
+                depends_on: vec![],
+            },
+            TestItem {
+                object_id: 3.into(),
+                depends_on: vec![],
+            },
+        ];
+
+        let sorted = items.into_iter().sort_by_dependencies().unwrap();
+
+        assert_eq!(
+            sorted.iter().map(|i| i.object_id.value).collect_vec(),
+            vec![None, Some(2), Some(3), Some(1)]
+        );
     }
 
     #[test]
     fn handles_empty_input() {
         let items: Vec<TestItem> = vec![];
-        let sorted = items.into_iter().sort_by_dependencies();
+        let sorted = items.into_iter().sort_by_dependencies().unwrap();
         assert_eq!(sorted, vec![]);
     }
 
@@ -306,7 +463,7 @@ mod tests {
             object_id: 1.into(),
             depends_on: vec![],
         }];
-        let sorted = items.into_iter().sort_by_dependencies();
+        let sorted = items.into_iter().sort_by_dependencies().unwrap();
         assert_eq!(
             sorted,
             vec![TestItem {
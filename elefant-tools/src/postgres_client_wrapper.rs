@@ -1,4 +1,4 @@
-use crate::Result;
+use crate::{Result, ResultExt};
 use bytes::Buf;
 use std::fmt::Display;
 use std::ops::Deref;
@@ -23,7 +23,7 @@ impl PostgresClientWrapper {
     /// Create a new PostgresClientWrapper.
     ///
     /// This will connect to the postgres server to figure out the version of the server.
-    /// If the version is less than 12, an error is returned.
+    /// If the version is less than [crate::MIN_SUPPORTED_POSTGRES_VERSION], an error is returned.
     #[instrument(skip_all)]
     pub async fn new(connection_string: &str) -> Result<Self> {
         let client = PostgresClient::new(connection_string).await?;
@@ -39,7 +39,7 @@ impl PostgresClientWrapper {
                     .expect("failed to get version from row")
                     .parse()
                     .expect("failed to parse version");
-                if version < 120000 {
+                if version < crate::MIN_SUPPORTED_POSTGRES_VERSION * 10000 {
                     return Err(crate::ElefantToolsError::UnsupportedPostgresVersion(
                         version,
                     ));
@@ -115,31 +115,25 @@ impl PostgresClient {
 
     /// Execute a query that does not return any results.
     pub async fn execute_non_query(&self, sql: &str) -> Result {
-        self.client.batch_execute(sql).await.map_err(|e| {
-            crate::ElefantToolsError::PostgresErrorWithQuery {
-                source: e,
-                query: sql.to_string(),
-            }
-        })?;
+        self.client.batch_execute(sql).await.with_query(sql)?;
 
         Ok(())
     }
 
     /// Execute a query that returns results.
     pub async fn get_results<T: FromRow>(&self, sql: &str) -> Result<Vec<T>> {
-        let query_results = self.client.query_raw(sql, Vec::<i32>::new()).await.map_err(|e| {
-            crate::ElefantToolsError::PostgresErrorWithQuery {
-                source: e,
-                query: sql.to_string(),
-            }
-        })?;
+        let query_results = self
+            .client
+            .query_raw(sql, Vec::<i32>::new())
+            .await
+            .with_query(sql)?;
 
         pin_mut!(query_results);
 
         let mut output = Vec::new();
 
-        while let Some(row) = query_results.try_next().await? {
-            output.push(T::from_row(row)?);
+        while let Some(row) = query_results.try_next().await.with_query(sql)? {
+            output.push(T::from_row(row).with_query(sql)?);
         }
 
         Ok(output)
@@ -152,6 +146,7 @@ impl PostgresClient {
             return Err(crate::ElefantToolsError::InvalidNumberOfResults {
                 actual: results.len(),
                 expected: 1,
+                query: sql.to_string(),
             });
         }
 
@@ -179,18 +174,29 @@ impl PostgresClient {
         Ok(result.0)
     }
 
+    /// Prepares the query without running it, and returns the names of the columns it would return.
+    pub async fn get_column_names(&self, sql: &str) -> Result<Vec<String>> {
+        let statement = self.client.prepare(sql).await.with_query(sql)?;
+
+        Ok(statement
+            .columns()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect())
+    }
+
     /// Starts a COPY IN operation.
     pub async fn copy_in<U>(&self, sql: &str) -> Result<CopyInSink<U>>
     where
         U: Buf + Send + 'static,
     {
-        let sink = self.client.copy_in(sql).await?;
+        let sink = self.client.copy_in(sql).await.with_query(sql)?;
         Ok(sink)
     }
 
     /// Starts a COPY OUT operation.
     pub async fn copy_out(&self, sql: &str) -> Result<CopyOutStream> {
-        let stream = self.client.copy_out(sql).await?;
+        let stream = self.client.copy_out(sql).await.with_query(sql)?;
         Ok(stream)
     }
 }
@@ -207,6 +213,18 @@ pub trait FromRow: Sized {
     fn from_row(row: Row) -> Result<Self>;
 }
 
+/// Reads all the columns of the row, which is useful when the number of columns is only known
+/// at runtime.
+impl<T: FromSqlOwned> FromRow for Vec<T> {
+    fn from_row(row: Row) -> Result<Self> {
+        let mut values = Vec::with_capacity(row.len());
+        for i in 0..row.len() {
+            values.push(row.try_get(i)?);
+        }
+        Ok(values)
+    }
+}
+
 impl<T1: FromSqlOwned> FromRow for (T1,) {
     fn from_row(row: Row) -> Result<Self> {
         Ok((row.try_get(0)?,))
@@ -0,0 +1,21 @@
+//! The items most programs using elefant-tools need: copying between storages, reading the
+//! schema of a database and the models describing it.
+//!
+//! ```
+//! use elefant_tools::prelude::*;
+//! ```
+//!
+//! [crate::Result] is left out, so it doesn't shadow the standard `Result`.
+
+pub use crate::{
+    apply_sql_directory, apply_sql_file, apply_sql_file_parallel, apply_sql_string, copy_data,
+    copy_table, execute_copy, plan_copy, CopyDataOptions, CopyDataReport, CopyTableOptions,
+    CopyTableReport, CsvDirectoryDestination, CsvDirectoryOptions, CsvFileOptions, CsvFileSource,
+    DataFormat, ElefantToolsError, IdentifierQuoter, PgCustomFormatDestination,
+    PostgresAggregateFunction,
+    PostgresClientWrapper, PostgresColumn, PostgresCompositeType, PostgresConstraint,
+    PostgresDatabase, PostgresDomain, PostgresEnum, PostgresExtension, PostgresFunction,
+    PostgresIndex, PostgresInstanceStorage, PostgresSchema, PostgresSequence, PostgresTable,
+    PostgresTrigger, PostgresView, SchemaReader, SqlDataMode, SqlDirectoryDestination, SqlFile,
+    SqlFileOptions,
+};
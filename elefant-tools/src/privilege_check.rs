@@ -0,0 +1,224 @@
+use crate::models::PUBLIC_GRANTEE;
+use crate::*;
+use itertools::Itertools;
+
+/// Removes the privileges of every object in the definition, see
+/// [CopyDataOptions::skip_privileges].
+pub(crate) fn remove_privileges(definition: &mut PostgresDatabase) {
+    for schema in &mut definition.schemas {
+        schema.privileges.clear();
+
+        for table in &mut schema.tables {
+            table.privileges.clear();
+        }
+
+        for view in &mut schema.views {
+            view.privileges.clear();
+        }
+
+        for function in &mut schema.functions {
+            function.privileges.clear();
+        }
+    }
+}
+
+/// Checks that the roles the privileges are granted to exist in the destination, so the copy
+/// fails with the names of all the missing roles before anything is created.
+pub(crate) fn check_roles(definition: &PostgresDatabase, roles: &[String]) -> Result<()> {
+    let missing = definition
+        .schemas
+        .iter()
+        .flat_map(|schema| {
+            schema
+                .privileges
+                .iter()
+                .chain(schema.tables.iter().flat_map(|t| &t.privileges))
+                .chain(schema.views.iter().flat_map(|v| &v.privileges))
+                .chain(schema.functions.iter().flat_map(|f| &f.privileges))
+        })
+        .map(|p| &p.grantee)
+        .filter(|grantee| *grantee != PUBLIC_GRANTEE && !roles.contains(grantee))
+        .sorted()
+        .dedup()
+        .cloned()
+        .collect_vec();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ElefantToolsError::RolesNotFound(missing))
+    }
+}
+
+/// Checks that the roles the row level security policies apply to exist in the destination, as
+/// the policies cannot be created otherwise. Unlike privileges, the policies cannot be skipped,
+/// as leaving them out would make the restricted rows visible.
+pub(crate) fn check_policy_roles(definition: &PostgresDatabase, roles: &[String]) -> Result<()> {
+    let missing = definition
+        .schemas
+        .iter()
+        .flat_map(|schema| {
+            schema.policies.iter().filter_map(move |policy| {
+                let missing_roles = policy
+                    .roles
+                    .iter()
+                    .filter(|role| *role != PUBLIC_GRANTEE && !roles.contains(role))
+                    .collect_vec();
+
+                if missing_roles.is_empty() {
+                    None
+                } else {
+                    Some(format!(
+                        "{} on {}.{} (roles {})",
+                        policy.name,
+                        schema.name,
+                        policy.table_name,
+                        missing_roles.iter().join(", ")
+                    ))
+                }
+            })
+        })
+        .collect_vec();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ElefantToolsError::PolicyRolesNotFound(missing))
+    }
+}
+
+/// Checks that the roles of [CopyDataOptions::grant_sequences_to] exist in the destination.
+pub(crate) fn check_sequence_grant_roles(grant_to: &[String], roles: &[String]) -> Result<()> {
+    let missing = grant_to
+        .iter()
+        .filter(|role| *role != PUBLIC_GRANTEE && !roles.contains(role))
+        .sorted()
+        .dedup()
+        .cloned()
+        .collect_vec();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ElefantToolsError::SequenceGrantRolesNotFound(missing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn privilege(grantee: &str) -> PostgresPrivilege {
+        PostgresPrivilege {
+            grantee: grantee.to_string(),
+            privilege: "SELECT".to_string(),
+            ..default()
+        }
+    }
+
+    fn database() -> PostgresDatabase {
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                privileges: vec![privilege("reporting")],
+                tables: vec![PostgresTable {
+                    name: "events".to_string(),
+                    privileges: vec![privilege("reporting"), privilege("app")],
+                    ..default()
+                }],
+                views: vec![PostgresView {
+                    name: "event_counts".to_string(),
+                    privileges: vec![privilege("dashboard")],
+                    ..default()
+                }],
+                functions: vec![PostgresFunction {
+                    function_name: "count_events".to_string(),
+                    privileges: vec![PostgresPrivilege {
+                        revoked: true,
+                        ..privilege(PUBLIC_GRANTEE)
+                    }],
+                    ..default()
+                }],
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn missing_roles_are_listed() {
+        let definition = database();
+
+        let result = check_roles(&definition, &["app".to_string()]);
+
+        assert!(matches!(
+            result,
+            Err(ElefantToolsError::RolesNotFound(roles)) if roles == vec!["dashboard".to_string(), "reporting".to_string()]
+        ));
+    }
+
+    #[test]
+    fn existing_roles_are_accepted() {
+        let definition = database();
+
+        let roles = vec![
+            "app".to_string(),
+            "dashboard".to_string(),
+            "reporting".to_string(),
+        ];
+
+        check_roles(&definition, &roles).unwrap();
+    }
+
+    #[test]
+    fn privileges_are_removed() {
+        let mut definition = database();
+
+        remove_privileges(&mut definition);
+
+        check_roles(&definition, &[]).unwrap();
+    }
+
+    #[test]
+    fn missing_policy_roles_are_listed() {
+        let mut definition = database();
+        definition.schemas[0].policies = vec![
+            PostgresRowLevelSecurityPolicy {
+                name: "own_events".to_string(),
+                table_name: "events".to_string(),
+                roles: vec!["app".to_string(), "auditor".to_string()],
+                ..default()
+            },
+            PostgresRowLevelSecurityPolicy {
+                name: "everyone_reads".to_string(),
+                table_name: "events".to_string(),
+                roles: vec![PUBLIC_GRANTEE.to_string()],
+                ..default()
+            },
+        ];
+
+        let result = check_policy_roles(&definition, &["app".to_string()]);
+
+        assert!(matches!(
+            result,
+            Err(ElefantToolsError::PolicyRolesNotFound(policies)) if policies == vec!["own_events on public.events (roles auditor)".to_string()]
+        ));
+    }
+
+    #[test]
+    fn missing_sequence_grant_roles_are_listed() {
+        let roles = vec!["app".to_string()];
+
+        check_sequence_grant_roles(&["app".to_string(), PUBLIC_GRANTEE.to_string()], &roles)
+            .unwrap();
+
+        let result = check_sequence_grant_roles(
+            &["worker".to_string(), "app".to_string(), "auditor".to_string()],
+            &roles,
+        );
+        assert!(matches!(
+            result,
+            Err(ElefantToolsError::SequenceGrantRolesNotFound(missing)) if missing == vec!["auditor".to_string(), "worker".to_string()]
+        ));
+    }
+}
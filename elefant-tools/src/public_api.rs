@@ -0,0 +1,81 @@
+//! Guards the public API. The names exported from the crate root and the prelude are compared
+//! to `snapshots/public_api.txt`, so exposing or removing an item also has to update the
+//! snapshot.
+
+/// The names made public by the `pub use` and `pub mod` items of the source file, qualified
+/// with `path`. Glob re-exports are listed as `path::*`, as their names cannot be known here.
+fn get_exported_names(source: &str, path: &str) -> Vec<String> {
+    let source = source
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.starts_with("//") && !line.starts_with("#["))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut names = Vec::new();
+
+    for statement in source.split(';').map(|s| s.trim()) {
+        if let Some(module) = statement.strip_prefix("pub mod ") {
+            names.push(format!("mod {}::{}", path, module.trim()));
+        } else if let Some(used) = statement.strip_prefix("pub use ") {
+            let items = match used.split_once('{') {
+                Some((_, items)) => items.trim_end_matches('}').split(',').collect(),
+                None => vec![used],
+            };
+
+            for item in items.into_iter().map(|i| i.trim()).filter(|i| !i.is_empty()) {
+                let name = match item.split_once(" as ") {
+                    Some((_, alias)) => alias,
+                    None => item.rsplit("::").next().unwrap_or(item),
+                };
+                names.push(format!("{}::{}", path, name.trim()));
+            }
+        }
+    }
+
+    names
+}
+
+#[test]
+fn exported_names_are_read_from_use_and_mod_items() {
+    let source = r#"
+//! pub use not::Exported;
+pub mod things;
+mod private;
+#[cfg(feature = "x")]
+pub use a::b::Single;
+pub(crate) use internal::Thing;
+pub use c::{
+    First, d::Second,
+    Third as Renamed,
+};
+pub use e::*;
+"#;
+
+    assert_eq!(
+        get_exported_names(source, "krate"),
+        vec![
+            "mod krate::things",
+            "krate::Single",
+            "krate::First",
+            "krate::Second",
+            "krate::Renamed",
+            "krate::*",
+        ]
+    );
+}
+
+#[test]
+fn public_api_matches_snapshot() {
+    let mut names = get_exported_names(include_str!("lib.rs"), "elefant_tools");
+    names.extend(get_exported_names(
+        include_str!("prelude.rs"),
+        "elefant_tools::prelude",
+    ));
+    names.sort();
+
+    let mut api = names.join("\n");
+    api.push('\n');
+
+    similar_asserts::assert_eq!(api, include_str!("snapshots/public_api.txt"));
+}
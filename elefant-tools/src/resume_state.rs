@@ -0,0 +1,233 @@
+//! Lets an interrupted copy continue where it stopped, see [CopyDataOptions::resume_state_path].
+
+use crate::models::{PostgresDatabase, PostgresSchema, PostgresTable};
+use crate::{ElefantToolsError, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::info;
+
+/// What is written to the resume file.
+#[derive(Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+struct ResumeStateFile {
+    /// Hash of the structure of the source, so a resume file isn't used with another source.
+    source_hash: String,
+    /// Whether the structure needed before the data was applied.
+    pre_data_structure_applied: bool,
+    /// The tables which data was copied completely.
+    completed_tables: Vec<CompletedTable>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct CompletedTable {
+    schema_name: String,
+    table_name: String,
+}
+
+/// The progress of a copy, which is written to the resume file as each step finishes.
+pub(crate) struct ResumeState {
+    path: PathBuf,
+    resuming: bool,
+    // A std mutex, as it is never held across an await. Writing the file while holding it keeps
+    // parallel table copies from overwriting each other's progress.
+    state: Mutex<ResumeStateFile>,
+}
+
+impl ResumeState {
+    /// Reads the resume file at `path`, or starts a new one if it does not exist. Fails with
+    /// [ElefantToolsError::ResumeStateMismatch] if the file was written for a source with another
+    /// structure.
+    pub(crate) fn load(path: &Path, source_definition: &PostgresDatabase) -> Result<Self> {
+        let source_hash = get_source_hash(source_definition)?;
+
+        let (state, resuming) = match std::fs::read_to_string(path) {
+            Ok(json) => {
+                let state: ResumeStateFile = serde_json::from_str(&json)?;
+                if state.source_hash != source_hash {
+                    return Err(ElefantToolsError::ResumeStateMismatch {
+                        path: path.display().to_string(),
+                    });
+                }
+                info!(
+                    "Resuming the copy from {}, {} tables were already copied",
+                    path.display(),
+                    state.completed_tables.len()
+                );
+                (state, true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (
+                ResumeStateFile {
+                    source_hash,
+                    ..Default::default()
+                },
+                false,
+            ),
+            Err(e) => return Err(e).with_context_path("read", path),
+        };
+
+        let resume_state = ResumeState {
+            path: path.to_path_buf(),
+            resuming,
+            state: Mutex::new(state),
+        };
+
+        // Written right away, so a copy interrupted while applying the structure is resumed as well.
+        resume_state.update(|_| {})?;
+
+        Ok(resume_state)
+    }
+
+    /// Whether an earlier copy was interrupted, so the destination might already have some of
+    /// the structure and data.
+    pub(crate) fn is_resuming(&self) -> bool {
+        self.resuming
+    }
+
+    pub(crate) fn is_pre_data_structure_applied(&self) -> bool {
+        self.state.lock().unwrap().pre_data_structure_applied // SAFE: Only panics if another thread panicked while updating
+    }
+
+    pub(crate) fn has_completed_tables(&self) -> bool {
+        !self.state.lock().unwrap().completed_tables.is_empty() // SAFE: Only panics if another thread panicked while updating
+    }
+
+    pub(crate) fn is_table_completed(&self, schema: &PostgresSchema, table: &PostgresTable) -> bool {
+        self.state
+            .lock()
+            .unwrap() // SAFE: Only panics if another thread panicked while updating
+            .completed_tables
+            .iter()
+            .any(|t| t.schema_name == schema.name && t.table_name == table.name)
+    }
+
+    pub(crate) fn mark_pre_data_structure_applied(&self) -> Result {
+        self.update(|state| state.pre_data_structure_applied = true)
+    }
+
+    pub(crate) fn mark_table_completed(
+        &self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+    ) -> Result {
+        self.update(|state| {
+            state.completed_tables.push(CompletedTable {
+                schema_name: schema.name.clone(),
+                table_name: table.name.clone(),
+            })
+        })
+    }
+
+    /// Removes the resume file, once the copy has finished.
+    pub(crate) fn remove(self) -> Result {
+        std::fs::remove_file(&self.path).with_context_path("remove", &self.path)
+    }
+
+    /// Updates the state and writes it to the resume file. The file is replaced by a rename, so
+    /// it is never left half written if the process is killed.
+    fn update(&self, change: impl FnOnce(&mut ResumeStateFile)) -> Result {
+        let mut state = self.state.lock().unwrap(); // SAFE: Only panics if another thread panicked while updating
+        change(&mut state);
+
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        std::fs::write(&temp_path, serde_json::to_string_pretty(&*state)?)
+            .with_context_path("write", &temp_path)?;
+        std::fs::rename(&temp_path, &self.path).with_context_path("write", &self.path)?;
+
+        Ok(())
+    }
+}
+
+/// Hashes the structure of the source. The values of the sequences are left out, as they change
+/// while the source is in use.
+fn get_source_hash(source_definition: &PostgresDatabase) -> Result<String> {
+    let mut definition = source_definition.clone();
+    for schema in &mut definition.schemas {
+        for sequence in &mut schema.sequences {
+            sequence.last_value = None;
+        }
+    }
+
+    let json = serde_json::to_string(&definition)?;
+
+    // FNV-1a, as the hash has to be the same across versions of Rust.
+    let hash = json.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    Ok(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PostgresSequence;
+    use crate::default;
+
+    fn definition() -> PostgresDatabase {
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![PostgresTable::new("users"), PostgresTable::new("events")],
+                sequences: vec![PostgresSequence {
+                    name: "users_id_seq".to_string(),
+                    last_value: Some(10),
+                    ..default()
+                }],
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("elefant-resume-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn remembers_the_progress_between_runs() {
+        let path = temp_path();
+        let definition = definition();
+        let schema = &definition.schemas[0];
+
+        let state = ResumeState::load(&path, &definition).unwrap();
+        assert!(!state.is_resuming());
+        assert!(path.exists());
+        state.mark_pre_data_structure_applied().unwrap();
+        state
+            .mark_table_completed(schema, &schema.tables[0])
+            .unwrap();
+        drop(state);
+
+        let mut changed_sequence = definition.clone();
+        changed_sequence.schemas[0].sequences[0].last_value = Some(500);
+
+        let state = ResumeState::load(&path, &changed_sequence).unwrap();
+        assert!(state.is_resuming());
+        assert!(state.is_pre_data_structure_applied());
+        assert!(state.has_completed_tables());
+        assert!(state.is_table_completed(schema, &schema.tables[0]));
+        assert!(!state.is_table_completed(schema, &schema.tables[1]));
+
+        state.remove().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn rejects_resume_files_of_other_sources() {
+        let path = temp_path();
+        let definition = definition();
+
+        ResumeState::load(&path, &definition).unwrap();
+
+        let mut other = definition.clone();
+        other.schemas[0].tables.pop();
+
+        let error = ResumeState::load(&path, &other).err().unwrap();
+        assert!(matches!(error, ElefantToolsError::ResumeStateMismatch { .. }));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+use crate::storage::DataFormat;
+
+/// The signature, flags and header extension length at the start of the binary copy format.
+const BINARY_HEADER_LENGTH: usize = 11 + 4 + 4;
+
+/// Counts the rows in the data stream of a table, as the chunks pass by.
+///
+/// The chunks can be split anywhere, also in the middle of a row.
+pub(crate) enum RowCounter {
+    /// Each row ends with a line break. Line breaks in values are escaped as `\n`.
+    Text,
+    /// Each row ends with a line break, but values in quotes can contain line breaks.
+    Csv {
+        in_quotes: bool,
+        skip_header: bool,
+    },
+    Binary(BinaryRowCounter),
+}
+
+impl RowCounter {
+    pub(crate) fn new(data_format: &DataFormat) -> Self {
+        match data_format {
+            DataFormat::Text { .. } => RowCounter::Text,
+            DataFormat::Csv { header, .. } => RowCounter::Csv {
+                in_quotes: false,
+                skip_header: *header,
+            },
+            DataFormat::PostgresBinary { .. } => RowCounter::Binary(BinaryRowCounter::default()),
+        }
+    }
+
+    /// Returns how many rows ended in the chunk.
+    pub(crate) fn count(&mut self, bytes: &[u8]) -> u64 {
+        match self {
+            RowCounter::Text => bytes.iter().filter(|b| **b == b'\n').count() as u64,
+            RowCounter::Csv {
+                in_quotes,
+                skip_header,
+            } => {
+                let mut rows = 0;
+                for b in bytes {
+                    if *b == b'"' {
+                        // An escaped quote (`""`) toggles twice, so it doesn't change anything.
+                        *in_quotes = !*in_quotes;
+                    } else if *b == b'\n' && !*in_quotes {
+                        if *skip_header {
+                            *skip_header = false;
+                        } else {
+                            rows += 1;
+                        }
+                    }
+                }
+                rows
+            }
+            RowCounter::Binary(counter) => counter.count(bytes),
+        }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+enum BinaryState {
+    #[default]
+    Header,
+    FieldCount,
+    FieldLength {
+        remaining_fields: i16,
+    },
+    Trailer,
+}
+
+/// Follows the tuples of the binary copy format, see
+/// <https://www.postgresql.org/docs/current/sql-copy.html#id-1.9.3.55.9.4>.
+#[derive(Debug, Default)]
+pub(crate) struct BinaryRowCounter {
+    state: BinaryState,
+    /// The bytes read so far of the header or integer currently being read.
+    pending: Vec<u8>,
+    /// How many bytes of the current value or header extension are left.
+    skip: usize,
+}
+
+impl BinaryRowCounter {
+    fn count(&mut self, mut bytes: &[u8]) -> u64 {
+        let mut rows = 0;
+
+        loop {
+            if self.skip > 0 {
+                let skipped = self.skip.min(bytes.len());
+                bytes = &bytes[skipped..];
+                self.skip -= skipped;
+                if self.skip > 0 {
+                    break;
+                }
+            }
+
+            let needed = match self.state {
+                BinaryState::Header => BINARY_HEADER_LENGTH,
+                BinaryState::FieldCount => 2,
+                BinaryState::FieldLength { .. } => 4,
+                BinaryState::Trailer => break,
+            };
+
+            let take = (needed - self.pending.len()).min(bytes.len());
+            self.pending.extend_from_slice(&bytes[..take]);
+            bytes = &bytes[take..];
+            if self.pending.len() < needed {
+                break;
+            }
+
+            match self.state {
+                BinaryState::Header => {
+                    let extension_length = &self.pending[BINARY_HEADER_LENGTH - 4..];
+                    self.skip = u32::from_be_bytes(extension_length.try_into().unwrap()) as usize;
+                    self.state = BinaryState::FieldCount;
+                }
+                BinaryState::FieldCount => {
+                    let field_count = i16::from_be_bytes([self.pending[0], self.pending[1]]);
+                    self.state = match field_count {
+                        -1 => BinaryState::Trailer,
+                        0 => BinaryState::FieldCount,
+                        n => BinaryState::FieldLength {
+                            remaining_fields: n,
+                        },
+                    };
+                    if field_count >= 0 {
+                        rows += 1;
+                    }
+                }
+                BinaryState::FieldLength { remaining_fields } => {
+                    let length = i32::from_be_bytes(self.pending[..4].try_into().unwrap());
+                    // Null values have a length of -1, and no bytes.
+                    self.skip = length.max(0) as usize;
+                    self.state = if remaining_fields == 1 {
+                        BinaryState::FieldCount
+                    } else {
+                        BinaryState::FieldLength {
+                            remaining_fields: remaining_fields - 1,
+                        }
+                    };
+                }
+                BinaryState::Trailer => unreachable!(),
+            }
+
+            self.pending.clear();
+        }
+
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default;
+
+    fn count_in_chunks_of(counter: &mut RowCounter, data: &[u8], chunk_size: usize) -> u64 {
+        data.chunks(chunk_size).map(|c| counter.count(c)).sum()
+    }
+
+    fn binary_data() -> Vec<u8> {
+        let mut data = b"PGCOPY\n\xff\r\n\0".to_vec();
+        data.extend_from_slice(&0u32.to_be_bytes());
+        // A header extension, which should be skipped.
+        data.extend_from_slice(&3u32.to_be_bytes());
+        data.extend_from_slice(b"ext");
+
+        // (1, 'a\nb')
+        data.extend_from_slice(&2i16.to_be_bytes());
+        data.extend_from_slice(&4i32.to_be_bytes());
+        data.extend_from_slice(&1i32.to_be_bytes());
+        data.extend_from_slice(&3i32.to_be_bytes());
+        data.extend_from_slice(b"a\nb");
+
+        // (2, null)
+        data.extend_from_slice(&2i16.to_be_bytes());
+        data.extend_from_slice(&4i32.to_be_bytes());
+        data.extend_from_slice(&2i32.to_be_bytes());
+        data.extend_from_slice(&(-1i32).to_be_bytes());
+
+        // A row of a table without columns.
+        data.extend_from_slice(&0i16.to_be_bytes());
+
+        data.extend_from_slice(&(-1i16).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn counts_binary_rows_regardless_of_how_the_chunks_are_split() {
+        let data = binary_data();
+
+        for chunk_size in [1, 2, 3, 7, data.len()] {
+            let mut counter = RowCounter::new(&DataFormat::PostgresBinary {
+                postgres_version: None,
+            });
+            assert_eq!(
+                count_in_chunks_of(&mut counter, &data, chunk_size),
+                3,
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn counts_text_rows() {
+        let data = b"1\ta\\nb\n2\t\\N\n3\tc\n";
+
+        for chunk_size in [1, 4, data.len()] {
+            let mut counter = RowCounter::new(&DataFormat::Text { options: default() });
+            assert_eq!(count_in_chunks_of(&mut counter, data, chunk_size), 3);
+        }
+    }
+
+    #[test]
+    fn counts_csv_rows_with_line_breaks_in_quotes() {
+        let data = b"id,value\n1,\"a\nb\"\n2,\"say \"\"hi\"\"\"\n3,\n";
+
+        for chunk_size in [1, 5, data.len()] {
+            let mut counter = RowCounter::new(&DataFormat::Csv {
+                header: true,
+                delimiter: ',',
+            });
+            assert_eq!(count_in_chunks_of(&mut counter, data, chunk_size), 3);
+
+            let mut counter = RowCounter::new(&DataFormat::Csv {
+                header: false,
+                delimiter: ',',
+            });
+            assert_eq!(count_in_chunks_of(&mut counter, data, chunk_size), 4);
+        }
+    }
+}
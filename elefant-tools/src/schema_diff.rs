@@ -0,0 +1,1085 @@
+//! Compares the structure of two databases, and generates the SQL that migrates one to the other.
+//!
+//! Unlike the differential copy, which only creates the objects missing in the destination,
+//! this also finds objects that were removed or changed, and alters them in place where possible.
+
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::{IdentifierQuoter, Quotable};
+use crate::{
+    ColumnIdentity, PostgresColumn, PostgresConstraint, PostgresDatabase, PostgresFunction,
+    PostgresIndex, PostgresIndexType, PostgresSchema, PostgresTable, PostgresView,
+    TableTypeDetails,
+};
+use std::collections::HashSet;
+
+/// A difference between two databases, see [diff]. Objects that were added or changed
+/// reference the source, and objects that were removed reference the target.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SchemaChange<'a> {
+    AddedSchema(&'a PostgresSchema),
+    /// The schema is dropped with `cascade`, as the objects in it are not listed separately.
+    RemovedSchema(&'a PostgresSchema),
+    AddedTable {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+    },
+    RemovedTable {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+    },
+    AddedColumn {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        column: &'a PostgresColumn,
+    },
+    RemovedColumn {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        column: &'a PostgresColumn,
+    },
+    /// The type, nullability, default value, generation expression or identity of the column
+    /// changed.
+    ChangedColumn {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        from: &'a PostgresColumn,
+        to: &'a PostgresColumn,
+    },
+    AddedIndex {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        index: &'a PostgresIndex,
+    },
+    RemovedIndex {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        index: &'a PostgresIndex,
+    },
+    /// The index is built differently, so it has to be recreated. `target_table` is the table
+    /// in the target, which knows the constraints currently using the index.
+    ChangedIndex {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        target_table: &'a PostgresTable,
+        from: &'a PostgresIndex,
+        to: &'a PostgresIndex,
+    },
+    AddedConstraint {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        constraint: &'a PostgresConstraint,
+    },
+    RemovedConstraint {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        constraint: &'a PostgresConstraint,
+    },
+    ChangedConstraint {
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+        from: &'a PostgresConstraint,
+        to: &'a PostgresConstraint,
+    },
+    AddedFunction {
+        schema: &'a PostgresSchema,
+        function: &'a PostgresFunction,
+    },
+    RemovedFunction {
+        schema: &'a PostgresSchema,
+        function: &'a PostgresFunction,
+    },
+    ChangedFunction {
+        schema: &'a PostgresSchema,
+        from: &'a PostgresFunction,
+        to: &'a PostgresFunction,
+    },
+    AddedView {
+        schema: &'a PostgresSchema,
+        view: &'a PostgresView,
+    },
+    RemovedView {
+        schema: &'a PostgresSchema,
+        view: &'a PostgresView,
+    },
+    ChangedView {
+        schema: &'a PostgresSchema,
+        from: &'a PostgresView,
+        to: &'a PostgresView,
+    },
+}
+
+/// The differences between two databases, see [diff].
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct SchemaDiff<'a> {
+    pub changes: Vec<SchemaChange<'a>>,
+}
+
+/// Finds the changes needed to make the structure of `target` match `source`. Objects are
+/// matched by their name, and functions by their name and arguments, so renamed objects show up
+/// as removed and added.
+///
+/// Only schemas, tables, columns, indexes, constraints, functions and views are compared. Other
+/// objects, such as types, sequences and triggers, are left as they are, and so are comments and
+/// privileges.
+pub fn diff<'a>(source: &'a PostgresDatabase, target: &'a PostgresDatabase) -> SchemaDiff<'a> {
+    let mut changes = Vec::new();
+
+    for schema in &source.schemas {
+        match target.try_get_schema(&schema.name) {
+            Some(target_schema) => diff_schema(schema, target_schema, &mut changes),
+            None => {
+                changes.push(SchemaChange::AddedSchema(schema));
+                add_schema_objects(schema, &mut changes);
+            }
+        }
+    }
+
+    for target_schema in &target.schemas {
+        if source.try_get_schema(&target_schema.name).is_none() {
+            changes.push(SchemaChange::RemovedSchema(target_schema));
+        }
+    }
+
+    SchemaDiff { changes }
+}
+
+fn add_schema_objects<'a>(schema: &'a PostgresSchema, changes: &mut Vec<SchemaChange<'a>>) {
+    for table in &schema.tables {
+        add_table(schema, table, changes);
+    }
+
+    for function in &schema.functions {
+        changes.push(SchemaChange::AddedFunction { schema, function });
+    }
+
+    for view in &schema.views {
+        changes.push(SchemaChange::AddedView { schema, view });
+    }
+}
+
+/// Adds the table, and the indexes and constraints that are not part of `create table`.
+fn add_table<'a>(
+    schema: &'a PostgresSchema,
+    table: &'a PostgresTable,
+    changes: &mut Vec<SchemaChange<'a>>,
+) {
+    changes.push(SchemaChange::AddedTable { schema, table });
+
+    // Hypertables get their indexes and unique constraints together with the table.
+    let is_hypertable = table.is_timescale_table();
+
+    for index in &table.indices {
+        // Primary keys are part of `create table`.
+        if !is_hypertable
+            && index.index_constraint_type != PostgresIndexType::PrimaryKey
+            && !is_partition_index(index)
+        {
+            changes.push(SchemaChange::AddedIndex {
+                schema,
+                table,
+                index,
+            });
+        }
+    }
+
+    for constraint in &table.constraints {
+        let is_created_with_table = match constraint {
+            PostgresConstraint::Check(check) => !check.not_valid,
+            PostgresConstraint::Unique(_) => is_hypertable,
+            PostgresConstraint::ForeignKey(_) => false,
+        };
+
+        if !is_created_with_table {
+            changes.push(SchemaChange::AddedConstraint {
+                schema,
+                table,
+                constraint,
+            });
+        }
+    }
+}
+
+/// The indexes of partitions are created by Postgres when the index is created on the
+/// partitioned table.
+fn is_partition_index(index: &PostgresIndex) -> bool {
+    index.parent_index.is_some()
+}
+
+fn diff_schema<'a>(
+    schema: &'a PostgresSchema,
+    target_schema: &'a PostgresSchema,
+    changes: &mut Vec<SchemaChange<'a>>,
+) {
+    for table in &schema.tables {
+        match target_schema.try_get_table(&table.name) {
+            Some(target_table) => diff_table(schema, table, target_schema, target_table, changes),
+            None => add_table(schema, table, changes),
+        }
+    }
+
+    for target_table in &target_schema.tables {
+        if schema.try_get_table(&target_table.name).is_none() {
+            changes.push(SchemaChange::RemovedTable {
+                schema: target_schema,
+                table: target_table,
+            });
+        }
+    }
+
+    for function in &schema.functions {
+        match find_function(target_schema, function) {
+            Some(target_function) => {
+                if is_function_changed(schema, target_function, function) {
+                    changes.push(SchemaChange::ChangedFunction {
+                        schema,
+                        from: target_function,
+                        to: function,
+                    });
+                }
+            }
+            None => changes.push(SchemaChange::AddedFunction { schema, function }),
+        }
+    }
+
+    for target_function in &target_schema.functions {
+        if find_function(schema, target_function).is_none() {
+            changes.push(SchemaChange::RemovedFunction {
+                schema: target_schema,
+                function: target_function,
+            });
+        }
+    }
+
+    for view in &schema.views {
+        match target_schema.views.iter().find(|v| v.name == view.name) {
+            Some(target_view) => {
+                if is_view_changed(target_view, view) {
+                    changes.push(SchemaChange::ChangedView {
+                        schema,
+                        from: target_view,
+                        to: view,
+                    });
+                }
+            }
+            None => changes.push(SchemaChange::AddedView { schema, view }),
+        }
+    }
+
+    for target_view in &target_schema.views {
+        if !schema.views.iter().any(|v| v.name == target_view.name) {
+            changes.push(SchemaChange::RemovedView {
+                schema: target_schema,
+                view: target_view,
+            });
+        }
+    }
+}
+
+fn diff_table<'a>(
+    schema: &'a PostgresSchema,
+    table: &'a PostgresTable,
+    target_schema: &'a PostgresSchema,
+    target_table: &'a PostgresTable,
+    changes: &mut Vec<SchemaChange<'a>>,
+) {
+    // Partitions get their columns from the partitioned table.
+    if !matches!(
+        table.table_type,
+        TableTypeDetails::PartitionedChildTable { .. }
+    ) {
+        for column in &table.columns {
+            match target_table.column(&column.name) {
+                Some(target_column) => {
+                    if is_column_changed(target_column, column) {
+                        changes.push(SchemaChange::ChangedColumn {
+                            schema,
+                            table,
+                            from: target_column,
+                            to: column,
+                        });
+                    }
+                }
+                None => changes.push(SchemaChange::AddedColumn {
+                    schema,
+                    table,
+                    column,
+                }),
+            }
+        }
+
+        for target_column in &target_table.columns {
+            if table.column(&target_column.name).is_none() {
+                changes.push(SchemaChange::RemovedColumn {
+                    schema: target_schema,
+                    table: target_table,
+                    column: target_column,
+                });
+            }
+        }
+    }
+
+    for index in table.indices.iter().filter(|i| !is_partition_index(i)) {
+        match target_table.indices.iter().find(|i| i.name == index.name) {
+            Some(target_index) => {
+                if !index.is_equivalent_to(target_index, true) {
+                    changes.push(SchemaChange::ChangedIndex {
+                        schema,
+                        table,
+                        target_table,
+                        from: target_index,
+                        to: index,
+                    });
+                }
+            }
+            None => changes.push(SchemaChange::AddedIndex {
+                schema,
+                table,
+                index,
+            }),
+        }
+    }
+
+    for target_index in target_table
+        .indices
+        .iter()
+        .filter(|i| !is_partition_index(i))
+    {
+        if !table.indices.iter().any(|i| i.name == target_index.name) {
+            changes.push(SchemaChange::RemovedIndex {
+                schema: target_schema,
+                table: target_table,
+                index: target_index,
+            });
+        }
+    }
+
+    let quoter = IdentifierQuoter::empty();
+
+    for constraint in &table.constraints {
+        match target_table
+            .constraints
+            .iter()
+            .find(|c| c.name() == constraint.name())
+        {
+            Some(target_constraint) => {
+                // Compared by their definition, as the object ids differ between databases.
+                let definition =
+                    get_constraint_create_statement(constraint, table, schema, &quoter);
+                let target_definition =
+                    get_constraint_create_statement(target_constraint, table, schema, &quoter);
+                if definition != target_definition {
+                    changes.push(SchemaChange::ChangedConstraint {
+                        schema,
+                        table,
+                        from: target_constraint,
+                        to: constraint,
+                    });
+                }
+            }
+            None => changes.push(SchemaChange::AddedConstraint {
+                schema,
+                table,
+                constraint,
+            }),
+        }
+    }
+
+    for target_constraint in &target_table.constraints {
+        if !table
+            .constraints
+            .iter()
+            .any(|c| c.name() == target_constraint.name())
+        {
+            changes.push(SchemaChange::RemovedConstraint {
+                schema: target_schema,
+                table: target_table,
+                constraint: target_constraint,
+            });
+        }
+    }
+}
+
+fn is_column_changed(from: &PostgresColumn, to: &PostgresColumn) -> bool {
+    let quoter = IdentifierQuoter::empty();
+
+    from.get_type_expression(&quoter) != to.get_type_expression(&quoter)
+        || from.is_nullable != to.is_nullable
+        || from.default_value != to.default_value
+        || from.generated != to.generated
+        || from.identity != to.identity
+}
+
+fn find_function<'a>(
+    schema: &'a PostgresSchema,
+    function: &PostgresFunction,
+) -> Option<&'a PostgresFunction> {
+    schema
+        .functions
+        .iter()
+        .find(|f| f.function_name == function.function_name && f.arguments == function.arguments)
+}
+
+/// Compared by their definition, as the object ids and dependencies differ between databases.
+fn is_function_changed(
+    schema: &PostgresSchema,
+    from: &PostgresFunction,
+    to: &PostgresFunction,
+) -> bool {
+    let quoter = IdentifierQuoter::empty();
+
+    from.get_create_statement(schema, &quoter) != to.get_create_statement(schema, &quoter)
+}
+
+fn is_view_changed(from: &PostgresView, to: &PostgresView) -> bool {
+    from.definition != to.definition
+        || from.is_materialized != to.is_materialized
+        || from.view_options != to.view_options
+        || from
+            .columns
+            .iter()
+            .map(|c| &c.name)
+            .ne(to.columns.iter().map(|c| &c.name))
+}
+
+impl SchemaDiff<'_> {
+    /// If the databases have the same structure.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Generates the SQL that makes the target match the source.
+    ///
+    /// Everything is dropped before anything is created, and functions are created after the
+    /// tables, but before the default values that might use them are set. Changing the type of a
+    /// column is marked with a `-- WARNING` comment, as it fails if the existing values cannot be
+    /// cast to the new type, and so is changing the generation expression, which requires
+    /// recreating the column by hand. Review the SQL before running it.
+    pub fn to_migration_sql(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        let q = identifier_quoter;
+        let mut statements: Vec<String> = Vec::new();
+
+        // Dropping a unique constraint drops its index as well.
+        let indexes_dropped_with_constraints = self
+            .changes
+            .iter()
+            .filter_map(|change| match change {
+                SchemaChange::RemovedConstraint {
+                    schema,
+                    table,
+                    constraint: PostgresConstraint::Unique(uk),
+                }
+                | SchemaChange::ChangedConstraint {
+                    schema,
+                    table,
+                    from: PostgresConstraint::Unique(uk),
+                    ..
+                } => Some((&schema.name, &table.name, &uk.unique_index_name)),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        for change in &self.changes {
+            if let SchemaChange::AddedSchema(schema) = change {
+                statements.push(schema.get_create_statement(q));
+            }
+        }
+
+        for change in &self.changes {
+            if let SchemaChange::RemovedView { schema, view }
+            | SchemaChange::ChangedView {
+                schema, from: view, ..
+            } = change
+            {
+                statements.push(get_drop_view_statement(schema, view, q));
+            }
+        }
+
+        for change in &self.changes {
+            if let SchemaChange::RemovedConstraint {
+                schema,
+                table,
+                constraint,
+            }
+            | SchemaChange::ChangedConstraint {
+                schema,
+                table,
+                from: constraint,
+                ..
+            } = change
+            {
+                statements.push(get_drop_constraint_statement(schema, table, constraint, q));
+            }
+        }
+
+        for change in &self.changes {
+            let (schema, table, index) = match change {
+                SchemaChange::RemovedIndex {
+                    schema,
+                    table,
+                    index,
+                } => (schema, table, index),
+                SchemaChange::ChangedIndex {
+                    schema,
+                    target_table,
+                    from,
+                    ..
+                } => (schema, target_table, from),
+                _ => continue,
+            };
+
+            if !indexes_dropped_with_constraints.contains(&(&schema.name, &table.name, &index.name))
+            {
+                statements.push(index.get_drop_statement(schema, table, q));
+            }
+        }
+
+        for change in &self.changes {
+            if let SchemaChange::RemovedFunction { schema, function } = change {
+                statements.push(function.get_drop_statement(schema, q));
+            }
+        }
+
+        for change in &self.changes {
+            if let SchemaChange::RemovedTable { schema, table } = change {
+                statements.push(format!(
+                    "drop table {}.{};",
+                    schema.name.quote(q, ColumnName),
+                    table.name.quote(q, ColumnName)
+                ));
+            }
+        }
+
+        for change in &self.changes {
+            if let SchemaChange::RemovedColumn {
+                schema,
+                table,
+                column,
+            } = change
+            {
+                statements.push(format!(
+                    "{} drop column {};",
+                    get_alter_table(schema, table, q),
+                    column.name.quote(q, ColumnName)
+                ));
+            }
+        }
+
+        for change in &self.changes {
+            if let SchemaChange::RemovedSchema(schema) = change {
+                statements.push(format!(
+                    "drop schema {} cascade;",
+                    schema.name.quote(q, ColumnName)
+                ));
+            }
+        }
+
+        for change in &self.changes {
+            match change {
+                SchemaChange::AddedTable { schema, table } => {
+                    statements.push(table.get_create_statement(schema, q));
+                }
+                SchemaChange::AddedColumn {
+                    schema,
+                    table,
+                    column,
+                } => {
+                    statements.push(format!(
+                        "{} add column {};",
+                        get_alter_table(schema, table, q),
+                        column.get_column_definition(q)
+                    ));
+                }
+                SchemaChange::ChangedColumn {
+                    schema,
+                    table,
+                    from,
+                    to,
+                } => {
+                    statements.extend(get_alter_column_statements(schema, table, from, to, q));
+                }
+                _ => {}
+            }
+        }
+
+        for change in &self.changes {
+            match change {
+                SchemaChange::AddedFunction { schema, function } => {
+                    statements.push(function.get_create_statement(schema, q));
+                }
+                SchemaChange::ChangedFunction { schema, from, to } => {
+                    // `create or replace` cannot change the result or the kind of a function.
+                    if from.result == to.result && from.kind == to.kind {
+                        statements.push(to.get_replace_statement(schema, q));
+                    } else {
+                        statements.push(from.get_drop_statement(schema, q));
+                        statements.push(to.get_create_statement(schema, q));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for change in &self.changes {
+            match change {
+                SchemaChange::AddedTable { schema, table } => {
+                    statements.extend(
+                        table.columns.iter().filter_map(|c| {
+                            c.get_alter_table_set_default_statement(table, schema, q)
+                        }),
+                    );
+                }
+                SchemaChange::AddedColumn {
+                    schema,
+                    table,
+                    column,
+                } => {
+                    statements
+                        .extend(column.get_alter_table_set_default_statement(table, schema, q));
+                }
+                SchemaChange::ChangedColumn {
+                    schema,
+                    table,
+                    from,
+                    to,
+                } if from.default_value != to.default_value => {
+                    statements.push(
+                        match to.get_alter_table_set_default_statement(table, schema, q) {
+                            Some(sql) => sql,
+                            None => format!(
+                                "{} alter column {} drop default;",
+                                get_alter_table(schema, table, q),
+                                to.name.quote(q, ColumnName)
+                            ),
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        for change in &self.changes {
+            match change {
+                SchemaChange::AddedIndex {
+                    schema,
+                    table,
+                    index,
+                } => {
+                    statements.push(index.get_create_index_command(schema, table, q));
+                }
+                SchemaChange::ChangedIndex {
+                    schema,
+                    table,
+                    target_table,
+                    to,
+                    ..
+                } => {
+                    statements.push(to.get_create_index_command(schema, table, q));
+
+                    // A unique constraint that is unchanged was dropped together with the index.
+                    for constraint in &table.constraints {
+                        if let PostgresConstraint::Unique(uk) = constraint {
+                            if uk.unique_index_name == to.name
+                                && target_table.constraints.iter().any(|c| c.name() == uk.name)
+                                && !indexes_dropped_with_constraints.contains(&(
+                                    &schema.name,
+                                    &table.name,
+                                    &uk.unique_index_name,
+                                ))
+                            {
+                                statements.push(uk.get_create_statement(table, schema, q));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for change in &self.changes {
+            if let SchemaChange::AddedConstraint {
+                schema,
+                table,
+                constraint,
+            }
+            | SchemaChange::ChangedConstraint {
+                schema,
+                table,
+                to: constraint,
+                ..
+            } = change
+            {
+                statements.push(get_constraint_create_statement(
+                    constraint, table, schema, q,
+                ));
+            }
+        }
+
+        for change in &self.changes {
+            if let SchemaChange::AddedView { schema, view }
+            | SchemaChange::ChangedView {
+                schema, to: view, ..
+            } = change
+            {
+                statements.push(view.get_create_view_sql(schema, q));
+            }
+        }
+
+        statements.join("\n")
+    }
+}
+
+fn get_alter_table(
+    schema: &PostgresSchema,
+    table: &PostgresTable,
+    identifier_quoter: &IdentifierQuoter,
+) -> String {
+    format!(
+        "alter table {}.{}",
+        schema.name.quote(identifier_quoter, ColumnName),
+        table.name.quote(identifier_quoter, ColumnName)
+    )
+}
+
+fn get_constraint_create_statement(
+    constraint: &PostgresConstraint,
+    table: &PostgresTable,
+    schema: &PostgresSchema,
+    identifier_quoter: &IdentifierQuoter,
+) -> String {
+    match constraint {
+        PostgresConstraint::Check(check) => {
+            check.get_create_statement(table, schema, identifier_quoter)
+        }
+        PostgresConstraint::ForeignKey(fk) => {
+            fk.get_create_statement(table, schema, identifier_quoter)
+        }
+        PostgresConstraint::Unique(uk) => uk.get_create_statement(table, schema, identifier_quoter),
+    }
+}
+
+fn get_drop_constraint_statement(
+    schema: &PostgresSchema,
+    table: &PostgresTable,
+    constraint: &PostgresConstraint,
+    identifier_quoter: &IdentifierQuoter,
+) -> String {
+    format!(
+        "{} drop constraint {};",
+        get_alter_table(schema, table, identifier_quoter),
+        constraint.name().quote(identifier_quoter, ColumnName)
+    )
+}
+
+fn get_drop_view_statement(
+    schema: &PostgresSchema,
+    view: &PostgresView,
+    identifier_quoter: &IdentifierQuoter,
+) -> String {
+    format!(
+        "drop {}view {}.{};",
+        if view.is_materialized {
+            "materialized "
+        } else {
+            ""
+        },
+        schema.name.quote(identifier_quoter, ColumnName),
+        view.name.quote(identifier_quoter, ColumnName)
+    )
+}
+
+/// Alters the column in place. The default value is left out, as it is set after the functions
+/// have been created.
+fn get_alter_column_statements(
+    schema: &PostgresSchema,
+    table: &PostgresTable,
+    from: &PostgresColumn,
+    to: &PostgresColumn,
+    identifier_quoter: &IdentifierQuoter,
+) -> Vec<String> {
+    let q = identifier_quoter;
+    let alter_column = format!(
+        "{} alter column {}",
+        get_alter_table(schema, table, q),
+        to.name.quote(q, ColumnName)
+    );
+    let mut statements = Vec::new();
+
+    if from.generated != to.generated {
+        statements.push(format!(
+            "-- WARNING: The generation expression of {}.{}.{} changed, which requires recreating the column",
+            schema.name, table.name, to.name
+        ));
+    }
+
+    let from_type = from.get_type_expression(q);
+    let to_type = to.get_type_expression(q);
+    if from_type != to_type {
+        statements.push(format!(
+            "-- WARNING: Changing the type of {}.{}.{} from {} to {} rewrites the table, and fails if the existing values cannot be cast",
+            schema.name, table.name, to.name, from_type, to_type
+        ));
+        statements.push(to.get_alter_type_statement(table, schema, q));
+    }
+
+    if from.is_nullable != to.is_nullable {
+        statements.push(format!(
+            "{} {} not null;",
+            alter_column,
+            if to.is_nullable { "drop" } else { "set" }
+        ));
+    }
+
+    if from.identity != to.identity {
+        let generated = |identity: &ColumnIdentity| match identity {
+            ColumnIdentity::GeneratedAlways => "always",
+            ColumnIdentity::GeneratedByDefault => "by default",
+        };
+
+        statements.push(match (&from.identity, &to.identity) {
+            (_, None) => format!("{} drop identity;", alter_column),
+            (None, Some(identity)) => format!(
+                "{} add generated {} as identity;",
+                alter_column,
+                generated(identity)
+            ),
+            (Some(_), Some(identity)) => {
+                format!("{} set generated {};", alter_column, generated(identity))
+            }
+        });
+    }
+
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema_reader::tests::introspect_schema;
+    use crate::test_helpers;
+    use crate::test_helpers::TestHelper;
+    use crate::*;
+    use elefant_test_macros::pg_test;
+
+    fn column(name: &str, data_type: &str) -> PostgresColumn {
+        PostgresColumn {
+            name: name.to_string(),
+            ordinal_position: 1,
+            is_nullable: true,
+            data_type: data_type.to_string(),
+            default_value: None,
+            generated: None,
+            comment: None,
+            array_dimensions: 0,
+            data_type_length: None,
+            identity: None,
+            identity_options: None,
+            inherits_default: false,
+        }
+    }
+
+    fn database(tables: Vec<PostgresTable>) -> PostgresDatabase {
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables,
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn identical_databases_have_no_changes() {
+        let database = database(vec![PostgresTable {
+            name: "people".to_string(),
+            columns: vec![column("id", "int4")],
+            ..default()
+        }]);
+
+        let diff = diff(&database, &database);
+
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_migration_sql(&IdentifierQuoter::empty()), "");
+    }
+
+    #[test]
+    fn alters_changed_columns_in_place() {
+        let source = database(vec![PostgresTable {
+            name: "people".to_string(),
+            columns: vec![
+                PostgresColumn {
+                    is_nullable: false,
+                    ..column("id", "int8")
+                },
+                PostgresColumn {
+                    default_value: Some("'unknown'::text".to_string()),
+                    ..column("name", "text")
+                },
+                column("email", "text"),
+            ],
+            ..default()
+        }]);
+        let target = database(vec![PostgresTable {
+            name: "people".to_string(),
+            columns: vec![
+                column("id", "int4"),
+                column("name", "text"),
+                column("age", "int4"),
+            ],
+            ..default()
+        }]);
+
+        let diff = diff(&source, &target);
+
+        assert_eq!(diff.changes.len(), 4);
+        assert_eq!(
+            diff.to_migration_sql(&IdentifierQuoter::empty()),
+            "alter table public.people drop column age;
+-- WARNING: Changing the type of public.people.id from int4 to int8 rewrites the table, and fails if the existing values cannot be cast
+alter table public.people alter column id type int8 using id::int8;
+alter table public.people alter column id set not null;
+alter table public.people add column email text;
+alter table public.people alter column name set default 'unknown'::text;"
+        );
+    }
+
+    #[test]
+    fn recreates_changed_indexes_and_constraints() {
+        let index = |name: &str, column: &str| PostgresIndex {
+            name: name.to_string(),
+            key_columns: vec![PostgresIndexKeyColumn {
+                name: column.to_string(),
+                ordinal_position: 1,
+                direction: None,
+                nulls_order: None,
+                operator_class: None,
+                collation: None,
+            }],
+            index_type: "btree".to_string(),
+            ..default()
+        };
+        let check = |clause: &str| {
+            PostgresConstraint::Check(PostgresCheckConstraint {
+                name: "name_not_empty".to_string(),
+                check_clause: clause.into(),
+                ..default()
+            })
+        };
+
+        let source = database(vec![PostgresTable {
+            name: "people".to_string(),
+            columns: vec![column("name", "text"), column("email", "text")],
+            indices: vec![index("people_idx", "email")],
+            constraints: vec![check("(name <> ''::text)")],
+            ..default()
+        }]);
+        let target = database(vec![PostgresTable {
+            name: "people".to_string(),
+            columns: vec![column("name", "text"), column("email", "text")],
+            indices: vec![index("people_idx", "name"), index("old_idx", "name")],
+            constraints: vec![check("(name IS NOT NULL)")],
+            ..default()
+        }]);
+
+        let diff = diff(&source, &target);
+
+        assert_eq!(
+            diff.to_migration_sql(&IdentifierQuoter::empty()),
+            "alter table public.people drop constraint name_not_empty;
+drop index public.people_idx;
+drop index public.old_idx;
+create index people_idx on public.people using btree (email);
+alter table public.people add constraint name_not_empty check (name <> ''::text);"
+        );
+    }
+
+    #[test]
+    fn replaces_functions_with_changed_bodies() {
+        let function = |body: &str| PostgresFunction {
+            function_name: "answer".to_string(),
+            language: "sql".to_string(),
+            sql_body: body.into(),
+            result: Some("integer".to_string()),
+            ..default()
+        };
+        let with_function = |function: PostgresFunction| PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                functions: vec![function],
+                ..default()
+            }],
+            ..default()
+        };
+
+        let source = with_function(function("select 42"));
+        let target = with_function(function("select 41"));
+
+        let diff = diff(&source, &target);
+
+        assert!(matches!(
+            diff.changes[..],
+            [SchemaChange::ChangedFunction { .. }]
+        ));
+        assert!(diff
+            .to_migration_sql(&IdentifierQuoter::empty())
+            .starts_with("create or replace function public.answer ()"));
+    }
+
+    #[pg_test(arg(postgres = 16), arg(postgres = 16))]
+    async fn migrates_the_target_to_the_source(source: &TestHelper, target: &TestHelper) {
+        //language=postgresql
+        source
+            .execute_not_query(
+                r#"
+            create schema reporting;
+            create table customers(
+                id bigint primary key,
+                name text not null default 'unknown',
+                email text,
+                constraint name_not_empty check (name <> '')
+            );
+            create unique index customers_email_idx on customers(email);
+            create table orders(
+                id int primary key,
+                customer_id bigint references customers(id)
+            );
+            create function order_count() returns bigint language sql as $$ select count(*) from orders $$;
+            create view reporting.totals as select count(*) as orders from orders;
+            "#,
+            )
+            .await;
+
+        //language=postgresql
+        target
+            .execute_not_query(
+                r#"
+            create table customers(
+                id int primary key,
+                name text,
+                age int
+            );
+            create index customers_email_idx on customers(name);
+            create table legacy(id int);
+            create function order_count() returns bigint language sql as $$ select 0::bigint $$;
+            "#,
+            )
+            .await;
+
+        let source_definition = introspect_schema(source).await;
+        let target_definition = introspect_schema(target).await;
+        let identifier_quoter = IdentifierQuoter::empty();
+
+        let sql = diff(&source_definition, &target_definition).to_migration_sql(&identifier_quoter);
+        assert!(sql.contains("-- WARNING: Changing the type of public.customers.id"));
+
+        target.execute_not_query(&sql).await;
+
+        let migrated_definition = introspect_schema(target).await;
+        let remaining = diff(&source_definition, &migrated_definition);
+        assert!(
+            remaining.is_empty(),
+            "Expected no changes after migrating, got {:?}",
+            remaining.changes
+        );
+    }
+}
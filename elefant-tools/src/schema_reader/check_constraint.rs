@@ -9,6 +9,8 @@ pub struct CheckConstraintResult {
     pub constraint_name: String,
     pub check_clause: String,
     pub comment: Option<String>,
+    pub no_inherit: bool,
+    pub not_valid: bool,
 }
 
 impl FromRow for CheckConstraintResult {
@@ -19,6 +21,8 @@ impl FromRow for CheckConstraintResult {
             constraint_name: row.try_get(2)?,
             check_clause: row.try_get(3)?,
             comment: row.try_get(4)?,
+            no_inherit: row.try_get(5)?,
+            not_valid: row.try_get(6)?,
         })
     }
 }
@@ -27,12 +31,15 @@ impl FromRow for CheckConstraintResult {
 define_working_query!(
     get_check_constraints,
     CheckConstraintResult,
+    schema_column = "table_schema",
     r#"
 select ns.nspname                                     as table_schema,
        cl.relname                                     as table_name,
        ct.conname                                     as constraint_name,
-       substring(pg_get_constraintdef(ct.oid) from 7) as constraint_def,
-       des.description
+       regexp_replace(substring(pg_get_constraintdef(ct.oid) from 7), '( NO INHERIT)?( NOT VALID)?$', '') as constraint_def,
+       des.description,
+       ct.connoinherit                                as no_inherit,
+       not ct.convalidated                            as not_valid
 from pg_constraint ct
          join pg_class cl on cl.oid = ct.conrelid
          join pg_namespace ns on ns.oid = cl.relnamespace
@@ -0,0 +1,110 @@
+use crate::postgres_client_wrapper::FromRow;
+use crate::schema_reader::define_working_query;
+use tokio_postgres::Row;
+
+pub struct CompositeTypeResult {
+    pub schema_name: String,
+    pub name: String,
+    pub comment: Option<String>,
+    pub oid: i64,
+    pub depends_on: Option<Vec<i64>>,
+}
+
+impl FromRow for CompositeTypeResult {
+    fn from_row(row: Row) -> crate::Result<Self> {
+        Ok(Self {
+            schema_name: row.try_get(0)?,
+            name: row.try_get(1)?,
+            comment: row.try_get(2)?,
+            oid: row.try_get(3)?,
+            depends_on: row.try_get(4)?,
+        })
+    }
+}
+
+pub struct CompositeTypeAttributeResult {
+    pub schema_name: String,
+    pub type_name: String,
+    pub attribute_name: String,
+    pub ordinal_position: i16,
+    pub data_type: String,
+    pub array_dimensions: i32,
+    pub data_type_length: Option<i32>,
+}
+
+impl FromRow for CompositeTypeAttributeResult {
+    fn from_row(row: Row) -> crate::Result<Self> {
+        Ok(Self {
+            schema_name: row.try_get(0)?,
+            type_name: row.try_get(1)?,
+            attribute_name: row.try_get(2)?,
+            ordinal_position: row.try_get(3)?,
+            data_type: row.try_get(4)?,
+            array_dimensions: match row.try_get(5) {
+                Ok(d) => d,
+                Err(_) => row.try_get::<_, i16>(5)? as i32,
+            },
+            data_type_length: row.try_get(6)?,
+        })
+    }
+}
+
+//language=postgresql
+define_working_query!(
+    get_composite_types,
+    CompositeTypeResult,
+    schema_column = "nspname",
+    r#"
+select ns.nspname,
+       t.typname,
+       d.description,
+       t.oid::int8,
+       (select array_agg(dep.refobjid::int8)
+        from pg_depend dep
+        where dep.classid = 'pg_class'::regclass
+          and dep.objid = cl.oid
+          and dep.deptype <> 'e'
+          and dep.refobjid > 16384
+          and dep.refobjid <> t.oid) as depends_on
+from pg_type t
+         join pg_class cl on cl.oid = t.typrelid
+         join pg_namespace ns on ns.oid = t.typnamespace
+         left join pg_description d on d.objoid = t.oid and d.classoid = 'pg_type'::regclass
+         left join pg_depend dep on dep.objid = ns.oid
+where t.typtype = 'c'
+  and cl.relkind = 'c'
+  and t.oid > 16384
+  and (dep.objid is null or dep.deptype <> 'e')
+  and not exists (select 1 from pg_depend ext_dep where ext_dep.objid = t.oid and ext_dep.deptype = 'e')
+  and has_type_privilege(t.oid, 'USAGE')
+order by ns.nspname, t.typname;
+"#
+);
+
+//language=postgresql
+define_working_query!(
+    get_composite_type_attributes,
+    CompositeTypeAttributeResult,
+    schema_column = "nspname",
+    r#"
+select ns.nspname,
+       t.typname,
+       attr.attname,
+       attr.attnum,
+       coalesce(non_array_type.typname, attribute_type.typname),
+       attr.attndims,
+       information_schema._pg_char_max_length(coalesce(non_array_type.oid, attribute_type.oid), attr.atttypmod)
+from pg_type t
+         join pg_class cl on cl.oid = t.typrelid
+         join pg_namespace ns on ns.oid = t.typnamespace
+         join pg_attribute attr on attr.attrelid = cl.oid
+         join pg_type attribute_type on attribute_type.oid = attr.atttypid
+         left join pg_type non_array_type on non_array_type.oid = attribute_type.typelem and non_array_type.typarray = attribute_type.oid
+where t.typtype = 'c'
+  and cl.relkind = 'c'
+  and t.oid > 16384
+  and attr.attnum > 0
+  and not attr.attisdropped
+order by ns.nspname, t.typname, attr.attnum;
+"#
+);
@@ -38,6 +38,7 @@ impl FromRow for DomainResult {
 define_working_query!(
     get_domains,
     DomainResult,
+    schema_column = "schema_name",
     r#"
 select nsp.nspname                                     as schema_name,
        typ.typname                                     as domain_name,
@@ -7,6 +7,7 @@ pub struct EnumResult {
     pub name: String,
     pub comment: Option<String>,
     pub values: Vec<String>,
+    pub oid: i64,
 }
 
 impl FromRow for EnumResult {
@@ -16,6 +17,7 @@ impl FromRow for EnumResult {
             name: row.try_get(1)?,
             comment: row.try_get(2)?,
             values: row.try_get(3)?,
+            oid: row.try_get(4)?,
         })
     }
 }
@@ -24,9 +26,10 @@ impl FromRow for EnumResult {
 define_working_query!(
     get_enums,
     EnumResult,
+    schema_column = "nspname",
     r#"
-select enums.nspname, enums.typname, max(enums.description) as description, array_agg(enums.enumlabel)  from (
-select ns.nspname, t.typname, e.enumlabel, d.description
+select enums.nspname, enums.typname, max(enums.description) as description, array_agg(enums.enumlabel), enums.oid  from (
+select ns.nspname, t.typname, e.enumlabel, d.description, t.oid::int8
 from pg_enum e
 join pg_type t on e.enumtypid = t.oid
 join pg_namespace ns on t.typnamespace = ns.oid
@@ -36,6 +39,6 @@ where (dep.objid is null or dep.deptype <> 'e' )
   and has_type_privilege(t.oid, 'USAGE')
 order by ns.nspname, t.typname, e.enumsortorder
 ) as enums
-group by enums.nspname, enums.typname;
+group by enums.nspname, enums.typname, enums.oid;
 "#
 );
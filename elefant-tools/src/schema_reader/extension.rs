@@ -7,6 +7,7 @@ pub struct ExtensionResult {
     pub extension_schema_name: String,
     pub extension_version: String,
     pub extension_relocatable: bool,
+    pub oid: i64,
 }
 
 impl FromRow for ExtensionResult {
@@ -16,6 +17,7 @@ impl FromRow for ExtensionResult {
             extension_schema_name: row.try_get(1)?,
             extension_version: row.try_get(2)?,
             extension_relocatable: row.try_get(3)?,
+            oid: row.try_get(4)?,
         })
     }
 }
@@ -28,7 +30,8 @@ define_working_query!(
 select ext.extname        as extension_name,
        ns.nspname   as extension_schema_name,
        ext.extversion     as extension_version,
-       ext.extrelocatable as extension_relocatable
+       ext.extrelocatable as extension_relocatable,
+       ext.oid::int8      as oid
 from pg_catalog.pg_extension ext
          join pg_namespace ns on ext.extnamespace = ns.oid
         where ext.oid > 16384;
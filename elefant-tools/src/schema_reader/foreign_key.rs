@@ -35,6 +35,7 @@ impl FromRow for ForeignKeyResult {
 define_working_query!(
     get_foreign_keys,
     ForeignKeyResult,
+    schema_column = "source_schema_name",
     r#"
 select con.conname              as constraint_name,
        con_ns.nspname           as constraint_schema_name,
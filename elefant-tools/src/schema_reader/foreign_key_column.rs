@@ -1,4 +1,5 @@
 use crate::postgres_client_wrapper::FromRow;
+use crate::schema_reader::versioned_query::VersionedQuery;
 use crate::schema_reader::SchemaReader;
 use tokio_postgres::Row;
 use tracing::instrument;
@@ -27,14 +28,11 @@ impl FromRow for ForeignKeyColumnResult {
     }
 }
 
-impl SchemaReader<'_> {
-    #[instrument(skip_all)]
-    pub(in crate::schema_reader) async fn get_foreign_key_columns(
-        &self,
-    ) -> crate::Result<Vec<ForeignKeyColumnResult>> {
+const FOREIGN_KEY_COLUMNS_QUERY: VersionedQuery = VersionedQuery::new(&[
+    (
+        150,
         //language=postgresql
-        let query = if self.connection.version() >= 150 {
-            r#"
+        r#"
 select con.conname       as constraint_name,
        con_ns.nspname    as constraint_schema_name,
        tab.relname       as source_table_name,
@@ -55,9 +53,12 @@ from pg_constraint con
 where con.contype = 'f'
 and (dep.objid is null or dep.deptype <> 'e' )
 order by constraint_schema_name, source_table_name, constraint_name, source_table_attr.attnum;
-"#
-        } else {
-            r#"
+"#,
+    ),
+    (
+        0,
+        //language=postgresql
+        r#"
 select con.conname       as constraint_name,
        con_ns.nspname    as constraint_schema_name,
        tab.relname       as source_table_name,
@@ -76,9 +77,16 @@ from pg_constraint con
                    on target_table_attr.attrelid = con.confrelid and target_table_attr.attnum = cols.confkey
 where con.contype = 'f'
 order by constraint_schema_name, source_table_name, constraint_name, source_table_attr.attnum;
-"#
-        };
+"#,
+    ),
+]);
 
-        self.connection.get_results(query).await
+impl SchemaReader<'_> {
+    #[instrument(skip_all)]
+    pub(in crate::schema_reader) async fn get_foreign_key_columns(
+        &self,
+    ) -> crate::Result<Vec<ForeignKeyColumnResult>> {
+        self.get_versioned_results(&FOREIGN_KEY_COLUMNS_QUERY, "source_schema_name")
+            .await
     }
 }
@@ -1,4 +1,5 @@
 use crate::postgres_client_wrapper::{FromRow, RowEnumExt};
+use crate::schema_reader::versioned_query::VersionedQuery;
 use crate::schema_reader::SchemaReader;
 use crate::{FinalModify, FunctionKind, Parallel, Volatility};
 use tokio_postgres::Row;
@@ -92,14 +93,11 @@ impl FromRow for FunctionResult {
     }
 }
 
-impl SchemaReader<'_> {
-    #[instrument(skip_all)]
-    pub(in crate::schema_reader) async fn get_functions(
-        &self,
-    ) -> crate::Result<Vec<FunctionResult>> {
+const FUNCTIONS_QUERY: VersionedQuery = VersionedQuery::new(&[
+    (
+        140,
         //language=postgresql
-        let query = if self.connection.version() >= 140 {
-            r#"
+        r#"
 select ns.nspname as schema_name,
     proc.proname as function_name,
        pl.lanname as language_name,
@@ -138,7 +136,12 @@ select ns.nspname as schema_name,
        agg.agginitval,
        agg.aggminitval,
        proc.oid::int8,
-       (select array_agg(refobjid::int8) from pg_depend dep where proc.oid = dep.objid and dep.deptype <> 'e' and dep.refobjid > 16384) as depends_on
+       -- Objects belonging to an extension, such as its types, functions and operators, are not
+       -- introspected themselves, so the dependency is on the extension instead.
+       (select array_agg(coalesce(ext_dep.refobjid, dep.refobjid)::int8)
+        from pg_depend dep
+                 left join pg_depend ext_dep on ext_dep.classid = dep.refclassid and ext_dep.objid = dep.refobjid and ext_dep.deptype = 'e'
+        where proc.oid = dep.objid and dep.deptype <> 'e' and dep.refobjid > 16384) as depends_on
 from pg_proc proc
          join pg_namespace ns on proc.pronamespace = ns.oid
          join pg_language pl on proc.prolang = pl.oid
@@ -149,12 +152,15 @@ from pg_proc proc
          left join pg_extension ext on dep.refobjid = ext.oid
          left join pg_description des on proc.oid = des.objoid
          left join pg_aggregate agg on proc.oid = agg.aggfnoid
-where ns.nspname = 'public' and ext.extname is null
+where (ns.oid > 16384 or ns.nspname = 'public') and ext.extname is null
       and has_function_privilege(proc.oid, 'EXECUTE')
 order by ns.nspname, proc.proname;
-"#
-        } else {
-            r#"
+"#,
+    ),
+    (
+        0,
+        //language=postgresql
+        r#"
 select ns.nspname as schema_name,
     proc.proname as function_name,
        pl.lanname as language_name,
@@ -193,7 +199,12 @@ select ns.nspname as schema_name,
        agg.agginitval,
        agg.aggminitval,
        proc.oid::int8,
-       (select array_agg(refobjid::int8) from pg_depend dep where proc.oid = dep.objid and dep.deptype <> 'e' and dep.refobjid > 16384 and dep.objid <> dep.refobjid) as depends_on
+       -- Objects belonging to an extension, such as its types, functions and operators, are not
+       -- introspected themselves, so the dependency is on the extension instead.
+       (select array_agg(coalesce(ext_dep.refobjid, dep.refobjid)::int8)
+        from pg_depend dep
+                 left join pg_depend ext_dep on ext_dep.classid = dep.refclassid and ext_dep.objid = dep.refobjid and ext_dep.deptype = 'e'
+        where proc.oid = dep.objid and dep.deptype <> 'e' and dep.refobjid > 16384 and dep.objid <> dep.refobjid) as depends_on
 from pg_proc proc
          join pg_namespace ns on proc.pronamespace = ns.oid
          join pg_language pl on proc.prolang = pl.oid
@@ -206,11 +217,19 @@ from pg_proc proc
          left join pg_aggregate agg on proc.oid = agg.aggfnoid
          left join pg_type agg_type on agg.aggtranstype = agg_type.oid
          left join pg_type m_agg_type on agg.aggmtranstype = m_agg_type.oid
-where ns.nspname = 'public' and ext.extname is null
+where (ns.oid > 16384 or ns.nspname = 'public') and ext.extname is null
       and has_function_privilege(proc.oid, 'EXECUTE')
 order by ns.nspname, proc.proname;
-"#
-        };
-        self.connection.get_results(query).await
+"#,
+    ),
+]);
+
+impl SchemaReader<'_> {
+    #[instrument(skip_all)]
+    pub(in crate::schema_reader) async fn get_functions(
+        &self,
+    ) -> crate::Result<Vec<FunctionResult>> {
+        self.get_versioned_results(&FUNCTIONS_QUERY, "schema_name")
+            .await
     }
 }
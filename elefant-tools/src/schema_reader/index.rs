@@ -1,4 +1,5 @@
 use crate::postgres_client_wrapper::FromRow;
+use crate::schema_reader::versioned_query::VersionedQuery;
 use crate::schema_reader::SchemaReader;
 use tokio_postgres::Row;
 use tracing::instrument;
@@ -15,6 +16,10 @@ pub struct IndexResult {
     pub nulls_not_distinct: bool,
     pub comment: Option<String>,
     pub storage_parameters: Option<Vec<String>>,
+    pub depends_on: Option<Vec<i64>>,
+    pub tablespace: Option<String>,
+    pub not_valid: bool,
+    pub parent_index: Option<String>,
 }
 
 impl FromRow for IndexResult {
@@ -31,16 +36,19 @@ impl FromRow for IndexResult {
             nulls_not_distinct: row.try_get(8)?,
             comment: row.try_get(9)?,
             storage_parameters: row.try_get(10)?,
+            depends_on: row.try_get(11)?,
+            tablespace: row.try_get(12)?,
+            not_valid: row.try_get(13)?,
+            parent_index: row.try_get(14)?,
         })
     }
 }
 
-impl SchemaReader<'_> {
-    #[instrument(skip_all)]
-    pub(in crate::schema_reader) async fn get_indices(&self) -> crate::Result<Vec<IndexResult>> {
-        let query = if self.connection.version() >= 150 {
-            //language=postgresql
-            r#"
+const INDICES_QUERY: VersionedQuery = VersionedQuery::new(&[
+    (
+        150,
+        //language=postgresql
+        r#"
 select n.nspname           as table_schema,
        table_class.relname as table_name,
        index_class.relname as index_name,
@@ -51,7 +59,11 @@ select n.nspname           as table_schema,
        i.indisprimary      as is_primary_key,
        i.indnullsnotdistinct as nulls_not_distinct,
        d.description       as comment,
-       index_class.reloptions as table_storage_parameters
+       index_class.reloptions as table_storage_parameters,
+       (select array_agg(dep.refobjid::int8) from pg_depend dep where dep.objid = i.indexrelid and dep.classid = 'pg_class'::regclass and dep.refclassid = 'pg_proc'::regclass and dep.refobjid > 16384) as depends_on,
+       ts.spcname          as tablespace,
+       not i.indisvalid    as not_valid,
+       (select parent_class.relname from pg_inherits inh join pg_class parent_class on parent_class.oid = inh.inhparent where inh.inhrelid = i.indexrelid) as parent_index
 from pg_index i
          join pg_class table_class on table_class.oid = i.indrelid
          join pg_class index_class on index_class.oid = i.indexrelid
@@ -61,13 +73,15 @@ from pg_index i
          left join pg_description d on d.objoid = i.indexrelid
          left join pg_depend dep on dep.objid = n.oid
 where table_class.oid > 16384
-and table_class.relkind = 'r'
+and table_class.relkind in ('r', 'p', 'm')
 and (dep.objid is null or dep.deptype <> 'e' )
 order by table_schema, table_name, index_name;
-"#
-        } else {
-            //language=postgresql
-            r#"
+"#,
+    ),
+    (
+        0,
+        //language=postgresql
+        r#"
 select n.nspname           as table_schema,
        table_class.relname as table_name,
        index_class.relname as index_name,
@@ -78,7 +92,11 @@ select n.nspname           as table_schema,
        i.indisprimary      as is_primary_key,
        false as nulls_not_distinct,
        d.description       as comment,
-       index_class.reloptions as table_storage_parameters
+       index_class.reloptions as table_storage_parameters,
+       (select array_agg(dep.refobjid::int8) from pg_depend dep where dep.objid = i.indexrelid and dep.classid = 'pg_class'::regclass and dep.refclassid = 'pg_proc'::regclass and dep.refobjid > 16384) as depends_on,
+       ts.spcname          as tablespace,
+       not i.indisvalid    as not_valid,
+       (select parent_class.relname from pg_inherits inh join pg_class parent_class on parent_class.oid = inh.inhparent where inh.inhrelid = i.indexrelid) as parent_index
 from pg_index i
          join pg_class table_class on table_class.oid = i.indrelid
          join pg_class index_class on index_class.oid = i.indexrelid
@@ -88,12 +106,17 @@ from pg_index i
          left join pg_description d on d.objoid = i.indexrelid
          left join pg_depend dep on dep.objid = n.oid
 where table_class.oid > 16384
-and table_class.relkind = 'r'
+and table_class.relkind in ('r', 'p', 'm')
 and (dep.objid is null or dep.deptype <> 'e' )
 order by table_schema, table_name, index_name;
-"#
-        };
+"#,
+    ),
+]);
 
-        self.connection.get_results(query).await
+impl SchemaReader<'_> {
+    #[instrument(skip_all)]
+    pub(in crate::schema_reader) async fn get_indices(&self) -> crate::Result<Vec<IndexResult>> {
+        self.get_versioned_results(&INDICES_QUERY, "table_schema")
+            .await
     }
 }
@@ -11,6 +11,8 @@ pub struct IndexColumnResult {
     pub is_desc: Option<bool>,
     pub nulls_first: Option<bool>,
     pub ordinal_position: i32,
+    pub operator_class: Option<String>,
+    pub collation: Option<String>,
 }
 
 impl FromRow for IndexColumnResult {
@@ -24,6 +26,8 @@ impl FromRow for IndexColumnResult {
             is_desc: row.try_get(5)?,
             nulls_first: row.try_get(6)?,
             ordinal_position: row.try_get(7)?,
+            operator_class: row.try_get(8)?,
+            collation: row.try_get(9)?,
         })
     }
 }
@@ -32,6 +36,7 @@ impl FromRow for IndexColumnResult {
 define_working_query!(
     get_index_columns,
     IndexColumnResult,
+    schema_column = "table_schema",
     r#"
 select n.nspname                                              as table_schema,
       table_class.relname                                    as table_name,
@@ -40,18 +45,40 @@ select n.nspname                                              as table_schema,
       pg_catalog.pg_get_indexdef(a.attrelid, a.attnum, true) as indexdef,
       i.indoption[a.attnum - 1] & 1 <> 0                     as is_desc,
       i.indoption[a.attnum - 1] & 2 <> 0                     as nulls_first,
-      a.attnum::int                                               as ordinal_position
+      a.attnum::int                                               as ordinal_position,
+      case
+          when a.attnum <= i.indnkeyatts and not opc.opcdefault then
+              case
+                  when opc_ns.nspname = 'pg_catalog' then quote_ident(opc.opcname)
+                  else quote_ident(opc_ns.nspname) || '.' || quote_ident(opc.opcname)
+                  end
+          end                                                 as operator_class,
+      case
+          when a.attnum <= i.indnkeyatts
+              and i.indcollation[a.attnum - 1] <> 0
+              and i.indcollation[a.attnum - 1] <> coalesce(table_attribute.attcollation, attribute_type.typcollation) then
+              case
+                  when coll_ns.nspname = 'pg_catalog' then quote_ident(coll.collname)
+                  else quote_ident(coll_ns.nspname) || '.' || quote_ident(coll.collname)
+                  end
+          end                                                 as collation
 from pg_index i
         join pg_class table_class on table_class.oid = i.indrelid
         join pg_class index_class on index_class.oid = i.indexrelid
         left join pg_namespace n on n.oid = table_class.relnamespace
         left join pg_tablespace ts on ts.oid = index_class.reltablespace
         join pg_catalog.pg_attribute a on a.attrelid = index_class.oid
+        join pg_type attribute_type on attribute_type.oid = a.atttypid
+        left join pg_attribute table_attribute on table_attribute.attrelid = i.indrelid and table_attribute.attnum = i.indkey[a.attnum - 1]
+        left join pg_opclass opc on opc.oid = i.indclass[a.attnum - 1]
+        left join pg_namespace opc_ns on opc_ns.oid = opc.opcnamespace
+        left join pg_collation coll on coll.oid = i.indcollation[a.attnum - 1]
+        left join pg_namespace coll_ns on coll_ns.oid = coll.collnamespace
          left join pg_depend dep on dep.objid = n.oid
 where a.attnum > 0
  and not a.attisdropped
  and table_class.oid > 16384
-and table_class.relkind = 'r'
+and table_class.relkind in ('r', 'p', 'm')
   and (dep.objid is null or dep.deptype <> 'e' )
 order by table_schema, table_name, index_name, ordinal_position
 "#
@@ -2,29 +2,34 @@ use crate::models::PostgresSequence;
 use crate::models::*;
 use crate::object_id::ObjectIdGenerator;
 use crate::postgres_client_wrapper::PostgresClientWrapper;
+use crate::quoting::quote_value_string;
 use crate::schema_reader::check_constraint::CheckConstraintResult;
 use crate::schema_reader::foreign_key::ForeignKeyResult;
 use crate::schema_reader::foreign_key_column::ForeignKeyColumnResult;
 use crate::schema_reader::index::IndexResult;
 use crate::schema_reader::index_column::IndexColumnResult;
+use crate::schema_reader::privileges::{PrivilegeObjectType, PrivilegeResult};
 use crate::schema_reader::table::TablesResult;
 use crate::schema_reader::table_column::TableColumnsResult;
-use crate::schema_reader::timescale_continuous_aggregate::ContinuousAggregateResult;
-use crate::schema_reader::timescale_hypertable::HypertableResult;
-use crate::schema_reader::timescale_hypertable_dimension::TimescaleHypertableDimensionResult;
+use crate::schema_reader::timescale::TimescaleDefinitions;
 use crate::schema_reader::unique_constraint::UniqueConstraintResult;
 use crate::schema_reader::view::ViewResult;
 use crate::schema_reader::view_column::ViewColumnResult;
-use crate::TableTypeDetails::TimescaleHypertable;
 use crate::{ElefantToolsError, ObjectId, Result};
 use futures::try_join;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use ordered_float::NotNan;
 use tracing::instrument;
 
+pub use summary::SchemaSummary;
+pub(crate) use summary::human_readable_bytes;
+
 mod check_constraint;
+mod composite_type;
 mod domain;
 mod enumeration;
 mod extension;
@@ -33,33 +38,77 @@ mod foreign_key_column;
 mod function;
 mod index;
 mod index_column;
+mod policy;
+mod privileges;
 mod schema;
 mod sequence;
+mod summary;
 mod table;
 mod table_column;
 #[cfg(test)]
 pub mod tests;
+#[cfg(feature = "timescale")]
+mod timescale;
+#[cfg(feature = "timescale")]
 mod timescale_continuous_aggregate;
+#[cfg(not(feature = "timescale"))]
+mod timescale_disabled;
+#[cfg(not(feature = "timescale"))]
+use timescale_disabled as timescale;
+#[cfg(feature = "timescale")]
 mod timescale_hypertable;
+#[cfg(feature = "timescale")]
 mod timescale_hypertable_dimension;
+#[cfg(feature = "timescale")]
 mod timescale_job;
 mod trigger;
 mod unique_constraint;
+mod versioned_query;
 mod view;
 mod view_column;
 
+/// Reads the structure of a database.
 pub struct SchemaReader<'a> {
     connection: &'a PostgresClientWrapper,
+    object_id_generator: Option<Arc<ObjectIdGenerator>>,
+    schema: Option<String>,
 }
 
 impl SchemaReader<'_> {
-    pub fn new(connection: &PostgresClientWrapper) -> SchemaReader {
-        SchemaReader { connection }
+    pub fn new(connection: &PostgresClientWrapper) -> SchemaReader<'_> {
+        SchemaReader {
+            connection,
+            object_id_generator: None,
+            schema: None,
+        }
+    }
+
+    /// Creates a reader that only introspects the objects in the specified schema, for when
+    /// only a small part of a large database is needed. Extensions are still all introspected.
+    pub fn for_schema<'a>(connection: &'a PostgresClientWrapper, schema: &str) -> SchemaReader<'a> {
+        SchemaReader {
+            connection,
+            object_id_generator: None,
+            schema: Some(schema.to_string()),
+        }
+    }
+
+    /// Creates a reader that takes the [ObjectId]s from the provided generator instead of
+    /// starting from scratch. Introspections sharing a generator never reuse each other's ids.
+    pub fn with_object_id_generator(
+        connection: &PostgresClientWrapper,
+        object_id_generator: Arc<ObjectIdGenerator>,
+    ) -> SchemaReader<'_> {
+        SchemaReader {
+            connection,
+            object_id_generator: Some(object_id_generator),
+            schema: None,
+        }
     }
 
     #[instrument(skip_all)]
     pub async fn introspect_database(&self) -> Result<PostgresDatabase> {
-        let mut object_id_generator = ObjectIdGenerator::new();
+        let object_id_generator = self.object_id_generator.clone().unwrap_or_default();
         let mut object_id_mapping = PgOidToObjectIdMapping::default();
 
         let (
@@ -80,6 +129,10 @@ impl SchemaReader<'_> {
             triggers,
             enums,
             domains,
+            composite_types,
+            composite_type_attributes,
+            policies,
+            privileges,
         ) = try_join!(
             self.get_extensions(),
             self.get_schemas(),
@@ -97,7 +150,11 @@ impl SchemaReader<'_> {
             self.get_functions(),
             self.get_triggers(),
             self.get_enums(),
-            self.get_domains()
+            self.get_domains(),
+            self.get_composite_types(),
+            self.get_composite_type_attributes(),
+            self.get_policies(),
+            self.get_privileges()
         )?;
 
         let mut extensions = extensions;
@@ -117,17 +174,9 @@ impl SchemaReader<'_> {
             extensions.retain(|e| e.extension_name != "timescaledb_toolkit");
         }
 
-        let (hypertables, hypertable_dimensions, continuous_aggregates, timescale_jobs) =
-            if db.timescale_support.is_enabled {
-                try_join!(
-                    self.get_hypertables(),
-                    self.get_hypertable_dimensions(),
-                    self.get_continuous_aggregates(),
-                    self.get_timescale_jobs()
-                )?
-            } else {
-                (vec![], vec![], vec![], vec![])
-            };
+        let timescale = self
+            .get_timescale_definitions(&db.timescale_support)
+            .await?;
 
         for row in schemas {
             let schema = PostgresSchema {
@@ -155,9 +204,8 @@ impl SchemaReader<'_> {
                 &index_columns,
                 &foreign_keys,
                 &foreign_key_columns,
-                &hypertables,
-                &hypertable_dimensions,
-                &mut object_id_generator,
+                &timescale,
+                &object_id_generator,
             )?;
 
             object_id_mapping.insert(oid, table.object_id);
@@ -168,6 +216,7 @@ impl SchemaReader<'_> {
 
         for sequence in sequences {
             let current_schema = db.get_or_create_schema_mut(&sequence.schema_name);
+            let oid = sequence.oid;
 
             let sequence = PostgresSequence {
                 name: sequence.sequence_name.clone(),
@@ -186,6 +235,8 @@ impl SchemaReader<'_> {
                 object_id: object_id_generator.next(),
             };
 
+            object_id_mapping.insert(oid, sequence.object_id);
+
             current_schema.sequences.push(sequence);
         }
 
@@ -210,8 +261,10 @@ impl SchemaReader<'_> {
             let view = Self::add_view(
                 view,
                 &view_columns,
-                &continuous_aggregates,
-                &mut object_id_generator,
+                &timescale,
+                &indices,
+                &index_columns,
+                &object_id_generator,
             );
 
             object_id_mapping.insert(oid, view.object_id);
@@ -323,6 +376,7 @@ impl SchemaReader<'_> {
                     comment: function.comment.clone(),
                     object_id: object_id_generator.next(),
                     depends_on: vec![],
+                    privileges: vec![],
                 };
 
                 object_id_mapping.insert(oid, function.object_id);
@@ -332,6 +386,7 @@ impl SchemaReader<'_> {
         }
 
         for extension in &extensions {
+            let oid = extension.oid;
             let extension = PostgresExtension {
                 name: extension.extension_name.clone(),
                 schema_name: extension.extension_schema_name.clone(),
@@ -340,21 +395,13 @@ impl SchemaReader<'_> {
                 object_id: object_id_generator.next(),
             };
 
+            object_id_mapping.insert(oid, extension.object_id);
             db.enabled_extensions.push(extension);
         }
 
         for trigger in triggers {
-            if db.timescale_support.is_enabled
-                && hypertables.iter().any(|h| {
-                    h.table_name == trigger.table_name && h.table_schema == trigger.schema_name
-                })
-            {
-                // Skip the trigger if it's a TimescaleDB internal trigger
-                if trigger.name == "ts_insert_blocker"
-                    || trigger.name == "ts_cagg_invalidation_trigger"
-                {
-                    continue;
-                }
+            if timescale.is_internal_trigger(&trigger) {
+                continue;
             }
 
             let current_schema = db.get_or_create_schema_mut(&trigger.schema_name);
@@ -379,6 +426,7 @@ impl SchemaReader<'_> {
 
         for enumeration in enums {
             let current_schema = db.get_or_create_schema_mut(&enumeration.schema_name);
+            let oid = enumeration.oid;
 
             let enumeration = PostgresEnum {
                 name: enumeration.name.clone(),
@@ -387,24 +435,12 @@ impl SchemaReader<'_> {
                 object_id: object_id_generator.next(),
             };
 
+            object_id_mapping.insert(oid, enumeration.object_id);
+
             current_schema.enums.push(enumeration);
         }
 
-        for timescale_job in timescale_jobs {
-            db.timescale_support
-                .user_defined_jobs
-                .push(TimescaleDbUserDefinedJob {
-                    function_name: timescale_job.function_name.clone(),
-                    function_schema: timescale_job.function_schema.clone(),
-                    check_config_name: timescale_job.check_config_name.clone(),
-                    check_config_schema: timescale_job.check_config_schema.clone(),
-                    schedule_interval: timescale_job.schedule_interval,
-                    fixed_schedule: timescale_job.fixed_schedule,
-                    config: timescale_job.config.clone().map(|c| c.into()),
-                    scheduled: timescale_job.scheduled,
-                    object_id: object_id_generator.next(),
-                })
-        }
+        timescale.add_jobs(&mut db, &object_id_generator);
 
         for domain in &domains {
             let current_schema = db.get_or_create_schema_mut(&domain.schema_name);
@@ -437,6 +473,37 @@ impl SchemaReader<'_> {
             current_schema.domains.push(domain);
         }
 
+        for composite_type in &composite_types {
+            let current_schema = db.get_or_create_schema_mut(&composite_type.schema_name);
+
+            let oid = composite_type.oid;
+
+            let composite_type = PostgresCompositeType {
+                name: composite_type.name.clone(),
+                attributes: composite_type_attributes
+                    .iter()
+                    .filter(|a| {
+                        a.schema_name == composite_type.schema_name
+                            && a.type_name == composite_type.name
+                    })
+                    .map(|a| PostgresCompositeTypeAttribute {
+                        name: a.attribute_name.clone(),
+                        ordinal_position: a.ordinal_position as i32,
+                        data_type: a.data_type.clone(),
+                        data_type_length: a.data_type_length,
+                        array_dimensions: a.array_dimensions,
+                    })
+                    .collect(),
+                comment: composite_type.comment.clone(),
+                object_id: object_id_generator.next(),
+                depends_on: vec![],
+            };
+
+            object_id_mapping.insert(oid, composite_type.object_id);
+
+            current_schema.composite_types.push(composite_type);
+        }
+
         for view in &views {
             if let Some(depends_on) = &view.depends_on {
                 let current_schema = db.get_or_create_schema_mut(&view.schema_name);
@@ -455,6 +522,26 @@ impl SchemaReader<'_> {
                     }
                 }
             }
+
+            if let (Some(oids), Some(names)) =
+                (&view.column_dependency_oids, &view.column_dependency_names)
+            {
+                let current_schema = db.get_or_create_schema_mut(&view.schema_name);
+
+                let own_object_id = object_id_mapping.get(view.oid).unwrap(); // SAFE: We have just inserted the oid above
+
+                let this = current_schema
+                    .views
+                    .iter_mut()
+                    .find(|v| v.object_id == own_object_id)
+                    .unwrap(); // SAFE: We have just inserted it above
+
+                for (oid, name) in oids.iter().zip(names) {
+                    if let Some(depends_on) = object_id_mapping.get(*oid) {
+                        this.column_dependencies.push((depends_on, name.clone()));
+                    }
+                }
+            }
         }
 
         for table in &tables {
@@ -474,6 +561,12 @@ impl SchemaReader<'_> {
                         this.depends_on.push(depends_on);
                     }
                 }
+
+                // The oids of the dependencies differ between databases, for example when a
+                // sequence is created after the tables using it, so they are sorted to keep the
+                // dependencies comparable. Several columns can use the same sequence.
+                this.depends_on.sort_by(|a, b| a.partial_cmp(b).unwrap()); // SAFE: Mapped object ids always have a value
+                this.depends_on.dedup();
             }
         }
 
@@ -495,6 +588,11 @@ impl SchemaReader<'_> {
                             this.depends_on.push(depends_on);
                         }
                     }
+
+                    // Several objects of the same extension map to the extension, and the oids
+                    // are not ordered the same way between databases.
+                    this.depends_on.sort_by(|a, b| a.partial_cmp(b).unwrap()); // SAFE: Mapped object ids always have a value
+                    this.depends_on.dedup();
                 } else {
                     let this = current_schema
                         .functions
@@ -507,6 +605,9 @@ impl SchemaReader<'_> {
                             this.depends_on.push(depends_on);
                         }
                     }
+
+                    this.depends_on.sort_by(|a, b| a.partial_cmp(b).unwrap()); // SAFE: Mapped object ids always have a value
+                    this.depends_on.dedup();
                 }
             }
         }
@@ -531,28 +632,179 @@ impl SchemaReader<'_> {
             }
         }
 
+        for composite_type in &composite_types {
+            if let Some(depends_on) = &composite_type.depends_on {
+                let current_schema = db.get_or_create_schema_mut(&composite_type.schema_name);
+
+                let own_object_id = object_id_mapping.get(composite_type.oid).unwrap(); // SAFE: We have just inserted the oid above
+
+                let this = current_schema
+                    .composite_types
+                    .iter_mut()
+                    .find(|v| v.object_id == own_object_id)
+                    .unwrap(); // SAFE: We have just inserted it above
+
+                for oid in depends_on {
+                    if let Some(depends_on) = object_id_mapping.get(*oid) {
+                        this.depends_on.push(depends_on);
+                    }
+                }
+
+                // Several attributes can use the same type.
+                this.depends_on.sort_by(|a, b| a.partial_cmp(b).unwrap()); // SAFE: Mapped object ids always have a value
+                this.depends_on.dedup();
+            }
+        }
+
+        for index in &indices {
+            if let Some(depends_on) = &index.depends_on {
+                let current_schema = db.get_or_create_schema_mut(&index.table_schema);
+
+                if let Some(view) = current_schema
+                    .views
+                    .iter_mut()
+                    .find(|v| v.name == index.table_name)
+                {
+                    if let Some(this) = view.indices.iter_mut().find(|i| i.name == index.index_name)
+                    {
+                        for oid in depends_on {
+                            if let Some(depends_on) = object_id_mapping.get(*oid) {
+                                this.depends_on.push(depends_on);
+                            }
+                        }
+                    }
+                    continue;
+                }
+
+                let Some(table) = current_schema
+                    .tables
+                    .iter_mut()
+                    .find(|t| t.name == index.table_name)
+                else {
+                    continue;
+                };
+
+                let is_timescale_table = table.is_timescale_table();
+
+                let Some(this) = table.indices.iter_mut().find(|i| i.name == index.index_name)
+                else {
+                    continue;
+                };
+
+                for oid in depends_on {
+                    if let Some(depends_on) = object_id_mapping.get(*oid) {
+                        this.depends_on.push(depends_on);
+                    }
+                }
+
+                // Indices on hypertables are created together with the table itself,
+                // so the table has to wait for the functions used by the index.
+                if is_timescale_table {
+                    for depends_on in this.depends_on.clone() {
+                        if !table.depends_on.contains(&depends_on) {
+                            table.depends_on.push(depends_on);
+                        }
+                    }
+                }
+            }
+        }
+
+        for policy in policies {
+            let current_schema = db.get_or_create_schema_mut(&policy.schema_name);
+
+            let mut depends_on = policy
+                .depends_on
+                .iter()
+                .flatten()
+                .filter_map(|oid| object_id_mapping.get(*oid))
+                .collect_vec();
+            depends_on.sort_by(|a, b| a.partial_cmp(b).unwrap()); // SAFE: Mapped object ids always have a value
+            depends_on.dedup();
+
+            current_schema
+                .policies
+                .push(PostgresRowLevelSecurityPolicy {
+                    name: policy.name,
+                    table_name: policy.table_name,
+                    command: policy.command,
+                    permissive: policy.permissive,
+                    roles: policy.roles,
+                    using_expression: policy.using_expression,
+                    check_expression: policy.check_expression,
+                    object_id: object_id_generator.next(),
+                    depends_on,
+                });
+        }
+
+        Self::add_privileges(&mut db, privileges, &object_id_mapping);
+
         Ok(db)
     }
 
+    /// Adds the privileges to the objects they are on. Privileges on objects that are not
+    /// introspected, or that cannot have privileges in the model, such as aggregate functions,
+    /// are ignored.
+    fn add_privileges(
+        db: &mut PostgresDatabase,
+        privileges: Vec<PrivilegeResult>,
+        object_id_mapping: &PgOidToObjectIdMapping,
+    ) {
+        for row in privileges {
+            let Some(schema) = db.schemas.iter_mut().find(|s| s.name == row.schema_name) else {
+                continue;
+            };
+
+            let object_id = object_id_mapping.get(row.oid);
+            let target = match (row.object_type, object_id) {
+                (PrivilegeObjectType::Schema, _) => Some(&mut schema.privileges),
+                (PrivilegeObjectType::Relation, Some(object_id)) => schema
+                    .tables
+                    .iter_mut()
+                    .find(|t| t.object_id == object_id)
+                    .map(|t| &mut t.privileges)
+                    .or_else(|| {
+                        schema
+                            .views
+                            .iter_mut()
+                            .find(|v| v.object_id == object_id)
+                            .map(|v| &mut v.privileges)
+                    }),
+                (PrivilegeObjectType::Function, Some(object_id)) => schema
+                    .functions
+                    .iter_mut()
+                    .find(|f| f.object_id == object_id)
+                    .map(|f| &mut f.privileges),
+                (_, None) => None,
+            };
+
+            if let Some(target) = target {
+                target.push(PostgresPrivilege {
+                    grantee: row.grantee,
+                    privilege: row.privilege,
+                    with_grant_option: row.with_grant_option,
+                    revoked: row.revoked,
+                });
+            }
+        }
+    }
+
     #[instrument(skip_all)]
     fn add_view(
         view: &ViewResult,
         view_columns: &[ViewColumnResult],
-        continuous_aggregates: &[ContinuousAggregateResult],
-        object_id_generator: &mut ObjectIdGenerator,
+        timescale: &TimescaleDefinitions,
+        indices: &[IndexResult],
+        index_columns: &[IndexColumnResult],
+        object_id_generator: &ObjectIdGenerator,
     ) -> PostgresView {
-        let continuous_aggregate = continuous_aggregates
-            .iter()
-            .find(|c| c.view_name == view.view_name && c.view_schema == view.schema_name);
+        let continuous_aggregate = timescale.get_continuous_aggregate(view);
         PostgresView {
             name: view.view_name.clone(),
-            definition: if let Some(ca) = &continuous_aggregate {
-                &ca.view_definition
-            } else {
-                &view.definition
-            }
-            .clone()
-            .into(),
+            definition: continuous_aggregate
+                .as_ref()
+                .map_or(&view.definition, |(definition, _)| definition)
+                .clone()
+                .into(),
             columns: view_columns
                 .iter()
                 .filter(|c| c.view_name == view.view_name && c.schema_name == view.schema_name)
@@ -563,92 +815,19 @@ impl SchemaReader<'_> {
                 .collect(),
             comment: view.comment.clone(),
             is_materialized: view.is_materialized || continuous_aggregate.is_some(),
-            view_options: if let Some(ca) = continuous_aggregate {
-                let refresh = if let (Some(refresh), Some(start), Some(end)) = (
-                    ca.refresh_interval,
-                    ca.refresh_start_offset,
-                    ca.refresh_end_offset,
-                ) {
-                    Some(TimescaleContinuousAggregateRefreshOptions {
-                        interval: refresh,
-                        start_offset: start,
-                        end_offset: end,
-                    })
-                } else {
-                    None
-                };
-
-                let compression = if let (false, None, None, None, None, None) = (
-                    ca.compression_enabled,
-                    ca.compress_after,
-                    ca.compress_job_interval,
-                    &ca.compress_segment_by,
-                    &ca.compress_order_by,
-                    &ca.compress_chunk_time_interval,
-                ) {
-                    None
-                } else {
-                    Some(HypertableCompression {
-                        enabled: ca.compression_enabled,
-                        compression_schedule_interval: ca.compress_job_interval,
-                        chunk_time_interval: ca.compress_chunk_time_interval,
-                        compress_after: ca.compress_after,
-                        order_by_columns: Self::get_hypertable_compression_order_by_columns(
-                            &ca.compress_order_by,
-                            &ca.compress_order_by_desc,
-                            &ca.compress_order_by_nulls_first,
-                        ),
-                        segment_by_columns: ca.compress_segment_by.clone(),
-                    })
-                };
-
-                let retention = if let (Some(schedule_interval), Some(drop_after)) =
-                    (ca.retention_schedule_interval, ca.retention_drop_after)
-                {
-                    Some(HypertableRetention {
-                        schedule_interval,
-                        drop_after,
-                    })
-                } else {
-                    None
-                };
-
-                ViewOptions::TimescaleContinuousAggregate {
-                    refresh,
-                    compression,
-                    retention,
-                }
-            } else {
-                ViewOptions::None
-            },
+            not_populated: view.not_populated,
+            view_options: continuous_aggregate.map_or(ViewOptions::None, |(_, options)| options),
             object_id: object_id_generator.next(),
             depends_on: vec![],
-        }
-    }
-
-    fn get_hypertable_compression_order_by_columns(
-        compress_order_by: &Option<Vec<String>>,
-        compress_order_by_desc: &Option<Vec<bool>>,
-        compress_order_by_nulls_first: &Option<Vec<bool>>,
-    ) -> Option<Vec<HypertableCompressionOrderedColumn>> {
-        if let (Some(order_by), Some(desc), Some(nulls_first)) = (
-            &compress_order_by,
-            &compress_order_by_desc,
-            &compress_order_by_nulls_first,
-        ) {
-            let cols = itertools::izip!(order_by, desc, nulls_first)
-                .map(
-                    |(column, desc, nulls_first)| HypertableCompressionOrderedColumn {
-                        column_name: column.clone(),
-                        descending: *desc,
-                        nulls_first: *nulls_first,
-                    },
-                )
-                .collect();
-
-            Some(cols)
-        } else {
-            None
+            column_dependencies: vec![],
+            indices: Self::add_indices(
+                indices,
+                index_columns,
+                &view.schema_name,
+                &view.view_name,
+                object_id_generator,
+            ),
+            privileges: vec![],
         }
     }
 
@@ -663,9 +842,8 @@ impl SchemaReader<'_> {
         index_columns: &[IndexColumnResult],
         foreign_keys: &[ForeignKeyResult],
         foreign_key_columns: &[ForeignKeyColumnResult],
-        hypertables: &[HypertableResult],
-        hypertable_dimensions: &[TimescaleHypertableDimensionResult],
-        object_id_generator: &mut ObjectIdGenerator,
+        timescale: &TimescaleDefinitions,
+        object_id_generator: &ObjectIdGenerator,
     ) -> Result<PostgresTable> {
         let table_columns = Self::add_columns(columns, row);
 
@@ -677,80 +855,16 @@ impl SchemaReader<'_> {
             row,
             object_id_generator,
         );
-        let indices = Self::add_indices(indices, index_columns, row, object_id_generator);
-
-        let hypertable = hypertables
-            .iter()
-            .find(|h| h.table_name == row.table_name && h.table_schema == row.schema_name);
-
-        let table_details = if let Some(hypertable) = hypertable {
-            let mut dimensions = vec![];
-
-            for dim in hypertable_dimensions.iter() {
-                if dim.table_name == row.table_name && dim.table_schema == row.schema_name {
-                    let dim = if let Some(interval) = dim.time_interval {
-                        HypertableDimension::Time {
-                            column_name: dim.column_name.clone(),
-                            time_interval: interval,
-                        }
-                    } else if let Some(interval) = dim.integer_interval {
-                        HypertableDimension::SpaceInterval {
-                            column_name: dim.column_name.clone(),
-                            integer_interval: interval,
-                        }
-                    } else if let Some(num_partitions) = dim.num_partitions {
-                        HypertableDimension::SpacePartitions {
-                            column_name: dim.column_name.clone(),
-                            num_partitions,
-                        }
-                    } else {
-                        return Err(ElefantToolsError::HypertableDimensionWithoutInterval {
-                            table_name: row.table_name.clone(),
-                            dimension_number: dim.dimension_number,
-                        });
-                    };
-
-                    dimensions.push(dim);
-                }
-            }
-
-            let compression = if let (false, None, None, None, None, None) = (
-                hypertable.compression_enabled,
-                hypertable.compress_after,
-                hypertable.compression_chunk_interval,
-                hypertable.compression_schedule_interval,
-                &hypertable.compress_segment_by,
-                &hypertable.compress_order_by,
-            ) {
-                None
-            } else {
-                Some(HypertableCompression {
-                    enabled: hypertable.compression_enabled,
-                    compression_schedule_interval: hypertable.compression_schedule_interval,
-                    chunk_time_interval: hypertable.compression_chunk_interval,
-                    compress_after: hypertable.compress_after,
-                    order_by_columns: Self::get_hypertable_compression_order_by_columns(
-                        &hypertable.compress_order_by,
-                        &hypertable.compress_order_by_desc,
-                        &hypertable.compress_order_by_nulls_first,
-                    ),
-                    segment_by_columns: hypertable.compress_segment_by.clone(),
-                })
-            };
-
-            let retention = hypertable
-                .retention_schedule_interval
-                .zip(hypertable.retention_drop_after)
-                .map(|(schedule_interval, drop_after)| HypertableRetention {
-                    schedule_interval,
-                    drop_after,
-                });
+        let indices = Self::add_indices(
+            indices,
+            index_columns,
+            &row.schema_name,
+            &row.table_name,
+            object_id_generator,
+        );
 
-            TimescaleHypertable {
-                dimensions,
-                compression,
-                retention,
-            }
+        let table_details = if let Some(hypertable) = timescale.get_hypertable(row)? {
+            hypertable
         } else if row.is_partition {
             let parent_tables = row.parent_tables.clone().ok_or_else(|| {
                 ElefantToolsError::PartitionedTableWithoutParent(row.table_name.clone())
@@ -823,9 +937,14 @@ impl SchemaReader<'_> {
             indices,
             comment: row.comment.clone(),
             storage_parameters: row.storage_parameters.clone().unwrap_or_default(),
+            access_method: row.access_method.clone(),
+            tablespace: row.tablespace.clone(),
             table_type: table_details,
             object_id: object_id_generator.next(),
             depends_on: vec![],
+            privileges: vec![],
+            rls_enabled: row.rls_enabled,
+            rls_forced: row.rls_forced,
         };
 
         Ok(table)
@@ -845,7 +964,7 @@ impl SchemaReader<'_> {
         foreign_key_columns: &[ForeignKeyColumnResult],
         unique_constraints: &[UniqueConstraintResult],
         row: &TablesResult,
-        object_id_generator: &mut ObjectIdGenerator,
+        object_id_generator: &ObjectIdGenerator,
     ) -> Vec<PostgresConstraint> {
         let mut constraints: Vec<PostgresConstraint> = check_constraints
             .iter()
@@ -856,6 +975,8 @@ impl SchemaReader<'_> {
                     check_clause: check_constraint.check_clause.clone().into(),
                     comment: check_constraint.comment.clone(),
                     object_id: object_id_generator.next(),
+                    no_inherit: check_constraint.no_inherit,
+                    not_valid: check_constraint.not_valid,
                 }
                 .into()
             })
@@ -921,6 +1042,7 @@ impl SchemaReader<'_> {
             .map(|c| PostgresUniqueConstraint {
                 name: c.constraint_name.clone(),
                 unique_index_name: c.index_name.clone(),
+                definition: c.definition.clone(),
                 comment: c.comment.clone(),
                 object_id: object_id_generator.next(),
             })
@@ -937,20 +1059,21 @@ impl SchemaReader<'_> {
     fn add_indices(
         indices: &[IndexResult],
         index_columns: &[IndexColumnResult],
-        row: &TablesResult,
-        object_id_generator: &mut ObjectIdGenerator,
+        schema_name: &str,
+        table_name: &str,
+        object_id_generator: &ObjectIdGenerator,
     ) -> Vec<PostgresIndex> {
         let mut result = vec![];
 
         let indices = indices
             .iter()
-            .filter(|c| c.table_schema == row.schema_name && c.table_name == row.table_name);
+            .filter(|c| c.table_schema == schema_name && c.table_name == table_name);
         for index in indices {
             let index_columns = index_columns
                 .iter()
                 .filter(|c| {
-                    c.table_schema == row.schema_name
-                        && c.table_name == row.table_name
+                    c.table_schema == schema_name
+                        && c.table_name == table_name
                         && c.index_name == index.index_name
                 })
                 .collect_vec();
@@ -970,6 +1093,8 @@ impl SchemaReader<'_> {
                         (true, Some(false)) => Some(PostgresIndexNullsOrder::Last),
                         _ => None,
                     },
+                    operator_class: c.operator_class.clone(),
+                    collation: c.collation.clone(),
                 })
                 .collect_vec();
 
@@ -1001,7 +1126,11 @@ impl SchemaReader<'_> {
                 },
                 comment: index.comment.clone(),
                 storage_parameters: index.storage_parameters.clone().unwrap_or_else(Vec::new),
+                tablespace: index.tablespace.clone(),
+                not_valid: index.not_valid,
+                parent_index: index.parent_index.clone(),
                 object_id: object_id_generator.next(),
+                depends_on: vec![],
             });
         }
 
@@ -1020,10 +1149,36 @@ macro_rules! define_working_query {
             }
         }
     };
+    ($fn_name:ident, $result:ident, schema_column = $schema_column:literal, $query:literal) => {
+        impl $crate::schema_reader::SchemaReader<'_> {
+            #[tracing::instrument(skip_all)]
+            pub(in crate::schema_reader) async fn $fn_name(&self) -> $crate::Result<Vec<$result>> {
+                self.connection
+                    .get_results(&self.scope_query($query, $schema_column))
+                    .await
+            }
+        }
+    };
 }
 
 pub(crate) use define_working_query;
 
+impl SchemaReader<'_> {
+    /// Restricts the query to the rows of the schema the reader is for, by filtering on the
+    /// column of the result holding the name of the schema.
+    fn scope_query<'q>(&self, sql: &'q str, schema_column: &str) -> Cow<'q, str> {
+        match &self.schema {
+            Some(schema) => Cow::Owned(format!(
+                "select * from ({}) scoped where scoped.{} = {};",
+                sql.trim().trim_end_matches(';'),
+                schema_column,
+                quote_value_string(schema)
+            )),
+            None => Cow::Borrowed(sql),
+        }
+    }
+}
+
 fn none_if_irrelevant(s: String) -> Option<String> {
     if s == "-" || s == "0" {
         None
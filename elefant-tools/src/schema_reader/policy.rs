@@ -0,0 +1,66 @@
+use crate::postgres_client_wrapper::{FromRow, RowEnumExt};
+use crate::schema_reader::define_working_query;
+use crate::PostgresPolicyCommand;
+use tokio_postgres::Row;
+
+pub struct PolicyResult {
+    pub schema_name: String,
+    pub table_name: String,
+    pub name: String,
+    pub command: PostgresPolicyCommand,
+    pub permissive: bool,
+    pub roles: Vec<String>,
+    pub using_expression: Option<String>,
+    pub check_expression: Option<String>,
+    pub depends_on: Option<Vec<i64>>,
+}
+
+impl FromRow for PolicyResult {
+    fn from_row(row: Row) -> crate::Result<Self> {
+        Ok(Self {
+            schema_name: row.try_get(0)?,
+            table_name: row.try_get(1)?,
+            name: row.try_get(2)?,
+            command: row.try_get_enum_value(3)?,
+            permissive: row.try_get(4)?,
+            roles: row.try_get(5)?,
+            using_expression: row.try_get(6)?,
+            check_expression: row.try_get(7)?,
+            depends_on: row.try_get(8)?,
+        })
+    }
+}
+
+//language=postgresql
+define_working_query!(
+    get_policies,
+    PolicyResult,
+    schema_column = "nspname",
+    r#"
+select ns.nspname,
+       cl.relname,
+       pol.polname,
+       pol.polcmd,
+       pol.polpermissive,
+       array(select case when role_oid = 0 then 'public' else pg_get_userbyid(role_oid)::text end
+             from unnest(pol.polroles) with ordinality as r(role_oid, position)
+             order by r.position)::text[] as roles,
+       pg_get_expr(pol.polqual, pol.polrelid),
+       pg_get_expr(pol.polwithcheck, pol.polrelid),
+       (select array_agg(dep.refobjid::int8)
+        from pg_depend dep
+        where dep.classid = 'pg_policy'::regclass
+          and dep.objid = pol.oid
+          and dep.deptype <> 'e'
+          and dep.refobjid > 16384
+          and dep.refobjid <> pol.polrelid) as depends_on
+from pg_policy pol
+         join pg_class cl on cl.oid = pol.polrelid
+         join pg_namespace ns on ns.oid = cl.relnamespace
+         left join pg_depend dep on dep.objid = ns.oid
+where cl.oid > 16384
+  and (dep.objid is null or dep.deptype <> 'e')
+  and has_table_privilege(cl.oid, 'SELECT, INSERT, UPDATE')
+order by ns.nspname, cl.relname, pol.polname;
+"#
+);
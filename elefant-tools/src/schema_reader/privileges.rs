@@ -0,0 +1,120 @@
+use crate::postgres_client_wrapper::{FromPgChar, FromRow, RowEnumExt};
+use crate::schema_reader::define_working_query;
+use crate::ElefantToolsError;
+use tokio_postgres::Row;
+
+/// The kind of object a privilege is on. The values are the ones `acldefault` uses.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PrivilegeObjectType {
+    Schema,
+    /// Tables and views.
+    Relation,
+    Function,
+}
+
+impl FromPgChar for PrivilegeObjectType {
+    fn from_pg_char(c: char) -> Result<Self, ElefantToolsError> {
+        match c {
+            'n' => Ok(PrivilegeObjectType::Schema),
+            'r' => Ok(PrivilegeObjectType::Relation),
+            'f' => Ok(PrivilegeObjectType::Function),
+            _ => Err(ElefantToolsError::UnknownPrivilegeObjectType(c.to_string())),
+        }
+    }
+}
+
+pub struct PrivilegeResult {
+    pub object_type: PrivilegeObjectType,
+    pub schema_name: String,
+    pub oid: i64,
+    pub grantee: String,
+    pub privilege: String,
+    pub with_grant_option: bool,
+    pub revoked: bool,
+}
+
+impl FromRow for PrivilegeResult {
+    fn from_row(row: Row) -> crate::Result<Self> {
+        Ok(Self {
+            object_type: row.try_get_enum_value(0)?,
+            schema_name: row.try_get(1)?,
+            oid: row.try_get(2)?,
+            grantee: row.try_get(3)?,
+            privilege: row.try_get(4)?,
+            with_grant_option: row.try_get(5)?,
+            revoked: row.try_get(6)?,
+        })
+    }
+}
+
+// The privileges are compared with the initial privileges of the object, which are the ones in
+// pg_init_privs for objects created by initdb or an extension, such as the public schema, and the
+// default privileges for everything else. Objects that have never had their privileges changed
+// have a null acl, and are skipped entirely.
+//language=postgresql
+define_working_query!(
+    get_privileges,
+    PrivilegeResult,
+    schema_column = "schema_name",
+    r#"
+with objects as (select 'n'::"char" as object_type,
+                        ns.oid,
+                        ns.nspname as schema_name,
+                        ns.nspowner as owner,
+                        ns.nspacl as acl,
+                        'pg_namespace'::regclass as class_oid
+                 from pg_namespace ns
+                 where ns.oid > 16384
+                    or ns.nspname = 'public'
+                 union all
+                 select 'r'::"char", cl.oid, ns.nspname, cl.relowner, cl.relacl, 'pg_class'::regclass
+                 from pg_class cl
+                          join pg_namespace ns on ns.oid = cl.relnamespace
+                 where cl.relkind in ('r', 'p', 'v', 'm')
+                   and cl.oid > 16384
+                 union all
+                 select 'f'::"char", proc.oid, ns.nspname, proc.proowner, proc.proacl, 'pg_proc'::regclass
+                 from pg_proc proc
+                          join pg_namespace ns on ns.oid = proc.pronamespace
+                 where proc.oid > 16384),
+     acls as (select o.object_type,
+                     o.oid,
+                     o.schema_name,
+                     o.owner,
+                     coalesce(o.acl, acldefault(o.object_type, o.owner)) as current_acl,
+                     coalesce(ip.initprivs, acldefault(o.object_type, o.owner)) as initial_acl
+              from objects o
+                       left join pg_init_privs ip
+                                 on ip.objoid = o.oid and ip.classoid = o.class_oid and ip.objsubid = 0
+              where o.acl is not null
+                 or ip.initprivs is not null),
+     current_privileges as (select a.object_type, a.oid, a.schema_name, p.grantee, p.privilege_type, p.is_grantable
+                            from acls a,
+                                 aclexplode(a.current_acl) p
+                            where p.grantee <> a.owner),
+     initial_privileges as (select a.object_type, a.oid, a.schema_name, p.grantee, p.privilege_type, p.is_grantable
+                            from acls a,
+                                 aclexplode(a.initial_acl) p
+                            where p.grantee <> a.owner),
+     changes as ((select *, false as is_revoked
+                  from current_privileges
+                  except
+                  select *, false
+                  from initial_privileges)
+                 union all
+                 (select *, true
+                  from initial_privileges
+                  except
+                  select *, true
+                  from current_privileges))
+select c.object_type,
+       c.schema_name::text,
+       c.oid::int8,
+       case when c.grantee = 0 then 'public' else pg_get_userbyid(c.grantee)::text end as grantee_name,
+       c.privilege_type,
+       c.is_grantable,
+       c.is_revoked
+from changes c
+order by c.object_type, c.oid, c.is_revoked desc, grantee_name, c.privilege_type;
+"#
+);
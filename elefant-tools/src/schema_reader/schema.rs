@@ -20,6 +20,7 @@ impl FromRow for SchemaResult {
 define_working_query!(
     get_schemas,
     SchemaResult,
+    schema_column = "name",
     r#"
 SELECT n.nspname AS name,
        d.description AS comment
@@ -17,6 +17,7 @@ pub struct SequenceResult {
     pub is_internally_created: bool,
     pub author_table: Option<String>,
     pub author_table_column_position: Option<i32>,
+    pub oid: i64,
 }
 
 impl FromRow for SequenceResult {
@@ -36,6 +37,7 @@ impl FromRow for SequenceResult {
             is_internally_created: row.try_get::<_, Option<i8>>(11)? == Some('i' as i8),
             author_table: row.try_get(12)?,
             author_table_column_position: row.try_get(13)?,
+            oid: row.try_get(14)?,
         })
     }
 }
@@ -44,6 +46,7 @@ impl FromRow for SequenceResult {
 define_working_query!(
     get_sequences,
     SequenceResult,
+    schema_column = "schemaname",
     r#"
 SELECT n.nspname      AS schemaname,
        c.relname      AS sequencename,
@@ -61,7 +64,8 @@ SELECT n.nspname      AS schemaname,
        d.description  AS comment,
        col_dep.deptype,
        col_table_dep.relname as author_table,
-       col_dep.refobjsubid as author_table_column_position
+       col_dep.refobjsubid as author_table_column_position,
+       c.oid::int8
 FROM pg_sequence s
          JOIN pg_class c ON c.oid = s.seqrelid
          join pg_type t on t.oid = s.seqtypid
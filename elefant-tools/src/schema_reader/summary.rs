@@ -0,0 +1,234 @@
+use crate::postgres_client_wrapper::FromRow;
+use crate::quoting::quote_value_string;
+use crate::schema_reader::SchemaReader;
+use crate::Result;
+use std::fmt::{Display, Formatter};
+use tokio_postgres::Row;
+use tracing::instrument;
+
+/// How many objects a database contains, and how much space its tables use.
+///
+/// This is much faster to get than a full introspection, so it is useful for showing what is
+/// about to happen before starting a long copy.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SchemaSummary {
+    pub schemas: i64,
+    /// Tables, including partitions.
+    pub tables: i64,
+    /// Both normal and materialized views.
+    pub views: i64,
+    pub indices: i64,
+    pub sequences: i64,
+    /// Functions, procedures and aggregate functions.
+    pub functions: i64,
+    /// The size of the tables and materialized views in bytes, including their indices
+    /// and toast data.
+    pub total_bytes: i64,
+}
+
+impl SchemaSummary {
+    /// Whether there are no objects besides the schemas themselves.
+    pub fn is_empty(&self) -> bool {
+        self.tables == 0
+            && self.views == 0
+            && self.indices == 0
+            && self.sequences == 0
+            && self.functions == 0
+    }
+}
+
+impl FromRow for SchemaSummary {
+    fn from_row(row: Row) -> Result<Self> {
+        Ok(SchemaSummary {
+            schemas: row.try_get(0)?,
+            tables: row.try_get(1)?,
+            views: row.try_get(2)?,
+            indices: row.try_get(3)?,
+            sequences: row.try_get(4)?,
+            functions: row.try_get(5)?,
+            total_bytes: row.try_get(6)?,
+        })
+    }
+}
+
+impl Display for SchemaSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} tables, {} indices, {} views, {} sequences, {} functions, ~{}",
+            with_thousands_separator(self.tables),
+            with_thousands_separator(self.indices),
+            with_thousands_separator(self.views),
+            with_thousands_separator(self.sequences),
+            with_thousands_separator(self.functions),
+            human_readable_bytes(self.total_bytes)
+        )
+    }
+}
+
+fn with_thousands_separator(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+
+    if value < 0 {
+        result.push('-');
+    }
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(c);
+    }
+
+    result
+}
+
+pub(crate) fn human_readable_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["kB", "MB", "GB", "TB", "PB"];
+
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = "bytes";
+    for u in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = u;
+    }
+
+    if value < 10.0 {
+        format!("{:.1} {}", value, unit)
+    } else {
+        format!("{:.0} {}", value, unit)
+    }
+}
+
+impl SchemaReader<'_> {
+    /// Counts the objects in the database using a single query, without introspecting
+    /// their definitions. If `schema` is specified only that schema is counted.
+    ///
+    /// The same objects as [SchemaReader::introspect_database] are included, so objects
+    /// belonging to extensions are not counted.
+    #[instrument(skip_all)]
+    pub async fn quick_summary(&self, schema: Option<&str>) -> Result<SchemaSummary> {
+        let schema_filter = match schema {
+            Some(schema) => format!("n.nspname = {}", quote_value_string(schema)),
+            None => "true".to_string(),
+        };
+
+        //language=postgresql
+        let query = format!(
+            r#"
+with namespaces as (select n.oid
+                    from pg_namespace n
+                             left join pg_depend dep on dep.objid = n.oid and dep.deptype = 'e'
+                    where (n.oid > 16384 or n.nspname = 'public')
+                      and dep.objid is null
+                      and {schema_filter}),
+     relations as (select cl.oid, cl.relkind
+                   from pg_class cl
+                            left join pg_depend dep on dep.objid = cl.oid and dep.deptype = 'e'
+                   where cl.relnamespace in (select oid from namespaces)
+                     and dep.objid is null)
+select (select count(*) from namespaces),
+       (select count(*) from relations where relkind in ('r', 'p')),
+       (select count(*) from relations where relkind in ('v', 'm')),
+       (select count(*) from relations where relkind in ('i', 'I')),
+       (select count(*) from relations where relkind = 'S'),
+       (select count(*)
+        from pg_proc p
+                 left join pg_depend dep on dep.objid = p.oid and dep.deptype = 'e'
+        where p.pronamespace in (select oid from namespaces)
+          and dep.objid is null),
+       (select coalesce(sum(pg_total_relation_size(oid)), 0)::int8
+        from relations
+        where relkind in ('r', 'm'));
+"#
+        );
+
+        self.connection.get_result(&query).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::TestHelper;
+    use crate::{storage, test_helpers};
+    use elefant_test_macros::pg_test;
+
+    #[test]
+    fn formats_summary() {
+        let summary = SchemaSummary {
+            schemas: 2,
+            tables: 312,
+            views: 3,
+            indices: 1204,
+            sequences: 0,
+            functions: 97,
+            total_bytes: 48 * 1024 * 1024 * 1024,
+        };
+
+        assert_eq!(
+            summary.to_string(),
+            "312 tables, 1,204 indices, 3 views, 0 sequences, 97 functions, ~48 GB"
+        );
+    }
+
+    #[test]
+    fn formats_small_numbers() {
+        assert_eq!(with_thousands_separator(0), "0");
+        assert_eq!(with_thousands_separator(999), "999");
+        assert_eq!(with_thousands_separator(1_234_567), "1,234,567");
+        assert_eq!(human_readable_bytes(512), "512 bytes");
+        assert_eq!(human_readable_bytes(8192), "8.0 kB");
+        assert_eq!(human_readable_bytes(1536 * 1024), "1.5 MB");
+    }
+
+    #[pg_test(arg(postgres = 15))]
+    async fn summarizes_copy_source_fixture(helper: &TestHelper) {
+        helper
+            .execute_not_query(storage::tests::get_copy_source_database_create_script(
+                helper.get_conn().version(),
+            ))
+            .await;
+
+        helper
+            .execute_not_query(
+                r#"
+            create schema other;
+            create table other.things(id int);
+            create function other.thing_count() returns bigint language sql as $$ select count(*) from other.things $$;
+            "#,
+            )
+            .await;
+
+        let reader = SchemaReader::new(helper.get_conn());
+
+        let summary = reader.quick_summary(Some("public")).await.unwrap();
+        assert!(summary.total_bytes > 0);
+        assert_eq!(
+            summary,
+            SchemaSummary {
+                schemas: 1,
+                tables: 11,
+                views: 1,
+                indices: 12,
+                sequences: 5,
+                // The functions of btree_gin belongs to the extension.
+                functions: 0,
+                total_bytes: summary.total_bytes,
+            }
+        );
+
+        let summary = reader.quick_summary(None).await.unwrap();
+        assert_eq!(summary.schemas, 2);
+        assert_eq!(summary.tables, 12);
+        assert_eq!(summary.functions, 1);
+    }
+}
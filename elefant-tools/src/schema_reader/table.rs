@@ -20,6 +20,10 @@ pub struct TablesResult {
     pub oid: i64,
     pub depends_on: Option<Vec<i64>>,
     pub type_oid: i64,
+    pub access_method: Option<String>,
+    pub tablespace: Option<String>,
+    pub rls_enabled: bool,
+    pub rls_forced: bool,
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
@@ -57,6 +61,10 @@ impl FromRow for TablesResult {
             oid: row.try_get(12)?,
             depends_on: row.try_get(13)?,
             type_oid: row.try_get(14)?,
+            access_method: row.try_get(15)?,
+            tablespace: row.try_get(16)?,
+            rls_enabled: row.try_get(17)?,
+            rls_forced: row.try_get(18)?,
         })
     }
 }
@@ -65,6 +73,7 @@ impl FromRow for TablesResult {
 define_working_query!(
     get_tables,
     TablesResult,
+    schema_column = "nspname",
     r#"
 select
     ns.nspname,
@@ -83,14 +92,24 @@ select
     cl.relispartition,
     cl.reloptions,
    cl.oid::int8,
-   (select array_agg(refobjid::int8) from pg_depend dep where cl.oid = dep.objid and dep.deptype <> 'e' and dep.refobjid > 16384 and dep.objid <> dep.refobjid) as depends_on,
-   cl.reltype::int8
+   (select array_agg(refobjid::int8) from pg_depend dep where (cl.oid = dep.objid or dep.objid in (select con.oid from pg_constraint con where con.conrelid = cl.oid and con.contype = 'c')
+       -- Sequences and functions used by column defaults, such as nextval('my_sequence'::regclass)
+       or (dep.classid = 'pg_attrdef'::regclass and dep.objid in (select ad.oid from pg_attrdef ad where ad.adrelid = cl.oid)
+           and (dep.refclassid = 'pg_proc'::regclass or dep.refobjid in (select seq.oid from pg_class seq where seq.relkind = 'S')))
+   ) and dep.deptype <> 'e' and dep.refobjid > 16384 and dep.objid <> dep.refobjid and dep.refobjid <> cl.oid) as depends_on,
+   cl.reltype::int8,
+   nullif(am.amname, 'heap') as access_method,
+   ts.spcname as tablespace,
+   cl.relrowsecurity,
+   cl.relforcerowsecurity
 from pg_class cl
          join pg_catalog.pg_namespace ns on ns.oid = cl.relnamespace
          left join pg_description des on des.objoid = cl.oid and des.objsubid = 0
          left join pg_partitioned_table pt on pt.partrelid = cl.oid
          left join pg_class default_partition on default_partition.oid = pt.partdefid
          left join pg_depend dep on dep.objid = ns.oid
+         left join pg_am am on am.oid = cl.relam
+         left join pg_tablespace ts on ts.oid = cl.reltablespace
 where cl.relkind in ('r', 'p')
   and cl.oid > 16384
   and (dep.objid is null or dep.deptype <> 'e' )
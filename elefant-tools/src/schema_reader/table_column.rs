@@ -1,6 +1,6 @@
 use crate::postgres_client_wrapper::{FromRow, RowEnumExt};
 use crate::schema_reader::define_working_query;
-use crate::{ColumnIdentity, PostgresColumn};
+use crate::{ColumnIdentity, IdentityOptions, PostgresColumn};
 use tokio_postgres::Row;
 
 #[derive(Debug, Eq, PartialEq)]
@@ -17,6 +17,14 @@ pub struct TableColumnsResult {
     pub array_dimensions: i32,
     pub data_type_length: Option<i32>,
     pub identity: Option<ColumnIdentity>,
+    pub identity_sequence_name: Option<String>,
+    pub identity_start: Option<i64>,
+    pub identity_increment: Option<i64>,
+    pub identity_min: Option<i64>,
+    pub identity_max: Option<i64>,
+    pub identity_cache: Option<i64>,
+    pub identity_cycle: Option<bool>,
+    pub inherits_default: bool,
 }
 
 impl FromRow for TableColumnsResult {
@@ -37,6 +45,14 @@ impl FromRow for TableColumnsResult {
             },
             data_type_length: row.try_get(10)?,
             identity: row.try_get_opt_enum_value(11)?,
+            identity_sequence_name: row.try_get(12)?,
+            identity_start: row.try_get(13)?,
+            identity_increment: row.try_get(14)?,
+            identity_min: row.try_get(15)?,
+            identity_max: row.try_get(16)?,
+            identity_cache: row.try_get(17)?,
+            identity_cycle: row.try_get(18)?,
+            inherits_default: row.try_get(19)?,
         })
     }
 }
@@ -54,14 +70,29 @@ impl TableColumnsResult {
             array_dimensions: self.array_dimensions,
             data_type_length: self.data_type_length,
             identity: self.identity,
+            identity_options: self.get_identity_options(),
+            inherits_default: self.inherits_default,
         }
     }
+
+    fn get_identity_options(&self) -> Option<IdentityOptions> {
+        Some(IdentityOptions {
+            sequence_name: self.identity_sequence_name.clone()?,
+            start: self.identity_start?,
+            increment: self.identity_increment?,
+            min: self.identity_min?,
+            max: self.identity_max?,
+            cache: self.identity_cache?,
+            cycle: self.identity_cycle?,
+        })
+    }
 }
 
 //language=postgresql
 define_working_query!(
     get_columns,
     TableColumnsResult,
+    schema_column = "nspname",
     r#"
 select ns.nspname,
        cl.relname,
@@ -80,7 +111,19 @@ select ns.nspname,
        des.description,
        attr.attndims                                                                               as array_dimensions,
        information_schema._pg_char_max_length(coalesce(non_array_type.oid, t.oid), attr.atttypmod) as data_type_length,
-       attidentity
+       attr.attidentity,
+       identity_sequence.relname::text,
+       identity_sequence.seqstart,
+       identity_sequence.seqincrement,
+       identity_sequence.seqmin,
+       identity_sequence.seqmax,
+       identity_sequence.seqcache,
+       identity_sequence.seqcycle,
+       -- Partitions get the default of the parent when they are created or when it is changed.
+       coalesce(not attr.attislocal
+                    and attr.attgenerated = ''::"char"
+                    and pg_get_expr(ad.adbin, ad.adrelid) = pg_get_expr(parent_ad.adbin, parent_ad.adrelid),
+                false)                                                                             as inherits_default
 from pg_attribute attr
          join pg_class cl on attr.attrelid = cl.oid
          join pg_type t on attr.atttypid = t.oid
@@ -89,6 +132,19 @@ from pg_attribute attr
          left join pg_description des on des.objoid = cl.oid and des.objsubid = attr.attnum
          left join pg_type non_array_type on non_array_type.oid = t.typelem and non_array_type.typarray = t.oid
          left join pg_depend dep on dep.objid = ns.oid
+         -- The sequence of an identity column has an internal dependency on the column.
+         left join lateral (select seq_class.relname, seq.*
+                            from pg_depend seq_dep
+                                     join pg_sequence seq on seq.seqrelid = seq_dep.objid
+                                     join pg_class seq_class on seq_class.oid = seq.seqrelid
+                            where attr.attidentity <> ''
+                              and seq_dep.classid = 'pg_class'::regclass
+                              and seq_dep.refobjid = attr.attrelid
+                              and seq_dep.refobjsubid = attr.attnum
+                              and seq_dep.deptype = 'i') identity_sequence on true
+         left join pg_inherits inh on cl.relispartition and inh.inhrelid = cl.oid
+         left join pg_attribute parent_attr on parent_attr.attrelid = inh.inhparent and parent_attr.attname = attr.attname
+         left join pg_attrdef parent_ad on parent_ad.adrelid = parent_attr.attrelid and parent_ad.adnum = parent_attr.attnum
 where cl.relkind in ('r', 'p')
   and cl.oid > 16384
   and attr.attnum > 0
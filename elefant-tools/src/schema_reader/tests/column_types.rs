@@ -11,6 +11,7 @@ use elefant_test_macros::pg_test;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn array_columns(helper: &TestHelper) {
@@ -52,6 +53,7 @@ async fn array_columns(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn column_types_of_limited_size(helper: &TestHelper) {
@@ -16,6 +16,7 @@ use ordered_float::NotNan;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn comments_on_stuff(helper: &TestHelper) {
@@ -86,6 +87,7 @@ async fn comments_on_stuff(helper: &TestHelper) {
                             PostgresConstraint::Unique(PostgresUniqueConstraint {
                                 name: "my_table_another_value_key".to_string(),
                                 unique_index_name: "my_table_another_value_key".to_string(),
+                                definition: "UNIQUE (another_value)".to_string(),
                                 comment: Some("This is a unique constraint".to_string()),
                                 ..default()
                             }),
@@ -98,6 +100,8 @@ async fn comments_on_stuff(helper: &TestHelper) {
                                     ordinal_position: 1,
                                     direction: Some(PostgresIndexColumnDirection::Ascending),
                                     nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                    operator_class: None,
+                                    collation: None,
                                 }],
                                 index_type: "btree".to_string(),
                                 index_constraint_type: PostgresIndexType::Unique {
@@ -108,6 +112,7 @@ async fn comments_on_stuff(helper: &TestHelper) {
                             },
                         ],
                         comment: Some("This is a 'table'".to_string()),
+                        depends_on: vec![6.into()],
                         ..default()
                     }
                 ],
@@ -2,8 +2,9 @@ use crate::schema_reader::tests;
 use crate::test_helpers;
 use crate::test_helpers::TestHelper;
 use crate::{
-    default, PostgresColumn, PostgresDatabase, PostgresDomain, PostgresDomainConstraint,
-    PostgresEnum, PostgresSchema, PostgresTable, TimescaleSupport,
+    default, ObjectId, PostgresColumn, PostgresCompositeType, PostgresCompositeTypeAttribute,
+    PostgresDatabase, PostgresDomain, PostgresDomainConstraint, PostgresEnum, PostgresSchema,
+    PostgresTable, TimescaleSupport,
 };
 use elefant_test_macros::pg_test;
 
@@ -12,6 +13,7 @@ use elefant_test_macros::pg_test;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn enums(helper: &TestHelper) {
@@ -48,6 +50,7 @@ async fn enums(helper: &TestHelper) {
                             ..default()
                         },
                     ],
+                    depends_on: vec![ObjectId::new(3)],
                     ..default()
                 }],
                 enums: vec![PostgresEnum {
@@ -59,7 +62,7 @@ async fn enums(helper: &TestHelper) {
                         "happy".to_string(),
                     ],
                     comment: Some("This is a mood".to_string()),
-                    ..default()
+                    object_id: ObjectId::new(3),
                 }],
                 ..default()
             }],
@@ -75,6 +78,7 @@ async fn enums(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn domains(helper: &TestHelper) {
@@ -182,3 +186,118 @@ create table movie
     )
     .await;
 }
+
+#[pg_test(arg(postgres = 12))]
+#[pg_test(arg(postgres = 13))]
+#[pg_test(arg(postgres = 14))]
+#[pg_test(arg(postgres = 15))]
+#[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
+#[pg_test(arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16))]
+async fn composite_types(helper: &TestHelper) {
+    tests::test_introspection(
+        helper,
+        r#"
+    create type mood as enum ('sad', 'happy');
+    create domain zip_code as varchar(5);
+    create type address as (
+        street text,
+        zip zip_code,
+        tags text[],
+        current_mood mood,
+        city varchar(50)
+    );
+
+    comment on type address is 'A postal address';
+
+    create table person (
+        name text,
+        home address
+    );
+    "#,
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![PostgresTable {
+                    name: "person".to_string(),
+                    columns: vec![
+                        PostgresColumn {
+                            name: "name".to_string(),
+                            is_nullable: true,
+                            ordinal_position: 1,
+                            data_type: "text".to_string(),
+                            ..default()
+                        },
+                        PostgresColumn {
+                            name: "home".to_string(),
+                            is_nullable: true,
+                            ordinal_position: 2,
+                            data_type: "address".to_string(),
+                            ..default()
+                        },
+                    ],
+                    depends_on: vec![ObjectId::new(5)],
+                    ..default()
+                }],
+                enums: vec![PostgresEnum {
+                    name: "mood".to_string(),
+                    values: vec!["sad".to_string(), "happy".to_string()],
+                    object_id: ObjectId::new(3),
+                    ..default()
+                }],
+                domains: vec![PostgresDomain {
+                    name: "zip_code".to_string(),
+                    base_type_name: "varchar".to_string(),
+                    object_id: ObjectId::new(4),
+                    data_type_length: Some(5),
+                    ..default()
+                }],
+                composite_types: vec![PostgresCompositeType {
+                    name: "address".to_string(),
+                    attributes: vec![
+                        PostgresCompositeTypeAttribute {
+                            name: "street".to_string(),
+                            ordinal_position: 1,
+                            data_type: "text".to_string(),
+                            ..default()
+                        },
+                        PostgresCompositeTypeAttribute {
+                            name: "zip".to_string(),
+                            ordinal_position: 2,
+                            data_type: "zip_code".to_string(),
+                            ..default()
+                        },
+                        PostgresCompositeTypeAttribute {
+                            name: "tags".to_string(),
+                            ordinal_position: 3,
+                            data_type: "text".to_string(),
+                            array_dimensions: 1,
+                            ..default()
+                        },
+                        PostgresCompositeTypeAttribute {
+                            name: "current_mood".to_string(),
+                            ordinal_position: 4,
+                            data_type: "mood".to_string(),
+                            ..default()
+                        },
+                        PostgresCompositeTypeAttribute {
+                            name: "city".to_string(),
+                            ordinal_position: 5,
+                            data_type: "varchar".to_string(),
+                            data_type_length: Some(50),
+                            ..default()
+                        },
+                    ],
+                    comment: Some("A postal address".to_string()),
+                    object_id: ObjectId::new(5),
+                    depends_on: vec![ObjectId::new(3), ObjectId::new(4)],
+                }],
+                ..default()
+            }],
+            timescale_support: TimescaleSupport::from_test_helper(helper),
+            ..default()
+        },
+    )
+    .await;
+}
@@ -15,6 +15,7 @@ use elefant_test_macros::pg_test;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn foreign_keys(helper: &TestHelper) {
@@ -51,6 +52,8 @@ async fn foreign_keys(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -58,6 +61,7 @@ async fn foreign_keys(helper: &TestHelper) {
                             index_constraint_type: PostgresIndexType::PrimaryKey,
                             ..default()
                         }],
+                        depends_on: vec![7.into()],
                         ..default()
                     },
                     PostgresTable {
@@ -103,6 +107,8 @@ async fn foreign_keys(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -110,6 +116,7 @@ async fn foreign_keys(helper: &TestHelper) {
                             index_constraint_type: PostgresIndexType::PrimaryKey,
                             ..default()
                         }],
+                        depends_on: vec![8.into()],
                         ..default()
                     },
                 ],
@@ -153,6 +160,7 @@ async fn foreign_keys(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn foreign_key_constraints(helper: &TestHelper) {
@@ -241,12 +249,16 @@ async fn foreign_key_constraints(helper: &TestHelper) {
                                     ordinal_position: 1,
                                     direction: Some(PostgresIndexColumnDirection::Ascending),
                                     nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                    operator_class: None,
+                                    collation: None,
                                 },
                                 PostgresIndexKeyColumn {
                                     name: "order_id".to_string(),
                                     ordinal_position: 2,
                                     direction: Some(PostgresIndexColumnDirection::Ascending),
                                     nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                    operator_class: None,
+                                    collation: None,
                                 },
                             ],
                             index_type: "btree".to_string(),
@@ -274,6 +286,8 @@ async fn foreign_key_constraints(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -300,6 +314,8 @@ async fn foreign_key_constraints(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
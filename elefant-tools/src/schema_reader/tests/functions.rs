@@ -1,7 +1,7 @@
 use crate::schema_reader::tests;
 use crate::test_helpers::TestHelper;
 use crate::{
-    default, FunctionKind, Parallel, PostgresAggregateFunction, PostgresDatabase, PostgresFunction,
+    default, FunctionKind, IdentifierQuoter, Parallel, PostgresAggregateFunction, PostgresDatabase, PostgresFunction,
     PostgresSchema, TimescaleSupport, Volatility,
 };
 use crate::{test_helpers, PostgresColumn, PostgresTable};
@@ -13,6 +13,7 @@ use ordered_float::NotNan;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn test_functions(helper: &TestHelper) {
@@ -152,6 +153,7 @@ async fn test_functions(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn functions_returning_tables(helper: &TestHelper) {
@@ -221,3 +223,106 @@ $$ language plpgsql;
     )
     .await;
 }
+
+#[pg_test(arg(postgres = 15))]
+async fn functions_in_other_schemas(helper: &TestHelper) {
+    helper
+        .execute_not_query(
+            r#"
+    create schema util;
+    create function util.double(a int4) returns int4 as $$ select a * 2 $$ language sql;
+    "#,
+        )
+        .await;
+
+    let db = tests::introspect_schema(helper).await;
+
+    let util = db.try_get_schema("util").unwrap();
+    assert_eq!(util.functions.len(), 1);
+    assert_eq!(util.functions[0].function_name, "double");
+}
+
+#[pg_test(arg(postgres = 12))]
+#[pg_test(arg(postgres = 13))]
+#[pg_test(arg(postgres = 14))]
+#[pg_test(arg(postgres = 15))]
+#[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
+async fn aggregates_depend_on_their_functions_and_extensions(helper: &TestHelper) {
+    helper
+        .execute_not_query(
+            r#"
+    create extension citext;
+    create schema z_functions;
+
+    create function z_functions.pick_larger(state citext, value citext) returns citext
+        as $$ select citext_larger(state, value) $$ language sql;
+    create function z_functions.finish(state citext) returns citext
+        as $$ select state $$ language sql;
+
+    create aggregate citext_max(citext) (
+        sfunc = z_functions.pick_larger,
+        stype = citext,
+        finalfunc = z_functions.finish,
+        sortop = >
+    );
+    "#,
+        )
+        .await;
+
+    let db = tests::introspect_schema(helper).await;
+
+    // The operator name alone is ambiguous with the built-in > operator, so it is qualified.
+    let aggregate = &db.try_get_schema("public").unwrap().aggregate_functions[0];
+    assert_eq!(aggregate.sort_operator.as_deref(), Some("public.>"));
+
+    let functions = &db.try_get_schema("z_functions").unwrap().functions;
+    let citext = db
+        .enabled_extensions
+        .iter()
+        .find(|e| e.name == "citext")
+        .unwrap();
+    let mut expected = functions.iter().map(|f| f.object_id).collect::<Vec<_>>();
+    expected.push(citext.object_id);
+
+    assert_eq!(aggregate.depends_on.len(), expected.len());
+    for object_id in expected {
+        assert!(aggregate.depends_on.contains(&object_id));
+    }
+}
+
+#[pg_test(arg(postgres = 12), arg(postgres = 16))]
+async fn function_costs_only_differ_when_set_explicitly(pg_12: &TestHelper, pg_16: &TestHelper) {
+    let sql = r#"
+    create function add(a int4, b int4) returns int4 as $$ begin return a + b; end; $$ language plpgsql;
+
+    create function double(a int4) returns int4 as $$ select a * 2 $$ language sql immutable;
+
+    create function numbers() returns setof int4 as $$ select generate_series(1, 10) $$ language sql;
+
+    create function expensive_numbers() returns setof int4 as $$ select generate_series(1, 10) $$
+        language sql cost 5000 rows 10;
+    "#;
+
+    pg_12.execute_not_query(sql).await;
+    pg_16.execute_not_query(sql).await;
+
+    let pg_12_db = tests::introspect_schema(pg_12).await;
+    let pg_16_db = tests::introspect_schema(pg_16).await;
+
+    let pg_16_schema = pg_16_db.try_get_schema("public").unwrap();
+    let pg_12_functions = &pg_12_db.try_get_schema("public").unwrap().functions;
+    let pg_16_functions = &pg_16_schema.functions;
+
+    assert_eq!(pg_12_functions, pg_16_functions);
+
+    for function in pg_16_functions {
+        let is_explicit = function.function_name == "expensive_numbers";
+        assert_eq!(function.is_default_cost(), !is_explicit);
+        assert_eq!(function.is_default_rows(), !is_explicit);
+
+        let sql = function.get_create_statement(pg_16_schema, &IdentifierQuoter::empty());
+        assert_eq!(sql.contains(" cost "), is_explicit);
+        assert_eq!(sql.contains(" rows "), is_explicit);
+    }
+}
@@ -14,6 +14,7 @@ use elefant_test_macros::pg_test;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn indices(helper: &TestHelper) {
@@ -51,6 +52,8 @@ async fn indices(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::First),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -65,6 +68,8 @@ async fn indices(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -79,6 +84,8 @@ async fn indices(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Descending),
                                 nulls_order: Some(PostgresIndexNullsOrder::First),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -93,6 +100,8 @@ async fn indices(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Descending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -117,6 +126,7 @@ async fn indices(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn index_types(helper: &TestHelper) {
@@ -150,6 +160,8 @@ async fn index_types(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: None,
                                 nulls_order: None,
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "gin".to_string(),
                             predicate: None,
@@ -164,6 +176,8 @@ async fn index_types(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: None,
                                 nulls_order: None,
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "gist".to_string(),
                             predicate: None,
@@ -188,6 +202,7 @@ async fn index_types(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn filtered_index(helper: &TestHelper) {
@@ -219,6 +234,8 @@ async fn filtered_index(helper: &TestHelper) {
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Ascending),
                             nulls_order: Some(PostgresIndexNullsOrder::Last),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         predicate: Some("(value % 2) = 0".to_string()),
@@ -242,6 +259,7 @@ async fn filtered_index(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn index_with_include(helper: &TestHelper) {
@@ -283,6 +301,8 @@ async fn index_with_include(helper: &TestHelper) {
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Ascending),
                             nulls_order: Some(PostgresIndexNullsOrder::Last),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         predicate: None,
@@ -306,6 +326,7 @@ async fn index_with_include(helper: &TestHelper) {
 
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn table_with_non_distinct_nulls(helper: &TestHelper) {
@@ -331,6 +352,7 @@ async fn table_with_non_distinct_nulls(helper: &TestHelper) {
                     constraints: vec![PostgresConstraint::Unique(PostgresUniqueConstraint {
                         name: "my_table_value_key".to_string(),
                         unique_index_name: "my_table_value_key".to_string(),
+                        definition: "UNIQUE NULLS NOT DISTINCT (value)".to_string(),
                         ..default()
                     })],
                     indices: vec![PostgresIndex {
@@ -340,6 +362,8 @@ async fn table_with_non_distinct_nulls(helper: &TestHelper) {
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Ascending),
                             nulls_order: Some(PostgresIndexNullsOrder::Last),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         predicate: None,
@@ -359,3 +383,101 @@ async fn table_with_non_distinct_nulls(helper: &TestHelper) {
     )
     .await;
 }
+
+#[pg_test(arg(postgres = 12))]
+#[pg_test(arg(postgres = 13))]
+#[pg_test(arg(postgres = 14))]
+#[pg_test(arg(postgres = 15))]
+#[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
+#[pg_test(arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16))]
+async fn index_operator_classes_and_collations(helper: &TestHelper) {
+    tests::test_introspection(
+        helper,
+        r#"
+    create table my_table(
+        name varchar(100) not null,
+        data jsonb not null
+    );
+
+    create index my_table_name_pattern_idx on my_table (name varchar_pattern_ops);
+    create index my_table_name_c_idx on my_table (name collate "C");
+    create index my_table_data_idx on my_table using gin (data jsonb_path_ops);
+    "#,
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![PostgresTable {
+                    name: "my_table".to_string(),
+                    columns: vec![
+                        PostgresColumn {
+                            name: "name".to_string(),
+                            ordinal_position: 1,
+                            is_nullable: false,
+                            data_type: "varchar".to_string(),
+                            data_type_length: Some(100),
+                            ..default()
+                        },
+                        PostgresColumn {
+                            name: "data".to_string(),
+                            ordinal_position: 2,
+                            is_nullable: false,
+                            data_type: "jsonb".to_string(),
+                            ..default()
+                        },
+                    ],
+                    indices: vec![
+                        PostgresIndex {
+                            name: "my_table_data_idx".to_string(),
+                            key_columns: vec![PostgresIndexKeyColumn {
+                                name: "data".to_string(),
+                                ordinal_position: 1,
+                                direction: None,
+                                nulls_order: None,
+                                operator_class: Some("jsonb_path_ops".to_string()),
+                                collation: None,
+                            }],
+                            index_type: "gin".to_string(),
+                            index_constraint_type: PostgresIndexType::Index,
+                            ..default()
+                        },
+                        PostgresIndex {
+                            name: "my_table_name_c_idx".to_string(),
+                            key_columns: vec![PostgresIndexKeyColumn {
+                                name: "name".to_string(),
+                                ordinal_position: 1,
+                                direction: Some(PostgresIndexColumnDirection::Ascending),
+                                nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: Some("\"C\"".to_string()),
+                            }],
+                            index_type: "btree".to_string(),
+                            index_constraint_type: PostgresIndexType::Index,
+                            ..default()
+                        },
+                        PostgresIndex {
+                            name: "my_table_name_pattern_idx".to_string(),
+                            key_columns: vec![PostgresIndexKeyColumn {
+                                name: "name".to_string(),
+                                ordinal_position: 1,
+                                direction: Some(PostgresIndexColumnDirection::Ascending),
+                                nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: Some("varchar_pattern_ops".to_string()),
+                                collation: None,
+                            }],
+                            index_type: "btree".to_string(),
+                            index_constraint_type: PostgresIndexType::Index,
+                            ..default()
+                        },
+                    ],
+                    ..default()
+                }],
+                ..default()
+            }],
+            timescale_support: TimescaleSupport::from_test_helper(helper),
+            ..default()
+        },
+    )
+    .await;
+}
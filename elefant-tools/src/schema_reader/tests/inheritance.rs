@@ -14,6 +14,7 @@ use elefant_test_macros::pg_test;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn inherited_tables(helper: &TestHelper) {
@@ -70,7 +71,7 @@ create table cats(
                         table_type: TableTypeDetails::InheritedTable {
                             parent_tables: vec!["pets".to_string()],
                         },
-                        depends_on: vec![9.into()],
+                        depends_on: vec![9.into(), 10.into()],
                         ..default()
                     },
                     PostgresTable {
@@ -114,7 +115,7 @@ create table cats(
                         table_type: TableTypeDetails::InheritedTable {
                             parent_tables: vec!["pets".to_string()],
                         },
-                        depends_on: vec![9.into()],
+                        depends_on: vec![9.into(), 10.into()],
                         ..default()
                     },
                     PostgresTable {
@@ -149,11 +150,14 @@ create table cats(
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             index_constraint_type: PostgresIndexType::PrimaryKey,
                             ..default()
                         }],
+                        depends_on: vec![10.into()],
                         ..default()
                     },
                 ],
@@ -177,6 +181,7 @@ create table cats(
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn multiple_inheritance(helper: &TestHelper) {
@@ -254,3 +259,90 @@ create table animorph() inherits (animal, human);
     )
     .await;
 }
+
+#[pg_test(arg(postgres = 12))]
+#[pg_test(arg(postgres = 13))]
+#[pg_test(arg(postgres = 14))]
+#[pg_test(arg(postgres = 15))]
+#[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
+#[pg_test(arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16))]
+async fn no_inherit_and_not_valid_checks(helper: &TestHelper) {
+    test_introspection(
+        helper,
+        r#"
+create table pets (
+    name text not null,
+    constraint only_abstract_pets check (false) no inherit
+);
+
+create table dogs(
+    breed text not null
+) inherits (pets);
+
+insert into dogs(name, breed) values ('', 'poodle');
+
+alter table dogs add constraint dogs_name_check check (length(name) > 0) not valid;
+    "#,
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                tables: vec![
+                    PostgresTable {
+                        name: "dogs".to_string(),
+                        columns: vec![
+                            PostgresColumn {
+                                name: "name".to_string(),
+                                ordinal_position: 1,
+                                is_nullable: false,
+                                data_type: "text".to_string(),
+                                ..default()
+                            },
+                            PostgresColumn {
+                                name: "breed".to_string(),
+                                ordinal_position: 2,
+                                is_nullable: false,
+                                data_type: "text".to_string(),
+                                ..default()
+                            },
+                        ],
+                        constraints: vec![PostgresConstraint::Check(PostgresCheckConstraint {
+                            name: "dogs_name_check".to_string(),
+                            check_clause: "((length(name) > 0))".into(),
+                            not_valid: true,
+                            ..default()
+                        })],
+                        table_type: TableTypeDetails::InheritedTable {
+                            parent_tables: vec!["pets".to_string()],
+                        },
+                        depends_on: vec![5.into()],
+                        ..default()
+                    },
+                    PostgresTable {
+                        name: "pets".to_string(),
+                        object_id: 5.into(),
+                        columns: vec![PostgresColumn {
+                            name: "name".to_string(),
+                            ordinal_position: 1,
+                            is_nullable: false,
+                            data_type: "text".to_string(),
+                            ..default()
+                        }],
+                        constraints: vec![PostgresConstraint::Check(PostgresCheckConstraint {
+                            name: "only_abstract_pets".to_string(),
+                            check_clause: "(false)".into(),
+                            no_inherit: true,
+                            ..default()
+                        })],
+                        ..default()
+                    },
+                ],
+                name: "public".to_string(),
+                ..default()
+            }],
+            timescale_support: TimescaleSupport::from_test_helper(helper),
+            ..default()
+        },
+    )
+    .await;
+}
@@ -6,9 +6,12 @@ mod foreign_keys;
 mod functions;
 mod indices;
 mod inheritance;
+mod object_ids;
 mod partitioning;
+mod policies;
 mod respects_permissions;
 mod storage_parameters;
+#[cfg(feature = "timescale")]
 mod timescale;
 mod triggers;
 mod views;
@@ -42,6 +45,7 @@ async fn test_introspection(
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn reads_simple_schema(helper: &TestHelper) {
@@ -103,6 +107,7 @@ async fn reads_simple_schema(helper: &TestHelper) {
                         PostgresConstraint::Unique(PostgresUniqueConstraint {
                             name: "my_table_name_key".to_string(),
                             unique_index_name: "my_table_name_key".to_string(),
+                            definition: "UNIQUE (name)".to_string(),
                             ..default()
                         }),
                     ],
@@ -114,6 +119,8 @@ async fn reads_simple_schema(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -128,6 +135,8 @@ async fn reads_simple_schema(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -144,6 +153,8 @@ async fn reads_simple_schema(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -152,6 +163,7 @@ async fn reads_simple_schema(helper: &TestHelper) {
                             ..default()
                         },
                     ],
+                    depends_on: vec![9.into()],
                     ..default()
                 }],
                 sequences: vec![PostgresSequence {
@@ -179,6 +191,7 @@ async fn reads_simple_schema(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn identity_column_always_generated(helper: &TestHelper) {
@@ -204,6 +217,15 @@ async fn identity_column_always_generated(helper: &TestHelper) {
                             is_nullable: false,
                             data_type: "int4".to_string(),
                             identity: Some(ColumnIdentity::GeneratedAlways),
+                            identity_options: Some(IdentityOptions {
+                                sequence_name: "my_table_id_seq".to_string(),
+                                start: 1,
+                                increment: 1,
+                                min: 1,
+                                max: 2147483647,
+                                cache: 1,
+                                cycle: false,
+                            }),
                             ..default()
                         },
                         PostgresColumn {
@@ -222,6 +244,8 @@ async fn identity_column_always_generated(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -260,6 +284,7 @@ async fn identity_column_always_generated(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn identity_column_by_default(helper: &TestHelper) {
@@ -285,6 +310,15 @@ async fn identity_column_by_default(helper: &TestHelper) {
                             is_nullable: false,
                             data_type: "int4".to_string(),
                             identity: Some(ColumnIdentity::GeneratedByDefault),
+                            identity_options: Some(IdentityOptions {
+                                sequence_name: "my_table_id_seq".to_string(),
+                                start: 1,
+                                increment: 1,
+                                min: 1,
+                                max: 2147483647,
+                                cache: 1,
+                                cycle: false,
+                            }),
                             ..default()
                         },
                         PostgresColumn {
@@ -303,6 +337,8 @@ async fn identity_column_by_default(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -341,6 +377,7 @@ async fn identity_column_by_default(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn identity_column_custom_sequence(helper: &TestHelper) {
@@ -366,6 +403,15 @@ async fn identity_column_custom_sequence(helper: &TestHelper) {
                             is_nullable: false,
                             data_type: "int4".to_string(),
                             identity: Some(ColumnIdentity::GeneratedByDefault),
+                            identity_options: Some(IdentityOptions {
+                                sequence_name: "my_table_id_seq".to_string(),
+                                start: 10,
+                                increment: 10,
+                                min: 1,
+                                max: 2147483647,
+                                cache: 1,
+                                cycle: false,
+                            }),
                             ..default()
                         },
                         PostgresColumn {
@@ -384,6 +430,8 @@ async fn identity_column_custom_sequence(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
@@ -422,6 +470,7 @@ async fn identity_column_custom_sequence(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn table_without_columns(helper: &TestHelper) {
@@ -451,6 +500,7 @@ async fn table_without_columns(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn table_without_primary_key(helper: &TestHelper) {
@@ -499,6 +549,7 @@ async fn table_without_primary_key(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn composite_primary_keys(helper: &TestHelper) {
@@ -556,12 +607,16 @@ async fn composite_primary_keys(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             },
                             PostgresIndexKeyColumn {
                                 name: "id_part_2".to_string(),
                                 ordinal_position: 2,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             },
                         ],
                         index_type: "btree".to_string(),
@@ -586,6 +641,7 @@ async fn composite_primary_keys(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn generated_column(helper: &TestHelper) {
@@ -636,6 +692,7 @@ async fn generated_column(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn test_quoted_identifier_names(helper: &TestHelper) {
@@ -664,6 +721,8 @@ async fn test_quoted_identifier_names(helper: &TestHelper) {
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Ascending),
                             nulls_order: Some(PostgresIndexNullsOrder::Last),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         predicate: None,
@@ -671,6 +730,7 @@ async fn test_quoted_identifier_names(helper: &TestHelper) {
                         index_constraint_type: PostgresIndexType::PrimaryKey,
                         ..default()
                     }],
+                    depends_on: vec![4.into()],
                     ..default()
                 }],
                 sequences: vec![PostgresSequence {
@@ -686,3 +746,91 @@ async fn test_quoted_identifier_names(helper: &TestHelper) {
     )
     .await
 }
+
+#[pg_test(arg(postgres = 15))]
+async fn introspects_only_the_requested_schema(helper: &TestHelper) {
+    helper
+        .execute_not_query(
+            r#"
+        create type mood as enum ('happy', 'sad');
+        create table people(id serial primary key, mood mood);
+
+        create schema other;
+        create type other.mood as enum ('angry');
+        create table other.pets(id serial primary key, owner_id int references public.people(id));
+        create index pets_owner_idx on other.pets(owner_id);
+        create view other.pet_view as select id from other.pets;
+        create function other.pet_count() returns bigint language sql as $$ select count(*) from other.pets $$;
+        "#,
+        )
+        .await;
+
+    let db = SchemaReader::for_schema(helper.get_conn(), "other")
+        .introspect_database()
+        .await
+        .unwrap();
+
+    assert_eq!(db.schemas.len(), 1);
+    let schema = &db.schemas[0];
+    assert_eq!(schema.name, "other");
+    assert_eq!(
+        schema
+            .tables
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["pets"]
+    );
+    assert_eq!(
+        schema.tables[0]
+            .indices
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["pets_owner_idx", "pets_pkey"]
+    );
+    assert!(matches!(
+        schema.tables[0].constraints.as_slice(),
+        [PostgresConstraint::ForeignKey(_)]
+    ));
+    assert_eq!(
+        schema
+            .sequences
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["pets_id_seq"]
+    );
+    assert_eq!(
+        schema
+            .views
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["pet_view"]
+    );
+    assert_eq!(
+        schema
+            .functions
+            .iter()
+            .map(|f| f.function_name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["pet_count"]
+    );
+    assert_eq!(
+        schema
+            .enums
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["mood"]
+    );
+
+    let full = SchemaReader::new(helper.get_conn())
+        .introspect_database()
+        .await
+        .unwrap();
+    let full_schema = full.try_get_schema("other").unwrap();
+    assert_eq!(schema.tables[0].columns, full_schema.tables[0].columns);
+    assert_eq!(schema.enums[0].values, full_schema.enums[0].values);
+}
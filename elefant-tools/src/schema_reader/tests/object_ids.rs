@@ -0,0 +1,86 @@
+use crate::object_id::ObjectIdGenerator;
+use crate::schema_reader::SchemaReader;
+use crate::test_helpers;
+use crate::test_helpers::TestHelper;
+use crate::{ObjectId, PostgresDatabase};
+use elefant_test_macros::pg_test;
+use std::sync::Arc;
+
+const STRUCTURE: &str = r#"
+    create type mood as enum ('happy', 'sad');
+    create table people(id serial primary key, name text, mood mood);
+    create index people_name_idx on people(name);
+    create view people_view as select id, name from people;
+    create function people_count() returns bigint language sql as $$ select count(*) from people $$;
+"#;
+
+fn get_object_ids(database: &PostgresDatabase) -> Vec<ObjectId> {
+    let mut ids = Vec::new();
+
+    for schema in &database.schemas {
+        ids.push(schema.object_id);
+        ids.extend(schema.tables.iter().map(|t| t.object_id));
+        ids.extend(
+            schema
+                .tables
+                .iter()
+                .flat_map(|t| t.indices.iter().map(|i| i.object_id)),
+        );
+        ids.extend(schema.views.iter().map(|v| v.object_id));
+        ids.extend(schema.functions.iter().map(|f| f.object_id));
+        ids.extend(schema.sequences.iter().map(|s| s.object_id));
+        ids.extend(schema.enums.iter().map(|e| e.object_id));
+    }
+
+    ids
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn separate_introspections_reuse_object_ids(first: &TestHelper, second: &TestHelper) {
+    first.execute_not_query(STRUCTURE).await;
+    second.execute_not_query(STRUCTURE).await;
+
+    let first_database = SchemaReader::new(first.get_conn())
+        .introspect_database()
+        .await
+        .unwrap();
+    let second_database = SchemaReader::new(second.get_conn())
+        .introspect_database()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_object_ids(&first_database),
+        get_object_ids(&second_database)
+    );
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn shared_generator_gives_disjoint_object_ids(first: &TestHelper, second: &TestHelper) {
+    first.execute_not_query(STRUCTURE).await;
+    second.execute_not_query(STRUCTURE).await;
+
+    let generator = Arc::new(ObjectIdGenerator::new());
+
+    let first_reader = SchemaReader::with_object_id_generator(first.get_conn(), generator.clone());
+    let second_reader =
+        SchemaReader::with_object_id_generator(second.get_conn(), generator.clone());
+    let (first_database, second_database) = futures::try_join!(
+        first_reader.introspect_database(),
+        second_reader.introspect_database()
+    )
+    .unwrap();
+
+    let first_ids = get_object_ids(&first_database);
+    let second_ids = get_object_ids(&second_database);
+    assert!(first_ids.len() > 5);
+    assert_eq!(first_ids.len(), second_ids.len());
+
+    for id in &first_ids {
+        assert!(
+            !second_ids.contains(id),
+            "{:?} was generated for both databases",
+            id
+        );
+    }
+}
@@ -2,8 +2,10 @@ use crate::schema_reader::tests::test_introspection;
 use crate::test_helpers;
 use crate::test_helpers::TestHelper;
 use crate::{
-    default, PartitionedTableColumns, PostgresColumn, PostgresDatabase, PostgresSchema,
-    PostgresTable, TablePartitionStrategy, TableTypeDetails, TimescaleSupport,
+    default, PartitionedTableColumns, PostgresColumn, PostgresDatabase, PostgresIndex,
+    PostgresIndexColumnDirection, PostgresIndexKeyColumn, PostgresIndexNullsOrder,
+    PostgresSchema, PostgresTable, SchemaReader, TablePartitionStrategy, TableTypeDetails,
+    TimescaleSupport,
 };
 use elefant_test_macros::pg_test;
 
@@ -12,6 +14,7 @@ use elefant_test_macros::pg_test;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn range_partitions(helper: &TestHelper) {
@@ -244,6 +247,7 @@ CREATE TABLE sales_march PARTITION OF sales
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn list_partitions(helper: &TestHelper) {
@@ -444,6 +448,7 @@ CREATE TABLE furniture PARTITION OF products
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn hash_partitions(helper: &TestHelper) {
@@ -641,3 +646,168 @@ CREATE TABLE orders_3 PARTITION OF orders
     )
     .await;
 }
+
+#[pg_test(arg(postgres = 12))]
+#[pg_test(arg(postgres = 13))]
+#[pg_test(arg(postgres = 14))]
+#[pg_test(arg(postgres = 15))]
+#[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
+async fn partitioned_indexes(helper: &TestHelper) {
+    fn columns() -> Vec<PostgresColumn> {
+        vec![
+            PostgresColumn {
+                name: "sale_id".to_string(),
+                is_nullable: true,
+                ordinal_position: 1,
+                data_type: "int4".to_string(),
+                ..default()
+            },
+            PostgresColumn {
+                name: "sale_date".to_string(),
+                is_nullable: true,
+                ordinal_position: 2,
+                data_type: "date".to_string(),
+                ..default()
+            },
+        ]
+    }
+
+    fn index(name: &str) -> PostgresIndex {
+        PostgresIndex {
+            name: name.to_string(),
+            key_columns: vec![PostgresIndexKeyColumn {
+                name: "sale_date".to_string(),
+                ordinal_position: 1,
+                direction: Some(PostgresIndexColumnDirection::Ascending),
+                nulls_order: Some(PostgresIndexNullsOrder::Last),
+                operator_class: None,
+                collation: None,
+            }],
+            index_type: "btree".to_string(),
+            ..default()
+        }
+    }
+
+    test_introspection(
+        helper,
+        r#"
+create table sales (
+    sale_id int,
+    sale_date date
+) partition by range (sale_date);
+
+create table sales_january partition of sales
+    for values from ('2023-01-01') to ('2023-02-01');
+
+create table sales_february partition of sales
+    for values from ('2023-02-01') to ('2023-03-01');
+
+create index sales_sale_date_idx on only sales (sale_date);
+create index sales_january_custom_idx on sales_january (sale_date);
+alter index sales_sale_date_idx attach partition sales_january_custom_idx;
+    "#,
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![
+                    PostgresTable {
+                        name: "sales".to_string(),
+                        object_id: 3.into(),
+                        columns: columns(),
+                        indices: vec![PostgresIndex {
+                            not_valid: true,
+                            ..index("sales_sale_date_idx")
+                        }],
+                        table_type: TableTypeDetails::PartitionedParentTable {
+                            partition_columns: PartitionedTableColumns::Columns(vec![
+                                "sale_date".to_string()
+                            ]),
+                            default_partition_name: None,
+                            partition_strategy: TablePartitionStrategy::Range,
+                        },
+                        ..default()
+                    },
+                    PostgresTable {
+                        name: "sales_february".to_string(),
+                        table_type: TableTypeDetails::PartitionedChildTable {
+                            partition_expression:
+                                "FOR VALUES FROM ('2023-02-01') TO ('2023-03-01')".to_string(),
+                            parent_table: "sales".to_string(),
+                        },
+                        depends_on: vec![3.into()],
+                        columns: columns(),
+                        ..default()
+                    },
+                    PostgresTable {
+                        name: "sales_january".to_string(),
+                        table_type: TableTypeDetails::PartitionedChildTable {
+                            partition_expression:
+                                "FOR VALUES FROM ('2023-01-01') TO ('2023-02-01')".to_string(),
+                            parent_table: "sales".to_string(),
+                        },
+                        depends_on: vec![3.into()],
+                        columns: columns(),
+                        indices: vec![PostgresIndex {
+                            parent_index: Some("sales_sale_date_idx".to_string()),
+                            ..index("sales_january_custom_idx")
+                        }],
+                        ..default()
+                    },
+                ],
+                ..default()
+            }],
+            timescale_support: TimescaleSupport::from_test_helper(helper),
+            ..default()
+        },
+    )
+    .await;
+}
+
+#[pg_test(arg(postgres = 12))]
+#[pg_test(arg(postgres = 17))]
+async fn partitions_inherit_defaults_of_the_parent(helper: &TestHelper) {
+    helper
+        .execute_not_query(
+            r#"
+create table invoices (
+    invoice_id int not null,
+    issued_on date not null
+) partition by range (issued_on);
+
+create table invoices_2023 partition of invoices
+    for values from ('2023-01-01') to ('2024-01-01');
+
+create table invoices_2024 (
+    invoice_id int not null,
+    issued_on date not null,
+    status text not null default 'open'
+);
+
+alter table invoices add column status text not null default 'open';
+alter table invoices attach partition invoices_2024 for values from ('2024-01-01') to ('2025-01-01');
+    "#,
+        )
+        .await;
+
+    let db = SchemaReader::new(helper.get_conn())
+        .introspect_database()
+        .await
+        .unwrap();
+
+    let status = |table: &str| {
+        db.table("public", table)
+            .and_then(|t| t.column("status"))
+            .unwrap()
+            .clone()
+    };
+
+    assert_eq!(
+        status("invoices_2023").default_value.as_deref(),
+        Some("'open'::text")
+    );
+    assert!(status("invoices_2023").inherits_default);
+    // Partitions that were attached rather than created from the parent keep their own columns.
+    assert!(!status("invoices_2024").inherits_default);
+    assert!(!status("invoices").inherits_default);
+}
@@ -0,0 +1,120 @@
+use crate::schema_reader::tests;
+use crate::test_helpers;
+use crate::test_helpers::TestHelper;
+use crate::{
+    default, PostgresColumn, PostgresDatabase, PostgresPolicyCommand,
+    PostgresRowLevelSecurityPolicy, PostgresSchema, PostgresTable, TimescaleSupport,
+};
+use elefant_test_macros::pg_test;
+
+#[pg_test(arg(postgres = 12))]
+#[pg_test(arg(postgres = 13))]
+#[pg_test(arg(postgres = 14))]
+#[pg_test(arg(postgres = 15))]
+#[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
+async fn row_level_security_policies(helper: &TestHelper) {
+    tests::test_introspection(helper, r#"
+        do $$
+            begin
+                create role policy_reader;
+            exception when duplicate_object or unique_violation then null;
+            end
+        $$;
+
+        create table documents(
+            owner text not null,
+            title text not null
+        );
+
+        alter table documents enable row level security;
+        alter table documents force row level security;
+
+        create policy own_documents on documents for select to public using (owner = current_user);
+        create policy titled_documents on documents as restrictive for insert to policy_reader with check (length(title) > 0);
+
+        create table open_documents(
+            title text not null
+        );
+
+        create policy disabled_policy on open_documents using (true);
+    "#, PostgresDatabase {
+        schemas: vec![
+            PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![
+                    PostgresTable {
+                        name: "documents".to_string(),
+                        columns: vec![
+                            PostgresColumn {
+                                name: "owner".to_string(),
+                                ordinal_position: 1,
+                                is_nullable: false,
+                                data_type: "text".to_string(),
+                                ..default()
+                            },
+                            PostgresColumn {
+                                name: "title".to_string(),
+                                ordinal_position: 2,
+                                is_nullable: false,
+                                data_type: "text".to_string(),
+                                ..default()
+                            },
+                        ],
+                        rls_enabled: true,
+                        rls_forced: true,
+                        ..default()
+                    },
+                    PostgresTable {
+                        name: "open_documents".to_string(),
+                        columns: vec![
+                            PostgresColumn {
+                                name: "title".to_string(),
+                                ordinal_position: 1,
+                                is_nullable: false,
+                                data_type: "text".to_string(),
+                                ..default()
+                            },
+                        ],
+                        ..default()
+                    },
+                ],
+                policies: vec![
+                    PostgresRowLevelSecurityPolicy {
+                        name: "own_documents".to_string(),
+                        table_name: "documents".to_string(),
+                        command: PostgresPolicyCommand::Select,
+                        permissive: true,
+                        roles: vec!["public".to_string()],
+                        using_expression: Some("(owner = CURRENT_USER)".to_string()),
+                        check_expression: None,
+                        ..default()
+                    },
+                    PostgresRowLevelSecurityPolicy {
+                        name: "titled_documents".to_string(),
+                        table_name: "documents".to_string(),
+                        command: PostgresPolicyCommand::Insert,
+                        permissive: false,
+                        roles: vec!["policy_reader".to_string()],
+                        using_expression: None,
+                        check_expression: Some("(length(title) > 0)".to_string()),
+                        ..default()
+                    },
+                    PostgresRowLevelSecurityPolicy {
+                        name: "disabled_policy".to_string(),
+                        table_name: "open_documents".to_string(),
+                        command: PostgresPolicyCommand::All,
+                        permissive: true,
+                        roles: vec!["public".to_string()],
+                        using_expression: Some("true".to_string()),
+                        check_expression: None,
+                        ..default()
+                    },
+                ],
+                ..default()
+            }
+        ],
+        timescale_support: TimescaleSupport::from_test_helper(helper),
+        ..default()
+    }).await;
+}
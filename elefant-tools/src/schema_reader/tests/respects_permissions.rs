@@ -4,8 +4,8 @@ use crate::test_helpers::{get_test_connection_full, TestHelper};
 use crate::TableTypeDetails::TimescaleHypertable;
 use crate::ViewOptions::TimescaleContinuousAggregate;
 use crate::{
-    default, PostgresColumn, PostgresDatabase, PostgresSchema, PostgresTable, PostgresView,
-    PostgresViewColumn, TimescaleSupport,
+    default, PostgresColumn, PostgresDatabase, PostgresPrivilege, PostgresSchema, PostgresTable,
+    PostgresView, PostgresViewColumn, TimescaleSupport,
 };
 use crate::{
     test_helpers, HypertableDimension, ObjectId, PostgresIndex, PostgresIndexColumnDirection,
@@ -13,6 +13,14 @@ use crate::{
 };
 use elefant_test_macros::pg_test;
 
+fn grant(grantee: &str, privilege: &str) -> PostgresPrivilege {
+    PostgresPrivilege {
+        grantee: grantee.to_string(),
+        privilege: privilege.to_string(),
+        ..default()
+    }
+}
+
 #[pg_test(arg(postgres = 12))]
 #[pg_test(arg(postgres = 13))]
 #[pg_test(arg(postgres = 14))]
@@ -129,6 +137,7 @@ async fn respects_permissions(helper: &TestHelper) {
                             ..default()
                         }],
                         object_id: 3.into(),
+                        privileges: vec![grant("two_user", "SELECT")],
                         ..default()
                     }],
                     views: vec![PostgresView {
@@ -139,12 +148,24 @@ async fn respects_permissions(helper: &TestHelper) {
                             ordinal_position: 1,
                         }],
                         depends_on: vec![3.into()],
+                        column_dependencies: vec![(3.into(), "id".to_string())],
                         ..default()
                     }],
+                    privileges: vec![
+                        grant("one_user", "CREATE"),
+                        grant("one_user", "USAGE"),
+                        grant("two_user", "USAGE"),
+                    ],
                     ..default()
                 },
                 PostgresSchema {
                     name: "public".to_string(),
+                    privileges: vec![
+                        grant("one_user", "CREATE"),
+                        grant("one_user", "USAGE"),
+                        grant("two_user", "CREATE"),
+                        grant("two_user", "USAGE"),
+                    ],
                     ..default()
                 },
             ],
@@ -285,17 +306,25 @@ async fn hypertable_permissions(helper: &TestHelper) {
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Descending),
                                 nulls_order: Some(PostgresIndexNullsOrder::First),
+                                operator_class: None,
+                                collation: None,
                             }],
                             index_type: "btree".to_string(),
                             predicate: None,
                             included_columns: vec![],
                             index_constraint_type: PostgresIndexType::Index,
                             storage_parameters: vec![],
+                            tablespace: None,
                             comment: None,
+                            not_valid: false,
+                            parent_index: None,
                             object_id: ObjectId::new(3),
+                            depends_on: vec![],
                         }],
                         comment: None,
                         storage_parameters: vec![],
+                        access_method: None,
+                        tablespace: None,
                         table_type: TimescaleHypertable {
                             dimensions: vec![HypertableDimension::Time {
                                 column_name: "time".to_string(),
@@ -307,9 +336,13 @@ async fn hypertable_permissions(helper: &TestHelper) {
                             }],
                             compression: None,
                             retention: None,
+                            chunk_target_size: None,
                         },
                         object_id: ObjectId::new(4),
                         depends_on: vec![],
+                        privileges: vec![grant("ht_two_user", "SELECT")],
+                        rls_enabled: false,
+                        rls_forced: false,
                     }],
                     sequences: vec![],
                     views: vec![PostgresView {
@@ -332,21 +365,38 @@ async fn hypertable_permissions(helper: &TestHelper) {
                         ],
                         comment: None,
                         is_materialized: true,
+                        not_populated: false,
                         view_options: TimescaleContinuousAggregate {
                             refresh: None,
                             compression: None,
-                            retention: None
+                            retention: None,
+                            materialized_only: true,
+                            finalized: true,
                         },
                         object_id: ObjectId::new(5),
                         depends_on: vec![],
+                        column_dependencies: vec![],
+                        indices: vec![],
+                        privileges: vec![],
                     }],
                     name: "ht_one".to_string(),
                     object_id: ObjectId::new(1),
+                    privileges: vec![
+                        grant("ht_one_user", "CREATE"),
+                        grant("ht_one_user", "USAGE"),
+                        grant("ht_two_user", "USAGE"),
+                    ],
                     ..default()
                 },
                 PostgresSchema {
                     name: "public".to_string(),
                     object_id: ObjectId::new(2),
+                    privileges: vec![
+                        grant("ht_one_user", "CREATE"),
+                        grant("ht_one_user", "USAGE"),
+                        grant("ht_two_user", "CREATE"),
+                        grant("ht_two_user", "USAGE"),
+                    ],
                     ..default()
                 }
             ],
@@ -12,6 +12,7 @@ use elefant_test_macros::pg_test;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn index_storage_parameters(helper: &TestHelper) {
@@ -40,6 +41,8 @@ async fn index_storage_parameters(helper: &TestHelper) {
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Ascending),
                             nulls_order: Some(PostgresIndexNullsOrder::Last),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         index_constraint_type: PostgresIndexType::Index,
@@ -89,6 +92,8 @@ async fn index_storage_parameters_pg_12(helper: &TestHelper) {
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Ascending),
                             nulls_order: Some(PostgresIndexNullsOrder::Last),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         index_constraint_type: PostgresIndexType::Index,
@@ -77,12 +77,16 @@ CREATE INDEX ix_symbol_time ON stocks_real_time (symbol, time DESC);
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Ascending),
                                 nulls_order: Some(PostgresIndexNullsOrder::Last),
+                                operator_class: None,
+                                collation: None,
                             },
                             PostgresIndexKeyColumn {
                                 name: "\"time\"".to_string(),
                                 ordinal_position: 2,
                                 direction: Some(PostgresIndexColumnDirection::Descending),
                                 nulls_order: Some(PostgresIndexNullsOrder::First),
+                                operator_class: None,
+                                collation: None,
                             },
                         ],
                         index_type: "btree".to_string(),
@@ -96,6 +100,8 @@ CREATE INDEX ix_symbol_time ON stocks_real_time (symbol, time DESC);
                                 ordinal_position: 1,
                                 direction: Some(PostgresIndexColumnDirection::Descending),
                                 nulls_order: Some(PostgresIndexNullsOrder::First),
+                                operator_class: None,
+                                collation: None,
                             }
                         ],
                         index_type: "btree".to_string(),
@@ -116,10 +122,13 @@ CREATE INDEX ix_symbol_time ON stocks_real_time (symbol, time DESC);
                             HypertableDimension::SpaceInterval {
                                 column_name: "day_volume".to_string(),
                                 integer_interval: 100,
+                                integer_now_func_schema: None,
+                                integer_now_func: None,
                             },
                         ],
                         compression: None,
                         retention: None,
+                        chunk_target_size: None,
                     },
                     ..default()
                 }],
@@ -202,6 +211,8 @@ select add_compression_policy('stocks_real_time', interval '7 days');
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Descending),
                             nulls_order: Some(PostgresIndexNullsOrder::First),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         index_constraint_type: PostgresIndexType::Index,
@@ -232,6 +243,7 @@ select add_compression_policy('stocks_real_time', interval '7 days');
                             compress_after: Some(Interval::new(0, 7, 0)),
                         }),
                         retention: None,
+                        chunk_target_size: None,
                     },
                     ..default()
                 }],
@@ -329,6 +341,8 @@ SELECT add_retention_policy('stock_candlestick_daily', INTERVAL '2 years');
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Descending),
                             nulls_order: Some(PostgresIndexNullsOrder::First),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         index_constraint_type: PostgresIndexType::Index,
@@ -341,6 +355,7 @@ SELECT add_retention_policy('stock_candlestick_daily', INTERVAL '2 years');
                         }],
                         compression: None,
                         retention: None,
+                        chunk_target_size: None,
                     },
                     ..default()
                 }],
@@ -404,6 +419,8 @@ SELECT add_retention_policy('stock_candlestick_daily', INTERVAL '2 years');
                             schedule_interval: Interval::new(0, 1, 0),
                             drop_after: Interval::new(24, 0, 0),
                         }),
+                        materialized_only: true,
+                        finalized: true,
                     },
                     ..default()
                 }],
@@ -500,6 +517,8 @@ SELECT add_retention_policy('stock_candlestick_daily', INTERVAL '2 years');
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Descending),
                             nulls_order: Some(PostgresIndexNullsOrder::First),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         index_constraint_type: PostgresIndexType::Index,
@@ -512,6 +531,7 @@ SELECT add_retention_policy('stock_candlestick_daily', INTERVAL '2 years');
                         }],
                         compression: None,
                         retention: None,
+                        chunk_target_size: None,
                     },
                     ..default()
                 }],
@@ -575,6 +595,8 @@ SELECT add_retention_policy('stock_candlestick_daily', INTERVAL '2 years');
                             schedule_interval: Interval::new(0, 1, 0),
                             drop_after: Interval::new(24, 0, 0),
                         }),
+                        materialized_only: true,
+                        finalized: true,
                     },
                     ..default()
                 }],
@@ -622,6 +644,8 @@ SELECT add_retention_policy('conditions', INTERVAL '24 hours');
                             ordinal_position: 1,
                             direction: Some(PostgresIndexColumnDirection::Descending),
                             nulls_order: Some(PostgresIndexNullsOrder::First),
+                            operator_class: None,
+                            collation: None,
                         }],
                         index_type: "btree".to_string(),
                         index_constraint_type: PostgresIndexType::Index,
@@ -637,6 +661,7 @@ SELECT add_retention_policy('conditions', INTERVAL '24 hours');
                             drop_after: Interval::new(0, 0, 86400000000),
                             schedule_interval: Interval::new(0, 1, 0),
                         }),
+                        chunk_target_size: None,
                     },
                     ..default()
                 }],
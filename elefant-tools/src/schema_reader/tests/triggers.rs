@@ -34,6 +34,7 @@ alter function pg_catalog.tsvector_update_trigger() owner to postgres;
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn triggers(helper: &TestHelper) {
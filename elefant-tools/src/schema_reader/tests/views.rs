@@ -2,8 +2,9 @@ use crate::schema_reader::tests;
 use crate::test_helpers;
 use crate::test_helpers::TestHelper;
 use crate::{
-    default, PostgresColumn, PostgresDatabase, PostgresSchema, PostgresTable, PostgresView,
-    PostgresViewColumn, TimescaleSupport,
+    default, PostgresColumn, PostgresDatabase, PostgresIndex, PostgresIndexColumnDirection,
+    PostgresIndexKeyColumn, PostgresIndexNullsOrder, PostgresIndexType, PostgresSchema,
+    PostgresTable, PostgresView, PostgresViewColumn, TimescaleSupport,
 };
 use elefant_test_macros::pg_test;
 
@@ -48,6 +49,7 @@ async fn test_views(helper: &TestHelper) {
                         ordinal_position: 1,
                     }],
                     depends_on: vec![2.into()],
+                    column_dependencies: vec![(2.into(), "name".to_string())],
                     is_materialized: false,
                     ..default()
                 }],
@@ -61,6 +63,7 @@ async fn test_views(helper: &TestHelper) {
 }
 
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 16))]
 async fn test_views_pg_16(helper: &TestHelper) {
     tests::test_introspection(
@@ -98,6 +101,7 @@ async fn test_views_pg_16(helper: &TestHelper) {
                         ordinal_position: 1,
                     }],
                     depends_on: vec![2.into()],
+                    column_dependencies: vec![(2.into(), "name".to_string())],
                     is_materialized: false,
                     ..default()
                 }],
@@ -115,6 +119,7 @@ async fn test_views_pg_16(helper: &TestHelper) {
 #[pg_test(arg(postgres = 14))]
 #[pg_test(arg(postgres = 15))]
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16))]
 async fn materialized_view(helper: &TestHelper) {
@@ -145,6 +150,59 @@ async fn materialized_view(helper: &TestHelper) {
     .await;
 }
 
+#[pg_test(arg(postgres = 12))]
+#[pg_test(arg(postgres = 13))]
+#[pg_test(arg(postgres = 14))]
+#[pg_test(arg(postgres = 15))]
+#[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
+#[pg_test(arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16))]
+async fn materialized_view_with_index(helper: &TestHelper) {
+    tests::test_introspection(
+        helper,
+        r#"
+        create materialized view my_view as select 1 as value;
+        create unique index my_view_value_idx on my_view (value);
+    "#,
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                views: vec![PostgresView {
+                    name: "my_view".to_string(),
+                    definition: "SELECT 1 AS value;".into(),
+                    columns: vec![PostgresViewColumn {
+                        name: "value".to_string(),
+                        ordinal_position: 1,
+                    }],
+                    is_materialized: true,
+                    indices: vec![PostgresIndex {
+                        name: "my_view_value_idx".to_string(),
+                        key_columns: vec![PostgresIndexKeyColumn {
+                            name: "value".to_string(),
+                            ordinal_position: 1,
+                            direction: Some(PostgresIndexColumnDirection::Ascending),
+                            nulls_order: Some(PostgresIndexNullsOrder::Last),
+                            operator_class: None,
+                            collation: None,
+                        }],
+                        index_type: "btree".to_string(),
+                        index_constraint_type: PostgresIndexType::Unique {
+                            nulls_distinct: true,
+                        },
+                        ..default()
+                    }],
+                    ..default()
+                }],
+                ..default()
+            }],
+            timescale_support: TimescaleSupport::from_test_helper(helper),
+            ..default()
+        },
+    )
+    .await;
+}
+
 #[pg_test(arg(postgres = 12))]
 #[pg_test(arg(postgres = 13))]
 #[pg_test(arg(postgres = 14))]
@@ -184,6 +242,7 @@ async fn view_depends_15_below(helper: &TestHelper) {
                         }],
                         is_materialized: true,
                         depends_on: vec![2.into()],
+                        column_dependencies: vec![(2.into(), "value".to_string())],
                         ..default()
                     },
                 ],
@@ -197,6 +256,7 @@ async fn view_depends_15_below(helper: &TestHelper) {
 }
 
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 16))]
 async fn view_depends_16(helper: &TestHelper) {
     tests::test_introspection(
@@ -232,6 +292,7 @@ async fn view_depends_16(helper: &TestHelper) {
                         }],
                         is_materialized: true,
                         depends_on: vec![2.into()],
+                        column_dependencies: vec![(2.into(), "value".to_string())],
                         ..default()
                     },
                 ],
@@ -272,6 +333,7 @@ async fn view_depends_15_below_opposite(helper: &TestHelper) {
                         }],
                         is_materialized: true,
                         depends_on: vec![3.into()],
+                        column_dependencies: vec![(3.into(), "value".to_string())],
                         ..default()
                     },
                     PostgresView {
@@ -296,6 +358,7 @@ async fn view_depends_15_below_opposite(helper: &TestHelper) {
 }
 
 #[pg_test(arg(postgres = 16))]
+#[pg_test(arg(postgres = 17))]
 #[pg_test(arg(timescale_db = 16))]
 async fn view_depends_16_opposite(helper: &TestHelper) {
     tests::test_introspection(
@@ -320,6 +383,7 @@ async fn view_depends_16_opposite(helper: &TestHelper) {
                         }],
                         is_materialized: true,
                         depends_on: vec![3.into()],
+                        column_dependencies: vec![(3.into(), "value".to_string())],
                         ..default()
                     },
                     PostgresView {
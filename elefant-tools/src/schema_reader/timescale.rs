@@ -0,0 +1,257 @@
+use crate::models::*;
+use crate::object_id::ObjectIdGenerator;
+use crate::schema_reader::table::TablesResult;
+use crate::schema_reader::timescale_continuous_aggregate::ContinuousAggregateResult;
+use crate::schema_reader::timescale_hypertable::HypertableResult;
+use crate::schema_reader::timescale_hypertable_dimension::TimescaleHypertableDimensionResult;
+use crate::schema_reader::timescale_job::TimescaleJobResult;
+use crate::schema_reader::trigger::TriggerResult;
+use crate::schema_reader::view::ViewResult;
+use crate::schema_reader::SchemaReader;
+use crate::{ElefantToolsError, Result};
+use futures::try_join;
+
+/// The timescale specific parts of a database, read when the timescaledb extension is installed.
+#[derive(Default)]
+pub(super) struct TimescaleDefinitions {
+    hypertables: Vec<HypertableResult>,
+    hypertable_dimensions: Vec<TimescaleHypertableDimensionResult>,
+    continuous_aggregates: Vec<ContinuousAggregateResult>,
+    jobs: Vec<TimescaleJobResult>,
+}
+
+impl SchemaReader<'_> {
+    pub(super) async fn get_timescale_definitions(
+        &self,
+        timescale_support: &TimescaleSupport,
+    ) -> Result<TimescaleDefinitions> {
+        if !timescale_support.is_enabled {
+            return Ok(TimescaleDefinitions::default());
+        }
+
+        let (hypertables, hypertable_dimensions, continuous_aggregates, jobs) = try_join!(
+            self.get_hypertables(),
+            self.get_hypertable_dimensions(),
+            self.get_continuous_aggregates(),
+            self.get_timescale_jobs()
+        )?;
+
+        Ok(TimescaleDefinitions {
+            hypertables,
+            hypertable_dimensions,
+            continuous_aggregates,
+            jobs,
+        })
+    }
+}
+
+impl TimescaleDefinitions {
+    /// The table type of the table, if it is a hypertable.
+    pub(super) fn get_hypertable(&self, row: &TablesResult) -> Result<Option<TableTypeDetails>> {
+        let Some(hypertable) = self
+            .hypertables
+            .iter()
+            .find(|h| h.table_name == row.table_name && h.table_schema == row.schema_name)
+        else {
+            return Ok(None);
+        };
+
+        let mut dimensions = vec![];
+
+        for dim in self.hypertable_dimensions.iter() {
+            if dim.table_name == row.table_name && dim.table_schema == row.schema_name {
+                let dim = if let Some(interval) = dim.time_interval {
+                    HypertableDimension::Time {
+                        column_name: dim.column_name.clone(),
+                        time_interval: interval,
+                    }
+                } else if let Some(interval) = dim.integer_interval {
+                    HypertableDimension::SpaceInterval {
+                        column_name: dim.column_name.clone(),
+                        integer_interval: interval,
+                        integer_now_func_schema: dim.integer_now_func_schema.clone(),
+                        integer_now_func: dim.integer_now_func.clone(),
+                    }
+                } else if let Some(num_partitions) = dim.num_partitions {
+                    HypertableDimension::SpacePartitions {
+                        column_name: dim.column_name.clone(),
+                        num_partitions,
+                    }
+                } else {
+                    return Err(ElefantToolsError::HypertableDimensionWithoutInterval {
+                        table_name: row.table_name.clone(),
+                        dimension_number: dim.dimension_number,
+                    });
+                };
+
+                dimensions.push(dim);
+            }
+        }
+
+        let compression = if let (false, None, None, None, None, None) = (
+            hypertable.compression_enabled,
+            hypertable.compress_after,
+            hypertable.compression_chunk_interval,
+            hypertable.compression_schedule_interval,
+            &hypertable.compress_segment_by,
+            &hypertable.compress_order_by,
+        ) {
+            None
+        } else {
+            Some(HypertableCompression {
+                enabled: hypertable.compression_enabled,
+                compression_schedule_interval: hypertable.compression_schedule_interval,
+                chunk_time_interval: hypertable.compression_chunk_interval,
+                compress_after: hypertable.compress_after,
+                order_by_columns: get_hypertable_compression_order_by_columns(
+                    &hypertable.compress_order_by,
+                    &hypertable.compress_order_by_desc,
+                    &hypertable.compress_order_by_nulls_first,
+                ),
+                segment_by_columns: hypertable.compress_segment_by.clone(),
+            })
+        };
+
+        let retention = hypertable
+            .retention_schedule_interval
+            .zip(hypertable.retention_drop_after)
+            .map(|(schedule_interval, drop_after)| HypertableRetention {
+                schedule_interval,
+                drop_after,
+            });
+
+        Ok(Some(TableTypeDetails::TimescaleHypertable {
+            dimensions,
+            compression,
+            retention,
+            chunk_target_size: Some(hypertable.chunk_target_size).filter(|size| *size > 0),
+        }))
+    }
+
+    /// The definition and options of the view, if it is a continuous aggregate.
+    pub(super) fn get_continuous_aggregate(
+        &self,
+        view: &ViewResult,
+    ) -> Option<(&String, ViewOptions)> {
+        let ca = self
+            .continuous_aggregates
+            .iter()
+            .find(|c| c.view_name == view.view_name && c.view_schema == view.schema_name)?;
+
+        let refresh = if let (Some(refresh), Some(start), Some(end)) = (
+            ca.refresh_interval,
+            ca.refresh_start_offset,
+            ca.refresh_end_offset,
+        ) {
+            Some(TimescaleContinuousAggregateRefreshOptions {
+                interval: refresh,
+                start_offset: start,
+                end_offset: end,
+            })
+        } else {
+            None
+        };
+
+        let compression = if let (false, None, None, None, None, None) = (
+            ca.compression_enabled,
+            ca.compress_after,
+            ca.compress_job_interval,
+            &ca.compress_segment_by,
+            &ca.compress_order_by,
+            &ca.compress_chunk_time_interval,
+        ) {
+            None
+        } else {
+            Some(HypertableCompression {
+                enabled: ca.compression_enabled,
+                compression_schedule_interval: ca.compress_job_interval,
+                chunk_time_interval: ca.compress_chunk_time_interval,
+                compress_after: ca.compress_after,
+                order_by_columns: get_hypertable_compression_order_by_columns(
+                    &ca.compress_order_by,
+                    &ca.compress_order_by_desc,
+                    &ca.compress_order_by_nulls_first,
+                ),
+                segment_by_columns: ca.compress_segment_by.clone(),
+            })
+        };
+
+        let retention = if let (Some(schedule_interval), Some(drop_after)) =
+            (ca.retention_schedule_interval, ca.retention_drop_after)
+        {
+            Some(HypertableRetention {
+                schedule_interval,
+                drop_after,
+            })
+        } else {
+            None
+        };
+
+        Some((
+            &ca.view_definition,
+            ViewOptions::TimescaleContinuousAggregate {
+                refresh,
+                compression,
+                retention,
+                materialized_only: ca.materialized_only,
+                finalized: ca.finalized,
+            },
+        ))
+    }
+
+    /// If the trigger is one of the triggers TimescaleDB creates on hypertables itself.
+    pub(super) fn is_internal_trigger(&self, trigger: &TriggerResult) -> bool {
+        (trigger.name == "ts_insert_blocker" || trigger.name == "ts_cagg_invalidation_trigger")
+            && self.hypertables.iter().any(|h| {
+                h.table_name == trigger.table_name && h.table_schema == trigger.schema_name
+            })
+    }
+
+    pub(super) fn add_jobs(
+        &self,
+        db: &mut PostgresDatabase,
+        object_id_generator: &ObjectIdGenerator,
+    ) {
+        for timescale_job in &self.jobs {
+            db.timescale_support
+                .user_defined_jobs
+                .push(TimescaleDbUserDefinedJob {
+                    function_name: timescale_job.function_name.clone(),
+                    function_schema: timescale_job.function_schema.clone(),
+                    check_config_name: timescale_job.check_config_name.clone(),
+                    check_config_schema: timescale_job.check_config_schema.clone(),
+                    schedule_interval: timescale_job.schedule_interval,
+                    fixed_schedule: timescale_job.fixed_schedule,
+                    config: timescale_job.config.clone().map(|c| c.into()),
+                    scheduled: timescale_job.scheduled,
+                    object_id: object_id_generator.next(),
+                })
+        }
+    }
+}
+
+fn get_hypertable_compression_order_by_columns(
+    compress_order_by: &Option<Vec<String>>,
+    compress_order_by_desc: &Option<Vec<bool>>,
+    compress_order_by_nulls_first: &Option<Vec<bool>>,
+) -> Option<Vec<HypertableCompressionOrderedColumn>> {
+    if let (Some(order_by), Some(desc), Some(nulls_first)) = (
+        &compress_order_by,
+        &compress_order_by_desc,
+        &compress_order_by_nulls_first,
+    ) {
+        let cols = itertools::izip!(order_by, desc, nulls_first)
+            .map(
+                |(column, desc, nulls_first)| HypertableCompressionOrderedColumn {
+                    column_name: column.clone(),
+                    descending: *desc,
+                    nulls_first: *nulls_first,
+                },
+            )
+            .collect();
+
+        Some(cols)
+    } else {
+        None
+    }
+}
@@ -8,7 +8,7 @@ pub struct ContinuousAggregateResult {
     // pub hypertable_name: String,
     pub view_schema: String,
     pub view_name: String,
-    // pub materialized_only: bool,
+    pub materialized_only: bool,
     pub view_definition: String,
     pub refresh_interval: Option<Interval>,
     pub refresh_start_offset: Option<Interval>,
@@ -23,6 +23,7 @@ pub struct ContinuousAggregateResult {
     pub compress_chunk_time_interval: Option<Interval>,
     pub retention_schedule_interval: Option<Interval>,
     pub retention_drop_after: Option<Interval>,
+    pub finalized: bool,
 }
 
 impl FromRow for ContinuousAggregateResult {
@@ -32,7 +33,7 @@ impl FromRow for ContinuousAggregateResult {
             // hypertable_name: row.try_get(1)?,
             view_schema: row.try_get(2)?,
             view_name: row.try_get(3)?,
-            // materialized_only: row.try_get(4)?,
+            materialized_only: row.try_get(4)?,
             view_definition: row.try_get(5)?,
             refresh_interval: row.try_get(6)?,
             refresh_start_offset: row.try_get(7)?,
@@ -47,6 +48,7 @@ impl FromRow for ContinuousAggregateResult {
             compress_chunk_time_interval: row.try_get(16)?,
             retention_schedule_interval: row.try_get(17)?,
             retention_drop_after: row.try_get(18)?,
+            finalized: row.try_get(19)?,
         })
     }
 }
@@ -55,6 +57,7 @@ impl FromRow for ContinuousAggregateResult {
 define_working_query!(
     get_continuous_aggregates,
     ContinuousAggregateResult,
+    schema_column = "view_schema",
     r#"
 
 SELECT ht.schema_name                                       AS hypertable_schema,
@@ -78,7 +81,8 @@ SELECT ht.schema_name                                       AS hypertable_schema
        cs.segmentby                                         as compress_segmentby,
         _timescaledb_functions.to_interval(dim.compress_interval_length) as compress_chunk_time_interval,
         retention_job.schedule_interval as retention_schedule_interval,
-        (retention_job.config->>'drop_after')::interval as retention_drop_after
+        (retention_job.config->>'drop_after')::interval as retention_drop_after,
+        cagg.finalized
 FROM _timescaledb_catalog.continuous_agg cagg
          join _timescaledb_catalog.hypertable ht on cagg.raw_hypertable_id = ht.id
          join _timescaledb_catalog.hypertable mat_ht on cagg.mat_hypertable_id = mat_ht.id
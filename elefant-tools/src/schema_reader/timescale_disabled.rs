@@ -0,0 +1,49 @@
+use crate::models::*;
+use crate::object_id::ObjectIdGenerator;
+use crate::schema_reader::table::TablesResult;
+use crate::schema_reader::trigger::TriggerResult;
+use crate::schema_reader::view::ViewResult;
+use crate::schema_reader::SchemaReader;
+use crate::{ElefantToolsError, Result};
+
+/// Stands in for the timescale specific parts of a database when elefant-tools is compiled
+/// without the `timescale` feature. Databases with the timescaledb extension cannot be read,
+/// as their hypertables would otherwise be read as plain tables.
+pub(super) struct TimescaleDefinitions;
+
+impl SchemaReader<'_> {
+    pub(super) async fn get_timescale_definitions(
+        &self,
+        timescale_support: &TimescaleSupport,
+    ) -> Result<TimescaleDefinitions> {
+        if timescale_support.is_enabled {
+            Err(ElefantToolsError::TimescaleSupportDisabled)
+        } else {
+            Ok(TimescaleDefinitions)
+        }
+    }
+}
+
+impl TimescaleDefinitions {
+    pub(super) fn get_hypertable(&self, _row: &TablesResult) -> Result<Option<TableTypeDetails>> {
+        Ok(None)
+    }
+
+    pub(super) fn get_continuous_aggregate(
+        &self,
+        _view: &ViewResult,
+    ) -> Option<(&String, ViewOptions)> {
+        None
+    }
+
+    pub(super) fn is_internal_trigger(&self, _trigger: &TriggerResult) -> bool {
+        false
+    }
+
+    pub(super) fn add_jobs(
+        &self,
+        _db: &mut PostgresDatabase,
+        _object_id_generator: &ObjectIdGenerator,
+    ) {
+    }
+}
@@ -16,6 +16,7 @@ pub struct HypertableResult {
     pub compress_segment_by: Option<Vec<String>>,
     pub retention_schedule_interval: Option<Interval>,
     pub retention_drop_after: Option<Interval>,
+    pub chunk_target_size: i64,
 }
 
 impl FromRow for HypertableResult {
@@ -33,6 +34,7 @@ impl FromRow for HypertableResult {
             compress_segment_by: row.try_get(9)?,
             retention_schedule_interval: row.try_get(10)?,
             retention_drop_after: row.try_get(11)?,
+            chunk_target_size: row.try_get(12)?,
         })
     }
 }
@@ -41,6 +43,7 @@ impl FromRow for HypertableResult {
 define_working_query!(
     get_hypertables,
     HypertableResult,
+    schema_column = "schema_name",
     r#"
 select ht.schema_name,
          ht.table_name,
@@ -53,7 +56,8 @@ select ht.schema_name,
         cs.orderby_nullsfirst,
         cs.segmentby,
         retention_job.schedule_interval as retention_schedule_interval,
-        (retention_job.config->>'drop_after')::interval as retention_drop_after
+        (retention_job.config->>'drop_after')::interval as retention_drop_after,
+        ht.chunk_target_size
 
 from (select * from _timescaledb_catalog.hypertable ht
                join pg_catalog.pg_namespace n on ht.schema_name = n.nspname
@@ -11,6 +11,8 @@ pub struct TimescaleHypertableDimensionResult {
     pub time_interval: Option<Interval>,
     pub integer_interval: Option<i64>,
     pub num_partitions: Option<i16>,
+    pub integer_now_func_schema: Option<String>,
+    pub integer_now_func: Option<String>,
 }
 
 impl FromRow for TimescaleHypertableDimensionResult {
@@ -23,6 +25,8 @@ impl FromRow for TimescaleHypertableDimensionResult {
             time_interval: row.try_get(4)?,
             integer_interval: row.try_get(5)?,
             num_partitions: row.try_get(6)?,
+            integer_now_func_schema: row.try_get(7)?,
+            integer_now_func: row.try_get(8)?,
         })
     }
 }
@@ -31,6 +35,7 @@ impl FromRow for TimescaleHypertableDimensionResult {
 define_working_query!(
     get_hypertable_dimensions,
     TimescaleHypertableDimensionResult,
+    schema_column = "hypertable_schema",
     r#"
 select h.hypertable_schema,
        h.hypertable_name,
@@ -38,8 +43,14 @@ select h.hypertable_schema,
        h.column_name,
        h.time_interval,
        h.integer_interval,
-       h.num_partitions
+       h.num_partitions,
+       d.integer_now_func_schema::text,
+       d.integer_now_func::text
 from timescaledb_information.dimensions h
+         join _timescaledb_catalog.hypertable ht
+              on ht.schema_name = h.hypertable_schema and ht.table_name = h.hypertable_name
+         join _timescaledb_catalog.dimension d
+              on d.hypertable_id = ht.id and d.column_name = h.column_name
 order by h.hypertable_schema, h.hypertable_name, h.dimension_number
 "#
 );
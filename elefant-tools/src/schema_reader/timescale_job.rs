@@ -33,6 +33,7 @@ impl FromRow for TimescaleJobResult {
 define_working_query!(
     get_timescale_jobs,
     TimescaleJobResult,
+    schema_column = "proc_schema",
     r#"
 select job.proc_name,
        job.proc_schema,
@@ -71,6 +71,7 @@ impl FromRow for TriggerResult {
 define_working_query!(
     get_triggers,
     TriggerResult,
+    schema_column = "trigger_schema",
     r#"
 SELECT n.nspname     AS trigger_schema,
        t.tgname      AS trigger_name,
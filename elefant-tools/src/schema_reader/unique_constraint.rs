@@ -8,6 +8,7 @@ pub struct UniqueConstraintResult {
     pub table_name: String,
     pub constraint_name: String,
     pub index_name: String,
+    pub definition: String,
     pub comment: Option<String>,
 }
 
@@ -18,7 +19,8 @@ impl FromRow for UniqueConstraintResult {
             table_name: row.try_get(1)?,
             constraint_name: row.try_get(2)?,
             index_name: row.try_get(3)?,
-            comment: row.try_get(4)?,
+            definition: row.try_get(4)?,
+            comment: row.try_get(5)?,
         })
     }
 }
@@ -27,11 +29,13 @@ impl FromRow for UniqueConstraintResult {
 define_working_query!(
     get_unique_constraints,
     UniqueConstraintResult,
+    schema_column = "table_schema",
     r#"
 select ns.nspname                                     as table_schema,
        cl.relname                                     as table_name,
        con.conname                                     as constraint_name,
        index_class.relname                            as index_name,
+       pg_get_constraintdef(con.oid)                  as definition,
          d.description                                  as comment
 from pg_constraint con
          join pg_class cl on cl.oid = con.conrelid
@@ -42,6 +46,7 @@ from pg_constraint con
          left join pg_depend dep on dep.objid = ns.oid
 where con.oid > 16384
   and con.contype = 'u'
+  and con.conparentid = 0
   and (dep.objid is null or dep.deptype <> 'e' )
 order by ns.nspname, cl.relname, con.conname;
 "#
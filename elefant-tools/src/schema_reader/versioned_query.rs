@@ -0,0 +1,64 @@
+use crate::postgres_client_wrapper::FromRow;
+use crate::schema_reader::SchemaReader;
+use crate::Result;
+
+/// A catalog query that has to be written differently depending on the version of the server,
+/// for example because a catalog column only exists in newer versions.
+///
+/// Each variant has the minimum version it works with, in the format of
+/// [crate::PostgresClientWrapper::version], so `150` for Postgres 15. The variants are ordered
+/// from the newest version to the oldest, and the first one the server is new enough for is used.
+pub(in crate::schema_reader) struct VersionedQuery {
+    variants: &'static [(i32, &'static str)],
+}
+
+impl VersionedQuery {
+    /// Creates a new query from `(minimum version, sql)` variants. The last variant should have a
+    /// minimum version of `0`, so there is a query for every version.
+    pub(in crate::schema_reader) const fn new(variants: &'static [(i32, &'static str)]) -> Self {
+        Self { variants }
+    }
+
+    /// The sql to run on a server with the specified version.
+    pub(in crate::schema_reader) fn for_version(&self, version: i32) -> &'static str {
+        self.variants
+            .iter()
+            .find(|(minimum_version, _)| version >= *minimum_version)
+            .or(self.variants.last())
+            .map(|(_, sql)| *sql)
+            .expect("A versioned query needs at least one variant")
+    }
+}
+
+impl SchemaReader<'_> {
+    /// Runs the variant of the query that matches the version of the server, restricted to the
+    /// schema of the reader by the `schema_column` of the result.
+    pub(in crate::schema_reader) async fn get_versioned_results<T: FromRow>(
+        &self,
+        query: &VersionedQuery,
+        schema_column: &str,
+    ) -> Result<Vec<T>> {
+        let sql = query.for_version(self.connection.version());
+        self.connection
+            .get_results(&self.scope_query(sql, schema_column))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUERY: VersionedQuery =
+        VersionedQuery::new(&[(170, "seventeen"), (150, "fifteen"), (0, "oldest")]);
+
+    #[test]
+    fn picks_the_newest_variant_the_server_supports() {
+        assert_eq!(QUERY.for_version(180), "seventeen");
+        assert_eq!(QUERY.for_version(170), "seventeen");
+        assert_eq!(QUERY.for_version(160), "fifteen");
+        assert_eq!(QUERY.for_version(150), "fifteen");
+        assert_eq!(QUERY.for_version(140), "oldest");
+        assert_eq!(QUERY.for_version(120), "oldest");
+    }
+}
@@ -11,6 +11,9 @@ pub struct ViewResult {
     pub is_materialized: bool,
     pub depends_on: Option<Vec<i64>>,
     pub type_oid: i64,
+    pub column_dependency_oids: Option<Vec<i64>>,
+    pub column_dependency_names: Option<Vec<String>>,
+    pub not_populated: bool,
 }
 
 impl FromRow for ViewResult {
@@ -24,6 +27,9 @@ impl FromRow for ViewResult {
             is_materialized: row.try_get(5)?,
             depends_on: row.try_get(6)?,
             type_oid: row.try_get(7)?,
+            column_dependency_oids: row.try_get(8)?,
+            column_dependency_names: row.try_get(9)?,
+            not_populated: row.try_get(10)?,
         })
     }
 }
@@ -32,6 +38,7 @@ impl FromRow for ViewResult {
 define_working_query!(
     get_views,
     ViewResult,
+    schema_column = "schema_name",
     r#"
 select tab.oid::int8,
     tab.relname                   as view_name,
@@ -44,9 +51,22 @@ select tab.oid::int8,
                  join pg_depend dep on rew.oid = dep.objid
                  join pg_class source_view on dep.refobjid = source_view.oid and source_view.oid <> tab.oid
         where rew.ev_class = tab.oid) as depends_on,
-    tab.reltype::int8
+    tab.reltype::int8,
+    col_dep.table_oids,
+    col_dep.column_names,
+    not tab.relispopulated        as not_populated
 from pg_class tab
          join pg_namespace ns on tab.relnamespace = ns.oid
+         left join lateral (select array_agg(d.refobjid::int8 order by d.refobjid, att.attnum) as table_oids,
+                                   array_agg(att.attname::text order by d.refobjid, att.attnum) as column_names
+                            from (select distinct dep.refobjid, dep.refobjsubid
+                                  from pg_rewrite rew
+                                           join pg_depend dep on rew.oid = dep.objid
+                                  where rew.ev_class = tab.oid
+                                    and dep.refclassid = 'pg_class'::regclass
+                                    and dep.refobjsubid > 0
+                                    and dep.refobjid <> tab.oid) d
+                                     join pg_attribute att on att.attrelid = d.refobjid and att.attnum = d.refobjsubid) col_dep on true
          left join pg_description des on des.objoid = tab.oid
          left join pg_depend dep on dep.objid = ns.oid
 where tab.oid > 16384
@@ -26,6 +26,7 @@ impl FromRow for ViewColumnResult {
 define_working_query!(
     get_view_columns,
     ViewColumnResult,
+    schema_column = "schema_name",
     r#"
 select tab.relname  as view_name,
        ns.nspname   as schema_name,
@@ -0,0 +1,294 @@
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::{IdentifierQuoter, Quotable};
+use crate::storage::CopySource;
+use crate::{
+    ElefantToolsError, PostgresConstraint, PostgresForeignKey, PostgresSchema, PostgresTable,
+    Result,
+};
+use itertools::Itertools;
+use tracing::warn;
+
+/// Orders the rows of a table with a foreign key to itself, so parents come before their
+/// children. See [crate::CopyDataOptions::order_self_referencing].
+///
+/// Rows where any of the foreign key columns are null, or which references themselves, are
+/// the roots. The rest of the rows are ordered by how far they are from a root.
+pub(crate) struct SelfReferenceOrdering<'a> {
+    schema: &'a PostgresSchema,
+    table: &'a PostgresTable,
+    foreign_key: &'a PostgresForeignKey,
+}
+
+impl<'a> SelfReferenceOrdering<'a> {
+    /// Returns `None` if the table has no foreign key to itself. Fails if the table has more
+    /// than one, as the rows are only ordered by a single foreign key.
+    pub(crate) fn find(
+        schema: &'a PostgresSchema,
+        table: &'a PostgresTable,
+    ) -> Result<Option<Self>> {
+        let foreign_keys = table
+            .constraints
+            .iter()
+            .filter_map(|c| match c {
+                PostgresConstraint::ForeignKey(fk)
+                    if fk.referenced_table == table.name
+                        && fk
+                            .referenced_schema
+                            .as_ref()
+                            .is_none_or(|s| *s == schema.name) =>
+                {
+                    Some(fk)
+                }
+                _ => None,
+            })
+            .collect_vec();
+
+        match foreign_keys.as_slice() {
+            [] => Ok(None),
+            [foreign_key] => Ok(Some(SelfReferenceOrdering {
+                schema,
+                table,
+                foreign_key,
+            })),
+            _ => Err(ElefantToolsError::MultipleSelfReferences {
+                schema_name: schema.name.clone(),
+                table_name: table.name.clone(),
+                foreign_key_names: foreign_keys.iter().map(|fk| &fk.name).join(", "),
+            }),
+        }
+    }
+
+    /// A select of the writable columns of the table, with parents before their children.
+    pub(crate) fn get_ordered_select(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        let columns = self
+            .table
+            .get_writable_columns()
+            .map(|c| format!("t.{}", c.name.quote(identifier_quoter, ColumnName)))
+            .join(", ");
+
+        format!(
+            "{} select {} from {} t join ordered o on o.row_id = t.ctid order by o.depth",
+            self.get_ordered_cte(identifier_quoter),
+            columns,
+            self.get_relation_name(identifier_quoter)
+        )
+    }
+
+    /// Fails if some of the rows cannot be reached from a root, because their parents
+    /// reference each other in a cycle. Such rows cannot be inserted one at a time.
+    ///
+    /// Only warns if the source cannot run queries.
+    pub(crate) async fn check_for_cycles<S: CopySource>(
+        &self,
+        source: &S,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Result<()> {
+        let sql = format!(
+            "{} select count(*)::text from {} t where t.ctid not in (select row_id from ordered)",
+            self.get_ordered_cte(identifier_quoter),
+            self.get_relation_name(identifier_quoter)
+        );
+
+        let Some(rows) = source.get_text_rows(&sql).await? else {
+            warn!(
+                "The source cannot run queries, so table {}.{} is not checked for rows referencing each other in a cycle",
+                self.schema.name, self.table.name
+            );
+            return Ok(());
+        };
+
+        let cyclic_rows = rows
+            .first()
+            .and_then(|r| r.first())
+            .and_then(|c| c.as_deref())
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(0);
+
+        if cyclic_rows > 0 {
+            return Err(ElefantToolsError::SelfReferenceCycle {
+                schema_name: self.schema.name.clone(),
+                table_name: self.table.name.clone(),
+                foreign_key_name: self.foreign_key.name.clone(),
+                rows: cyclic_rows,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn get_relation_name(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        format!(
+            "{}.{}",
+            self.schema.name.quote(identifier_quoter, ColumnName),
+            self.table.name.quote(identifier_quoter, ColumnName)
+        )
+    }
+
+    /// A recursive cte named `ordered`, with the `ctid` of each row reachable from a root as
+    /// `row_id`, and the distance to the root as `depth`.
+    fn get_ordered_cte(&self, identifier_quoter: &IdentifierQuoter) -> String {
+        let columns = self
+            .foreign_key
+            .columns
+            .iter()
+            .sorted_by_key(|c| c.ordinal_position)
+            .map(|c| c.name.quote(identifier_quoter, ColumnName))
+            .collect_vec();
+        let referenced_columns = self
+            .foreign_key
+            .referenced_columns
+            .iter()
+            .sorted_by_key(|c| c.ordinal_position)
+            .map(|c| c.name.quote(identifier_quoter, ColumnName))
+            .collect_vec();
+
+        let list = |alias: &str, columns: &[String]| {
+            columns.iter().map(|c| format!("{}.{}", alias, c)).join(", ")
+        };
+        let keys = (0..referenced_columns.len())
+            .map(|i| format!("key_{}", i))
+            .collect_vec();
+
+        let references_itself = format!(
+            "({}) = ({})",
+            list("t", &columns),
+            list("t", &referenced_columns)
+        );
+        let any_null = columns
+            .iter()
+            .map(|c| format!("t.{} is null", c))
+            .join(" or ");
+        let relation_name = self.get_relation_name(identifier_quoter);
+
+        format!(
+            "with recursive ordered (row_id, {keys}, depth) as (select t.ctid, {referenced}, 0 from {relation} t where {any_null} or {references_itself} union all select t.ctid, {referenced}, o.depth + 1 from {relation} t join ordered o on ({columns}) = ({o_keys}) where not {references_itself})",
+            keys = keys.join(", "),
+            referenced = list("t", &referenced_columns),
+            relation = relation_name,
+            any_null = any_null,
+            references_itself = references_itself,
+            columns = list("t", &columns),
+            o_keys = list("o", &keys),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default, PostgresColumn, PostgresForeignKeyColumn, PostgresForeignKeyReferencedColumn};
+
+    #[test]
+    fn orders_by_the_foreign_key_to_the_table_itself() {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..default()
+        };
+        let column = |name: &str, ordinal_position: i32| PostgresColumn {
+            name: name.to_string(),
+            ordinal_position,
+            data_type: "int4".to_string(),
+            ..default()
+        };
+        let table = PostgresTable {
+            name: "tree_node".to_string(),
+            columns: vec![column("id", 1), column("field_id", 2), column("parent_id", 3)],
+            constraints: vec![PostgresConstraint::ForeignKey(PostgresForeignKey {
+                name: "tree_node_field_id_parent_id_fkey".to_string(),
+                columns: vec![
+                    PostgresForeignKeyColumn {
+                        name: "field_id".to_string(),
+                        ordinal_position: 1,
+                        affected_by_delete_action: true,
+                    },
+                    PostgresForeignKeyColumn {
+                        name: "parent_id".to_string(),
+                        ordinal_position: 2,
+                        affected_by_delete_action: true,
+                    },
+                ],
+                referenced_table: "tree_node".to_string(),
+                referenced_columns: vec![
+                    PostgresForeignKeyReferencedColumn {
+                        name: "field_id".to_string(),
+                        ordinal_position: 1,
+                    },
+                    PostgresForeignKeyReferencedColumn {
+                        name: "id".to_string(),
+                        ordinal_position: 2,
+                    },
+                ],
+                ..default()
+            })],
+            ..default()
+        };
+
+        let ordering = SelfReferenceOrdering::find(&schema, &table).unwrap().unwrap();
+
+        assert_eq!(
+            ordering.get_ordered_select(&IdentifierQuoter::empty()),
+            "with recursive ordered (row_id, key_0, key_1, depth) as (\
+select t.ctid, t.field_id, t.id, 0 from public.tree_node t \
+where t.field_id is null or t.parent_id is null or (t.field_id, t.parent_id) = (t.field_id, t.id) \
+union all \
+select t.ctid, t.field_id, t.id, o.depth + 1 from public.tree_node t \
+join ordered o on (t.field_id, t.parent_id) = (o.key_0, o.key_1) \
+where not (t.field_id, t.parent_id) = (t.field_id, t.id)) \
+select t.id, t.field_id, t.parent_id from public.tree_node t join ordered o on o.row_id = t.ctid order by o.depth"
+        );
+    }
+
+    #[test]
+    fn ignores_foreign_keys_to_other_tables() {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..default()
+        };
+        let table = PostgresTable {
+            name: "tree_node".to_string(),
+            constraints: vec![PostgresConstraint::ForeignKey(PostgresForeignKey {
+                name: "tree_node_field_id_fkey".to_string(),
+                referenced_table: "field".to_string(),
+                ..default()
+            })],
+            ..default()
+        };
+
+        assert!(SelfReferenceOrdering::find(&schema, &table).unwrap().is_none());
+    }
+
+    #[test]
+    fn fails_with_several_foreign_keys_to_the_table_itself() {
+        let schema = PostgresSchema {
+            name: "public".to_string(),
+            ..default()
+        };
+        let self_reference = |name: &str| {
+            PostgresConstraint::ForeignKey(PostgresForeignKey {
+                name: name.to_string(),
+                referenced_table: "employee".to_string(),
+                ..default()
+            })
+        };
+        let table = PostgresTable {
+            name: "employee".to_string(),
+            constraints: vec![
+                self_reference("employee_manager_id_fkey"),
+                self_reference("employee_mentor_id_fkey"),
+            ],
+            ..default()
+        };
+
+        let result = SelfReferenceOrdering::find(&schema, &table);
+
+        assert!(
+            matches!(
+                &result,
+                Err(ElefantToolsError::MultipleSelfReferences { foreign_key_names, .. })
+                    if foreign_key_names == "employee_manager_id_fkey, employee_mentor_id_fkey"
+            ),
+            "{:?}",
+            result.err()
+        );
+    }
+}
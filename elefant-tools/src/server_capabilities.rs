@@ -0,0 +1,163 @@
+//! What a Postgres server supports, derived from its version and the installed extensions.
+//!
+//! Code that has to behave differently depending on the server should ask these instead of
+//! comparing version numbers, so the minimum versions are only written down once.
+
+use crate::{PostgresClientWrapper, Result};
+use std::fmt::{Display, Formatter};
+
+/// A version of TimescaleDB, such as `2.13.1`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct TimescaleVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl TimescaleVersion {
+    /// Parses the version of the extension as reported by `pg_extension.extversion`. Suffixes
+    /// such as `-dev` are ignored, and a missing patch version is treated as `0`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.split('-').next()?;
+        let mut parts = version.split('.').map(|p| p.parse::<u32>());
+
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = match parts.next() {
+            Some(patch) => patch.ok()?,
+            None => 0,
+        };
+
+        Some(TimescaleVersion {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl Display for TimescaleVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// What a Postgres server supports. See [crate::PostgresInstanceStorage::capabilities].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ServerCapabilities {
+    /// The version of the server, in the format of [PostgresClientWrapper::version], so `150`
+    /// for Postgres 15.
+    pub version: i32,
+    /// `generated always as (...) stored` columns. Added in Postgres 12.
+    pub supports_generated_columns: bool,
+    /// `generated always as identity` columns. Added in Postgres 10.
+    pub supports_identity_columns: bool,
+    /// `copy ... with (freeze)`. Added in Postgres 9.3, so every supported version has it.
+    pub supports_copy_freeze: bool,
+    /// Extensions marked as trusted, which can be created without being a superuser. Added in
+    /// Postgres 13.
+    pub supports_trusted_extensions: bool,
+    /// `unique nulls not distinct` constraints and indexes. Added in Postgres 15.
+    pub supports_nulls_not_distinct: bool,
+    /// `copy ... with (header match)`, which checks the header of a csv file against the
+    /// columns. Added in Postgres 15.
+    pub supports_copy_header_match: bool,
+    /// `alter table ... set access method`. Added in Postgres 15.
+    pub supports_set_access_method: bool,
+    /// The installed version of TimescaleDB, if it is installed in the database.
+    pub timescale: Option<TimescaleVersion>,
+}
+
+impl ServerCapabilities {
+    /// The capabilities of a server with the specified version, in the format of
+    /// [PostgresClientWrapper::version].
+    pub fn new(version: i32, timescale: Option<TimescaleVersion>) -> Self {
+        ServerCapabilities {
+            version,
+            supports_generated_columns: version >= 120,
+            supports_identity_columns: version >= 100,
+            supports_copy_freeze: true,
+            supports_trusted_extensions: version >= 130,
+            supports_nulls_not_distinct: version >= 150,
+            supports_copy_header_match: version >= 150,
+            supports_set_access_method: version >= 150,
+            timescale,
+        }
+    }
+
+    /// Reads the capabilities of the database the connection is connected to.
+    pub(crate) async fn read(connection: &PostgresClientWrapper) -> Result<Self> {
+        let timescale = connection
+            .get_single_results::<String>(
+                "select extversion::text from pg_extension where extname = 'timescaledb'",
+            )
+            .await?
+            .first()
+            .and_then(|v| TimescaleVersion::parse(v));
+
+        Ok(ServerCapabilities::new(connection.version(), timescale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Capability = fn(&ServerCapabilities) -> bool;
+
+    #[test]
+    fn capabilities_match_the_documented_minimum_versions() {
+        let minimum_versions: [(&str, i32, Capability); 6] = [
+            ("generated columns", 120, |c| c.supports_generated_columns),
+            ("identity columns", 100, |c| c.supports_identity_columns),
+            ("trusted extensions", 130, |c| c.supports_trusted_extensions),
+            ("nulls not distinct", 150, |c| c.supports_nulls_not_distinct),
+            ("copy header match", 150, |c| c.supports_copy_header_match),
+            ("set access method", 150, |c| c.supports_set_access_method),
+        ];
+
+        for (name, minimum_version, is_supported) in minimum_versions {
+            assert!(
+                !is_supported(&ServerCapabilities::new(minimum_version - 1, None)),
+                "{} should not be supported before {}",
+                name,
+                minimum_version
+            );
+            assert!(
+                is_supported(&ServerCapabilities::new(minimum_version, None)),
+                "{} should be supported from {}",
+                name,
+                minimum_version
+            );
+            assert!(
+                is_supported(&ServerCapabilities::new(170, None)),
+                "{} should be supported on the newest version",
+                name
+            );
+        }
+
+        let oldest = ServerCapabilities::new(crate::MIN_SUPPORTED_POSTGRES_VERSION * 10, None);
+        assert!(oldest.supports_copy_freeze);
+        assert!(oldest.supports_generated_columns);
+        assert!(oldest.supports_identity_columns);
+    }
+
+    #[test]
+    fn parses_timescale_versions() {
+        let version = |major, minor, patch| {
+            Some(TimescaleVersion {
+                major,
+                minor,
+                patch,
+            })
+        };
+
+        assert_eq!(TimescaleVersion::parse("2.13.1"), version(2, 13, 1));
+        assert_eq!(TimescaleVersion::parse("2.14"), version(2, 14, 0));
+        assert_eq!(TimescaleVersion::parse("2.15.0-dev"), version(2, 15, 0));
+        assert_eq!(TimescaleVersion::parse("two"), None);
+        assert_eq!(TimescaleVersion::parse(""), None);
+        assert!(TimescaleVersion::parse("2.9.3") < TimescaleVersion::parse("2.13.0"));
+        assert_eq!(version(2, 13, 1).unwrap().to_string(), "2.13.1");
+    }
+}
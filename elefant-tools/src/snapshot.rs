@@ -0,0 +1,172 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The version of the snapshot format written by [introspect_to_file]. It is increased whenever
+/// the models change in a way that older snapshots cannot be read with, so a stale snapshot is
+/// reported as such instead of failing with a confusing error about a missing field.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// A JSON snapshot of the structure of a database.
+#[derive(Serialize, Deserialize)]
+struct DatabaseSnapshot {
+    version: u32,
+    database: PostgresDatabase,
+}
+
+/// Only the version, so it can be checked before the rest of the snapshot is read.
+#[derive(Deserialize)]
+struct SnapshotVersion {
+    version: u32,
+}
+
+impl PostgresDatabase {
+    /// Serializes the database into a JSON snapshot, which can be read with
+    /// [PostgresDatabase::from_snapshot_json].
+    pub fn to_snapshot_json(&self) -> Result<String> {
+        let snapshot = DatabaseSnapshot {
+            version: SNAPSHOT_VERSION,
+            database: self.clone(),
+        };
+
+        Ok(serde_json::to_string_pretty(&snapshot)?)
+    }
+
+    /// Reads a JSON snapshot written by [PostgresDatabase::to_snapshot_json]. Fails with
+    /// [ElefantToolsError::UnsupportedSnapshotVersion] if the snapshot was written by a version of
+    /// elefant-tools with another snapshot format.
+    pub fn from_snapshot_json(json: &str) -> Result<Self> {
+        let SnapshotVersion { version } = serde_json::from_str(json)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(ElefantToolsError::UnsupportedSnapshotVersion {
+                version,
+                supported_version: SNAPSHOT_VERSION,
+            });
+        }
+
+        let snapshot: DatabaseSnapshot = serde_json::from_str(json)?;
+
+        Ok(snapshot.database)
+    }
+}
+
+/// Introspects the database and writes a JSON snapshot of it to `out_path`, returning the
+/// introspected database.
+///
+/// This blocks until the snapshot is written, so it can be called from build scripts without
+/// setting up an async runtime. It cannot be called from within an async runtime.
+/// See `examples/schema_validation_build_script.rs` for how to fail a build when the database
+/// does not have the expected columns.
+pub fn introspect_to_file(
+    connection_string: &str,
+    out_path: impl AsRef<Path>,
+) -> Result<PostgresDatabase> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    let database = runtime.block_on(async {
+        let connection = PostgresClientWrapper::new(connection_string).await?;
+        SchemaReader::new(&connection).introspect_database().await
+    })?;
+
+    let out_path = out_path.as_ref();
+    std::fs::write(out_path, database.to_snapshot_json()?).with_context_path("write", out_path)?;
+
+    Ok(database)
+}
+
+/// Reads a JSON snapshot written by [introspect_to_file].
+pub fn read_snapshot_file(path: impl AsRef<Path>) -> Result<PostgresDatabase> {
+    let path = path.as_ref();
+    let json = std::fs::read_to_string(path).with_context_path("read", path)?;
+
+    PostgresDatabase::from_snapshot_json(&json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema_reader::tests::introspect_schema;
+    use crate::test_helpers::TestHelper;
+    use elefant_test_macros::pg_test;
+
+    fn database() -> PostgresDatabase {
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![PostgresTable {
+                    columns: vec![PostgresColumn {
+                        name: "email".to_string(),
+                        data_type: "text".to_string(),
+                        ..default()
+                    }],
+                    ..PostgresTable::new("users")
+                }],
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let json = database().to_snapshot_json().unwrap();
+
+        assert!(json.contains(&format!("\"version\": {}", SNAPSHOT_VERSION)));
+        assert_eq!(PostgresDatabase::from_snapshot_json(&json).unwrap(), database());
+    }
+
+    #[test]
+    fn snapshots_of_other_versions_are_rejected() {
+        let json = database()
+            .to_snapshot_json()
+            .unwrap()
+            .replacen(&format!("\"version\": {}", SNAPSHOT_VERSION), "\"version\": 0", 1);
+
+        let result = PostgresDatabase::from_snapshot_json(&json);
+
+        assert!(
+            matches!(
+                result,
+                Err(ElefantToolsError::UnsupportedSnapshotVersion {
+                    version: 0,
+                    supported_version: SNAPSHOT_VERSION
+                })
+            ),
+            "{:?}",
+            result
+        );
+    }
+
+    #[pg_test(arg(postgres = 15))]
+    async fn introspects_to_file(helper: &TestHelper) {
+        helper
+            .execute_not_query("create table users(id int primary key, email text not null);")
+            .await;
+
+        let path = std::env::temp_dir().join(format!("{}.json", helper.test_db_name));
+        let connection_string = helper.get_connection_string();
+
+        let introspected = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || introspect_to_file(&connection_string, path))
+                .await
+                .unwrap()
+                .unwrap()
+        };
+
+        let from_file = read_snapshot_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(from_file, introspected);
+        assert_eq!(from_file, introspect_schema(helper).await);
+
+        let email = from_file
+            .table("public", "users")
+            .and_then(|t| t.column("email"))
+            .unwrap();
+        assert_eq!(email.data_type, "text");
+        assert!(!email.is_nullable);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::object_id::ObjectId;
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
+use crate::storage::CopyDestination;
+use crate::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+/// Copies a schema into a new staging schema, and swaps it with the existing schema once the copy
+/// is done, see [CopyDataOptions::staging_swap].
+///
+/// The existing schema keeps serving queries while the copy runs, and is replaced in a single
+/// transaction afterwards.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StagingSwap {
+    /// The schema the data is copied into, before it is renamed to the copied schema.
+    pub staging_schema: String,
+    /// The name the existing schema is given while it is replaced.
+    pub old_schema: String,
+    /// Keep the replaced schema under `old_schema` after the swap, instead of dropping it.
+    pub keep_old_schema: bool,
+}
+
+impl StagingSwap {
+    /// Names the staging and the old schema after the current time, so each run gets its own.
+    pub fn new(keep_old_schema: bool) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+
+        StagingSwap {
+            staging_schema: format!("_elefant_staging_{}", timestamp),
+            old_schema: format!("_elefant_old_{}", timestamp),
+            keep_old_schema,
+        }
+    }
+
+    /// Renames the existing schema out of the way, if there is one, and the staging schema to
+    /// `schema_name`. The statements should run in a single transaction.
+    pub(crate) fn get_swap_statements(
+        &self,
+        schema_name: &str,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Vec<String> {
+        let quoted_schema_name = schema_name.quote(identifier_quoter, ColumnName);
+
+        vec![
+            format!(
+                "do $$ begin if exists (select 1 from pg_namespace where nspname = {}) then alter schema {} rename to {}; end if; end $$;",
+                quote_value_string(schema_name),
+                quoted_schema_name,
+                self.old_schema.quote(identifier_quoter, ColumnName)
+            ),
+            format!(
+                "alter schema {} rename to {};",
+                self.staging_schema.quote(identifier_quoter, ColumnName),
+                quoted_schema_name
+            ),
+        ]
+    }
+
+    /// Drops the replaced schema, unless it should be kept.
+    pub(crate) fn get_drop_old_schema_statement(
+        &self,
+        identifier_quoter: &IdentifierQuoter,
+    ) -> Option<String> {
+        if self.keep_old_schema {
+            None
+        } else {
+            Some(format!(
+                "drop schema if exists {} cascade;",
+                self.old_schema.quote(identifier_quoter, ColumnName)
+            ))
+        }
+    }
+}
+
+/// Swaps the staging schema in, once everything has been copied into it.
+pub(crate) async fn swap_staging_schema<D: CopyDestination>(
+    destination: &mut D,
+    staging_swap: &StagingSwap,
+    schema_name: &str,
+) -> Result<()> {
+    let identifier_quoter = destination.get_identifier_quoter();
+
+    info!(
+        "Swapping schema {} with staging schema {}",
+        schema_name, staging_swap.staging_schema
+    );
+    destination.begin_transaction().await?;
+    for statement in staging_swap.get_swap_statements(schema_name, &identifier_quoter) {
+        destination.apply_transactional_statement(&statement).await?;
+    }
+    destination.commit_transaction().await?;
+
+    if let Some(statement) = staging_swap.get_drop_old_schema_statement(&identifier_quoter) {
+        destination.apply_transactional_statement(&statement).await?;
+    }
+
+    Ok(())
+}
+
+/// Validates that a staging swap can be used with the rest of the options, and returns the name
+/// of the schema that is swapped.
+pub(crate) fn get_swapped_schema_name(options: &CopyDataOptions) -> Result<&str> {
+    let invalid = |reason: &str| ElefantToolsError::InvalidStagingSwap(reason.to_string());
+
+    let Some(target_schema) = &options.target_schema else {
+        return Err(invalid("a single schema has to be copied"));
+    };
+    if options.differential {
+        return Err(invalid("it cannot be combined with a differential copy"));
+    }
+    if options.indexes_only {
+        return Err(invalid("it cannot be combined with indexes_only"));
+    }
+
+    Ok(options.rename_schema_to.as_deref().unwrap_or(target_schema))
+}
+
+/// Finds the objects outside `schema_name` that depend on objects in it. They would keep
+/// referencing the replaced schema after the swap, and be dropped together with it.
+pub(crate) fn find_swap_blockers(destination: &PostgresDatabase, schema_name: &str) -> Vec<String> {
+    let Some(schema) = destination.try_get_schema(schema_name) else {
+        return Vec::new();
+    };
+
+    let swapped_objects = schema
+        .tables
+        .iter()
+        .map(|t| t.object_id)
+        .chain(schema.views.iter().map(|v| v.object_id))
+        .chain(schema.sequences.iter().map(|s| s.object_id))
+        .chain(schema.functions.iter().map(|f| f.object_id))
+        .chain(schema.aggregate_functions.iter().map(|f| f.object_id))
+        .chain(schema.enums.iter().map(|e| e.object_id))
+        .chain(schema.domains.iter().map(|d| d.object_id))
+        .chain(schema.composite_types.iter().map(|t| t.object_id))
+        .collect_vec();
+    let depends_on_swapped =
+        |depends_on: &[ObjectId]| depends_on.iter().any(|id| swapped_objects.contains(id));
+
+    let mut blockers = Vec::new();
+    for other in destination.schemas.iter().filter(|s| s.name != schema_name) {
+        for table in &other.tables {
+            if depends_on_swapped(&table.depends_on) {
+                blockers.push(format!("table {}.{}", other.name, table.name));
+            }
+
+            for constraint in &table.constraints {
+                if let PostgresConstraint::ForeignKey(fk) = constraint {
+                    if fk.referenced_schema.as_deref() == Some(schema_name) {
+                        blockers.push(format!(
+                            "foreign key {} on table {}.{}",
+                            fk.name, other.name, table.name
+                        ));
+                    }
+                }
+            }
+        }
+
+        for view in &other.views {
+            if depends_on_swapped(&view.depends_on) {
+                blockers.push(format!("view {}.{}", other.name, view.name));
+            }
+        }
+
+        for function in &other.functions {
+            if depends_on_swapped(&function.depends_on) {
+                blockers.push(format!("function {}.{}", other.name, function.function_name));
+            }
+        }
+
+        for domain in &other.domains {
+            if depends_on_swapped(&domain.depends_on) {
+                blockers.push(format!("domain {}.{}", other.name, domain.name));
+            }
+        }
+
+        for composite_type in &other.composite_types {
+            if depends_on_swapped(&composite_type.depends_on) {
+                blockers.push(format!("type {}.{}", other.name, composite_type.name));
+            }
+        }
+    }
+
+    blockers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swaps_schemas_and_drops_the_old_one() {
+        let swap = StagingSwap {
+            staging_schema: "_elefant_staging_1".to_string(),
+            old_schema: "_elefant_old_1".to_string(),
+            keep_old_schema: false,
+        };
+        let identifier_quoter = IdentifierQuoter::empty();
+
+        assert_eq!(
+            swap.get_swap_statements("app", &identifier_quoter),
+            vec![
+                "do $$ begin if exists (select 1 from pg_namespace where nspname = 'app') then alter schema app rename to _elefant_old_1; end if; end $$;".to_string(),
+                "alter schema _elefant_staging_1 rename to app;".to_string(),
+            ]
+        );
+        assert_eq!(
+            swap.get_drop_old_schema_statement(&identifier_quoter),
+            Some("drop schema if exists _elefant_old_1 cascade;".to_string())
+        );
+
+        let swap = StagingSwap {
+            keep_old_schema: true,
+            ..swap
+        };
+        assert_eq!(swap.get_drop_old_schema_statement(&identifier_quoter), None);
+    }
+
+    #[test]
+    fn finds_objects_in_other_schemas_depending_on_the_swapped_schema() {
+        let destination = PostgresDatabase {
+            schemas: vec![
+                PostgresSchema {
+                    name: "app".to_string(),
+                    tables: vec![PostgresTable {
+                        name: "items".to_string(),
+                        object_id: 1.into(),
+                        ..default()
+                    }],
+                    ..default()
+                },
+                PostgresSchema {
+                    name: "reporting".to_string(),
+                    tables: vec![PostgresTable {
+                        name: "item_notes".to_string(),
+                        constraints: vec![PostgresConstraint::ForeignKey(PostgresForeignKey {
+                            name: "item_notes_item_id_fkey".to_string(),
+                            referenced_schema: Some("app".to_string()),
+                            referenced_table: "items".to_string(),
+                            ..default()
+                        })],
+                        ..default()
+                    }],
+                    views: vec![
+                        PostgresView {
+                            name: "item_summary".to_string(),
+                            depends_on: vec![1.into()],
+                            ..default()
+                        },
+                        PostgresView {
+                            name: "unrelated".to_string(),
+                            ..default()
+                        },
+                    ],
+                    ..default()
+                },
+            ],
+            ..default()
+        };
+
+        assert_eq!(
+            find_swap_blockers(&destination, "app"),
+            vec![
+                "foreign key item_notes_item_id_fkey on table reporting.item_notes".to_string(),
+                "view reporting.item_summary".to_string(),
+            ]
+        );
+        assert!(find_swap_blockers(&destination, "reporting").is_empty());
+    }
+}
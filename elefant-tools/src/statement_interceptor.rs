@@ -0,0 +1,270 @@
+use crate::storage::{AsyncCleanup, CopyDestination, TableData};
+use crate::*;
+use bytes::Bytes;
+use futures::Stream;
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// A DDL statement about to be applied to the destination, see [StatementInterceptor].
+///
+/// The statement can consist of several SQL statements that belong together, such as a table
+/// and the comments on its columns.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DdlStatement {
+    pub sql: String,
+    /// If the statement can run in a transaction. Statements such as
+    /// `create index concurrently` have to run outside a transaction.
+    pub transactional: bool,
+}
+
+impl DdlStatement {
+    pub fn transactional(sql: impl Into<String>) -> Self {
+        DdlStatement {
+            sql: sql.into(),
+            transactional: true,
+        }
+    }
+
+    pub fn non_transactional(sql: impl Into<String>) -> Self {
+        DdlStatement {
+            sql: sql.into(),
+            transactional: false,
+        }
+    }
+}
+
+/// What to do with an intercepted statement.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum InterceptResult {
+    /// Apply the statement as is.
+    Keep,
+    /// Apply these statements instead, in order.
+    Replace(Vec<DdlStatement>),
+    /// Don't apply the statement. It is listed in [CopyDataReport::skipped_statements].
+    Skip,
+}
+
+/// Decides what happens to each DDL statement of a copy before it is applied to the destination,
+/// see [CopyDataOptions::statement_interceptor]. The data of the tables is not intercepted.
+///
+/// This allows enforcing policies without an option for each of them, such as never dropping
+/// anything, or giving all tables a specific storage parameter.
+pub trait StatementInterceptor: Debug + Send + Sync {
+    fn intercept(&self, statement: &DdlStatement) -> InterceptResult;
+}
+
+/// Sets a storage parameter on every created table, such as `fillfactor=70`, by altering the
+/// table right after it is created. Partitioned tables cannot have storage parameters, so they
+/// are left alone, but their partitions are not.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AppendStorageParameter {
+    pub storage_parameter: String,
+}
+
+impl AppendStorageParameter {
+    pub fn new(storage_parameter: impl Into<String>) -> Self {
+        AppendStorageParameter {
+            storage_parameter: storage_parameter.into(),
+        }
+    }
+}
+
+impl StatementInterceptor for AppendStorageParameter {
+    fn intercept(&self, statement: &DdlStatement) -> InterceptResult {
+        let Some(table_name) = get_created_table_name(&statement.sql) else {
+            return InterceptResult::Keep;
+        };
+
+        if statement.sql.contains("\n) partition by ") {
+            return InterceptResult::Keep;
+        }
+
+        InterceptResult::Replace(vec![
+            statement.clone(),
+            DdlStatement {
+                sql: format!(
+                    "alter table {} set ({});",
+                    table_name, self.storage_parameter
+                ),
+                transactional: statement.transactional,
+            },
+        ])
+    }
+}
+
+/// Finds the name of the table created by a `create table` statement, as it is written in the
+/// statement, so it is still quoted and qualified.
+fn get_created_table_name(sql: &str) -> Option<&str> {
+    let rest = sql.strip_prefix("create table ")?;
+
+    let mut in_quotes = false;
+    for (index, c) in rest.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if !in_quotes && (c.is_whitespace() || c == '(') => {
+                return Some(&rest[..index]).filter(|name| !name.is_empty());
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Passes the DDL statements through the [StatementInterceptor] before they reach the actual
+/// destination. Without an interceptor the statements are applied as is.
+#[derive(Clone)]
+pub(crate) struct InterceptingDestination<D> {
+    destination: D,
+    interceptor: Option<Arc<dyn StatementInterceptor>>,
+    skipped_statements: Arc<Mutex<Vec<String>>>,
+}
+
+impl<D: CopyDestination> InterceptingDestination<D> {
+    pub(crate) fn new(
+        destination: D,
+        interceptor: Option<Arc<dyn StatementInterceptor>>,
+        skipped_statements: Arc<Mutex<Vec<String>>>,
+    ) -> Self {
+        InterceptingDestination {
+            destination,
+            interceptor,
+            skipped_statements,
+        }
+    }
+
+    async fn apply_statement(&mut self, statement: DdlStatement) -> Result<()> {
+        let statements = match &self.interceptor {
+            None => vec![statement],
+            Some(interceptor) => match interceptor.intercept(&statement) {
+                InterceptResult::Keep => vec![statement],
+                InterceptResult::Replace(statements) => statements,
+                InterceptResult::Skip => {
+                    info!("Skipping statement: {}", statement.sql);
+                    self.skipped_statements.lock().unwrap().push(statement.sql); // SAFE: Only panics if another thread panicked while pushing
+                    vec![]
+                }
+            },
+        };
+
+        for statement in statements {
+            if statement.transactional {
+                self.destination
+                    .apply_transactional_statement(&statement.sql)
+                    .await?;
+            } else {
+                self.destination
+                    .apply_non_transactional_statement(&statement.sql)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<D: CopyDestination> CopyDestination for InterceptingDestination<D> {
+    async fn apply_data<S: Stream<Item = Result<Bytes>> + Send, C: AsyncCleanup>(
+        &mut self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+        data: TableData<S, C>,
+    ) -> Result<()> {
+        self.destination.apply_data(schema, table, data).await
+    }
+
+    async fn apply_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        self.apply_statement(DdlStatement::transactional(statement))
+            .await
+    }
+
+    async fn apply_non_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        self.apply_statement(DdlStatement::non_transactional(statement))
+            .await
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        self.destination.begin_transaction().await
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        self.destination.commit_transaction().await
+    }
+
+    fn get_identifier_quoter(&self) -> Arc<IdentifierQuoter> {
+        self.destination.get_identifier_quoter()
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.destination.finish().await
+    }
+
+    // The futures of the wrapped destination are returned directly, as the destination might not
+    // be Sync, in which case a future borrowing this wrapper would not be Send.
+    fn try_introspect(
+        &self,
+    ) -> impl Future<Output = Result<Option<PostgresDatabase>>> + Send {
+        self.destination.try_introspect()
+    }
+
+    fn has_data_in_table(
+        &self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+    ) -> impl Future<Output = Result<bool>> + Send {
+        self.destination.has_data_in_table(schema, table)
+    }
+
+    fn get_text_rows(
+        &self,
+        sql: &str,
+    ) -> impl Future<Output = Result<Option<Vec<Vec<Option<String>>>>>> + Send {
+        self.destination.get_text_rows(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_created_table_names() {
+        assert_eq!(
+            get_created_table_name("create table public.items (\n    id int4\n);"),
+            Some("public.items")
+        );
+        assert_eq!(
+            get_created_table_name(r#"create table "My Schema"."My Table" (id int4);"#),
+            Some(r#""My Schema"."My Table""#)
+        );
+        assert_eq!(
+            get_created_table_name("create table public.items_2024 partition of public.items for values in (2024);"),
+            Some("public.items_2024")
+        );
+        assert_eq!(get_created_table_name("create index items_idx on items (id);"), None);
+    }
+
+    #[test]
+    fn appends_storage_parameter_to_created_tables() {
+        let interceptor = AppendStorageParameter::new("fillfactor=70");
+
+        let create_table = DdlStatement::transactional("create table public.items (\n    id int4\n);");
+        assert_eq!(
+            interceptor.intercept(&create_table),
+            InterceptResult::Replace(vec![
+                create_table.clone(),
+                DdlStatement::transactional("alter table public.items set (fillfactor=70);"),
+            ])
+        );
+
+        let partitioned = DdlStatement::transactional(
+            "create table public.items (\n    id int4\n) partition by list (id);",
+        );
+        assert_eq!(interceptor.intercept(&partitioned), InterceptResult::Keep);
+
+        let create_index = DdlStatement::transactional("create index items_idx on public.items (id);");
+        assert_eq!(interceptor.intercept(&create_index), InterceptResult::Keep);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::models::PostgresSchema;
+use crate::models::PostgresTable;
+use crate::quoting::IdentifierQuoter;
+use crate::storage::data_format::DataFormat;
+use crate::storage::table_data::TableData;
+use crate::storage::{encode_file_name, BaseCopyTarget, CopyDestination};
+use crate::{
+    AsyncCleanup, CopyDestinationFactory, Result, ResultExt, SequentialOrParallel, SupportedParallelism,
+};
+use bytes::Bytes;
+use futures::{pin_mut, Stream, StreamExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tracing::instrument;
+
+#[cfg(test)]
+mod tests;
+
+/// Options that control how the csv files are written.
+#[derive(Debug, Clone)]
+pub struct CsvDirectoryOptions {
+    /// The character separating the values in a row.
+    pub delimiter: char,
+    /// Whether to include a header row with the column names in each file.
+    pub header: bool,
+}
+
+impl Default for CsvDirectoryOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            header: true,
+        }
+    }
+}
+
+/// A destination that writes the data of each table to a separate csv file in a directory.
+///
+/// The files are named `<schema>.<table>.csv` (or `.tsv` if the delimiter is a tab), with the
+/// characters of the names that are not safe in file names percent-encoded.
+/// Only the data is written, all DDL statements are skipped.
+#[derive(Clone)]
+pub struct CsvDirectoryDestination {
+    path: PathBuf,
+    options: CsvDirectoryOptions,
+    quoter: Arc<IdentifierQuoter>,
+}
+
+impl CsvDirectoryDestination {
+    /// Creates a new destination writing to the specified directory. The directory is created
+    /// if it doesn't exist already.
+    pub async fn new(path: impl AsRef<Path>, options: CsvDirectoryOptions) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&path)
+            .await
+            .with_context_path("create directory", &path)?;
+
+        Ok(Self {
+            path,
+            options,
+            quoter: Arc::new(IdentifierQuoter::empty()),
+        })
+    }
+
+    /// The path of the file the data of the specified table will be written to.
+    pub fn get_table_file_path(&self, schema: &PostgresSchema, table: &PostgresTable) -> PathBuf {
+        let extension = if self.options.delimiter == '\t' {
+            "tsv"
+        } else {
+            "csv"
+        };
+
+        self.path.join(format!(
+            "{}.{}.{}",
+            encode_file_name(&schema.name),
+            encode_file_name(&table.name),
+            extension
+        ))
+    }
+}
+
+impl BaseCopyTarget for CsvDirectoryDestination {
+    async fn supported_data_format(&self) -> Result<Vec<DataFormat>> {
+        Ok(vec![DataFormat::Csv {
+            header: self.options.header,
+            delimiter: self.options.delimiter,
+        }])
+    }
+}
+
+impl<'a> CopyDestinationFactory<'a> for CsvDirectoryDestination {
+    type SequentialDestination = CsvDirectoryDestination;
+    type ParallelDestination = CsvDirectoryDestination;
+
+    async fn create_destination(
+        &'a mut self,
+    ) -> Result<SequentialOrParallel<Self::SequentialDestination, Self::ParallelDestination>> {
+        Ok(SequentialOrParallel::Parallel(self.clone()))
+    }
+
+    async fn create_sequential_destination(&'a mut self) -> Result<Self::SequentialDestination> {
+        Ok(self.clone())
+    }
+
+    fn supported_parallelism(&self) -> SupportedParallelism {
+        SupportedParallelism::Parallel
+    }
+}
+
+impl CopyDestination for CsvDirectoryDestination {
+    #[instrument(skip_all)]
+    async fn apply_data<S: Stream<Item = Result<Bytes>> + Send, C: AsyncCleanup>(
+        &mut self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+        data: TableData<S, C>,
+    ) -> Result<()> {
+        let path = self.get_table_file_path(schema, table);
+
+        data.apply_then_cleanup(|stream| async move {
+            let file = File::create(&path)
+                .await
+                .with_context_path("create", &path)?;
+            let mut file = BufWriter::new(file);
+
+            pin_mut!(stream);
+
+            while let Some(chunk) = stream.next().await {
+                file.write_all(&chunk?)
+                    .await
+                    .with_context_path("write to", &path)?;
+            }
+
+            file.flush().await.with_context_path("write to", &path)?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn apply_transactional_statement(&mut self, _statement: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn apply_non_transactional_statement(&mut self, _statement: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_identifier_quoter(&self) -> Arc<IdentifierQuoter> {
+        self.quoter.clone()
+    }
+}
@@ -0,0 +1,189 @@
+use crate::copy_data::{copy_data, CopyDataOptions};
+use crate::storage::csv_directory::*;
+use crate::test_helpers;
+use crate::test_helpers::TestHelper;
+use crate::{default, PostgresInstanceStorage};
+use elefant_test_macros::pg_test;
+use indoc::indoc;
+use uuid::Uuid;
+
+async fn export_to_directory(source: &TestHelper, options: CsvDirectoryOptions) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("elefant-csv-{}", Uuid::new_v4()));
+
+    let mut destination = CsvDirectoryDestination::new(&path, options).await.unwrap();
+    let source = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(&source, &mut destination, CopyDataOptions::default())
+        .await
+        .unwrap();
+
+    path
+}
+
+const PEOPLE: &str = r#"
+    create table people(
+        id int primary key,
+        name text,
+        note text
+    );
+
+    insert into people(id, name, note) values
+        (1, 'Smith, John', 'said "hello"'),
+        (2, 'Jane', E'first line\nsecond line'),
+        (3, 'Bob', null),
+        (4, 'Tab', E'a\tb');
+
+    create view people_view as select * from people;
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn exports_tables_as_csv(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(PEOPLE).await;
+
+    let path = export_to_directory(source, CsvDirectoryOptions::default()).await;
+
+    let files = std::fs::read_dir(&path)
+        .unwrap()
+        .map(|f| f.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(files, vec!["public.people.csv".to_string()]);
+
+    let content = std::fs::read_to_string(path.join("public.people.csv")).unwrap();
+    similar_asserts::assert_eq!(
+        content,
+        indoc! {r#"
+            id,name,note
+            1,"Smith, John","said ""hello"""
+            2,Jane,"first line
+            second line"
+            3,Bob,
+            4,Tab,a	b
+        "#}
+    );
+
+    // Make sure Postgres can read the files again.
+    destination
+        .execute_not_query("create table people_copy(id int, name text, note text);")
+        .await;
+    let sink = destination
+        .get_conn()
+        .copy_in::<bytes::Bytes>(
+            "copy people_copy (id, name, note) from stdin with (format csv, header true);",
+        )
+        .await
+        .unwrap();
+    futures::pin_mut!(sink);
+    futures::SinkExt::send(&mut sink, bytes::Bytes::from(content))
+        .await
+        .unwrap();
+    sink.finish().await.unwrap();
+
+    let copied = destination
+        .get_results::<(i32, String, Option<String>)>(
+            "select id, name, note from people_copy order by id;",
+        )
+        .await;
+    assert_eq!(
+        copied,
+        vec![
+            (
+                1,
+                "Smith, John".to_string(),
+                Some(r#"said "hello""#.to_string())
+            ),
+            (
+                2,
+                "Jane".to_string(),
+                Some("first line\nsecond line".to_string())
+            ),
+            (3, "Bob".to_string(), None),
+            (4, "Tab".to_string(), Some("a\tb".to_string())),
+        ]
+    );
+
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[pg_test(arg(postgres = 15))]
+async fn exports_tables_as_tsv(source: &TestHelper) {
+    source.execute_not_query(PEOPLE).await;
+
+    let path = export_to_directory(
+        source,
+        CsvDirectoryOptions {
+            delimiter: '\t',
+            header: false,
+        },
+    )
+    .await;
+
+    let content = std::fs::read_to_string(path.join("public.people.tsv")).unwrap();
+    similar_asserts::assert_eq!(
+        content,
+        "1\tSmith, John\t\"said \"\"hello\"\"\"\n2\tJane\t\"first line\nsecond line\"\n3\tBob\t\n4\tTab\t\"a\tb\"\n"
+    );
+
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test]
+async fn writes_to_nested_directories_with_spaces_and_unicode() {
+    let root = std::env::temp_dir().join(format!("elefant csv {}", Uuid::new_v4()));
+    let path = root.join("données").join("数据 export");
+
+    let mut destination = CsvDirectoryDestination::new(&path, CsvDirectoryOptions::default())
+        .await
+        .unwrap();
+
+    let schema = PostgresSchema {
+        name: "public".to_string(),
+        ..default()
+    };
+    let table = PostgresTable {
+        name: "people".to_string(),
+        ..default()
+    };
+    let rows = ["id\n", "1\n"].map(|row| Ok(Bytes::from(row)));
+    let data = TableData {
+        data: futures::stream::iter(rows),
+        data_format: DataFormat::Csv {
+            header: true,
+            delimiter: ',',
+        },
+        cleanup: (),
+    };
+    destination.apply_data(&schema, &table, data).await.unwrap();
+
+    let file_path = destination.get_table_file_path(&schema, &table);
+    assert_eq!(file_path, path.join("public.people.csv"));
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "id\n1\n");
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn names_cannot_escape_the_directory() {
+    let path = std::env::temp_dir().join(format!("elefant-csv-{}", Uuid::new_v4()));
+
+    let destination = CsvDirectoryDestination::new(&path, CsvDirectoryOptions::default())
+        .await
+        .unwrap();
+
+    let schema = PostgresSchema {
+        name: "my.schema".to_string(),
+        ..default()
+    };
+    let table = PostgresTable {
+        name: "../../a/b".to_string(),
+        ..default()
+    };
+
+    assert_eq!(
+        destination.get_table_file_path(&schema, &table),
+        path.join("my%2Eschema.%2E%2E%2F%2E%2E%2Fa%2Fb.csv")
+    );
+
+    std::fs::remove_dir_all(path).unwrap();
+}
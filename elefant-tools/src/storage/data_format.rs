@@ -1,18 +1,119 @@
+use crate::{ElefantToolsError, Result};
+use serde::{Deserialize, Serialize};
+
 /// Describes how data can be copied when using the `COPY` command in postgres.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DataFormat {
     /// Slightly slower, but works across postgres versions, is human-readable and can be
     /// outputted in text files.
-    Text,
+    Text { options: CopyTextOptions },
 
     /// Faster, but has strict requirements to the postgres version and is not human-readable.
     PostgresBinary { postgres_version: Option<String> },
+
+    /// Comma (or otherwise) separated values, as understood by Postgres' `format csv`.
+    /// Mostly useful for exporting data to other tools.
+    Csv { header: bool, delimiter: char },
+}
+
+/// Options for the text format of the `COPY` command.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CopyTextOptions {
+    /// The character separating the columns in a row.
+    pub delimiter: char,
+    /// The string that represents a null value.
+    pub null_marker: String,
+}
+
+impl Default for CopyTextOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: '\t',
+            null_marker: "\\N".to_string(),
+        }
+    }
+}
+
+impl CopyTextOptions {
+    /// Checks that the options can be used by Postgres, and that the data written with them can
+    /// be read back line by line, as is done by [crate::apply_sql_file].
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| {
+            Err(ElefantToolsError::InvalidCopyTextOptions(
+                reason.to_string(),
+            ))
+        };
+
+        // Postgres uses these characters for escape sequences in the text format.
+        if !self.delimiter.is_ascii()
+            || matches!(self.delimiter, '\n' | '\r' | '\\' | '.' | 'a'..='z' | '0'..='9')
+        {
+            return invalid(&format!("{:?} cannot be used as delimiter", self.delimiter));
+        }
+
+        if self.null_marker.contains(['\n', '\r']) {
+            return invalid("The null marker cannot contain line breaks");
+        }
+
+        if self.null_marker.contains(self.delimiter) {
+            return invalid("The null marker cannot contain the delimiter");
+        }
+
+        // A row starting with a null value would otherwise be read as the end of the data.
+        if self.null_marker.starts_with("\\.") {
+            return invalid("The null marker cannot start with '\\.'");
+        }
+
+        Ok(())
+    }
+}
+
+/// Quotes a value for use in the options of the `COPY` command, escaping control characters.
+fn quote_copy_option(value: &str) -> String {
+    if value.contains(['\\', '\t']) {
+        format!(
+            "E'{}'",
+            value
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\'', "''")
+        )
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+impl DataFormat {
+    /// The options to pass to the `COPY` command to use this data format.
+    pub(crate) fn get_copy_options(&self) -> String {
+        match self {
+            DataFormat::Text { options } => {
+                let mut copy_options = "format text, header false".to_string();
+                if options.delimiter != '\t' {
+                    copy_options.push_str(", delimiter ");
+                    copy_options.push_str(&quote_copy_option(&options.delimiter.to_string()));
+                }
+                if options.null_marker != "\\N" {
+                    copy_options.push_str(", null ");
+                    copy_options.push_str(&quote_copy_option(&options.null_marker));
+                }
+                copy_options
+            }
+            DataFormat::PostgresBinary { .. } => "format binary, header false".to_string(),
+            DataFormat::Csv { header, delimiter } => format!(
+                "format csv, header {}, delimiter {}",
+                header,
+                quote_copy_option(&delimiter.to_string())
+            ),
+        }
+    }
 }
 
 impl PartialEq for DataFormat {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (DataFormat::Text, DataFormat::Text) => true,
+            // Like csv, the delimiter and null marker doesn't decide compatibility.
+            (DataFormat::Text { .. }, DataFormat::Text { .. }) => true,
             (
                 DataFormat::PostgresBinary {
                     postgres_version: left_pg_version,
@@ -25,6 +126,9 @@ impl PartialEq for DataFormat {
                 (_, None) => true,
                 (Some(left), Some(right)) => left == right,
             },
+            // Postgres can both produce and consume any csv flavour, so the exact options
+            // doesn't matter for whether two targets are compatible.
+            (DataFormat::Csv { .. }, DataFormat::Csv { .. }) => true,
             _ => false,
         }
     }
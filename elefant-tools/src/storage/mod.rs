@@ -2,11 +2,15 @@ use crate::models::PostgresDatabase;
 use crate::*;
 use bytes::Bytes;
 use futures::Stream;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+mod csv_directory;
 mod data_format;
 mod elefant_file;
+mod pg_custom_format;
 mod postgres;
+mod sql_directory;
 mod sql_file;
 mod table_data;
 
@@ -14,9 +18,14 @@ mod table_data;
 use crate::models::PostgresSchema;
 use crate::models::PostgresTable;
 use crate::quoting::IdentifierQuoter;
+pub use csv_directory::{CsvDirectoryDestination, CsvDirectoryOptions};
 pub use data_format::*;
-pub use postgres::PostgresInstanceStorage;
-pub use sql_file::{apply_sql_file, apply_sql_string, SqlDataMode, SqlFile, SqlFileOptions};
+pub use pg_custom_format::PgCustomFormatDestination;
+pub use postgres::{ConnectionPool, PooledConnection, PostgresInstanceStorage};
+pub use sql_directory::{apply_sql_directory, SqlDirectoryDestination};
+pub use sql_file::{
+    apply_sql_file, apply_sql_file_parallel, apply_sql_string, SqlDataMode, SqlFile, SqlFileOptions, TableBytesWritten,
+};
 pub use table_data::*;
 
 /// A trait for thing that are either a CopyDestination or CopySource.
@@ -25,6 +34,92 @@ pub trait BaseCopyTarget {
     fn supported_data_format(
         &self,
     ) -> impl std::future::Future<Output = Result<Vec<DataFormat>>> + Send;
+
+    /// The encoding and locale of the underlying database. Targets that are not backed
+    /// by a database should return `Ok(None)`.
+    fn get_database_encoding(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Option<DatabaseEncoding>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The names of the table access methods available in the underlying database. Targets that
+    /// are not backed by a database should return `Ok(None)`.
+    fn get_table_access_methods(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Option<Vec<String>>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The names of the tablespaces in the underlying database. Targets that are not backed by a
+    /// database should return `Ok(None)`.
+    fn get_tablespaces(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Option<Vec<String>>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The names of the roles in the underlying database. Targets that are not backed by a
+    /// database should return `Ok(None)`.
+    fn get_roles(&self) -> impl std::future::Future<Output = Result<Option<Vec<String>>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The names of the extensions installed in the underlying database. Targets that are not
+    /// backed by a database should return `Ok(None)`.
+    fn get_installed_extensions(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Option<Vec<String>>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The names of the extensions that are available to be created in the underlying database,
+    /// including the installed ones. Targets that are not backed by a database should return
+    /// `Ok(None)`.
+    fn get_available_extensions(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Option<Vec<String>>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The names of the extensions that are available in the underlying database, but can only be
+    /// created by a superuser, which the connected user isn't. Targets that are not backed by a
+    /// database should return `Ok(None)`.
+    fn get_superuser_only_extensions(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Option<Vec<String>>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// Identifies the server the underlying database runs on, so a source and a destination on
+    /// the same server can be detected. Targets that are not backed by a database should return
+    /// `Ok(None)`.
+    fn get_server_identity(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Option<String>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The size of the underlying database in bytes. Targets that are not backed by a database
+    /// should return `Ok(None)`.
+    fn get_database_size(&self) -> impl std::future::Future<Output = Result<Option<u64>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// The connection to the underlying database, which the
+    /// [crate::IntrospectionExtension]s read from. Targets that are not backed by a database
+    /// should return `None`.
+    fn get_postgres_connection(&self) -> Option<&PostgresClientWrapper> {
+        None
+    }
+
+    /// What the server of the underlying database supports. Targets that are not backed by a
+    /// database should return `Ok(None)`.
+    fn get_server_capabilities(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Option<ServerCapabilities>>> + Send {
+        async { Ok(None) }
+    }
 }
 
 /// A factory for providing copy sources. This is used to create a source that can be used to read data from.
@@ -52,6 +147,17 @@ pub trait CopySourceFactory: BaseCopyTarget {
     /// Should return what kind of parallelism is supported by the source. This is used
     /// for negotiation with the destination.
     fn supported_parallelism(&self) -> SupportedParallelism;
+
+    /// Should find the text columns of the tables in the definition with data that cannot be
+    /// converted to `destination_encoding`, see [CopyDataOptions::preflight_encoding_check].
+    /// Sources that cannot check this should return `Ok(None)`.
+    fn find_invalidly_encoded_columns(
+        &self,
+        _definition: &PostgresDatabase,
+        _destination_encoding: &str,
+    ) -> impl std::future::Future<Output = Result<Option<Vec<InvalidlyEncodedColumn>>>> + Send {
+        async { Ok(None) }
+    }
 }
 
 /// A copy source is something that can be used to read data from a source.
@@ -75,6 +181,29 @@ pub trait CopySource: Send {
         table: &PostgresTable,
         data_format: &DataFormat,
     ) -> impl std::future::Future<Output = Result<TableData<Self::DataStream, Self::Cleanup>>> + Send;
+
+    /// Should return a data-stream with the result of the `select` query in the specified format.
+    /// This is used instead of `get_data` for tables in [CopyDataOptions::table_select_overrides].
+    fn get_data_from_select(
+        &self,
+        select: &str,
+        data_format: &DataFormat,
+    ) -> impl std::future::Future<Output = Result<TableData<Self::DataStream, Self::Cleanup>>> + Send;
+
+    /// Should return the names of the columns returned by the `select` query, without running it.
+    fn get_select_columns(
+        &self,
+        select: &str,
+    ) -> impl std::future::Future<Output = Result<Vec<String>>> + Send;
+
+    /// Should run the query, which only returns `text` columns, and return the rows. This is
+    /// used to verify the copied data. Sources that cannot run queries should return `Ok(None)`.
+    fn get_text_rows(
+        &self,
+        _sql: &str,
+    ) -> impl std::future::Future<Output = Result<Option<Vec<Vec<Option<String>>>>>> + Send {
+        async { Ok(None) }
+    }
 }
 
 /// A factory for providing copy destinations. This is used to create a destination that can be used to write data to.
@@ -158,6 +287,15 @@ pub trait CopyDestination: Send {
     ) -> impl std::future::Future<Output = Result<bool>> + Send {
         async { Ok(false) }
     }
+
+    /// Should run the query, which only returns `text` columns, and return the rows. This is
+    /// used to verify the copied data. Destinations that cannot run queries should return `Ok(None)`.
+    fn get_text_rows(
+        &self,
+        _sql: &str,
+    ) -> impl std::future::Future<Output = Result<Option<Vec<Vec<Option<String>>>>>> + Send {
+        async { Ok(None) }
+    }
 }
 
 /// A type that can be either a sequential or parallel source or destination.
@@ -167,7 +305,7 @@ pub enum SequentialOrParallel<S: Send, P: Send + Clone + Sync> {
 }
 
 /// Indicates if parallelism is supported.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum SupportedParallelism {
     /// Only sequential single-threaded operations are available.
     Sequential,
@@ -194,6 +332,13 @@ impl<S: CopySource, P: CopySource + Clone + Sync> SequentialOrParallel<S, P> {
             SequentialOrParallel::Parallel(p) => p.get_introspection().await,
         }
     }
+
+    pub(crate) async fn get_select_columns(&self, select: &str) -> Result<Vec<String>> {
+        match self {
+            SequentialOrParallel::Sequential(s) => s.get_select_columns(select).await,
+            SequentialOrParallel::Parallel(p) => p.get_select_columns(select).await,
+        }
+    }
 }
 
 impl<S: CopyDestination, P: CopyDestination + Clone + Sync> SequentialOrParallel<S, P> {
@@ -224,6 +369,13 @@ impl<S: CopyDestination, P: CopyDestination + Clone + Sync> SequentialOrParallel
             SequentialOrParallel::Parallel(p) => p.try_introspect().await,
         }
     }
+
+    pub(crate) async fn get_text_rows(&self, sql: &str) -> Result<Option<Vec<Vec<Option<String>>>>> {
+        match self {
+            SequentialOrParallel::Sequential(s) => s.get_text_rows(sql).await,
+            SequentialOrParallel::Parallel(p) => p.get_text_rows(sql).await,
+        }
+    }
 }
 
 /// A CopyDestination that panics when used.
@@ -265,8 +417,25 @@ impl CopyDestination for ParallelCopyDestinationNotAvailable {
     }
 }
 
+/// Percent-encodes the characters of a name that are not safe to use in a file name on every
+/// platform. `.` is encoded as well, as it separates the schema and table in the names of the
+/// data files.
+pub(crate) fn encode_file_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-' {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::test_helpers::{assert_pg_error, TestHelper};
     use tokio_postgres::error::SqlState;
 
@@ -0,0 +1,655 @@
+use crate::models::PostgresSchema;
+use crate::models::PostgresTable;
+use crate::quoting::IdentifierQuoter;
+use crate::storage::data_format::{CopyTextOptions, DataFormat};
+use crate::storage::sql_directory::StatementObject;
+use crate::storage::table_data::TableData;
+use crate::storage::{BaseCopyTarget, CopyDestination};
+use crate::{
+    AsyncCleanup, CopyDestinationFactory, ParallelCopyDestinationNotAvailable, Result, ResultExt,
+    SequentialOrParallel, SupportedParallelism,
+};
+use bytes::Bytes;
+use futures::{pin_mut, Stream, StreamExt};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tracing::{debug, instrument};
+
+#[cfg(test)]
+mod tests;
+
+/// The version of the archive format that is written. This is the version written by pg_dump 14
+/// and 15, which every pg_restore since then can read.
+const ARCHIVE_VERSION: [u8; 3] = [1, 14, 0];
+
+/// The size in bytes of the integers in the archive.
+const INT_SIZE: u8 = 4;
+
+/// The size in bytes of the file offsets in the archive.
+const OFFSET_SIZE: u8 = 8;
+
+/// Identifies the custom format in the header, as opposed to the directory or tar formats.
+const CUSTOM_FORMAT: u8 = 1;
+
+/// Starts the data block of a table entry.
+const DATA_BLOCK: u8 = 1;
+
+/// Marks the offset of an entry as pointing to its data block.
+const OFFSET_SET: u8 = 2;
+
+/// Marks an entry as not having any data.
+const OFFSET_NO_DATA: u8 = 3;
+
+/// The size of the chunks the data blocks are split into, so the length of every chunk fits in
+/// the integers of the archive.
+const MAX_DATA_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The section of the dump an entry belongs to, which decides when pg_restore applies it and
+/// what `--section` selects.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Section {
+    PreData = 2,
+    Data = 3,
+    PostData = 4,
+}
+
+/// An entry in the table of contents of the archive.
+#[derive(Debug)]
+struct TocEntry {
+    dump_id: i32,
+    /// The kind of object, as listed by `pg_restore --list`, such as `TABLE` or `INDEX`.
+    desc: &'static str,
+    namespace: Option<String>,
+    /// The name of the object, which is what `pg_restore --table` and `--index` match.
+    tag: String,
+    section: Section,
+    /// The statement creating the object. Empty for entries with data.
+    defn: String,
+    /// The `copy ... from stdin` statement the data of the entry is loaded with.
+    copy_statement: Option<String>,
+    dependencies: Vec<i32>,
+    /// The table the data or index of the entry belongs to.
+    table: Option<(String, String)>,
+    /// The position of the data block of the entry in the data file.
+    data_position: Option<u64>,
+}
+
+/// A destination that writes a PostgreSQL custom format archive, as written by `pg_dump -Fc`,
+/// which can be restored with `pg_restore`, including in parallel with `--jobs`.
+///
+/// Every statement becomes an entry in the table of contents, named after the object it is
+/// about, so `pg_restore --list` and the selection options of pg_restore work as usual. The data
+/// of each table is written as a `TABLE DATA` entry. The archive is not compressed.
+///
+/// While the copy is running, the data is written to a `.data` file next to the archive, as the
+/// table of contents comes before the data in the archive, but is only known when the copy
+/// finishes.
+pub struct PgCustomFormatDestination {
+    path: PathBuf,
+    data_path: PathBuf,
+    data_file: BufWriter<File>,
+    /// The number of bytes written to the data file.
+    data_position: u64,
+    database_name: String,
+    created_at: SystemTime,
+    quoter: Arc<IdentifierQuoter>,
+    entries: Vec<TocEntry>,
+    /// The dump id of the entry creating each table, by schema and name.
+    table_entries: HashMap<(String, String), i32>,
+    /// Whether the data of a table has been written, after which statements belong to the
+    /// post-data section.
+    has_written_data: bool,
+}
+
+impl PgCustomFormatDestination {
+    /// Creates a new destination writing the archive to the specified file. The database name is
+    /// only used for the header of the archive, and is shown by `pg_restore --list`.
+    pub async fn new(
+        path: impl AsRef<Path>,
+        identifier_quoter: Arc<IdentifierQuoter>,
+        database_name: &str,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let mut data_path = path.clone().into_os_string();
+        data_path.push(".data");
+        let data_path = PathBuf::from(data_path);
+
+        let data_file = File::create(&data_path)
+            .await
+            .with_context_path("create", &data_path)?;
+
+        let mut destination = Self {
+            path,
+            data_path,
+            data_file: BufWriter::new(data_file),
+            data_position: 0,
+            database_name: database_name.to_string(),
+            created_at: SystemTime::now(),
+            quoter: identifier_quoter,
+            entries: Vec::new(),
+            table_entries: HashMap::new(),
+            has_written_data: false,
+        };
+
+        // pg_restore reads the encoding of the data from these entries. The data is always utf-8,
+        // as that is the client encoding of the connections. pg_restore clears the search path
+        // when connecting, so it is set to what the statements are applied with on import, as
+        // not all of them are qualified with a schema.
+        for (desc, defn) in [
+            ("ENCODING", "SET client_encoding = 'UTF8';\n"),
+            ("STDSTRINGS", "SET standard_conforming_strings = 'on';\n"),
+            (
+                "SEARCHPATH",
+                "SELECT pg_catalog.set_config('search_path', 'public', false);\n",
+            ),
+        ] {
+            destination.push_entry(
+                desc,
+                None,
+                desc.to_string(),
+                Section::PreData,
+                defn.to_string(),
+            );
+        }
+
+        Ok(destination)
+    }
+
+    fn push_entry(
+        &mut self,
+        desc: &'static str,
+        namespace: Option<String>,
+        tag: String,
+        section: Section,
+        defn: String,
+    ) -> i32 {
+        let dump_id = self.entries.len() as i32 + 1;
+        self.entries.push(TocEntry {
+            dump_id,
+            desc,
+            namespace,
+            tag,
+            section,
+            defn,
+            copy_statement: None,
+            dependencies: Vec::new(),
+            table: None,
+            data_position: None,
+        });
+        dump_id
+    }
+
+    /// Adds the statement as an entry to the table of contents.
+    fn add_statement(&mut self, statement: &str) {
+        let EntryDescription {
+            desc,
+            namespace,
+            tag,
+            table,
+        } = EntryDescription::parse(statement);
+
+        // Sequences are created after the data, so setting their values stays in the order of the
+        // statements instead of being part of the data section, like pg_dump does.
+        let section = if self.has_written_data {
+            Section::PostData
+        } else {
+            Section::PreData
+        };
+
+        let mut defn = statement.trim().to_string();
+        if !defn.ends_with(';') {
+            defn.push(';');
+        }
+        defn.push('\n');
+
+        debug!("Adding {} entry for {}", desc, tag);
+        let dump_id = self.push_entry(desc, namespace.clone(), tag.clone(), section, defn);
+        self.entries[dump_id as usize - 1].table = table;
+
+        if desc == "TABLE" {
+            if let Some(namespace) = namespace {
+                self.table_entries
+                    .entry((namespace, tag))
+                    .or_insert(dump_id);
+            }
+        }
+    }
+
+    fn get_header(&self) -> Vec<u8> {
+        let mut header = b"PGDMP".to_vec();
+        header.extend(ARCHIVE_VERSION);
+        header.extend([INT_SIZE, OFFSET_SIZE, CUSTOM_FORMAT]);
+
+        // The compression level, the data is not compressed.
+        write_int(&mut header, 0);
+
+        let seconds = self
+            .created_at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        for value in get_creation_time_fields(seconds) {
+            write_int(&mut header, value);
+        }
+
+        write_str(&mut header, Some(&self.database_name));
+        // The version of the server the data was read from, which isn't known here.
+        write_str(&mut header, None);
+        write_str(
+            &mut header,
+            Some(&format!("elefant-tools {}", env!("CARGO_PKG_VERSION"))),
+        );
+
+        header
+    }
+
+    /// Writes the table of contents, with the data blocks placed after it from `data_start`.
+    fn get_toc(&self, data_start: u64) -> Vec<u8> {
+        let mut toc = Vec::new();
+        write_int(&mut toc, self.entries.len() as i32);
+
+        for entry in &self.entries {
+            write_int(&mut toc, entry.dump_id);
+            write_int(&mut toc, entry.data_position.is_some() as i32);
+            // The table oid and oid of the object in the source catalog.
+            write_str(&mut toc, Some("0"));
+            write_str(&mut toc, Some("0"));
+            write_str(&mut toc, Some(&entry.tag));
+            write_str(&mut toc, Some(entry.desc));
+            write_int(&mut toc, entry.section as i32);
+            write_str(&mut toc, Some(&entry.defn));
+            // The drop statement, used by `pg_restore --clean`.
+            write_str(&mut toc, Some(""));
+            write_str(&mut toc, entry.copy_statement.as_deref());
+            write_str(&mut toc, entry.namespace.as_deref());
+            // The tablespace and table access method, which are part of the statements instead.
+            write_str(&mut toc, None);
+            write_str(&mut toc, None);
+            // The owner, which is part of the statements as well.
+            write_str(&mut toc, Some(""));
+            // Whether the table has oids.
+            write_str(&mut toc, Some("false"));
+            for dependency in &entry.dependencies {
+                write_str(&mut toc, Some(&dependency.to_string()));
+            }
+            write_str(&mut toc, None);
+
+            match entry.data_position {
+                Some(position) => write_offset(&mut toc, OFFSET_SET, data_start + position),
+                None => write_offset(&mut toc, OFFSET_NO_DATA, 0),
+            }
+        }
+
+        toc
+    }
+
+    async fn write_archive(&mut self) -> Result<()> {
+        self.data_file.flush().await?;
+
+        add_post_data_dependencies(&mut self.entries);
+
+        let header = self.get_header();
+        let toc_length = self.get_toc(0).len();
+        let toc = self.get_toc((header.len() + toc_length) as u64);
+
+        let file = File::create(&self.path).await?;
+        let mut file = BufWriter::new(file);
+        file.write_all(&header).await?;
+        file.write_all(&toc).await?;
+
+        let mut data = File::open(&self.data_path)
+            .await
+            .with_context_path("open", &self.data_path)?;
+        tokio::io::copy(&mut data, &mut file).await?;
+        file.flush().await?;
+
+        tokio::fs::remove_file(&self.data_path)
+            .await
+            .with_context_path("remove", &self.data_path)?;
+
+        Ok(())
+    }
+}
+
+/// What the table of contents says about the object a statement is about.
+#[derive(Debug, Eq, PartialEq)]
+struct EntryDescription {
+    desc: &'static str,
+    namespace: Option<String>,
+    tag: String,
+    /// The table of an index.
+    table: Option<(String, String)>,
+}
+
+impl EntryDescription {
+    fn parse(statement: &str) -> EntryDescription {
+        let statement = statement.trim_start();
+        let object = StatementObject::parse(statement);
+        let desc = get_entry_desc(statement, &object);
+
+        let (namespace, tag, table) = match object {
+            StatementObject::Schema(name) | StatementObject::Extension(name) => (None, name, None),
+            StatementObject::CreatedRelation {
+                name: (schema, name),
+                ..
+            }
+            | StatementObject::Relation {
+                name: (schema, name),
+                ..
+            }
+            | StatementObject::Other {
+                name: (schema, name),
+                ..
+            } => (Some(schema), name, None),
+            StatementObject::CreatedIndex { index_name, table } => {
+                (Some(table.0.clone()), index_name, Some(table))
+            }
+            StatementObject::Index(name) => (None, name, None),
+            StatementObject::Unknown => {
+                let first_line = statement.lines().next().unwrap_or_default();
+                (None, first_line.trim_end_matches(';').to_string(), None)
+            }
+        };
+
+        EntryDescription {
+            desc,
+            namespace,
+            tag,
+            table,
+        }
+    }
+}
+
+/// Gets the kind of object the statement is about, using the names pg_dump uses where there is
+/// one.
+fn get_entry_desc(statement: &str, object: &StatementObject) -> &'static str {
+    let prefixes = [
+        ("comment on ", "COMMENT"),
+        ("grant ", "ACL"),
+        ("revoke ", "ACL"),
+        ("select pg_catalog.setval(", "SEQUENCE SET"),
+        ("create materialized view ", "MATERIALIZED VIEW"),
+        ("alter materialized view ", "MATERIALIZED VIEW"),
+        ("refresh materialized view ", "MATERIALIZED VIEW DATA"),
+        ("create trigger ", "TRIGGER"),
+        ("create policy ", "POLICY"),
+        ("create procedure ", "PROCEDURE"),
+        ("alter procedure ", "PROCEDURE"),
+        ("create table ", "TABLE"),
+    ];
+    if let Some((_, desc)) = prefixes
+        .iter()
+        .find(|(prefix, _)| statement.starts_with(prefix))
+    {
+        return desc;
+    }
+
+    match object {
+        StatementObject::Schema(_) => "SCHEMA",
+        StatementObject::Extension(_) => "EXTENSION",
+        StatementObject::CreatedIndex { .. } | StatementObject::Index(_) => "INDEX",
+        StatementObject::CreatedRelation { kind, .. } | StatementObject::Relation { kind, .. } => {
+            match *kind {
+                "views" => "VIEW",
+                "sequences" => "SEQUENCE",
+                _ if statement.contains(" foreign key ") => "FK CONSTRAINT",
+                _ if statement.contains(" add constraint ") => "CONSTRAINT",
+                _ if statement.contains(" set default ") => "DEFAULT",
+                _ if statement.contains(" row level security") => "ROW SECURITY",
+                _ => "TABLE",
+            }
+        }
+        StatementObject::Other { kind, .. } => match *kind {
+            "functions" => "FUNCTION",
+            "aggregates" => "AGGREGATE",
+            "types" => "TYPE",
+            "domains" => "DOMAIN",
+            _ => "SQL",
+        },
+        StatementObject::Unknown => "SQL",
+    }
+}
+
+/// Adds the dependencies pg_restore needs to apply the entries after the data in parallel. Each
+/// entry depends on the entry before it that isn't an index, as the statements are only known to
+/// work in the order they were written, and the first of them depends on all the data, so
+/// constraints are not checked against partially loaded tables. Indexes only depend on the data of
+/// their table, so they are built in parallel.
+fn add_post_data_dependencies(entries: &mut [TocEntry]) {
+    let data_entries = entries
+        .iter()
+        .filter(|e| e.section == Section::Data)
+        .map(|e| e.dump_id)
+        .collect::<Vec<_>>();
+    let table_data_entries = entries
+        .iter()
+        .filter(|e| e.data_position.is_some())
+        .filter_map(|e| Some((e.table.clone()?, e.dump_id)))
+        .collect::<HashMap<_, _>>();
+
+    let mut previous = None;
+    let mut indexes = Vec::new();
+
+    for entry in entries
+        .iter_mut()
+        .filter(|e| e.section == Section::PostData)
+    {
+        let is_created_index = entry.desc == "INDEX" && entry.defn.starts_with("create ");
+
+        match previous {
+            Some(previous) => entry.dependencies.push(previous),
+            None => {
+                let table_data = entry
+                    .table
+                    .as_ref()
+                    .filter(|_| is_created_index)
+                    .and_then(|t| table_data_entries.get(t));
+                match table_data {
+                    Some(table_data) => entry.dependencies.push(*table_data),
+                    None => entry.dependencies.extend(&data_entries),
+                }
+            }
+        }
+
+        if is_created_index {
+            indexes.push(entry.dump_id);
+        } else {
+            entry.dependencies.append(&mut indexes);
+            previous = Some(entry.dump_id);
+        }
+    }
+}
+
+/// Writes an integer the way pg_dump does, as a sign byte followed by the absolute value in
+/// little endian.
+fn write_int(buffer: &mut Vec<u8>, value: i32) {
+    buffer.push((value < 0) as u8);
+    buffer.extend(&value.unsigned_abs().to_le_bytes()[..INT_SIZE as usize]);
+}
+
+/// Writes a string as its length followed by the bytes. `None` is written as length -1.
+fn write_str(buffer: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            write_int(buffer, value.len() as i32);
+            buffer.extend(value.as_bytes());
+        }
+        None => write_int(buffer, -1),
+    }
+}
+
+fn write_offset(buffer: &mut Vec<u8>, state: u8, offset: u64) {
+    buffer.push(state);
+    buffer.extend(&offset.to_le_bytes()[..OFFSET_SIZE as usize]);
+}
+
+/// Splits the time into the fields of a C `struct tm` in the order of the archive header: seconds,
+/// minutes, hours, day of month, month from 0, years since 1900 and the daylight saving flag.
+fn get_creation_time_fields(unix_seconds: u64) -> [i32; 7] {
+    let days = (unix_seconds / 86400) as i64;
+    let seconds_of_day = (unix_seconds % 86400) as i32;
+
+    // Converts the days since 1970-01-01 to a date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    [
+        seconds_of_day % 60,
+        seconds_of_day / 60 % 60,
+        seconds_of_day / 3600,
+        day as i32,
+        month as i32 - 1,
+        year as i32 - 1900,
+        0,
+    ]
+}
+
+impl BaseCopyTarget for PgCustomFormatDestination {
+    async fn supported_data_format(&self) -> Result<Vec<DataFormat>> {
+        // pg_restore loads the data with copy statements in the default text format.
+        Ok(vec![DataFormat::Text {
+            options: CopyTextOptions::default(),
+        }])
+    }
+}
+
+impl<'a> CopyDestinationFactory<'a> for PgCustomFormatDestination {
+    type SequentialDestination = &'a mut PgCustomFormatDestination;
+    type ParallelDestination = ParallelCopyDestinationNotAvailable;
+
+    async fn create_destination(
+        &'a mut self,
+    ) -> Result<SequentialOrParallel<Self::SequentialDestination, Self::ParallelDestination>> {
+        Ok(SequentialOrParallel::Sequential(self))
+    }
+
+    async fn create_sequential_destination(&'a mut self) -> Result<Self::SequentialDestination> {
+        Ok(self)
+    }
+
+    fn supported_parallelism(&self) -> SupportedParallelism {
+        SupportedParallelism::Sequential
+    }
+}
+
+impl CopyDestination for &mut PgCustomFormatDestination {
+    #[instrument(skip_all)]
+    async fn apply_data<S: Stream<Item = Result<Bytes>> + Send, C: AsyncCleanup>(
+        &mut self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+        data: TableData<S, C>,
+    ) -> Result<()> {
+        let this = &mut **self;
+        this.has_written_data = true;
+
+        let dump_id = this.push_entry(
+            "TABLE DATA",
+            Some(schema.name.clone()),
+            table.name.clone(),
+            Section::Data,
+            String::new(),
+        );
+        let data_position = this.data_position;
+
+        let copy_statement = table.get_copy_in_command(
+            schema,
+            &DataFormat::Text {
+                options: CopyTextOptions::default(),
+            },
+            &this.quoter,
+        );
+
+        let data_path = this.data_path.clone();
+        let data_file = &mut this.data_file;
+        let written = data
+            .apply_then_cleanup(|stream| async move {
+                let mut block = vec![DATA_BLOCK];
+                write_int(&mut block, dump_id);
+                data_file.write_all(&block).await?;
+                let mut written = block.len() as u64;
+
+                pin_mut!(stream);
+
+                while let Some(bytes) = stream.next().await {
+                    for chunk in bytes?.chunks(MAX_DATA_CHUNK_SIZE) {
+                        let mut length = Vec::new();
+                        write_int(&mut length, chunk.len() as i32);
+                        data_file.write_all(&length).await?;
+                        data_file.write_all(chunk).await?;
+                        written += (length.len() + chunk.len()) as u64;
+                    }
+                }
+
+                let mut end = Vec::new();
+                write_int(&mut end, 0);
+                data_file.write_all(&end).await?;
+                written += end.len() as u64;
+
+                Ok(written)
+            })
+            .await
+            .with_context_path("write to", &data_path)?;
+
+        this.data_position += written;
+
+        let table_entry = this
+            .table_entries
+            .get(&(schema.name.clone(), table.name.clone()))
+            .copied();
+        let entry = &mut this.entries[dump_id as usize - 1];
+        entry.copy_statement = Some(format!("{}\n", copy_statement));
+        entry.dependencies.extend(table_entry);
+        entry.table = Some((schema.name.clone(), table.name.clone()));
+        entry.data_position = Some(data_position);
+
+        Ok(())
+    }
+
+    async fn apply_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        self.add_statement(statement);
+        Ok(())
+    }
+
+    async fn apply_non_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        self.add_statement(statement);
+        Ok(())
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_identifier_quoter(&self) -> Arc<IdentifierQuoter> {
+        self.quoter.clone()
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        let path = self.path.clone();
+        self.write_archive()
+            .await
+            .with_context_path("write to", &path)
+    }
+}
@@ -0,0 +1,425 @@
+use crate::copy_data::{copy_data, CopyDataOptions};
+use crate::schema_reader::tests::introspect_schema;
+use crate::storage::pg_custom_format::*;
+use crate::storage::tests::validate_copy_state;
+use crate::test_helpers;
+use crate::test_helpers::TestHelper;
+use crate::{default, storage, PostgresColumn, PostgresInstanceStorage};
+use elefant_test_macros::pg_test;
+use std::process::Command;
+use uuid::Uuid;
+
+fn temp_file() -> PathBuf {
+    std::env::temp_dir().join(format!("elefant-custom-format-{}.dump", Uuid::new_v4()))
+}
+
+/// Reads back the parts of an archive the tests look at.
+struct ArchiveReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+#[derive(Debug)]
+struct ReadEntry {
+    dump_id: i32,
+    had_dumper: bool,
+    tag: String,
+    desc: String,
+    section: i32,
+    defn: String,
+    copy_statement: Option<String>,
+    namespace: Option<String>,
+    dependencies: Vec<i32>,
+    data_offset: Option<usize>,
+}
+
+impl ArchiveReader<'_> {
+    fn read_bytes(&mut self, count: usize) -> &[u8] {
+        let bytes = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        bytes
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        self.read_bytes(1)[0]
+    }
+
+    fn read_int(&mut self) -> i32 {
+        let sign = self.read_byte();
+        let value = i32::from_le_bytes(self.read_bytes(4).try_into().unwrap());
+        if sign == 1 {
+            -value
+        } else {
+            value
+        }
+    }
+
+    fn read_str(&mut self) -> Option<String> {
+        let length = self.read_int();
+        if length < 0 {
+            return None;
+        }
+        Some(String::from_utf8(self.read_bytes(length as usize).to_vec()).unwrap())
+    }
+
+    fn read_header(&mut self) -> Vec<i32> {
+        assert_eq!(self.read_bytes(5), b"PGDMP");
+        assert_eq!(self.read_bytes(3), [1, 14, 0]);
+        assert_eq!(self.read_bytes(3), [4, 8, 1]);
+        (0..8).map(|_| self.read_int()).collect()
+    }
+
+    fn read_toc(&mut self) -> Vec<ReadEntry> {
+        self.read_header();
+        assert_eq!(self.read_str().as_deref(), Some("test_db"));
+        assert_eq!(self.read_str(), None);
+        self.read_str().unwrap();
+
+        let count = self.read_int();
+        (0..count)
+            .map(|_| {
+                let dump_id = self.read_int();
+                let had_dumper = self.read_int() == 1;
+                self.read_str();
+                self.read_str();
+                let tag = self.read_str().unwrap();
+                let desc = self.read_str().unwrap();
+                let section = self.read_int();
+                let defn = self.read_str().unwrap();
+                self.read_str();
+                let copy_statement = self.read_str();
+                let namespace = self.read_str();
+                self.read_str();
+                self.read_str();
+                self.read_str();
+                assert_eq!(self.read_str().as_deref(), Some("false"));
+                let mut dependencies = Vec::new();
+                while let Some(dependency) = self.read_str() {
+                    dependencies.push(dependency.parse().unwrap());
+                }
+                let state = self.read_byte();
+                let offset = u64::from_le_bytes(self.read_bytes(8).try_into().unwrap());
+
+                ReadEntry {
+                    dump_id,
+                    had_dumper,
+                    tag,
+                    desc,
+                    section,
+                    defn,
+                    copy_statement,
+                    namespace,
+                    dependencies,
+                    data_offset: (state == OFFSET_SET).then_some(offset as usize),
+                }
+            })
+            .collect()
+    }
+
+    fn read_data_block(&mut self, offset: usize) -> (i32, String) {
+        self.position = offset;
+        assert_eq!(self.read_byte(), DATA_BLOCK);
+        let dump_id = self.read_int();
+
+        let mut data = Vec::new();
+        loop {
+            let length = self.read_int();
+            if length == 0 {
+                break;
+            }
+            data.extend(self.read_bytes(length as usize));
+        }
+
+        (dump_id, String::from_utf8(data).unwrap())
+    }
+}
+
+fn description(desc: &'static str, namespace: Option<&str>, tag: &str) -> EntryDescription {
+    EntryDescription {
+        desc,
+        namespace: namespace.map(|n| n.to_string()),
+        tag: tag.to_string(),
+        table: None,
+    }
+}
+
+#[test]
+fn entries_are_described_by_their_statement() {
+    let cases = [
+        (
+            "create schema if not exists sales;",
+            description("SCHEMA", None, "sales"),
+        ),
+        (
+            "create extension if not exists pg_trgm;",
+            description("EXTENSION", None, "pg_trgm"),
+        ),
+        (
+            "create table public.people (id int4 not null);",
+            description("TABLE", Some("public"), "people"),
+        ),
+        (
+            "create view public.adults as select 1;",
+            description("VIEW", Some("public"), "adults"),
+        ),
+        (
+            "create materialized view public.totals as select 1;",
+            description("MATERIALIZED VIEW", Some("public"), "totals"),
+        ),
+        (
+            "alter table public.people add constraint people_pkey primary key (id);",
+            description("CONSTRAINT", Some("public"), "people"),
+        ),
+        (
+            "alter table public.orders add constraint orders_fk foreign key (person_id) references public.people (id);",
+            description("FK CONSTRAINT", Some("public"), "orders"),
+        ),
+        (
+            "alter table public.people alter column id set default nextval('public.people_id_seq');",
+            description("DEFAULT", Some("public"), "people"),
+        ),
+        (
+            "comment on table public.people is 'Everyone';",
+            description("COMMENT", Some("public"), "people"),
+        ),
+        (
+            "grant select on table public.people to reader;",
+            description("ACL", Some("public"), "people"),
+        ),
+        (
+            "select pg_catalog.setval('public.people_id_seq', 3, true);",
+            description("SEQUENCE SET", Some("public"), "people_id_seq"),
+        ),
+        (
+            "create function public.add(a int4, b int4) returns int4 as $$ select a + b $$ language sql;",
+            description("FUNCTION", Some("public"), "add"),
+        ),
+        (
+            "create trigger audit after insert on public.people for each row execute function public.audit();",
+            description("TRIGGER", Some("public"), "people"),
+        ),
+        (
+            "insert into public.empty select from generate_series(1, 3);",
+            description(
+                "SQL",
+                None,
+                "insert into public.empty select from generate_series(1, 3)",
+            ),
+        ),
+    ];
+
+    for (statement, expected) in cases {
+        assert_eq!(
+            EntryDescription::parse(statement),
+            expected,
+            "{}",
+            statement
+        );
+    }
+
+    assert_eq!(
+        EntryDescription::parse(
+            "create index people_name_idx on public.people using btree (name);"
+        ),
+        EntryDescription {
+            desc: "INDEX",
+            namespace: Some("public".to_string()),
+            tag: "people_name_idx".to_string(),
+            table: Some(("public".to_string(), "people".to_string())),
+        }
+    );
+}
+
+#[test]
+fn creation_time_is_split_into_date_fields() {
+    assert_eq!(get_creation_time_fields(0), [0, 0, 0, 1, 0, 70, 0]);
+    // 2024-02-29 12:34:56 UTC
+    assert_eq!(
+        get_creation_time_fields(1709210096),
+        [56, 34, 12, 29, 1, 124, 0]
+    );
+}
+
+#[test]
+fn integers_are_written_with_a_sign_byte() {
+    let mut buffer = Vec::new();
+    write_int(&mut buffer, 258);
+    write_int(&mut buffer, -1);
+    write_str(&mut buffer, Some("ab"));
+    write_str(&mut buffer, None);
+
+    assert_eq!(
+        buffer,
+        [0, 2, 1, 0, 0, 1, 1, 0, 0, 0, 0, 2, 0, 0, 0, b'a', b'b', 1, 1, 0, 0, 0]
+    );
+}
+
+#[tokio::test]
+async fn writes_the_data_after_the_table_of_contents() {
+    let path = temp_file();
+    let mut destination =
+        PgCustomFormatDestination::new(&path, Arc::new(IdentifierQuoter::empty()), "test_db")
+            .await
+            .unwrap();
+    let mut destination = &mut destination;
+
+    let schema = PostgresSchema {
+        name: "public".to_string(),
+        ..default()
+    };
+    let table = PostgresTable {
+        name: "people".to_string(),
+        columns: vec![PostgresColumn {
+            name: "name".to_string(),
+            ordinal_position: 1,
+            data_type: "text".to_string(),
+            ..default()
+        }],
+        ..default()
+    };
+
+    destination
+        .apply_transactional_statement("create table public.people (name text);")
+        .await
+        .unwrap();
+    let rows = ["Alice\n", "Bob\n"].map(|row| Ok(Bytes::from(row)));
+    let data = TableData {
+        data: futures::stream::iter(rows),
+        data_format: DataFormat::Text {
+            options: CopyTextOptions::default(),
+        },
+        cleanup: (),
+    };
+    destination.apply_data(&schema, &table, data).await.unwrap();
+    for statement in [
+        "create index people_name_idx on public.people using btree (name);",
+        "create index people_upper_name_idx on public.people using btree (upper(name));",
+        "alter table public.people add constraint people_name_key unique (name)",
+        "create index people_lower_name_idx on public.people using btree (lower(name));",
+    ] {
+        destination
+            .apply_transactional_statement(statement)
+            .await
+            .unwrap();
+    }
+    destination.finish().await.unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+    let mut reader = ArchiveReader {
+        bytes: &bytes,
+        position: 0,
+    };
+    let entries = reader.read_toc();
+
+    let descs = entries.iter().map(|e| e.desc.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        descs,
+        vec![
+            "ENCODING",
+            "STDSTRINGS",
+            "SEARCHPATH",
+            "TABLE",
+            "TABLE DATA",
+            "INDEX",
+            "INDEX",
+            "CONSTRAINT",
+            "INDEX"
+        ]
+    );
+    assert_eq!(
+        entries.iter().map(|e| e.section).collect::<Vec<_>>(),
+        vec![2, 2, 2, 2, 3, 4, 4, 4, 4]
+    );
+    assert_eq!(entries[3].defn, "create table public.people (name text);\n");
+    assert_eq!(
+        entries[7].defn,
+        "alter table public.people add constraint people_name_key unique (name);\n"
+    );
+
+    let data_entry = &entries[4];
+    assert!(data_entry.had_dumper);
+    assert_eq!(data_entry.tag, "people");
+    assert_eq!(data_entry.namespace.as_deref(), Some("public"));
+    assert_eq!(
+        data_entry.copy_statement.as_deref(),
+        Some("copy public.people (name) from stdin with (format text, header false);\n")
+    );
+    assert_eq!(data_entry.dependencies, vec![4]);
+    assert!(entries
+        .iter()
+        .filter(|e| !e.had_dumper)
+        .all(|e| e.data_offset.is_none()));
+
+    let dependencies = entries[5..]
+        .iter()
+        .map(|e| e.dependencies.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(dependencies, vec![vec![5], vec![5], vec![5, 6, 7], vec![8]]);
+
+    let (dump_id, data) = reader.read_data_block(data_entry.data_offset.unwrap());
+    assert_eq!(dump_id, data_entry.dump_id);
+    assert_eq!(data, "Alice\nBob\n");
+    assert_eq!(reader.position, bytes.len());
+
+    assert!(!path.with_extension("dump.data").exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+async fn restores_with_pg_restore(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(storage::tests::get_copy_source_database_create_script(
+            source.get_conn().version(),
+        ))
+        .await;
+
+    let path = temp_file();
+    {
+        let mut custom_format =
+            PgCustomFormatDestination::new(&path, Arc::new(IdentifierQuoter::empty()), "test_db")
+                .await
+                .unwrap();
+        let source = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+
+        copy_data(&source, &mut custom_format, CopyDataOptions::default())
+            .await
+            .unwrap();
+    }
+
+    let list = Command::new("pg_restore")
+        .arg("--list")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(
+        list.status.success(),
+        "{}",
+        String::from_utf8_lossy(&list.stderr)
+    );
+    assert!(String::from_utf8_lossy(&list.stdout).contains("TABLE DATA public people"));
+
+    let restore = Command::new("pg_restore")
+        .arg("--exit-on-error")
+        .arg("--jobs=4")
+        .arg(format!("--dbname={}", destination.get_connection_string()))
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(
+        restore.status.success(),
+        "{}",
+        String::from_utf8_lossy(&restore.stderr)
+    );
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+
+    assert_eq!(source_schema, destination_schema);
+
+    validate_copy_state(destination).await;
+
+    std::fs::remove_file(&path).unwrap();
+}
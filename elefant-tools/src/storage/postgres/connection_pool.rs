@@ -1,30 +1,110 @@
 use crate::{AsyncCleanup, PostgresClientWrapper};
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::ops::Deref;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
 
-/// A very simple connection pool for Postgres connections.
-#[derive(Clone, Default)]
+/// A pool of Postgres connections, that has at most `max_size` connections open at a time.
+///
+/// Connections are borrowed with [ConnectionPool::get_connection] and handed back with
+/// [ConnectionPool::release_connection], so the next borrower can reuse them. A connection that
+/// is dropped rather than released is closed, which frees its place in the pool. Idle connections
+/// are checked with a `select 1` before they are handed out again, and replaced by a new
+/// connection if the check fails.
+///
+/// Cloning the pool gives another handle to the same connections.
+#[derive(Clone)]
 pub struct ConnectionPool {
-    connection_pool: Arc<Mutex<Vec<PostgresClientWrapper>>>,
+    idle_connections: Arc<Mutex<Vec<PostgresClientWrapper>>>,
+    permits: Arc<Semaphore>,
+    max_size: NonZeroUsize,
 }
 
 impl ConnectionPool {
-    pub fn new() -> Self {
+    /// Creates a pool that has at most `max_size` connections open at a time.
+    pub fn new(max_size: NonZeroUsize) -> Self {
         Self {
-            connection_pool: Arc::new(Mutex::new(Vec::new())),
+            idle_connections: Arc::new(Mutex::new(Vec::new())),
+            permits: Arc::new(Semaphore::new(max_size.get())),
+            max_size,
         }
     }
 
-    /// Get a connection from the pool. If the pool is empty, this will return `None`.
-    pub async fn get_connection(&self) -> Option<PostgresClientWrapper> {
-        let mut pool = self.connection_pool.lock().await;
-        pool.pop()
+    /// Creates a pool that opens a new connection whenever none are idle.
+    pub fn unbounded() -> Self {
+        Self::new(NonZeroUsize::new(Semaphore::MAX_PERMITS).unwrap())
+    }
+
+    /// The maximum number of connections the pool has open at a time.
+    pub fn max_size(&self) -> NonZeroUsize {
+        self.max_size
+    }
+
+    /// Borrows a connection from the pool, waiting for one to be released if the pool is full.
+    /// An idle connection is reused if one is still alive, otherwise `connect` is called to open
+    /// a new connection.
+    pub async fn get_connection<F, Fut>(&self, connect: F) -> crate::Result<PooledConnection>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = crate::Result<PostgresClientWrapper>>,
+    {
+        let permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .expect("The semaphore of the connection pool is never closed");
+
+        loop {
+            let idle_connection = self.idle_connections.lock().await.pop();
+            let Some(connection) = idle_connection else {
+                break;
+            };
+
+            match connection.execute_non_query("select 1").await {
+                Ok(()) => {
+                    return Ok(PooledConnection {
+                        connection,
+                        _permit: permit,
+                    })
+                }
+                Err(e) => warn!("Discarding pooled connection that failed the health check: {}", e),
+            }
+        }
+
+        let connection = connect().await?;
+
+        Ok(PooledConnection {
+            connection,
+            _permit: permit,
+        })
     }
 
     /// Release a connection back to the pool.
-    pub async fn release_connection(&self, connection: PostgresClientWrapper) {
-        let mut pool = self.connection_pool.lock().await;
-        pool.push(connection);
+    pub async fn release_connection(&self, connection: PooledConnection) {
+        let mut idle_connections = self.idle_connections.lock().await;
+        idle_connections.push(connection.connection);
+    }
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        Self::unbounded()
+    }
+}
+
+/// A connection borrowed from a [ConnectionPool]. The connection is closed when this is dropped,
+/// use [ConnectionPool::release_connection] to hand it back to the pool instead.
+pub struct PooledConnection {
+    connection: PostgresClientWrapper,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledConnection {
+    type Target = PostgresClientWrapper;
+
+    fn deref(&self) -> &Self::Target {
+        &self.connection
     }
 }
 
@@ -32,12 +112,12 @@ impl ConnectionPool {
 /// is called.
 pub struct ReleaseConnection {
     pool: ConnectionPool,
-    connection: PostgresClientWrapper,
+    connection: PooledConnection,
 }
 
 impl ReleaseConnection {
     /// Create a new `ReleaseConnection` instance.
-    pub fn new(pool: ConnectionPool, connection: PostgresClientWrapper) -> Self {
+    pub fn new(pool: ConnectionPool, connection: PooledConnection) -> Self {
         Self { pool, connection }
     }
 }
@@ -1,4 +1,4 @@
-mod connection_pool;
+pub(crate) mod connection_pool;
 mod parallel_copy_destination;
 mod parallel_copy_source;
 mod postgres_instance_storage;
@@ -7,4 +7,17 @@ mod sequential_copy_source;
 #[cfg(test)]
 mod tests;
 
+use crate::DataFormat;
+pub use connection_pool::{ConnectionPool, PooledConnection};
 pub use postgres_instance_storage::PostgresInstanceStorage;
+
+/// Builds the command for copying the result of a select query out of the database.
+fn get_copy_out_select_command(select: &str, data_format: &DataFormat) -> String {
+    let select = select.trim().trim_end_matches(';');
+
+    format!(
+        "copy ({}) to stdout with ({}, encoding 'utf-8');",
+        select,
+        data_format.get_copy_options()
+    )
+}
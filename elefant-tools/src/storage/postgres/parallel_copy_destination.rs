@@ -1,11 +1,11 @@
 use crate::helpers::IMPORT_PREFIX;
 use crate::quoting::{AttemptedKeywordUsage, Quotable};
 use crate::schema_reader::SchemaReader;
-use crate::storage::postgres::connection_pool::ConnectionPool;
+use crate::storage::postgres::connection_pool::{ConnectionPool, PooledConnection};
 use crate::storage::postgres::postgres_instance_storage::PostgresInstanceStorage;
 use crate::{
     AsyncCleanup, CopyDestination, IdentifierQuoter, PostgresClientWrapper, PostgresDatabase,
-    PostgresSchema, PostgresTable, TableData,
+    PostgresSchema, PostgresTable, ResultExt, TableData,
 };
 use bytes::Bytes;
 use futures::{pin_mut, SinkExt, Stream, StreamExt};
@@ -30,26 +30,26 @@ impl<'a> ParallelSafePostgresInstanceCopyDestinationStorage<'a> {
         main_connection.execute_non_query(IMPORT_PREFIX).await?;
 
         Ok(ParallelSafePostgresInstanceCopyDestinationStorage {
-            connection_pool: ConnectionPool::new(),
+            connection_pool: storage.connection_pool.clone(),
             main_connection,
             identifier_quoter: storage.identifier_quoter.clone(),
             in_flight_statements: Arc::new(tokio::sync::Mutex::new(HashSet::new())),
         })
     }
 
-    async fn get_connection(&self) -> crate::Result<PostgresClientWrapper> {
-        if let Some(existing) = self.connection_pool.get_connection().await {
-            Ok(existing)
-        } else {
-            let new_conn = self.main_connection.create_another_connection().await?;
+    async fn get_connection(&self) -> crate::Result<PooledConnection> {
+        self.connection_pool
+            .get_connection(|| async {
+                let new_conn = self.main_connection.create_another_connection().await?;
 
-            new_conn.execute_non_query(IMPORT_PREFIX).await?;
+                new_conn.execute_non_query(IMPORT_PREFIX).await?;
 
-            Ok(new_conn)
-        }
+                Ok(new_conn)
+            })
+            .await
     }
 
-    async fn release_connection(&self, connection: PostgresClientWrapper) {
+    async fn release_connection(&self, connection: PooledConnection) {
         self.connection_pool.release_connection(connection).await;
     }
 }
@@ -61,28 +61,31 @@ impl<'a> CopyDestination for ParallelSafePostgresInstanceCopyDestinationStorage<
         table: &PostgresTable,
         data: TableData<S, C>,
     ) -> crate::Result<()> {
-        let data_format = data.data_format;
-
         let copy_statement =
-            table.get_copy_in_command(schema, &data_format, &self.identifier_quoter);
-
-        let connection = self.get_connection().await?;
+            table.get_copy_in_command(schema, &data.data_format, &self.identifier_quoter);
+        let this = &*self;
 
-        let sink = connection.copy_in::<Bytes>(&copy_statement).await?;
-        pin_mut!(sink);
+        // The connection is only released on success, as it might be stuck in the
+        // middle of a copy otherwise.
+        let connection = data
+            .apply_then_cleanup(|stream| async move {
+                let connection = this.get_connection().await?;
 
-        let stream = data.data;
+                let sink = connection.copy_in::<Bytes>(&copy_statement).await?;
+                pin_mut!(sink);
+                pin_mut!(stream);
 
-        pin_mut!(stream);
+                while let Some(item) = stream.next().await {
+                    let item = item?;
+                    sink.feed(item).await.with_query(&copy_statement)?;
+                }
 
-        while let Some(item) = stream.next().await {
-            let item = item?;
-            sink.feed(item).await?;
-        }
+                sink.close().await.with_query(&copy_statement)?;
 
-        sink.close().await?;
+                Ok(connection)
+            })
+            .await?;
 
-        data.cleanup.cleanup().await?;
         self.release_connection(connection).await;
 
         Ok(())
@@ -170,4 +173,8 @@ impl<'a> CopyDestination for ParallelSafePostgresInstanceCopyDestinationStorage<
             .await?;
         Ok(result)
     }
+
+    async fn get_text_rows(&self, sql: &str) -> crate::Result<Option<Vec<Vec<Option<String>>>>> {
+        Ok(Some(self.main_connection.get_results(sql).await?))
+    }
 }
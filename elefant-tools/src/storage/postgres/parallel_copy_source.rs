@@ -1,5 +1,8 @@
+use super::get_copy_out_select_command;
 use crate::schema_reader::SchemaReader;
-use crate::storage::postgres::connection_pool::{ConnectionPool, ReleaseConnection};
+use crate::storage::postgres::connection_pool::{
+    ConnectionPool, PooledConnection, ReleaseConnection,
+};
 use crate::storage::postgres::postgres_instance_storage::PostgresInstanceStorage;
 use crate::{
     CopySource, DataFormat, ElefantToolsError, IdentifierQuoter, PostgresClientWrapper,
@@ -35,24 +38,26 @@ impl<'a> ParallelSafePostgresInstanceCopySourceStorage<'a> {
             .get_single_result("select pg_export_snapshot();")
             .await?;
 
+        // The connections are bound to the snapshot of this source, so they can't be shared
+        // with the pool of the storage. The source gets a pool of the same size instead.
         Ok(ParallelSafePostgresInstanceCopySourceStorage {
-            connection_pool: ConnectionPool::new(),
+            connection_pool: ConnectionPool::new(storage.connection_pool.max_size()),
             transaction_id,
             main_connection,
             identifier_quoter: storage.identifier_quoter.clone(),
         })
     }
 
-    async fn get_connection(&self) -> crate::Result<PostgresClientWrapper> {
-        if let Some(existing) = self.connection_pool.get_connection().await {
-            Ok(existing)
-        } else {
-            let new_conn = self.main_connection.create_another_connection().await?;
+    async fn get_connection(&self) -> crate::Result<PooledConnection> {
+        self.connection_pool
+            .get_connection(|| async {
+                let new_conn = self.main_connection.create_another_connection().await?;
 
-            new_conn.execute_non_query(&format!("begin transaction isolation level repeatable read read only; set transaction snapshot '{}';", self.transaction_id)).await?;
+                new_conn.execute_non_query(&format!("begin transaction isolation level repeatable read read only; set transaction snapshot '{}';", self.transaction_id)).await?;
 
-            Ok(new_conn)
-        }
+                Ok(new_conn)
+            })
+            .await
     }
 }
 
@@ -74,9 +79,38 @@ impl<'a> CopySource for ParallelSafePostgresInstanceCopySourceStorage<'a> {
     ) -> crate::Result<TableData<Self::DataStream, Self::Cleanup>> {
         let copy_command = table.get_copy_out_command(schema, data_format, &self.identifier_quoter);
 
+        self.copy_out(&copy_command, data_format).await
+    }
+
+    #[instrument(skip_all)]
+    async fn get_data_from_select(
+        &self,
+        select: &str,
+        data_format: &DataFormat,
+    ) -> crate::Result<TableData<Self::DataStream, Self::Cleanup>> {
+        let copy_command = get_copy_out_select_command(select, data_format);
+
+        self.copy_out(&copy_command, data_format).await
+    }
+
+    async fn get_select_columns(&self, select: &str) -> crate::Result<Vec<String>> {
+        self.main_connection.get_column_names(select).await
+    }
+
+    async fn get_text_rows(&self, sql: &str) -> crate::Result<Option<Vec<Vec<Option<String>>>>> {
+        Ok(Some(self.main_connection.get_results(sql).await?))
+    }
+}
+
+impl ParallelSafePostgresInstanceCopySourceStorage<'_> {
+    async fn copy_out(
+        &self,
+        copy_command: &str,
+        data_format: &DataFormat,
+    ) -> crate::Result<TableData<<Self as CopySource>::DataStream, ReleaseConnection>> {
         let connection = self.get_connection().await?;
 
-        let copy_out_stream = connection.copy_out(&copy_command).await?;
+        let copy_out_stream = connection.copy_out(copy_command).await?;
 
         let stream = copy_out_stream.map_err(
             tokio_postgres_error_to_crate_error as fn(tokio_postgres::Error) -> ElefantToolsError,
@@ -4,9 +4,14 @@ use crate::storage::postgres::parallel_copy_destination::ParallelSafePostgresIns
 use crate::storage::postgres::parallel_copy_source::ParallelSafePostgresInstanceCopySourceStorage;
 use crate::storage::postgres::sequential_copy_destination::SequentialSafePostgresInstanceCopyDestinationStorage;
 use crate::storage::postgres::sequential_copy_source::SequentialSafePostgresInstanceCopySourceStorage;
+use crate::database_encoding::find_invalidly_encoded_columns;
+use crate::storage::postgres::connection_pool::ConnectionPool;
+use crate::server_capabilities::ServerCapabilities;
 use crate::{
-    BaseCopyTarget, CopyDestinationFactory, CopySourceFactory, DataFormat, ElefantToolsError,
-    IdentifierQuoter, PostgresClientWrapper, SequentialOrParallel, SupportedParallelism,
+    BaseCopyTarget, CopyDestinationFactory, CopyTextOptions, CopySourceFactory, DataFormat, DatabaseEncoding,
+    ElefantToolsError,
+    IdentifierQuoter, InvalidlyEncodedColumn, PostgresClientWrapper, PostgresDatabase, SequentialOrParallel,
+    SupportedParallelism,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -14,16 +19,34 @@ use tokio_postgres::Row;
 use tracing::instrument;
 
 /// A CopyTarget for Postgres.
+///
+/// Schema changes are applied on `connection`, while data is copied on the connections borrowed
+/// from the connection pool.
 pub struct PostgresInstanceStorage<'a> {
     pub(crate) connection: &'a PostgresClientWrapper,
+    pub(crate) connection_pool: ConnectionPool,
     pub(crate) postgres_version: String,
+    pub(crate) capabilities: ServerCapabilities,
     pub(crate) identifier_quoter: Arc<IdentifierQuoter>,
 }
 
 impl<'a> PostgresInstanceStorage<'a> {
-    #[instrument(skip_all)]
+    /// Creates a storage that opens another connection to the same database whenever a parallel
+    /// copy needs one.
     pub async fn new(connection: &'a PostgresClientWrapper) -> crate::Result<Self> {
+        Self::with_connection_pool(connection, ConnectionPool::unbounded()).await
+    }
+
+    /// Creates a storage that borrows the connections for parallel copies from the pool, so no
+    /// more than [ConnectionPool::max_size] connections are open besides `connection`. The pool
+    /// must not be shared with storages of other databases, as its idle connections are reused.
+    #[instrument(skip_all)]
+    pub async fn with_connection_pool(
+        connection: &'a PostgresClientWrapper,
+        connection_pool: ConnectionPool,
+    ) -> crate::Result<Self> {
         let postgres_version = connection.get_single_result("select version()").await?;
+        let capabilities = ServerCapabilities::read(connection).await?;
 
         let keywords = connection
             .get_results::<Keyword>(
@@ -49,7 +72,9 @@ impl<'a> PostgresInstanceStorage<'a> {
 
         Ok(PostgresInstanceStorage {
             connection,
+            connection_pool,
             postgres_version,
+            capabilities,
             identifier_quoter: Arc::new(quoter),
         })
     }
@@ -57,6 +82,12 @@ impl<'a> PostgresInstanceStorage<'a> {
     pub fn get_identifier_quoter(&self) -> Arc<IdentifierQuoter> {
         self.identifier_quoter.clone()
     }
+
+    /// What the server supports, as of when the storage was created. Extensions installed
+    /// afterwards, such as by a copy into the database, are not reflected.
+    pub fn capabilities(&self) -> ServerCapabilities {
+        self.capabilities
+    }
 }
 
 struct Keyword {
@@ -96,12 +127,114 @@ impl FromPgChar for KeywordType {
 impl BaseCopyTarget for PostgresInstanceStorage<'_> {
     async fn supported_data_format(&self) -> crate::Result<Vec<DataFormat>> {
         Ok(vec![
-            DataFormat::Text,
+            DataFormat::Text { options: CopyTextOptions::default() },
             DataFormat::PostgresBinary {
                 postgres_version: Some(self.postgres_version.clone()),
             },
+            DataFormat::Csv {
+                header: true,
+                delimiter: ',',
+            },
         ])
     }
+
+    async fn get_database_encoding(&self) -> crate::Result<Option<DatabaseEncoding>> {
+        Ok(Some(DatabaseEncoding::read(self.connection).await?))
+    }
+
+    async fn get_table_access_methods(&self) -> crate::Result<Option<Vec<String>>> {
+        Ok(Some(
+            self.connection
+                .get_single_results("select amname::text from pg_am where amtype = 't'")
+                .await?,
+        ))
+    }
+
+    async fn get_tablespaces(&self) -> crate::Result<Option<Vec<String>>> {
+        Ok(Some(
+            self.connection
+                .get_single_results("select spcname::text from pg_tablespace")
+                .await?,
+        ))
+    }
+
+    async fn get_roles(&self) -> crate::Result<Option<Vec<String>>> {
+        Ok(Some(
+            self.connection
+                .get_single_results("select rolname::text from pg_roles")
+                .await?,
+        ))
+    }
+
+    async fn get_installed_extensions(&self) -> crate::Result<Option<Vec<String>>> {
+        Ok(Some(
+            self.connection
+                .get_single_results("select extname::text from pg_extension")
+                .await?,
+        ))
+    }
+
+    async fn get_available_extensions(&self) -> crate::Result<Option<Vec<String>>> {
+        Ok(Some(
+            self.connection
+                .get_single_results("select name::text from pg_available_extensions")
+                .await?,
+        ))
+    }
+
+    async fn get_superuser_only_extensions(&self) -> crate::Result<Option<Vec<String>>> {
+        // Trusted extensions can be created by anyone with create privileges on the database,
+        // but they were only added in Postgres 13.
+        let untrusted = if self.capabilities.supports_trusted_extensions {
+            "and not ver.trusted"
+        } else {
+            ""
+        };
+
+        Ok(Some(
+            self.connection
+                .get_single_results(&format!(
+                    r#"
+select ext.name::text
+from pg_available_extensions ext
+         join pg_available_extension_versions ver on ver.name = ext.name and ver.version = ext.default_version
+where ver.superuser {untrusted}
+  and not (select rolsuper from pg_roles where rolname = current_user)
+"#
+                ))
+                .await?,
+        ))
+    }
+
+    async fn get_server_identity(&self) -> crate::Result<Option<String>> {
+        // The port and the start time of the server are readable by any user, unlike the
+        // data directory and the system identifier.
+        Ok(Some(
+            self.connection
+                .get_single_result(
+                    "select current_setting('port') || ' ' || pg_postmaster_start_time()::text",
+                )
+                .await?,
+        ))
+    }
+
+    async fn get_database_size(&self) -> crate::Result<Option<u64>> {
+        let size: i64 = self
+            .connection
+            .get_single_result("select pg_database_size(current_database())")
+            .await?;
+
+        Ok(Some(size as u64))
+    }
+
+    fn get_postgres_connection(&self) -> Option<&PostgresClientWrapper> {
+        Some(self.connection)
+    }
+
+    async fn get_server_capabilities(&self) -> crate::Result<Option<ServerCapabilities>> {
+        // Read again, as extensions might have been installed since the storage was created.
+        Ok(Some(ServerCapabilities::read(self.connection).await?))
+    }
 }
 
 impl<'a> CopySourceFactory for PostgresInstanceStorage<'a> {
@@ -125,6 +258,26 @@ impl<'a> CopySourceFactory for PostgresInstanceStorage<'a> {
     fn supported_parallelism(&self) -> SupportedParallelism {
         SupportedParallelism::Parallel
     }
+
+    async fn find_invalidly_encoded_columns(
+        &self,
+        definition: &PostgresDatabase,
+        destination_encoding: &str,
+    ) -> crate::Result<Option<Vec<InvalidlyEncodedColumn>>> {
+        // The main connection might be in the transaction of a source already, which the failing
+        // checks would abort.
+        let connection = self.connection.create_another_connection().await?;
+
+        Ok(Some(
+            find_invalidly_encoded_columns(
+                &connection,
+                definition,
+                destination_encoding,
+                &self.identifier_quoter,
+            )
+            .await?,
+        ))
+    }
 }
 
 impl<'a> CopyDestinationFactory<'a> for PostgresInstanceStorage<'a> {
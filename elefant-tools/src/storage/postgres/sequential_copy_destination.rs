@@ -4,7 +4,7 @@ use crate::schema_reader::SchemaReader;
 use crate::storage::postgres::postgres_instance_storage::PostgresInstanceStorage;
 use crate::{
     AsyncCleanup, CopyDestination, IdentifierQuoter, PostgresClientWrapper, PostgresDatabase,
-    PostgresSchema, PostgresTable, TableData,
+    PostgresSchema, PostgresTable, ResultExt, TableData,
 };
 use bytes::Bytes;
 use futures::{pin_mut, SinkExt, Stream, StreamExt};
@@ -37,28 +37,25 @@ impl<'a> CopyDestination for SequentialSafePostgresInstanceCopyDestinationStorag
         table: &PostgresTable,
         data: TableData<S, C>,
     ) -> crate::Result<()> {
-        let data_format = data.data_format;
-
         let copy_statement =
-            table.get_copy_in_command(schema, &data_format, &self.identifier_quoter);
-
-        let sink = self.connection.copy_in::<Bytes>(&copy_statement).await?;
-        pin_mut!(sink);
-
-        let stream = data.data;
-
-        pin_mut!(stream);
+            table.get_copy_in_command(schema, &data.data_format, &self.identifier_quoter);
+        let connection = &self.connection;
 
-        while let Some(item) = stream.next().await {
-            let item = item?;
-            sink.feed(item).await?;
-        }
+        data.apply_then_cleanup(|stream| async move {
+            let sink = connection.copy_in::<Bytes>(&copy_statement).await?;
+            pin_mut!(sink);
+            pin_mut!(stream);
 
-        sink.close().await?;
+            while let Some(item) = stream.next().await {
+                let item = item?;
+                sink.feed(item).await.with_query(&copy_statement)?;
+            }
 
-        data.cleanup.cleanup().await?;
+            sink.close().await.with_query(&copy_statement)?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     async fn apply_transactional_statement(&mut self, statement: &str) -> crate::Result<()> {
@@ -112,4 +109,8 @@ impl<'a> CopyDestination for SequentialSafePostgresInstanceCopyDestinationStorag
         let result = self.connection.get_single_result::<bool>(&query).await?;
         Ok(result)
     }
+
+    async fn get_text_rows(&self, sql: &str) -> crate::Result<Option<Vec<Vec<Option<String>>>>> {
+        Ok(Some(self.connection.get_results(sql).await?))
+    }
 }
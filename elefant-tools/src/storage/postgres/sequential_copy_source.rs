@@ -1,3 +1,4 @@
+use super::get_copy_out_select_command;
 use crate::schema_reader::SchemaReader;
 use crate::{
     CopySource, DataFormat, ElefantToolsError, IdentifierQuoter, PostgresClientWrapper,
@@ -50,7 +51,36 @@ impl<'a> CopySource for SequentialSafePostgresInstanceCopySourceStorage<'a> {
     ) -> crate::Result<TableData<Self::DataStream, Self::Cleanup>> {
         let copy_command = table.get_copy_out_command(schema, data_format, &self.identifier_quoter);
 
-        let copy_out_stream = self.connection.copy_out(&copy_command).await?;
+        self.copy_out(&copy_command, data_format).await
+    }
+
+    #[instrument(skip_all)]
+    async fn get_data_from_select(
+        &self,
+        select: &str,
+        data_format: &DataFormat,
+    ) -> crate::Result<TableData<Self::DataStream, Self::Cleanup>> {
+        let copy_command = get_copy_out_select_command(select, data_format);
+
+        self.copy_out(&copy_command, data_format).await
+    }
+
+    async fn get_select_columns(&self, select: &str) -> crate::Result<Vec<String>> {
+        self.connection.get_column_names(select).await
+    }
+
+    async fn get_text_rows(&self, sql: &str) -> crate::Result<Option<Vec<Vec<Option<String>>>>> {
+        Ok(Some(self.connection.get_results(sql).await?))
+    }
+}
+
+impl SequentialSafePostgresInstanceCopySourceStorage<'_> {
+    async fn copy_out(
+        &self,
+        copy_command: &str,
+        data_format: &DataFormat,
+    ) -> crate::Result<TableData<<Self as CopySource>::DataStream, ()>> {
+        let copy_out_stream = self.connection.copy_out(copy_command).await?;
 
         let stream = copy_out_stream.map_err(
             tokio_postgres_error_to_crate_error as fn(tokio_postgres::Error) -> ElefantToolsError,
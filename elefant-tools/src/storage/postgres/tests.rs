@@ -1,5 +1,5 @@
 use crate::chunk_reader::StringChunkReader;
-use crate::copy_data::{copy_data, CopyDataOptions};
+use crate::copy_data::{copy_data, execute_copy, plan_copy, CopyDataOptions};
 use crate::helpers::StringExt;
 use crate::quoting::{AttemptedKeywordUsage, Quotable};
 use crate::schema_reader::tests::introspect_schema;
@@ -8,15 +8,21 @@ use crate::storage::tests::validate_copy_state;
 use crate::test_helpers;
 use crate::test_helpers::*;
 use crate::{
-    apply_sql_string, default, storage, DataFormat, IdentifierQuoter, PostgresColumn,
-    PostgresDatabase, PostgresIndex, PostgresIndexColumnDirection, PostgresIndexKeyColumn,
-    PostgresIndexNullsOrder, PostgresIndexType, PostgresInstanceStorage, PostgresSchema,
-    PostgresSequence, PostgresTable, SqlDataMode, SqlFile, SqlFileOptions,
+    apply_sql_string, default, storage, AppendStorageParameter, ColumnRename, ConnectionPool, DataFormat,
+    DdlStatement, ElefantToolsError, ExtensionAvailability, InterceptResult, ObjectFilter, StatementInterceptor,
+    IdentifierQuoter, PostgresClientWrapper,
+    PostgresColumn, PostgresDatabase, PostgresIndex, PostgresIndexColumnDirection,
+    PlannedExtension, PostgresIndexKeyColumn, PostgresIndexNullsOrder, PostgresIndexType, PostgresInstanceStorage,
+    PostgresSchema, PostgresSequence, PostgresTable, SqlDataMode, SqlFile, SqlFileOptions,
+    StagingSwap, SupportedParallelism, TableTypeDetails, TableVerification, TableVerificationResult, VerificationLevel, RowMismatch,
+    ColumnMismatch, BaseCopyTarget, TimescaleVersion,
 };
 use elefant_test_macros::pg_test;
 use itertools::Itertools;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
+use tokio_postgres::error::SqlState;
 
 async fn test_copy(data_format: DataFormat, source: &TestHelper, destination: &TestHelper) {
     source
@@ -26,7 +32,7 @@ async fn test_copy(data_format: DataFormat, source: &TestHelper, destination: &T
         .await;
 
     let source_schema = introspect_schema(source).await;
-    let source = PostgresInstanceStorage::new(source.get_conn())
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
         .await
         .unwrap();
 
@@ -35,7 +41,7 @@ async fn test_copy(data_format: DataFormat, source: &TestHelper, destination: &T
         .unwrap();
 
     copy_data(
-        &source,
+        &source_storage,
         &mut destination_worker,
         CopyDataOptions {
             data_format: Some(data_format),
@@ -50,6 +56,8 @@ async fn test_copy(data_format: DataFormat, source: &TestHelper, destination: &T
 
     assert_eq!(source_schema, destination_schema);
 
+    assert_differential_noop(source, destination).await;
+
     validate_copy_state(destination).await;
 }
 
@@ -65,1266 +73,5719 @@ async fn copies_between_databases_binary_format(source: &TestHelper, destination
     .await;
 }
 
+#[pg_test(arg(postgres = 12), arg(postgres = 12))]
+#[pg_test(arg(postgres = 13), arg(postgres = 13))]
+#[pg_test(arg(postgres = 14), arg(postgres = 14))]
 #[pg_test(arg(postgres = 15), arg(postgres = 15))]
-async fn copies_between_databases_text_format(source: &TestHelper, destination: &TestHelper) {
-    test_copy(DataFormat::Text, source, destination).await;
-}
-
-async fn test_round_trip(sql: &str, source: &TestHelper, destination: &TestHelper) {
-    apply_sql_string(sql, source.get_conn()).await.unwrap();
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn copies_between_databases_as_restricted_user(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(storage::tests::get_copy_source_database_create_script(
+            source.get_conn().version(),
+        ))
+        .await;
 
     let source_schema = introspect_schema(source).await;
-    let source = PostgresInstanceStorage::new(source.get_conn())
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
         .await
         .unwrap();
 
-    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+    let restricted_destination = destination.as_restricted_user().await;
+    let mut destination_worker = PostgresInstanceStorage::new(restricted_destination.get_conn())
         .await
         .unwrap();
 
-    copy_data(
-        &source,
+    let report = copy_data(
+        &source_storage,
         &mut destination_worker,
         CopyDataOptions {
-            data_format: None,
             max_parallel: Some(NonZeroUsize::new(16).unwrap()),
+            privilege_downgrade: true,
             ..default()
         },
     )
     .await
     .expect("Failed to copy data");
 
+    assert_eq!(report.warnings, Vec::<String>::new());
+
     let destination_schema = introspect_schema(destination).await;
 
     assert_eq!(source_schema, destination_schema);
+
+    validate_copy_state(destination).await;
 }
 
-macro_rules! test_round_trip {
-    ($name:ident, $sql:literal) => {
-        mod $name {
-            use super::*;
+#[pg_test(arg(postgres = 12), arg(postgres = 12))]
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn privilege_downgrade_skips_untrusted_extensions(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+    create extension dblink;
 
-            const SQL: &str = $sql;
+    create table items(id int primary key);
+    insert into items values (1), (2);
+    "#,
+        )
+        .await;
 
-            #[pg_test(arg(postgres = 12), arg(postgres = 12))]
-            #[pg_test(arg(postgres = 13), arg(postgres = 13))]
-            #[pg_test(arg(postgres = 14), arg(postgres = 14))]
-            #[pg_test(arg(postgres = 15), arg(postgres = 15))]
-            #[pg_test(arg(postgres = 16), arg(postgres = 16))]
-            async fn non_differential(source: &TestHelper, destination: &TestHelper) {
-                test_round_trip(SQL, source, destination).await;
-            }
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let restricted_destination = destination.as_restricted_user().await;
+    let mut destination_worker = PostgresInstanceStorage::new(restricted_destination.get_conn())
+        .await
+        .unwrap();
 
-            #[pg_test(arg(postgres = 12))]
-            #[pg_test(arg(postgres = 13))]
-            #[pg_test(arg(postgres = 14))]
-            #[pg_test(arg(postgres = 15))]
-            #[pg_test(arg(postgres = 16))]
-            async fn differential(source: &TestHelper) {
-                test_differential_copy_generic(source, SQL).await;
-            }
-        }
+    let options = CopyDataOptions {
+        privilege_downgrade: true,
+        ..default()
     };
-}
+    let plan = plan_copy(&source_storage, &destination_worker, options)
+        .await
+        .unwrap();
+    assert_eq!(plan.skipped_extensions, vec!["dblink".to_string()]);
 
-test_round_trip!(
-    foreign_key_actions_are_preserved,
-    r#"
-        CREATE TABLE products (
-            product_no integer PRIMARY KEY,
-            name text,
-            price numeric
-        );
+    let report = execute_copy(plan, &source_storage, &mut destination_worker)
+        .await
+        .expect("Failed to copy data");
 
-        CREATE TABLE orders (
-            order_id integer PRIMARY KEY,
-            shipping_address text
-        );
+    assert_eq!(
+        report.warnings,
+        vec!["Skipped extension dblink as the destination user is not allowed to create it"]
+    );
 
-        CREATE TABLE order_items (
-            product_no integer REFERENCES products ON DELETE RESTRICT ON UPDATE CASCADE,
-            order_id integer REFERENCES orders ON DELETE CASCADE ON UPDATE RESTRICT,
-            quantity integer,
-            PRIMARY KEY (product_no, order_id)
-        );
-    "#
-);
+    let destination_schema = introspect_schema(destination).await;
+    assert!(destination_schema.enabled_extensions.is_empty());
+    let items = destination
+        .get_single_results::<i32>("select id from items order by id")
+        .await;
+    assert_eq!(items, vec![1, 2]);
+}
 
-#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 12), arg(postgres = 12))]
 #[pg_test(arg(postgres = 16), arg(postgres = 16))]
-async fn filtered_foreign_key_set_null(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(
-        r#"
-        CREATE TABLE tenants (
-            tenant_id integer PRIMARY KEY
-        );
-
-        CREATE TABLE users (
-            tenant_id integer REFERENCES tenants ON DELETE CASCADE,
-            user_id integer NOT NULL,
-            PRIMARY KEY (tenant_id, user_id)
-        );
+async fn privilege_downgrade_keeps_untrusted_extensions_present_already(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+    create extension dblink;
 
-        CREATE TABLE posts (
-            tenant_id integer REFERENCES tenants ON DELETE CASCADE,
-            post_id integer NOT NULL,
-            author_id integer,
-            PRIMARY KEY (tenant_id, post_id),
-            FOREIGN KEY (tenant_id, author_id) REFERENCES users ON DELETE SET NULL (author_id)
-        );
+    create table items(id int primary key);
+    insert into items values (1), (2);
     "#,
-        source,
-        destination,
-    )
-    .await;
-}
+        )
+        .await;
+    // Managed Postgres services install such extensions for the user.
+    destination.execute_not_query("create extension dblink;").await;
 
-test_round_trip!(
-    generated_columns,
-    r#"
-    CREATE TABLE people (
-        height_cm numeric,
-        height_in numeric GENERATED ALWAYS AS (height_cm / 2.54) STORED
-    );
-    "#
-);
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let restricted_destination = destination.as_restricted_user().await;
+    let mut destination_worker = PostgresInstanceStorage::new(restricted_destination.get_conn())
+        .await
+        .unwrap();
 
-test_round_trip!(
-    functions,
-    r#"
+    let options = CopyDataOptions {
+        privilege_downgrade: true,
+        ..default()
+    };
+    let plan = plan_copy(&source_storage, &destination_worker, options)
+        .await
+        .unwrap();
+    assert!(plan.skipped_extensions.is_empty());
+    assert!(plan.extensions.contains(&PlannedExtension {
+        name: "dblink".to_string(),
+        availability: ExtensionAvailability::Installed,
+    }));
 
-    create function add(a integer, b integer) returns integer as $$
-        begin
-            return a + b;
-        end;
-    $$ language plpgsql;
+    let report = execute_copy(plan, &source_storage, &mut destination_worker)
+        .await
+        .expect("Failed to copy data");
 
-    create function filter_stuff(value text) returns table(id int, name text) as
-        $$
-        begin
+    assert_eq!(report.warnings, Vec::<String>::new());
+    let extensions = destination
+        .get_single_results::<String>("select extname::text from pg_extension where extname = 'dblink'")
+        .await;
+    assert_eq!(extensions, vec!["dblink".to_string()]);
+    let items = destination
+        .get_single_results::<i32>("select id from items order by id")
+        .await;
+    assert_eq!(items, vec![1, 2]);
+}
 
-        create temp table temp_table(id int, name text);
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn copies_between_databases_text_format(source: &TestHelper, destination: &TestHelper) {
+    test_copy(DataFormat::Text { options: default() }, source, destination).await;
+}
 
-        insert into temp_table(id, name) values (1, 'foo'), (2, 'bar');
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn reports_timings_of_every_copied_table(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(storage::tests::get_copy_source_database_create_script(
+            source.get_conn().version(),
+        ))
+        .await;
 
-        return query select * from temp_table where name = value;
+    let source_schema = introspect_schema(source).await;
+    let source = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
 
-        end;
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-        $$ language plpgsql;
-    "#
-);
+    let report = copy_data(
+        &source,
+        &mut destination_worker,
+        CopyDataOptions {
+            max_parallel: Some(NonZeroUsize::new(16).unwrap()),
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
 
-test_round_trip!(
-    qouted_identifier_name,
-    r#"
-        create table "MyTable" (
-            "MyColumn" int,
-            "MyTextColumn" text
-        );
+    let expected_tables = source_schema
+        .schemas
+        .iter()
+        .flat_map(|s| {
+            s.tables
+                .iter()
+                .filter(|t| {
+                    !matches!(t.table_type, TableTypeDetails::PartitionedParentTable { .. })
+                })
+                .map(|t| format!("{}.{}", s.name, t.name))
+        })
+        .sorted()
+        .collect_vec();
+
+    let reported_tables = report
+        .table_timings
+        .iter()
+        .map(|t| format!("{}.{}", t.schema_name, t.table_name))
+        .sorted()
+        .collect_vec();
+
+    assert_eq!(reported_tables, expected_tables);
+
+    for timing in &report.table_timings {
+        assert!(timing.bytes > 0, "{:?}", timing);
+        assert!(timing.chunks > 0, "{:?}", timing);
+        assert_eq!(timing.source_wait + timing.destination_wait, timing.total);
+
+        let rows = destination
+            .get_single_result::<i64>(&format!(
+                "select count(*) from only \"{}\".\"{}\";",
+                timing.schema_name, timing.table_name
+            ))
+            .await;
+        assert_eq!(timing.rows, rows as u64, "{:?}", timing);
+    }
+}
 
-        create index "MyIndex" on "MyTable" (lower("MyTextColumn"));
-    "#
-);
+const DOCUMENTS: &str = r#"
+    create table documents(
+        id int4 primary key,
+        tenant text not null,
+        body bytea
+    );
 
-//language=postgresql
-test_round_trip!(
-    ddl_dependencies_1,
-    r#"
-        create function a_is_odd(a integer) returns boolean as $$
-        begin
-            return a % 2 = 1;
-        end;
-        $$ language plpgsql;
+    insert into documents(id, tenant, body)
+    values (1, 'acme', 'huge blob'),
+           (2, 'initech', 'another huge blob'),
+           (3, 'acme', null);
+"#;
 
-        create function b_is_even(a integer) returns boolean as $$
-        begin
-            return a_is_odd(a) = false;
-        end;
-        $$ language plpgsql;
-    "#
-);
+async fn copy_documents_with_select(
+    select: &str,
+    source: &TestHelper,
+    destination: &TestHelper,
+) -> crate::Result<()> {
+    source.execute_not_query(DOCUMENTS).await;
 
-//language=postgresql
-test_round_trip!(
-    ddl_dependencies_2,
-    r#"
-        create function b_is_odd(a integer) returns boolean as $$
-        begin
-            return a % 2 = 1;
-        end;
-        $$ language plpgsql;
+    let source = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
 
-        create function a_is_even(a integer) returns boolean as $$
-        begin
-            return b_is_odd(a) = false;
-        end;
-        $$ language plpgsql;
-    "#
-);
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-//language=postgresql
-test_round_trip!(
-    ddl_dependencies_1_1,
-    r#"
-        create function b_is_even(a integer) returns boolean as $$
-        begin
-            return a_is_odd(a) = false;
-        end;
-        $$ language plpgsql;
-
-        create function a_is_odd(a integer) returns boolean as $$
-        begin
-            return a % 2 = 1;
-        end;
-        $$ language plpgsql;
-    "#
-);
-
-//language=postgresql
-test_round_trip!(
-    ddl_dependencies_2_2,
-    r#"
-        create function a_is_even(a integer) returns boolean as $$
-        begin
-            return b_is_odd(a) = false;
-        end;
-        $$ language plpgsql;
-
-        create function b_is_odd(a integer) returns boolean as $$
-        begin
-            return a % 2 = 1;
-        end;
-        $$ language plpgsql;
-    "#
-);
-
-test_round_trip!(
-    ddl_dependencies_3,
-    r#"
-        create function is_odd(a integer) returns boolean as $$
-        begin
-            return a % 2 = 1;
-        end;
-        $$ language plpgsql;
+    copy_data(
+        &source,
+        &mut destination_worker,
+        CopyDataOptions {
+            max_parallel: Some(NonZeroUsize::new(16).unwrap()),
+            table_select_overrides: HashMap::from([(
+                "public.documents".to_string(),
+                select.to_string(),
+            )]),
+            ..default()
+        },
+    )
+    .await?;
 
-        create table tab(
-            value int not null check (is_odd(value))
-        );
-    "#
-);
+    Ok(())
+}
 
-test_round_trip!(
-    ddl_dependencies_4,
-    r#"
-        create view a_view as select 1 as value;
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn copies_tables_using_select_override(source: &TestHelper, destination: &TestHelper) {
+    copy_documents_with_select(
+        "select id, upper(tenant) as tenant, null::bytea as body from public.documents where id < 3;",
+        source,
+        destination,
+    )
+    .await
+    .unwrap();
 
-        create view b_view as select * from a_view;
-    "#
-);
+    let rows = destination
+        .get_results::<(i32, String, Option<Vec<u8>>)>(
+            "select id, tenant, body from documents order by id;",
+        )
+        .await;
 
-test_round_trip!(
-    ddl_dependencies_4_opposite,
-    r#"
-        create view b_view as select 1 as value;
+    assert_eq!(
+        rows,
+        vec![
+            (1, "ACME".to_string(), None),
+            (2, "INITECH".to_string(), None),
+        ]
+    );
+}
 
-        create view a_view as select * from b_view;
-    "#
-);
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn select_override_must_return_the_columns_of_the_table(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    let result = copy_documents_with_select(
+        "select tenant, id, tenant as owner from public.documents",
+        source,
+        destination,
+    )
+    .await;
 
-test_round_trip!(
-    ddl_dependencies_5,
-    r#"
-        create materialized view a_view as select 1 as value;
+    match result {
+        Err(ElefantToolsError::TableSelectOverrideMismatch {
+            schema_name,
+            table_name,
+            expected_columns,
+            missing_columns,
+            extra_columns,
+        }) => {
+            assert_eq!(schema_name, "public");
+            assert_eq!(table_name, "documents");
+            assert_eq!(expected_columns, vec!["id", "tenant", "body"]);
+            assert_eq!(missing_columns, vec!["body"]);
+            assert_eq!(extra_columns, vec!["owner"]);
+        }
+        other => panic!("Expected a column mismatch, got {:?}", other),
+    }
 
-        create materialized view b_view as select * from a_view;
-    "#
-);
+    // Nothing is created when the overrides are invalid.
+    let tables = destination
+        .get_single_results::<String>(
+            "select table_name::text from information_schema.tables where table_schema = 'public';",
+        )
+        .await;
+    assert_eq!(tables, Vec::<String>::new());
+}
 
-test_round_trip!(
-    ddl_dependencies_5_opposite,
-    r#"
-        create materialized view b_view as select 1 as value;
+const PEOPLE_WITH_SSN: &str = r#"
+    create table people(
+        id int4 primary key,
+        name text not null,
+        ssn text check (length(ssn) = 11),
+        ssn_hash text generated always as (md5(ssn)) stored
+    );
 
-        create materialized view a_view as select * from b_view;
-    "#
-);
+    create index people_ssn_idx on people(ssn);
+    create index people_name_idx on people(lower(name));
 
-test_round_trip!(
-    functions_reading_from_tables_in_pure_sql,
-    r#"
-create table my_table(
-    value int not null
-);
+    create view people_ssn as select id, ssn from people;
 
-create function my_function() returns bigint as $$
-    select sum(value) from my_table
-$$ language sql;
-"#
-);
+    insert into people(id, name, ssn)
+    values (1, 'foo', '123-45-6789'),
+           (2, 'bar', null);
+"#;
 
-test_round_trip!(
-    comments_on_stuff,
-    r#"
-        create table my_table(
-            value serial not null,
-            another_value int not null unique
-        );
+async fn copy_people_excluding_ssn(
+    drop_dependent_objects: bool,
+    source: &TestHelper,
+    destination: &TestHelper,
+) -> crate::Result<()> {
+    source.execute_not_query(PEOPLE_WITH_SSN).await;
 
-        alter table my_table add constraint my_table_value_check check (value > 0);
+    let source = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
 
-        comment on table my_table is 'This is a ''table''';
-        comment on column my_table.value is 'This is a column';
-        comment on constraint my_table_value_check on my_table is 'This is a constraint';
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-        create function my_function() returns int as $$ begin return 1; end; $$ language plpgsql;
-        create function my_function_2(a int, b int) returns int as $$ begin return a + b; end; $$ language plpgsql;
+    copy_data(
+        &source,
+        &mut destination_worker,
+        CopyDataOptions {
+            max_parallel: Some(NonZeroUsize::new(16).unwrap()),
+            exclude_columns: vec![("public.people".to_string(), "ssn".to_string())],
+            drop_dependent_objects,
+            ..default()
+        },
+    )
+    .await?;
 
-        comment on function my_function() is 'This is a function';
-        comment on function my_function_2(int, int) is 'This is another function';
+    Ok(())
+}
 
-        create view my_view as select 1 as value;
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn excluded_columns_with_dependent_objects_are_reported(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    let result = copy_people_excluding_ssn(false, source, destination).await;
+
+    match result {
+        Err(ElefantToolsError::ExcludedColumnsHaveDependentObjects(objects)) => {
+            assert_eq!(
+                objects,
+                vec![
+                    "generated column public.people.ssn_hash",
+                    "index people_ssn_idx on public.people",
+                    "constraint people_ssn_check on public.people",
+                    "view public.people_ssn",
+                ]
+            );
+        }
+        other => panic!("Expected dependent objects to be reported, got {:?}", other),
+    }
+}
 
-        comment on view my_view is 'This is a view';
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn excluded_columns_are_not_created_or_copied(source: &TestHelper, destination: &TestHelper) {
+    copy_people_excluding_ssn(true, source, destination)
+        .await
+        .unwrap();
 
-        comment on schema public is 'This is a schema';
+    let destination_schema = introspect_schema(destination).await;
+    let people = destination_schema.schemas[0].try_get_table("people").unwrap();
 
-        comment on sequence my_table_value_seq is 'This is a sequence';
+    assert_eq!(
+        people.columns.iter().map(|c| c.name.as_str()).collect_vec(),
+        vec!["id", "name"]
+    );
+    assert_eq!(
+        people.indices.iter().map(|i| i.name.as_str()).sorted().collect_vec(),
+        vec!["people_name_idx", "people_pkey"]
+    );
+    assert!(people.constraints.is_empty());
+    assert!(destination_schema.schemas[0].views.is_empty());
 
-        comment on index my_table_another_value_key is 'This is an index';
-        comment on constraint my_table_another_value_key on my_table is 'This is a unique constraint';
+    let rows = destination
+        .get_results::<(i32, String)>("select id, name from people order by id;")
+        .await;
+    assert_eq!(rows, vec![(1, "foo".to_string()), (2, "bar".to_string())]);
+}
 
-    "#
+/// The layout pg_partman leaves behind: a config schema with rows pointing at the partitioned
+/// tables, a template table, and partitions that were created dynamically.
+//language=postgresql
+const PG_PARTMAN_LIKE_LAYOUT: &str = r#"
+create schema partman;
+
+create table partman.part_config(
+    parent_table text primary key,
+    control text not null,
+    partition_interval text not null,
+    premake int4 not null default 4,
+    template_table text
 );
 
-test_round_trip!(
-    array_columns,
-    r#"
-        create table my_table(
-            id serial primary key,
-            names text[]
-        );
-    "#
+create table partman.template_public_events(
+    id int4,
+    created_at timestamptz
 );
 
-test_round_trip!(
-    materialized_views,
-    r#"
-        create table my_table(
-            id serial primary key,
-            name text
-        );
+create table public.events(
+    id int4 not null,
+    created_at timestamptz not null,
+    payload text
+) partition by range (created_at);
 
-        insert into my_table(name) values ('foo'), ('bar');
+create table public.events_default partition of public.events default;
+create table public.events_p20240101 partition of public.events for values from ('2024-01-01 00:00:00+00') to ('2024-02-01 00:00:00+00');
+create table public.events_p20240201 partition of public.events for values from ('2024-02-01 00:00:00+00') to ('2024-03-01 00:00:00+00');
 
-        create materialized view my_materialized_view as select id, name from my_table;
+insert into partman.part_config(parent_table, control, partition_interval, template_table)
+values ('public.events', 'created_at', '1 month', 'partman.template_public_events');
 
-        comment on materialized view my_materialized_view is 'This is a materialized view';
-    "#
-);
+insert into public.events(id, created_at, payload)
+values (1, '2024-01-15 00:00:00+00', 'january'),
+       (2, '2024-02-15 00:00:00+00', 'february'),
+       (3, '2025-01-15 00:00:00+00', 'default');
+"#;
 
-test_round_trip!(
-    triggers,
-    r#"
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn excluded_schemas_are_not_copied(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(PG_PARTMAN_LIKE_LAYOUT).await;
 
-        create table my_table(
-            value int
-        );
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-        create function my_trigger_function() returns trigger as $$
-        begin return new; end;
-        $$ language plpgsql;
-        
-        create function my_parametised_trigger_function() returns trigger as $$
-        begin return new; end;
-        $$ language plpgsql;
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            exclude_schemas: vec!["partman".to_string()],
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
 
-        create trigger my_trigger after insert on my_table for each row execute function my_trigger_function();
+    let destination_schema = introspect_schema(destination).await;
+    assert!(destination_schema.try_get_schema("partman").is_none());
 
-        comment on trigger my_trigger on my_table is 'This is a trigger';
+    let partitions = destination
+        .get_results::<(String, String)>(
+            "select c.relname::text, pg_get_expr(c.relpartbound, c.oid) from pg_inherits i join pg_class c on c.oid = i.inhrelid where i.inhparent = 'public.events'::regclass order by c.relname;",
+        )
+        .await;
+    assert_eq!(
+        partitions,
+        vec![
+            ("events_default".to_string(), "DEFAULT".to_string()),
+            (
+                "events_p20240101".to_string(),
+                "FOR VALUES FROM ('2024-01-01 00:00:00+00') TO ('2024-02-01 00:00:00+00')"
+                    .to_string()
+            ),
+            (
+                "events_p20240201".to_string(),
+                "FOR VALUES FROM ('2024-02-01 00:00:00+00') TO ('2024-03-01 00:00:00+00')"
+                    .to_string()
+            ),
+        ]
+    );
 
-        create trigger scoped_trigger before update on my_table for each row when (OLD.value is distinct from NEW.value) execute procedure my_trigger_function();
+    let rows = destination
+        .get_results::<(String, i32, String)>(
+            "select tableoid::regclass::text, id, payload from public.events order by id;",
+        )
+        .await;
+    assert_eq!(
+        rows,
+        vec![
+            ("events_p20240101".to_string(), 1, "january".to_string()),
+            ("events_p20240201".to_string(), 2, "february".to_string()),
+            ("events_default".to_string(), 3, "default".to_string()),
+        ]
+    );
+}
 
-        create trigger truncate_trigger after truncate on my_table for each statement execute procedure my_trigger_function();
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn filters_copied_tables(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(
+            r#"
+create table customers(id int4 primary key, name text not null);
+create table orders(id int4 primary key, customer_id int4 not null references customers(id));
+create table audit_log(id int4 primary key, message text);
+create view customer_orders as select c.name, o.id from customers c join orders o on o.customer_id = c.id;
+
+insert into customers(id, name) values (1, 'Alice');
+insert into orders(id, customer_id) values (1, 1);
+insert into audit_log(id, message) values (1, 'created');
+"#,
+        )
+        .await;
 
-        create trigger updt_insert_trigger before update or insert on my_table for each row execute procedure my_parametised_trigger_function(42, 'foo');
-    "#
-);
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-test_round_trip!(
-    enumerations,
-    r#"
-    create type mood as enum ('sad', 'ok', 'happy');
+    let result = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            object_filter: ObjectFilter {
+                include_tables: vec!["public.orders".to_string(), "public.audit_*".to_string()],
+                ..default()
+            },
+            ..default()
+        },
+    )
+    .await;
+    assert!(
+        matches!(
+            &result,
+            Err(ElefantToolsError::ExcludedTablesHaveDependentObjects(objects)) if objects.len() == 2
+        ),
+        "{:?}",
+        result
+    );
+
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            object_filter: ObjectFilter {
+                include_tables: vec!["public.orders".to_string(), "public.audit_*".to_string()],
+                ..default()
+            },
+            drop_dependent_objects: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let destination_schema = introspect_schema(destination).await;
+    let public = destination_schema.try_get_schema("public").unwrap();
+    assert_eq!(
+        public.tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+        vec!["audit_log", "orders"]
+    );
+    assert!(public.views.is_empty());
+    assert!(public.tables[1].constraints.is_empty());
+
+    let orders = destination
+        .get_results::<(i32, i32)>("select id, customer_id from orders;")
+        .await;
+    assert_eq!(orders, vec![(1, 1)]);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn filters_copied_functions_and_triggers(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(
+            r#"
+create table customers(id int4 primary key, name text not null, updated_at timestamptz);
+create function touch_updated_at() returns trigger language plpgsql as $$
+begin
+    new.updated_at = now();
+    return new;
+end;
+$$;
+create trigger customers_touch before update on customers for each row execute function touch_updated_at();
+create function greeting(name text) returns text language sql as $$ select 'Hello ' || name $$;
+
+insert into customers(id, name) values (1, 'Alice');
+"#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            object_filter: ObjectFilter {
+                exclude_functions: vec!["public.touch_updated_at".to_string()],
+                ..default()
+            },
+            ..default()
+        },
+    )
+    .await;
+    assert!(
+        matches!(
+            &result,
+            Err(ElefantToolsError::ExcludedObjectsAreRequired(objects)) if objects == &vec![
+                "trigger customers_touch on public.customers requires function public.touch_updated_at()".to_string()
+            ]
+        ),
+        "{:?}",
+        result
+    );
+
+    let destination_schema = introspect_schema(destination).await;
+    assert!(destination_schema.try_get_schema("public").is_none_or(|s| s.tables.is_empty()));
+
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            object_filter: ObjectFilter {
+                exclude_functions: vec!["public.touch_updated_at()".to_string()],
+                exclude_triggers: vec!["public.customers.customers_touch".to_string()],
+                ..default()
+            },
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let destination_schema = introspect_schema(destination).await;
+    let public = destination_schema.try_get_schema("public").unwrap();
+    assert_eq!(
+        public.functions.iter().map(|f| f.function_name.as_str()).collect::<Vec<_>>(),
+        vec!["greeting"]
+    );
+    assert!(public.triggers.is_empty());
+
+    let customers = destination
+        .get_results::<(i32, String)>("select id, name from customers;")
+        .await;
+    assert_eq!(customers, vec![(1, "Alice".to_string())]);
+}
+
+//language=postgresql
+const OBJECTS_WITH_PRIVILEGES: &str = r#"
+do $$
+    begin
+        create role copied_privileges_reader;
+    exception when duplicate_object or unique_violation then null;
+    end
+$$;
+
+create schema reports;
+grant usage on schema reports to copied_privileges_reader;
+
+create table reports.sales(id int4 primary key, amount int4 not null);
+grant select on table reports.sales to copied_privileges_reader;
+grant update on table reports.sales to copied_privileges_reader with grant option;
+
+create view reports.sales_total as select sum(amount) as total from reports.sales;
+grant select on table reports.sales_total to copied_privileges_reader;
+
+create function reports.sales_count() returns int8 language sql as 'select count(*) from reports.sales';
+revoke execute on function reports.sales_count() from public;
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn copies_privileges(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(OBJECTS_WITH_PRIVILEGES).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(&source_storage, &mut destination_storage, default())
+        .await
+        .unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+
+    let source_reports = source_schema.try_get_schema("reports").unwrap();
+    let destination_reports = destination_schema.try_get_schema("reports").unwrap();
+
+    assert_eq!(destination_reports.privileges.len(), 1);
+    assert_eq!(destination_reports.privileges, source_reports.privileges);
+    assert_eq!(destination_reports.tables[0].privileges.len(), 2);
+    assert_eq!(
+        destination_reports.tables[0].privileges,
+        source_reports.tables[0].privileges
+    );
+    assert_eq!(
+        destination_reports.views[0].privileges,
+        source_reports.views[0].privileges
+    );
+    assert_eq!(
+        destination_reports.functions[0].privileges,
+        source_reports.functions[0].privileges
+    );
+    assert!(destination_reports.functions[0].privileges[0].revoked);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 16))]
+async fn missing_roles_fail_the_copy_unless_privileges_are_skipped(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(OBJECTS_WITH_PRIVILEGES).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(&source_storage, &mut destination_storage, default()).await;
+    assert!(
+        matches!(
+            &result,
+            Err(ElefantToolsError::RolesNotFound(roles)) if roles == &vec!["copied_privileges_reader".to_string()]
+        ),
+        "{:?}",
+        result
+    );
+
+    let destination_schema = introspect_schema(destination).await;
+    assert!(destination_schema.try_get_schema("reports").is_none());
+
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            skip_privileges: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let destination_schema = introspect_schema(destination).await;
+    let reports = destination_schema.try_get_schema("reports").unwrap();
+    assert!(reports.privileges.is_empty());
+    assert!(reports.tables[0].privileges.is_empty());
+    assert!(reports.functions[0].privileges.is_empty());
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn grants_copied_sequences_to_roles(source: &TestHelper, destination: &TestHelper) {
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+            do $$
+                begin
+                    create role copied_sequence_user;
+                exception when duplicate_object or unique_violation then null;
+                end
+            $$;
+
+            create table orders(id serial primary key, note text);
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            grant_sequences_to: vec![
+                "copied_sequence_user".to_string(),
+                "missing_sequence_user".to_string(),
+            ],
+            ..default()
+        },
+    )
+    .await;
+    assert!(
+        matches!(
+            &result,
+            Err(ElefantToolsError::SequenceGrantRolesNotFound(roles)) if roles == &vec!["missing_sequence_user".to_string()]
+        ),
+        "{:?}",
+        result
+    );
+
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            grant_sequences_to: vec!["copied_sequence_user".to_string()],
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let privileges = destination
+        .get_results::<(bool, bool, bool)>(
+            "select has_sequence_privilege('copied_sequence_user', 'public.orders_id_seq', 'usage'),
+                    has_sequence_privilege('copied_sequence_user', 'public.orders_id_seq', 'select'),
+                    has_sequence_privilege('copied_sequence_user', 'public.orders_id_seq', 'update');",
+        )
+        .await;
+    assert_eq!(privileges, vec![(true, true, false)]);
+}
+
+//language=postgresql
+const TABLES_WITH_POLICIES: &str = r#"
+do $$
+    begin
+        create role copied_policy_reader;
+    exception when duplicate_object or unique_violation then null;
+    end
+$$;
+
+create table documents(id int4 primary key, owner text not null, title text not null);
+create function is_owner(owner text) returns bool language sql stable as $$ select owner = current_user $$;
+
+alter table documents enable row level security;
+alter table documents force row level security;
+
+create policy own_documents on documents for all to copied_policy_reader using (is_owner(owner)) with check (is_owner(owner));
+
+insert into documents(id, owner, title) values (1, 'someone_else', 'hidden');
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn copies_row_level_security_policies(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(TABLES_WITH_POLICIES).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(&source_storage, &mut destination_storage, default())
+        .await
+        .unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    let source_public = source_schema.try_get_schema("public").unwrap();
+    let destination_public = destination_schema.try_get_schema("public").unwrap();
+
+    let documents = destination_public.try_get_table("documents").unwrap();
+    assert!(documents.rls_enabled);
+    assert!(documents.rls_forced);
+    assert_eq!(destination_public.policies, source_public.policies);
+    assert_eq!(
+        destination_public.policies[0].using_expression,
+        Some("is_owner(owner)".to_string())
+    );
+
+    let rows = destination
+        .get_single_results::<i32>("select id from documents;")
+        .await;
+    assert_eq!(rows, vec![1]);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 16))]
+async fn missing_policy_roles_fail_the_copy(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(TABLES_WITH_POLICIES).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            skip_privileges: true,
+            ..default()
+        },
+    )
+    .await;
+    assert!(
+        matches!(
+            &result,
+            Err(ElefantToolsError::PolicyRolesNotFound(policies)) if policies == &vec![
+                "own_documents on public.documents (roles copied_policy_reader)".to_string()
+            ]
+        ),
+        "{:?}",
+        result
+    );
+
+    let destination_schema = introspect_schema(destination).await;
+    assert!(destination_schema
+        .try_get_schema("public")
+        .is_none_or(|s| s.tables.is_empty()));
+}
+
+//language=postgresql
+const TABLES_WITH_ACCESS_METHODS: &str = r#"
+create access method my_heap type table handler heap_tableam_handler;
+
+create table default_am(id int) using heap;
+create table custom_am(id int) using my_heap;
+
+insert into default_am values (1);
+insert into custom_am values (2);
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn table_access_methods_are_copied(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(TABLES_WITH_ACCESS_METHODS).await;
+    destination
+        .execute_not_query("create access method my_heap type table handler heap_tableam_handler;")
+        .await;
+
+    let source_schema = introspect_schema(source).await;
+    let public = source_schema.try_get_schema("public").unwrap();
+    assert_eq!(public.try_get_table("default_am").unwrap().access_method, None);
+    assert_eq!(
+        public.try_get_table("custom_am").unwrap().access_method,
+        Some("my_heap".to_string())
+    );
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(&source_storage, &mut destination_storage, default())
+        .await
+        .unwrap();
+
+    let access_methods = destination
+        .get_results::<(String, String)>(
+            "select c.relname::text, am.amname::text from pg_class c join pg_am am on am.oid = c.relam where c.relname in ('default_am', 'custom_am') order by c.relname;",
+        )
+        .await;
+    assert_eq!(
+        access_methods,
+        vec![
+            ("custom_am".to_string(), "my_heap".to_string()),
+            ("default_am".to_string(), "heap".to_string()),
+        ]
+    );
+
+    let rows = destination
+        .get_single_results::<i32>("select id from custom_am;")
+        .await;
+    assert_eq!(rows, vec![2]);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_changes_the_table_access_method(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(TABLES_WITH_ACCESS_METHODS).await;
+    destination
+        .execute_not_query(
+            r#"
+            create access method my_heap type table handler heap_tableam_handler;
+            create table custom_am(id int);
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(
+        destination_schema
+            .try_get_schema("public")
+            .unwrap()
+            .try_get_table("custom_am")
+            .unwrap()
+            .access_method,
+        Some("my_heap".to_string())
+    );
+}
+
+#[pg_test(arg(postgres = 14), arg(postgres = 14))]
+async fn differential_copy_keeps_the_table_access_method_before_postgres_15(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(TABLES_WITH_ACCESS_METHODS).await;
+    destination
+        .execute_not_query(
+            r#"
+            create access method my_heap type table handler heap_tableam_handler;
+            create table custom_am(id int);
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(
+        destination_schema
+            .try_get_schema("public")
+            .unwrap()
+            .try_get_table("custom_am")
+            .unwrap()
+            .access_method,
+        Some("heap".to_string())
+    );
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_only_replaces_functions_with_changed_bodies(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+            create function reformatted() returns text language plpgsql as $$
+            begin
+                return 'unchanged';
+            end;
+            $$;
+
+            create function changed() returns text language plpgsql as $$
+            begin
+                return 'new  value';
+            end;
+            $$;
+            "#,
+        )
+        .await;
+    //language=postgresql
+    destination
+        .execute_not_query(
+            r#"
+            create function reformatted() returns text language plpgsql as $$ begin return 'unchanged'; end; $$;
+
+            create function changed() returns text language plpgsql as $$ begin return 'new value'; end; $$;
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let bodies = destination
+        .get_results::<(String, String)>(
+            "select proname::text, prosrc from pg_proc where proname in ('changed', 'reformatted') order by proname;",
+        )
+        .await;
+    assert_eq!(bodies[0].0, "changed");
+    assert!(bodies[0].1.contains("'new  value'"), "{}", bodies[0].1);
+    assert_eq!(
+        bodies[1],
+        (
+            "reformatted".to_string(),
+            " begin return 'unchanged'; end; ".to_string()
+        )
+    );
+}
+
+const COMMENTED_OBJECTS: &str = r#"
+    create type mood as enum ('happy', 'sad');
+    create domain positive_int as int check (value > 0);
+    create sequence my_sequence;
+
+    create table people(
+        id int primary key,
+        name text not null constraint name_not_empty check (name <> ''),
+        mood mood
+    );
+    create index people_name_idx on people(name);
+
+    create view people_view as select id, name from people;
+
+    create function people_count() returns bigint language sql as $$ select count(*) from people $$;
+
+    create function noop_trigger() returns trigger language plpgsql as $$ begin return new; end; $$;
+    create trigger people_trigger before insert on people for each row execute function noop_trigger();
+
+    comment on schema public is 'The schema';
+    comment on table people is 'The people';
+    comment on column people.name is 'The name';
+    comment on constraint name_not_empty on people is 'Names are required';
+    comment on index people_name_idx is 'Lookup by name';
+    comment on view people_view is 'The view';
+    comment on function people_count() is 'Counts people';
+    comment on sequence my_sequence is 'A sequence';
+    comment on domain positive_int is 'Positive numbers';
+    comment on type mood is 'How people feel';
+    comment on trigger people_trigger on people is 'Does nothing';
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_updates_changed_comments(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(COMMENTED_OBJECTS).await;
+    destination.execute_not_query(COMMENTED_OBJECTS).await;
+
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+            comment on column people.name is 'The full name';
+            comment on index people_name_idx is 'Find people by name';
+            comment on sequence my_sequence is 'Another sequence';
+            comment on trigger people_trigger on people is 'Still does nothing';
+            comment on function people_count() is null;
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(destination_schema, source_schema);
+}
+
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+async fn dry_run_records_statements_without_changing_the_destination(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+            create table items(id int primary key, name text not null);
+            insert into items(id, name) select g, 'item ' || g from generate_series(1, 100) g;
+            analyze items;
+
+            create table new_items(id int primary key);
+            create index new_items_id_idx on new_items (id);
+            "#,
+        )
+        .await;
+    //language=postgresql
+    destination
+        .execute_not_query("create table items(id int primary key, name text not null);")
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let report = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            dry_run: true,
+            max_parallel: Some(NonZeroUsize::new(4).unwrap()),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert!(report.table_timings.is_empty());
+    let dry_run = report.dry_run.unwrap();
+
+    assert!(dry_run
+        .statements
+        .iter()
+        .any(|s| s.sql.starts_with("create table public.new_items ")));
+    assert!(!dry_run
+        .statements
+        .iter()
+        .any(|s| s.sql.starts_with("create table public.items ")));
+    assert_eq!(dry_run.summary().created_tables, 1);
+    assert_eq!(dry_run.summary().created_indexes, 1);
+
+    let items = dry_run
+        .tables
+        .iter()
+        .find(|t| t.table_name == "items")
+        .unwrap();
+    assert_eq!(items.estimated_rows, Some(100));
+
+    let destination_tables = destination
+        .get_single_results::<String>(
+            "select tablename::text from pg_tables where schemaname = 'public' order by tablename;",
+        )
+        .await;
+    assert_eq!(destination_tables, vec!["items".to_string()]);
+    let destination_rows = destination
+        .get_single_result::<i64>("select count(*) from items;")
+        .await;
+    assert_eq!(destination_rows, 0);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn missing_table_access_method_fails_before_creating_anything(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(TABLES_WITH_ACCESS_METHODS).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(&source_storage, &mut destination_storage, default()).await;
+
+    match result {
+        Err(ElefantToolsError::TableAccessMethodNotFound {
+            schema_name,
+            table_name,
+            access_method,
+        }) => {
+            assert_eq!(schema_name, "public");
+            assert_eq!(table_name, "custom_am");
+            assert_eq!(access_method, "my_heap");
+        }
+        other => panic!("Expected TableAccessMethodNotFound, got {:?}", other),
+    }
+
+    let destination_schema = introspect_schema(destination).await;
+    assert!(destination_schema
+        .try_get_schema("public")
+        .is_none_or(|s| s.tables.is_empty()));
+}
+
+//language=postgresql
+const TABLES_TO_CHECKSUM: &str = r#"
+create table with_key(id int primary key, amount numeric(10, 3), created timestamptz);
+create table without_key(value text);
+create table events(id int not null, created date not null) partition by range (created);
+create table events_2023 partition of events for values from ('2023-01-01') to ('2024-01-01');
+create table events_2024 partition of events for values from ('2024-01-01') to ('2025-01-01');
+
+insert into with_key select i, i * 1.5, '2024-01-01 12:00:00+02'::timestamptz + make_interval(days => i) from generate_series(1, 50) i;
+insert into without_key values ('a'), (''), (null);
+insert into events select i, '2023-06-01'::date + i from generate_series(1, 400) i;
+"#;
+
+#[pg_test(arg(postgres = 12), arg(postgres = 17))]
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn checksum_verification_matches_after_copy(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(TABLES_TO_CHECKSUM).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let report = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            data_format: Some(DataFormat::Text { options: default() }),
+            verification: VerificationLevel::Checksum,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut verified = report
+        .verification
+        .iter()
+        .map(|v| (v.table_name.as_str(), v.result.is_match()))
+        .collect::<Vec<_>>();
+    verified.sort();
+    assert_eq!(
+        verified,
+        vec![
+            ("events_2023", true),
+            ("events_2024", true),
+            ("with_key", true),
+            ("without_key", true),
+        ]
+    );
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn checksum_verification_reports_every_differing_table(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(TABLES_TO_CHECKSUM).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(&source_storage, &mut destination_storage, default())
+        .await
+        .unwrap();
+
+    destination
+        .execute_not_query(
+            r#"
+        update with_key set amount = 0 where id = 7;
+        update without_key set value = null where value = '';
+        "#,
+        )
+        .await;
+
+    // A differential copy leaves the tables with data alone, so the changes are kept.
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    let report = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            verification: VerificationLevel::Checksum,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut mismatched = report
+        .verification
+        .iter()
+        .filter(|v| !v.result.is_match())
+        .map(|v| v.table_name.as_str())
+        .collect::<Vec<_>>();
+    mismatched.sort();
+    assert_eq!(mismatched, vec!["with_key", "without_key"]);
+    assert_eq!(report.verification.len(), 4);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn skips_the_data_of_tables_larger_than_the_limit(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        create table events(id serial primary key, payload text not null);
+        create table users(id serial primary key, name text not null);
+
+        insert into events(payload) select repeat('x', 200) from generate_series(1, 2000);
+        insert into users(name) values ('foo'), ('bar');
+        "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let report = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            max_table_data_size_bytes: Some(64 * 1024),
+            verification: VerificationLevel::Sample { rows_per_table: 10 },
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.skipped_tables.len(), 1);
+    assert_eq!(report.skipped_tables[0].table_name, "events");
+    assert!(report.skipped_tables[0].size_bytes > 64 * 1024);
+    assert_eq!(
+        report
+            .verification
+            .iter()
+            .map(|v| v.table_name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["users"]
+    );
+
+    let events = destination
+        .get_single_result::<i64>("select count(*) from events;")
+        .await;
+    assert_eq!(events, 0);
+    let users = destination
+        .get_single_result::<i64>("select count(*) from users;")
+        .await;
+    assert_eq!(users, 2);
+
+    // The sequence continues after the rows in the source, even if they were not copied.
+    let last_value = destination
+        .get_single_result::<i64>("select last_value from events_id_seq;")
+        .await;
+    assert_eq!(last_value, 2000);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn reports_how_far_the_copy_got_when_the_destination_is_full(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        create table a_users(id int primary key, name text not null);
+        create table b_events(id int primary key, payload text not null);
+
+        insert into a_users values (1, 'foo'), (2, 'bar');
+        insert into b_events select i, repeat('x', 100) from generate_series(1, 100) i;
+        "#,
+        )
+        .await;
+
+    // Simulates the disk running full while the events are copied.
+    destination
+        .execute_not_query(
+            r#"
+        create table b_events(id int primary key, payload text not null);
+
+        create function fail_with_disk_full() returns trigger as $$
+        begin
+            raise exception 'could not extend file: No space left on device' using errcode = 'disk_full';
+        end;
+        $$ language plpgsql;
+
+        create trigger disk_full before insert on b_events for each row execute function fail_with_disk_full();
+        "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let error = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap_err();
+
+    assert!(
+        matches!(&error, ElefantToolsError::DestinationDiskFull { table_name, .. } if table_name == "b_events"),
+        "{:?}",
+        error
+    );
+    assert!(error.to_string().contains("b_events"));
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn warns_when_the_copied_tables_exceed_the_destination_capacity(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        create table events(id serial primary key, payload text not null);
+
+        insert into events(payload) select repeat('x', 200) from generate_series(1, 2000);
+        "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let plan = plan_copy(
+        &source_storage,
+        &destination_storage,
+        CopyDataOptions {
+            destination_capacity_bytes: Some(1024),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert!(plan.estimated_data_size_bytes.unwrap() > 200 * 2000);
+    assert!(plan.capacity_warning.is_some());
+
+    let plan = plan_copy(
+        &source_storage,
+        &destination_storage,
+        CopyDataOptions {
+            destination_capacity_bytes: Some(u64::MAX),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(plan.capacity_warning, None);
+}
+
+//language=postgresql
+const TABLES_TO_VERIFY: &str = r#"
+create table with_key(id int primary key, amount numeric(10, 3), created timestamptz);
+create table without_key(value text);
+
+insert into with_key select i, i * 1.5, '2024-01-01 12:00:00+02'::timestamptz + make_interval(days => i) from generate_series(1, 50) i;
+insert into without_key values ('a'), ('b');
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn sampled_verification_matches_after_copy(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(TABLES_TO_VERIFY).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let report = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            verification: VerificationLevel::Sample { rows_per_table: 20 },
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        report.verification,
+        vec![
+            TableVerification {
+                schema_name: "public".to_string(),
+                table_name: "with_key".to_string(),
+                result: TableVerificationResult::Sampled {
+                    rows_compared: 20,
+                    mismatched_rows: 0,
+                    examples: vec![],
+                },
+            },
+            TableVerification {
+                schema_name: "public".to_string(),
+                table_name: "without_key".to_string(),
+                result: TableVerificationResult::RowCountOnly {
+                    source_rows: 2,
+                    destination_rows: 2,
+                },
+            },
+        ]
+    );
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn sampled_verification_reports_differing_rows(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(TABLES_TO_VERIFY).await;
+    // A differential copy leaves tables with data alone, so the data of the destination
+    // stays different from the source.
+    destination.execute_not_query(TABLES_TO_VERIFY).await;
+    destination
+        .execute_not_query(
+            r#"
+            set timezone = 'America/New_York';
+            update with_key set amount = 0 where id = 1;
+            delete from with_key where id = 2;
+            delete from without_key where value = 'b';
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let mut report = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            verification: VerificationLevel::Sample { rows_per_table: 1000 },
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    // The rows are sampled in a random order.
+    for table in &mut report.verification {
+        if let TableVerificationResult::Sampled { examples, .. } = &mut table.result {
+            examples.sort_by(|a, b| a.primary_key.cmp(&b.primary_key));
+        }
+    }
+
+    assert_eq!(
+        report.verification,
+        vec![
+            TableVerification {
+                schema_name: "public".to_string(),
+                table_name: "with_key".to_string(),
+                result: TableVerificationResult::Sampled {
+                    rows_compared: 50,
+                    mismatched_rows: 2,
+                    examples: vec![
+                        RowMismatch {
+                            primary_key: vec![("id".to_string(), "1".to_string())],
+                            missing_in_destination: false,
+                            differing_columns: vec![ColumnMismatch {
+                                column_name: "amount".to_string(),
+                                source_value: Some("1.5".to_string()),
+                                destination_value: Some("0".to_string()),
+                            }],
+                        },
+                        RowMismatch {
+                            primary_key: vec![("id".to_string(), "2".to_string())],
+                            missing_in_destination: true,
+                            differing_columns: vec![],
+                        },
+                    ],
+                },
+            },
+            TableVerification {
+                schema_name: "public".to_string(),
+                table_name: "without_key".to_string(),
+                result: TableVerificationResult::RowCountOnly {
+                    source_rows: 2,
+                    destination_rows: 1,
+                },
+            },
+        ]
+    );
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn planning_a_copy_does_not_change_the_destination(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(TABLES_TO_VERIFY).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let plan = plan_copy(
+        &source_storage,
+        &destination_storage,
+        CopyDataOptions {
+            max_parallel: Some(NonZeroUsize::new(4).unwrap()),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(plan.parallelism, SupportedParallelism::Parallel);
+    assert!(matches!(plan.data_format, DataFormat::PostgresBinary { .. }));
+    assert_eq!(
+        plan.tables
+            .iter()
+            .map(|t| format!("{}.{}", t.schema_name, t.table_name))
+            .collect_vec(),
+        vec!["public.with_key", "public.without_key"]
+    );
+
+    let destination_tables = destination
+        .get_single_results::<i64>("select count(*) from pg_tables where schemaname = 'public';")
+        .await;
+    assert_eq!(destination_tables, vec![0]);
+
+    execute_copy(plan, &source_storage, &mut destination_storage)
+        .await
+        .unwrap();
+
+    let rows = destination
+        .get_single_results::<i64>("select count(*) from with_key;")
+        .await;
+    assert_eq!(rows, vec![50]);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn copies_in_larger_chunks_between_databases_on_the_same_server(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        create table items(id int primary key, name text not null);
+        insert into items(id, name) select i, 'item ' || i from generate_series(1, 10000) i;
+        "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let unoptimized = plan_copy(&source_storage, &destination_storage, default())
+        .await
+        .unwrap();
+    assert!(!unoptimized.same_server);
+
+    let plan = plan_copy(
+        &source_storage,
+        &destination_storage,
+        CopyDataOptions {
+            same_server_optimization: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(plan.same_server);
+    assert!(plan
+        .to_string()
+        .contains("Same server: data is copied in larger chunks"));
+
+    execute_copy(plan, &source_storage, &mut destination_storage)
+        .await
+        .unwrap();
+
+    let rows = destination
+        .get_single_results::<i64>(
+            "select count(*) from items where name = 'item ' || id;",
+        )
+        .await;
+    assert_eq!(rows, vec![10000]);
+}
+
+async fn create_leak_proof_functions(source: &TestHelper) {
+    source
+        .execute_not_query(
+            r#"
+    create function is_positive(value int4) returns bool as $$ select value > 0 $$
+        language sql immutable leakproof;
+
+    create function whoami() returns text as $$ select current_user::text $$
+        language sql security definer;
+    "#,
+        )
+        .await;
+}
+
+#[pg_test(arg(postgres = 12), arg(postgres = 12))]
+#[pg_test(arg(postgres = 13), arg(postgres = 13))]
+#[pg_test(arg(postgres = 14), arg(postgres = 14))]
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn privilege_downgrade_skips_leak_proof_for_non_superusers(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    create_leak_proof_functions(source).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let restricted_destination = destination.as_restricted_user().await;
+    let mut destination_worker = PostgresInstanceStorage::new(restricted_destination.get_conn())
+        .await
+        .unwrap();
+
+    let report = copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            privilege_downgrade: true,
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
+
+    assert_eq!(report.warnings.len(), 1);
+    assert!(report.warnings[0].contains("leakproof"));
+
+    let destination_schema = introspect_schema(destination).await;
+    let functions = &destination_schema.try_get_schema("public").unwrap().functions;
+    let is_positive = functions.iter().find(|f| f.function_name == "is_positive").unwrap();
+    assert!(!is_positive.leak_proof);
+    let whoami = functions.iter().find(|f| f.function_name == "whoami").unwrap();
+    assert!(whoami.security_definer);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn leak_proof_fails_for_non_superusers_without_privilege_downgrade(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    create_leak_proof_functions(source).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let restricted_destination = destination.as_restricted_user().await;
+    let mut destination_worker = PostgresInstanceStorage::new(restricted_destination.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(&source_storage, &mut destination_worker, default()).await;
+
+    assert_pg_error(result.map(|_| ()), SqlState::INSUFFICIENT_PRIVILEGE);
+}
+
+async fn test_round_trip(sql: &str, source: &TestHelper, destination: &TestHelper) {
+    apply_sql_string(sql, source.get_conn()).await.unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            data_format: None,
+            max_parallel: Some(NonZeroUsize::new(16).unwrap()),
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
+
+    let destination_schema = introspect_schema(destination).await;
+
+    assert_eq!(source_schema, destination_schema);
+
+    assert_differential_noop(source, destination).await;
+}
+
+/// Runs a differential copy against a destination that is already in sync with the source,
+/// and asserts that it did not have to execute any ddl to get there.
+///
+/// Every ddl command is logged by an event trigger in the destination while the differential
+/// copy runs, so any comparison that does not normalize both sides the same way shows up here.
+pub async fn assert_differential_noop(source: &TestHelper, destination: &TestHelper) {
+    destination
+        .execute_not_query(
+            r#"
+            create schema elefant_noop_check;
+            create table elefant_noop_check.ddl_log(command_tag text not null, query text not null);
+            create function elefant_noop_check.log_ddl() returns event_trigger language plpgsql as $$
+            begin
+                insert into elefant_noop_check.ddl_log values (tg_tag, current_query());
+            end;
+            $$;
+            create event trigger elefant_noop_check on ddl_command_end execute function elefant_noop_check.log_ddl();
+            "#,
+        )
+        .await;
+
+    let source = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(
+        &source,
+        &mut destination_worker,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await;
+
+    let executed = destination
+        .get_results::<(String, String)>(
+            "select command_tag, query from elefant_noop_check.ddl_log;",
+        )
+        .await;
+
+    destination
+        .execute_not_query(
+            r#"
+            drop event trigger elefant_noop_check;
+            drop schema elefant_noop_check cascade;
+            "#,
+        )
+        .await;
+
+    result.expect("Failed to run differential copy");
+
+    assert!(
+        executed.is_empty(),
+        "Differential copy into an up to date destination executed ddl:\n{}",
+        executed
+            .iter()
+            .map(|(tag, query)| format!("{tag}: {query}"))
+            .join("\n")
+    );
+}
+
+macro_rules! test_round_trip {
+    ($name:ident, $sql:literal) => {
+        mod $name {
+            use super::*;
+
+            const SQL: &str = $sql;
+
+            #[pg_test(arg(postgres = 12), arg(postgres = 12))]
+            #[pg_test(arg(postgres = 13), arg(postgres = 13))]
+            #[pg_test(arg(postgres = 14), arg(postgres = 14))]
+            #[pg_test(arg(postgres = 15), arg(postgres = 15))]
+            #[pg_test(arg(postgres = 16), arg(postgres = 16))]
+            #[pg_test(arg(postgres = 17), arg(postgres = 17))]
+            async fn non_differential(source: &TestHelper, destination: &TestHelper) {
+                test_round_trip(SQL, source, destination).await;
+            }
+
+            #[pg_test(arg(postgres = 12))]
+            #[pg_test(arg(postgres = 13))]
+            #[pg_test(arg(postgres = 14))]
+            #[pg_test(arg(postgres = 15))]
+            #[pg_test(arg(postgres = 16))]
+            #[pg_test(arg(postgres = 17))]
+            async fn differential(source: &TestHelper) {
+                test_differential_copy_generic(source, SQL).await;
+            }
+        }
+    };
+}
+
+test_round_trip!(
+    foreign_key_actions_are_preserved,
+    r#"
+        CREATE TABLE products (
+            product_no integer PRIMARY KEY,
+            name text,
+            price numeric
+        );
+
+        CREATE TABLE orders (
+            order_id integer PRIMARY KEY,
+            shipping_address text
+        );
+
+        CREATE TABLE order_items (
+            product_no integer REFERENCES products ON DELETE RESTRICT ON UPDATE CASCADE,
+            order_id integer REFERENCES orders ON DELETE CASCADE ON UPDATE RESTRICT,
+            quantity integer,
+            PRIMARY KEY (product_no, order_id)
+        );
+    "#
+);
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn filtered_foreign_key_set_null(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(
+        r#"
+        CREATE TABLE tenants (
+            tenant_id integer PRIMARY KEY
+        );
+
+        CREATE TABLE users (
+            tenant_id integer REFERENCES tenants ON DELETE CASCADE,
+            user_id integer NOT NULL,
+            PRIMARY KEY (tenant_id, user_id)
+        );
+
+        CREATE TABLE posts (
+            tenant_id integer REFERENCES tenants ON DELETE CASCADE,
+            post_id integer NOT NULL,
+            author_id integer,
+            PRIMARY KEY (tenant_id, post_id),
+            FOREIGN KEY (tenant_id, author_id) REFERENCES users ON DELETE SET NULL (author_id)
+        );
+    "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+test_round_trip!(
+    generated_columns,
+    r#"
+    CREATE TABLE people (
+        height_cm numeric,
+        height_in numeric GENERATED ALWAYS AS (height_cm / 2.54) STORED
+    );
+    "#
+);
+
+test_round_trip!(
+    functions,
+    r#"
+
+    create function add(a integer, b integer) returns integer as $$
+        begin
+            return a + b;
+        end;
+    $$ language plpgsql;
+
+    create function filter_stuff(value text) returns table(id int, name text) as
+        $$
+        begin
+
+        create temp table temp_table(id int, name text);
+
+        insert into temp_table(id, name) values (1, 'foo'), (2, 'bar');
+
+        return query select * from temp_table where name = value;
+
+        end;
+
+        $$ language plpgsql;
+    "#
+);
+
+test_round_trip!(
+    qouted_identifier_name,
+    r#"
+        create table "MyTable" (
+            "MyColumn" int,
+            "MyTextColumn" text
+        );
+
+        create index "MyIndex" on "MyTable" (lower("MyTextColumn"));
+    "#
+);
+
+//language=postgresql
+test_round_trip!(
+    ddl_dependencies_1,
+    r#"
+        create function a_is_odd(a integer) returns boolean as $$
+        begin
+            return a % 2 = 1;
+        end;
+        $$ language plpgsql;
+
+        create function b_is_even(a integer) returns boolean as $$
+        begin
+            return a_is_odd(a) = false;
+        end;
+        $$ language plpgsql;
+    "#
+);
+
+//language=postgresql
+test_round_trip!(
+    ddl_dependencies_2,
+    r#"
+        create function b_is_odd(a integer) returns boolean as $$
+        begin
+            return a % 2 = 1;
+        end;
+        $$ language plpgsql;
+
+        create function a_is_even(a integer) returns boolean as $$
+        begin
+            return b_is_odd(a) = false;
+        end;
+        $$ language plpgsql;
+    "#
+);
+
+//language=postgresql
+test_round_trip!(
+    ddl_dependencies_1_1,
+    r#"
+        create function b_is_even(a integer) returns boolean as $$
+        begin
+            return a_is_odd(a) = false;
+        end;
+        $$ language plpgsql;
+
+        create function a_is_odd(a integer) returns boolean as $$
+        begin
+            return a % 2 = 1;
+        end;
+        $$ language plpgsql;
+    "#
+);
+
+//language=postgresql
+test_round_trip!(
+    ddl_dependencies_2_2,
+    r#"
+        create function a_is_even(a integer) returns boolean as $$
+        begin
+            return b_is_odd(a) = false;
+        end;
+        $$ language plpgsql;
+
+        create function b_is_odd(a integer) returns boolean as $$
+        begin
+            return a % 2 = 1;
+        end;
+        $$ language plpgsql;
+    "#
+);
+
+test_round_trip!(
+    ddl_dependencies_3,
+    r#"
+        create function is_odd(a integer) returns boolean as $$
+        begin
+            return a % 2 = 1;
+        end;
+        $$ language plpgsql;
+
+        create table tab(
+            value int not null check (is_odd(value))
+        );
+    "#
+);
+
+test_round_trip!(
+    ddl_dependencies_4,
+    r#"
+        create view a_view as select 1 as value;
+
+        create view b_view as select * from a_view;
+    "#
+);
+
+test_round_trip!(
+    ddl_dependencies_4_opposite,
+    r#"
+        create view b_view as select 1 as value;
+
+        create view a_view as select * from b_view;
+    "#
+);
+
+test_round_trip!(
+    ddl_dependencies_5,
+    r#"
+        create materialized view a_view as select 1 as value;
+
+        create materialized view b_view as select * from a_view;
+    "#
+);
+
+test_round_trip!(
+    ddl_dependencies_5_opposite,
+    r#"
+        create materialized view b_view as select 1 as value;
+
+        create materialized view a_view as select * from b_view;
+    "#
+);
+
+test_round_trip!(
+    functions_reading_from_tables_in_pure_sql,
+    r#"
+create table my_table(
+    value int not null
+);
+
+create function my_function() returns bigint as $$
+    select sum(value) from my_table
+$$ language sql;
+"#
+);
+
+test_round_trip!(
+    comments_on_stuff,
+    r#"
+        create table my_table(
+            value serial not null,
+            another_value int not null unique
+        );
+
+        alter table my_table add constraint my_table_value_check check (value > 0);
+
+        comment on table my_table is 'This is a ''table''';
+        comment on column my_table.value is 'This is a column';
+        comment on constraint my_table_value_check on my_table is 'This is a constraint';
+
+        create function my_function() returns int as $$ begin return 1; end; $$ language plpgsql;
+        create function my_function_2(a int, b int) returns int as $$ begin return a + b; end; $$ language plpgsql;
+
+        comment on function my_function() is 'This is a function';
+        comment on function my_function_2(int, int) is 'This is another function';
+
+        create view my_view as select 1 as value;
+
+        comment on view my_view is 'This is a view';
+
+        comment on schema public is 'This is a schema';
+
+        comment on sequence my_table_value_seq is 'This is a sequence';
+
+        comment on index my_table_another_value_key is 'This is an index';
+        comment on constraint my_table_another_value_key on my_table is 'This is a unique constraint';
+
+    "#
+);
+
+test_round_trip!(
+    array_columns,
+    r#"
+        create table my_table(
+            id serial primary key,
+            names text[]
+        );
+    "#
+);
+
+test_round_trip!(
+    materialized_views,
+    r#"
+        create table my_table(
+            id serial primary key,
+            name text
+        );
+
+        insert into my_table(name) values ('foo'), ('bar');
+
+        create materialized view my_materialized_view as select id, name from my_table;
+
+        comment on materialized view my_materialized_view is 'This is a materialized view';
+    "#
+);
+
+test_round_trip!(
+    materialized_views_with_no_data,
+    r#"
+        create table my_table(
+            id serial primary key,
+            name text
+        );
+
+        insert into my_table(name) values ('foo'), ('bar');
+
+        create materialized view populated_view as select id, name from my_table;
+
+        create materialized view unpopulated_view as select id, name from my_table with no data;
+    "#
+);
+
+test_round_trip!(
+    leak_proof_and_security_definer_functions,
+    r#"
+    create function is_positive(value int4) returns bool as $$ select value > 0 $$
+        language sql immutable leakproof;
+
+    create function whoami() returns text as $$ select current_user::text $$
+        language sql security definer;
+    "#
+);
+
+#[pg_test(arg(postgres = 12), arg(postgres = 12))]
+#[pg_test(arg(postgres = 13), arg(postgres = 13))]
+#[pg_test(arg(postgres = 14), arg(postgres = 14))]
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn copies_rows_of_tables_without_copyable_columns(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    test_round_trip(
+        r#"
+        create table identity_and_generated (
+            id int generated always as identity,
+            doubled int generated always as (id * 2) stored
+        );
+
+        create table only_generated (
+            value int generated always as (42) stored
+        );
+
+        create table no_columns ();
+
+        insert into identity_and_generated default values;
+        insert into identity_and_generated default values;
+        insert into only_generated default values;
+        insert into only_generated default values;
+        insert into no_columns default values;
+    "#,
+        source,
+        destination,
+    )
+    .await;
+
+    let identity_rows = destination
+        .get_results::<(i32, i32)>("select id, doubled from identity_and_generated order by id;")
+        .await;
+    assert_eq!(identity_rows, vec![(1, 2), (2, 4)]);
+
+    let generated_values = destination
+        .get_single_results::<i32>("select value from only_generated;")
+        .await;
+    assert_eq!(generated_values, vec![42, 42]);
+
+    let no_columns_rows = destination
+        .get_single_result::<i64>("select count(*) from no_columns;")
+        .await;
+    assert_eq!(no_columns_rows, 1);
+}
+
+async fn copy_with_staging_swap(
+    source: &TestHelper,
+    destination: &TestHelper,
+    keep_old_schema: bool,
+) -> crate::Result<()> {
+    // The source keeps its snapshot transaction open, so each copy gets its own connection.
+    let source_connection = source.get_schema_connection("public").await;
+    let source_storage = PostgresInstanceStorage::new(&source_connection)
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            target_schema: Some("app".to_string()),
+            staging_swap: Some(StagingSwap::new(keep_old_schema)),
+            ..default()
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn get_schema_names(helper: &TestHelper) -> Vec<String> {
+    helper
+        .get_single_results::<String>(
+            "select nspname::text from pg_namespace where nspname = 'app' or nspname like '\\_elefant\\_%' order by nspname;",
+        )
+        .await
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn staging_swap_refreshes_the_schema_while_it_is_queried(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        create schema app;
+        create table app.items(id int primary key, name text not null);
+        create view app.item_names as select name from app.items;
+        insert into app.items(id, name) values (1, 'first');
+        "#,
+        )
+        .await;
+
+    copy_with_staging_swap(source, destination, false)
+        .await
+        .expect("Failed to copy data");
+
+    let names = destination
+        .get_single_results::<String>("select name from app.item_names order by name;")
+        .await;
+    assert_eq!(names, vec!["first".to_string()]);
+
+    source
+        .execute_not_query("insert into app.items(id, name) values (2, 'second');")
+        .await;
+
+    // A reader keeps querying the old schema while the refresh runs.
+    let reader = destination.get_schema_connection("public").await;
+    let query_while_refreshing = async {
+        let mut count = 0;
+        for _ in 0..10 {
+            count = reader
+                .get_single_result::<i64>("select count(*) from app.items")
+                .await
+                .unwrap();
+            assert!(count == 1 || count == 2, "Unexpected count {}", count);
+        }
+        count
+    };
+
+    let (result, _) = tokio::join!(
+        copy_with_staging_swap(source, destination, false),
+        query_while_refreshing
+    );
+    result.expect("Failed to copy data");
+
+    let names = destination
+        .get_single_results::<String>("select name from app.item_names order by name;")
+        .await;
+    assert_eq!(names, vec!["first".to_string(), "second".to_string()]);
+
+    assert_eq!(get_schema_names(destination).await, vec!["app".to_string()]);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn staging_swap_can_keep_the_old_schema(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(
+            r#"
+        create schema app;
+        create table app.items(id int primary key);
+        insert into app.items(id) values (1);
+        "#,
+        )
+        .await;
+
+    copy_with_staging_swap(source, destination, false)
+        .await
+        .expect("Failed to copy data");
+    copy_with_staging_swap(source, destination, true)
+        .await
+        .expect("Failed to copy data");
+
+    let schemas = get_schema_names(destination).await;
+    assert_eq!(schemas.len(), 2, "{:?}", schemas);
+    assert!(schemas[0].starts_with("_elefant_old_"), "{:?}", schemas);
+    assert_eq!(schemas[1], "app");
+
+    let old_items = destination
+        .get_single_result::<i64>(&format!("select count(*) from {}.items;", schemas[0]))
+        .await;
+    assert_eq!(old_items, 1);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn staging_swap_is_blocked_by_objects_depending_on_the_schema(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        create schema app;
+        create table app.items(id int primary key);
+        "#,
+        )
+        .await;
+
+    copy_with_staging_swap(source, destination, false)
+        .await
+        .expect("Failed to copy data");
+
+    destination
+        .execute_not_query("create view public.item_ids as select id from app.items;")
+        .await;
+
+    let result = copy_with_staging_swap(source, destination, false).await;
+
+    match result {
+        Err(ElefantToolsError::StagingSwapBlocked {
+            schema_name,
+            blockers,
+        }) => {
+            assert_eq!(schema_name, "app");
+            assert_eq!(blockers, vec!["view public.item_ids".to_string()]);
+        }
+        other => panic!("Expected the swap to be blocked, got {:?}", other),
+    }
+
+    assert_eq!(get_schema_names(destination).await, vec!["app".to_string()]);
+}
+
+const MATERIALIZED_VIEW_WITH_INDEX: &str = r#"
+    create table my_table(
+        id serial primary key,
+        name text
+    );
+
+    insert into my_table(name) values ('foo'), ('bar');
+
+    create materialized view my_materialized_view as select id, name from my_table;
+
+    create unique index my_materialized_view_id_idx on my_materialized_view (id);
+    create index my_materialized_view_name_idx on my_materialized_view (name) where name is not null;
+
+    comment on index my_materialized_view_id_idx is 'Required to refresh concurrently';
+"#;
+
+#[pg_test(arg(postgres = 12), arg(postgres = 12))]
+#[pg_test(arg(postgres = 13), arg(postgres = 13))]
+#[pg_test(arg(postgres = 14), arg(postgres = 14))]
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn materialized_views_can_be_refreshed_concurrently_after_copy(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    test_round_trip(MATERIALIZED_VIEW_WITH_INDEX, source, destination).await;
+
+    destination
+        .execute_not_query("refresh materialized view concurrently my_materialized_view;")
+        .await;
+
+    let names = destination
+        .get_single_results::<String>("select name from my_materialized_view order by id;")
+        .await;
+    assert_eq!(names, vec!["foo".to_string(), "bar".to_string()]);
+}
+
+test_round_trip!(
+    triggers,
+    r#"
+
+        create table my_table(
+            value int
+        );
+
+        create function my_trigger_function() returns trigger as $$
+        begin return new; end;
+        $$ language plpgsql;
+        
+        create function my_parametised_trigger_function() returns trigger as $$
+        begin return new; end;
+        $$ language plpgsql;
+
+        create trigger my_trigger after insert on my_table for each row execute function my_trigger_function();
+
+        comment on trigger my_trigger on my_table is 'This is a trigger';
+
+        create trigger scoped_trigger before update on my_table for each row when (OLD.value is distinct from NEW.value) execute procedure my_trigger_function();
+
+        create trigger truncate_trigger after truncate on my_table for each statement execute procedure my_trigger_function();
+
+        create trigger updt_insert_trigger before update or insert on my_table for each row execute procedure my_parametised_trigger_function(42, 'foo');
+    "#
+);
+
+test_round_trip!(
+    enumerations,
+    r#"
+    create type mood as enum ('sad', 'ok', 'happy');
     create table person (
         name text,
         current_mood mood
     );
 
-    alter type mood add value 'mehh' before 'ok';
-    "#
-);
+    alter type mood add value 'mehh' before 'ok';
+    "#
+);
+
+test_round_trip!(
+    composite_types,
+    r#"
+    create type mood as enum ('sad', 'ok', 'happy');
+    create domain zip_code as varchar(5);
+
+    create type address as (
+        street text,
+        zip zip_code,
+        tags text[],
+        current_mood mood
+    );
+
+    comment on type address is 'A postal address';
+
+    create table person (
+        name text,
+        home address
+    );
+
+    create function get_street(a address) returns text as $$
+        select a.street
+    $$ language sql immutable;
+
+    insert into person(name, home) values ('foo', row('Main street', '12345', '{a,b}', 'happy'));
+    "#
+);
+
+test_round_trip!(
+    row_level_security_policies,
+    r#"
+    create table documents(id int4 primary key, owner text not null, title text not null);
+
+    create function is_owner(owner text) returns bool as $$
+        select owner = current_user
+    $$ language sql stable;
+
+    alter table documents enable row level security;
+
+    create policy own_documents on documents for select using (is_owner(owner));
+    create policy titled_documents on documents as restrictive for insert to public with check (length(title) > 0);
+
+    insert into documents(id, owner, title) values (1, 'someone', 'hello');
+    "#
+);
+
+test_round_trip!(
+    enumerations_used_by_functions_in_other_schemas,
+    r#"
+    create schema zzz_types;
+
+    create type zzz_types.mood as enum ('sad', 'ok', 'happy');
+
+    create function cheer_up(m zzz_types.mood) returns zzz_types.mood as $$
+        select case when m = 'sad' then 'ok'::zzz_types.mood else 'happy'::zzz_types.mood end
+    $$ language sql immutable;
+    "#
+);
+
+test_round_trip!(
+    range_partitions,
+    r#"
+    CREATE TABLE sales (
+                       sale_id INT,
+                       sale_date DATE,
+                       product_id INT,
+                       quantity INT,
+                       amount NUMERIC
+) partition by range (sale_date);
+
+CREATE TABLE sales_january PARTITION OF sales
+    FOR VALUES FROM ('2023-01-01') TO ('2023-02-01');
+
+CREATE TABLE sales_february PARTITION OF sales
+    FOR VALUES FROM ('2023-02-01') TO ('2023-03-01');
+
+CREATE TABLE sales_march PARTITION OF sales
+    FOR VALUES FROM ('2023-03-01') TO ('2023-04-01');
+    "#
+);
+
+test_round_trip!(
+    partially_attached_partitioned_index,
+    r#"
+create table sales (
+    sale_id int,
+    sale_date date
+) partition by range (sale_date);
+
+create table sales_january partition of sales
+    for values from ('2023-01-01') to ('2023-02-01');
+
+create table sales_february partition of sales
+    for values from ('2023-02-01') to ('2023-03-01');
+
+create index sales_sale_date_idx on only sales (sale_date);
+create index sales_january_custom_idx on sales_january (sale_date);
+alter index sales_sale_date_idx attach partition sales_january_custom_idx;
+    "#
+);
+
+test_round_trip!(
+    list_partitions,
+    r#"
+CREATE TABLE products (
+    product_id int,
+    category TEXT,
+    product_name TEXT,
+    price NUMERIC
+) partition by list(category);
+
+CREATE TABLE electronics PARTITION OF products
+    FOR VALUES IN ('Electronics');
+
+CREATE TABLE clothing PARTITION OF products
+    FOR VALUES IN ('Clothing');
+
+CREATE TABLE furniture PARTITION OF products
+    FOR VALUES IN ('Furniture');
+    "#
+);
+
+test_round_trip!(
+    hash_partitions,
+    r#"
+CREATE TABLE orders (
+    order_id int,
+    order_date DATE,
+    customer_id INT,
+    total_amount NUMERIC
+) partition by hash(customer_id);
+
+CREATE TABLE orders_1 PARTITION OF orders
+    FOR VALUES WITH (MODULUS 3, REMAINDER 0);
+
+CREATE TABLE orders_2 PARTITION OF orders
+    FOR VALUES WITH (MODULUS 3, REMAINDER 1);
+
+CREATE TABLE orders_3 PARTITION OF orders
+    FOR VALUES WITH (MODULUS 3, REMAINDER 2);
+    "#
+);
+
+test_round_trip!(
+    partition_defaults_added_after_partitions,
+    r#"
+create table invoices (
+    invoice_id int not null,
+    issued_on date not null
+) partition by range (issued_on);
+
+create table invoices_2023 partition of invoices
+    for values from ('2023-01-01') to ('2024-01-01');
+
+create table invoices_2024 partition of invoices
+    for values from ('2024-01-01') to ('2025-01-01');
+
+alter table invoices add column status text not null default 'open';
+alter table invoices add column amount numeric;
+alter table invoices alter column amount set default 0;
+
+insert into invoices(invoice_id, issued_on) values (1, '2023-06-01'), (2, '2024-06-01');
+    "#
+);
+
+test_round_trip!(
+    inheritance,
+    r#"
+create table pets (
+    id serial primary key,
+    name text not null check(length(name) > 1)
+);
+
+create table dogs(
+    breed text not null check(length(breed) > 1)
+) inherits (pets);
+
+create table cats(
+    color text not null
+) inherits (pets);
+    "#
+);
+
+test_round_trip!(
+    inheritance_with_no_inherit_and_not_valid_checks,
+    r#"
+create table pets (
+    id int primary key,
+    name text not null,
+    constraint only_abstract_pets check (false) no inherit
+);
+
+create table dogs(
+    breed text not null
+) inherits (pets);
+
+insert into dogs(id, name, breed) values (1, 'Fido', 'labrador'), (2, '', 'poodle');
+
+alter table pets add constraint pets_name_not_empty check (length(name) > 0) not valid;
+alter table dogs add constraint dogs_breed_check check (length(breed) > 1) no inherit not valid;
+"#
+);
+
+test_round_trip!(
+    multiple_inheritance,
+    r#"
+create table animal(
+    breed text not null
+);
+
+create table human(
+    name text not null
+);
+
+create table animorph() inherits (animal, human);
+"#
+);
+
+test_round_trip!(
+    aggregate_with_functions_in_later_schema_and_extension_sort_operator,
+    r#"
+create extension citext;
+create schema z_functions;
+
+create function z_functions.pick_larger(state citext, value citext) returns citext
+    as $$ select citext_larger(state, value) $$ language sql;
+create function z_functions.finish(state citext) returns citext
+    as $$ select state $$ language sql;
+
+create aggregate citext_max(citext) (
+    sfunc = z_functions.pick_larger,
+    stype = citext,
+    finalfunc = z_functions.finish,
+    sortop = >
+);
+"#
+);
+
+test_round_trip!(
+    functions_returning_custom_table,
+    r#"
+create function my_function() returns table(id int, name text) as $$
+begin
+    return query select 1, 'foo';
+end;
+$$ language plpgsql;
+"#
+);
+
+test_round_trip!(
+    functions_returning_table_type,
+    r#"
+
+create table my_table(id int, name text);
+
+create function my_function() returns setof my_table as $$
+begin
+    return query select 1, 'foo';
+end;
+$$ language plpgsql;
+"#
+);
+
+#[pg_test(arg(postgres = 13), arg(postgres = 13))]
+#[pg_test(arg(postgres = 14), arg(postgres = 14))]
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn storage_parameters(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(
+        r#"
+    create table my_table(name text not null) with (fillfactor=50);
+
+    create index my_index on my_table(name) with (fillfactor = 20, deduplicate_items = off);
+    "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+#[pg_test(arg(postgres = 12), arg(postgres = 12))]
+async fn storage_parameters_pg_12(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(
+        r#"
+    create table my_table(name text not null) with (fillfactor=50);
+
+    create index my_index on my_table(name) with (fillfactor = 20);
+    "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_hypertable_time_single_dimension(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(r#"
+
+CREATE TABLE stocks_real_time (
+  time TIMESTAMPTZ NOT NULL,
+  symbol TEXT NOT NULL,
+  price DOUBLE PRECISION NULL,
+  day_volume INT NULL
+);
+
+SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
+
+CREATE INDEX ix_symbol_time ON stocks_real_time (symbol, time DESC);
+
+insert into stocks_real_time(time, symbol, price, day_volume) values ('2023-01-01', 'AAPL', 100.0, 1000);
+
+        "#, source, destination).await;
+
+    let items = destination
+        .get_results::<(String, f64, i32)>(
+            "select symbol, price, day_volume from stocks_real_time;",
+        )
+        .await;
+
+    assert_eq!(items, vec![("AAPL".to_string(), 100.0, 1000)]);
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_hypertable_time_multiple_dimensions(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    test_round_trip(
+        r#"
+
+CREATE TABLE stocks_real_time (
+  time TIMESTAMPTZ NOT NULL,
+  symbol TEXT NOT NULL,
+  price DOUBLE PRECISION NULL,
+  day_volume INT NULL,
+  primary key (time, symbol, day_volume)
+);
+
+SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
+SELECT add_dimension('stocks_real_time', by_hash('symbol', 4));
+SELECT add_dimension('stocks_real_time', by_range('day_volume', 100));
+
+CREATE INDEX ix_symbol_time ON stocks_real_time (symbol, time DESC);
+
+        "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_hypertable_compression(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(
+        r#"
+
+CREATE TABLE stocks_real_time (
+  time TIMESTAMPTZ NOT NULL,
+  symbol TEXT NOT NULL,
+  price DOUBLE PRECISION NULL,
+  day_volume INT NOT NULL
+);
+
+SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
+
+alter table stocks_real_time set(
+    timescaledb.compress,
+        timescaledb.compress_segmentby = 'symbol',
+        timescaledb.compress_orderby = 'time,day_volume',
+        timescaledb.compress_chunk_time_interval='14 days'
+        );
+
+select add_compression_policy('stocks_real_time', interval '7 days');
+
+        "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_continuous_aggregate(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(r#"
+CREATE TABLE stocks_real_time (
+  time TIMESTAMPTZ NOT NULL,
+  symbol TEXT NOT NULL,
+  price DOUBLE PRECISION NULL,
+  day_volume INT NOT NULL
+);
+
+SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
+
+insert into stocks_real_time(time, symbol, price, day_volume) values ('2023-01-01', 'AAPL', 100.0, 1000);
+
+CREATE MATERIALIZED VIEW stock_candlestick_daily
+WITH (timescaledb.continuous) AS
+SELECT
+  time_bucket('1 day', "time") AS day,
+  symbol,
+  max(price) AS high,
+  first(price, time) AS open,
+  last(price, time) AS close,
+  min(price) AS low
+FROM stocks_real_time srt
+GROUP BY day, symbol
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('stock_candlestick_daily',
+                                       start_offset => INTERVAL '6 month',
+                                       end_offset => INTERVAL '1 day',
+                                       schedule_interval => INTERVAL '1 hour');
+
+alter materialized view stock_candlestick_daily set (timescaledb.compress = true);
+
+SELECT add_compression_policy('stock_candlestick_daily', compress_after=>'360 days'::interval);
+SELECT add_retention_policy('stock_candlestick_daily', INTERVAL '2 years');
+       "#, source, destination).await;
+
+    let items = destination
+        .get_results::<(String, String, f64, f64, f64, f64)>(
+            "select day::text, symbol, high, open, close, low from stock_candlestick_daily;",
+        )
+        .await;
+
+    assert_eq!(
+        items,
+        vec![(
+            "2023-01-01 00:00:00+00".to_string(),
+            "AAPL".to_string(),
+            100.0,
+            100.0,
+            100.0,
+            100.0
+        )]
+    );
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_continuous_aggregate_real_time(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(r#"
+CREATE TABLE stocks_real_time (
+  time TIMESTAMPTZ NOT NULL,
+  symbol TEXT NOT NULL,
+  price DOUBLE PRECISION NULL
+);
+
+SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
+
+CREATE MATERIALIZED VIEW stock_daily_high
+WITH (timescaledb.continuous, timescaledb.materialized_only = false) AS
+SELECT
+  time_bucket('1 day', "time") AS day,
+  symbol,
+  max(price) AS high
+FROM stocks_real_time srt
+GROUP BY day, symbol
+WITH NO DATA;
+
+CREATE MATERIALIZED VIEW stock_daily_low
+WITH (timescaledb.continuous, timescaledb.materialized_only = true) AS
+SELECT
+  time_bucket('1 day', "time") AS day,
+  symbol,
+  min(price) AS low
+FROM stocks_real_time srt
+GROUP BY day, symbol
+WITH NO DATA;
+       "#, source, destination).await;
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_retention_policy(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(
+        r#"
+CREATE TABLE conditions (
+  time TIMESTAMPTZ NOT NULL
+);
+
+SELECT create_hypertable('conditions', by_range('time', '1 hour'::interval));
+SELECT add_retention_policy('conditions', INTERVAL '24 hours');
+       "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_user_defined_jobs(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(
+        r#"
+CREATE PROCEDURE user_defined_action(job_id INT, config JSONB)
+    LANGUAGE PLPGSQL AS
+    $$
+    BEGIN
+        RAISE NOTICE 'Executing job % with config %', job_id, config;
+    END
+    $$;
+
+SELECT add_job('user_defined_action', '1h', config => '{"hypertable":"metrics"}');
+       "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+// This is quite slow, so we only test against 1 postgres instance
+// We are not really testing postgres, but the internal parallel handling
+// in this program.
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn ensure_survives_many_tables(source: &TestHelper, destination: &TestHelper) {
+    let mut sql = String::new();
+
+    for i in 0..50 {
+        sql.push_str(&format!(
+            "create table my_table_{}(id serial primary key, name text);\n",
+            i
+        ));
+        sql.push_str(&format!(
+            r#"
+insert into my_table_{} (
+    name
+)
+select
+    md5(random()::text)
+from generate_series(1, 1000) s(i);"#,
+            i
+        ))
+    }
+
+    test_round_trip(&sql, source, destination).await;
+
+    for i in 0..50 {
+        let items = destination
+            .get_results::<(i32, String)>(&format!("select id, name from my_table_{};", i))
+            .await;
+        assert_eq!(items.len(), 1000);
+    }
+}
+
+#[pg_test(arg(postgres = 15))]
+async fn copies_between_schemas_in_same_db(helper: &TestHelper) {
+    helper
+        .execute_not_query("create schema source_schema; create schema destination_schema;")
+        .await;
+
+    let source = helper.get_schema_connection("source_schema").await;
+    let destination = helper.get_schema_connection("destination_schema").await;
+
+    source
+        .execute_non_query(
+            r#"
+        create table my_table(id serial primary key, name text not null);
+        insert into my_table (
+    name
+)
+select
+    md5(random()::text)
+from generate_series(1, 1000) s(i);
+        "#,
+        )
+        .await
+        .unwrap();
+
+    let source_storage = PostgresInstanceStorage::new(&source).await.unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(&destination).await.unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            target_schema: Some("source_schema".to_string()),
+            rename_schema_to: Some("destination_schema".to_string()),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let destination_schema = SchemaReader::new(&destination)
+        .introspect_database()
+        .await
+        .unwrap()
+        .filtered_to_schema("destination_schema");
+
+    assert_eq!(
+        destination_schema,
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "destination_schema".to_string(),
+                tables: vec![PostgresTable {
+                    name: "my_table".to_string(),
+                    columns: vec![
+                        PostgresColumn {
+                            name: "id".to_string(),
+                            data_type: "int4".to_string(),
+                            is_nullable: false,
+                            ordinal_position: 1,
+                            default_value: Some("nextval('my_table_id_seq'::regclass)".to_string()),
+                            ..default()
+                        },
+                        PostgresColumn {
+                            name: "name".to_string(),
+                            data_type: "text".to_string(),
+                            is_nullable: false,
+                            ordinal_position: 2,
+                            ..default()
+                        },
+                    ],
+                    indices: vec![PostgresIndex {
+                        name: "my_table_pkey".to_string(),
+                        key_columns: vec![PostgresIndexKeyColumn {
+                            ordinal_position: 1,
+                            name: "id".to_string(),
+                            direction: Some(PostgresIndexColumnDirection::Ascending),
+                            nulls_order: Some(PostgresIndexNullsOrder::Last),
+                            operator_class: None,
+                            collation: None,
+                        }],
+                        index_constraint_type: PostgresIndexType::PrimaryKey,
+                        index_type: "btree".to_string(),
+                        ..default()
+                    }],
+                    depends_on: vec![8.into()],
+                    ..default()
+                }],
+                sequences: vec![PostgresSequence {
+                    name: "my_table_id_seq".to_string(),
+                    data_type: "int4".to_string(),
+                    max_value: 2147483647,
+                    last_value: Some(1000),
+                    ..default()
+                }],
+                ..default()
+            }],
+            ..default()
+        }
+    );
+
+    let items = source
+        .get_results::<(i32, String)>("select id, name from my_table;")
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1000);
+
+    let items = destination
+        .get_results::<(i32, String)>("select id, name from my_table;")
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1000);
+}
+
+//language=postgresql
+const TABLES_WITH_GENERATED_KEYS: &str = r#"
+create table serial_keys(id serial primary key, name text not null);
+create table identity_keys(id int4 generated always as identity primary key, name text not null);
+
+insert into serial_keys(name) values ('foo'), ('bar');
+insert into identity_keys(name) values ('foo'), ('bar'), ('baz');
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn copied_sequences_continue_after_the_copied_rows(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(TABLES_WITH_GENERATED_KEYS).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(&source_storage, &mut destination_storage, default())
+        .await
+        .unwrap();
+
+    destination
+        .execute_not_query(
+            r#"
+        insert into serial_keys(name) values ('new');
+        insert into identity_keys(name) values ('new');
+        "#,
+        )
+        .await;
+
+    let serial_ids = destination
+        .get_single_results::<i32>("select id from serial_keys order by id;")
+        .await;
+    assert_eq!(serial_ids, vec![1, 2, 3]);
+
+    let identity_ids = destination
+        .get_single_results::<i32>("select id from identity_keys order by id;")
+        .await;
+    assert_eq!(identity_ids, vec![1, 2, 3, 4]);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn schema_only_copy_does_not_set_sequence_values(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(TABLES_WITH_GENERATED_KEYS).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            schema_only: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let destination_schema = introspect_schema(destination).await;
+    let sequences = &destination_schema.try_get_schema("public").unwrap().sequences;
+    assert_eq!(sequences.len(), 2);
+    assert!(sequences.iter().all(|s| s.last_value.is_none()), "{:?}", sequences);
+}
+
+//language=postgresql
+const SEQUENCES_WITH_CUSTOM_OPTIONS: &str = r#"
+create sequence small_sequence as smallint;
+create sequence cycling_sequence as integer minvalue 10 maxvalue 20 start 15 cycle;
+create sequence descending_sequence increment by -2 cache 5;
+create sequence big_sequence;
+"#;
+
+#[pg_test(arg(postgres = 12), arg(postgres = 16))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+async fn copies_sequences_with_custom_options(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(SEQUENCES_WITH_CUSTOM_OPTIONS).await;
+    source
+        .execute_not_query("select nextval('cycling_sequence') from generate_series(1, 6);")
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(&source_storage, &mut destination_storage, default())
+        .await
+        .unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(
+        source_schema.try_get_schema("public").unwrap().sequences,
+        destination_schema.try_get_schema("public").unwrap().sequences
+    );
+
+    // The sequence is at its maxvalue, so it starts over from its minvalue.
+    let next_value = destination
+        .get_single_result::<i64>("select nextval('cycling_sequence');")
+        .await;
+    assert_eq!(next_value, 10);
+
+    assert_differential_noop(source, destination).await;
+}
+
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+async fn differential_copy_alters_changed_sequences(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(SEQUENCES_WITH_CUSTOM_OPTIONS).await;
+    destination
+        .execute_not_query(
+            r#"
+        create sequence small_sequence as bigint;
+        create sequence cycling_sequence as integer minvalue 10 maxvalue 20 start 15;
+        create sequence descending_sequence increment by -2 cache 5;
+        create sequence big_sequence as smallint;
+        "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(
+        source_schema.try_get_schema("public").unwrap().sequences,
+        destination_schema.try_get_schema("public").unwrap().sequences
+    );
+}
+
+#[pg_test(arg(postgres = 15))]
+async fn rename_schema_copy_uses_sequences_of_the_renamed_schema(helper: &TestHelper) {
+    //language=postgresql
+    helper
+        .execute_not_query(
+            r#"
+        create schema a;
+        create schema b;
+
+        create table a.my_table(id serial primary key, name text not null);
+        create table a.child(id serial primary key, parent_id int4 not null references a.my_table(id));
+        insert into a.my_table(name) values ('foo'), ('bar'), ('baz');
+
+        create table b.my_table(id serial primary key, name text not null);
+        insert into b.my_table(name) select 'item ' || i from generate_series(1, 500) i;
+        "#,
+        )
+        .await;
+
+    let source = helper.get_schema_connection("a").await;
+    let destination = helper.get_schema_connection("b").await;
+
+    let source_storage = PostgresInstanceStorage::new(&source).await.unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(&destination).await.unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            target_schema: Some("a".to_string()),
+            rename_schema_to: Some("c".to_string()),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let id = helper
+        .get_single_result::<i32>("insert into c.my_table(name) values ('new') returning id;")
+        .await;
+    assert_eq!(id, 4);
+
+    let b_last_value = helper
+        .get_single_result::<i64>("select last_value from b.my_table_id_seq;")
+        .await;
+    assert_eq!(b_last_value, 500);
+
+    let referenced_table = helper
+        .get_single_result::<String>(
+            "select confrelid::regclass::text from pg_constraint where conrelid = 'c.child'::regclass and contype = 'f';",
+        )
+        .await;
+    assert_eq!(referenced_table, "c.my_table");
+}
+
+#[pg_test(arg(postgres = 15))]
+async fn rename_schema_copy_keeps_sequence_shared_by_tables(helper: &TestHelper) {
+    //language=postgresql
+    helper
+        .execute_not_query(
+            r#"
+        create schema a;
+        create schema b;
+
+        create sequence a.global_id_seq;
+        create table a.customers(id int8 primary key default nextval('a.global_id_seq'), name text not null);
+        create table a.orders(id int8 primary key default nextval('a.global_id_seq'), customer_id int8 not null references a.customers(id));
+        insert into a.customers(name) values ('foo'), ('bar');
+        insert into a.orders(customer_id) select id from a.customers;
+        "#,
+        )
+        .await;
+
+    let source = helper.get_schema_connection("a").await;
+    let destination = helper.get_schema_connection("b").await;
+
+    let source_definition = SchemaReader::new(&source)
+        .introspect_database()
+        .await
+        .unwrap();
+    let source_schema = source_definition.try_get_schema("a").unwrap();
+    let sequence = &source_schema.sequences[0];
+    for table in &source_schema.tables {
+        assert_eq!(table.depends_on, vec![sequence.object_id], "{}", table.name);
+    }
+
+    let source_storage = PostgresInstanceStorage::new(&source).await.unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(&destination).await.unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            target_schema: Some("a".to_string()),
+            rename_schema_to: Some("b".to_string()),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let customer_id = helper
+        .get_single_result::<i64>("insert into b.customers(name) values ('new') returning id;")
+        .await;
+    assert_eq!(customer_id, 5);
+
+    let order_id = helper
+        .get_single_result::<i64>(
+            "insert into b.orders(customer_id) values (5) returning id;",
+        )
+        .await;
+    assert_eq!(order_id, 6);
+
+    let a_last_value = helper
+        .get_single_result::<i64>("select last_value from a.global_id_seq;")
+        .await;
+    assert_eq!(a_last_value, 4);
+}
+
+test_round_trip!(
+    column_default_calls_function_in_later_schema,
+    r#"
+create schema a_tables;
+create schema z_functions;
+
+create function z_functions.generate_uid() returns text as $$
+    select 'uid-' || md5(random()::text);
+$$ language sql volatile;
+
+create table a_tables.items(
+    id text primary key default z_functions.generate_uid(),
+    name text not null
+);
+
+insert into a_tables.items(name) values ('foo'), ('bar');
+"#
+);
+
+test_round_trip!(
+    two_way_references,
+    r#"
+create table assets(
+    asset_id serial primary key,
+    asset_digiupload_id int
+);
+
+create table asset_digiuploads(
+    asset_digiupload_id serial primary key,
+    asset_id int references assets(asset_id)
+);
+
+alter table assets add constraint fk_asset_digiupload_id foreign key (asset_digiupload_id) references asset_digiuploads(asset_digiupload_id);
+"#
+);
+
+test_round_trip!(
+    multiple_unique_constraints_on_same_table,
+    r#"
+create table users(
+    id serial primary key,
+    username text not null unique,
+    email text not null unique
+);
+"#
+);
+
+const INDEX_SORT_ORDERS_SQL: &str = r#"
+create table items(
+    id int primary key,
+    sku text not null,
+    name text not null,
+    price int,
+    constraint items_sku_key unique (sku) include (name)
+);
+
+create unique index items_price_idx on items (price desc nulls first) include (name, sku);
+create index items_name_idx on items (name asc nulls last, price desc);
+create index items_name_hash_idx on items using hash (name);
+"#;
+
+#[pg_test(arg(postgres = 12), arg(postgres = 16))]
+async fn differential_copy_keeps_equivalent_indexes_across_versions(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(INDEX_SORT_ORDERS_SQL).await;
+    destination.execute_not_query(INDEX_SORT_ORDERS_SQL).await;
+
+    assert_differential_noop(source, destination).await;
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_recreates_changed_indexes(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+            create table items(
+                sku text not null,
+                name text not null,
+                price int,
+                constraint items_sku_key unique (sku)
+            );
+            create unique index items_price_idx on items (price);
+            create table orders(
+                id int primary key,
+                item_sku text not null references items(sku)
+            );
+            "#,
+        )
+        .await;
+    destination
+        .execute_not_query(
+            r#"
+            create table items(
+                sku text not null,
+                name text not null,
+                price int,
+                constraint items_sku_key unique (sku) include (name)
+            );
+            create unique index items_price_idx on items (price desc nulls first) include (name);
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    let source_items = source_schema
+        .try_get_schema("public")
+        .unwrap()
+        .try_get_table("items")
+        .unwrap();
+    let destination_items = destination_schema
+        .try_get_schema("public")
+        .unwrap()
+        .try_get_table("items")
+        .unwrap();
+    for index in &source_items.indices {
+        let destination_index = destination_items
+            .indices
+            .iter()
+            .find(|i| i.name == index.name)
+            .unwrap();
+        assert!(index.is_equivalent_to(destination_index, true), "{}", index.name);
+    }
+    assert!(destination_items
+        .constraints
+        .iter()
+        .any(|c| c.name() == "items_sku_key"));
+
+    assert_differential_noop(source, destination).await;
+}
+
+#[derive(Debug)]
+struct SkipCreateIndex;
+
+impl StatementInterceptor for SkipCreateIndex {
+    fn intercept(&self, statement: &DdlStatement) -> InterceptResult {
+        if statement.sql.starts_with("create index ") {
+            InterceptResult::Skip
+        } else {
+            InterceptResult::Keep
+        }
+    }
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn statement_interceptor_skips_and_replaces_statements(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+            create table items(
+                id int primary key,
+                name text not null
+            );
+            create index items_name_idx on items (name);
+            insert into items(id, name) values (1, 'foo');
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let report = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            statement_interceptor: Some(Arc::new(SkipCreateIndex)),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        report.skipped_statements,
+        vec!["create index items_name_idx on public.items using btree (name asc nulls last);".to_string()]
+    );
+
+    let destination_schema = introspect_schema(destination).await;
+    let items = destination_schema
+        .try_get_schema("public")
+        .unwrap()
+        .try_get_table("items")
+        .unwrap();
+    assert_eq!(
+        items.indices.iter().map(|i| i.name.as_str()).collect_vec(),
+        vec!["items_pkey"]
+    );
+
+    destination.execute_not_query("drop table items;").await;
+
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            statement_interceptor: Some(Arc::new(AppendStorageParameter::new("fillfactor=70"))),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let destination_schema = introspect_schema(destination).await;
+    let items = destination_schema
+        .try_get_schema("public")
+        .unwrap()
+        .try_get_table("items")
+        .unwrap();
+    assert_eq!(items.storage_parameters, vec!["fillfactor=70".to_string()]);
+    assert_eq!(items.indices.len(), 2);
+
+    let names = destination
+        .get_single_results::<String>("select name from items;")
+        .await;
+    assert_eq!(names, vec!["foo".to_string()]);
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn maps_default_tablespace(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(
+            r#"
+            create table items(
+                id int primary key using index tablespace pg_default,
+                name text not null
+            ) tablespace pg_default;
+            create index items_name_idx on items (name) tablespace pg_default;
+            insert into items(id, name) values (1, 'foo');
+            "#,
+        )
+        .await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            tablespace_mapping: HashMap::from([(
+                "pg_default".to_string(),
+                "missing_tablespace".to_string(),
+            )]),
+            ..default()
+        },
+    )
+    .await;
+    assert!(
+        matches!(&result, Err(ElefantToolsError::TablespaceNotFound(t)) if t == "missing_tablespace"),
+        "{:?}",
+        result
+    );
+    assert!(introspect_schema(destination)
+        .await
+        .try_get_schema("public")
+        .and_then(|s| s.try_get_table("items"))
+        .is_none());
+
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            tablespace_mapping: HashMap::from([(
+                "pg_default".to_string(),
+                "pg_default".to_string(),
+            )]),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(source_schema, destination_schema);
+
+    let items = destination_schema
+        .try_get_schema("public")
+        .unwrap()
+        .try_get_table("items")
+        .unwrap();
+    assert_eq!(items.tablespace, None);
+    assert!(items.indices.iter().all(|i| i.tablespace.is_none()));
+}
+
+test_round_trip!(
+    domains,
+    r#"
+create domain public.year as integer
+    constraint year_check check (((value >= 1901) and (value <= 2155)));
+
+create domain public.twenties as year
+    constraint twenties_check check (value >= 1920 and value <= 1929);
+
+comment on domain public.year is 'year between 1901 and 2155';
+
+create domain unix_year as integer default 1970;
+
+create domain non_null_year as year not null;
+
+create domain smol_text as varchar(10);
+
+create table movie
+(
+    name text not null,
+    year year not null
+);
+"#
+);
+
+test_round_trip!(
+    functions_in_index_expressions_and_check_constraints,
+    r#"
+create schema util;
+
+create function util.normalize_phone(phone text) returns text
+    language sql immutable
+    as $$ select regexp_replace(phone, '[^0-9]', '', 'g') $$;
+
+create function util.is_active(status text) returns boolean
+    language sql immutable
+    as $$ select status = 'active' $$;
+
+create table contacts(
+    id serial primary key,
+    phone text not null check (length(util.normalize_phone(phone)) > 0),
+    status text not null
+);
+
+create index contacts_phone_idx on contacts (lower(util.normalize_phone(phone)));
+create index contacts_active_idx on contacts (id) where util.is_active(status);
+
+insert into contacts(phone, status) values ('+45 12 34 56 78', 'active'), ('555-1234', 'inactive');
+"#
+);
+
+test_round_trip!(
+    limited_length_columns,
+    r#"
+create table my_table(
+    name varchar(200) not null,
+    var_char_array varchar(666)[] not null
+);
+"#
+);
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_foreign_keys_on_compressed_tables(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    test_round_trip(
+        r#"
+create table user_files(
+    id serial primary key,
+    file_name text not null
+);
+
+create table user_file_downloads(
+    time timestamptz not null,
+    user_file_id int not null references user_files(id)
+);
+
+select create_hypertable('user_file_downloads', by_range('time', '7 day'::interval));
+
+alter table user_file_downloads set(
+    timescaledb.compress,
+        timescaledb.compress_segmentby = 'user_file_id'
+    );
+
+select add_compression_policy('user_file_downloads', interval '7 days');
+
+       "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_integer_now_func_with_retention(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    test_round_trip(
+        r#"
+create table events(
+    time bigint not null,
+    value int not null
+);
+
+select create_hypertable('events', by_range('time', 1000));
+
+create function events_now() returns bigint language sql stable as $$ select extract(epoch from now())::bigint $$;
+
+select set_integer_now_func('events', 'events_now');
+
+select add_retention_policy('events', 5000::bigint);
+
+insert into events(time, value) values (1, 1), (2000, 2);
+       "#,
+        source,
+        destination,
+    )
+    .await;
+}
+
+#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
+#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
+async fn timescale_differential_copy_into_existing_plain_table(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    let table_sql = r#"
+create table events(
+    time timestamptz not null,
+    value int not null
+);
+"#;
+
+    source
+        .execute_not_query(&format!(
+            r#"{table_sql}
+select create_hypertable('events', by_range('time', '7 day'::interval));
+select set_adaptive_chunking('events', '1048576');
+insert into events(time, value) values ('2024-01-01', 1), ('2024-02-01', 2);
+"#
+        ))
+        .await;
+
+    destination
+        .execute_not_query(&format!(
+            r#"{table_sql}
+insert into events(time, value) values ('2024-01-01', 1), ('2024-02-01', 2);
+"#
+        ))
+        .await;
+
+    let source_schema = introspect_schema(source).await;
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
+
+    let destination_schema = introspect_schema(destination).await;
+
+    assert_eq!(source_schema, destination_schema);
+
+    let items = destination
+        .get_results::<(i32,)>("select value from events order by value;")
+        .await;
+
+    assert_eq!(items, vec![(1,), (2,)]);
+}
+
+async fn export_to_string(source: &TestHelper) -> String {
+    let mut result_file = Vec::<u8>::new();
+
+    {
+        let quoter = IdentifierQuoter::empty();
+
+        let mut sql_file = SqlFile::new(
+            &mut result_file,
+            Arc::new(quoter),
+            SqlFileOptions {
+                chunk_separator: "test_chunk_separator".to_string(),
+                max_commands_per_chunk: 1,
+                data_mode: SqlDataMode::InsertStatements,
+                ..default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let source = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+
+        copy_data(&source, &mut sql_file, CopyDataOptions::default())
+            .await
+            .unwrap();
+    }
+
+    String::from_utf8(result_file).unwrap()
+}
+const SEPARATOR_LINE: &str = "-- chunk-separator-test_chunk_separator --\n";
+
+pub async fn test_differential_copy_generic(source: &TestHelper, setup_query: &str) {
+    source.execute_not_query(setup_query).await;
+
+    let source_schema = introspect_schema(source).await;
+
+    let sql = export_to_string(source).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+
+    let commands = sql
+        .as_bytes()
+        .read_lines_until_separator_line_to_vec(SEPARATOR_LINE)
+        .await
+        .unwrap();
+
+    for i in 0..commands.len() {
+        let to_execute = commands.iter().take(i);
+
+        let destination = source.create_another_database(&format!("test_{i}")).await;
+
+        for command in to_execute {
+            destination.execute_not_query(command).await;
+        }
+
+        let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+            .await
+            .unwrap();
+
+        copy_data(
+            &source_storage,
+            &mut destination_worker,
+            CopyDataOptions {
+                data_format: None,
+                max_parallel: None,
+                differential: true,
+                ..default()
+            },
+        )
+        .await
+        .expect("Failed to copy data");
+
+        let destination_schema = introspect_schema(&destination).await;
+
+        assert_eq!(source_schema, destination_schema);
+
+        let destination_raw_connection = destination.get_conn().underlying_connection();
+        let source_raw_connection = source.get_conn().underlying_connection();
+
+        for schema in &source_schema.schemas {
+            for table in &schema.tables {
+                let mut query = "select ".to_string();
+
+                query.push_join(
+                    ", ",
+                    table
+                        .columns
+                        .iter()
+                        .filter(|c| c.generated.is_none())
+                        .map(|c| {
+                            format!(
+                                "{}::text",
+                                c.name.quote(
+                                    &source_storage.identifier_quoter,
+                                    AttemptedKeywordUsage::ColumnName
+                                )
+                            )
+                        })
+                        .collect_vec(),
+                );
+
+                query.push_str(" from ");
+                query.push_str(&schema.name.quote(
+                    &source_storage.identifier_quoter,
+                    AttemptedKeywordUsage::Other,
+                ));
+                query.push('.');
+                query.push_str(&table.name.quote(
+                    &source_storage.identifier_quoter,
+                    AttemptedKeywordUsage::TypeOrFunctionName,
+                ));
+
+                let from_source = source_raw_connection.query(&query, &[]).await.unwrap();
+                let from_destination = destination_raw_connection.query(&query, &[]).await.unwrap();
+
+                assert_eq!(
+                    from_source.len(),
+                    from_destination.len(),
+                    "Table: {}.{}. Expected {}, got {}",
+                    schema.name,
+                    table.name,
+                    from_source.len(),
+                    from_destination.len()
+                );
+
+                for (row_index, (source_row, destination_row)) in
+                    from_source.iter().zip(from_destination).enumerate()
+                {
+                    for (idx, col) in source_row.columns().iter().enumerate() {
+                        let source_value: String = source_row.get(idx);
+                        let destination_value: String = destination_row.get(idx);
+                        assert_eq!(
+                            source_value,
+                            destination_value,
+                            "Table: {}.{}. Row: {}. Column: {}. Expected {:?}, got {:?}",
+                            schema.name,
+                            table.name,
+                            row_index,
+                            col.name(),
+                            source_value,
+                            destination_value
+                        );
+                    }
+                }
+            }
+        }
+
+        destination.stop().await;
+    }
+}
+
+#[pg_test(arg(postgres = 15))]
+async fn test_differential_copy(source: &TestHelper) {
+    test_differential_copy_generic(source, r#"
+
+        CREATE TABLE products (
+            product_no integer PRIMARY KEY,
+            name text,
+            price numeric
+        );
+
+        insert into products(product_no, name, price) values (1, 'foo', 1.0), (2, 'bar', 2.0), (3, 'baz', 3.0);
+
+        CREATE TABLE orders (
+            order_id integer PRIMARY KEY,
+            shipping_address text
+        );
+
+        insert into orders(order_id, shipping_address) values (1, 'foo'), (2, 'bar'), (3, 'baz');
+
+        CREATE TABLE order_items (
+            product_no integer REFERENCES products ON DELETE RESTRICT ON UPDATE CASCADE,
+            order_id integer REFERENCES orders ON DELETE CASCADE ON UPDATE RESTRICT,
+            quantity integer,
+            PRIMARY KEY (product_no, order_id)
+        );
+
+        insert into order_items(product_no, order_id, quantity) values (1, 1, 1), (2, 2, 2), (3, 3, 3);
+    "#).await;
+}
 
-test_round_trip!(
-    range_partitions,
-    r#"
-    CREATE TABLE sales (
-                       sale_id INT,
-                       sale_date DATE,
-                       product_id INT,
-                       quantity INT,
-                       amount NUMERIC
-) partition by range (sale_date);
 
-CREATE TABLE sales_january PARTITION OF sales
-    FOR VALUES FROM ('2023-01-01') TO ('2023-02-01');
+test_round_trip!(identity_column_by_default, r#"
+    create table my_table(
+        id int generated by default as identity primary key,
+        name text not null
+    );
 
-CREATE TABLE sales_february PARTITION OF sales
-    FOR VALUES FROM ('2023-02-01') TO ('2023-03-01');
+    insert into my_table(name) values ('foo'), ('bar');
+"#);
 
-CREATE TABLE sales_march PARTITION OF sales
-    FOR VALUES FROM ('2023-03-01') TO ('2023-04-01');
-    "#
-);
+test_round_trip!(identity_column_always, r#"
+    create table my_table(
+        id int generated always as identity primary key,
+        name text not null
+    );
 
-test_round_trip!(
-    list_partitions,
-    r#"
-CREATE TABLE products (
-    product_id int,
-    category TEXT,
-    product_name TEXT,
-    price NUMERIC
-) partition by list(category);
+    insert into my_table(name) values ('foo'), ('bar');
+"#);
 
-CREATE TABLE electronics PARTITION OF products
-    FOR VALUES IN ('Electronics');
+test_round_trip!(identity_column_by_default_custom_sequence, r#"
+    create table my_table(
+        id int generated by default as identity (START WITH 10 INCREMENT BY 10) primary key,
+        name text not null
+    );
 
-CREATE TABLE clothing PARTITION OF products
-    FOR VALUES IN ('Clothing');
+    insert into my_table(name) values ('foo'), ('bar');
+"#);
 
-CREATE TABLE furniture PARTITION OF products
-    FOR VALUES IN ('Furniture');
-    "#
-);
+test_round_trip!(identity_column_by_default_custom_sequence_start_only, r#"
+    create table my_table(
+        id int generated by default as identity (START WITH 10) primary key,
+        name text not null
+    );
 
-test_round_trip!(
-    hash_partitions,
-    r#"
-CREATE TABLE orders (
-    order_id int,
-    order_date DATE,
-    customer_id INT,
-    total_amount NUMERIC
-) partition by hash(customer_id);
+    insert into my_table(name) values ('foo'), ('bar');
+"#);
 
-CREATE TABLE orders_1 PARTITION OF orders
-    FOR VALUES WITH (MODULUS 3, REMAINDER 0);
+test_round_trip!(identity_column_by_default_custom_sequence_increment_only, r#"
+    create table my_table(
+        id int generated by default as identity (INCREMENT BY 10) primary key,
+        name text not null
+    );
 
-CREATE TABLE orders_2 PARTITION OF orders
-    FOR VALUES WITH (MODULUS 3, REMAINDER 1);
+    insert into my_table(name) values ('foo'), ('bar');
+"#);
 
-CREATE TABLE orders_3 PARTITION OF orders
-    FOR VALUES WITH (MODULUS 3, REMAINDER 2);
-    "#
-);
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn identity_column_sequence_continues_correctly(source: &TestHelper, destination: &TestHelper) {
+    test_round_trip(r#"
+    create table my_table(
+        id int generated by default as identity primary key,
+        name text not null
+    );
 
-test_round_trip!(
-    inheritance,
-    r#"
-create table pets (
-    id serial primary key,
-    name text not null check(length(name) > 1)
-);
+    insert into my_table(name) values ('foo'), ('bar');
+"#, source, destination).await;
 
-create table dogs(
-    breed text not null check(length(breed) > 1)
-) inherits (pets);
+    destination.execute_not_query("insert into my_table(name) values ('baz'), ('qux')").await;
 
-create table cats(
-    color text not null
-) inherits (pets);
-    "#
-);
+    let items = destination.get_results::<(i32, String)>("select id, name from my_table order by id").await;
 
-test_round_trip!(
-    multiple_inheritance,
-    r#"
-create table animal(
-    breed text not null
-);
+    assert_eq!(items, vec![(1, "foo".to_string()), (2, "bar".to_string()), (3, "baz".to_string()), (4, "qux".to_string())]);
 
-create table human(
-    name text not null
-);
+}
 
-create table animorph() inherits (animal, human);
-"#
-);
+test_round_trip!(identity_columns_on_renamed_tables, r#"
+    create table my_table(
+        id int generated by default as identity primary key,
+        name text not null
+    );
 
-test_round_trip!(
-    functions_returning_custom_table,
-    r#"
-create function my_function() returns table(id int, name text) as $$
-begin
-    return query select 1, 'foo';
-end;
-$$ language plpgsql;
-"#
-);
+    insert into my_table(name) values ('foo'), ('bar');
 
-test_round_trip!(
-    functions_returning_table_type,
-    r#"
+    alter table my_table rename to new_my_table;
+"#);
 
-create table my_table(id int, name text);
+test_round_trip!(identity_columns_with_renamed_sequence, r#"
+    create table my_table(
+        id int generated always as identity (start with 10 increment by 5 cache 3) primary key,
+        name text not null
+    );
+
+    alter sequence my_table_id_seq rename to renamed_identity_seq;
+
+    insert into my_table(name) values ('foo'), ('bar');
+"#);
 
-create function my_function() returns setof my_table as $$
-begin
-    return query select 1, 'foo';
-end;
-$$ language plpgsql;
-"#
-);
 
-#[pg_test(arg(postgres = 13), arg(postgres = 13))]
-#[pg_test(arg(postgres = 14), arg(postgres = 14))]
 #[pg_test(arg(postgres = 15), arg(postgres = 15))]
-#[pg_test(arg(postgres = 16), arg(postgres = 16))]
-async fn storage_parameters(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(
-        r#"
-    create table my_table(name text not null) with (fillfactor=50);
+async fn differential_copy_changes_identity_options(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+            create table my_table(
+                id int generated always as identity (start with 10 increment by 5 cycle) primary key
+            );
+            alter sequence my_table_id_seq rename to renamed_identity_seq;
+            "#,
+        )
+        .await;
+    destination
+        .execute_not_query(
+            r#"
+            create table my_table(
+                id int generated always as identity primary key
+            );
+            alter sequence my_table_id_seq rename to renamed_identity_seq;
+            "#,
+        )
+        .await;
 
-    create index my_index on my_table(name) with (fillfactor = 20, deduplicate_items = off);
-    "#,
-        source,
-        destination,
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
     )
-    .await;
-}
+    .await
+    .unwrap();
 
-#[pg_test(arg(postgres = 12), arg(postgres = 12))]
-async fn storage_parameters_pg_12(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(
-        r#"
-    create table my_table(name text not null) with (fillfactor=50);
+    let destination_schema = introspect_schema(destination).await;
+    let identity_options = destination_schema
+        .try_get_schema("public")
+        .unwrap()
+        .try_get_table("my_table")
+        .unwrap()
+        .columns
+        .iter()
+        .find(|c| c.name == "id")
+        .unwrap()
+        .identity_options
+        .clone()
+        .unwrap();
+    assert_eq!(identity_options.sequence_name, "renamed_identity_seq");
+    assert_eq!(identity_options.start, 10);
+    assert_eq!(identity_options.increment, 5);
+    assert!(identity_options.cycle);
 
-    create index my_index on my_table(name) with (fillfactor = 20);
-    "#,
-        source,
-        destination,
-    )
-    .await;
+    assert_differential_noop(source, destination).await;
 }
 
+test_round_trip!(identity_columns_on_renamed_tables_id_column_is_not_first_column, r#"
+    create table my_table(
+        name text not null,
+        id int generated by default as identity primary key
+    );
+
+    insert into my_table(name) values ('foo'), ('bar');
+
+    alter table my_table rename to new_my_table;
+"#);
+
+
 #[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
 #[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
-async fn timescale_hypertable_time_single_dimension(source: &TestHelper, destination: &TestHelper) {
+async fn timescale_constraints_on_indices(source: &TestHelper, destination: &TestHelper) {
     test_round_trip(r#"
+    create table my_table(time timestamptz not null, event_id uuid not null, member_id int not null, web_site_url text not null);
 
-CREATE TABLE stocks_real_time (
-  time TIMESTAMPTZ NOT NULL,
-  symbol TEXT NOT NULL,
-  price DOUBLE PRECISION NULL,
-  day_volume INT NULL
-);
+    alter table my_table add constraint my_uniq unique (time, event_id);
+
+    select create_hypertable('my_table', by_range('time', '7 day'::interval));
+    "#, source, destination).await;
+}
+
+const PEOPLE_WITH_NICKNAME_VIEWS: &str = r#"
+    create table people(
+        id int primary key,
+        name text not null,
+        nickname text
+    );
 
-SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
+    insert into people(id, name, nickname) values (1, 'foo', 'f'), (2, 'bar', null);
 
-CREATE INDEX ix_symbol_time ON stocks_real_time (symbol, time DESC);
+    create view people_view as select id, name, nickname from people;
+    create view people_names as select name from people_view;
+"#;
 
-insert into stocks_real_time(time, symbol, price, day_volume) values ('2023-01-01', 'AAPL', 100.0, 1000);
+const DROP_NICKNAME: &str = r#"
+    drop view people_names;
+    drop view people_view;
+    alter table people drop column nickname;
+    create view people_view as select id, name from people;
+    create view people_names as select name from people_view;
+"#;
 
-        "#, source, destination).await;
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_drops_column_used_by_views(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(PEOPLE_WITH_NICKNAME_VIEWS).await;
+    source.execute_not_query(DROP_NICKNAME).await;
+    destination.execute_not_query(PEOPLE_WITH_NICKNAME_VIEWS).await;
 
-    let items = destination
-        .get_results::<(String, f64, i32)>(
-            "select symbol, price, day_volume from stocks_real_time;",
-        )
-        .await;
+    let source_schema = introspect_schema(source).await;
+    let people_view = &source_schema.schemas[0].views[1];
+    assert_eq!(people_view.name, "people_view");
+    assert_eq!(
+        people_view.column_dependencies,
+        vec![
+            (source_schema.schemas[0].tables[0].object_id, "id".to_string()),
+            (source_schema.schemas[0].tables[0].object_id, "name".to_string()),
+        ]
+    );
 
-    assert_eq!(items, vec![("AAPL".to_string(), 100.0, 1000)]);
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            differential: true,
+            allow_destructive: true,
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
+
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(source_schema, destination_schema);
+
+    let names = destination
+        .get_single_results::<String>("select name from people_names order by name;")
+        .await;
+    assert_eq!(names, vec!["bar".to_string(), "foo".to_string()]);
 }
 
-#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
-#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
-async fn timescale_hypertable_time_multiple_dimensions(
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_refuses_to_drop_views_without_allow_destructive(
     source: &TestHelper,
     destination: &TestHelper,
 ) {
-    test_round_trip(
-        r#"
-
-CREATE TABLE stocks_real_time (
-  time TIMESTAMPTZ NOT NULL,
-  symbol TEXT NOT NULL,
-  price DOUBLE PRECISION NULL,
-  day_volume INT NULL,
-  primary key (time, symbol, day_volume)
-);
-
-SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
-SELECT add_dimension('stocks_real_time', by_hash('symbol', 4));
-SELECT add_dimension('stocks_real_time', by_range('day_volume', 100));
+    source.execute_not_query(PEOPLE_WITH_NICKNAME_VIEWS).await;
+    source.execute_not_query(DROP_NICKNAME).await;
+    destination.execute_not_query(PEOPLE_WITH_NICKNAME_VIEWS).await;
 
-CREATE INDEX ix_symbol_time ON stocks_real_time (symbol, time DESC);
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-        "#,
-        source,
-        destination,
+    let result = copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
     )
     .await;
-}
 
-#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
-#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
-async fn timescale_hypertable_compression(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(
-        r#"
+    match result {
+        Err(crate::ElefantToolsError::ColumnDropBlockedByViews {
+            column_name, views, ..
+        }) => {
+            assert_eq!(column_name, "nickname");
+            assert_eq!(
+                views,
+                vec!["public.people_names".to_string(), "public.people_view".to_string()]
+            );
+        }
+        r => panic!("Expected the column drop to be blocked, got {:?}", r),
+    }
 
-CREATE TABLE stocks_real_time (
-  time TIMESTAMPTZ NOT NULL,
-  symbol TEXT NOT NULL,
-  price DOUBLE PRECISION NULL,
-  day_volume INT NOT NULL
-);
+    let nicknames = destination
+        .get_single_results::<Option<String>>("select nickname from people_view order by id;")
+        .await;
+    assert_eq!(nicknames, vec![Some("f".to_string()), None]);
+}
 
-SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
+//language=postgresql
+const OBJECTS_BEFORE_REMOVAL: &str = r#"
+create table people(id int primary key, name text not null, nickname text);
+create index people_name_idx on people(name);
+create table pets(id int primary key, owner_id int references people(id));
+create table toys(id int primary key, pet_id int references pets(id));
+create view pet_owners as select p.name from pets join people p on p.id = pets.owner_id;
+create function pet_count() returns bigint language sql as $$ select count(*) from pets $$;
+"#;
 
-alter table stocks_real_time set(
-    timescaledb.compress,
-        timescaledb.compress_segmentby = 'symbol',
-        timescaledb.compress_orderby = 'time,day_volume',
-        timescaledb.compress_chunk_time_interval='14 days'
-        );
+//language=postgresql
+const OBJECTS_AFTER_REMOVAL: &str = r#"
+create table people(id int primary key, name text not null);
+create table toys(id int primary key, pet_id int);
+"#;
 
-select add_compression_policy('stocks_real_time', interval '7 days');
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 12), arg(postgres = 16))]
+async fn differential_copy_drops_removed_objects_with_allow_drops(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(OBJECTS_AFTER_REMOVAL).await;
+    destination.execute_not_query(OBJECTS_BEFORE_REMOVAL).await;
 
-        "#,
-        source,
-        destination,
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            differential: true,
+            allow_drops: true,
+            ..default()
+        },
     )
-    .await;
-}
+    .await
+    .expect("Failed to copy data");
 
-#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
-#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
-async fn timescale_continuous_aggregate(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(r#"
-CREATE TABLE stocks_real_time (
-  time TIMESTAMPTZ NOT NULL,
-  symbol TEXT NOT NULL,
-  price DOUBLE PRECISION NULL,
-  day_volume INT NOT NULL
-);
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(source_schema, destination_schema);
+}
 
-SELECT create_hypertable('stocks_real_time', by_range('time', '7 days'::interval));
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_keeps_removed_objects_without_allow_drops(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(OBJECTS_AFTER_REMOVAL).await;
+    destination.execute_not_query(OBJECTS_BEFORE_REMOVAL).await;
 
-insert into stocks_real_time(time, symbol, price, day_volume) values ('2023-01-01', 'AAPL', 100.0, 1000);
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-CREATE MATERIALIZED VIEW stock_candlestick_daily
-WITH (timescaledb.continuous) AS
-SELECT
-  time_bucket('1 day', "time") AS day,
-  symbol,
-  max(price) AS high,
-  first(price, time) AS open,
-  last(price, time) AS close,
-  min(price) AS low
-FROM stocks_real_time srt
-GROUP BY day, symbol
-WITH NO DATA;
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            differential: true,
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
 
-SELECT add_continuous_aggregate_policy('stock_candlestick_daily',
-                                       start_offset => INTERVAL '6 month',
-                                       end_offset => INTERVAL '1 day',
-                                       schedule_interval => INTERVAL '1 hour');
+    let destination_schema = introspect_schema(destination).await;
+    let public = destination_schema.try_get_schema("public").unwrap();
+    assert_eq!(
+        public.tables.iter().map(|t| t.name.as_str()).collect_vec(),
+        vec!["people", "pets", "toys"]
+    );
+    let people = public.try_get_table("people").unwrap();
+    assert!(people.column("nickname").is_some());
+    assert!(people.indices.iter().any(|i| i.name == "people_name_idx"));
+    assert_eq!(public.views.len(), 1);
+    assert_eq!(public.functions.len(), 1);
+}
 
-alter materialized view stock_candlestick_daily set (timescaledb.compress = true);
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_only_drops_tables_selected_by_the_table_filters(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(OBJECTS_AFTER_REMOVAL).await;
+    destination.execute_not_query(OBJECTS_BEFORE_REMOVAL).await;
 
-SELECT add_compression_policy('stock_candlestick_daily', compress_after=>'360 days'::interval);
-SELECT add_retention_policy('stock_candlestick_daily', INTERVAL '2 years');
-       "#, source, destination).await;
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-    let items = destination
-        .get_results::<(String, String, f64, f64, f64, f64)>(
-            "select day::text, symbol, high, open, close, low from stock_candlestick_daily;",
-        )
-        .await;
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            differential: true,
+            allow_drops: true,
+            object_filter: ObjectFilter {
+                include_tables: vec!["people".to_string()],
+                ..default()
+            },
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
 
+    let destination_schema = introspect_schema(destination).await;
+    let public = destination_schema.try_get_schema("public").unwrap();
     assert_eq!(
-        items,
-        vec![(
-            "2023-01-01 00:00:00+00".to_string(),
-            "AAPL".to_string(),
-            100.0,
-            100.0,
-            100.0,
-            100.0
-        )]
+        public.tables.iter().map(|t| t.name.as_str()).collect_vec(),
+        vec!["people", "pets", "toys"]
     );
+    assert!(public.try_get_table("people").unwrap().column("nickname").is_none());
+    assert_eq!(public.views.len(), 1);
 }
 
-#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
-#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
-async fn timescale_retention_policy(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(
-        r#"
-CREATE TABLE conditions (
-  time TIMESTAMPTZ NOT NULL
-);
+const PEOPLE_WITH_NICKNAME: &str = r#"
+    create table people(
+        id int primary key,
+        name text not null,
+        nickname varchar(20)
+    );
+
+    insert into people(id, name, nickname) values (1, 'foo', 'f'), (2, 'bar', null);
+"#;
+
+async fn differential_copy_with_renames(
+    source: &TestHelper,
+    destination: &TestHelper,
+    column_renames: Vec<ColumnRename>,
+) {
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            differential: true,
+            allow_destructive: true,
+            column_renames,
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_renames_hinted_columns(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(PEOPLE_WITH_NICKNAME).await;
+    source
+        .execute_not_query(
+            r#"
+        alter table people rename column nickname to display_name;
+        alter table people alter column display_name type text;
+        "#,
+        )
+        .await;
+    destination.execute_not_query(PEOPLE_WITH_NICKNAME).await;
 
-SELECT create_hypertable('conditions', by_range('time', '1 hour'::interval));
-SELECT add_retention_policy('conditions', INTERVAL '24 hours');
-       "#,
+    differential_copy_with_renames(
         source,
         destination,
+        vec![ColumnRename {
+            table: "public.people".to_string(),
+            old_name: "nickname".to_string(),
+            new_name: "display_name".to_string(),
+        }],
     )
     .await;
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(
+        source_schema.schemas[0].tables[0].columns,
+        destination_schema.schemas[0].tables[0].columns
+    );
+
+    let display_names = destination
+        .get_single_results::<Option<String>>("select display_name from people order by id;")
+        .await;
+    assert_eq!(display_names, vec![Some("f".to_string()), None]);
 }
 
-#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
-#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
-async fn timescale_user_defined_jobs(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(
-        r#"
-CREATE PROCEDURE user_defined_action(job_id INT, config JSONB)
-    LANGUAGE PLPGSQL AS
-    $$
-    BEGIN
-        RAISE NOTICE 'Executing job % with config %', job_id, config;
-    END
-    $$;
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn differential_copy_ignores_rename_hints_for_missing_columns(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(PEOPLE_WITH_NICKNAME).await;
+    source
+        .execute_not_query("alter table people drop column nickname;")
+        .await;
+    destination.execute_not_query(PEOPLE_WITH_NICKNAME).await;
 
-SELECT add_job('user_defined_action', '1h', config => '{"hypertable":"metrics"}');
-       "#,
+    // The new column does not exist in the source, so the old column is dropped as usual.
+    differential_copy_with_renames(
         source,
         destination,
+        vec![ColumnRename {
+            table: "public.people".to_string(),
+            old_name: "nickname".to_string(),
+            new_name: "display_name".to_string(),
+        }],
     )
     .await;
-}
 
-// This is quite slow, so we only test against 1 postgres instance
-// We are not really testing postgres, but the internal parallel handling
-// in this program.
-#[pg_test(arg(postgres = 15), arg(postgres = 15))]
-async fn ensure_survives_many_tables(source: &TestHelper, destination: &TestHelper) {
-    let mut sql = String::new();
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(source_schema, destination_schema);
+}
 
-    for i in 0..50 {
-        sql.push_str(&format!(
-            "create table my_table_{}(id serial primary key, name text);\n",
-            i
-        ));
-        sql.push_str(&format!(
+#[cfg(feature = "timescale")]
+#[pg_test(arg(postgres = 16), arg(timescale_db = 16))]
+async fn converts_plain_tables_to_hypertables(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(
             r#"
-insert into my_table_{} (
-    name
-)
-select
-    md5(random()::text)
-from generate_series(1, 1000) s(i);"#,
-            i
-        ))
-    }
+        create table metrics(
+            time timestamptz not null,
+            device_id int not null,
+            value double precision not null
+        );
 
-    test_round_trip(&sql, source, destination).await;
+        insert into metrics(time, device_id, value)
+        select '2024-01-01'::timestamptz + (i || ' days')::interval, i % 3, i
+        from generate_series(1, 30) i;
+        "#,
+        )
+        .await;
 
-    for i in 0..50 {
-        let items = destination
-            .get_results::<(i32, String)>(&format!("select id, name from my_table_{};", i))
-            .await;
-        assert_eq!(items.len(), 1000);
-    }
-}
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-#[pg_test(arg(postgres = 15))]
-async fn copies_between_schemas_in_same_db(helper: &TestHelper) {
-    helper
-        .execute_not_query("create schema source_schema; create schema destination_schema;")
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            hypertable_conversions: vec![crate::HypertableConversion {
+                schema: "public".to_string(),
+                table: "metrics".to_string(),
+                time_column: "time".to_string(),
+                chunk_interval: "7 days".to_string(),
+                partitioning_column: None,
+            }],
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
+
+    let chunks = destination
+        .get_single_result::<i64>(
+            "select count(*) from timescaledb_information.chunks where hypertable_name = 'metrics';",
+        )
         .await;
+    assert!(chunks > 1, "Expected the data to be split into chunks, got {}", chunks);
 
-    let source = helper.get_schema_connection("source_schema").await;
-    let destination = helper.get_schema_connection("destination_schema").await;
+    let rows = destination
+        .get_single_result::<i64>("select count(*) from metrics;")
+        .await;
+    assert_eq!(rows, 30);
+}
 
-    source
-        .execute_non_query(
-            r#"
-        create table my_table(id serial primary key, name text not null);
-        insert into my_table (
-    name
-)
-select
-    md5(random()::text)
-from generate_series(1, 1000) s(i);
-        "#,
-        )
+test_round_trip!(index_operator_classes_and_collations, r#"
+    create table my_table(
+        name varchar(100) not null,
+        data jsonb not null
+    );
+
+    create index my_table_name_pattern_idx on my_table (name varchar_pattern_ops);
+    create index my_table_name_c_idx on my_table (name collate "C" text_pattern_ops desc);
+    create index my_table_data_idx on my_table using gin (data jsonb_path_ops);
+
+    insert into my_table(name, data) values ('foo', '{"a": 1}'), ('bar', '{"b": [1, 2]}');
+"#);
+
+const INDEXED_ORDERS: &str = r#"
+    create table customers(
+        id int primary key,
+        email text not null constraint customers_email_key unique,
+        name text not null
+    );
+
+    create index customers_name_idx on customers (name);
+    comment on index customers_name_idx is 'For searching by name';
+
+    create table orders(
+        id int primary key,
+        customer_id int not null references customers(id),
+        customer_email text not null references customers(email),
+        reference text not null,
+        created_at timestamp not null
+    );
+
+    create unique index orders_reference_idx on orders (reference);
+    create index orders_created_at_idx on orders (created_at desc);
+
+    insert into customers(id, email, name) values (1, 'foo@example.com', 'foo'), (2, 'bar@example.com', 'bar');
+    insert into orders(id, customer_id, customer_email, reference, created_at)
+    values (1, 1, 'foo@example.com', 'A-1', '2024-01-01'), (2, 2, 'bar@example.com', 'A-2', '2024-01-02');
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15), arg(postgres = 15))]
+async fn skip_indexes_and_indexes_only_copy_matches_one_shot_copy(
+    source: &TestHelper,
+    one_shot: &TestHelper,
+    two_phase: &TestHelper,
+) {
+    source.execute_not_query(INDEXED_ORDERS).await;
+
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
         .await
         .unwrap();
 
-    let source_storage = PostgresInstanceStorage::new(&source).await.unwrap();
-    let mut destination_storage = PostgresInstanceStorage::new(&destination).await.unwrap();
+    let mut one_shot_worker = PostgresInstanceStorage::new(one_shot.get_conn())
+        .await
+        .unwrap();
+    copy_data(&source_storage, &mut one_shot_worker, default())
+        .await
+        .expect("Failed to copy data");
 
+    let mut two_phase_worker = PostgresInstanceStorage::new(two_phase.get_conn())
+        .await
+        .unwrap();
     copy_data(
         &source_storage,
-        &mut destination_storage,
+        &mut two_phase_worker,
         CopyDataOptions {
-            target_schema: Some("source_schema".to_string()),
-            rename_schema_to: Some("destination_schema".to_string()),
+            skip_indexes: true,
             ..default()
         },
     )
     .await
-    .unwrap();
-
-    let destination_schema = SchemaReader::new(&destination)
-        .introspect_database()
-        .await
-        .unwrap()
-        .filtered_to_schema("destination_schema");
+    .expect("Failed to copy data without indexes");
 
+    let without_indexes = introspect_schema(two_phase).await;
+    let customers = without_indexes.schemas[0].try_get_table("customers").unwrap();
     assert_eq!(
-        destination_schema,
-        PostgresDatabase {
-            schemas: vec![PostgresSchema {
-                name: "destination_schema".to_string(),
-                tables: vec![PostgresTable {
-                    name: "my_table".to_string(),
-                    columns: vec![
-                        PostgresColumn {
-                            name: "id".to_string(),
-                            data_type: "int4".to_string(),
-                            is_nullable: false,
-                            ordinal_position: 1,
-                            default_value: Some("nextval('my_table_id_seq'::regclass)".to_string()),
-                            ..default()
-                        },
-                        PostgresColumn {
-                            name: "name".to_string(),
-                            data_type: "text".to_string(),
-                            is_nullable: false,
-                            ordinal_position: 2,
-                            ..default()
-                        },
-                    ],
-                    indices: vec![PostgresIndex {
-                        name: "my_table_pkey".to_string(),
-                        key_columns: vec![PostgresIndexKeyColumn {
-                            ordinal_position: 1,
-                            name: "id".to_string(),
-                            direction: Some(PostgresIndexColumnDirection::Ascending),
-                            nulls_order: Some(PostgresIndexNullsOrder::Last)
-                        }],
-                        index_constraint_type: PostgresIndexType::PrimaryKey,
-                        index_type: "btree".to_string(),
-                        ..default()
-                    }],
-                    ..default()
-                }],
-                sequences: vec![PostgresSequence {
-                    name: "my_table_id_seq".to_string(),
-                    data_type: "int4".to_string(),
-                    max_value: 2147483647,
-                    last_value: Some(1000),
-                    ..default()
-                }],
-                ..default()
-            }],
-            ..default()
-        }
+        customers.indices.iter().map(|i| i.name.as_str()).collect_vec(),
+        vec!["customers_pkey"]
+    );
+    assert!(customers.constraints.is_empty());
+    let orders = without_indexes.schemas[0].try_get_table("orders").unwrap();
+    assert_eq!(
+        orders.indices.iter().map(|i| i.name.as_str()).collect_vec(),
+        vec!["orders_pkey"]
+    );
+    // The foreign key to the skipped unique constraint has to wait for the second copy.
+    assert_eq!(
+        orders.constraints.iter().map(|c| c.name()).collect_vec(),
+        vec!["orders_customer_id_fkey"]
     );
 
-    let items = source
-        .get_results::<(i32, String)>("select id, name from my_table;")
-        .await
-        .unwrap();
-    assert_eq!(items.len(), 1000);
+    let emails = two_phase
+        .get_single_results::<String>("select customer_email from orders order by id;")
+        .await;
+    assert_eq!(emails, vec!["foo@example.com", "bar@example.com"]);
 
-    let items = destination
-        .get_results::<(i32, String)>("select id, name from my_table;")
+    // The second copy is a separate run, like it would be from the command line.
+    let mut two_phase_worker = PostgresInstanceStorage::new(two_phase.get_conn())
         .await
         .unwrap();
-    assert_eq!(items.len(), 1000);
-}
+    copy_data(
+        &source_storage,
+        &mut two_phase_worker,
+        CopyDataOptions {
+            indexes_only: true,
+            create_indexes_concurrently: true,
+            max_parallel: Some(NonZeroUsize::new(4).unwrap()),
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to create the missing indexes");
 
-test_round_trip!(
-    two_way_references,
-    r#"
-create table assets(
-    asset_id serial primary key,
-    asset_digiupload_id int
-);
+    let one_shot_schema = introspect_schema(one_shot).await;
+    let two_phase_schema = introspect_schema(two_phase).await;
+    assert_eq!(one_shot_schema, two_phase_schema);
+    assert_eq!(introspect_schema(source).await, two_phase_schema);
 
-create table asset_digiuploads(
-    asset_digiupload_id serial primary key,
-    asset_id int references assets(asset_id)
-);
+    let order_count = two_phase
+        .get_single_result::<i64>("select count(*) from orders;")
+        .await;
+    assert_eq!(order_count, 2);
+}
 
-alter table assets add constraint fk_asset_digiupload_id foreign key (asset_digiupload_id) references asset_digiuploads(asset_digiupload_id);
-"#
-);
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn skip_primary_keys_defers_foreign_keys(source: &TestHelper, destination: &TestHelper) {
+    source.execute_not_query(INDEXED_ORDERS).await;
+    let source_schema = introspect_schema(source).await;
 
-test_round_trip!(
-    multiple_unique_constraints_on_same_table,
-    r#"
-create table users(
-    id serial primary key,
-    username text not null unique,
-    email text not null unique
-);
-"#
-);
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-test_round_trip!(
-    domains,
-    r#"
-create domain public.year as integer
-    constraint year_check check (((value >= 1901) and (value <= 2155)));
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            skip_indexes: true,
+            skip_primary_keys: true,
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data without indexes");
 
-create domain public.twenties as year
-    constraint twenties_check check (value >= 1920 and value <= 1929);
+    let without_indexes = introspect_schema(destination).await;
+    for table in &without_indexes.schemas[0].tables {
+        assert!(table.indices.is_empty(), "{} has indices", table.name);
+        assert!(table.constraints.is_empty(), "{} has constraints", table.name);
+    }
 
-comment on domain public.year is 'year between 1901 and 2155';
+    let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_worker,
+        CopyDataOptions {
+            indexes_only: true,
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to create the missing indexes");
 
-create domain unix_year as integer default 1970;
+    let destination_schema = introspect_schema(destination).await;
+    assert_eq!(source_schema, destination_schema);
+}
 
-create domain non_null_year as year not null;
+test_round_trip!(
+    unique_constraint_with_included_columns,
+    r#"
+    create table products(
+        id int not null,
+        sku text not null,
+        name text not null,
+        constraint products_sku_key unique (sku) include (name)
+    );
 
-create domain smol_text as varchar(10);
+    create table events(
+        id int not null,
+        created_at date not null,
+        payload text,
+        constraint events_id_created_at_key unique (id, created_at) include (payload)
+    ) partition by range (created_at);
 
-create table movie
-(
-    name text not null,
-    year year not null
-);
-"#
-);
+    create table events_2024 partition of events for values from ('2024-01-01') to ('2025-01-01');
 
-test_round_trip!(
-    limited_length_columns,
-    r#"
-create table my_table(
-    name varchar(200) not null,
-    var_char_array varchar(666)[] not null
-);
+    insert into products(id, sku, name) values (1, 'a', 'foo'), (2, 'b', 'bar');
+    insert into events(id, created_at, payload) values (1, '2024-02-01', 'foo');
 "#
 );
 
-#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
-#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
-async fn timescale_foreign_keys_on_compressed_tables(
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn unique_constraint_with_nulls_not_distinct_and_included_columns(
     source: &TestHelper,
     destination: &TestHelper,
 ) {
     test_round_trip(
         r#"
-create table user_files(
-    id serial primary key,
-    file_name text not null
-);
-
-create table user_file_downloads(
-    time timestamptz not null,
-    user_file_id int not null references user_files(id)
-);
-
-select create_hypertable('user_file_downloads', by_range('time', '7 day'::interval));
-
-alter table user_file_downloads set(
-    timescaledb.compress,
-        timescaledb.compress_segmentby = 'user_file_id'
+    create table products(
+        id int not null,
+        sku text,
+        name text not null,
+        constraint products_sku_key unique nulls not distinct (sku) include (name)
     );
 
-select add_compression_policy('user_file_downloads', interval '7 days');
+    create table events(
+        id int,
+        created_at date not null,
+        payload text,
+        constraint events_id_created_at_key unique nulls not distinct (id, created_at) include (payload)
+    ) partition by range (created_at);
 
-       "#,
+    create table events_2024 partition of events for values from ('2024-01-01') to ('2025-01-01');
+
+    insert into products(id, sku, name) values (1, null, 'foo'), (2, 'b', 'bar');
+    insert into events(id, created_at, payload) values (null, '2024-02-01', 'foo');
+    "#,
         source,
         destination,
     )
     .await;
+
+    let index_definitions = destination
+        .get_single_results::<String>(
+            "select pg_get_indexdef(indexrelid) from pg_index where indrelid in ('products'::regclass, 'events'::regclass) order by 1;",
+        )
+        .await;
+    assert_eq!(
+        index_definitions,
+        vec![
+            "CREATE UNIQUE INDEX events_id_created_at_key ON ONLY public.events USING btree (id, created_at) INCLUDE (payload) NULLS NOT DISTINCT".to_string(),
+            "CREATE UNIQUE INDEX products_sku_key ON public.products USING btree (sku) INCLUDE (name) NULLS NOT DISTINCT".to_string(),
+        ]
+    );
 }
 
-async fn export_to_string(source: &TestHelper) -> String {
-    let mut result_file = Vec::<u8>::new();
+const CROSS_SCHEMA_DEPENDENCIES: &str = r#"
+    create schema a_reports;
+    create schema z_core;
 
-    {
-        let quoter = IdentifierQuoter::empty();
+    create table z_core.customers(
+        id int primary key,
+        name text not null
+    );
 
-        let mut sql_file = SqlFile::new(
-            &mut result_file,
-            Arc::new(quoter),
-            SqlFileOptions {
-                chunk_separator: "test_chunk_separator".to_string(),
-                max_commands_per_chunk: 1,
-                data_mode: SqlDataMode::InsertStatements,
-                ..default()
-            },
-        )
-        .await
-        .unwrap();
+    create table z_core.orders(
+        id int primary key,
+        customer_id int not null references z_core.customers(id),
+        total numeric not null
+    );
 
-        let source = PostgresInstanceStorage::new(source.get_conn())
-            .await
-            .unwrap();
+    create table a_reports.report_subscriptions(
+        id int primary key,
+        customer_id int not null references z_core.customers(id)
+    );
 
-        copy_data(&source, &mut sql_file, CopyDataOptions::default())
-            .await
-            .unwrap();
-    }
+    create view a_reports.customer_totals as
+        select c.name, sum(o.total) as total
+        from z_core.customers c
+        join z_core.orders o on o.customer_id = c.id
+        group by c.name;
 
-    String::from_utf8(result_file).unwrap()
+    create materialized view z_core.order_stats as
+        select customer_id, count(*) as order_count from z_core.orders group by customer_id;
+
+    create materialized view a_reports.busy_customers as
+        select customer_id from z_core.order_stats where order_count > 1;
+
+    insert into z_core.customers(id, name) values (1, 'foo'), (2, 'bar');
+    insert into z_core.orders(id, customer_id, total) values (1, 1, 10), (2, 1, 20), (3, 2, 5);
+    insert into a_reports.report_subscriptions(id, customer_id) values (1, 2);
+    refresh materialized view z_core.order_stats;
+    refresh materialized view a_reports.busy_customers;
+"#;
+
+#[pg_test(arg(postgres = 12), arg(postgres = 12))]
+#[pg_test(arg(postgres = 13), arg(postgres = 13))]
+#[pg_test(arg(postgres = 14), arg(postgres = 14))]
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+#[pg_test(arg(postgres = 17), arg(postgres = 17))]
+async fn objects_depending_on_later_schemas_are_created_in_dependency_order(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    test_round_trip(CROSS_SCHEMA_DEPENDENCIES, source, destination).await;
+
+    let busy_customers = destination
+        .get_single_results::<i32>("select customer_id from a_reports.busy_customers;")
+        .await;
+    assert_eq!(busy_customers, vec![1]);
 }
-const SEPARATOR_LINE: &str = "-- chunk-separator-test_chunk_separator --\n";
 
-pub async fn test_differential_copy_generic(source: &TestHelper, setup_query: &str) {
-    source.execute_not_query(setup_query).await;
+const CHAINED_MATERIALIZED_VIEWS: &str = r#"
+    create table orders(id int primary key, customer_id int not null);
+    insert into orders(id, customer_id) values (1, 1), (2, 1), (3, 2);
 
-    let source_schema = introspect_schema(source).await;
+    create materialized view z_order_counts as
+        select customer_id, count(*) as order_count from orders group by customer_id;
 
-    let sql = export_to_string(source).await;
+    create materialized view a_repeat_customers as
+        select customer_id from z_order_counts where order_count > 1;
+"#;
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn refreshes_chained_materialized_views_in_dependency_order(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(CHAINED_MATERIALIZED_VIEWS).await;
 
     let source_storage = PostgresInstanceStorage::new(source.get_conn())
         .await
         .unwrap();
-
-    let commands = sql
-        .as_bytes()
-        .read_lines_until_separator_line_to_vec(SEPARATOR_LINE)
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
         .await
         .unwrap();
 
-    for i in 0..commands.len() {
-        let to_execute = commands.iter().take(i);
-
-        let destination = source.create_another_database(&format!("test_{i}")).await;
-
-        for command in to_execute {
-            destination.execute_not_query(command).await;
-        }
-
-        let mut destination_worker = PostgresInstanceStorage::new(destination.get_conn())
-            .await
-            .unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            max_parallel: Some(NonZeroUsize::new(4).unwrap()),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
 
-        copy_data(
-            &source_storage,
-            &mut destination_worker,
-            CopyDataOptions {
-                data_format: None,
-                max_parallel: None,
-                differential: true,
-                ..default()
-            },
+    let order_counts = destination
+        .get_results::<(i32, i64)>(
+            "select customer_id, order_count from z_order_counts order by customer_id;",
         )
-        .await
-        .expect("Failed to copy data");
-
-        let destination_schema = introspect_schema(&destination).await;
-
-        assert_eq!(source_schema, destination_schema);
-
-        let destination_raw_connection = destination.get_conn().underlying_connection();
-        let source_raw_connection = source.get_conn().underlying_connection();
-
-        for schema in &source_schema.schemas {
-            for table in &schema.tables {
-                let mut query = "select ".to_string();
+        .await;
+    assert_eq!(order_counts, vec![(1, 2), (2, 1)]);
+    let repeat_customers = destination
+        .get_single_results::<i32>("select customer_id from a_repeat_customers;")
+        .await;
+    assert_eq!(repeat_customers, vec![1]);
+}
 
-                query.push_join(
-                    ", ",
-                    table
-                        .columns
-                        .iter()
-                        .filter(|c| c.generated.is_none())
-                        .map(|c| {
-                            format!(
-                                "{}::text",
-                                c.name.quote(
-                                    &source_storage.identifier_quoter,
-                                    AttemptedKeywordUsage::ColumnName
-                                )
-                            )
-                        })
-                        .collect_vec(),
-                );
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn leaves_materialized_views_unpopulated_without_refreshing(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source.execute_not_query(CHAINED_MATERIALIZED_VIEWS).await;
 
-                query.push_str(" from ");
-                query.push_str(&schema.name.quote(
-                    &source_storage.identifier_quoter,
-                    AttemptedKeywordUsage::Other,
-                ));
-                query.push('.');
-                query.push_str(&table.name.quote(
-                    &source_storage.identifier_quoter,
-                    AttemptedKeywordUsage::TypeOrFunctionName,
-                ));
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-                let from_source = source_raw_connection.query(&query, &[]).await.unwrap();
-                let from_destination = destination_raw_connection.query(&query, &[]).await.unwrap();
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            schema_only: true,
+            refresh_materialized_views: false,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
 
-                assert_eq!(
-                    from_source.len(),
-                    from_destination.len(),
-                    "Table: {}.{}. Expected {}, got {}",
-                    schema.name,
-                    table.name,
-                    from_source.len(),
-                    from_destination.len()
-                );
+    let populated = destination
+        .get_single_results::<bool>(
+            "select relispopulated from pg_class where relkind = 'm' order by relname;",
+        )
+        .await;
+    assert_eq!(populated, vec![false, false]);
+}
 
-                for (row_index, (source_row, destination_row)) in
-                    from_source.iter().zip(from_destination).enumerate()
-                {
-                    for (idx, col) in source_row.columns().iter().enumerate() {
-                        let source_value: String = source_row.get(idx);
-                        let destination_value: String = destination_row.get(idx);
-                        assert_eq!(
-                            source_value,
-                            destination_value,
-                            "Table: {}.{}. Row: {}. Column: {}. Expected {:?}, got {:?}",
-                            schema.name,
-                            table.name,
-                            row_index,
-                            col.name(),
-                            source_value,
-                            destination_value
-                        );
-                    }
-                }
-            }
-        }
+#[pg_test(arg(postgres = 15))]
+async fn errors_include_the_query(helper: &TestHelper) {
+    let error = helper
+        .get_conn()
+        .get_single_result::<i32>("select 1 where false;")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid number of results returned from query `select 1 where false;`. Expected `1`, got `0`"
+    );
 
-        destination.stop().await;
-    }
+    let error = helper
+        .get_conn()
+        .get_single_results::<i32>("select 'not a number'::int4;")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, ElefantToolsError::PostgresErrorWithQuery { query, .. } if query == "select 'not a number'::int4;"),
+        "{:?}",
+        error
+    );
+    assert!(std::error::Error::source(&error).is_some());
 }
 
-#[pg_test(arg(postgres = 15))]
-async fn test_differential_copy(source: &TestHelper) {
-    test_differential_copy_generic(source, r#"
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+async fn sequential_copy_uses_only_the_existing_connections(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        create table items(id int primary key, name text not null);
+        insert into items(id, name) select i, 'item ' || i from generate_series(1, 100) i;
 
-        CREATE TABLE products (
-            product_no integer PRIMARY KEY,
-            name text,
-            price numeric
-        );
+        create table orders(id int primary key, item_id int not null references items(id));
+        insert into orders(id, item_id) select i, i from generate_series(1, 100) i;
 
-        insert into products(product_no, name, price) values (1, 'foo', 1.0), (2, 'bar', 2.0), (3, 'baz', 3.0);
+        create table z_connections(count int not null);
+        "#,
+        )
+        .await;
 
-        CREATE TABLE orders (
-            order_id integer PRIMARY KEY,
-            shipping_address text
-        );
+    let application_name = format!("sequential_copy_{}", uuid::Uuid::new_v4().simple());
+    let connection_string = |helper: &TestHelper| {
+        format!(
+            "host=localhost port={} user=postgres password=passw0rd dbname={} application_name={}",
+            helper.port, helper.test_db_name, application_name
+        )
+    };
+    let source_connection = PostgresClientWrapper::new(&connection_string(source))
+        .await
+        .unwrap();
+    let destination_connection = PostgresClientWrapper::new(&connection_string(destination))
+        .await
+        .unwrap();
 
-        insert into orders(order_id, shipping_address) values (1, 'foo'), (2, 'bar'), (3, 'baz');
+    let source_storage = PostgresInstanceStorage::new(&source_connection)
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(&destination_connection)
+        .await
+        .unwrap();
 
-        CREATE TABLE order_items (
-            product_no integer REFERENCES products ON DELETE RESTRICT ON UPDATE CASCADE,
-            order_id integer REFERENCES orders ON DELETE CASCADE ON UPDATE RESTRICT,
-            quantity integer,
-            PRIMARY KEY (product_no, order_id)
-        );
+    // The last table is copied using a select that counts the connections while the copy runs.
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            max_parallel: Some(NonZeroUsize::new(1).unwrap()),
+            table_select_overrides: HashMap::from([(
+                "public.z_connections".to_string(),
+                format!(
+                    "select count(*)::int4 from pg_stat_activity where application_name = '{}'",
+                    application_name
+                ),
+            )]),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
 
-        insert into order_items(product_no, order_id, quantity) values (1, 1, 1), (2, 2, 2), (3, 3, 3);
-    "#).await;
+    let connections = destination
+        .get_single_result::<i32>("select count from z_connections;")
+        .await;
+    assert_eq!(connections, 2);
+
+    let orders = destination
+        .get_single_result::<i64>("select count(*) from orders;")
+        .await;
+    assert_eq!(orders, 100);
 }
 
+#[pg_test(arg(postgres_replicated = 16))]
+async fn copies_from_a_replica(helper: &ReplicatedTestHelper) {
+    helper
+        .primary
+        .execute_not_query(storage::tests::get_copy_source_database_create_script(
+            helper.primary.get_conn().version(),
+        ))
+        .await;
+    helper.wait_for_replay_lsn().await;
 
-test_round_trip!(identity_column_by_default, r#"
-    create table my_table(
-        id int generated by default as identity primary key,
-        name text not null
-    );
+    let source_schema = introspect_schema(&helper.replica).await;
+    let destination = helper.primary.create_another_database("destination").await;
 
-    insert into my_table(name) values ('foo'), ('bar');
-"#);
+    let source_storage = PostgresInstanceStorage::new(helper.replica.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-test_round_trip!(identity_column_always, r#"
-    create table my_table(
-        id int generated always as identity primary key,
-        name text not null
-    );
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            max_parallel: Some(NonZeroUsize::new(4).unwrap()),
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data from the replica");
 
-    insert into my_table(name) values ('foo'), ('bar');
-"#);
+    assert_eq!(introspect_schema(&destination).await, source_schema);
+    validate_copy_state(&destination).await;
 
-test_round_trip!(identity_column_by_default_custom_sequence, r#"
-    create table my_table(
-        id int generated by default as identity (START WITH 10 INCREMENT BY 10) primary key,
-        name text not null
-    );
+    destination.stop().await;
+}
 
-    insert into my_table(name) values ('foo'), ('bar');
-"#);
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+async fn copies_one_index_per_partition_of_partitioned_indexes(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        create table sales (
+            sale_id int not null,
+            sale_date date not null,
+            primary key (sale_id, sale_date)
+        ) partition by range (sale_date);
+
+        create table sales_january partition of sales
+            for values from ('2023-01-01') to ('2023-02-01');
+
+        create table sales_february partition of sales
+            for values from ('2023-02-01') to ('2023-03-01');
+
+        create index sales_sale_date_idx on only sales (sale_date);
+        create index sales_january_custom_idx on sales_january (sale_date);
+        alter index sales_sale_date_idx attach partition sales_january_custom_idx;
+        create index sales_february_custom_idx on sales_february (sale_date);
+        alter index sales_sale_date_idx attach partition sales_february_custom_idx;
+        "#,
+        )
+        .await;
 
-test_round_trip!(identity_column_by_default_custom_sequence_start_only, r#"
-    create table my_table(
-        id int generated by default as identity (START WITH 10) primary key,
-        name text not null
-    );
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
 
-    insert into my_table(name) values ('foo'), ('bar');
-"#);
+    copy_data(&source_storage, &mut destination_storage, default())
+        .await
+        .unwrap();
 
-test_round_trip!(identity_column_by_default_custom_sequence_increment_only, r#"
-    create table my_table(
-        id int generated by default as identity (INCREMENT BY 10) primary key,
-        name text not null
+    let index_counts = destination
+        .get_results::<(String, i64)>(
+            "select tablename::text, count(*) from pg_indexes where schemaname = 'public' group by tablename order by tablename;",
+        )
+        .await;
+    assert_eq!(
+        index_counts,
+        vec![
+            ("sales".to_string(), 2),
+            ("sales_february".to_string(), 2),
+            ("sales_january".to_string(), 2),
+        ]
     );
 
-    insert into my_table(name) values ('foo'), ('bar');
-"#);
+    let invalid_indexes = destination
+        .get_single_result::<i64>("select count(*) from pg_index where not indisvalid;")
+        .await;
+    assert_eq!(invalid_indexes, 0);
 
-#[pg_test(arg(postgres = 15), arg(postgres = 15))]
-async fn identity_column_sequence_continues_correctly(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(r#"
-    create table my_table(
-        id int generated by default as identity primary key,
-        name text not null
-    );
+    let destination_schema = introspect_schema(destination).await;
+    let public = destination_schema.try_get_schema("public").unwrap();
+    let parent_indices = public
+        .tables
+        .iter()
+        .flat_map(|t| &t.indices)
+        .filter(|i| i.parent_index.as_deref() == Some("sales_sale_date_idx"))
+        .count();
+    assert_eq!(parent_indices, 2);
+
+    assert_differential_noop(source, destination).await;
+}
 
-    insert into my_table(name) values ('foo'), ('bar');
-"#, source, destination).await;
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+async fn copies_with_more_parallelism_than_connections_allowed(
+    source: &TestHelper,
+    destination: &TestHelper,
+) {
+    source
+        .execute_not_query(
+            r#"
+        do $$
+        begin
+            for i in 1..12 loop
+                execute format('create table items_%s(id int primary key, name text not null);', i);
+                execute format('insert into items_%s(id, name) select g, ''item '' || g from generate_series(1, 1000) g;', i);
+            end loop;
+        end
+        $$;
+        "#,
+        )
+        .await;
 
-    destination.execute_not_query("insert into my_table(name) values ('baz'), ('qux')").await;
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
 
-    let items = destination.get_results::<(i32, String)>("select id, name from my_table order by id").await;
+    // Connection limits don't apply to superusers, and the restricted connection itself takes one
+    // of the connections, which leaves room for the two connections of the pool.
+    let restricted_destination = destination.as_restricted_user().await;
+    destination
+        .execute_not_query(&format!(
+            "alter database {} connection limit 3;",
+            destination.test_db_name
+        ))
+        .await;
 
-    assert_eq!(items, vec![(1, "foo".to_string()), (2, "bar".to_string()), (3, "baz".to_string()), (4, "qux".to_string())]);
+    let mut destination_storage = PostgresInstanceStorage::with_connection_pool(
+        restricted_destination.get_conn(),
+        ConnectionPool::new(NonZeroUsize::new(2).unwrap()),
+    )
+    .await
+    .unwrap();
 
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            max_parallel: Some(NonZeroUsize::new(8).unwrap()),
+            ..default()
+        },
+    )
+    .await
+    .expect("Failed to copy data");
+
+    for i in 1..=12 {
+        let count = destination
+            .get_single_result::<i64>(&format!("select count(*) from items_{};", i))
+            .await;
+        assert_eq!(count, 1000);
+    }
 }
 
-test_round_trip!(identity_columns_on_renamed_tables, r#"
-    create table my_table(
-        id int generated by default as identity primary key,
-        name text not null
-    );
+#[pg_test(arg(postgres = 16))]
+async fn connection_pool_replaces_dead_idle_connections(helper: &TestHelper) {
+    let pool = ConnectionPool::new(NonZeroUsize::new(1).unwrap());
+    let connect = || helper.get_conn().create_another_connection();
 
-    insert into my_table(name) values ('foo'), ('bar');
+    let connection = pool.get_connection(connect).await.unwrap();
+    let first_pid = connection
+        .get_single_result::<i32>("select pg_backend_pid();")
+        .await
+        .unwrap();
+    pool.release_connection(connection).await;
 
-    alter table my_table rename to new_my_table;
-"#);
+    let connection = pool.get_connection(connect).await.unwrap();
+    let reused_pid = connection
+        .get_single_result::<i32>("select pg_backend_pid();")
+        .await
+        .unwrap();
+    assert_eq!(reused_pid, first_pid);
+    pool.release_connection(connection).await;
 
+    helper
+        .execute_not_query(&format!(
+            "select pg_terminate_backend({first_pid}, 5000);"
+        ))
+        .await;
 
-test_round_trip!(identity_columns_on_renamed_tables_id_column_is_not_first_column, r#"
-    create table my_table(
-        name text not null,
-        id int generated by default as identity primary key
+    let connection = pool.get_connection(connect).await.unwrap();
+    let replaced_pid = connection
+        .get_single_result::<i32>("select pg_backend_pid();")
+        .await
+        .unwrap();
+    assert_ne!(replaced_pid, first_pid);
+}
+
+#[pg_test(arg(postgres = 14))]
+#[pg_test(arg(postgres = 16))]
+async fn reports_the_capabilities_of_the_server(helper: &TestHelper) {
+    let storage = PostgresInstanceStorage::new(helper.get_conn()).await.unwrap();
+    let capabilities = storage.capabilities();
+
+    assert_eq!(capabilities.version, helper.get_conn().version());
+    assert_eq!(
+        capabilities.supports_nulls_not_distinct,
+        helper.get_conn().version() >= 150
     );
+    assert!(capabilities.supports_trusted_extensions);
+    assert_eq!(capabilities.timescale, None);
+}
 
-    insert into my_table(name) values ('foo'), ('bar');
+#[pg_test(arg(timescale_db = 15))]
+async fn reports_the_installed_timescale_version(helper: &TestHelper) {
+    let storage = PostgresInstanceStorage::new(helper.get_conn()).await.unwrap();
 
-    alter table my_table rename to new_my_table;
-"#);
+    let timescale = storage.capabilities().timescale.unwrap();
+    assert!(timescale >= TimescaleVersion::parse(crate::MIN_SUPPORTED_TIMESCALEDB_VERSION).unwrap());
+    assert_eq!(
+        storage.get_server_capabilities().await.unwrap(),
+        Some(storage.capabilities())
+    );
+}
 
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+async fn resumes_an_interrupted_copy(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(
+            r#"
+        create table a_users(id int primary key, name text not null);
+        create table b_events(id int primary key, payload text not null);
 
-#[pg_test(arg(timescale_db = 15), arg(timescale_db = 15))]
-#[pg_test(arg(timescale_db = 16), arg(timescale_db = 16))]
-async fn timescale_constraints_on_indices(source: &TestHelper, destination: &TestHelper) {
-    test_round_trip(r#"
-    create table my_table(time timestamptz not null, event_id uuid not null, member_id int not null, web_site_url text not null);
+        insert into a_users values (1, 'foo'), (2, 'bar');
+        insert into b_events select i, 'event ' || i from generate_series(1, 100) i;
+        "#,
+        )
+        .await;
 
-    alter table my_table add constraint my_uniq unique (time, event_id);
+    let resume_path =
+        std::env::temp_dir().join(format!("elefant-resume-{}.json", uuid::Uuid::new_v4()));
 
-    select create_hypertable('my_table', by_range('time', '7 day'::interval));
-    "#, source, destination).await;
-}
\ No newline at end of file
+    let source_storage = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+
+    // Fails halfway through the events, after the users have been copied.
+    copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            resume_state_path: Some(resume_path.clone()),
+            table_select_overrides: HashMap::from([(
+                "public.b_events".to_string(),
+                "select id, payload from b_events where 1 / (50 - id) is not null".to_string(),
+            )]),
+            ..default()
+        },
+    )
+    .await
+    .unwrap_err();
+    assert!(resume_path.exists());
+
+    // As if some of the events had been copied before the connection was lost.
+    destination
+        .execute_not_query("insert into b_events values (1, 'partial');")
+        .await;
+
+    let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+        .await
+        .unwrap();
+    let report = copy_data(
+        &source_storage,
+        &mut destination_storage,
+        CopyDataOptions {
+            resume_state_path: Some(resume_path.clone()),
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert!(!resume_path.exists());
+    assert_eq!(
+        report
+            .table_timings
+            .iter()
+            .map(|t| t.table_name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["b_events"]
+    );
+
+    let users = destination
+        .get_single_result::<i64>("select count(*) from a_users;")
+        .await;
+    assert_eq!(users, 2);
+    let events = destination
+        .get_single_result::<i64>("select count(*) from b_events;")
+        .await;
+    assert_eq!(events, 100);
+    let partial = destination
+        .get_single_result::<String>("select payload from b_events where id = 1;")
+        .await;
+    assert_eq!(partial, "event 1");
+
+
+    // The primary keys are created once, after the data.
+    let indexes = destination
+        .get_single_result::<i64>("select count(*) from pg_indexes where schemaname = 'public';")
+        .await;
+    assert_eq!(indexes, 2);
+}
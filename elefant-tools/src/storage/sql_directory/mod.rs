@@ -0,0 +1,657 @@
+use crate::helpers::IMPORT_PREFIX;
+use crate::models::PostgresSchema;
+use crate::models::PostgresTable;
+use crate::quoting::IdentifierQuoter;
+use crate::storage::data_format::DataFormat;
+use crate::storage::sql_file::CHUNK_SEPARATOR_PREFIX;
+use crate::storage::table_data::TableData;
+use crate::storage::{encode_file_name, BaseCopyTarget, CopyDestination};
+use crate::{
+    apply_sql_file, AsyncCleanup, CopyDestinationFactory, ElefantToolsError,
+    ParallelCopyDestinationNotAvailable, PostgresClientWrapper, Result, ResultExt,
+    SequentialOrParallel, SqlFile, SqlFileOptions, SupportedParallelism,
+};
+use bytes::Bytes;
+use futures::Stream;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::BufReader;
+use tracing::{debug, instrument};
+
+#[cfg(test)]
+mod tests;
+
+/// The file listing the order the statements and data files are applied in.
+const APPLY_ORDER_FILE: &str = "apply_order.txt";
+
+/// The directory the data of the tables is written to.
+const DATA_DIRECTORY: &str = "data";
+
+/// The file for the statements that don't belong to a specific object.
+const OTHER_FILE: &str = "other.sql";
+
+/// A destination that writes the structure of the database as one file per object, which makes
+/// the export easy to review and to keep in version control.
+///
+/// The statements are placed in files such as `schemas/public/tables/people.sql`, with the chunk
+/// separator line of [SqlFile] before each statement. The data of each table is written as a
+/// [SqlFile] to `data/<schema>.<table>.sql`. As the statements of a file are not applied together,
+/// the order to apply them in is written to `apply_order.txt`, which [apply_sql_directory] uses to
+/// import the directory again.
+///
+/// Set [SqlFileOptions::chunk_separator] to a fixed value to only get differences in the files
+/// of the objects that changed between two exports. [SqlFileOptions::max_commands_per_chunk] and
+/// [SqlFileOptions::transaction_per_chunk] are not used, as every statement is its own chunk.
+pub struct SqlDirectoryDestination {
+    path: PathBuf,
+    options: SqlFileOptions,
+    quoter: Arc<IdentifierQuoter>,
+    chunk_separator: String,
+    /// The content of the statement files by their path relative to the directory. They are
+    /// only written when the copy finishes, as the statements of a file are spread over the copy.
+    files: BTreeMap<String, String>,
+    /// The paths of the files in the order their statements and data are applied in.
+    apply_order: Vec<String>,
+    /// The file of each table, view and sequence, by schema and name, so statements that don't
+    /// say which kind of relation they are about end up in the right file.
+    relation_files: HashMap<(String, String), String>,
+    /// The file of each index by name, as comments on indices don't include the table.
+    index_files: HashMap<String, String>,
+}
+
+impl SqlDirectoryDestination {
+    /// Creates a new destination writing to the specified directory. The directory is created
+    /// if it doesn't exist already. If it contains an earlier export, the files of that export are
+    /// removed, so objects that no longer exist don't leave their files behind.
+    pub async fn new(
+        path: impl AsRef<Path>,
+        identifier_quoter: Arc<IdentifierQuoter>,
+        options: SqlFileOptions,
+    ) -> Result<Self> {
+        options.copy_options.validate()?;
+
+        let path = path.as_ref().to_path_buf();
+
+        if tokio::fs::try_exists(path.join(APPLY_ORDER_FILE))
+            .await
+            .with_context_path("read", &path)?
+        {
+            remove_previous_export(&path).await?;
+        }
+
+        tokio::fs::create_dir_all(&path)
+            .await
+            .with_context_path("create directory", &path)?;
+
+        let chunk_separator = format!("{}{} --", CHUNK_SEPARATOR_PREFIX, options.chunk_separator);
+
+        Ok(Self {
+            path,
+            options,
+            quoter: identifier_quoter,
+            chunk_separator,
+            files: BTreeMap::new(),
+            apply_order: Vec::new(),
+            relation_files: HashMap::new(),
+            index_files: HashMap::new(),
+        })
+    }
+
+    /// Adds the statement to the end of the file of the object it is about.
+    fn add_statement(&mut self, statement: &str) {
+        let file_path = self.get_statement_file_path(statement);
+        debug!("Writing statement to {}", file_path);
+
+        let content = self.files.entry(file_path.clone()).or_default();
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&self.chunk_separator);
+        content.push('\n');
+        content.push_str(statement.trim_end());
+        content.push('\n');
+
+        self.apply_order.push(file_path);
+    }
+
+    /// Finds the file the statement belongs in, based on the kind and name of the object it is
+    /// about. Statements that are not recognized go in `other.sql`.
+    fn get_statement_file_path(&mut self, statement: &str) -> String {
+        let object = StatementObject::parse(statement);
+
+        match object {
+            StatementObject::Schema(schema) => schema_file_path(&schema),
+            StatementObject::Extension(name) => {
+                format!("extensions/{}.sql", encode_file_name(&name))
+            }
+            StatementObject::CreatedRelation { kind, name } => {
+                let path = object_file_path(kind, &name);
+                self.relation_files.insert(name, path.clone());
+                path
+            }
+            StatementObject::Relation { kind, name } => self.get_relation_file_path(kind, name),
+            StatementObject::CreatedIndex { index_name, table } => {
+                let path = self.get_relation_file_path("tables", table);
+                self.index_files.insert(index_name, path.clone());
+                path
+            }
+            StatementObject::Index(index_name) => self
+                .index_files
+                .get(&index_name)
+                .cloned()
+                .unwrap_or_else(|| OTHER_FILE.to_string()),
+            StatementObject::Other { kind, name } => object_file_path(kind, &name),
+            StatementObject::Unknown => OTHER_FILE.to_string(),
+        }
+    }
+
+    /// The file of a relation created earlier in the copy, or the file for a relation of the
+    /// specified kind if it wasn't, such as the sequences of identity columns.
+    fn get_relation_file_path(&self, kind: &'static str, name: (String, String)) -> String {
+        match self.relation_files.get(&name) {
+            Some(path) => path.clone(),
+            None => object_file_path(kind, &name),
+        }
+    }
+
+    /// The path of the data file of the table, relative to the directory.
+    fn get_data_file_path(schema: &PostgresSchema, table: &PostgresTable) -> String {
+        format!(
+            "{}/{}.{}.sql",
+            DATA_DIRECTORY,
+            encode_file_name(&schema.name),
+            encode_file_name(&table.name)
+        )
+    }
+
+    async fn write_files(&self) -> Result<()> {
+        for (relative_path, content) in &self.files {
+            let path = self.path.join(relative_path);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .with_context_path("create directory", parent)?;
+            }
+
+            tokio::fs::write(&path, content)
+                .await
+                .with_context_path("write to", &path)?;
+        }
+
+        let mut apply_order = self.apply_order.join("\n");
+        apply_order.push('\n');
+        let path = self.path.join(APPLY_ORDER_FILE);
+        tokio::fs::write(&path, apply_order)
+            .await
+            .with_context_path("write to", &path)?;
+
+        Ok(())
+    }
+}
+
+/// Removes the files written by an earlier export to the directory.
+async fn remove_previous_export(path: &Path) -> Result<()> {
+    for directory in ["schemas", "extensions", DATA_DIRECTORY] {
+        let directory = path.join(directory);
+        if tokio::fs::try_exists(&directory)
+            .await
+            .with_context_path("read", &directory)?
+        {
+            tokio::fs::remove_dir_all(&directory)
+                .await
+                .with_context_path("remove", &directory)?;
+        }
+    }
+
+    for file in [OTHER_FILE, APPLY_ORDER_FILE] {
+        let file = path.join(file);
+        if tokio::fs::try_exists(&file)
+            .await
+            .with_context_path("read", &file)?
+        {
+            tokio::fs::remove_file(&file)
+                .await
+                .with_context_path("remove", &file)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn schema_file_path(schema: &str) -> String {
+    format!("schemas/{}/schema.sql", encode_file_name(schema))
+}
+
+fn object_file_path(kind: &str, (schema, name): &(String, String)) -> String {
+    format!(
+        "schemas/{}/{}/{}.sql",
+        encode_file_name(schema),
+        kind,
+        encode_file_name(name)
+    )
+}
+
+/// The object a DDL statement is about, as far as it matters for placing it in a file.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum StatementObject {
+    Schema(String),
+    Extension(String),
+    /// A table, view or sequence being created, with the directory of its kind.
+    CreatedRelation {
+        kind: &'static str,
+        name: (String, String),
+    },
+    /// A statement about a table, view or sequence that doesn't say which one it is, with the
+    /// directory of the kind it most likely is.
+    Relation {
+        kind: &'static str,
+        name: (String, String),
+    },
+    CreatedIndex {
+        index_name: String,
+        table: (String, String),
+    },
+    Index(String),
+    /// Any other object in a schema, with the directory of its kind.
+    Other {
+        kind: &'static str,
+        name: (String, String),
+    },
+    Unknown,
+}
+
+impl StatementObject {
+    pub(crate) fn parse(statement: &str) -> StatementObject {
+        let statement = statement.trim_start();
+        Self::try_parse(statement).unwrap_or(StatementObject::Unknown)
+    }
+
+    fn try_parse(statement: &str) -> Option<StatementObject> {
+        let schema_prefixes = [
+            "create schema if not exists ",
+            "alter schema ",
+            "comment on schema ",
+        ];
+        if let Some(rest) = strip_any_prefix(statement, &schema_prefixes) {
+            return Some(StatementObject::Schema(parse_name(rest)?.0.pop()?));
+        }
+
+        if let Some(rest) = statement.strip_prefix("create extension if not exists ") {
+            return Some(StatementObject::Extension(parse_name(rest)?.0.pop()?));
+        }
+
+        let created_relations = [
+            ("create table ", "tables"),
+            ("create view ", "views"),
+            ("create materialized view ", "views"),
+            ("create sequence ", "sequences"),
+        ];
+        for (prefix, kind) in created_relations {
+            if let Some(rest) = statement.strip_prefix(prefix) {
+                return Some(StatementObject::CreatedRelation {
+                    kind,
+                    name: parse_qualified_name(rest)?,
+                });
+            }
+        }
+
+        let relations = [
+            ("alter table only ", "tables"),
+            ("alter table ", "tables"),
+            ("comment on table ", "tables"),
+            ("comment on column ", "tables"),
+            ("alter materialized view ", "views"),
+            ("refresh materialized view ", "views"),
+            ("alter sequence ", "sequences"),
+            ("comment on sequence ", "sequences"),
+            ("select pg_catalog.setval('", "sequences"),
+        ];
+        for (prefix, kind) in relations {
+            if let Some(rest) = statement.strip_prefix(prefix) {
+                return Some(StatementObject::Relation {
+                    kind,
+                    name: parse_qualified_name(rest)?,
+                });
+            }
+        }
+
+        if let Some(rest) = strip_any_prefix(statement, &["create index ", "create unique index "])
+        {
+            let rest = rest.strip_prefix("concurrently ").unwrap_or(rest);
+            let (mut index_name, rest) = parse_name(rest)?;
+            let rest = rest.strip_prefix(" on ")?;
+            let rest = rest.strip_prefix("only ").unwrap_or(rest);
+            return Some(StatementObject::CreatedIndex {
+                index_name: index_name.pop()?,
+                table: parse_qualified_name(rest)?,
+            });
+        }
+
+        if let Some(rest) = strip_any_prefix(statement, &["comment on index ", "alter index ", "drop index "]) {
+            return Some(StatementObject::Index(parse_name(rest)?.0.pop()?));
+        }
+
+        if let Some(rest) = strip_any_prefix(statement, &["create trigger ", "create policy "]) {
+            let (_, rest) = parse_name(rest)?;
+            let (_, table) = rest.split_once(" on ")?;
+            return Some(StatementObject::Relation {
+                kind: "tables",
+                name: parse_qualified_name(table)?,
+            });
+        }
+
+        if let Some(rest) = statement.strip_prefix("comment on constraint ") {
+            let (_, rest) = parse_name(rest)?;
+            let table = rest.strip_prefix(" on ")?;
+            return Some(StatementObject::Relation {
+                kind: "tables",
+                name: parse_qualified_name(table)?,
+            });
+        }
+
+        let other_objects = [
+            ("create function ", "functions"),
+            ("create procedure ", "functions"),
+            ("alter function ", "functions"),
+            ("alter procedure ", "functions"),
+            ("comment on function ", "functions"),
+            ("comment on procedure ", "functions"),
+            ("create aggregate ", "aggregates"),
+            ("create type ", "types"),
+            ("create domain ", "domains"),
+        ];
+        for (prefix, kind) in other_objects {
+            if let Some(rest) = statement.strip_prefix(prefix) {
+                return Some(StatementObject::Other {
+                    kind,
+                    name: parse_qualified_name(rest)?,
+                });
+            }
+        }
+
+        if let Some(rest) = strip_any_prefix(statement, &["grant ", "revoke "]) {
+            let (_, object) = rest.split_once(" on ")?;
+            return Self::parse_privilege_object(object);
+        }
+
+        None
+    }
+
+    /// Parses the object of a grant or revoke statement, such as `table public.people`.
+    fn parse_privilege_object(object: &str) -> Option<StatementObject> {
+        if let Some(rest) = object.strip_prefix("schema ") {
+            return Some(StatementObject::Schema(parse_name(rest)?.0.pop()?));
+        }
+
+        let relations = [("table ", "tables"), ("sequence ", "sequences")];
+        for (prefix, kind) in relations {
+            if let Some(rest) = object.strip_prefix(prefix) {
+                return Some(StatementObject::Relation {
+                    kind,
+                    name: parse_qualified_name(rest)?,
+                });
+            }
+        }
+
+        if let Some(rest) = strip_any_prefix(object, &["function ", "procedure "]) {
+            return Some(StatementObject::Other {
+                kind: "functions",
+                name: parse_qualified_name(rest)?,
+            });
+        }
+
+        None
+    }
+}
+
+fn strip_any_prefix<'a>(statement: &'a str, prefixes: &[&str]) -> Option<&'a str> {
+    prefixes
+        .iter()
+        .find_map(|prefix| statement.strip_prefix(prefix))
+}
+
+/// Parses a possibly qualified name, such as `public."My Table"`, into its schema and name.
+/// Unqualified names are in the `public` schema. Further parts, like the column of
+/// `comment on column`, are ignored.
+fn parse_qualified_name(s: &str) -> Option<(String, String)> {
+    let (mut parts, _) = parse_name(s)?;
+
+    if parts.len() == 1 {
+        return Some(("public".to_string(), parts.pop()?));
+    }
+
+    let mut parts = parts.into_iter();
+    Some((parts.next()?, parts.next()?))
+}
+
+/// Parses the parts of a dotted name at the start of the string, where each part is either
+/// quoted or a plain identifier, and returns them unquoted together with the rest of the string.
+fn parse_name(s: &str) -> Option<(Vec<String>, &str)> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+
+    loop {
+        let (part, after) = parse_identifier(rest)?;
+        parts.push(part);
+
+        match after.strip_prefix('.') {
+            Some(after) => rest = after,
+            None => return Some((parts, after)),
+        }
+    }
+}
+
+fn parse_identifier(s: &str) -> Option<(String, &str)> {
+    if let Some(quoted) = s.strip_prefix('"') {
+        let mut identifier = String::new();
+        let mut chars = quoted.char_indices().peekable();
+
+        while let Some((index, c)) = chars.next() {
+            if c == '"' {
+                if chars.peek().is_some_and(|(_, next)| *next == '"') {
+                    chars.next();
+                    identifier.push('"');
+                } else {
+                    return Some((identifier, &quoted[index + 1..]));
+                }
+            } else {
+                identifier.push(c);
+            }
+        }
+
+        None
+    } else {
+        let end = s
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .unwrap_or(s.len());
+
+        if end == 0 {
+            None
+        } else {
+            Some((s[..end].to_string(), &s[end..]))
+        }
+    }
+}
+
+impl BaseCopyTarget for SqlDirectoryDestination {
+    async fn supported_data_format(&self) -> Result<Vec<DataFormat>> {
+        Ok(vec![DataFormat::Text {
+            options: self.options.get_copy_text_options(),
+        }])
+    }
+}
+
+impl<'a> CopyDestinationFactory<'a> for SqlDirectoryDestination {
+    type SequentialDestination = &'a mut SqlDirectoryDestination;
+    type ParallelDestination = ParallelCopyDestinationNotAvailable;
+
+    async fn create_destination(
+        &'a mut self,
+    ) -> Result<SequentialOrParallel<Self::SequentialDestination, Self::ParallelDestination>> {
+        Ok(SequentialOrParallel::Sequential(self))
+    }
+
+    async fn create_sequential_destination(&'a mut self) -> Result<Self::SequentialDestination> {
+        Ok(self)
+    }
+
+    fn supported_parallelism(&self) -> SupportedParallelism {
+        SupportedParallelism::Sequential
+    }
+}
+
+impl CopyDestination for &mut SqlDirectoryDestination {
+    #[instrument(skip_all)]
+    async fn apply_data<S: Stream<Item = Result<Bytes>> + Send, C: AsyncCleanup>(
+        &mut self,
+        schema: &PostgresSchema,
+        table: &PostgresTable,
+        data: TableData<S, C>,
+    ) -> Result<()> {
+        let relative_path = SqlDirectoryDestination::get_data_file_path(schema, table);
+        let path = self.path.join(&relative_path);
+
+        let mut sql_file =
+            SqlFile::new_file(&path, self.quoter.clone(), self.options.clone()).await?;
+        let mut destination = &mut sql_file;
+        destination.apply_data(schema, table, data).await?;
+        destination.finish().await?;
+
+        // Tables without rows don't get a data file, so they don't clutter the directory.
+        let has_data = sql_file
+            .get_table_bytes_written()
+            .iter()
+            .any(|t| t.bytes > 0);
+        if has_data {
+            self.apply_order.push(relative_path);
+        } else {
+            tokio::fs::remove_file(&path)
+                .await
+                .with_context_path("remove", &path)?;
+        }
+
+        Ok(())
+    }
+
+    async fn apply_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        self.add_statement(statement);
+        Ok(())
+    }
+
+    async fn apply_non_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        self.add_statement(statement);
+        Ok(())
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_identifier_quoter(&self) -> Arc<IdentifierQuoter> {
+        self.quoter.clone()
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.write_files().await
+    }
+}
+
+/// Applies a directory written by [SqlDirectoryDestination] to the provided connection.
+///
+/// The statements are applied one by one in the order listed in `apply_order.txt`, and the data
+/// files are applied with [apply_sql_file], so the memory used is bounded the same way.
+#[instrument(skip_all)]
+pub async fn apply_sql_directory(
+    path: impl AsRef<Path>,
+    target_connection: &PostgresClientWrapper,
+) -> Result<()> {
+    let path = path.as_ref();
+
+    let apply_order_path = path.join(APPLY_ORDER_FILE);
+    let apply_order = tokio::fs::read_to_string(&apply_order_path)
+        .await
+        .with_context_path("read", &apply_order_path)?;
+
+    target_connection.execute_non_query(IMPORT_PREFIX).await?;
+
+    let mut remaining_statements: HashMap<&str, VecDeque<String>> = HashMap::new();
+
+    for relative_path in apply_order.lines().filter(|l| !l.is_empty()) {
+        let file_path = path.join(relative_path);
+
+        if relative_path.starts_with(&format!("{}/", DATA_DIRECTORY)) {
+            debug!("Applying data file {}", relative_path);
+            let file = File::open(&file_path)
+                .await
+                .with_context_path("open", &file_path)?;
+            let mut reader = BufReader::new(file);
+            apply_sql_file(&mut reader, target_connection)
+                .await
+                .with_context_path("read", &file_path)?;
+            continue;
+        }
+
+        let statements = match remaining_statements.get_mut(relative_path) {
+            Some(statements) => statements,
+            None => {
+                let content = tokio::fs::read_to_string(&file_path)
+                    .await
+                    .with_context_path("read", &file_path)?;
+                let statements = split_statements(&content).ok_or_else(|| {
+                    ElefantToolsError::InvalidSqlDirectory {
+                        path: path.display().to_string(),
+                        reason: format!("'{}' doesn't start with a chunk separator", relative_path),
+                    }
+                })?;
+                remaining_statements
+                    .entry(relative_path)
+                    .or_insert(statements)
+            }
+        };
+
+        let Some(statement) = statements.pop_front() else {
+            return Err(ElefantToolsError::InvalidSqlDirectory {
+                path: path.display().to_string(),
+                reason: format!(
+                    "'{}' has fewer statements than listed in {}",
+                    relative_path, APPLY_ORDER_FILE
+                ),
+            });
+        };
+
+        target_connection.execute_non_query(&statement).await?;
+    }
+
+    Ok(())
+}
+
+/// Splits the content of a statement file on its chunk separator, which is the first line of the
+/// file. Returns `None` if the file doesn't start with a chunk separator.
+fn split_statements(content: &str) -> Option<VecDeque<String>> {
+    let mut lines = content.split('\n');
+    let separator = lines.next()?;
+    if !separator.starts_with(CHUNK_SEPARATOR_PREFIX) {
+        return None;
+    }
+
+    let mut statements = VecDeque::new();
+    let mut current = String::new();
+    for line in lines {
+        if line == separator {
+            statements.push_back(current.trim_end().to_string());
+            current.clear();
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    statements.push_back(current.trim_end().to_string());
+
+    Some(statements)
+}
@@ -0,0 +1,236 @@
+use crate::copy_data::{copy_data, CopyDataOptions};
+use crate::schema_reader::tests::introspect_schema;
+use crate::storage::sql_directory::*;
+use crate::storage::tests::validate_copy_state;
+use crate::test_helpers;
+use crate::test_helpers::TestHelper;
+use crate::{default, storage, PostgresInstanceStorage};
+use elefant_test_macros::pg_test;
+use indoc::indoc;
+use uuid::Uuid;
+
+fn temp_directory() -> PathBuf {
+    std::env::temp_dir().join(format!("elefant-sql-directory-{}", Uuid::new_v4()))
+}
+
+async fn new_destination(path: &Path) -> SqlDirectoryDestination {
+    SqlDirectoryDestination::new(
+        path,
+        Arc::new(IdentifierQuoter::empty()),
+        SqlFileOptions {
+            chunk_separator: "test_chunk_separator".to_string(),
+            ..default()
+        },
+    )
+    .await
+    .unwrap()
+}
+
+async fn write_statements(path: &Path, statements: &[&str]) {
+    let mut destination = new_destination(path).await;
+    let mut destination = &mut destination;
+
+    for statement in statements {
+        destination
+            .apply_transactional_statement(statement)
+            .await
+            .unwrap();
+    }
+
+    destination.finish().await.unwrap();
+}
+
+fn file_path(statement: &str) -> String {
+    let mut destination = SqlDirectoryDestination {
+        path: PathBuf::new(),
+        options: default(),
+        quoter: Arc::new(IdentifierQuoter::empty()),
+        chunk_separator: String::new(),
+        files: BTreeMap::new(),
+        apply_order: Vec::new(),
+        relation_files: HashMap::new(),
+        index_files: HashMap::new(),
+    };
+
+    destination.get_statement_file_path(statement)
+}
+
+#[test]
+fn statements_are_placed_in_the_file_of_their_object() {
+    let cases = [
+        ("create schema if not exists my_schema;", "schemas/my_schema/schema.sql"),
+        ("comment on schema my_schema is 'hello';", "schemas/my_schema/schema.sql"),
+        ("grant usage on schema my_schema to app;", "schemas/my_schema/schema.sql"),
+        ("create extension if not exists btree_gin;", "extensions/btree_gin.sql"),
+        ("create table public.people (\n    id int4 not null\n);", "schemas/public/tables/people.sql"),
+        ("alter table public.people add constraint people_pkey primary key (id);", "schemas/public/tables/people.sql"),
+        ("comment on column public.people.id is 'the id';", "schemas/public/tables/people.sql"),
+        ("create unique index people_name on public.people using btree (name);", "schemas/public/tables/people.sql"),
+        ("create trigger my_trigger after insert or update of name on public.people for each row execute function public.my_function();", "schemas/public/tables/people.sql"),
+        ("create policy own_people on public.people for select to public using ((name = CURRENT_USER));", "schemas/public/tables/people.sql"),
+        ("alter table public.people enable row level security;", "schemas/public/tables/people.sql"),
+        ("grant select on table public.people to app;", "schemas/public/tables/people.sql"),
+        ("create view public.people_view (id) as select id from people;", "schemas/public/views/people_view.sql"),
+        ("refresh materialized view public.people_summary;", "schemas/public/views/people_summary.sql"),
+        ("create sequence public.my_sequence as bigint;", "schemas/public/sequences/my_sequence.sql"),
+        ("select pg_catalog.setval('public.people_id_seq', 3, true);", "schemas/public/sequences/people_id_seq.sql"),
+        ("create function public.my_function () returns trigger as $$ begin end $$;", "schemas/public/functions/my_function.sql"),
+        ("grant execute on function public.my_function() to app;", "schemas/public/functions/my_function.sql"),
+        ("create aggregate public.my_sum (int4) (\n sfunc = int4pl\n);", "schemas/public/aggregates/my_sum.sql"),
+        ("create type public.mood as enum ('happy');", "schemas/public/types/mood.sql"),
+        ("create domain public.positive as int4 check (value > 0);", "schemas/public/domains/positive.sql"),
+        ("select add_job('public.my_job', '1 hour');", "other.sql"),
+    ];
+
+    for (statement, expected) in cases {
+        assert_eq!(file_path(statement), expected, "{}", statement);
+    }
+}
+
+#[test]
+fn quoted_names_are_encoded_in_file_names() {
+    assert_eq!(
+        file_path(r#"create table "My Schema"."Weird ""name"".æ" (id int4);"#),
+        "schemas/My%20Schema/tables/Weird%20%22name%22%2E%C3%A6.sql"
+    );
+}
+
+#[tokio::test]
+async fn statements_about_created_objects_follow_them() {
+    let path = temp_directory();
+
+    write_statements(
+        &path,
+        &[
+            "create view public.people_view (id) as select 1;",
+            "grant select on table public.people_view to app;",
+            "create table public.items (id int4);",
+            "create index items_idx on public.items using btree (id);",
+            "comment on index items_idx is 'hello';",
+        ],
+    )
+    .await;
+
+    let view = std::fs::read_to_string(path.join("schemas/public/views/people_view.sql")).unwrap();
+    similar_asserts::assert_eq!(
+        view,
+        indoc! {"
+            -- chunk-separator-test_chunk_separator --
+            create view public.people_view (id) as select 1;
+
+            -- chunk-separator-test_chunk_separator --
+            grant select on table public.people_view to app;
+        "}
+    );
+
+    let table = std::fs::read_to_string(path.join("schemas/public/tables/items.sql")).unwrap();
+    similar_asserts::assert_eq!(
+        table,
+        indoc! {"
+            -- chunk-separator-test_chunk_separator --
+            create table public.items (id int4);
+
+            -- chunk-separator-test_chunk_separator --
+            create index items_idx on public.items using btree (id);
+
+            -- chunk-separator-test_chunk_separator --
+            comment on index items_idx is 'hello';
+        "}
+    );
+
+    let apply_order = std::fs::read_to_string(path.join("apply_order.txt")).unwrap();
+    similar_asserts::assert_eq!(
+        apply_order,
+        indoc! {"
+            schemas/public/views/people_view.sql
+            schemas/public/views/people_view.sql
+            schemas/public/tables/items.sql
+            schemas/public/tables/items.sql
+            schemas/public/tables/items.sql
+        "}
+    );
+
+    assert_eq!(split_statements(&table).unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn exporting_again_removes_the_files_of_removed_objects() {
+    let path = temp_directory();
+    std::fs::create_dir_all(&path).unwrap();
+    std::fs::write(path.join("readme.md"), "not part of the export").unwrap();
+
+    write_statements(&path, &["create table public.old (id int4);"]).await;
+    write_statements(&path, &["create table public.new (id int4);"]).await;
+
+    assert!(!path.join("schemas/public/tables/old.sql").exists());
+    assert!(path.join("schemas/public/tables/new.sql").exists());
+    assert!(path.join("readme.md").exists());
+}
+
+#[test]
+fn statement_files_must_start_with_a_chunk_separator() {
+    assert_eq!(split_statements("create table foo (id int4);"), None);
+    assert_eq!(
+        split_statements(
+            "-- chunk-separator-abc --\nselect 1;\n\n-- chunk-separator-abc --\nselect\n  2;\n"
+        ),
+        Some(VecDeque::from(vec![
+            "select 1;".to_string(),
+            "select\n  2;".to_string()
+        ]))
+    );
+}
+
+#[pg_test(arg(postgres = 15), arg(postgres = 15))]
+#[pg_test(arg(postgres = 16), arg(postgres = 16))]
+async fn round_trips_through_a_directory(source: &TestHelper, destination: &TestHelper) {
+    source
+        .execute_not_query(storage::tests::get_copy_source_database_create_script(
+            source.get_conn().version(),
+        ))
+        .await;
+
+    let path = temp_directory();
+    {
+        let mut sql_directory = new_destination(&path).await;
+        let source = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+
+        copy_data(&source, &mut sql_directory, CopyDataOptions::default())
+            .await
+            .unwrap();
+    }
+
+    assert!(path.join("schemas/public/tables/people.sql").exists());
+    assert!(path.join("data/public.people.sql").exists());
+
+    apply_sql_directory(&path, destination.get_conn())
+        .await
+        .unwrap();
+
+    let source_schema = introspect_schema(source).await;
+    let destination_schema = introspect_schema(destination).await;
+
+    assert_eq!(source_schema, destination_schema);
+
+    validate_copy_state(destination).await;
+}
+
+#[pg_test(arg(postgres = 16))]
+async fn applying_fails_when_a_statement_file_is_missing_statements(destination: &TestHelper) {
+    let path = temp_directory();
+    write_statements(&path, &["create table public.items (id int4);"]).await;
+    std::fs::write(
+        path.join("apply_order.txt"),
+        "schemas/public/tables/items.sql\nschemas/public/tables/items.sql\n",
+    )
+    .unwrap();
+
+    let result = apply_sql_directory(&path, destination.get_conn()).await;
+
+    assert!(matches!(
+        result,
+        Err(ElefantToolsError::InvalidSqlDirectory { .. })
+    ));
+}
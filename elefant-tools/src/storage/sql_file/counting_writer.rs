@@ -0,0 +1,62 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Wraps a writer and keeps track of how many bytes have been written to it, and how
+/// many of those haven't been flushed yet.
+pub(crate) struct CountingWriter<F: AsyncWrite + Unpin + Send + Sync> {
+    inner: F,
+    /// The total number of bytes written.
+    bytes_written: u64,
+    /// The number of bytes written since the last flush.
+    unflushed_bytes: usize,
+}
+
+impl<F: AsyncWrite + Unpin + Send + Sync> CountingWriter<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        Self {
+            inner,
+            bytes_written: 0,
+            unflushed_bytes: 0,
+        }
+    }
+
+    pub(crate) fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Flushes the writer if more than `max_buffer_bytes` have been written since
+    /// the last flush. Awaiting this is what applies backpressure to the data source,
+    /// as the source isn't polled for more data while the writer catches up.
+    pub(crate) async fn flush_if_above(&mut self, max_buffer_bytes: usize) -> io::Result<()> {
+        if self.unflushed_bytes >= max_buffer_bytes {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: AsyncWrite + Unpin + Send + Sync> AsyncWrite for CountingWriter<F> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let written = ready!(Pin::new(&mut self.inner).poll_write(cx, buf))?;
+        self.bytes_written += written as u64;
+        self.unflushed_bytes += written;
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(Pin::new(&mut self.inner).poll_flush(cx))?;
+        self.unflushed_bytes = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt::{Display, Formatter};
+
+static DATA_CHUNK_HEADER_PREFIX: &str = "-- elefant:data ";
+
+/// The first line of a chunk with a copy statement, followed by the data of the table.
+///
+/// This tells [super::apply_sql_file] to stream the lines after the copy statement to the
+/// database, instead of having to guess it from the copy statement.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct DataChunkHeader {
+    /// The quoted name of the table, as `schema.table`.
+    pub table: String,
+    pub format: String,
+}
+
+impl DataChunkHeader {
+    /// Parses the first line of a chunk. Returns `None` if the line is not a data chunk header.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.strip_prefix(DATA_CHUNK_HEADER_PREFIX)?.trim_end();
+
+        // The table is last, as quoted names can contain spaces.
+        let (format, table) = line.split_once(' ')?;
+
+        Some(DataChunkHeader {
+            format: format.strip_prefix("format=")?.to_string(),
+            table: table.strip_prefix("table=")?.to_string(),
+        })
+    }
+}
+
+impl Display for DataChunkHeader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}format={} table={}",
+            DATA_CHUNK_HEADER_PREFIX, self.format, self.table
+        )
+    }
+}
+
+/// Recognizes the copy statements of files written before [DataChunkHeader] was added,
+/// or edited by hand afterwards.
+pub(crate) fn is_copy_from_stdin_statement(chunk: &str) -> bool {
+    let chunk = chunk.trim().to_lowercase();
+
+    chunk.starts_with("copy ") && chunk.contains(" from stdin") && chunk.ends_with(';')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_round_trips_with_quoted_table_names() {
+        let header = DataChunkHeader {
+            table: r#"public."my table""#.to_string(),
+            format: "text".to_string(),
+        };
+
+        let line = header.to_string();
+        assert_eq!(line, r#"-- elefant:data format=text table=public."my table""#);
+        assert_eq!(DataChunkHeader::parse(&line), Some(header));
+    }
+
+    #[test]
+    fn ignores_other_comments() {
+        assert_eq!(DataChunkHeader::parse("-- copy public.t from stdin;"), None);
+        assert_eq!(DataChunkHeader::parse("-- elefant:data table=public.t"), None);
+    }
+
+    #[test]
+    fn recognizes_copy_statements_regardless_of_case() {
+        assert!(is_copy_from_stdin_statement(
+            "copy public.t (value) from stdin with (format text, header false);"
+        ));
+        assert!(is_copy_from_stdin_statement("COPY public.t (value) FROM STDIN;\n"));
+        assert!(!is_copy_from_stdin_statement("copy public.t (value) to stdout;"));
+        assert!(!is_copy_from_stdin_statement(
+            "create table copy_from_stdin_log (value text);"
+        ));
+    }
+}
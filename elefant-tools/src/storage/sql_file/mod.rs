@@ -1,40 +1,78 @@
 use crate::chunk_reader::{ChunkResult, StringChunkReader};
-use crate::helpers::IMPORT_PREFIX;
+use crate::parallel_runner::ParallelRunner;
+use crate::storage::postgres::connection_pool::ConnectionPool;
+use crate::storage::sql_file::counting_writer::CountingWriter;
+use crate::storage::sql_file::data_chunk_header::{is_copy_from_stdin_statement, DataChunkHeader};
+use crate::storage::sql_file::non_transactional::{
+    find_non_transactional_statement, has_multiple_statements, is_non_transactional_statement,
+    NON_TRANSACTIONAL_CHUNK_HEADER,
+};
+use crate::helpers::{StringExt, IMPORT_PREFIX};
 use crate::models::PostgresSchema;
 use crate::models::PostgresTable;
 use crate::models::SimplifiedDataType;
 use crate::quoting::{AttemptedKeywordUsage, IdentifierQuoter, Quotable};
-use crate::storage::data_format::DataFormat;
+use crate::storage::data_format::{CopyTextOptions, DataFormat};
 use crate::storage::table_data::TableData;
 use crate::storage::{BaseCopyTarget, CopyDestination};
-use crate::{AsyncCleanup, ColumnIdentity, CopyDestinationFactory, ParallelCopyDestinationNotAvailable, PostgresClientWrapper, Result, SequentialOrParallel, SupportedParallelism};
+use crate::{AsyncCleanup, ColumnIdentity, CopyDestinationFactory, ElefantToolsError, ParallelCopyDestinationNotAvailable, PostgresClientWrapper, Result, ResultExt, SequentialOrParallel, SupportedParallelism};
 use bytes::Bytes;
 use futures::{pin_mut, SinkExt, Stream, StreamExt};
 use itertools::Itertools;
 use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::vec;
 use tokio::fs::File;
 use tokio::io::{
     AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter,
 };
-use tracing::instrument;
+use tracing::{debug, info, instrument};
 use uuid::Uuid;
 
+mod counting_writer;
+mod data_chunk_header;
+mod non_transactional;
 #[cfg(test)]
 mod tests;
 
 /// Options that control how the SQL file is generated.
+#[derive(Debug, Clone)]
 pub struct SqlFileOptions {
     /// How many rows are inserted per insert statement.
     pub max_rows_per_insert: usize,
-    /// The string that separates chunks of commands in the file.
+    /// How big an insert statement can be in bytes before a new statement is started, regardless
+    /// of `max_rows_per_insert`. A single row larger than this is still written as one statement.
+    pub max_bytes_per_insert: usize,
+    /// The string that separates chunks of commands in the file. A random one is used by default.
+    /// Writing fails with [ElefantToolsError::ChunkSeparatorInData] if a row of copy data is the
+    /// separator line, for example because an earlier export using the same separator is stored
+    /// in the database.
     pub chunk_separator: String,
     /// How many DDL commands to generate per chunk at most.
     pub max_commands_per_chunk: usize,
+    /// Wrap each chunk of DDL commands in `begin;` and `commit;`, so the chunk is applied
+    /// atomically even when the file is imported with a tool that runs statements one by one, like
+    /// `psql`. Statements that cannot run inside a transaction are placed in their own unwrapped
+    /// chunk. Data chunks are never wrapped, as they consist of a single statement.
+    pub transaction_per_chunk: bool,
     /// How to generate statements for inserting data. See the specific option values
     /// in [SqlDataMode] for more information.
     pub data_mode: SqlDataMode,
+    /// How many bytes to write at most before waiting for the underlying writer
+    /// to flush. This bounds the memory used when the writer is slower than the source.
+    pub max_buffer_bytes: usize,
+    /// The delimiter and null marker used by the copy statements. Only used when
+    /// `data_mode` is [SqlDataMode::CopyStatements].
+    pub copy_options: CopyTextOptions,
+    /// Tables with more columns than this are written as copy statements, even when `data_mode`
+    /// is [SqlDataMode::InsertStatements], as insert statements for very wide tables are huge and
+    /// slow to generate.
+    pub insert_mode_column_limit: usize,
+    /// Create the directories the file is placed in if they don't exist, when the file is
+    /// created with [SqlFile::new_file].
+    pub create_parent_directories: bool,
 }
 
 /// How to generate statements for inserting data.
@@ -81,21 +119,42 @@ impl From<String> for SqlDataMode {
     }
 }
 
+impl SqlFileOptions {
+    /// The options the data should be received in. Insert statements are generated
+    /// by parsing the default text format, so custom options only apply to copy statements.
+    pub(crate) fn get_copy_text_options(&self) -> CopyTextOptions {
+        match self.data_mode {
+            SqlDataMode::CopyStatements => self.copy_options.clone(),
+            SqlDataMode::InsertStatements => CopyTextOptions::default(),
+        }
+    }
+}
+
 impl Default for SqlFileOptions {
     fn default() -> Self {
         Self {
             max_rows_per_insert: 1000,
+            max_bytes_per_insert: 16 * 1024 * 1024,
             chunk_separator: Uuid::new_v4().to_string(),
             max_commands_per_chunk: 10,
+            transaction_per_chunk: false,
             data_mode: SqlDataMode::InsertStatements,
+            max_buffer_bytes: 4 * 1024 * 1024,
+            copy_options: CopyTextOptions::default(),
+            insert_mode_column_limit: 200,
+            create_parent_directories: true,
         }
     }
 }
 
-/// A file to output sql to
+/// A file to output sql to.
+///
+/// Lines are always separated by `\n`, regardless of the platform, as the data of copy statements
+/// has to use the same line endings as Postgres, and a file mixing line endings would be applied
+/// differently depending on the tool used.
 pub struct SqlFile<F: AsyncWrite + Unpin + Send + Sync> {
     /// The underlying file, though it can be anything that implements `AsyncWrite`
-    file: F,
+    file: CountingWriter<F>,
     /// If 'nothing' has been written to the chunk yet.
     is_empty: bool,
     /// The options that control how the file is generated.
@@ -106,38 +165,77 @@ pub struct SqlFile<F: AsyncWrite + Unpin + Send + Sync> {
     current_command_count: usize,
     /// The string that separates chunks of commands in the file.
     chunk_separator: Vec<u8>,
+    /// How many bytes have been written for the data of each table.
+    table_bytes_written: Vec<TableBytesWritten>,
+    /// Reused for rendering a row before deciding which insert statement it goes into.
+    row_buffer: Vec<u8>,
+    /// If the current chunk was started with `begin;` and still needs a `commit;`.
+    is_in_transaction: bool,
+    /// The path of the file, if it was created from a path. Used to give io errors context.
+    path: Option<PathBuf>,
+}
+
+/// How many bytes were written to the file for the data of a table.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableBytesWritten {
+    pub schema_name: String,
+    pub table_name: String,
+    pub bytes: u64,
+    /// The size of the largest insert statement written for the table. Always 0 for copy statements,
+    /// as they are streamed to the database when imported.
+    pub largest_insert_bytes: u64,
 }
 
 impl SqlFile<BufWriter<File>> {
     /// Create a new `SqlFile` from a file path.
-    /// This automatically creates a new file and returns a `SqlFile` that writes to it.
+    /// This automatically creates a new file and returns a `SqlFile` that writes to it. The
+    /// directories the file is placed in are created as well, unless
+    /// [SqlFileOptions::create_parent_directories] is turned off.
     #[instrument(skip_all)]
     pub async fn new_file(
-        path: &str,
+        path: impl AsRef<Path>,
         identifier_quoter: Arc<IdentifierQuoter>,
         options: SqlFileOptions,
     ) -> Result<Self> {
-        let file = File::create(path).await?;
+        let path = path.as_ref();
+
+        if options.create_parent_directories {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .with_context_path("create directory", parent)?;
+            }
+        }
+
+        let file = File::create(path).await.with_context_path("create", path)?;
 
         let file = BufWriter::new(file);
 
-        SqlFile::new(file, identifier_quoter, options).await
+        let mut sql_file = SqlFile::new(file, identifier_quoter, options)
+            .await
+            .with_context_path("write to", path)?;
+        sql_file.path = Some(path.to_path_buf());
+
+        Ok(sql_file)
     }
 }
 
-static CHUNK_SEPARATOR_PREFIX: &str = "-- chunk-separator-";
+pub(crate) static CHUNK_SEPARATOR_PREFIX: &str = "-- chunk-separator-";
 
 impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
     /// Create a new `SqlFile` from a file-like object. This does not do any additional buffering
     /// so it's recommended to use a `BufWriter` or similar.
     pub async fn new(
-        mut file: F,
+        file: F,
         identifier_quoter: Arc<IdentifierQuoter>,
         options: SqlFileOptions,
     ) -> Result<Self> {
+        options.copy_options.validate()?;
+
         let chunk_separator =
             format!("{}{} --", CHUNK_SEPARATOR_PREFIX, options.chunk_separator).into_bytes();
 
+        let mut file = CountingWriter::new(file);
         file.write_all(&chunk_separator).await?;
         file.write_all(IMPORT_PREFIX.as_bytes()).await?;
 
@@ -148,13 +246,60 @@ impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
             quoter: identifier_quoter,
             current_command_count: 0,
             chunk_separator,
+            table_bytes_written: Vec::new(),
+            row_buffer: Vec::new(),
+            is_in_transaction: false,
+            path: None,
         })
     }
+
+    /// The total number of bytes written to the file so far.
+    pub fn get_bytes_written(&self) -> u64 {
+        self.file.bytes_written()
+    }
+
+    /// How many bytes have been written for the data of each table so far.
+    pub fn get_table_bytes_written(&self) -> &[TableBytesWritten] {
+        &self.table_bytes_written
+    }
+
+    /// The size of the largest insert statement written so far.
+    pub fn get_largest_insert_bytes(&self) -> u64 {
+        self.table_bytes_written
+            .iter()
+            .map(|t| t.largest_insert_bytes)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn get_copy_text_options(&self) -> CopyTextOptions {
+        self.options.get_copy_text_options()
+    }
+
+    /// Attaches the path of the file to io errors, if the file was created from a path.
+    fn with_path_context<T>(&self, result: Result<T>) -> Result<T> {
+        match &self.path {
+            Some(path) => result.with_context_path("write to", path),
+            None => result,
+        }
+    }
+
+    /// Ends the transaction of the current chunk, if it was started with `begin;`.
+    async fn commit_chunk(&mut self) -> Result<()> {
+        if self.is_in_transaction {
+            self.file.write_all(b"\n\ncommit;").await?;
+            self.is_in_transaction = false;
+        }
+
+        Ok(())
+    }
 }
 
 impl<F: AsyncWrite + Unpin + Send + Sync> BaseCopyTarget for SqlFile<F> {
     async fn supported_data_format(&self) -> Result<Vec<DataFormat>> {
-        Ok(vec![DataFormat::Text])
+        Ok(vec![DataFormat::Text {
+            options: self.get_copy_text_options(),
+        }])
     }
 }
 
@@ -185,30 +330,96 @@ impl<F: AsyncWrite + Unpin + Send + Sync> CopyDestination for &mut SqlFile<F> {
         table: &PostgresTable,
         data: TableData<S, C>,
     ) -> Result<()> {
-        let file = &mut self.file;
-        if self.current_command_count > 0 {
-            file.write_all(b"\n").await?;
-            self.current_command_count = 0;
-        }
+        let this = &mut **self;
 
-        let stream = data.data;
+        let result = data.apply_then_cleanup(|stream| async move {
+            this.commit_chunk().await?;
 
-        pin_mut!(stream);
+            let file = &mut this.file;
+            if this.current_command_count > 0 {
+                file.write_all(b"\n").await?;
+                this.current_command_count = 0;
+            }
 
-        if self.options.data_mode == SqlDataMode::InsertStatements {
-            self.write_data_stream_to_insert_statements(&mut stream, schema, table)
-                .await?;
-        } else {
-            self.write_data_stream_to_copy_statements(&mut stream, schema, table)
-                .await?;
-        }
+            let bytes_before = this.file.bytes_written();
+
+            pin_mut!(stream);
+
+            let column_count = table.get_writable_columns().count();
+            let use_insert_statements = this.options.data_mode == SqlDataMode::InsertStatements
+                && if column_count > this.options.insert_mode_column_limit {
+                    info!(
+                        "Writing the data of table {}.{} as copy statements, as it has {} columns, which is more than the limit of {} for insert statements",
+                        schema.name, table.name, column_count, this.options.insert_mode_column_limit
+                    );
+                    false
+                } else {
+                    true
+                };
+
+            let largest_insert_bytes = if use_insert_statements {
+                this.write_data_stream_to_insert_statements(&mut stream, schema, table)
+                    .await?
+            } else {
+                this.write_data_stream_to_copy_statements(&mut stream, schema, table)
+                    .await?;
+                0
+            };
+
+            let bytes = this.file.bytes_written() - bytes_before;
+            debug!("Wrote {} bytes for table {}.{}", bytes, schema.name, table.name);
+            this.table_bytes_written.push(TableBytesWritten {
+                schema_name: schema.name.clone(),
+                table_name: table.name.clone(),
+                bytes,
+                largest_insert_bytes,
+            });
+
+            Ok(())
+        })
+        .await;
 
-        Ok(())
+        self.with_path_context(result)
     }
 
     #[instrument(skip_all)]
     async fn apply_transactional_statement(&mut self, statement: &str) -> Result<()> {
-        if self.current_command_count % self.options.max_commands_per_chunk == 0 {
+        let result = self.write_transactional_statement(statement).await;
+        self.with_path_context(result)
+    }
+
+    #[instrument(skip_all)]
+    async fn apply_non_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        let result = self.write_non_transactional_statement(statement).await;
+        self.with_path_context(result)
+    }
+
+    async fn begin_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn commit_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_identifier_quoter(&self) -> Arc<IdentifierQuoter> {
+        self.quoter.clone()
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        let result = self.finish_file().await;
+        self.with_path_context(result)
+    }
+}
+
+impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
+    async fn write_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        if self
+            .current_command_count
+            .is_multiple_of(self.options.max_commands_per_chunk)
+        {
+            self.commit_chunk().await?;
+
             if !self.is_empty {
                 self.file.write_all(b"\n\n").await?;
             }
@@ -216,6 +427,12 @@ impl<F: AsyncWrite + Unpin + Send + Sync> CopyDestination for &mut SqlFile<F> {
             self.file.write_all(&self.chunk_separator).await?;
             self.file.write_all(b"\n").await?;
             self.is_empty = true;
+
+            if self.options.transaction_per_chunk {
+                self.file.write_all(b"begin;").await?;
+                self.is_empty = false;
+                self.is_in_transaction = true;
+            }
         }
 
         if self.is_empty {
@@ -231,31 +448,46 @@ impl<F: AsyncWrite + Unpin + Send + Sync> CopyDestination for &mut SqlFile<F> {
         Ok(())
     }
 
-    #[instrument(skip_all)]
-    async fn apply_non_transactional_statement(&mut self, statement: &str) -> Result<()> {
-        self.apply_transactional_statement(statement).await
-    }
+    async fn write_non_transactional_statement(&mut self, statement: &str) -> Result<()> {
+        // Statements such as `create index concurrently` fail when they are sent together with
+        // other statements, so they always get a chunk of their own.
+        let must_run_alone = is_non_transactional_statement(statement);
 
-    async fn begin_transaction(&mut self) -> Result<()> {
-        Ok(())
-    }
+        if !self.options.transaction_per_chunk && !must_run_alone {
+            return self.write_transactional_statement(statement).await;
+        }
 
-    async fn commit_transaction(&mut self) -> Result<()> {
-        Ok(())
-    }
+        self.commit_chunk().await?;
 
-    fn get_identifier_quoter(&self) -> Arc<IdentifierQuoter> {
-        self.quoter.clone()
+        if !self.is_empty {
+            self.file.write_all(b"\n\n").await?;
+        }
+
+        self.file.write_all(&self.chunk_separator).await?;
+        self.file.write_all(b"\n").await?;
+        if must_run_alone {
+            self.file
+                .write_all(NON_TRANSACTIONAL_CHUNK_HEADER.as_bytes())
+                .await?;
+            self.file.write_all(b"\n").await?;
+        }
+        self.file.write_all(statement.as_bytes()).await?;
+        self.is_empty = false;
+
+        // Make sure the next transactional statement starts a new chunk.
+        self.current_command_count = 0;
+
+        Ok(())
     }
 
-    async fn finish(&mut self) -> Result<()> {
+    async fn finish_file(&mut self) -> Result<()> {
+        self.commit_chunk().await?;
         self.file.flush().await?;
         Ok(())
     }
-}
 
-impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
-    /// Writes the data stream to the file as insert statements.
+    /// Writes the data stream to the file as insert statements, and returns the size of the
+    /// largest statement written.
     #[instrument(skip_all)]
     async fn write_data_stream_to_insert_statements<
         S: Stream<Item = Result<Bytes>> + Send + Unpin,
@@ -264,14 +496,39 @@ impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
         stream: &mut S,
         schema: &PostgresSchema,
         table: &PostgresTable,
-    ) -> Result<()> {
+    ) -> Result<u64> {
         let file = &mut self.file;
+        let row = &mut self.row_buffer;
+        let mut statement_start = 0;
+        let mut largest_insert_bytes = 0;
 
         let column_types = table
             .get_writable_columns()
             .map(|c| c.get_simplified_data_type())
             .collect_vec();
 
+        // The start of the insert statements is the same for every statement of the table, so it
+        // is only rendered once, which matters for tables with many columns.
+        let mut insert_prefix = format!(
+            "insert into {}.{} (",
+            schema
+                .name
+                .quote(&self.quoter, AttemptedKeywordUsage::TypeOrFunctionName),
+            table
+                .name
+                .quote(&self.quoter, AttemptedKeywordUsage::TypeOrFunctionName)
+        );
+        insert_prefix.push_join(", ", table.get_writable_columns().map(|c| &c.name));
+        insert_prefix.push(')');
+        if table
+            .columns
+            .iter()
+            .any(|c| c.identity == Some(ColumnIdentity::GeneratedAlways))
+        {
+            insert_prefix.push_str(" overriding system value");
+        }
+        insert_prefix.push_str(" values\n");
+
         let mut count = 0;
         while let Some(bytes) = stream.next().await {
             if count == 0 {
@@ -281,52 +538,36 @@ impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
             }
             match bytes {
                 Ok(bytes) => {
-                    if count % self.options.max_rows_per_insert == 0 {
+                    row.clear();
+                    write_row(row, &column_types, &bytes);
+
+                    // The statement grows by the `,\n` before the row and the `;` ending it.
+                    let statement_bytes = file.bytes_written() - statement_start + 3;
+                    let is_full = count > 0
+                        && statement_bytes + row.len() as u64
+                            > self.options.max_bytes_per_insert as u64;
+
+                    if count % self.options.max_rows_per_insert == 0 || is_full {
                         if count > 0 {
-                            file.write_all(b";\n").await?;
+                            file.write_all(b";").await?;
+                            largest_insert_bytes = largest_insert_bytes
+                                .max(file.bytes_written() - statement_start);
+                            file.write_all(b"\n").await?;
                             file.write_all(&self.chunk_separator).await?;
                             file.write_all(b"\n").await?;
                         }
 
-                        file.write_all(b"insert into ").await?;
-                        file.write_all(
-                            schema
-                                .name
-                                .quote(&self.quoter, AttemptedKeywordUsage::TypeOrFunctionName)
-                                .as_bytes(),
-                        )
-                        .await?;
-                        file.write_all(b".").await?;
-                        file.write_all(
-                            table
-                                .name
-                                .quote(&self.quoter, AttemptedKeywordUsage::TypeOrFunctionName)
-                                .as_bytes(),
-                        )
-                        .await?;
-                        file.write_all(b" (").await?;
-                        for (index, column) in table.get_writable_columns().enumerate() {
-                            if index != 0 {
-                                file.write_all(b", ").await?;
-                            }
-                            file.write_all(column.name.as_bytes()).await?;
-                        }
-                        file.write_all(b")").await?;
-
-                        if table.columns.iter().any(|c| c.identity == Some(ColumnIdentity::GeneratedAlways)) {
-                            file.write_all(b" overriding system value").await?;
-                        }
-
-                        file.write_all(b" values").await?;
+                        statement_start = file.bytes_written();
 
-                        file.write_all(b"\n").await?;
+                        file.write_all(insert_prefix.as_bytes()).await?;
                         count = 0;
                     } else {
                         file.write_all(b",\n").await?;
                     }
                     count += 1;
 
-                    write_row(file, &column_types, bytes).await?;
+                    file.write_all(row).await?;
+                    file.flush_if_above(self.options.max_buffer_bytes).await?;
                 }
                 Err(e) => {
                     return Err(e);
@@ -335,12 +576,15 @@ impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
         }
 
         if count > 0 {
-            file.write_all(b";\n").await?;
+            file.write_all(b";").await?;
+            largest_insert_bytes =
+                largest_insert_bytes.max(file.bytes_written() - statement_start);
+            file.write_all(b"\n").await?;
         }
 
         file.flush().await?;
 
-        Ok(())
+        Ok(largest_insert_bytes)
     }
 
     /// Writes the data stream to the file as copy statements.
@@ -353,6 +597,7 @@ impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
         schema: &PostgresSchema,
         table: &PostgresTable,
     ) -> Result<()> {
+        let copy_text_options = self.get_copy_text_options();
         let file = &mut self.file;
 
         let mut count = 0;
@@ -362,8 +607,26 @@ impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
                 file.write_all(&self.chunk_separator).await?;
                 file.write_all(b"\n").await?;
 
-                let copy_command =
-                    table.get_copy_in_command(schema, &DataFormat::Text, &self.quoter);
+                let header = DataChunkHeader {
+                    table: format!(
+                        "{}.{}",
+                        schema.name.quote(&self.quoter, AttemptedKeywordUsage::ColumnName),
+                        table.name.quote(&self.quoter, AttemptedKeywordUsage::ColumnName)
+                    ),
+                    format: "text".to_string(),
+                };
+                file.write_all(header.to_string().as_bytes()).await?;
+                file.write_all(b"\n").await?;
+
+                // The data is received with the default options when writing insert statements,
+                // which is the case for the tables exceeding `insert_mode_column_limit`.
+                let copy_command = table.get_copy_in_command(
+                    schema,
+                    &DataFormat::Text {
+                        options: copy_text_options.clone(),
+                    },
+                    &self.quoter,
+                );
                 file.write_all(copy_command.as_bytes()).await?;
 
                 file.write_all(b"\n").await?;
@@ -372,7 +635,24 @@ impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
             }
             match bytes {
                 Ok(bytes) => {
+                    if contains_separator_line(&bytes, &self.chunk_separator) {
+                        return Err(ElefantToolsError::ChunkSeparatorInData {
+                            schema_name: schema.name.clone(),
+                            table_name: table.name.clone(),
+                        });
+                    }
+
+                    // Postgres escapes carriage returns in the text format, and rejects raw ones
+                    // when the file is applied, as they are indistinguishable from `\r\n` line endings.
+                    if bytes.contains(&b'\r') {
+                        return Err(ElefantToolsError::CarriageReturnInCopyData {
+                            schema_name: schema.name.clone(),
+                            table_name: table.name.clone(),
+                        });
+                    }
+
                     file.write_all(&bytes).await?;
+                    file.flush_if_above(self.options.max_buffer_bytes).await?;
                     count += 1;
                 }
                 Err(e) => {
@@ -390,109 +670,95 @@ impl<F: AsyncWrite + Unpin + Send + Sync> SqlFile<F> {
     }
 }
 
-/// Writes a single insert row
-async fn write_row<F: AsyncWrite + Unpin + Send + Sync>(
-    file: &mut F,
-    column_types: &[SimplifiedDataType],
-    bytes: Bytes,
-) -> Result<()> {
-    let without_line_break = bytes.slice(0..bytes.len() - 1);
+/// Checks if a line of the copy data is the chunk separator, which would end the data chunk early
+/// when the file is applied. Insert statements don't need this, as line breaks in their values are
+/// escaped, so a line always starts with `(` or `insert`.
+fn contains_separator_line(data: &[u8], chunk_separator: &[u8]) -> bool {
+    data.split(|b| *b == b'\n').any(|line| line == chunk_separator)
+}
+
+/// Writes a single insert row. The row is rendered into memory, as writing each column to the
+/// file separately is slow for tables with many columns.
+fn write_row(row: &mut Vec<u8>, column_types: &[SimplifiedDataType], bytes: &[u8]) {
+    let without_line_break = &bytes[..bytes.len() - 1];
     let column_bytes = without_line_break.split(|b| *b == b'\t');
 
     let cols = column_bytes.zip(column_types.iter());
-    file.write_all(b"(").await?;
+    row.push(b'(');
     for (index, (bytes, col_data_type)) in cols.enumerate() {
         if index != 0 {
-            file.write_all(b", ").await?;
+            row.extend_from_slice(b", ");
         }
 
-        write_column(file, bytes, col_data_type).await?;
+        write_column(row, bytes, col_data_type);
     }
-    file.write_all(b")").await?;
-
-    Ok(())
+    row.push(b')');
 }
 
 /// Writes a single column in an insert row
-async fn write_column<F: AsyncWrite + Unpin + Send + Sync>(
-    content: &mut F,
-    bytes: &[u8],
-    col_data_type: &SimplifiedDataType,
-) -> Result<()> {
+fn write_column(content: &mut Vec<u8>, bytes: &[u8], col_data_type: &SimplifiedDataType) {
     if bytes == [b'\\', b'N'] {
-        content.write_all(b"null").await?;
-        return Ok(());
+        content.extend_from_slice(b"null");
+        return;
     }
 
     match col_data_type {
-        SimplifiedDataType::Number => {
-            write_number_column(content, bytes).await?;
-        }
-        SimplifiedDataType::Text => {
-            write_text_column(content, bytes).await?;
-        }
-        SimplifiedDataType::Bool => {
-            write_bool_column(content, bytes).await?;
-        }
+        SimplifiedDataType::Number => write_number_column(content, bytes),
+        SimplifiedDataType::Text => write_text_column(content, bytes),
+        SimplifiedDataType::Bool => write_bool_column(content, bytes),
     }
-
-    Ok(())
 }
 
 /// Writes a `bool` column
-async fn write_bool_column<F: AsyncWrite + Unpin + Send + Sync>(
-    content: &mut F,
-    bytes: &[u8],
-) -> Result<()> {
-    let value = bytes[0] == b't';
-    content.write_all(format!("{}", value).as_bytes()).await?;
-    Ok(())
+fn write_bool_column(content: &mut Vec<u8>, bytes: &[u8]) {
+    if bytes[0] == b't' {
+        content.extend_from_slice(b"true");
+    } else {
+        content.extend_from_slice(b"false");
+    }
 }
 
 /// Writes a generic `text` column
-async fn write_text_column<F: AsyncWrite + Unpin + Send + Sync>(
-    content: &mut F,
-    bytes: &[u8],
-) -> Result<()> {
-    content.write_all(b"E'").await?;
-
-    if bytes.contains(&b'\'') {
-        let s = std::str::from_utf8(bytes).unwrap();
-        let s = s.replace('\'', "''");
-        content.write_all(s.as_bytes()).await?;
-    } else {
-        content.write_all(bytes).await?;
+///
+/// The value comes from `copy ... to stdout` in text format, where backslashes, tabs, newlines
+/// and other control characters are already escaped with a backslash. Those escapes mean the
+/// same in an `E''` string, so only the single quotes have to be doubled.
+fn write_text_column(content: &mut Vec<u8>, bytes: &[u8]) {
+    content.extend_from_slice(b"E'");
+
+    for part in bytes.split_inclusive(|b| *b == b'\'') {
+        content.extend_from_slice(part);
+        if part.ends_with(b"'") {
+            content.push(b'\'');
+        }
     }
-    content.write_all(b"'").await?;
 
-    Ok(())
+    content.push(b'\'');
 }
 
 /// Writes a `number` column
-async fn write_number_column<F: AsyncWrite + Unpin + Send + Sync>(
-    content: &mut F,
-    bytes: &[u8],
-) -> Result<()> {
-    match bytes[..] {
-        [b'N', b'a', b'N']
-        | [b'I', b'n', b'f', b'i', b'n', b'i', b't', b'y']
-        | [b'-', b'I', b'n', b'f', b'i', b'n', b'i', b't', b'y'] => {
-            content.write_all(b"'").await?;
-            content.write_all(bytes).await?;
-            content.write_all(b"'").await?;
+fn write_number_column(content: &mut Vec<u8>, bytes: &[u8]) {
+    match bytes {
+        b"NaN" | b"Infinity" | b"-Infinity" => {
+            content.push(b'\'');
+            content.extend_from_slice(bytes);
+            content.push(b'\'');
         }
         _ => {
-            content.write_all(bytes).await?;
+            content.extend_from_slice(bytes);
         }
     }
-
-    Ok(())
 }
 
 /// Applies the provided sql file context to the provided connection.
 /// If the sql file was generated by using the [SqlFile] struct,
 /// this function is quite memory efficient. If not the entire file
 /// will be read into memory before being executed in a single transaction.
+///
+/// Copy statements are streamed to the database line by line, but every other statement has to be
+/// sent to the database in one piece, so it is read into memory first. The memory used is therefore
+/// bounded by the largest statement in the file, which [SqlFileOptions::max_bytes_per_insert] limits
+/// for insert statements.
 #[instrument(skip_all)]
 pub async fn apply_sql_file<F: AsyncBufRead + Unpin + Send + Sync>(
     content: &mut F,
@@ -517,10 +783,9 @@ pub async fn apply_sql_file<F: AsyncBufRead + Unpin + Send + Sync>(
                 .await?;
             match read {
                 ChunkResult::Chunk(_) => {
-                    if sql_chunk.starts_with("copy ")
-                        && sql_chunk.ends_with(" from stdin with (format text, header false);\n")
-                    {
-                        let copy_in_stream = target_connection.copy_in::<Bytes>(&sql_chunk).await?;
+                    if let Some(copy_statement) = get_copy_statement(&sql_chunk) {
+                        let copy_in_stream =
+                            target_connection.copy_in::<Bytes>(&copy_statement).await?;
 
                         pin_mut!(copy_in_stream);
 
@@ -540,12 +805,12 @@ pub async fn apply_sql_file<F: AsyncBufRead + Unpin + Send + Sync>(
 
                         copy_in_stream.close().await?;
                     } else {
-                        target_connection.execute_non_query(&sql_chunk).await?;
+                        execute_chunk(&sql_chunk, target_connection).await?;
                     }
                 }
                 ChunkResult::End(read) => {
                     if read > 0 {
-                        target_connection.execute_non_query(&sql_chunk).await?;
+                        execute_chunk(&sql_chunk, target_connection).await?;
                     }
                     break;
                 }
@@ -559,6 +824,207 @@ pub async fn apply_sql_file<F: AsyncBufRead + Unpin + Send + Sync>(
     Ok(())
 }
 
+/// How many bytes of copy data are sent in one `copy` command by [apply_sql_file_parallel]. The
+/// data of larger tables is split over several commands, so it can be spread over the connections
+/// without reading the whole table into memory.
+const PARALLEL_COPY_BATCH_BYTES: usize = 8 * 1024 * 1024;
+
+/// A chunk of data that can be applied on any of the connections of [apply_sql_file_parallel].
+enum DataChunk {
+    Copy { statement: String, data: Vec<Bytes> },
+    Insert(String),
+}
+
+/// Applies the provided sql file like [apply_sql_file], but spreads the data of the tables over
+/// up to `max_parallelism` connections.
+///
+/// The schema chunks are still executed one at a time on `target_connection`, in the order they are
+/// in the file. Before a schema chunk is executed, all the data read so far has to be applied, so
+/// statements such as index and foreign key creation still only run after the data is in place.
+///
+/// The memory used is bounded by `max_parallelism` times the size of the largest insert statement,
+/// or [PARALLEL_COPY_BATCH_BYTES] for copy statements.
+#[instrument(skip_all)]
+pub async fn apply_sql_file_parallel<F: AsyncBufRead + Unpin + Send + Sync>(
+    content: &mut F,
+    target_connection: &PostgresClientWrapper,
+    max_parallelism: NonZeroUsize,
+) -> Result<()> {
+    if max_parallelism.get() == 1 {
+        return apply_sql_file(content, target_connection).await;
+    }
+
+    let mut sql_chunk = String::with_capacity(10000);
+
+    let read = content.read_line(&mut sql_chunk).await?;
+
+    if read == 0 {
+        return Ok(());
+    }
+
+    if !sql_chunk.starts_with(CHUNK_SEPARATOR_PREFIX) {
+        content.read_to_string(&mut sql_chunk).await?;
+        return target_connection.execute_non_query(&sql_chunk).await;
+    }
+
+    let separator = sql_chunk.clone();
+    let connection_pool = ConnectionPool::new(max_parallelism);
+    let mut parallel_runner = ParallelRunner::new(max_parallelism);
+
+    loop {
+        sql_chunk.clear();
+
+        let read = content
+            .read_lines_until_separator_line(&separator, &mut sql_chunk)
+            .await?;
+        let is_end = match read {
+            ChunkResult::Chunk(_) => false,
+            ChunkResult::End(0) => break,
+            ChunkResult::End(_) => true,
+        };
+
+        if let Some(statement) = get_copy_statement(&sql_chunk).filter(|_| !is_end) {
+            let mut data = Vec::new();
+            let mut batch_bytes = 0;
+
+            loop {
+                sql_chunk.clear();
+                let read = content.read_line(&mut sql_chunk).await?;
+                let is_done = read == 0 || sql_chunk.starts_with("\\.");
+                if !is_done {
+                    batch_bytes += read;
+                    data.push(Bytes::from(sql_chunk.clone()));
+                }
+
+                if !data.is_empty() && (is_done || batch_bytes >= PARALLEL_COPY_BATCH_BYTES) {
+                    let chunk = DataChunk::Copy {
+                        statement: statement.clone(),
+                        data: std::mem::take(&mut data),
+                    };
+                    batch_bytes = 0;
+                    parallel_runner
+                        .enqueue(apply_data_chunk(chunk, &connection_pool, target_connection))
+                        .await?;
+                }
+
+                if is_done {
+                    break;
+                }
+            }
+        } else if is_insert_statement(&sql_chunk) {
+            let chunk = DataChunk::Insert(sql_chunk.clone());
+            parallel_runner
+                .enqueue(apply_data_chunk(chunk, &connection_pool, target_connection))
+                .await?;
+        } else {
+            std::mem::replace(&mut parallel_runner, ParallelRunner::new(max_parallelism))
+                .run_remaining()
+                .await?;
+            execute_chunk(&sql_chunk, target_connection).await?;
+        }
+
+        if is_end {
+            break;
+        }
+    }
+
+    parallel_runner.run_remaining().await
+}
+
+/// Checks if the chunk holds the rows of a table written as an insert statement by [SqlFile].
+fn is_insert_statement(sql_chunk: &str) -> bool {
+    sql_chunk.trim_start().starts_with("insert into ")
+}
+
+/// Applies a chunk of data on a connection from the pool, opening a new connection if none are idle.
+async fn apply_data_chunk(
+    chunk: DataChunk,
+    connection_pool: &ConnectionPool,
+    target_connection: &PostgresClientWrapper,
+) -> Result<()> {
+    let connection = connection_pool
+        .get_connection(|| async {
+            let connection = target_connection.create_another_connection().await?;
+            connection.execute_non_query(IMPORT_PREFIX).await?;
+            Ok(connection)
+        })
+        .await?;
+
+    // The connection is only released on success, as it might be stuck in the
+    // middle of a copy otherwise.
+    match chunk {
+        DataChunk::Copy { statement, data } => {
+            let sink = connection.copy_in::<Bytes>(&statement).await?;
+            pin_mut!(sink);
+
+            for line in data {
+                sink.feed(line).await.with_query(&statement)?;
+            }
+
+            sink.close().await.with_query(&statement)?;
+        }
+        DataChunk::Insert(statement) => {
+            connection.execute_non_query(&statement).await?;
+        }
+    }
+
+    connection_pool.release_connection(connection).await;
+
+    Ok(())
+}
+
+/// Gets the copy statement of a chunk that is followed by the data of a table. Files written by
+/// [SqlFile] marks these chunks with a [DataChunkHeader], older files are recognized by the copy
+/// statement itself.
+fn get_copy_statement(sql_chunk: &str) -> Option<String> {
+    let (first_line, rest) = sql_chunk.split_once('\n').unwrap_or((sql_chunk, ""));
+
+    if let Some(header) = DataChunkHeader::parse(first_line) {
+        debug!("Copying {} data into {}", header.format, header.table);
+        Some(rest.to_string())
+    } else if is_copy_from_stdin_statement(sql_chunk) {
+        Some(sql_chunk.to_string())
+    } else {
+        None
+    }
+}
+
+/// Executes a single chunk of statements. Chunks written with [SqlFileOptions::transaction_per_chunk]
+/// carry their own `begin;` and `commit;`, so if such a chunk fails the transaction it opened is
+/// rolled back, instead of leaving the connection in an aborted transaction.
+///
+/// Chunks starting with [NON_TRANSACTIONAL_CHUNK_HEADER] are sent as a single statement, which
+/// Postgres runs in autocommit mode. Statements that cannot run in a transaction block are
+/// rejected if they are part of a chunk with other statements, as Postgres runs those chunks in
+/// an implicit transaction.
+async fn execute_chunk(sql_chunk: &str, target_connection: &PostgresClientWrapper) -> Result<()> {
+    if let Some(statement) = sql_chunk.strip_prefix(NON_TRANSACTIONAL_CHUNK_HEADER) {
+        if has_multiple_statements(statement) {
+            return Err(ElefantToolsError::NonTransactionalStatementInChunk(
+                statement.trim().to_string(),
+            ));
+        }
+
+        return target_connection.execute_non_query(statement).await;
+    }
+
+    if let Some(statement) = find_non_transactional_statement(sql_chunk) {
+        if has_multiple_statements(sql_chunk) {
+            return Err(ElefantToolsError::NonTransactionalStatementInChunk(
+                statement.to_string(),
+            ));
+        }
+    }
+
+    let result = target_connection.execute_non_query(sql_chunk).await;
+
+    if result.is_err() && sql_chunk.starts_with("begin;") {
+        target_connection.execute_non_query("rollback;").await?;
+    }
+
+    result
+}
+
 /// Applies the provided sql string to the provided connection. See [apply_sql_file] for more information.
 pub async fn apply_sql_string(
     file_content: &str,
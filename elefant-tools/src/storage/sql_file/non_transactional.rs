@@ -0,0 +1,92 @@
+/// The first line of a chunk holding a statement that cannot run inside a transaction block.
+///
+/// This tells [super::apply_sql_file] to run the chunk on its own in autocommit mode.
+pub(crate) static NON_TRANSACTIONAL_CHUNK_HEADER: &str = "-- elefant:non-transactional";
+
+/// The start of the statements Postgres refuses to run inside a transaction block. This includes
+/// multi statement queries, which Postgres runs in an implicit transaction.
+static NON_TRANSACTIONAL_COMMANDS: &[&str] = &[
+    "vacuum",
+    "create index concurrently ",
+    "create unique index concurrently ",
+    "drop index concurrently ",
+    "create database ",
+    "drop database ",
+    "create tablespace ",
+    "drop tablespace ",
+    "alter system ",
+];
+
+/// Checks if the statement cannot run inside a transaction block.
+pub(crate) fn is_non_transactional_statement(statement: &str) -> bool {
+    let statement = statement
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    NON_TRANSACTIONAL_COMMANDS
+        .iter()
+        .any(|command| statement.starts_with(command))
+        || (statement.starts_with("reindex ") && statement.contains(" concurrently "))
+}
+
+/// Finds the first line of the chunk that starts a statement which cannot run inside a
+/// transaction block.
+pub(crate) fn find_non_transactional_statement(chunk: &str) -> Option<&str> {
+    chunk
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| is_non_transactional_statement(line))
+}
+
+/// Checks if the chunk holds more than a single statement, ignoring the semicolon ending it.
+pub(crate) fn has_multiple_statements(chunk: &str) -> bool {
+    chunk
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with("--"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end_matches(';')
+        .contains(';')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_non_transactional_statements() {
+        assert!(is_non_transactional_statement("vacuum;"));
+        assert!(is_non_transactional_statement("VACUUM (analyze) public.t;"));
+        assert!(is_non_transactional_statement(
+            "create  index\nconcurrently my_idx on public.t (id);"
+        ));
+        assert!(is_non_transactional_statement(
+            "reindex table concurrently public.t;"
+        ));
+        assert!(!is_non_transactional_statement(
+            "create index my_idx on public.t (id);"
+        ));
+        assert!(!is_non_transactional_statement("reindex table public.t;"));
+        assert!(!is_non_transactional_statement(
+            "create table vacuum_log(id int);"
+        ));
+    }
+
+    #[test]
+    fn finds_non_transactional_statements_in_chunks() {
+        let chunk = "create table t(id int);\n\nvacuum t;";
+        assert_eq!(find_non_transactional_statement(chunk), Some("vacuum t;"));
+        assert!(has_multiple_statements(chunk));
+
+        assert_eq!(
+            find_non_transactional_statement("create table t(id int);"),
+            None
+        );
+        assert!(!has_multiple_statements(
+            "-- a comment; with a semicolon\nvacuum t;\n"
+        ));
+    }
+}
@@ -3,11 +3,26 @@ use crate::schema_reader::tests::introspect_schema;
 use crate::storage::sql_file::*;
 use crate::storage::tests::validate_copy_state;
 use crate::test_helpers::*;
-use crate::{default, storage, PostgresInstanceStorage};
+use crate::{
+    default, storage, AppendStorageParameter, ElefantToolsError, PostgresColumn,
+    PostgresConstraint, PostgresInstanceStorage,
+};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::future::Future;
 use indoc::indoc;
 use tokio::test;
+use tokio_postgres::error::SqlState;
 
 async fn export_to_string(source: &TestHelper, sql_file_options: SqlFileOptions) -> String {
+    export_to_string_with_options(source, sql_file_options, CopyDataOptions::default()).await
+}
+
+async fn export_to_string_with_options(
+    source: &TestHelper,
+    sql_file_options: SqlFileOptions,
+    copy_data_options: CopyDataOptions,
+) -> String {
     let mut result_file = Vec::<u8>::new();
 
     {
@@ -29,7 +44,7 @@ async fn export_to_string(source: &TestHelper, sql_file_options: SqlFileOptions)
             .await
             .unwrap();
 
-        copy_data(&source, &mut sql_file, CopyDataOptions::default())
+        copy_data(&source, &mut sql_file, copy_data_options)
             .await
             .unwrap();
     }
@@ -72,22 +87,6 @@ async fn exports_to_fake_file_15() {
                 name text[] not null
             );
             
-            create table public.tree_node (
-                id int4 not null,
-                field_id int4 not null,
-                name text not null,
-                parent_id int4,
-                constraint tree_node_pkey primary key (id)
-            );
-            
-            create table public.pets (
-                id int4 not null,
-                name text not null,
-                constraint pets_pkey primary key (id),
-                constraint pets_name_check check ((length(name) > 1))
-            );
-
-            -- chunk-separator-test_chunk_separator --
             create table public.ext_test_table (
                 id int4 not null,
                 name text not null,
@@ -100,6 +99,7 @@ async fn exports_to_fake_file_15() {
                 constraint field_pkey primary key (id)
             );
 
+            -- chunk-separator-test_chunk_separator --
             create table public.my_partitioned_table (
                 value int4 not null
             ) partition by range (value);
@@ -113,18 +113,20 @@ async fn exports_to_fake_file_15() {
                 constraint people_age_check check ((age > 0))
             );
 
-            create table public.my_partitioned_table_2 partition of my_partitioned_table FOR VALUES FROM (10) TO (20);
-            
-            -- chunk-separator-test_chunk_separator --
-            create table public.my_partitioned_table_1 partition of my_partitioned_table FOR VALUES FROM (1) TO (10);
-
-            create table public.dogs (
+            create table public.pets (
                 id int4 not null,
                 name text not null,
-                breed text not null,
-                constraint dogs_breed_check check ((length(breed) > 1)),
+                constraint pets_pkey primary key (id),
                 constraint pets_name_check check ((length(name) > 1))
-            ) inherits (pets);
+            );
+
+            create table public.tree_node (
+                id int4 not null,
+                field_id int4 not null,
+                name text not null,
+                parent_id int4,
+                constraint tree_node_pkey primary key (id)
+            );
 
             create table public.cats (
                 id int4 not null,
@@ -132,6 +134,19 @@ async fn exports_to_fake_file_15() {
                 color text not null,
                 constraint pets_name_check check ((length(name) > 1))
             ) inherits (pets);
+            
+            -- chunk-separator-test_chunk_separator --
+            create table public.dogs (
+                id int4 not null,
+                name text not null,
+                breed text not null,
+                constraint dogs_breed_check check ((length(breed) > 1)),
+                constraint pets_name_check check ((length(name) > 1))
+            ) inherits (pets);
+
+            create table public.my_partitioned_table_1 partition of my_partitioned_table FOR VALUES FROM (1) TO (10);
+
+            create table public.my_partitioned_table_2 partition of my_partitioned_table FOR VALUES FROM (10) TO (20);
 
             create view public.people_who_cant_drink (id, name, age) as  SELECT people.id,
                 people.name,
@@ -193,16 +208,16 @@ async fn exports_to_fake_file_15() {
 
             create unique index unique_name_per_level on public.tree_node using btree (field_id asc nulls last, parent_id asc nulls last, name asc nulls last) nulls not distinct;
 
-            create sequence public.ext_test_table_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.ext_test_table_id_seq as int4;
 
-            create sequence public.field_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.field_id_seq as int4;
 
-            create sequence public.people_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.people_id_seq as int4;
 
             -- chunk-separator-test_chunk_separator --
-            create sequence public.pets_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.pets_id_seq as int4;
 
-            create sequence public.tree_node_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.tree_node_id_seq as int4;
 
             select pg_catalog.setval('public.people_id_seq', 6, true);
 
@@ -284,22 +299,6 @@ async fn exports_to_fake_file_14() {
                 name text[] not null
             );
             
-            create table public.tree_node (
-                id int4 not null,
-                field_id int4 not null,
-                name text not null,
-                parent_id int4,
-                constraint tree_node_pkey primary key (id)
-            );
-
-            create table public.pets (
-                id int4 not null,
-                name text not null,
-                constraint pets_pkey primary key (id),
-                constraint pets_name_check check ((length(name) > 1))
-            );
-
-            -- chunk-separator-test_chunk_separator --
             create table public.ext_test_table (
                 id int4 not null,
                 name text not null,
@@ -312,6 +311,7 @@ async fn exports_to_fake_file_14() {
                 constraint field_pkey primary key (id)
             );
 
+            -- chunk-separator-test_chunk_separator --
             create table public.my_partitioned_table (
                 value int4 not null
             ) partition by range (value);
@@ -325,26 +325,41 @@ async fn exports_to_fake_file_14() {
                 constraint people_age_check check ((age > 0))
             );
 
-            create table public.my_partitioned_table_2 partition of my_partitioned_table FOR VALUES FROM (10) TO (20);
+            create table public.pets (
+                id int4 not null,
+                name text not null,
+                constraint pets_pkey primary key (id),
+                constraint pets_name_check check ((length(name) > 1))
+            );
 
-            -- chunk-separator-test_chunk_separator --
-            create table public.my_partitioned_table_1 partition of my_partitioned_table FOR VALUES FROM (1) TO (10);
+            create table public.tree_node (
+                id int4 not null,
+                field_id int4 not null,
+                name text not null,
+                parent_id int4,
+                constraint tree_node_pkey primary key (id)
+            );
 
-            create table public.dogs (
+            create table public.cats (
                 id int4 not null,
                 name text not null,
-                breed text not null,
-                constraint dogs_breed_check check ((length(breed) > 1)),
+                color text not null,
                 constraint pets_name_check check ((length(name) > 1))
             ) inherits (pets);
 
-            create table public.cats (
+            -- chunk-separator-test_chunk_separator --
+            create table public.dogs (
                 id int4 not null,
                 name text not null,
-                color text not null,
+                breed text not null,
+                constraint dogs_breed_check check ((length(breed) > 1)),
                 constraint pets_name_check check ((length(name) > 1))
             ) inherits (pets);
 
+            create table public.my_partitioned_table_1 partition of my_partitioned_table FOR VALUES FROM (1) TO (10);
+
+            create table public.my_partitioned_table_2 partition of my_partitioned_table FOR VALUES FROM (10) TO (20);
+
             create view public.people_who_cant_drink (id, name, age) as  SELECT people.id,
                 people.name,
                 people.age
@@ -405,16 +420,16 @@ async fn exports_to_fake_file_14() {
 
             create unique index unique_name_per_level on public.tree_node using btree (field_id asc nulls last, parent_id asc nulls last, name asc nulls last);
 
-            create sequence public.ext_test_table_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.ext_test_table_id_seq as int4;
 
-            create sequence public.field_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.field_id_seq as int4;
 
-            create sequence public.people_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.people_id_seq as int4;
 
             -- chunk-separator-test_chunk_separator --
-            create sequence public.pets_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.pets_id_seq as int4;
 
-            create sequence public.tree_node_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.tree_node_id_seq as int4;
 
             select pg_catalog.setval('public.people_id_seq', 6, true);
 
@@ -594,6 +609,154 @@ async fn copy_array_values() {
     assert_eq!(items[1].0, vec![4, 5, 6]);
 }
 
+#[test]
+async fn text_values_with_backslashes_round_trip() {
+    let source = get_test_helper("source").await;
+
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+        create table backslashes(
+            id int4,
+            value text
+        );
+
+        insert into backslashes(id, value)
+        values (1, 'C:\temp\file'),
+               (2, '\t and \n'),
+               (3, 'ends with a backslash\'),
+               (4, '\N'),
+               (5, E'real\ttab and\nnewline'),
+               (6, 'it''s C:\new'),
+               (7, null);
+        "#,
+        )
+        .await;
+
+    let result_file = export_to_string(&source, default()).await;
+
+    assert!(
+        result_file.contains(indoc! {r#"
+            insert into public.backslashes (id, value) values
+            (1, E'C:\\temp\\file'),
+            (2, E'\\t and \\n'),
+            (3, E'ends with a backslash\\'),
+            (4, E'\\N'),
+            (5, E'real\ttab and\nnewline'),
+            (6, E'it''s C:\\new'),
+            (7, null);
+            "#}),
+        "{}",
+        result_file
+    );
+
+    let destination = get_test_helper("destination").await;
+    apply_sql_string(&result_file, destination.get_conn())
+        .await
+        .unwrap();
+
+    let query = "select id, value from backslashes order by id;";
+    let expected = source.get_results::<(i32, Option<String>)>(query).await;
+    let items = destination
+        .get_results::<(i32, Option<String>)>(query)
+        .await;
+
+    assert_eq!(items, expected);
+    assert_eq!(items[0].1.as_deref(), Some(r"C:\temp\file"));
+    assert_eq!(items[3].1.as_deref(), Some(r"\N"));
+}
+
+#[test]
+async fn writes_insert_rows() {
+    let mut row = Vec::new();
+    write_row(
+        &mut row,
+        &[
+            SimplifiedDataType::Number,
+            SimplifiedDataType::Text,
+            SimplifiedDataType::Bool,
+            SimplifiedDataType::Number,
+            SimplifiedDataType::Text,
+        ],
+        b"1\tit's 'quoted'\tt\tNaN\t\\N\n",
+    );
+
+    assert_eq!(
+        String::from_utf8(row).unwrap(),
+        "(1, E'it''s ''quoted''', true, 'NaN', null)"
+    );
+}
+
+#[test]
+async fn wide_tables_fall_back_to_copy_statements() {
+    let source = get_test_helper("source").await;
+
+    let columns = (1..=500).map(|i| format!("c{} int4", i)).join(", ");
+    let values = (1..=500).map(|i| format!("s.i * {}", i)).join(", ");
+    //language=postgresql
+    source
+        .execute_not_query(&format!(
+            r#"
+        create table narrow(
+            id int4,
+            value text
+        );
+
+        insert into narrow(id, value)
+        values (1, 'foo'),
+               (2, 'it''s');
+
+        create table wide({columns});
+
+        insert into wide
+        select {values}
+        from generate_series(1, 100) s(i);
+        "#
+        ))
+        .await;
+
+    let result_file = export_to_string(
+        &source,
+        SqlFileOptions {
+            data_mode: SqlDataMode::InsertStatements,
+            insert_mode_column_limit: 200,
+            ..default()
+        },
+    )
+    .await;
+
+    assert!(
+        result_file.contains("insert into public.narrow (id, value) values"),
+        "{}",
+        result_file
+    );
+    assert!(
+        result_file.contains("copy public.wide (c1, "),
+        "{}",
+        result_file
+    );
+    assert!(!result_file.contains("insert into public.wide"));
+
+    let destination = get_test_helper("destination").await;
+    apply_sql_string(&result_file, destination.get_conn())
+        .await
+        .unwrap();
+
+    let query = "select id, value from narrow order by id;";
+    let expected = source.get_results::<(i32, Option<String>)>(query).await;
+    let items = destination
+        .get_results::<(i32, Option<String>)>(query)
+        .await;
+    assert_eq!(items, expected);
+
+    let query = "select c1, c250, c500 from wide order by c1;";
+    let expected = source.get_results::<(i32, i32, i32)>(query).await;
+    let items = destination.get_results::<(i32, i32, i32)>(query).await;
+    assert_eq!(items.len(), 100);
+    assert_eq!(items, expected);
+}
+
 #[test]
 async fn export_as_copy_statements() {
     let source = get_test_helper("source").await;
@@ -668,6 +831,7 @@ async fn export_as_copy_statements() {
             );
 
             -- chunk-separator-test_chunk_separator --
+            -- elefant:data format=text table=public.test_table
             copy public.test_table (value) from stdin with (format text, header false);
             -- chunk-separator-test_chunk_separator --
             1
@@ -676,6 +840,7 @@ async fn export_as_copy_statements() {
             \.
 
             -- chunk-separator-test_chunk_separator --
+            -- elefant:data format=text table=public.test_table_2
             copy public.test_table_2 (value) from stdin with (format text, header false);
             -- chunk-separator-test_chunk_separator --
             4
@@ -849,7 +1014,7 @@ insert into my_table (value, active_interval) values
 
 
             -- chunk-separator-test_chunk_separator --
-            create sequence public.my_table_id_seq as int4 increment by 1 minvalue 1 maxvalue 2147483647 start 1 cache 1;
+            create sequence public.my_table_id_seq as int4;
 
             select pg_catalog.setval('public.my_table_id_seq', 2, true);
 
@@ -861,3 +1026,1230 @@ insert into my_table (value, active_interval) values
         .await
         .unwrap();
 }
+
+//language=postgresql
+const TABLES_WITHOUT_COPYABLE_COLUMNS: &str = r#"
+create table identity_and_generated (
+    id int generated always as identity,
+    doubled int generated always as (id * 2) stored
+);
+
+create table only_generated (
+    value int generated always as (42) stored
+);
+
+create table no_columns ();
+
+insert into identity_and_generated default values;
+insert into identity_and_generated default values;
+insert into only_generated default values;
+insert into only_generated default values;
+insert into only_generated default values;
+insert into no_columns default values;
+"#;
+
+#[test]
+async fn tables_without_copyable_columns_are_exported_with_default_values() {
+    let source = get_test_helper("source").await;
+    source
+        .execute_not_query(TABLES_WITHOUT_COPYABLE_COLUMNS)
+        .await;
+
+    let result_file = export_to_string(&source, default()).await;
+
+    assert!(result_file.contains(
+        "insert into public.only_generated select from generate_series(1, 3);"
+    ));
+    assert!(result_file.contains("insert into public.no_columns select from generate_series(1, 1);"));
+
+    let destination = get_test_helper("destination").await;
+    apply_sql_string(&result_file, destination.get_conn())
+        .await
+        .unwrap();
+
+    let identity_rows = destination
+        .get_results::<(i32, i32)>("select id, doubled from identity_and_generated order by id;")
+        .await;
+    assert_eq!(identity_rows, vec![(1, 2), (2, 4)]);
+
+    let generated_values = destination
+        .get_single_results::<i32>("select value from only_generated;")
+        .await;
+    assert_eq!(generated_values, vec![42, 42, 42]);
+
+    let no_columns_rows = destination
+        .get_single_result::<i64>("select count(*) from no_columns;")
+        .await;
+    assert_eq!(no_columns_rows, 1);
+}
+
+/// A writer that takes a while to flush, and records how many bytes were
+/// waiting to be flushed at most.
+struct SlowWriter {
+    pending_bytes: usize,
+    max_pending_bytes: Arc<std::sync::atomic::AtomicUsize>,
+    flush_delay: Option<std::pin::Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl AsyncWrite for SlowWriter {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        self.pending_bytes += buf.len();
+        self.max_pending_bytes
+            .fetch_max(self.pending_bytes, std::sync::atomic::Ordering::Relaxed);
+        std::task::Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        let delay = self.flush_delay.get_or_insert_with(|| {
+            Box::pin(tokio::time::sleep(std::time::Duration::from_millis(1)))
+        });
+        std::task::ready!(delay.as_mut().poll(cx));
+
+        self.flush_delay = None;
+        self.pending_bytes = 0;
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[test]
+async fn buffered_bytes_are_bounded_with_slow_writer() {
+    let max_pending_bytes = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let writer = SlowWriter {
+        pending_bytes: 0,
+        max_pending_bytes: max_pending_bytes.clone(),
+        flush_delay: None,
+    };
+
+    let mut sql_file = SqlFile::new(
+        writer,
+        Arc::new(IdentifierQuoter::empty()),
+        SqlFileOptions {
+            data_mode: SqlDataMode::CopyStatements,
+            max_buffer_bytes: 1024,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let schema = PostgresSchema {
+        name: "public".to_string(),
+        ..default()
+    };
+    let table = PostgresTable {
+        name: "items".to_string(),
+        columns: vec![PostgresColumn {
+            name: "value".to_string(),
+            ordinal_position: 1,
+            is_nullable: false,
+            data_type: "text".to_string(),
+            default_value: None,
+            generated: None,
+            comment: None,
+            array_dimensions: 0,
+            data_type_length: None,
+            identity: None,
+            identity_options: None,
+            inherits_default: false,
+        }],
+        ..default()
+    };
+
+    // A source that produces data far faster than the writer can flush it.
+    let rows = (0..10_000).map(|i| Ok(Bytes::from(format!("value-{:05}\n", i))));
+    let data = TableData {
+        data: futures::stream::iter(rows),
+        data_format: DataFormat::Text { options: default() },
+        cleanup: (),
+    };
+
+    let bytes_before = sql_file.get_bytes_written();
+    let mut destination = &mut sql_file;
+    destination.apply_data(&schema, &table, data).await.unwrap();
+    destination.finish().await.unwrap();
+
+    let max_pending_bytes = max_pending_bytes.load(std::sync::atomic::Ordering::Relaxed);
+    assert!(
+        max_pending_bytes < 1024 + "value-00000\n".len(),
+        "At most {} bytes were pending",
+        max_pending_bytes
+    );
+
+    let table_bytes = sql_file.get_table_bytes_written();
+    assert_eq!(table_bytes.len(), 1);
+    assert_eq!(table_bytes[0].schema_name, "public");
+    assert_eq!(table_bytes[0].table_name, "items");
+    assert!(table_bytes[0].bytes > 10_000 * 12);
+    assert_eq!(
+        table_bytes[0].bytes,
+        sql_file.get_bytes_written() - bytes_before
+    );
+}
+
+#[test]
+async fn insert_statements_are_split_by_size() {
+    let source = get_test_helper("source").await;
+
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+        create table wide_rows(
+            id int4,
+            value text
+        );
+
+        insert into wide_rows(id, value)
+        select i, repeat(i::text, 1000000)
+        from generate_series(1, 8) i;
+        "#,
+        )
+        .await;
+
+    const MAX_BYTES_PER_INSERT: usize = 3_500_000;
+
+    let mut result_file = Vec::<u8>::new();
+    let largest_insert_bytes = {
+        let mut sql_file = SqlFile::new(
+            &mut result_file,
+            Arc::new(IdentifierQuoter::empty()),
+            SqlFileOptions {
+                chunk_separator: "test_chunk_separator".to_string(),
+                max_bytes_per_insert: MAX_BYTES_PER_INSERT,
+                ..default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let source = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+
+        copy_data(&source, &mut sql_file, CopyDataOptions::default())
+            .await
+            .unwrap();
+
+        sql_file.get_largest_insert_bytes()
+    };
+
+    let result_file = String::from_utf8(result_file).unwrap();
+    let insert_sizes = result_file
+        .split("-- chunk-separator-test_chunk_separator --\n")
+        .filter(|chunk| chunk.starts_with("insert into"))
+        .map(|chunk| chunk.trim_end().len())
+        .collect::<Vec<_>>();
+
+    // Each row is about 1 MB, so 3 rows fit in a statement.
+    assert_eq!(insert_sizes.len(), 3, "{:?}", insert_sizes);
+    assert!(insert_sizes.iter().all(|size| *size <= MAX_BYTES_PER_INSERT));
+    assert_eq!(
+        largest_insert_bytes,
+        *insert_sizes.iter().max().unwrap() as u64
+    );
+
+    let destination = get_test_helper("destination").await;
+    apply_sql_string(&result_file, destination.get_conn())
+        .await
+        .unwrap();
+
+    let count = destination
+        .get_single_result::<i64>("select count(*) from wide_rows where length(value) >= 1000000;")
+        .await;
+    assert_eq!(count, 8);
+}
+
+#[test]
+async fn export_as_copy_statements_with_custom_delimiter() {
+    let source = get_test_helper("source").await;
+
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+        create table test_table(
+            id int4 not null,
+            name text not null,
+            note text
+        );
+
+        insert into test_table(id, name, note)
+        values (1, 'a|b', null),
+               (2, E'tab\there', 'pipe at the end|'),
+               (3, '\.', E'back\\slash');
+        "#,
+        )
+        .await;
+
+    let result_file = export_to_string(
+        &source,
+        SqlFileOptions {
+            data_mode: SqlDataMode::CopyStatements,
+            copy_options: CopyTextOptions {
+                delimiter: '|',
+                null_marker: "NULL".to_string(),
+            },
+            ..default()
+        },
+    )
+    .await;
+
+    assert!(
+        result_file.contains(indoc! {r#"
+            copy public.test_table (id, name, note) from stdin with (format text, header false, delimiter '|', null 'NULL');
+            -- chunk-separator-test_chunk_separator --
+            1|a\|b|NULL
+            2|tab\there|pipe at the end\|
+            3|\\.|back\\slash
+            \.
+        "#}),
+        "Unexpected copy statement in file: {}",
+        result_file
+    );
+
+    let destination = get_test_helper("destination").await;
+    apply_sql_string(&result_file, destination.get_conn())
+        .await
+        .unwrap();
+
+    let items = destination
+        .get_results::<(i32, String, Option<String>)>(
+            "select id, name, note from test_table order by id;",
+        )
+        .await;
+
+    assert_eq!(
+        items,
+        vec![
+            (1, "a|b".to_string(), None),
+            (
+                2,
+                "tab\there".to_string(),
+                Some("pipe at the end|".to_string())
+            ),
+            (3, "\\.".to_string(), Some("back\\slash".to_string())),
+        ]
+    );
+}
+
+#[test]
+async fn rejects_copy_options_that_cannot_be_read_back() {
+    let invalid_options = [
+        CopyTextOptions {
+            delimiter: '\n',
+            ..default()
+        },
+        CopyTextOptions {
+            delimiter: '\\',
+            ..default()
+        },
+        CopyTextOptions {
+            delimiter: 'a',
+            ..default()
+        },
+        CopyTextOptions {
+            delimiter: ',',
+            null_marker: "a,b".to_string(),
+        },
+        CopyTextOptions {
+            delimiter: ',',
+            null_marker: "\\.".to_string(),
+        },
+    ];
+
+    for copy_options in invalid_options {
+        let result = SqlFile::new(
+            Vec::<u8>::new(),
+            Arc::new(IdentifierQuoter::empty()),
+            SqlFileOptions {
+                copy_options: copy_options.clone(),
+                ..default()
+            },
+        )
+        .await;
+
+        assert!(
+            matches!(result, Err(crate::ElefantToolsError::InvalidCopyTextOptions(_))),
+            "Expected {:?} to be rejected",
+            copy_options
+        );
+    }
+}
+
+/// A writer that fails once more than `remaining_bytes` have been written.
+struct FailingWriter {
+    remaining_bytes: usize,
+}
+
+impl AsyncWrite for FailingWriter {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        if buf.len() > self.remaining_bytes {
+            return std::task::Poll::Ready(Err(std::io::Error::other("disk full")));
+        }
+
+        self.remaining_bytes -= buf.len();
+        std::task::Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+struct RecordingCleanup {
+    ran: Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl AsyncCleanup for RecordingCleanup {
+    async fn cleanup(self) -> Result<()> {
+        self.ran.store(true, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[test]
+async fn source_is_cleaned_up_when_destination_fails_mid_stream() {
+    let mut sql_file = SqlFile::new(
+        FailingWriter {
+            remaining_bytes: 4096,
+        },
+        Arc::new(IdentifierQuoter::empty()),
+        SqlFileOptions {
+            data_mode: SqlDataMode::CopyStatements,
+            max_buffer_bytes: 1024,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let schema = PostgresSchema {
+        name: "public".to_string(),
+        ..default()
+    };
+    let table = PostgresTable {
+        name: "items".to_string(),
+        columns: vec![PostgresColumn {
+            name: "value".to_string(),
+            ordinal_position: 1,
+            data_type: "text".to_string(),
+            ..default()
+        }],
+        ..default()
+    };
+
+    let ran = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let rows = (0..10_000).map(|i| Ok(Bytes::from(format!("value-{:05}\n", i))));
+    let data = TableData {
+        data: futures::stream::iter(rows),
+        data_format: DataFormat::Text { options: default() },
+        cleanup: RecordingCleanup { ran: ran.clone() },
+    };
+
+    let mut destination = &mut sql_file;
+    let result = destination.apply_data(&schema, &table, data).await;
+
+    match result {
+        Err(ElefantToolsError::IoError(e)) => assert_eq!(e.to_string(), "disk full"),
+        r => panic!("Expected the write to fail, got {:?}", r),
+    }
+    assert!(ran.load(std::sync::atomic::Ordering::Relaxed));
+}
+
+#[test]
+async fn exports_table_using_select_override() {
+    let source = get_test_helper("source").await;
+
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+        create table documents(
+            id int4,
+            tenant text
+        );
+
+        insert into documents(id, tenant)
+        values (1, 'acme'),
+               (2, 'initech');
+        "#,
+        )
+        .await;
+
+    let mut result_file = Vec::<u8>::new();
+
+    {
+        let mut sql_file = SqlFile::new(
+            &mut result_file,
+            Arc::new(IdentifierQuoter::empty()),
+            SqlFileOptions {
+                chunk_separator: "test_chunk_separator".to_string(),
+                ..default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let source = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+
+        copy_data(
+            &source,
+            &mut sql_file,
+            CopyDataOptions {
+                table_select_overrides: HashMap::from([(
+                    "public.documents".to_string(),
+                    "select id * 10 as id, upper(tenant) as tenant from public.documents order by id"
+                        .to_string(),
+                )]),
+                ..default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let result_file = String::from_utf8(result_file).unwrap();
+
+    assert!(
+        result_file.contains(indoc! {r#"
+            insert into public.documents (id, tenant) values
+            (10, E'ACME'),
+            (20, E'INITECH');
+            "#}),
+        "{}",
+        result_file
+    );
+}
+
+#[test]
+async fn exports_statements_rewritten_by_interceptor() {
+    let source = get_test_helper("source").await;
+
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+        create table items(
+            id int4 primary key,
+            name text not null
+        );
+
+        create index items_name_idx on items (name);
+        "#,
+        )
+        .await;
+
+    let result_file = export_to_string_with_options(
+        &source,
+        default(),
+        CopyDataOptions {
+            statement_interceptor: Some(Arc::new(AppendStorageParameter::new("fillfactor=70"))),
+            ..default()
+        },
+    )
+    .await;
+
+    assert!(
+        result_file.contains("alter table public.items set (fillfactor=70);"),
+        "{}",
+        result_file
+    );
+    assert!(
+        result_file.contains("create index items_name_idx on public.items using btree (name asc nulls last);"),
+        "{}",
+        result_file
+    );
+}
+
+#[test]
+async fn chunks_are_applied_atomically_with_transaction_per_chunk() {
+    let mut result_file = Vec::<u8>::new();
+    {
+        let mut sql_file = SqlFile::new(
+            &mut result_file,
+            Arc::new(IdentifierQuoter::empty()),
+            SqlFileOptions {
+                chunk_separator: "test_chunk_separator".to_string(),
+                max_commands_per_chunk: 2,
+                transaction_per_chunk: true,
+                ..default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut destination = &mut sql_file;
+        destination
+            .apply_transactional_statement("create table first(id int4);")
+            .await
+            .unwrap();
+        destination
+            .apply_transactional_statement("insert into first(id) values (1);")
+            .await
+            .unwrap();
+        destination
+            .apply_non_transactional_statement("create index concurrently first_idx on first(id);")
+            .await
+            .unwrap();
+        destination
+            .apply_transactional_statement("create table second(id int4);")
+            .await
+            .unwrap();
+        destination
+            .apply_transactional_statement("insert into second(id) values ('not a number');")
+            .await
+            .unwrap();
+        destination.finish().await.unwrap();
+    }
+
+    let result_file = String::from_utf8(result_file).unwrap();
+
+    assert_eq!(
+        result_file,
+        indoc! {r#"
+        -- chunk-separator-test_chunk_separator --
+        SET statement_timeout = 0;
+        SET lock_timeout = 0;
+        SET idle_in_transaction_session_timeout = 0;
+        SET check_function_bodies = false;
+        SET xmloption = content;
+        SET row_security = off;
+        -- chunk-separator-test_chunk_separator --
+        begin;
+
+        create table first(id int4);
+
+        insert into first(id) values (1);
+
+        commit;
+
+        -- chunk-separator-test_chunk_separator --
+        -- elefant:non-transactional
+        create index concurrently first_idx on first(id);
+
+        -- chunk-separator-test_chunk_separator --
+        begin;
+
+        create table second(id int4);
+
+        insert into second(id) values ('not a number');
+
+        commit;"#}
+    );
+
+    // Run the statements one by one like psql does, stopping at the first error.
+    let destination = get_test_helper("destination").await;
+    let conn = destination.get_conn();
+    let mut failed = false;
+    for statement in result_file
+        .split("\n\n")
+        .flat_map(|part| part.split("-- chunk-separator-test_chunk_separator --\n"))
+        .filter(|statement| !statement.is_empty())
+    {
+        if conn.execute_non_query(statement).await.is_err() {
+            // Ending the session discards the failed transaction.
+            conn.execute_non_query("rollback;").await.unwrap();
+            failed = true;
+            break;
+        }
+    }
+    assert!(failed);
+
+    let tables = destination
+        .get_single_results::<String>(
+            "select tablename::text from pg_tables where schemaname = 'public' order by tablename;",
+        )
+        .await;
+    assert_eq!(tables, vec!["first".to_string()]);
+    let index_count = destination
+        .get_single_result::<i64>("select count(*) from pg_indexes where indexname = 'first_idx';")
+        .await;
+    assert_eq!(index_count, 1);
+
+    // apply_sql_file runs each chunk as one query, and has to clean up the failed transaction.
+    let destination = get_test_helper("destination").await;
+    let result = apply_sql_string(&result_file, destination.get_conn()).await;
+    assert!(result.is_err());
+
+    let tables = destination
+        .get_single_results::<String>(
+            "select tablename::text from pg_tables where schemaname = 'public' order by tablename;",
+        )
+        .await;
+    assert_eq!(tables, vec!["first".to_string()]);
+}
+
+fn exported_files_table() -> (PostgresSchema, PostgresTable) {
+    let schema = PostgresSchema {
+        name: "public".to_string(),
+        ..default()
+    };
+    let table = PostgresTable {
+        name: "exported_files".to_string(),
+        columns: vec![PostgresColumn {
+            name: "content".to_string(),
+            ordinal_position: 1,
+            data_type: "text".to_string(),
+            ..default()
+        }],
+        ..default()
+    };
+
+    (schema, table)
+}
+
+#[test]
+async fn rows_containing_the_chunk_separator_are_rejected() {
+    let mut result_file = Vec::<u8>::new();
+    let mut sql_file = SqlFile::new(
+        &mut result_file,
+        Arc::new(IdentifierQuoter::empty()),
+        SqlFileOptions {
+            chunk_separator: "test_chunk_separator".to_string(),
+            data_mode: SqlDataMode::CopyStatements,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (schema, table) = exported_files_table();
+
+    let rows = [
+        "-- chunk-separator-other_separator --\n",
+        "-- chunk-separator-test_chunk_separator -- with more text\n",
+        "-- chunk-separator-test_chunk_separator --\n",
+        "after the separator\n",
+    ]
+    .map(|row| Ok(Bytes::from(row)));
+    let data = TableData {
+        data: futures::stream::iter(rows),
+        data_format: DataFormat::Text { options: default() },
+        cleanup: (),
+    };
+
+    let mut destination = &mut sql_file;
+    let result = destination.apply_data(&schema, &table, data).await;
+
+    assert!(
+        matches!(&result, Err(ElefantToolsError::ChunkSeparatorInData { schema_name, table_name }) if schema_name == "public" && table_name == "exported_files"),
+        "{:?}",
+        result
+    );
+    drop(sql_file);
+
+    let written = String::from_utf8(result_file).unwrap();
+    assert!(written.contains("-- chunk-separator-test_chunk_separator -- with more text\n"));
+    assert!(!written.contains("after the separator"));
+}
+
+#[test]
+async fn copy_data_only_uses_line_feeds() {
+    let mut result_file = Vec::<u8>::new();
+    let mut sql_file = SqlFile::new(
+        &mut result_file,
+        Arc::new(IdentifierQuoter::empty()),
+        SqlFileOptions {
+            chunk_separator: "test_chunk_separator".to_string(),
+            data_mode: SqlDataMode::CopyStatements,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (schema, table) = exported_files_table();
+    let rows = ["first\\r\\nline\n", "second\n"].map(|row| Ok(Bytes::from(row)));
+    let data = TableData {
+        data: futures::stream::iter(rows),
+        data_format: DataFormat::Text { options: default() },
+        cleanup: (),
+    };
+
+    let mut destination = &mut sql_file;
+    destination.apply_data(&schema, &table, data).await.unwrap();
+    drop(sql_file);
+
+    assert!(!result_file.contains(&b'\r'));
+    let written = String::from_utf8(result_file).unwrap();
+    assert!(written.ends_with("first\\r\\nline\nsecond\n\\.\n"));
+}
+
+#[test]
+async fn copy_data_with_carriage_returns_is_rejected() {
+    let mut result_file = Vec::<u8>::new();
+    let mut sql_file = SqlFile::new(
+        &mut result_file,
+        Arc::new(IdentifierQuoter::empty()),
+        SqlFileOptions {
+            data_mode: SqlDataMode::CopyStatements,
+            ..default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (schema, table) = exported_files_table();
+    let rows = ["first\r\n"].map(|row| Ok(Bytes::from(row)));
+    let data = TableData {
+        data: futures::stream::iter(rows),
+        data_format: DataFormat::Text { options: default() },
+        cleanup: (),
+    };
+
+    let mut destination = &mut sql_file;
+    let result = destination.apply_data(&schema, &table, data).await;
+
+    assert!(
+        matches!(&result, Err(ElefantToolsError::CarriageReturnInCopyData { table_name, .. }) if table_name == "exported_files"),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+async fn creates_the_directories_of_the_file() {
+    let root = std::env::temp_dir().join(format!("elefant export {}", uuid::Uuid::new_v4()));
+    let path = root.join("données 数据").join("my export.sql");
+
+    let sql_file = SqlFile::new_file(&path, Arc::new(IdentifierQuoter::empty()), default())
+        .await
+        .unwrap();
+    drop(sql_file);
+    assert!(path.is_file());
+
+    // Paths given as strings keep working.
+    let string_path = root.join("second.sql").to_str().unwrap().to_string();
+    SqlFile::new_file(&string_path, Arc::new(IdentifierQuoter::empty()), default())
+        .await
+        .unwrap();
+    assert!(std::path::Path::new(&string_path).is_file());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+async fn directories_are_not_created_when_turned_off() {
+    let root = std::env::temp_dir().join(format!("elefant export {}", uuid::Uuid::new_v4()));
+    let path = root.join("my export.sql");
+
+    let result = SqlFile::new_file(
+        &path,
+        Arc::new(IdentifierQuoter::empty()),
+        SqlFileOptions {
+            create_parent_directories: false,
+            ..default()
+        },
+    )
+    .await;
+
+    assert!(
+        matches!(&result, Err(ElefantToolsError::IoErrorWithPath { operation: "create", .. })),
+        "{:?}",
+        result.err()
+    );
+    assert!(!root.exists());
+}
+
+#[test]
+async fn io_errors_include_the_path_of_the_file() {
+    let result = SqlFile::new_file(
+        "/does/not/exist/export.sql",
+        Arc::new(IdentifierQuoter::empty()),
+        SqlFileOptions {
+            create_parent_directories: false,
+            ..default()
+        },
+    )
+    .await;
+
+    let Err(error) = result else {
+        panic!("Expected creating the file to fail");
+    };
+    assert!(
+        matches!(&error, ElefantToolsError::IoErrorWithPath { path, operation: "create", .. } if path == "/does/not/exist/export.sql"),
+        "{:?}",
+        error
+    );
+    assert!(error.to_string().contains("/does/not/exist/export.sql"));
+}
+
+async fn apply_copy_file_and_read_values(sql_file: &str) -> Vec<i32> {
+    let destination = get_test_helper("destination").await;
+    apply_sql_string(sql_file, destination.get_conn())
+        .await
+        .unwrap();
+
+    destination
+        .get_single_results::<i32>("select value from public.t order by value;")
+        .await
+}
+
+#[test]
+async fn applies_copy_chunks_without_data_header() {
+    // Files written before the data chunk header was added.
+    let values = apply_copy_file_and_read_values(indoc! {r#"
+        -- chunk-separator-x --
+        create table public.t (
+            value int4 not null
+        );
+
+        -- chunk-separator-x --
+        copy public.t (value) from stdin with (format text, header false);
+        -- chunk-separator-x --
+        1
+        2
+        \.
+
+        "#})
+    .await;
+
+    assert_eq!(values, vec![1, 2]);
+}
+
+#[test]
+async fn applies_copy_chunks_with_data_header() {
+    let values = apply_copy_file_and_read_values(indoc! {r#"
+        -- chunk-separator-x --
+        create table public.t (
+            value int4 not null
+        );
+
+        -- chunk-separator-x --
+        -- elefant:data format=text table=public.t
+        copy public.t (value) from stdin with (format text, header false);
+        -- chunk-separator-x --
+        1
+        2
+        \.
+
+        "#})
+    .await;
+
+    assert_eq!(values, vec![1, 2]);
+}
+
+#[test]
+async fn applies_hand_edited_copy_chunks() {
+    let values = apply_copy_file_and_read_values(indoc! {r#"
+        -- chunk-separator-x --
+        create table public.t (
+            value int4 not null
+        );
+
+        -- chunk-separator-x --
+        COPY public.t (value) FROM STDIN;
+        -- chunk-separator-x --
+        1
+        2
+        \.
+
+        "#})
+    .await;
+
+    assert_eq!(values, vec![1, 2]);
+}
+
+const TREE_NODES: &str = r#"
+    create table field(
+        id serial primary key
+    );
+
+    create table tree_node(
+        id serial primary key,
+        field_id int not null references field(id),
+        name text not null,
+        parent_id int,
+        constraint field_id_id_unique unique (field_id, id),
+        foreign key (field_id, parent_id) references tree_node(field_id, id),
+        constraint unique_name_per_level unique nulls not distinct (field_id, parent_id, name)
+    );
+
+    insert into field(id) values (1);
+"#;
+
+/// Exports the tree nodes as one insert statement per row, and runs the statements against a
+/// destination where the foreign keys already exist.
+async fn insert_tree_nodes_one_at_a_time(
+    source: &TestHelper,
+    order_self_referencing: bool,
+) -> (TestHelper, crate::Result<()>) {
+    let result_file = export_to_string_with_options(
+        source,
+        SqlFileOptions {
+            data_mode: SqlDataMode::InsertStatements,
+            max_rows_per_insert: 1,
+            ..default()
+        },
+        CopyDataOptions {
+            order_self_referencing,
+            ..default()
+        },
+    )
+    .await;
+
+    let destination = get_test_helper("destination").await;
+    destination.execute_not_query(TREE_NODES).await;
+
+    for chunk in result_file.split("-- chunk-separator-test_chunk_separator --\n") {
+        if chunk.starts_with("insert into public.tree_node") {
+            let result = destination.get_conn().execute_non_query(chunk).await;
+            if result.is_err() {
+                return (destination, result);
+            }
+        }
+    }
+
+    (destination, Ok(()))
+}
+
+#[test]
+async fn exports_self_referencing_rows_with_parents_first() {
+    let source = get_test_helper("source").await;
+    source.execute_not_query(TREE_NODES).await;
+    // The foreign key is checked at the end of the statement, so the children can come first.
+    source
+        .execute_not_query(
+            "insert into tree_node(id, field_id, name, parent_id) values (3, 1, 'grandchild', 2), (2, 1, 'child', 1), (4, 1, 'own parent', 4), (1, 1, 'root', null);",
+        )
+        .await;
+
+    let (_, result) = insert_tree_nodes_one_at_a_time(&source, false).await;
+    assert_pg_error(result, SqlState::FOREIGN_KEY_VIOLATION);
+
+    let (destination, result) = insert_tree_nodes_one_at_a_time(&source, true).await;
+    result.unwrap();
+
+    let nodes = destination
+        .get_results::<(i32, String, Option<i32>)>(
+            "select id, name, parent_id from tree_node order by id;",
+        )
+        .await;
+    assert_eq!(
+        nodes,
+        vec![
+            (1, "root".to_string(), None),
+            (2, "child".to_string(), Some(1)),
+            (3, "grandchild".to_string(), Some(2)),
+            (4, "own parent".to_string(), Some(4)),
+        ]
+    );
+}
+
+#[test]
+async fn ordering_self_referencing_rows_fails_on_cycles() {
+    let source = get_test_helper("source").await;
+    source.execute_not_query(TREE_NODES).await;
+    source
+        .execute_not_query(
+            "insert into tree_node(id, field_id, name, parent_id) values (1, 1, 'root', null), (2, 1, 'a', 3), (3, 1, 'b', 2);",
+        )
+        .await;
+
+    let mut result_file = Vec::<u8>::new();
+    let mut sql_file = SqlFile::new(&mut result_file, Arc::new(IdentifierQuoter::empty()), default())
+        .await
+        .unwrap();
+    let source = PostgresInstanceStorage::new(source.get_conn())
+        .await
+        .unwrap();
+
+    let result = copy_data(
+        &source,
+        &mut sql_file,
+        CopyDataOptions {
+            order_self_referencing: true,
+            ..default()
+        },
+    )
+    .await;
+
+    match result {
+        Err(ElefantToolsError::SelfReferenceCycle {
+            schema_name,
+            table_name,
+            rows,
+            ..
+        }) => {
+            assert_eq!(schema_name, "public");
+            assert_eq!(table_name, "tree_node");
+            assert_eq!(rows, 2);
+        }
+        r => panic!("Expected a cycle error, got {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+async fn applies_data_in_parallel() {
+    let source = get_test_helper("source").await;
+
+    //language=postgresql
+    source
+        .execute_not_query(
+            r#"
+        create table customer(
+            id int4 primary key,
+            name text not null
+        );
+
+        create table purchase(
+            id int4 primary key,
+            customer_id int4 not null references customer(id),
+            amount numeric not null
+        );
+
+        create index purchase_customer_idx on purchase(customer_id);
+
+        insert into customer(id, name)
+        select i, 'customer ' || i
+        from generate_series(1, 1000) i;
+
+        insert into purchase(id, customer_id, amount)
+        select i, i % 1000 + 1, i * 1.5
+        from generate_series(1, 5000) i;
+        "#,
+        )
+        .await;
+
+    for data_mode in [SqlDataMode::InsertStatements, SqlDataMode::CopyStatements] {
+        let result_file = export_to_string(
+            &source,
+            SqlFileOptions {
+                data_mode: data_mode.clone(),
+                max_rows_per_insert: 100,
+                ..default()
+            },
+        )
+        .await;
+
+        let destination = get_test_helper("destination").await;
+        let mut bytes = result_file.as_bytes();
+        apply_sql_file_parallel(
+            &mut bytes,
+            destination.get_conn(),
+            NonZeroUsize::new(4).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let counts = destination
+            .get_single_results::<i64>(
+                "select count(*) from customer union all select count(*) from purchase;",
+            )
+            .await;
+        assert_eq!(counts, vec![1000, 5000], "{:?}", data_mode);
+
+        let destination_schema = introspect_schema(&destination).await;
+        let purchase = destination_schema.schemas[0]
+            .tables
+            .iter()
+            .find(|t| t.name == "purchase")
+            .unwrap();
+        assert!(purchase
+            .indices
+            .iter()
+            .any(|i| i.name == "purchase_customer_idx"));
+        assert!(purchase
+            .constraints
+            .iter()
+            .any(|c| matches!(c, PostgresConstraint::ForeignKey(_))));
+    }
+}
+
+#[test]
+async fn applies_non_transactional_chunks_in_autocommit() {
+    let destination = get_test_helper("destination").await;
+
+    //language=postgresql
+    let sql_file = indoc! {r#"
+        -- chunk-separator-test_chunk_separator --
+        begin;
+
+        create table vacuumed(id int4);
+
+        insert into vacuumed(id) values (1), (2);
+
+        commit;
+        -- chunk-separator-test_chunk_separator --
+        -- elefant:non-transactional
+        vacuum analyze public.vacuumed;
+        -- chunk-separator-test_chunk_separator --
+        vacuum public.vacuumed;
+    "#};
+
+    apply_sql_string(sql_file, destination.get_conn())
+        .await
+        .unwrap();
+
+    let analyzed = destination
+        .get_single_result::<i64>(
+            "select count(*) from pg_stat_user_tables where relname = 'vacuumed' and last_analyze is not null;",
+        )
+        .await;
+    assert_eq!(analyzed, 1);
+}
+
+#[test]
+async fn rejects_non_transactional_statements_in_transactional_chunks() {
+    //language=postgresql
+    let sql_files = [
+        indoc! {r#"
+            -- chunk-separator-test_chunk_separator --
+            create table vacuumed(id int4);
+
+            vacuum public.vacuumed;
+        "#},
+        indoc! {r#"
+            -- chunk-separator-test_chunk_separator --
+            -- elefant:non-transactional
+            begin;
+
+            vacuum;
+
+            commit;
+        "#},
+    ];
+
+    for sql_file in sql_files {
+        let destination = get_test_helper("destination").await;
+
+        let result = apply_sql_string(sql_file, destination.get_conn()).await;
+
+        match result {
+            Err(ElefantToolsError::NonTransactionalStatementInChunk(statement)) => {
+                assert!(statement.contains("vacuum"), "{}", statement);
+            }
+            r => panic!("Expected a non-transactional statement error, got {:?}", r),
+        }
+    }
+}
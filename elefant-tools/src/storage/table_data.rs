@@ -1,18 +1,44 @@
 use crate::storage::data_format::DataFormat;
-use crate::Result;
+use crate::{ElefantToolsError, Result};
 use bytes::Bytes;
 use futures::Stream;
 use std::future::Future;
 
 /// Data in a table. This data is a stream which can be read from the data source.
 ///
-/// Make sure to call `cleanup` when you have read all the data from the stream.
+/// Make sure to call `cleanup` when you are done reading from the stream, also when reading fails.
+/// [TableData::apply_then_cleanup] takes care of this.
 pub struct TableData<S: Stream<Item = Result<Bytes>> + Send, C: AsyncCleanup> {
     pub data: S,
     pub data_format: DataFormat,
     pub cleanup: C,
 }
 
+impl<S: Stream<Item = Result<Bytes>> + Send, C: AsyncCleanup> TableData<S, C> {
+    /// Consumes the data stream using `apply`, and runs the cleanup afterwards, even if `apply` fails.
+    ///
+    /// If both `apply` and the cleanup fail, both errors are returned in
+    /// [ElefantToolsError::CleanupFailed].
+    pub async fn apply_then_cleanup<T, F, Fut>(self, apply: F) -> Result<T>
+    where
+        F: FnOnce(S) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let result = apply(self.data).await;
+        let cleanup_result = self.cleanup.cleanup().await;
+
+        match (result, cleanup_result) {
+            (Ok(value), Ok(())) => Ok(value),
+            (Ok(_), Err(cleanup_error)) => Err(cleanup_error),
+            (Err(error), Ok(())) => Err(error),
+            (Err(error), Err(cleanup_error)) => Err(ElefantToolsError::CleanupFailed {
+                error: Box::new(error),
+                cleanup_error: Box::new(cleanup_error),
+            }),
+        }
+    }
+}
+
 pub trait AsyncCleanup: Send {
     fn cleanup(self) -> impl Future<Output = Result<()>> + Send;
 }
@@ -28,3 +54,51 @@ impl<S: Stream<Item = Result<Bytes>> + Send, C: AsyncCleanup> AsyncCleanup for T
         self.cleanup.cleanup()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct FailingCleanup {
+        ran: Arc<AtomicBool>,
+    }
+
+    impl AsyncCleanup for FailingCleanup {
+        async fn cleanup(self) -> Result<()> {
+            self.ran.store(true, Ordering::Relaxed);
+            Err(ElefantToolsError::CsvFileMissingHeader)
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_both_errors_when_apply_and_cleanup_fails() {
+        let ran = Arc::new(AtomicBool::new(false));
+        let data = TableData {
+            data: futures::stream::empty(),
+            data_format: DataFormat::Text { options: default() },
+            cleanup: FailingCleanup { ran: ran.clone() },
+        };
+
+        let result = data
+            .apply_then_cleanup(|_| async { Err::<(), _>(ElefantToolsError::ConfirmationDeclined) })
+            .await;
+
+        assert!(ran.load(Ordering::Relaxed));
+        match result {
+            Err(ElefantToolsError::CleanupFailed {
+                error,
+                cleanup_error,
+            }) => {
+                assert!(matches!(*error, ElefantToolsError::ConfirmationDeclined));
+                assert!(matches!(
+                    *cleanup_error,
+                    ElefantToolsError::CsvFileMissingHeader
+                ));
+            }
+            r => panic!("Expected both errors, got {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! The database versions Elefant Tools supports and is tested against.
+//!
+//! These are used both for the version checks when connecting and for reporting the
+//! supported versions to users, so the two can't drift apart.
+
+use itertools::Itertools;
+
+/// The oldest major version of Postgres that is supported.
+pub const MIN_SUPPORTED_POSTGRES_VERSION: i32 = 12;
+
+/// The newest major version of Postgres that is tested against.
+pub const MAX_TESTED_POSTGRES_VERSION: i32 = 17;
+
+/// The oldest version of TimescaleDB that is supported. Hypertables are created
+/// using the dimension builders, which were added in this version.
+pub const MIN_SUPPORTED_TIMESCALEDB_VERSION: &str = "2.13";
+
+/// The Postgres major versions TimescaleDB is tested against.
+pub const TESTED_TIMESCALEDB_POSTGRES_VERSIONS: &[i32] = &[15, 16];
+
+/// Gets a human-readable description of the supported database versions.
+pub fn get_supported_versions_description() -> String {
+    format!(
+        "Postgres {}-{}, TimescaleDB {}+ on Postgres {}",
+        MIN_SUPPORTED_POSTGRES_VERSION,
+        MAX_TESTED_POSTGRES_VERSION,
+        MIN_SUPPORTED_TIMESCALEDB_VERSION,
+        TESTED_TIMESCALEDB_POSTGRES_VERSIONS.iter().join(", ")
+    )
+}
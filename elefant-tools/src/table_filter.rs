@@ -0,0 +1,417 @@
+use crate::column_exclusion::DependentObject;
+use crate::object_filter::NamePattern;
+use crate::*;
+use itertools::Itertools;
+use tracing::warn;
+
+/// Removes the tables that are not selected by the include and exclude patterns from the
+/// definition, so they are neither created nor copied.
+///
+/// Partitions are selected when their partitioned table is, unless they are excluded themselves.
+/// Foreign keys to the removed tables, views using them and tables inheriting from them are
+/// reported as an error, unless `drop_dependent_objects` is set, in which case they are removed
+/// as well. Patterns without wildcards have to match a table, to catch typos.
+pub(crate) fn filter_tables(
+    definition: &mut PostgresDatabase,
+    include_tables: &[String],
+    exclude_tables: &[String],
+    drop_dependent_objects: bool,
+) -> Result<()> {
+    if include_tables.is_empty() && exclude_tables.is_empty() {
+        return Ok(());
+    }
+
+    let include_patterns = include_tables
+        .iter()
+        .map(|p| NamePattern::parse(p))
+        .collect_vec();
+    let exclude_patterns = exclude_tables
+        .iter()
+        .map(|p| NamePattern::parse(p))
+        .collect_vec();
+
+    for pattern in include_patterns.iter().chain(&exclude_patterns) {
+        let matches_any_table = definition
+            .schemas
+            .iter()
+            .any(|s| s.tables.iter().any(|t| pattern.matches(&s.name, &t.name)));
+
+        if !pattern.has_wildcards() && !matches_any_table {
+            return Err(ElefantToolsError::TableNotFound {
+                schema_name: pattern.schema.to_string(),
+                table_name: pattern.name.to_string(),
+            });
+        }
+    }
+
+    let mut excluded = Vec::new();
+    for schema in &definition.schemas {
+        for table in &schema.tables {
+            if !is_selected(schema, table, &include_patterns, &exclude_patterns) {
+                excluded.push((schema.name.clone(), table.name.clone()));
+            }
+        }
+    }
+
+    let dependent_objects = find_dependent_objects(definition, &mut excluded);
+
+    if !dependent_objects.is_empty() && !drop_dependent_objects {
+        return Err(ElefantToolsError::ExcludedTablesHaveDependentObjects(
+            dependent_objects.iter().map(|o| o.to_string()).collect(),
+        ));
+    }
+
+    for object in &dependent_objects {
+        warn!("Skipping {}, as it uses an excluded table", object);
+    }
+
+    for schema in &mut definition.schemas {
+        let schema_name = schema.name.clone();
+        let is_excluded =
+            |table_name: &str| excluded.contains(&(schema_name.clone(), table_name.to_string()));
+        let is_dependent = |object: DependentObject| dependent_objects.contains(&object);
+
+        schema.tables.retain(|t| !is_excluded(&t.name));
+
+        for table in &mut schema.tables {
+            let table_name = table.name.clone();
+
+            table.constraints.retain(|constraint| {
+                !is_dependent(DependentObject::Constraint {
+                    schema_name: schema_name.clone(),
+                    table_name: table_name.clone(),
+                    constraint_name: constraint.name().to_string(),
+                })
+            });
+        }
+
+        schema.triggers.retain(|t| !is_excluded(&t.table_name));
+        schema.policies.retain(|p| !is_excluded(&p.table_name));
+
+        schema
+            .sequences
+            .retain(|s| !s.author_table.as_deref().is_some_and(&is_excluded));
+
+        schema.views.retain(|view| {
+            !is_dependent(DependentObject::View {
+                schema_name: schema_name.clone(),
+                view_name: view.name.clone(),
+            })
+        });
+    }
+
+    Ok(())
+}
+
+/// If the table is selected by the include and exclude patterns of [filter_tables].
+pub(crate) fn is_table_selected(
+    schema: &PostgresSchema,
+    table: &PostgresTable,
+    include_tables: &[String],
+    exclude_tables: &[String],
+) -> bool {
+    let include_patterns = include_tables
+        .iter()
+        .map(|p| NamePattern::parse(p))
+        .collect_vec();
+    let exclude_patterns = exclude_tables
+        .iter()
+        .map(|p| NamePattern::parse(p))
+        .collect_vec();
+
+    is_selected(schema, table, &include_patterns, &exclude_patterns)
+}
+
+fn is_selected(
+    schema: &PostgresSchema,
+    table: &PostgresTable,
+    include_patterns: &[NamePattern],
+    exclude_patterns: &[NamePattern],
+) -> bool {
+    if exclude_patterns
+        .iter()
+        .any(|p| p.matches(&schema.name, &table.name))
+    {
+        return false;
+    }
+
+    if let TableTypeDetails::PartitionedChildTable { parent_table, .. } = &table.table_type {
+        if let Some(parent) = schema.try_get_table(parent_table) {
+            return is_selected(schema, parent, include_patterns, exclude_patterns);
+        }
+    }
+
+    include_patterns.is_empty()
+        || include_patterns
+            .iter()
+            .any(|p| p.matches(&schema.name, &table.name))
+}
+
+/// Finds the objects that cannot be created without the excluded tables. Tables inheriting from
+/// an excluded table are added to the excluded tables, so objects depending on them are found too.
+fn find_dependent_objects(
+    definition: &PostgresDatabase,
+    excluded: &mut Vec<(String, String)>,
+) -> Vec<DependentObject> {
+    let mut dependent_objects = Vec::new();
+
+    loop {
+        let mut inheriting = Vec::new();
+
+        for schema in &definition.schemas {
+            for table in &schema.tables {
+                let key = (schema.name.clone(), table.name.clone());
+                if excluded.contains(&key) {
+                    continue;
+                }
+
+                if let TableTypeDetails::InheritedTable { parent_tables } = &table.table_type {
+                    if parent_tables
+                        .iter()
+                        .any(|p| excluded.contains(&(schema.name.clone(), p.clone())))
+                    {
+                        inheriting.push(key);
+                    }
+                }
+            }
+        }
+
+        if inheriting.is_empty() {
+            break;
+        }
+
+        for (schema_name, table_name) in inheriting {
+            dependent_objects.push(DependentObject::Table {
+                schema_name: schema_name.clone(),
+                table_name: table_name.clone(),
+            });
+            excluded.push((schema_name, table_name));
+        }
+    }
+
+    for schema in &definition.schemas {
+        for table in &schema.tables {
+            if excluded.contains(&(schema.name.clone(), table.name.clone())) {
+                continue;
+            }
+
+            for constraint in &table.constraints {
+                if let PostgresConstraint::ForeignKey(fk) = constraint {
+                    let referenced_schema = fk.referenced_schema.as_ref().unwrap_or(&schema.name);
+
+                    if excluded.contains(&(referenced_schema.clone(), fk.referenced_table.clone()))
+                    {
+                        dependent_objects.push(DependentObject::Constraint {
+                            schema_name: schema.name.clone(),
+                            table_name: table.name.clone(),
+                            constraint_name: fk.name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let excluded_table_ids = definition
+        .schemas
+        .iter()
+        .flat_map(|s| {
+            s.tables
+                .iter()
+                .filter(|t| excluded.contains(&(s.name.clone(), t.name.clone())))
+                .map(|t| t.object_id)
+        })
+        .collect_vec();
+
+    let views = definition
+        .schemas
+        .iter()
+        .flat_map(|s| s.views.iter().map(move |v| (s, v)))
+        .collect_vec();
+
+    let mut dependent_views = views
+        .iter()
+        .filter(|(_, v)| v.depends_on.iter().any(|d| excluded_table_ids.contains(d)))
+        .map(|(_, v)| v.object_id)
+        .collect_vec();
+
+    loop {
+        let indirect = views
+            .iter()
+            .filter(|(_, v)| !dependent_views.contains(&v.object_id))
+            .filter(|(_, v)| v.depends_on.iter().any(|d| dependent_views.contains(d)))
+            .map(|(_, v)| v.object_id)
+            .collect_vec();
+
+        if indirect.is_empty() {
+            break;
+        }
+
+        dependent_views.extend(indirect);
+    }
+
+    for (schema, view) in &views {
+        if dependent_views.contains(&view.object_id) {
+            dependent_objects.push(DependentObject::View {
+                schema_name: schema.name.clone(),
+                view_name: view.name.clone(),
+            });
+        }
+    }
+
+    dependent_objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> PostgresDatabase {
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![
+                    PostgresTable {
+                        object_id: 1.into(),
+                        ..PostgresTable::new("customers")
+                    },
+                    PostgresTable {
+                        object_id: 2.into(),
+                        constraints: vec![PostgresConstraint::ForeignKey(PostgresForeignKey {
+                            name: "orders_customer_fk".to_string(),
+                            referenced_table: "customers".to_string(),
+                            ..default()
+                        })],
+                        ..PostgresTable::new("orders")
+                    },
+                    PostgresTable {
+                        object_id: 3.into(),
+                        table_type: TableTypeDetails::PartitionedParentTable {
+                            partition_strategy: TablePartitionStrategy::Range,
+                            default_partition_name: None,
+                            partition_columns: PartitionedTableColumns::Columns(vec![
+                                "time".to_string()
+                            ]),
+                        },
+                        ..PostgresTable::new("events")
+                    },
+                    PostgresTable {
+                        object_id: 4.into(),
+                        table_type: TableTypeDetails::PartitionedChildTable {
+                            parent_table: "events".to_string(),
+                            partition_expression:
+                                "for values from ('2024-01-01') to ('2025-01-01')".to_string(),
+                        },
+                        ..PostgresTable::new("events_2024")
+                    },
+                ],
+                views: vec![
+                    PostgresView {
+                        name: "customer_names".to_string(),
+                        object_id: 5.into(),
+                        depends_on: vec![1.into()],
+                        ..default()
+                    },
+                    PostgresView {
+                        name: "short_customer_names".to_string(),
+                        object_id: 6.into(),
+                        depends_on: vec![5.into()],
+                        ..default()
+                    },
+                ],
+                triggers: vec![PostgresTrigger {
+                    name: "customers_audit".to_string(),
+                    table_name: "customers".to_string(),
+                    ..default()
+                }],
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    fn get_table_names(definition: &PostgresDatabase) -> Vec<&str> {
+        definition.schemas[0]
+            .tables
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn includes_partitions_of_included_tables() {
+        let mut definition = database();
+
+        filter_tables(&mut definition, &["public.events".to_string()], &[], true).unwrap();
+
+        assert_eq!(get_table_names(&definition), vec!["events", "events_2024"]);
+    }
+
+    #[test]
+    fn excludes_tables_matching_wildcards() {
+        let mut definition = database();
+
+        filter_tables(&mut definition, &[], &["*.events*".to_string()], false).unwrap();
+
+        assert_eq!(get_table_names(&definition), vec!["customers", "orders"]);
+    }
+
+    #[test]
+    fn reports_objects_depending_on_excluded_tables() {
+        let mut definition = database();
+
+        let result = filter_tables(&mut definition, &[], &["customers".to_string()], false);
+
+        assert!(
+            matches!(&result, Err(ElefantToolsError::ExcludedTablesHaveDependentObjects(objects)) if objects == &vec![
+                "constraint orders_customer_fk on public.orders".to_string(),
+                "view public.customer_names".to_string(),
+                "view public.short_customer_names".to_string(),
+            ]),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn drops_objects_depending_on_excluded_tables() {
+        let mut definition = database();
+
+        filter_tables(&mut definition, &[], &["customers".to_string()], true).unwrap();
+
+        let schema = &definition.schemas[0];
+        assert_eq!(
+            get_table_names(&definition),
+            vec!["orders", "events", "events_2024"]
+        );
+        assert!(schema.tables[0].constraints.is_empty());
+        assert!(schema.views.is_empty());
+        assert!(schema.triggers.is_empty());
+    }
+
+    #[test]
+    fn fails_on_patterns_without_wildcards_matching_nothing() {
+        let mut definition = database();
+
+        let result = filter_tables(
+            &mut definition,
+            &["public.custmers".to_string()],
+            &[],
+            false,
+        );
+        assert!(
+            matches!(&result, Err(ElefantToolsError::TableNotFound { table_name, .. }) if table_name == "custmers"),
+            "{:?}",
+            result
+        );
+
+        filter_tables(
+            &mut definition,
+            &["public.missing_*".to_string()],
+            &[],
+            true,
+        )
+        .unwrap();
+        assert!(get_table_names(&definition).is_empty());
+    }
+}
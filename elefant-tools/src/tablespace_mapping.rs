@@ -0,0 +1,195 @@
+use crate::*;
+use itertools::Itertools;
+use std::collections::HashMap;
+
+/// The name used in [CopyDataOptions::tablespace_mapping] for tables and indexes stored in the
+/// default tablespace of the source database.
+pub(crate) const DEFAULT_TABLESPACE: &str = "pg_default";
+
+/// Changes the tablespaces of the tables and indexes in the definition, see
+/// [CopyDataOptions::tablespace_mapping] and [CopyDataOptions::no_tablespaces].
+pub(crate) fn map_tablespaces(
+    definition: &mut PostgresDatabase,
+    tablespace_mapping: &HashMap<String, String>,
+    no_tablespaces: bool,
+) {
+    let map = |tablespace: &mut Option<String>| {
+        if no_tablespaces {
+            *tablespace = None;
+        } else if let Some(mapped) =
+            tablespace_mapping.get(tablespace.as_deref().unwrap_or(DEFAULT_TABLESPACE))
+        {
+            *tablespace = Some(mapped.clone());
+        }
+    };
+
+    for schema in &mut definition.schemas {
+        for table in &mut schema.tables {
+            map(&mut table.tablespace);
+
+            for index in &mut table.indices {
+                map(&mut index.tablespace);
+            }
+        }
+
+        for view in &mut schema.views {
+            for index in &mut view.indices {
+                map(&mut index.tablespace);
+            }
+        }
+    }
+}
+
+/// Checks that the tablespaces used by the definition, and the tablespaces mapped to, exist in
+/// the destination, so the copy fails before anything is created.
+pub(crate) fn check_tablespaces(
+    definition: &PostgresDatabase,
+    tablespace_mapping: &HashMap<String, String>,
+    tablespaces: &[String],
+) -> Result<()> {
+    let used = definition.schemas.iter().flat_map(|schema| {
+        let table_indices = schema.tables.iter().flat_map(|t| &t.indices);
+        let view_indices = schema.views.iter().flat_map(|v| &v.indices);
+
+        schema
+            .tables
+            .iter()
+            .filter_map(|t| t.tablespace.as_ref())
+            .chain(table_indices.chain(view_indices).filter_map(|i| i.tablespace.as_ref()))
+    });
+
+    for tablespace in tablespace_mapping.values().sorted().chain(used) {
+        if !tablespaces.contains(tablespace) {
+            return Err(ElefantToolsError::TablespaceNotFound(tablespace.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> PostgresDatabase {
+        let index = |name: &str, tablespace: Option<&str>| PostgresIndex {
+            name: name.to_string(),
+            tablespace: tablespace.map(|t| t.to_string()),
+            ..default()
+        };
+
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![
+                    PostgresTable {
+                        name: "events".to_string(),
+                        tablespace: Some("fast_disk".to_string()),
+                        indices: vec![
+                            index("events_pkey", Some("fast_disk")),
+                            index("events_time_idx", None),
+                        ],
+                        ..default()
+                    },
+                    PostgresTable {
+                        name: "users".to_string(),
+                        indices: vec![index("users_pkey", Some("archive"))],
+                        ..default()
+                    },
+                ],
+                views: vec![PostgresView {
+                    name: "event_counts".to_string(),
+                    is_materialized: true,
+                    indices: vec![index("event_counts_idx", Some("fast_disk"))],
+                    ..default()
+                }],
+                ..default()
+            }],
+            ..default()
+        }
+    }
+
+    fn get_tablespaces(definition: &PostgresDatabase) -> Vec<(&str, Option<&str>)> {
+        let schema = &definition.schemas[0];
+
+        schema
+            .tables
+            .iter()
+            .flat_map(|t| {
+                std::iter::once((t.name.as_str(), t.tablespace.as_deref())).chain(
+                    t.indices
+                        .iter()
+                        .map(|i| (i.name.as_str(), i.tablespace.as_deref())),
+                )
+            })
+            .chain(
+                schema.views[0]
+                    .indices
+                    .iter()
+                    .map(|i| (i.name.as_str(), i.tablespace.as_deref())),
+            )
+            .collect()
+    }
+
+    #[test]
+    fn maps_tablespaces_of_tables_and_indexes() {
+        let mut definition = database();
+        let mapping = HashMap::from([
+            ("fast_disk".to_string(), "ssd".to_string()),
+            (DEFAULT_TABLESPACE.to_string(), "standard".to_string()),
+        ]);
+
+        map_tablespaces(&mut definition, &mapping, false);
+
+        assert_eq!(
+            get_tablespaces(&definition),
+            vec![
+                ("events", Some("ssd")),
+                ("events_pkey", Some("ssd")),
+                ("events_time_idx", Some("standard")),
+                ("users", Some("standard")),
+                ("users_pkey", Some("archive")),
+                ("event_counts_idx", Some("ssd")),
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_all_tablespaces() {
+        let mut definition = database();
+        let mapping = HashMap::from([("fast_disk".to_string(), "ssd".to_string())]);
+
+        map_tablespaces(&mut definition, &mapping, true);
+
+        assert!(get_tablespaces(&definition)
+            .iter()
+            .all(|(_, tablespace)| tablespace.is_none()));
+    }
+
+    #[test]
+    fn fails_on_missing_tablespaces() {
+        let definition = database();
+        let tablespaces = vec![
+            "pg_default".to_string(),
+            "fast_disk".to_string(),
+            "archive".to_string(),
+        ];
+
+        check_tablespaces(&definition, &HashMap::new(), &tablespaces).unwrap();
+
+        let result = check_tablespaces(&definition, &HashMap::new(), &tablespaces[..2]);
+        assert!(
+            matches!(&result, Err(ElefantToolsError::TablespaceNotFound(t)) if t == "archive"),
+            "{:?}",
+            result
+        );
+
+        let mapping = HashMap::from([("archive".to_string(), "ssd".to_string())]);
+        let result = check_tablespaces(&definition, &mapping, &tablespaces);
+        assert!(
+            matches!(&result, Err(ElefantToolsError::TablespaceNotFound(t)) if t == "ssd"),
+            "{:?}",
+            result
+        );
+    }
+}
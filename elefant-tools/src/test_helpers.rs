@@ -64,13 +64,18 @@ pub async fn get_test_helper(name: &str) -> TestHelper {
 
 /// Creates a new test helper, using a random database name and a specific port.
 pub async fn get_test_helper_on_port(name: &str, port: u16) -> TestHelper {
+    create_test_database(name, port, "").await
+}
+
+/// Creates a test database with the options appended to `create database`.
+async fn create_test_database(name: &str, port: u16, create_options: &str) -> TestHelper {
     let id = Uuid::new_v4().simple().to_string();
 
     let test_db_name = format!("test_db_{}", id);
     {
         let conn = get_test_connection_on_port("postgres", port).await;
 
-        conn.execute_non_query(&format!("create database {}", test_db_name))
+        conn.execute_non_query(&format!("create database {} {}", test_db_name, create_options))
             .await
             .expect("Failed to create test database");
     }
@@ -136,6 +141,14 @@ impl TestHelper {
         &self.main_connection
     }
 
+    /// Gets a connection string for the test database, for code that makes its own connections.
+    pub fn get_connection_string(&self) -> String {
+        format!(
+            "host=localhost port={} user=postgres password=passw0rd dbname={}",
+            self.port, self.test_db_name
+        )
+    }
+
     /// Gets a connection to a specific schema in the database.
     pub async fn get_schema_connection(&self, schema: &str) -> PostgresClientWrapper {
         let connection_string = format!("host=localhost port={} user=postgres password=passw0rd dbname={} options=--search_path={},public", self.port, self.test_db_name, schema);
@@ -153,6 +166,134 @@ impl TestHelper {
     pub async fn create_another_database(&self, name: &str) -> TestHelper {
         get_test_helper_on_port(name, self.port).await
     }
+
+    /// Creates another database on the same server with the specified encoding and the `C` locale.
+    pub async fn create_another_database_with_encoding(
+        &self,
+        name: &str,
+        encoding: &str,
+    ) -> TestHelper {
+        create_test_database(
+            name,
+            self.port,
+            &format!("template template0 encoding '{encoding}' lc_collate 'C' lc_ctype 'C'"),
+        )
+        .await
+    }
+
+    /// Connects to the same database as a role that owns it, but is not a superuser, like the
+    /// roles managed Postgres services hand out.
+    ///
+    /// The database is still dropped through this test helper.
+    pub async fn as_restricted_user(&self) -> TestHelper {
+        // The role is shared between all tests on the server, so it might be created concurrently.
+        self.execute_not_query(&format!(
+            r#"
+            do $$
+            begin
+                create role {RESTRICTED_USER} login password '{RESTRICTED_PASSWORD}' createdb createrole;
+            exception when duplicate_object or unique_violation then null;
+            end
+            $$;
+            alter database {} owner to {RESTRICTED_USER};
+            "#,
+            self.test_db_name
+        ))
+        .await;
+
+        TestHelper {
+            test_db_name: self.test_db_name.clone(),
+            main_connection: get_test_connection_full(
+                &self.test_db_name,
+                self.port,
+                RESTRICTED_USER,
+                RESTRICTED_PASSWORD,
+                None,
+            )
+            .await,
+            helper_name: format!("{}_restricted", self.helper_name),
+            port: self.port,
+            cleaned_up_nicely: true,
+            is_timescale_db: self.is_timescale_db,
+        }
+    }
+}
+
+const RESTRICTED_USER: &str = "elefant_restricted";
+const RESTRICTED_PASSWORD: &str = "passw0rd";
+
+/// A helper for running tests against a primary and a physical streaming replica of it.
+///
+/// The test database is created on the primary, and is dropped from the primary when the test
+/// is done. The replica is read-only, so all changes have to be made through the primary.
+pub struct ReplicatedTestHelper {
+    /// The test database on the primary.
+    pub primary: TestHelper,
+    /// The same test database on the replica.
+    pub replica: TestHelper,
+}
+
+/// Creates a new test database on the primary on `primary_port`, and connects to it on the
+/// replica on `replica_port` once the replica has replayed its creation.
+pub async fn get_replicated_test_helper_on_ports(
+    name: &str,
+    primary_port: u16,
+    replica_port: u16,
+) -> ReplicatedTestHelper {
+    let primary = get_test_helper_on_port(name, primary_port).await;
+    wait_for_replay_lsn(primary.get_conn(), replica_port).await;
+
+    let replica = TestHelper {
+        test_db_name: primary.test_db_name.clone(),
+        main_connection: get_test_connection_on_port(&primary.test_db_name, replica_port).await,
+        helper_name: format!("{name}_replica"),
+        port: replica_port,
+        // The database is dropped through the primary.
+        cleaned_up_nicely: true,
+        is_timescale_db: false,
+    };
+
+    ReplicatedTestHelper { primary, replica }
+}
+
+impl ReplicatedTestHelper {
+    /// Waits until the replica has replayed everything written to the primary so far.
+    pub async fn wait_for_replay_lsn(&self) {
+        wait_for_replay_lsn(self.primary.get_conn(), self.replica.port).await;
+    }
+
+    /// Stops the test helper, cleaning up the database.
+    pub async fn stop(self) {
+        self.primary.stop().await;
+    }
+}
+
+/// Waits until the replica on `replica_port` has replayed the current WAL location of the primary.
+async fn wait_for_replay_lsn(primary: &PostgresClientWrapper, replica_port: u16) {
+    let lsn = primary
+        .get_single_result::<String>("select pg_current_wal_lsn()::text;")
+        .await
+        .expect("Failed to get the WAL location of the primary");
+
+    let replica = get_test_connection_on_port("postgres", replica_port).await;
+    let started = std::time::Instant::now();
+    loop {
+        let replayed = replica
+            .get_single_result::<bool>(&format!(
+                "select pg_last_wal_replay_lsn() >= '{lsn}'::pg_lsn;"
+            ))
+            .await
+            .expect("Failed to get the replayed WAL location of the replica. Is it a replica?");
+        if replayed {
+            return;
+        }
+
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(30),
+            "Replica on port {replica_port} did not replay {lsn} within 30 seconds"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
 }
 
 /// Gets a connection to the specified database on the specified port.
@@ -302,6 +443,26 @@ mod tests {
         assert!((140..160).contains(&version));
     }
 
+    #[pg_test(arg(postgres_replicated = 16))]
+    async fn replica_follows_the_primary(helper: &ReplicatedTestHelper) {
+        helper
+            .primary
+            .execute_not_query("create table items(id int); insert into items values (1), (2);")
+            .await;
+        helper.wait_for_replay_lsn().await;
+
+        let ids = helper
+            .replica
+            .get_single_results::<i32>("select id from items order by id;")
+            .await;
+        assert_eq!(ids, vec![1, 2]);
+        let in_recovery = helper
+            .replica
+            .get_single_result::<bool>("select pg_is_in_recovery();")
+            .await;
+        assert!(in_recovery);
+    }
+
     macro_rules! test_injected_version {
         ($name:ident, $version:expr) => {
             #[pg_test(arg(postgres = $version))]
@@ -315,4 +476,5 @@ mod tests {
     test_injected_version!(test_injected_version_13, 13);
     test_injected_version!(test_injected_version_14, 14);
     test_injected_version!(test_injected_version_15, 15);
+    test_injected_version!(test_injected_version_17, 17);
 }
@@ -0,0 +1,495 @@
+//! Checks that the destination can hold the Timescale objects of the source, and turns them into
+//! plain Postgres objects for destinations without Timescale, see
+//! [CopyDataOptions::strip_timescale].
+
+use crate::*;
+use tracing::{info, warn};
+
+/// The Timescale objects of the definition, such as `hypertables public.metrics`, for explaining
+/// why Timescale is needed in the destination. Empty if it doesn't use any.
+fn get_used_timescale_features(definition: &PostgresDatabase) -> Vec<String> {
+    let qualified = |schema: &PostgresSchema, name: &str| format!("{}.{}", schema.name, name);
+
+    let mut hypertables = Vec::new();
+    let mut continuous_aggregates = Vec::new();
+    let mut compressed = Vec::new();
+    let mut retention = Vec::new();
+
+    for schema in &definition.schemas {
+        for table in &schema.tables {
+            if let TableTypeDetails::TimescaleHypertable {
+                compression,
+                retention: table_retention,
+                ..
+            } = &table.table_type
+            {
+                hypertables.push(qualified(schema, &table.name));
+                if compression.is_some() {
+                    compressed.push(qualified(schema, &table.name));
+                }
+                if table_retention.is_some() {
+                    retention.push(qualified(schema, &table.name));
+                }
+            }
+        }
+
+        for view in &schema.views {
+            if let ViewOptions::TimescaleContinuousAggregate {
+                compression,
+                retention: view_retention,
+                ..
+            } = &view.view_options
+            {
+                continuous_aggregates.push(qualified(schema, &view.name));
+                if compression.is_some() {
+                    compressed.push(qualified(schema, &view.name));
+                }
+                if view_retention.is_some() {
+                    retention.push(qualified(schema, &view.name));
+                }
+            }
+        }
+    }
+
+    let jobs = definition
+        .timescale_support
+        .user_defined_jobs
+        .iter()
+        .map(|j| format!("{}.{}", j.function_schema, j.function_name))
+        .collect::<Vec<_>>();
+
+    [
+        ("hypertables", hypertables),
+        ("continuous aggregates", continuous_aggregates),
+        ("compression on", compressed),
+        ("retention policies on", retention),
+        ("jobs running", jobs),
+    ]
+    .into_iter()
+    .filter(|(_, objects)| !objects.is_empty())
+    .map(|(feature, objects)| format!("{} {}", feature, objects.join(", ")))
+    .collect()
+}
+
+/// Fails if the source uses Timescale, but the destination does not have the extension, or has
+/// an older version of it. Only checked when the destination is a database.
+pub(crate) async fn check_timescale_compatibility<'d, S: CopySourceFactory, D: CopyDestinationFactory<'d>>(
+    source: &S,
+    destination: &D,
+    target_definition: &PostgresDatabase,
+    extensions: &[PlannedExtension],
+) -> Result<()> {
+    if !target_definition.timescale_support.is_enabled {
+        return Ok(());
+    }
+
+    let features = get_used_timescale_features(target_definition);
+    if features.is_empty() {
+        return Ok(());
+    }
+
+    let Some(destination_capabilities) = destination.get_server_capabilities().await? else {
+        return Ok(());
+    };
+    let source_version = source
+        .get_server_capabilities()
+        .await?
+        .and_then(|c| c.timescale);
+
+    get_timescale_incompatibility(
+        source_version,
+        destination_capabilities.timescale,
+        extensions,
+        features.join("; "),
+    )
+    .map_or(Ok(()), Err)
+}
+
+fn get_timescale_incompatibility(
+    source_version: Option<TimescaleVersion>,
+    destination_version: Option<TimescaleVersion>,
+    extensions: &[PlannedExtension],
+    features: String,
+) -> Option<ElefantToolsError> {
+    match (source_version, destination_version) {
+        (Some(source_version), Some(destination_version))
+            if (destination_version.major, destination_version.minor)
+                < (source_version.major, source_version.minor) =>
+        {
+            Some(ElefantToolsError::TimescaleVersionMismatch {
+                source_version,
+                destination_version,
+                features,
+            })
+        }
+        (_, Some(_)) => None,
+        (source_version, None) => {
+            // The extension is created by the copy if the destination user is allowed to.
+            let is_installable = extensions.iter().any(|e| {
+                e.name == "timescaledb" && e.availability == ExtensionAvailability::Installable
+            });
+
+            if is_installable {
+                None
+            } else {
+                Some(ElefantToolsError::TimescaleMissingInDestination {
+                    source_version,
+                    features,
+                })
+            }
+        }
+    }
+}
+
+/// Turns the hypertables into plain tables and the continuous aggregates into plain materialized
+/// views, and leaves out the extension, the jobs and the compression and retention policies.
+pub(crate) fn strip_timescale(definition: &mut PostgresDatabase) -> Result<()> {
+    if !definition.timescale_support.is_enabled {
+        return Ok(());
+    }
+
+    for schema in &mut definition.schemas {
+        for table in &mut schema.tables {
+            if table.is_timescale_table() {
+                info!("Copying hypertable {}.{} as a plain table", schema.name, table.name);
+                table.table_type = TableTypeDetails::Table;
+            }
+        }
+
+        for view in &mut schema.views {
+            if let ViewOptions::TimescaleContinuousAggregate { .. } = view.view_options {
+                let view_definition = replace_time_bucket(&view.definition);
+                if view_definition.contains("time_bucket") {
+                    return Err(ElefantToolsError::ContinuousAggregateNotStrippable {
+                        schema_name: schema.name.clone(),
+                        view_name: view.name.clone(),
+                    });
+                }
+
+                info!(
+                    "Copying continuous aggregate {}.{} as a plain materialized view",
+                    schema.name, view.name
+                );
+                view.definition = view_definition.into();
+                view.view_options = ViewOptions::None;
+            }
+        }
+    }
+
+    if !definition.timescale_support.user_defined_jobs.is_empty() {
+        warn!(
+            "Leaving out {} Timescale jobs, as they need Timescale to run",
+            definition.timescale_support.user_defined_jobs.len()
+        );
+    }
+
+    definition.timescale_support = TimescaleSupport::default();
+    definition
+        .enabled_extensions
+        .retain(|e| e.name != "timescaledb" && e.name != "timescaledb_toolkit");
+
+    Ok(())
+}
+
+/// Replaces `time_bucket(width, time)` with `date_bin`, which buckets the same way when given
+/// the origin Timescale uses, Monday 2000-01-03. Buckets of months or years, and the other
+/// forms of `time_bucket` are left as they are, as `date_bin` cannot do them.
+fn replace_time_bucket(definition: &str) -> String {
+    const ORIGIN: &str = "'2000-01-03 00:00:00+00'";
+
+    let mut result = String::with_capacity(definition.len());
+    let mut rest = definition;
+
+    while let Some(start) = find_time_bucket_call(rest) {
+        let (before, call) = rest.split_at(start);
+        let arguments_start = call.find('(').unwrap() + 1; // SAFE: find_time_bucket_call only matches calls
+
+        let Some((arguments, arguments_end)) = split_arguments(&call[arguments_start..]) else {
+            break;
+        };
+
+        let is_replaceable = arguments.len() == 2 && {
+            let width = arguments[0].to_lowercase();
+            !width.contains("mon") && !width.contains("year")
+        };
+
+        result.push_str(&remove_schema_qualification(before));
+        if is_replaceable {
+            result.push_str(&format!(
+                "date_bin({}, {}, {})",
+                arguments[0].trim(),
+                replace_time_bucket(arguments[1].trim()),
+                ORIGIN
+            ));
+        } else {
+            result.push_str(&call[..arguments_start + arguments_end + 1]);
+        }
+
+        rest = &call[arguments_start + arguments_end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Finds the next call of `time_bucket`, including a schema qualification such as `public.`.
+fn find_time_bucket_call(sql: &str) -> Option<usize> {
+    let mut offset = 0;
+
+    while let Some(index) = sql[offset..].find("time_bucket(") {
+        let start = offset + index;
+        let is_whole_word = sql[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_alphanumeric() && c != '_');
+        if is_whole_word {
+            return Some(start);
+        }
+        offset = start + 1;
+    }
+
+    None
+}
+
+/// Removes the schema in front of a call found by [find_time_bucket_call], which ends `before`.
+fn remove_schema_qualification(before: &str) -> String {
+    match before.strip_suffix('.') {
+        Some(qualified) => qualified
+            .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_' || c == '"')
+            .to_string(),
+        None => before.to_string(),
+    }
+}
+
+/// Splits the arguments of a call at the top level commas, starting after the opening
+/// parenthesis. Returns the arguments and the position of the closing parenthesis.
+fn split_arguments(sql: &str) -> Option<(Vec<&str>, usize)> {
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut argument_start = 0;
+
+    for (i, c) in sql.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => {
+                arguments.push(&sql[argument_start..i]);
+                return Some((arguments, i));
+            }
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                arguments.push(&sql[argument_start..i]);
+                argument_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use elefant_test_macros::pg_test;
+
+    #[test]
+    fn replaces_time_bucket_with_date_bin() {
+        assert_eq!(
+            replace_time_bucket(
+                r#"SELECT public.time_bucket('1 day'::interval, t."time") AS day, count(*) AS count
+   FROM t
+  GROUP BY (public.time_bucket('1 day'::interval, t."time"));"#
+            ),
+            r#"SELECT date_bin('1 day'::interval, t."time", '2000-01-03 00:00:00+00') AS day, count(*) AS count
+   FROM t
+  GROUP BY (date_bin('1 day'::interval, t."time", '2000-01-03 00:00:00+00'));"#
+        );
+    }
+
+    #[test]
+    fn leaves_the_unsupported_forms_of_time_bucket() {
+        let monthly = "SELECT time_bucket('1 month'::interval, \"time\") AS month FROM t";
+        assert_eq!(replace_time_bucket(monthly), monthly);
+
+        let with_offset =
+            "SELECT time_bucket('1 day'::interval, \"time\", '2 hours'::interval) AS day FROM t";
+        assert_eq!(replace_time_bucket(with_offset), with_offset);
+
+        let gapfill = "SELECT time_bucket_gapfill('1 day'::interval, \"time\") AS day FROM t";
+        assert_eq!(replace_time_bucket(gapfill), gapfill);
+    }
+
+    fn timescale_database() -> PostgresDatabase {
+        PostgresDatabase {
+            schemas: vec![PostgresSchema {
+                name: "public".to_string(),
+                tables: vec![PostgresTable {
+                    name: "metrics".to_string(),
+                    table_type: TableTypeDetails::TimescaleHypertable {
+                        dimensions: Vec::new(),
+                        compression: None,
+                        retention: None,
+                        chunk_target_size: None,
+                    },
+                    ..default()
+                }],
+                views: vec![PostgresView {
+                    name: "daily".to_string(),
+                    definition: "SELECT time_bucket('1 day'::interval, \"time\") AS day FROM metrics"
+                        .into(),
+                    is_materialized: true,
+                    view_options: ViewOptions::TimescaleContinuousAggregate {
+                        refresh: None,
+                        compression: None,
+                        retention: None,
+                        materialized_only: true,
+                        finalized: true,
+                    },
+                    ..default()
+                }],
+                ..default()
+            }],
+            enabled_extensions: vec![PostgresExtension {
+                name: "timescaledb".to_string(),
+                ..default()
+            }],
+            timescale_support: TimescaleSupport {
+                is_enabled: true,
+                ..default()
+            },
+            ..default()
+        }
+    }
+
+    #[test]
+    fn strips_the_timescale_objects() {
+        let mut definition = timescale_database();
+
+        strip_timescale(&mut definition).unwrap();
+
+        let schema = &definition.schemas[0];
+        assert_eq!(schema.tables[0].table_type, TableTypeDetails::Table);
+        assert_eq!(schema.views[0].view_options, ViewOptions::None);
+        assert!(schema.views[0].is_materialized);
+        assert!(!schema.views[0].definition.contains("time_bucket"));
+        assert_eq!(definition.enabled_extensions, vec![]);
+        assert_eq!(definition.timescale_support, TimescaleSupport::default());
+    }
+
+    #[test]
+    fn explains_what_needs_timescale_in_the_destination() {
+        let version = |minor| TimescaleVersion {
+            major: 2,
+            minor,
+            patch: 0,
+        };
+        let features = get_used_timescale_features(&timescale_database()).join("; ");
+        assert_eq!(
+            features,
+            "hypertables public.metrics; continuous aggregates public.daily"
+        );
+
+        let error = get_timescale_incompatibility(Some(version(11)), None, &[], features.clone());
+        assert_eq!(
+            error.unwrap().to_string(),
+            "The source uses TimescaleDB 2.11.0 for hypertables public.metrics; continuous aggregates public.daily, but the timescaledb extension is not available in the destination. Install it in the destination, or strip the Timescale objects (`--strip-timescale`) to copy the hypertables as plain tables"
+        );
+
+        let installable = [PlannedExtension {
+            name: "timescaledb".to_string(),
+            availability: ExtensionAvailability::Installable,
+        }];
+        assert!(
+            get_timescale_incompatibility(Some(version(11)), None, &installable, features.clone())
+                .is_none()
+        );
+
+        assert!(matches!(
+            get_timescale_incompatibility(
+                Some(version(14)),
+                Some(version(11)),
+                &[],
+                features.clone()
+            ),
+            Some(ElefantToolsError::TimescaleVersionMismatch { .. })
+        ));
+        assert!(
+            get_timescale_incompatibility(Some(version(11)), Some(version(14)), &[], features)
+                .is_none()
+        );
+    }
+
+    #[pg_test(arg(timescale_db = 16), arg(postgres = 16))]
+    async fn copies_hypertables_to_plain_postgres(source: &TestHelper, destination: &TestHelper) {
+        //language=postgresql
+        source
+            .execute_not_query(
+                r#"
+            create table metrics(
+                time timestamptz not null,
+                value int not null
+            );
+            select create_hypertable('metrics', 'time');
+
+            insert into metrics(time, value)
+            values ('2024-01-01 10:00:00+00', 1),
+                   ('2024-01-01 12:00:00+00', 2),
+                   ('2024-01-02 10:00:00+00', 3);
+
+            create materialized view daily with (timescaledb.continuous) as
+            select time_bucket('1 day', time) as day, sum(value) as total
+            from metrics
+            group by day
+            with no data;
+            "#,
+            )
+            .await;
+
+        let source_storage = PostgresInstanceStorage::new(source.get_conn())
+            .await
+            .unwrap();
+        let mut destination_storage = PostgresInstanceStorage::new(destination.get_conn())
+            .await
+            .unwrap();
+
+        let error = copy_data(
+            &source_storage,
+            &mut PostgresInstanceStorage::new(destination.get_conn())
+                .await
+                .unwrap(),
+            default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            matches!(error, ElefantToolsError::TimescaleMissingInDestination { .. }),
+            "{error}"
+        );
+
+        copy_data(
+            &source_storage,
+            &mut destination_storage,
+            CopyDataOptions {
+                strip_timescale: true,
+                ..default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let values = destination
+            .get_single_results::<i32>("select value from metrics order by time;")
+            .await;
+        assert_eq!(values, vec![1, 2, 3]);
+
+        let totals = destination
+            .get_single_results::<i64>("select total from daily order by day;")
+            .await;
+        assert_eq!(totals, vec![3, 3]);
+    }
+}
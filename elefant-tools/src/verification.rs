@@ -0,0 +1,643 @@
+use crate::copy_plan::CopiedTable;
+use crate::quoting::AttemptedKeywordUsage::ColumnName;
+use crate::quoting::{quote_value_string, IdentifierQuoter, Quotable};
+use crate::storage::{CopyDestination, CopySource};
+use crate::{
+    ElefantToolsError, PostgresColumn, PostgresIndexType, PostgresSchema, PostgresTable, Result,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How many of the mismatching rows of a table are included in the report.
+const MAX_MISMATCH_EXAMPLES: usize = 10;
+
+/// Tables with more rows than this times the number of sampled rows are sampled using
+/// `tablesample`, instead of ordering the entire table randomly.
+const TABLESAMPLE_THRESHOLD_FACTOR: f64 = 100.0;
+
+/// How the copied data is verified after the copy.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum VerificationLevel {
+    /// The copied data is not verified.
+    #[default]
+    None,
+    /// Compares `rows_per_table` random rows of each table with the source, column by column,
+    /// by looking them up by primary key. Tables without a primary key only have their
+    /// number of rows compared.
+    Sample { rows_per_table: usize },
+    /// Compares the number of rows and a checksum of all the rows of each table with the source.
+    /// This reads every table completely on both sides, but finds any difference, regardless of
+    /// whether the table has a primary key. The order of the rows does not matter. Partitions
+    /// are compared one by one, and hypertables with all their chunks.
+    Checksum,
+}
+
+/// The result of verifying the data of a single table.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableVerification {
+    pub schema_name: String,
+    pub table_name: String,
+    pub result: TableVerificationResult,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TableVerificationResult {
+    /// Random rows were compared by primary key.
+    Sampled {
+        rows_compared: usize,
+        mismatched_rows: usize,
+        /// The first few of the mismatching rows.
+        examples: Vec<RowMismatch>,
+    },
+    /// The table has no primary key, so only the number of rows were compared.
+    RowCountOnly {
+        source_rows: i64,
+        destination_rows: i64,
+    },
+    /// The number of rows and a checksum of all the rows were compared.
+    Checksum {
+        source_rows: i64,
+        destination_rows: i64,
+        source_checksum: String,
+        destination_checksum: String,
+    },
+}
+
+impl TableVerificationResult {
+    /// Whether the destination matched the source.
+    pub fn is_match(&self) -> bool {
+        match self {
+            TableVerificationResult::Sampled {
+                mismatched_rows, ..
+            } => *mismatched_rows == 0,
+            TableVerificationResult::RowCountOnly {
+                source_rows,
+                destination_rows,
+            } => source_rows == destination_rows,
+            TableVerificationResult::Checksum {
+                source_rows,
+                destination_rows,
+                source_checksum,
+                destination_checksum,
+            } => source_rows == destination_rows && source_checksum == destination_checksum,
+        }
+    }
+}
+
+/// A sampled row that is different in the destination.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RowMismatch {
+    /// The primary key of the row, as `(column, value)`.
+    pub primary_key: Vec<(String, String)>,
+    /// The row does not exist in the destination at all.
+    pub missing_in_destination: bool,
+    pub differing_columns: Vec<ColumnMismatch>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ColumnMismatch {
+    pub column_name: String,
+    pub source_value: Option<String>,
+    pub destination_value: Option<String>,
+}
+
+/// Verifies the data of the table by comparing `rows_per_table` random rows. Returns `None`
+/// if the source or the destination cannot be queried.
+pub(crate) async fn verify_table<S: CopySource, D: CopyDestination>(
+    source: &S,
+    destination: &D,
+    table: &CopiedTable<'_>,
+    rows_per_table: usize,
+    identifier_quoter: &IdentifierQuoter,
+) -> Result<Option<TableVerification>> {
+    let source_relation =
+        get_relation_name(table.source_schema, table.source_table, identifier_quoter);
+    let destination_relation =
+        get_relation_name(table.target_schema, table.target_table, identifier_quoter);
+
+    let key_columns = get_primary_key_columns(table.target_table, identifier_quoter);
+
+    let result = if key_columns.is_empty() {
+        let count_sql = |relation: &str| format!("select count(*)::text from {};", relation);
+
+        let Some(source_rows) = source.get_text_rows(&count_sql(&source_relation)).await? else {
+            return Ok(None);
+        };
+        let Some(destination_rows) = destination
+            .get_text_rows(&count_sql(&destination_relation))
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        TableVerificationResult::RowCountOnly {
+            source_rows: parse_count(&source_rows)?,
+            destination_rows: parse_count(&destination_rows)?,
+        }
+    } else {
+        let Some(sampled_keys) = source
+            .get_text_rows(
+                &get_sample_sql(
+                    source,
+                    &source_relation,
+                    &key_columns,
+                    rows_per_table,
+                    identifier_quoter,
+                )
+                .await?,
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        if sampled_keys.is_empty() {
+            TableVerificationResult::Sampled {
+                rows_compared: 0,
+                mismatched_rows: 0,
+                examples: vec![],
+            }
+        } else {
+            let columns = table
+                .target_table
+                .columns
+                .iter()
+                .sorted_by_key(|c| c.ordinal_position)
+                .collect_vec();
+
+            let fetch_sql = |relation: &str| {
+                get_fetch_sql(
+                    relation,
+                    &key_columns,
+                    &columns,
+                    &sampled_keys,
+                    identifier_quoter,
+                )
+            };
+
+            let Some(source_rows) = source.get_text_rows(&fetch_sql(&source_relation)).await?
+            else {
+                return Ok(None);
+            };
+            let Some(destination_rows) = destination
+                .get_text_rows(&fetch_sql(&destination_relation))
+                .await?
+            else {
+                return Ok(None);
+            };
+
+            compare_rows(
+                &key_columns,
+                &columns,
+                &sampled_keys,
+                source_rows,
+                destination_rows,
+            )
+        }
+    };
+
+    Ok(Some(TableVerification {
+        schema_name: table.target_schema.name.clone(),
+        table_name: table.target_table.name.clone(),
+        result,
+    }))
+}
+
+/// Verifies the data of the table by comparing the number of rows and a checksum of all the rows.
+/// Returns `None` if the source or the destination cannot be queried.
+pub(crate) async fn checksum_table<S: CopySource, D: CopyDestination>(
+    source: &S,
+    destination: &D,
+    table: &CopiedTable<'_>,
+    identifier_quoter: &IdentifierQuoter,
+) -> Result<Option<TableVerification>> {
+    let columns = table
+        .target_table
+        .columns
+        .iter()
+        .sorted_by_key(|c| c.ordinal_position)
+        .collect_vec();
+
+    let checksum_sql = |schema: &PostgresSchema, table: &PostgresTable| {
+        get_checksum_sql(
+            &get_relation_name(schema, table, identifier_quoter),
+            &columns,
+            identifier_quoter,
+        )
+    };
+
+    let Some(source_rows) = source
+        .get_text_rows(&checksum_sql(table.source_schema, table.source_table))
+        .await?
+    else {
+        return Ok(None);
+    };
+    let Some(destination_rows) = destination
+        .get_text_rows(&checksum_sql(table.target_schema, table.target_table))
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let checksum = |rows: &[Vec<Option<String>>]| {
+        rows.first()
+            .and_then(|row| row.get(1))
+            .cloned()
+            .flatten()
+            .unwrap_or_default()
+    };
+
+    Ok(Some(TableVerification {
+        schema_name: table.target_schema.name.clone(),
+        table_name: table.target_table.name.clone(),
+        result: TableVerificationResult::Checksum {
+            source_rows: parse_count(&source_rows)?,
+            destination_rows: parse_count(&destination_rows)?,
+            source_checksum: checksum(&source_rows),
+            destination_checksum: checksum(&destination_rows),
+        },
+    }))
+}
+
+/// Gets the query that counts the rows of the table and sums a hash of each row. Summing the
+/// hashes makes the checksum independent of the order the rows are read in.
+fn get_checksum_sql(
+    relation: &str,
+    columns: &[&PostgresColumn],
+    identifier_quoter: &IdentifierQuoter,
+) -> String {
+    // A row with the values as text distinguishes null from empty strings.
+    let row = format!(
+        "row({})::text",
+        columns
+            .iter()
+            .map(|c| get_normalized_column_expression(c, identifier_quoter))
+            .join(", ")
+    );
+
+    format!(
+        "select count(*)::text, coalesce(sum(('x' || substr(md5({}), 1, 16))::bit(64)::int8), 0)::text from {};",
+        row, relation
+    )
+}
+
+pub(crate) fn get_relation_name(
+    schema: &PostgresSchema,
+    table: &PostgresTable,
+    identifier_quoter: &IdentifierQuoter,
+) -> String {
+    format!(
+        "{}.{}",
+        schema.name.quote(identifier_quoter, ColumnName),
+        table.name.quote(identifier_quoter, ColumnName)
+    )
+}
+
+/// Gets the columns of the primary key of the table, in the order of the key.
+fn get_primary_key_columns<'t>(
+    table: &'t PostgresTable,
+    identifier_quoter: &IdentifierQuoter,
+) -> Vec<&'t PostgresColumn> {
+    let Some(primary_key) = table
+        .indices
+        .iter()
+        .find(|i| i.index_constraint_type == PostgresIndexType::PrimaryKey)
+    else {
+        return vec![];
+    };
+
+    // The key columns are already quoted in the index definition.
+    primary_key
+        .key_columns
+        .iter()
+        .filter_map(|key| {
+            table.columns.iter().find(|c| {
+                c.name == key.name || c.name.quote(identifier_quoter, ColumnName) == key.name
+            })
+        })
+        .collect()
+}
+
+/// Gets the select expression that returns the value of the column as text, in a form that
+/// does not depend on the settings of the connection.
+fn get_column_expression(column: &PostgresColumn, identifier_quoter: &IdentifierQuoter) -> String {
+    let name = column.name.quote(identifier_quoter, ColumnName);
+
+    if column.data_type == "timestamptz" && column.array_dimensions == 0 {
+        format!(
+            "case when isfinite({name}) then ({name} at time zone 'UTC')::text || '+00' else {name}::text end"
+        )
+    } else {
+        format!("{}::text", name)
+    }
+}
+
+/// Gets the select expression that returns the value of the column as text, like
+/// [get_column_expression], normalized the same way as [normalize_value].
+fn get_normalized_column_expression(
+    column: &PostgresColumn,
+    identifier_quoter: &IdentifierQuoter,
+) -> String {
+    let expression = get_column_expression(column, identifier_quoter);
+
+    if column.data_type == "numeric" && column.array_dimensions == 0 {
+        format!(
+            "case when strpos({expression}, '.') > 0 then rtrim(rtrim({expression}, '0'), '.') else {expression} end"
+        )
+    } else {
+        expression
+    }
+}
+
+/// Normalizes the parts of the value Postgres might format differently even though the values
+/// are the same.
+fn normalize_value(column: &PostgresColumn, value: Option<String>) -> Option<String> {
+    match value {
+        Some(value)
+            if column.data_type == "numeric"
+                && column.array_dimensions == 0
+                && value.contains('.') =>
+        {
+            Some(
+                value
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_string(),
+            )
+        }
+        value => value,
+    }
+}
+
+async fn get_sample_sql<S: CopySource>(
+    source: &S,
+    relation: &str,
+    key_columns: &[&PostgresColumn],
+    rows_per_table: usize,
+    identifier_quoter: &IdentifierQuoter,
+) -> Result<String> {
+    let estimated_rows = source
+        .get_text_rows(&format!(
+            "select reltuples::text from pg_class where oid = {}::regclass;",
+            quote_value_string(relation)
+        ))
+        .await?
+        .and_then(|rows| rows.into_iter().next())
+        .and_then(|row| row.into_iter().next().flatten())
+        .and_then(|r| r.parse::<f64>().ok())
+        .unwrap_or(0.0);
+
+    // Sampling a larger part of the blocks than needed, as the rows are not
+    // spread evenly across them.
+    let tablesample = if estimated_rows > rows_per_table as f64 * TABLESAMPLE_THRESHOLD_FACTOR {
+        let percentage = (rows_per_table as f64 * 10.0 / estimated_rows * 100.0).min(100.0);
+        format!(" tablesample system ({})", percentage)
+    } else {
+        String::new()
+    };
+
+    Ok(format!(
+        "select {} from {}{} order by random() limit {};",
+        key_columns
+            .iter()
+            .map(|c| get_column_expression(c, identifier_quoter))
+            .join(", "),
+        relation,
+        tablesample,
+        rows_per_table
+    ))
+}
+
+/// Gets the query that fetches the sampled rows by their primary key. The key values are passed
+/// as untyped literals, so they are parsed as the type of the key columns and the primary key
+/// index can be used.
+fn get_fetch_sql(
+    relation: &str,
+    key_columns: &[&PostgresColumn],
+    columns: &[&PostgresColumn],
+    sampled_keys: &[Vec<Option<String>>],
+    identifier_quoter: &IdentifierQuoter,
+) -> String {
+    let keys = sampled_keys
+        .iter()
+        .map(|key| {
+            format!(
+                "({})",
+                key.iter()
+                    .map(|v| v
+                        .as_deref()
+                        .map(quote_value_string)
+                        .unwrap_or_else(|| "null".to_string()))
+                    .join(", ")
+            )
+        })
+        .join(", ");
+
+    format!(
+        "select {}, {} from {} where ({}) in ({});",
+        key_columns
+            .iter()
+            .map(|c| get_column_expression(c, identifier_quoter))
+            .join(", "),
+        columns
+            .iter()
+            .map(|c| get_column_expression(c, identifier_quoter))
+            .join(", "),
+        relation,
+        key_columns
+            .iter()
+            .map(|c| c.name.quote(identifier_quoter, ColumnName))
+            .join(", "),
+        keys
+    )
+}
+
+fn compare_rows(
+    key_columns: &[&PostgresColumn],
+    columns: &[&PostgresColumn],
+    sampled_keys: &[Vec<Option<String>>],
+    source_rows: Vec<Vec<Option<String>>>,
+    destination_rows: Vec<Vec<Option<String>>>,
+) -> TableVerificationResult {
+    let by_key = |rows: Vec<Vec<Option<String>>>| {
+        rows.into_iter()
+            .map(|mut row| {
+                let values = row.split_off(key_columns.len());
+                (row, values)
+            })
+            .collect::<HashMap<_, _>>()
+    };
+    let source_rows = by_key(source_rows);
+    let destination_rows = by_key(destination_rows);
+
+    let mut rows_compared = 0;
+    let mut mismatched_rows = 0;
+    let mut examples = Vec::new();
+
+    for key in sampled_keys {
+        // The row might have been deleted in the source since it was sampled, if the source
+        // is not a snapshot.
+        let Some(source_values) = source_rows.get(key) else {
+            continue;
+        };
+        rows_compared += 1;
+
+        let mismatch = match destination_rows.get(key) {
+            None => Some((true, vec![])),
+            Some(destination_values) => {
+                let differing_columns = columns
+                    .iter()
+                    .zip(source_values.iter().zip(destination_values))
+                    .filter_map(|(column, (source_value, destination_value))| {
+                        let source_value = normalize_value(column, source_value.clone());
+                        let destination_value = normalize_value(column, destination_value.clone());
+                        (source_value != destination_value).then(|| ColumnMismatch {
+                            column_name: column.name.clone(),
+                            source_value,
+                            destination_value,
+                        })
+                    })
+                    .collect_vec();
+
+                (!differing_columns.is_empty()).then_some((false, differing_columns))
+            }
+        };
+
+        if let Some((missing_in_destination, differing_columns)) = mismatch {
+            mismatched_rows += 1;
+            if examples.len() < MAX_MISMATCH_EXAMPLES {
+                examples.push(RowMismatch {
+                    primary_key: key_columns
+                        .iter()
+                        .zip(key)
+                        .map(|(c, v)| (c.name.clone(), v.clone().unwrap_or_default()))
+                        .collect(),
+                    missing_in_destination,
+                    differing_columns,
+                });
+            }
+        }
+    }
+
+    TableVerificationResult::Sampled {
+        rows_compared,
+        mismatched_rows,
+        examples,
+    }
+}
+
+fn parse_count(rows: &[Vec<Option<String>>]) -> Result<i64> {
+    rows.first()
+        .and_then(|row| row.first())
+        .and_then(|v| v.as_deref())
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| ElefantToolsError::InvalidNumberOfResults {
+            actual: rows.len(),
+            expected: 1,
+            query: "select count(*)".to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default;
+
+    fn column(name: &str, data_type: &str) -> PostgresColumn {
+        PostgresColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn numeric_trailing_zeros_are_ignored() {
+        let numeric = column("amount", "numeric");
+        assert_eq!(
+            normalize_value(&numeric, Some("1.500".to_string())),
+            Some("1.5".to_string())
+        );
+        assert_eq!(
+            normalize_value(&numeric, Some("2.000".to_string())),
+            Some("2".to_string())
+        );
+        assert_eq!(
+            normalize_value(&numeric, Some("100".to_string())),
+            Some("100".to_string())
+        );
+
+        let text = column("name", "text");
+        assert_eq!(
+            normalize_value(&text, Some("1.500".to_string())),
+            Some("1.500".to_string())
+        );
+    }
+
+    #[test]
+    fn compares_sampled_rows_by_key() {
+        let id = column("id", "int4");
+        let value = column("value", "numeric");
+        let key_columns = [&id];
+        let columns = [&id, &value];
+        let row = |id: &str, value: &str| {
+            vec![
+                Some(id.to_string()),
+                Some(id.to_string()),
+                Some(value.to_string()),
+            ]
+        };
+
+        let result = compare_rows(
+            &key_columns,
+            &columns,
+            &[
+                vec![Some("1".to_string())],
+                vec![Some("2".to_string())],
+                vec![Some("3".to_string())],
+            ],
+            vec![row("1", "1.50"), row("2", "2"), row("3", "3")],
+            vec![row("1", "1.5"), row("2", "20")],
+        );
+
+        assert_eq!(
+            result,
+            TableVerificationResult::Sampled {
+                rows_compared: 3,
+                mismatched_rows: 2,
+                examples: vec![
+                    RowMismatch {
+                        primary_key: vec![("id".to_string(), "2".to_string())],
+                        missing_in_destination: false,
+                        differing_columns: vec![ColumnMismatch {
+                            column_name: "value".to_string(),
+                            source_value: Some("2".to_string()),
+                            destination_value: Some("20".to_string()),
+                        }],
+                    },
+                    RowMismatch {
+                        primary_key: vec![("id".to_string(), "3".to_string())],
+                        missing_in_destination: true,
+                        differing_columns: vec![],
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn checksums_normalized_rows() {
+        let id = column("id", "int4");
+        let amount = column("amount", "numeric");
+
+        assert_eq!(
+            get_checksum_sql(
+                "public.orders",
+                &[&id, &amount],
+                &IdentifierQuoter::empty()
+            ),
+            "select count(*)::text, coalesce(sum(('x' || substr(md5(row(id::text, case when strpos(amount::text, '.') > 0 then rtrim(rtrim(amount::text, '0'), '.') else amount::text end)::text), 1, 16))::bit(64)::int8), 0)::text from public.orders;"
+        );
+    }
+}
@@ -2,8 +2,12 @@ use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display};
 use std::ops::{Deref, DerefMut};
 
-/// A string that ignores repeated whitespace when comparing equality,
+/// A string that ignores differences in whitespace when comparing equality,
 /// while still storing the original string.
+///
+/// Whitespace is only significant between two words, where any run of it counts as a single
+/// space. The content of quoted literals, quoted identifiers and dollar quoted strings is
+/// compared as is, so a changed string literal in a function body is still a difference.
 #[repr(transparent)]
 #[derive(Default, Eq, Clone, Serialize, Deserialize)]
 pub struct WhitespaceIgnorantString(String);
@@ -54,8 +58,95 @@ impl Display for WhitespaceIgnorantString {
 
 impl PartialEq<Self> for WhitespaceIgnorantString {
     fn eq(&self, other: &Self) -> bool {
-        self.0.split_whitespace().collect::<String>()
-            == other.0.split_whitespace().collect::<String>()
+        normalize_whitespace(&self.0) == normalize_whitespace(&other.0)
+    }
+}
+
+/// Removes the whitespace that doesn't change the meaning of the sql, keeping a single space
+/// between words. Quoted text is copied without changes.
+fn normalize_whitespace(sql: &str) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut pending_space = false;
+    let mut rest = sql;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            pending_space = true;
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        if pending_space {
+            if result.chars().next_back().is_some_and(is_word_char) && is_word_char(c) {
+                result.push(' ');
+            }
+            pending_space = false;
+        }
+
+        let quoted_len = match c {
+            '\'' => {
+                let escape_string = is_escape_string_prefix(&result);
+                Some(get_quoted_len(rest, '\'', escape_string))
+            }
+            '"' => Some(get_quoted_len(rest, '"', false)),
+            '$' => get_dollar_quoted_len(rest),
+            _ => None,
+        };
+
+        let len = quoted_len.unwrap_or(c.len_utf8());
+        result.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    result
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Checks if the quote that follows starts an `E'...'` string, where backslashes escape quotes.
+fn is_escape_string_prefix(before: &str) -> bool {
+    let mut chars = before.chars().rev();
+    matches!(chars.next(), Some('e' | 'E')) && !chars.next().is_some_and(is_word_char)
+}
+
+/// Gets the length of the quoted text at the start of `text`, including the quotes. A doubled
+/// quote is part of the text. Unterminated text runs to the end.
+fn get_quoted_len(text: &str, quote: char, backslash_escapes: bool) -> usize {
+    let mut chars = text.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        if backslash_escapes && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            if text[i + 1..].starts_with(quote) {
+                chars.next();
+            } else {
+                return i + 1;
+            }
+        }
+    }
+
+    text.len()
+}
+
+/// Gets the length of the dollar quoted string at the start of `text`, such as `$body$...$body$`,
+/// including the tags. Returns `None` if `text` doesn't start with a tag, like the `$1` parameters.
+fn get_dollar_quoted_len(text: &str) -> Option<usize> {
+    let tag_len = text[1..].find('$')? + 2;
+    let tag = &text[..tag_len];
+
+    let tag_name = &tag[1..tag_len - 1];
+    if tag_name.starts_with(|c: char| c.is_ascii_digit())
+        || !tag_name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+
+    match text[tag_len..].find(tag) {
+        Some(end) => Some(tag_len + end + tag_len),
+        None => Some(text.len()),
     }
 }
 
@@ -95,6 +186,50 @@ mod tests {
         assert_eq!(s1, s2);
     }
 
+    #[test]
+    fn whitespace_between_words_is_kept() {
+        let s1 = WhitespaceIgnorantString::from("return a + b;");
+        let s2 = WhitespaceIgnorantString::from("returna+b;");
+        assert_ne!(s1, s2);
+    }
+
+    #[test]
+    fn reformatted_plpgsql_bodies_are_equal() {
+        let s1 = WhitespaceIgnorantString::from(
+            "\nbegin\n    if new.amount < 0 then\n        raise exception 'negative amount: %', new.amount;\n    end if;\n    return new;\nend;\n",
+        );
+        let s2 = WhitespaceIgnorantString::from(
+            "\n  begin\n\tif new.amount<0 then raise exception 'negative amount: %', new.amount;   \n  end if;\n\n  return new;\nend;",
+        );
+        assert_eq!(s1, s2);
+    }
+
+    #[test]
+    fn whitespace_in_string_literals_is_significant() {
+        let s1 = WhitespaceIgnorantString::from("begin raise notice 'a  b'; end;");
+        let s2 = WhitespaceIgnorantString::from("begin   raise notice 'a b';\nend;");
+        assert_ne!(s1, s2);
+
+        let s1 = WhitespaceIgnorantString::from("select 'it''s  here', E'\\'  x'");
+        let s2 = WhitespaceIgnorantString::from("select 'it''s  here',E'\\'  x'");
+        let s3 = WhitespaceIgnorantString::from("select 'it''s here', E'\\' x'");
+        assert_eq!(s1, s2);
+        assert_ne!(s1, s3);
+
+        let s1 = WhitespaceIgnorantString::from(r#"select "my  column" from t"#);
+        let s2 = WhitespaceIgnorantString::from(r#"select "my column" from t"#);
+        assert_ne!(s1, s2);
+    }
+
+    #[test]
+    fn whitespace_in_dollar_quoted_strings_is_significant() {
+        let s1 = WhitespaceIgnorantString::from("execute $q$select  1$q$ using $1;");
+        let s2 = WhitespaceIgnorantString::from("execute   $q$select  1$q$  using  $1;");
+        let s3 = WhitespaceIgnorantString::from("execute $q$select 1$q$ using $1;");
+        assert_eq!(s1, s2);
+        assert_ne!(s1, s3);
+    }
+
     #[test]
     fn e() {
         let s1 = WhitespaceIgnorantString::from(r#"{"hypertable":"metrics"}"#.to_string());
@@ -0,0 +1,4 @@
+#!/bin/bash
+# Allows the replica to stream the WAL of the primary.
+set -e
+echo "host replication all all scram-sha-256" >> "$PGDATA/pg_hba.conf"
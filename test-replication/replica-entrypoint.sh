@@ -0,0 +1,12 @@
+#!/bin/bash
+# Clones the primary into a physical streaming replica the first time the container starts.
+set -e
+if [ ! -s "$PGDATA/PG_VERSION" ]; then
+  until pg_basebackup --host=pg_16_primary --username=postgres --pgdata="$PGDATA" --write-recovery-conf --wal-method=stream; do
+    echo "Waiting for the primary to accept replication connections"
+    rm -rf "${PGDATA:?}"/*
+    sleep 1
+  done
+  chmod 0700 "$PGDATA"
+fi
+exec postgres